@@ -0,0 +1,274 @@
+//! Synthetic FBX files for the benchmarks.
+//!
+//! Each file contains a single model mesh with its geometry mesh and a
+//! Lambert material.
+//! The geometry has the normals and the UV by control point, as the
+//! fixtures of the loader tests.
+
+use std::{f64::consts::PI, io::Cursor};
+
+use fbxcel::{
+    low::{v7400::AttributeValue, FbxVersion},
+    writer::v7400::binary::{FbxFooter, Result as WriteResult, Writer},
+};
+
+/// Writer of the synthetic files.
+type FbxWriter = Writer<Cursor<Vec<u8>>>;
+
+/// Number of quads along each side of the grid.
+const GRID_SIZE: i32 = 200;
+
+/// Number of concave polygons.
+const NUM_CONCAVE: i32 = 1000;
+
+/// Number of vertices of each concave polygon.
+const CONCAVE_VERTICES: i32 = 32;
+
+/// Object ID of the model.
+const MODEL_ID: i64 = 1000;
+
+/// Object ID of the geometry.
+const GEOMETRY_ID: i64 = 1001;
+
+/// Object ID of the material.
+const MATERIAL_ID: i64 = 1002;
+
+/// Returns the FBX data to benchmark with their names.
+pub fn fixtures() -> Vec<(&'static str, Vec<u8>)> {
+    vec![("quads", grid()), ("concave", concave())]
+}
+
+/// Returns the FBX data of a grid of quads.
+fn grid() -> Vec<u8> {
+    let control_points = (0..=GRID_SIZE)
+        .flat_map(|y| (0..=GRID_SIZE).map(move |x| [f64::from(x), f64::from(y), 0.0]))
+        .collect::<Vec<_>>();
+    let index = |x: i32, y: i32| y * (GRID_SIZE + 1) + x;
+    let polygons = (0..GRID_SIZE)
+        .flat_map(|y| {
+            (0..GRID_SIZE).map(move |x| {
+                vec![
+                    index(x, y),
+                    index(x + 1, y),
+                    index(x + 1, y + 1),
+                    index(x, y + 1),
+                ]
+            })
+        })
+        .collect::<Vec<_>>();
+    write_fbx("grid", &control_points, &polygons)
+}
+
+/// Returns the FBX data of concave polygons in a row.
+///
+/// Each polygon is a disc with a quarter cut out, i.e. an arc and the center
+/// with a concave angle, since the triangulator supports polygons with at most
+/// one concave angle.
+fn concave() -> Vec<u8> {
+    let num_arc_points = CONCAVE_VERTICES - 1;
+    let control_points = (0..NUM_CONCAVE)
+        .flat_map(|i| {
+            let center = f64::from(i) * 2.0;
+            (0..num_arc_points)
+                .map(move |j| {
+                    let angle = f64::from(j) * 1.5 * PI / f64::from(num_arc_points - 1);
+                    [center + angle.cos(), angle.sin(), 0.0]
+                })
+                .chain(std::iter::once([center, 0.0, 0.0]))
+        })
+        .collect::<Vec<_>>();
+    let polygons = (0..NUM_CONCAVE)
+        .map(|i| (i * CONCAVE_VERTICES..(i + 1) * CONCAVE_VERTICES).collect())
+        .collect::<Vec<_>>();
+    write_fbx("concave", &control_points, &polygons)
+}
+
+/// Writes a binary FBX file of a mesh of the polygons.
+fn write_fbx(name: &str, control_points: &[[f64; 3]], polygons: &[Vec<i32>]) -> Vec<u8> {
+    // The writer errors are not `Send`, so they cannot be `anyhow::Error`.
+    write_fbx_impl(name, control_points, polygons)
+        .unwrap_or_else(|e| panic!("Failed to write fixture: {}", e))
+}
+
+/// Writes a binary FBX file of a mesh of the polygons.
+fn write_fbx_impl(
+    name: &str,
+    control_points: &[[f64; 3]],
+    polygons: &[Vec<i32>],
+) -> WriteResult<Vec<u8>> {
+    let mut writer = Writer::new(Cursor::new(Vec::new()), FbxVersion::V7_4)?;
+
+    open_node(&mut writer, "Documents", vec![])?;
+    writer.close_node()?;
+
+    open_node(&mut writer, "Objects", vec![])?;
+    leaf_node(
+        &mut writer,
+        "Model",
+        vec![MODEL_ID.into(), name_class(name, "Model"), "Mesh".into()],
+    )?;
+    write_geometry(&mut writer, name, control_points, polygons)?;
+    write_material(&mut writer, &format!("{}_material", name))?;
+    writer.close_node()?;
+
+    open_node(&mut writer, "Connections", vec![])?;
+    connect(&mut writer, MODEL_ID, 0)?;
+    connect(&mut writer, GEOMETRY_ID, MODEL_ID)?;
+    connect(&mut writer, MATERIAL_ID, MODEL_ID)?;
+    writer.close_node()?;
+
+    let sink = writer.finalize_and_flush(&FbxFooter::default())?;
+    Ok(sink.into_inner())
+}
+
+/// Opens a node with the attributes.
+fn open_node(writer: &mut FbxWriter, name: &str, attrs: Vec<AttributeValue>) -> WriteResult<()> {
+    let mut attrs_writer = writer.new_node(name)?;
+    for attr in attrs {
+        match attr {
+            AttributeValue::I32(v) => attrs_writer.append_i32(v)?,
+            AttributeValue::I64(v) => attrs_writer.append_i64(v)?,
+            AttributeValue::ArrI32(v) => attrs_writer.append_arr_i32_from_iter(None, v)?,
+            AttributeValue::ArrF64(v) => attrs_writer.append_arr_f64_from_iter(None, v)?,
+            AttributeValue::String(v) => attrs_writer.append_string_direct(&v)?,
+            v => panic!("Attribute {:?} is not used in fixtures", v),
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes a node with the attributes and no children.
+fn leaf_node(writer: &mut FbxWriter, name: &str, attrs: Vec<AttributeValue>) -> WriteResult<()> {
+    open_node(writer, name, attrs)?;
+    writer.close_node()?;
+    Ok(())
+}
+
+/// Returns the name and the class attribute of an object.
+fn name_class(name: &str, class: &str) -> AttributeValue {
+    format!("{}\u{0}\u{1}{}", name, class).into()
+}
+
+/// Writes the geometry.
+fn write_geometry(
+    writer: &mut FbxWriter,
+    name: &str,
+    control_points: &[[f64; 3]],
+    polygons: &[Vec<i32>],
+) -> WriteResult<()> {
+    open_node(
+        writer,
+        "Geometry",
+        vec![
+            GEOMETRY_ID.into(),
+            name_class(name, "Geometry"),
+            "Mesh".into(),
+        ],
+    )?;
+
+    let vertices = control_points.iter().flatten().copied();
+    leaf_node(
+        writer,
+        "Vertices",
+        vec![vertices.collect::<Vec<_>>().into()],
+    )?;
+    // The last index of each polygon is stored as its bitwise negation.
+    let polygon_vertices = polygons.iter().flat_map(|polygon| {
+        let last = polygon.len().saturating_sub(1);
+        polygon
+            .iter()
+            .enumerate()
+            .map(move |(k, &cpi)| if k == last { !cpi } else { cpi })
+    });
+    leaf_node(
+        writer,
+        "PolygonVertexIndex",
+        vec![polygon_vertices.collect::<Vec<_>>().into()],
+    )?;
+
+    let normals = control_points.iter().flat_map(|_| [0.0, 0.0, 1.0]);
+    write_layer_element(
+        writer,
+        "LayerElementNormal",
+        "ByControlPoint",
+        "Direct",
+        ("Normals", normals.collect::<Vec<f64>>().into()),
+    )?;
+    let uv = control_points.iter().flat_map(|&[x, y, _]| [x, y]);
+    write_layer_element(
+        writer,
+        "LayerElementUV",
+        "ByControlPoint",
+        "Direct",
+        ("UV", uv.collect::<Vec<f64>>().into()),
+    )?;
+    write_layer_element(
+        writer,
+        "LayerElementMaterial",
+        "ByPolygon",
+        "IndexToDirect",
+        ("Materials", vec![0i32; polygons.len()].into()),
+    )?;
+
+    open_node(writer, "Layer", vec![0i32.into()])?;
+    for ty in &[
+        "LayerElementNormal",
+        "LayerElementUV",
+        "LayerElementMaterial",
+    ] {
+        open_node(writer, "LayerElement", vec![])?;
+        leaf_node(writer, "Type", vec![(*ty).into()])?;
+        leaf_node(writer, "TypedIndex", vec![0i32.into()])?;
+        writer.close_node()?;
+    }
+    writer.close_node()?;
+
+    writer.close_node()?;
+    Ok(())
+}
+
+/// Writes the layer element with the index 0.
+fn write_layer_element(
+    writer: &mut FbxWriter,
+    name: &str,
+    mapping: &str,
+    reference: &str,
+    (data_name, data): (&str, AttributeValue),
+) -> WriteResult<()> {
+    open_node(writer, name, vec![0i32.into()])?;
+    leaf_node(writer, "MappingInformationType", vec![mapping.into()])?;
+    leaf_node(writer, "ReferenceInformationType", vec![reference.into()])?;
+    leaf_node(writer, data_name, vec![data])?;
+    writer.close_node()?;
+    Ok(())
+}
+
+/// Writes the Lambert material.
+fn write_material(writer: &mut FbxWriter, name: &str) -> WriteResult<()> {
+    open_node(
+        writer,
+        "Material",
+        vec![MATERIAL_ID.into(), name_class(name, "Material"), "".into()],
+    )?;
+    open_node(writer, "Properties70", vec![])?;
+    leaf_node(
+        writer,
+        "P",
+        vec![
+            "ShadingModel".into(),
+            "KString".into(),
+            "".into(),
+            "".into(),
+            "Lambert".into(),
+        ],
+    )?;
+    writer.close_node()?;
+    writer.close_node()?;
+    Ok(())
+}
+
+/// Writes the connection from the child object to the parent object.
+fn connect(writer: &mut FbxWriter, child: i64, parent: i64) -> WriteResult<()> {
+    leaf_node(writer, "C", vec!["OO".into(), child.into(), parent.into()])
+}
@@ -0,0 +1,82 @@
+//! Benchmarks of the FBX load pipeline.
+//!
+//! The FBX files are generated by the `common` module:
+//!
+//! * `convert`: parsing of in-memory FBX data and its conversion into a
+//!   scene, with each geometry
+//!   loaded as its bounding box (`bboxes_only`, without triangulation and
+//!   attribute expansion), as geometry only (`geometry`), and with the
+//!   materials (`full`).
+//!   The difference of `geometry` and `bboxes_only` is the cost of the
+//!   triangulation and the attribute expansion.
+//! * `fbx_load`: `fbx::load` of a file, including parsing.
+
+use std::{fs, io::Cursor};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use fbx_viewer::fbx::{self, LoadOptions};
+
+use self::common::fixtures;
+
+mod common;
+
+/// Benchmarks the conversion into scenes.
+fn convert(c: &mut Criterion) {
+    // Failures are not skipped, so that all the geometry is loaded.
+    let strict = LoadOptions {
+        strict: true,
+        ..Default::default()
+    };
+    let variants = [
+        (
+            "bboxes_only",
+            LoadOptions {
+                bboxes_only: true,
+                ..strict.clone()
+            },
+        ),
+        (
+            "geometry",
+            LoadOptions {
+                no_materials: true,
+                ..strict.clone()
+            },
+        ),
+        ("full", strict),
+    ];
+
+    let mut group = c.benchmark_group("convert");
+    for (name, bytes) in fixtures() {
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        for (variant, options) in &variants {
+            group.bench_with_input(BenchmarkId::new(*variant, name), &bytes, |b, bytes| {
+                b.iter(|| {
+                    fbx::load_from_reader_with_options(Cursor::new(bytes), options)
+                        .expect("Failed to load fixture")
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Benchmarks loading the files.
+fn fbx_load(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join(format!("fbx-viewer-bench-{}", std::process::id()));
+    fs::create_dir_all(&dir).expect("Failed to create temporary directory");
+
+    let mut group = c.benchmark_group("fbx_load");
+    for (name, bytes) in fixtures() {
+        let path = dir.join(format!("{}.fbx", name));
+        fs::write(&path, bytes).expect("Failed to write fixture");
+        group.bench_function(name, |b| {
+            b.iter(|| fbx::load(&path).expect("Failed to load fixture"))
+        });
+    }
+    group.finish();
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+criterion_group!(benches, convert, fbx_load);
+criterion_main!(benches);
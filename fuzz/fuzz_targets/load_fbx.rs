@@ -0,0 +1,12 @@
+//! Feeds arbitrary bytes through FBX parsing and scene construction.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Errors are expected for malformed data, but panics are not.
+    if let Ok(scene) = fbx_viewer::fbx::load_from_bytes(data) {
+        let _ = scene.stats();
+    }
+});
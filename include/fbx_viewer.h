@@ -0,0 +1,121 @@
+/*
+ * C API of fbx-viewer.
+ *
+ * Build the library with the `ffi` feature, e.g.:
+ *     cargo rustc --release --lib --features ffi --crate-type cdylib
+ *
+ * All pointers returned from the functions are owned by the scene, and valid
+ * until the scene is freed by `fbx_viewer_scene_free()`.
+ */
+
+#ifndef FBX_VIEWER_H
+#define FBX_VIEWER_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Scene loaded from an FBX file. */
+typedef struct FbxViewerScene FbxViewerScene;
+
+/* Flattened mesh. */
+typedef struct FbxViewerMesh {
+	/* Name, or NULL if unnamed. */
+	const char *name;
+	/* Positions (3 floats per vertex). */
+	const float *positions;
+	/* Normals (3 floats per vertex). */
+	const float *normals;
+	/* UV (2 floats per vertex). */
+	const float *uv;
+	/* Number of vertices. */
+	size_t num_vertices;
+	/* Number of submeshes. */
+	size_t num_submeshes;
+} FbxViewerMesh;
+
+/* Submesh with a single material. */
+typedef struct FbxViewerSubmesh {
+	/* Triangle vertex indices. */
+	const uint32_t *indices;
+	/* Number of indices. */
+	size_t num_indices;
+	/* Material index, or SIZE_MAX if the submesh has no material. */
+	size_t material;
+} FbxViewerSubmesh;
+
+/* Material. */
+typedef struct FbxViewerMaterial {
+	/* Name, or NULL if unnamed. */
+	const char *name;
+	float ambient[3];
+	float diffuse[3];
+	float emissive[3];
+	/* Black for Lambert materials. */
+	float specular[3];
+	/* Zero for Lambert materials. */
+	float shininess;
+	/* Diffuse texture index, or SIZE_MAX if the material has no texture. */
+	size_t diffuse_texture;
+} FbxViewerMaterial;
+
+/* Texture. */
+typedef struct FbxViewerTexture {
+	/* Name, or NULL if unnamed. */
+	const char *name;
+	uint32_t width;
+	uint32_t height;
+	/* RGBA pixels (4 bytes per pixel, rows from top to bottom). */
+	const uint8_t *pixels;
+	/* Whether the texture can be transparent. */
+	bool transparent;
+	/* Whether the texture repeats along U axis (clamped otherwise). */
+	bool repeat_u;
+	/* Whether the texture repeats along V axis (clamped otherwise). */
+	bool repeat_v;
+} FbxViewerTexture;
+
+/*
+ * Loads the FBX file at the UTF-8 path.
+ * Returns NULL on failure (see `fbx_viewer_last_error()`).
+ */
+FbxViewerScene *fbx_viewer_scene_load(const char *path);
+/* Frees the scene. NULL is ignored. */
+void fbx_viewer_scene_free(FbxViewerScene *scene);
+/*
+ * Returns the message of the last error on the current thread, or NULL.
+ * The string is valid until the next call of a function in this API on the
+ * same thread.
+ */
+const char *fbx_viewer_last_error(void);
+
+size_t fbx_viewer_scene_num_meshes(const FbxViewerScene *scene);
+/* Returns false if the index is out of range. */
+bool fbx_viewer_scene_mesh(const FbxViewerScene *scene, size_t index, FbxViewerMesh *out);
+/* Returns false if the index is out of range. */
+bool fbx_viewer_scene_submesh(
+	const FbxViewerScene *scene,
+	size_t mesh_index,
+	size_t submesh_index,
+	FbxViewerSubmesh *out);
+
+size_t fbx_viewer_scene_num_materials(const FbxViewerScene *scene);
+/* Returns false if the index is out of range. */
+bool fbx_viewer_scene_material(
+	const FbxViewerScene *scene,
+	size_t index,
+	FbxViewerMaterial *out);
+
+size_t fbx_viewer_scene_num_textures(const FbxViewerScene *scene);
+/* Returns false if the index is out of range. */
+bool fbx_viewer_scene_texture(const FbxViewerScene *scene, size_t index, FbxViewerTexture *out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* FBX_VIEWER_H */
@@ -0,0 +1,88 @@
+//! Persisted configuration.
+//!
+//! The configuration is stored as JSON in `fbx-viewer/config.json` under the
+//! user configuration directory (`$XDG_CONFIG_HOME`, `%APPDATA%`, or
+//! `~/.config`).
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+/// Maximum number of recent files kept.
+const MAX_RECENT_FILES: usize = 10;
+
+/// Persisted configuration.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Recently opened files, from newest to oldest.
+    pub recent_files: Vec<PathBuf>,
+}
+
+impl Config {
+    /// Loads the configuration.
+    ///
+    /// Returns the default configuration if the file does not exist or is
+    /// broken.
+    pub fn load() -> Self {
+        let path = match config_path() {
+            Some(v) => v,
+            None => return Self::default(),
+        };
+        match Self::load_from(&path) {
+            Ok(Some(config)) => config,
+            Ok(None) => Self::default(),
+            Err(e) => {
+                warn!("Failed to load config from {}: {:#}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Loads the configuration from the file, if it exists.
+    fn load_from(path: &Path) -> anyhow::Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path).context("Failed to read file")?;
+        let config = serde_json::from_str(&content).context("Failed to parse config")?;
+        debug!("Loaded config from {}", path.display());
+        Ok(Some(config))
+    }
+
+    /// Saves the configuration.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = config_path().ok_or_else(|| anyhow!("Config directory is unknown"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory {}", dir.display()))?;
+        }
+        let content = serde_json::to_string_pretty(self).context("Failed to serialize config")?;
+        fs::write(&path, content)
+            .with_context(|| format!("Failed to write config to {}", path.display()))?;
+        debug!("Saved config to {}", path.display());
+        Ok(())
+    }
+
+    /// Records the file as the most recently opened one.
+    pub fn add_recent_file(&mut self, path: &Path) {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+        self.recent_files.retain(|recent| *recent != path);
+        self.recent_files.insert(0, path);
+        self.recent_files.truncate(MAX_RECENT_FILES);
+    }
+}
+
+/// Returns the path of the config file.
+fn config_path() -> Option<PathBuf> {
+    let dir = env::var_os("XDG_CONFIG_HOME")
+        .or_else(|| env::var_os("APPDATA"))
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(dir.join("fbx-viewer").join("config.json"))
+}
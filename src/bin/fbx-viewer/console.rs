@@ -0,0 +1,224 @@
+//! Log console.
+
+use std::{
+    collections::VecDeque,
+    fmt::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+use tracing::{
+    field::{Field, Visit},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// Maximum number of records kept in the console.
+const CAPACITY: usize = 256;
+
+/// Log record.
+#[derive(Debug, Clone)]
+pub struct Record {
+    /// Level.
+    pub level: Level,
+    /// Target.
+    pub target: String,
+    /// Message, followed by the other fields.
+    pub message: String,
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{} {}] {}", self.level, self.target, self.message)
+    }
+}
+
+/// Log console.
+///
+/// Keeps the latest log records in a ring buffer.
+#[derive(Debug, Clone, Default)]
+pub struct LogConsole {
+    /// Records, from oldest to newest.
+    records: Arc<Mutex<VecDeque<Record>>>,
+}
+
+impl LogConsole {
+    /// Creates a new empty `LogConsole`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a layer which captures events into the console.
+    pub fn layer(&self) -> ConsoleLayer {
+        ConsoleLayer {
+            console: self.clone(),
+        }
+    }
+
+    /// Returns the records at or above the given level, from oldest to newest.
+    pub fn records(&self, min_level: Level) -> Vec<Record> {
+        self.records
+            .lock()
+            .expect("Log console is poisoned")
+            .iter()
+            .filter(|record| record.level <= min_level)
+            .cloned()
+            .collect()
+    }
+
+    /// Adds the record, dropping the oldest one if the console is full.
+    fn push(&self, record: Record) {
+        let mut records = self.records.lock().expect("Log console is poisoned");
+        if records.len() >= CAPACITY {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+}
+
+/// Layer which captures events into a [`LogConsole`].
+#[derive(Debug, Clone)]
+pub struct ConsoleLayer {
+    /// Console.
+    console: LogConsole,
+}
+
+impl<S: Subscriber> Layer<S> for ConsoleLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        self.console.push(Record {
+            level: *metadata.level(),
+            target: metadata.target().to_owned(),
+            message: visitor.message,
+        });
+    }
+}
+
+/// Visitor to format the fields of an event.
+#[derive(Default)]
+struct MessageVisitor {
+    /// Formatted message.
+    message: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if !self.message.is_empty() {
+            self.message.push(' ');
+        }
+        // Writing to a `String` never fails.
+        let _ = if field.name() == "message" {
+            write!(self.message, "{:?}", value)
+        } else {
+            write!(self.message, "{}={:?}", field.name(), value)
+        };
+    }
+}
+
+/// Log console panel drawn over the frames.
+#[derive(Debug, Clone)]
+pub struct ConsolePanel {
+    /// Title shown above the records.
+    pub title: String,
+    /// Records to show, from oldest to newest.
+    pub records: Vec<Record>,
+}
+
+impl ConsolePanel {
+    /// Lays out the panel in the text rows of the given number of columns.
+    ///
+    /// The title takes the first row, and the newest records fill the
+    /// others, wrapped at the row width.
+    /// Returns the rows with the levels of their records, or `None` for the
+    /// title.
+    pub fn layout(&self, columns: usize, rows: usize) -> Vec<(Option<Level>, String)> {
+        if columns == 0 || rows == 0 {
+            return Vec::new();
+        }
+        let mut lines = VecDeque::new();
+        'records: for record in self.records.iter().rev() {
+            let text = record.to_string();
+            let wrapped = text.lines().flat_map(|line| wrap(line, columns));
+            for line in wrapped.collect::<Vec<_>>().into_iter().rev() {
+                if lines.len() + 1 >= rows {
+                    break 'records;
+                }
+                lines.push_front((Some(record.level), line));
+            }
+        }
+        lines.push_front((None, self.title.chars().take(columns).collect()));
+
+        lines.into()
+    }
+}
+
+/// Splits the line into the rows of the given number of characters.
+///
+/// Empty lines take a row.
+fn wrap(line: &str, columns: usize) -> Vec<String> {
+    let chars = line.chars().collect::<Vec<_>>();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+    chars
+        .chunks(columns)
+        .map(|chunk| chunk.iter().collect())
+        .collect()
+}
+
+/// Returns the next level of the console filter.
+///
+/// Cycles through `WARN`, `ERROR`, and `INFO`.
+pub fn next_filter_level(level: Level) -> Level {
+    match level {
+        Level::WARN => Level::ERROR,
+        Level::ERROR => Level::INFO,
+        _ => Level::WARN,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a record of the level and the message.
+    fn record(level: Level, message: &str) -> Record {
+        Record {
+            level,
+            target: "t".to_owned(),
+            message: message.to_owned(),
+        }
+    }
+
+    #[test]
+    fn layout() {
+        let panel = ConsolePanel {
+            title: "Title longer than the row".to_owned(),
+            records: vec![
+                record(Level::INFO, "old"),
+                record(Level::WARN, "wrapped message"),
+                record(Level::ERROR, "new"),
+            ],
+        };
+
+        // "[WARN t] wrapped message" is wrapped at 10 columns, and its first
+        // row does not fit.
+        assert_eq!(
+            panel.layout(10, 5),
+            [
+                (None, "Title long".to_owned()),
+                (Some(Level::WARN), "rapped mes".to_owned()),
+                (Some(Level::WARN), "sage".to_owned()),
+                (Some(Level::ERROR), "[ERROR t] ".to_owned()),
+                (Some(Level::ERROR), "new".to_owned()),
+            ]
+        );
+        assert_eq!(panel.layout(80, 2).len(), 2);
+        assert_eq!(
+            panel.layout(80, 2)[1],
+            (Some(Level::ERROR), "[ERROR t] new".to_owned())
+        );
+        assert!(panel.layout(0, 3).is_empty());
+    }
+}
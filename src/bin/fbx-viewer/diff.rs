@@ -0,0 +1,20 @@
+//! Scene diff.
+
+use anyhow::Context;
+use fbx_viewer::{data::SceneDiff, fbx::LoadOptions, DiffOpt};
+
+use crate::load_scene;
+
+/// Loads the two FBX or glTF files and prints the differences.
+pub fn main(opt: &DiffOpt, load_options: &LoadOptions) -> anyhow::Result<()> {
+    let old = load_scene(&opt.old, load_options)
+        .with_context(|| format!("Failed to load scene from {}", opt.old.display()))?;
+    let new = load_scene(&opt.new, load_options)
+        .with_context(|| format!("Failed to load scene from {}", opt.new.display()))?;
+
+    println!("--- {}", opt.old.display());
+    println!("+++ {}", opt.new.display());
+    print!("{}", SceneDiff::new(&old, &new));
+
+    Ok(())
+}
@@ -0,0 +1,271 @@
+//! Scene dump.
+
+use anyhow::Context;
+use fbx_viewer::{
+    data::{Material, Mesh, Node, Scene, ShadingData, Texture, WrapMode},
+    fbx::LoadOptions,
+    DumpFormat, DumpOpt,
+};
+use rgb::RGB;
+use serde::Serialize;
+
+use crate::load_scene;
+
+/// Loads the FBX or glTF file and prints the scene description.
+pub fn main(opt: &DumpOpt, load_options: &LoadOptions) -> anyhow::Result<()> {
+    let scene = load_scene(&opt.fbx_path, load_options)
+        .with_context(|| format!("Failed to load scene from {}", opt.fbx_path.display()))?;
+
+    let dump = SceneDump::new(&scene)?;
+    match opt.format {
+        DumpFormat::Json => {
+            let json = serde_json::to_string_pretty(&dump).context("Failed to serialize scene")?;
+            println!("{}", json);
+        }
+    }
+
+    Ok(())
+}
+
+/// Description of a scene.
+///
+/// The hierarchy is meshes, their submeshes, and the nodes, materials, and
+/// textures they refer to by indices.
+#[derive(Serialize)]
+struct SceneDump<'a> {
+    /// Name.
+    name: Option<&'a str>,
+    /// Nodes.
+    nodes: Vec<NodeDump<'a>>,
+    /// Meshes.
+    meshes: Vec<MeshDump<'a>>,
+    /// Materials.
+    materials: Vec<MaterialDump<'a>>,
+    /// Textures.
+    textures: Vec<TextureDump<'a>>,
+    /// Objects skipped on load failures.
+    warnings: Vec<WarningDump<'a>>,
+}
+
+impl<'a> SceneDump<'a> {
+    /// Creates a new `SceneDump`.
+    fn new(scene: &'a Scene) -> anyhow::Result<Self> {
+        Ok(Self {
+            name: scene.name(),
+            nodes: scene.nodes().map(NodeDump::new).collect(),
+            meshes: scene
+                .meshes()
+                .map(|mesh| MeshDump::new(scene, mesh))
+                .collect::<anyhow::Result<_>>()?,
+            materials: scene.materials().map(MaterialDump::new).collect(),
+            textures: scene
+                .textures()
+                .map(TextureDump::new)
+                .collect::<anyhow::Result<_>>()?,
+            warnings: scene
+                .warnings()
+                .iter()
+                .map(|warning| WarningDump {
+                    object: &warning.object,
+                    message: &warning.message,
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Description of an object skipped on load failure.
+#[derive(Serialize)]
+struct WarningDump<'a> {
+    /// Object.
+    object: &'a str,
+    /// Error message.
+    message: &'a str,
+}
+
+/// Description of a node.
+#[derive(Serialize)]
+struct NodeDump<'a> {
+    /// Name.
+    name: Option<&'a str>,
+    /// Parent node index.
+    parent: Option<usize>,
+    /// Child node indices.
+    children: Vec<usize>,
+    /// Local translation.
+    translation: [f64; 3],
+    /// Local rotation in Euler angles in degrees.
+    rotation: [f64; 3],
+    /// Local scaling.
+    scaling: [f64; 3],
+}
+
+impl<'a> NodeDump<'a> {
+    /// Creates a new `NodeDump`.
+    fn new(node: &'a Node) -> Self {
+        Self {
+            name: node.name.as_deref(),
+            parent: node.parent.map(|index| index.to_usize()),
+            children: node.children.iter().map(|index| index.to_usize()).collect(),
+            translation: node.translation.into(),
+            rotation: node.rotation.into(),
+            scaling: node.scaling.into(),
+        }
+    }
+}
+
+/// Description of a mesh.
+#[derive(Serialize)]
+struct MeshDump<'a> {
+    /// Name.
+    name: Option<&'a str>,
+    /// Node index.
+    node: Option<usize>,
+    /// Geometry mesh index.
+    geometry: usize,
+    /// Name of the geometry mesh.
+    geometry_name: Option<&'a str>,
+    /// Number of vertices.
+    vertices: usize,
+    /// Number of triangles.
+    triangles: usize,
+    /// Submeshes.
+    submeshes: Vec<SubmeshDump>,
+}
+
+impl<'a> MeshDump<'a> {
+    /// Creates a new `MeshDump`.
+    fn new(scene: &'a Scene, mesh: &'a Mesh) -> anyhow::Result<Self> {
+        let geometry = scene
+            .geometry_mesh(mesh.geometry_mesh_index)
+            .with_context(|| format!("Failed to get geometry of mesh {:?}", mesh.name))?;
+        let submeshes = geometry
+            .indices_per_material
+            .iter()
+            .enumerate()
+            .map(|(i, indices)| SubmeshDump {
+                material: mesh.materials.get(i).map(|index| index.to_usize()),
+                triangles: indices.len() / 3,
+            })
+            .collect();
+
+        Ok(Self {
+            name: mesh.name.as_deref(),
+            node: mesh.node.map(|index| index.to_usize()),
+            geometry: mesh.geometry_mesh_index.to_usize(),
+            geometry_name: geometry.name.as_deref(),
+            vertices: geometry.positions.len(),
+            triangles: geometry.num_triangles(),
+            submeshes,
+        })
+    }
+}
+
+/// Description of a submesh.
+#[derive(Serialize)]
+struct SubmeshDump {
+    /// Material index.
+    material: Option<usize>,
+    /// Number of triangles.
+    triangles: usize,
+}
+
+/// Description of a material.
+#[derive(Serialize)]
+struct MaterialDump<'a> {
+    /// Name.
+    name: Option<&'a str>,
+    /// Shading model.
+    shading_model: &'static str,
+    /// Ambient color.
+    ambient: [f32; 3],
+    /// Diffuse color.
+    diffuse: [f32; 3],
+    /// Emissive color.
+    emissive: [f32; 3],
+    /// Specular color (Phong only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    specular: Option<[f32; 3]>,
+    /// Shininess (Phong only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shininess: Option<f32>,
+    /// Reflection color (Phong only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reflection: Option<[f32; 3]>,
+    /// Diffuse texture index.
+    diffuse_texture: Option<usize>,
+}
+
+impl<'a> MaterialDump<'a> {
+    /// Creates a new `MaterialDump`.
+    fn new(material: &'a Material) -> Self {
+        let lambert = material.data.lambert();
+        let phong = match &material.data {
+            ShadingData::Lambert(_) => None,
+            ShadingData::Phong(phong) => Some(phong),
+        };
+
+        Self {
+            name: material.name.as_deref(),
+            shading_model: if phong.is_some() { "phong" } else { "lambert" },
+            ambient: rgb(lambert.ambient),
+            diffuse: rgb(lambert.diffuse),
+            emissive: rgb(lambert.emissive),
+            specular: phong.map(|phong| rgb(phong.specular)),
+            shininess: phong.map(|phong| phong.shininess),
+            reflection: phong.map(|phong| rgb(phong.reflection)),
+            diffuse_texture: material.diffuse_texture.map(|index| index.to_usize()),
+        }
+    }
+}
+
+/// Description of a texture.
+#[derive(Serialize)]
+struct TextureDump<'a> {
+    /// Name.
+    name: Option<&'a str>,
+    /// Width.
+    width: u32,
+    /// Height.
+    height: u32,
+    /// Color type of the image.
+    color: String,
+    /// Whether the texture can be transparent.
+    transparent: bool,
+    /// Wrap mode for U axis.
+    wrap_mode_u: &'static str,
+    /// Wrap mode for V axis.
+    wrap_mode_v: &'static str,
+}
+
+impl<'a> TextureDump<'a> {
+    /// Creates a new `TextureDump`.
+    fn new(texture: &'a Texture) -> anyhow::Result<Self> {
+        let image = texture
+            .image
+            .get()
+            .with_context(|| format!("Failed to decode texture {:?}", texture.name))?;
+        Ok(Self {
+            name: texture.name.as_deref(),
+            width: image.width(),
+            height: image.height(),
+            color: format!("{:?}", image.color()),
+            transparent: texture.transparent,
+            wrap_mode_u: wrap_mode(texture.wrap_mode_u),
+            wrap_mode_v: wrap_mode(texture.wrap_mode_v),
+        })
+    }
+}
+
+/// Returns the color as an array.
+fn rgb(color: RGB<f32>) -> [f32; 3] {
+    [color.r, color.g, color.b]
+}
+
+/// Returns the name of the wrap mode.
+fn wrap_mode(mode: WrapMode) -> &'static str {
+    match mode {
+        WrapMode::Repeat => "repeat",
+        WrapMode::ClampToEdge => "clamp_to_edge",
+    }
+}
@@ -0,0 +1,57 @@
+//! Scene export.
+
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use fbx_viewer::{data::Scene, export, fbx::LoadOptions, ExportOpt};
+use tracing::info;
+
+use crate::load_scene;
+
+/// Loads the FBX or glTF file and writes it in the format detected from the output
+/// file extension.
+pub fn main(opt: &ExportOpt, load_options: &LoadOptions) -> anyhow::Result<()> {
+    let scene = load_scene(&opt.fbx_path, load_options)
+        .with_context(|| format!("Failed to load scene from {}", opt.fbx_path.display()))?;
+
+    if opt.check_watertight {
+        let leaky = scene
+            .stats()
+            .context("Failed to compute scene statistics")?
+            .meshes
+            .into_iter()
+            .filter(|mesh| !mesh.watertight)
+            .map(|mesh| format!("{:?}", mesh.name.unwrap_or_default()))
+            .collect::<Vec<_>>();
+        if !leaky.is_empty() {
+            bail!("Meshes are not watertight: {}", leaky.join(", "));
+        }
+    }
+
+    write_scene(&scene, &opt.output)?;
+    info!(
+        "Exported {} to {}",
+        opt.fbx_path.display(),
+        opt.output.display()
+    );
+
+    Ok(())
+}
+
+/// Writes the scene in the format detected from the file extension.
+pub fn write_scene(scene: &Scene, path: &Path) -> anyhow::Result<()> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("ply") => export::ply::write_ply(scene, path),
+        Some("stl") => export::stl::write_stl(scene, path),
+        Some("usda") => export::usd::write_usda(scene, path),
+        Some("usdz") => export::usd::write_usdz(scene, path),
+        _ => bail!(
+            "Unsupported output format (expected ply, stl, usda, or usdz): {}",
+            path.display()
+        ),
+    }
+}
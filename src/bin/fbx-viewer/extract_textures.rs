@@ -0,0 +1,98 @@
+//! Embedded texture extraction.
+
+use std::{collections::HashSet, fs, path::Path};
+
+use anyhow::Context;
+use fbx_viewer::{fbx, ExtractTexturesOpt};
+use tracing::{info, warn};
+
+/// Writes the textures embedded in the FBX file to the output directory.
+pub fn main(opt: &ExtractTexturesOpt) -> anyhow::Result<()> {
+    let media = fbx::load_embedded_media(&opt.fbx_path)
+        .with_context(|| format!("Failed to load FBX file {}", opt.fbx_path.display()))?;
+    if media.is_empty() {
+        warn!("No embedded textures in {}", opt.fbx_path.display());
+        return Ok(());
+    }
+
+    fs::create_dir_all(&opt.out)
+        .with_context(|| format!("Failed to create directory {}", opt.out.display()))?;
+    let mut used_names = HashSet::new();
+    for (i, media) in media.iter().enumerate() {
+        let filename = unique_filename(&file_name(media, i), |name| {
+            !used_names.contains(&name.to_ascii_lowercase())
+        });
+        used_names.insert(filename.to_ascii_lowercase());
+
+        let path = opt.out.join(&filename);
+        fs::write(&path, &media.content)
+            .with_context(|| format!("Failed to write texture {}", path.display()))?;
+        println!("{}", path.display());
+    }
+    info!(
+        "Extracted {} texture(s) to {}",
+        media.len(),
+        opt.out.display()
+    );
+
+    Ok(())
+}
+
+/// Returns the file name for the media.
+///
+/// The base name of the original file is used if available.
+/// The extension is detected from the content if the name has none.
+fn file_name(media: &fbx::EmbeddedMedia, index: usize) -> String {
+    // FBX files written on Windows use backslashes as path separators.
+    let base = media
+        .relative_filename
+        .as_deref()
+        .and_then(|path| path.rsplit(&['/', '\\'][..]).next())
+        .or(media.name.as_deref())
+        .map(sanitize)
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| format!("texture_{}", index));
+
+    if Path::new(&base).extension().is_some() {
+        return base;
+    }
+    let extension = image::guess_format(&media.content)
+        .ok()
+        .and_then(|format| format.extensions_str().first())
+        .copied()
+        .unwrap_or("bin");
+    format!("{}.{}", base, extension)
+}
+
+/// Replaces characters which are unsafe in file names.
+fn sanitize(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || "-_.".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    // Avoid hidden files, `.`, and `..`.
+    name.trim_start_matches('.').to_owned()
+}
+
+/// Returns the file name with a numeric suffix if the name is not available.
+fn unique_filename(name: &str, is_available: impl Fn(&str) -> bool) -> String {
+    if is_available(name) {
+        return name.to_owned();
+    }
+    let path = Path::new(name);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or(name);
+    let extension = path.extension().and_then(|s| s.to_str());
+    (1..)
+        .map(|n| match extension {
+            Some(ext) => format!("{}_{}.{}", stem, n, ext),
+            None => format!("{}_{}", stem, n),
+        })
+        .find(|candidate| is_available(candidate))
+        .expect("Should never fail: infinitely many candidates")
+}
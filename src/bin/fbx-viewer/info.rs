@@ -0,0 +1,299 @@
+//! Scene statistics.
+
+use std::borrow::Cow;
+
+use anyhow::Context;
+use fbx_viewer::{
+    data::{LoadWarning, MeshStats, SceneStats, TextureStats},
+    fbx::LoadOptions,
+    InfoFormat, InfoOpt,
+};
+use serde::Serialize;
+
+use crate::load_scene;
+
+/// Columns of the CSV output.
+const CSV_HEADER: [&str; 16] = [
+    "kind",
+    "name",
+    "vertices",
+    "triangles",
+    "materials",
+    "surface_area",
+    "volume",
+    "watertight",
+    "open_edges",
+    "non_manifold_edges",
+    "duplicate_faces",
+    "size_x",
+    "size_y",
+    "size_z",
+    "width",
+    "height",
+];
+
+/// Loads the FBX or glTF file and prints the statistics.
+pub fn main(opt: &InfoOpt, load_options: &LoadOptions) -> anyhow::Result<()> {
+    let scene = load_scene(&opt.fbx_path, load_options)
+        .with_context(|| format!("Failed to load scene from {}", opt.fbx_path.display()))?;
+    let stats = scene
+        .stats()
+        .context("Failed to compute scene statistics")?;
+    let path = opt.fbx_path.to_string_lossy();
+
+    match opt.format {
+        InfoFormat::Text => {
+            println!("{}", path);
+            print!("{}", stats);
+            if !scene.warnings().is_empty() {
+                println!("Skipped objects:");
+                for warning in scene.warnings() {
+                    println!("  {}", warning);
+                }
+            }
+        }
+        InfoFormat::Json => {
+            let report = StatsReport::new(&path, &stats, scene.warnings());
+            let json =
+                serde_json::to_string_pretty(&report).context("Failed to serialize statistics")?;
+            println!("{}", json);
+        }
+        InfoFormat::Csv => print!("{}", csv(&path, &stats)),
+    }
+
+    Ok(())
+}
+
+/// Statistics of a scene, for JSON output.
+#[derive(Serialize)]
+struct StatsReport<'a> {
+    /// Path of the file.
+    path: &'a str,
+    /// Meshes.
+    meshes: Vec<MeshReport<'a>>,
+    /// Total number of triangles.
+    total_triangles: usize,
+    /// Total surface area.
+    total_surface_area: f32,
+    /// Number of materials.
+    materials: usize,
+    /// Textures.
+    textures: Vec<TextureReport<'a>>,
+    /// Objects skipped on load failures.
+    warnings: Vec<WarningReport<'a>>,
+}
+
+impl<'a> StatsReport<'a> {
+    /// Creates a new `StatsReport`.
+    fn new(path: &'a str, stats: &'a SceneStats, warnings: &'a [LoadWarning]) -> Self {
+        Self {
+            path,
+            meshes: stats.meshes.iter().map(MeshReport::new).collect(),
+            total_triangles: stats.num_triangles(),
+            total_surface_area: stats.surface_area(),
+            materials: stats.num_materials,
+            textures: stats.textures.iter().map(TextureReport::new).collect(),
+            warnings: warnings
+                .iter()
+                .map(|warning| WarningReport {
+                    object: &warning.object,
+                    message: &warning.message,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Statistics of a mesh, for JSON output.
+#[derive(Serialize)]
+struct MeshReport<'a> {
+    /// Name.
+    name: Option<&'a str>,
+    /// Number of distinct vertex positions.
+    vertices: usize,
+    /// Number of triangles.
+    triangles: usize,
+    /// Surface area.
+    surface_area: f32,
+    /// Volume.
+    volume: f32,
+    /// Whether the mesh is watertight.
+    watertight: bool,
+    /// Number of edges of only one triangle.
+    open_edges: usize,
+    /// Number of edges shared by more than two triangles.
+    non_manifold_edges: usize,
+    /// Number of triangles at the same positions as another triangle.
+    duplicate_faces: usize,
+    /// Size of the bounding box.
+    dimensions: Option<[f32; 3]>,
+}
+
+impl<'a> MeshReport<'a> {
+    /// Creates a new `MeshReport`.
+    fn new(stats: &'a MeshStats) -> Self {
+        Self {
+            name: stats.name.as_deref(),
+            vertices: stats.num_vertices,
+            triangles: stats.num_triangles,
+            surface_area: stats.surface_area,
+            volume: stats.volume,
+            watertight: stats.watertight,
+            open_edges: stats.open_edges,
+            non_manifold_edges: stats.non_manifold_edges,
+            duplicate_faces: stats.duplicate_faces,
+            dimensions: stats.dimensions.map(Into::into),
+        }
+    }
+}
+
+/// Statistics of a texture, for JSON output.
+#[derive(Serialize)]
+struct TextureReport<'a> {
+    /// Name.
+    name: Option<&'a str>,
+    /// Width, or `None` if the image is broken.
+    width: Option<u32>,
+    /// Height, or `None` if the image is broken.
+    height: Option<u32>,
+}
+
+impl<'a> TextureReport<'a> {
+    /// Creates a new `TextureReport`.
+    fn new(stats: &'a TextureStats) -> Self {
+        Self {
+            name: stats.name.as_deref(),
+            width: stats.dimensions.map(|(width, _)| width),
+            height: stats.dimensions.map(|(_, height)| height),
+        }
+    }
+}
+
+/// Object skipped on load failure, for JSON output.
+#[derive(Serialize)]
+struct WarningReport<'a> {
+    /// Object.
+    object: &'a str,
+    /// Error message.
+    message: &'a str,
+}
+
+/// Returns the statistics as CSV.
+///
+/// The first row is the header, and the second row is the totals of the
+/// scene.
+fn csv(path: &str, stats: &SceneStats) -> String {
+    let scene = CsvRow {
+        kind: "scene",
+        name: path,
+        vertices: Some(stats.meshes.iter().map(|mesh| mesh.num_vertices).sum()),
+        triangles: Some(stats.num_triangles()),
+        materials: Some(stats.num_materials),
+        surface_area: Some(stats.surface_area()),
+        ..Default::default()
+    };
+    let meshes = stats.meshes.iter().map(|mesh| CsvRow {
+        kind: "mesh",
+        name: mesh.name.as_deref().unwrap_or_default(),
+        vertices: Some(mesh.num_vertices),
+        triangles: Some(mesh.num_triangles),
+        surface_area: Some(mesh.surface_area),
+        volume: Some(mesh.volume),
+        watertight: Some(mesh.watertight),
+        open_edges: Some(mesh.open_edges),
+        non_manifold_edges: Some(mesh.non_manifold_edges),
+        duplicate_faces: Some(mesh.duplicate_faces),
+        size: mesh.dimensions.map(Into::into),
+        ..Default::default()
+    });
+    let textures = stats.textures.iter().map(|texture| CsvRow {
+        kind: "texture",
+        name: texture.name.as_deref().unwrap_or_default(),
+        dimensions: texture.dimensions,
+        ..Default::default()
+    });
+
+    let mut csv = CSV_HEADER.join(",") + "\n";
+    for row in Some(scene).into_iter().chain(meshes).chain(textures) {
+        let fields = row.fields();
+        let fields = fields
+            .iter()
+            .map(|field| csv_escape(field))
+            .collect::<Vec<_>>();
+        csv += &fields.join(",");
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Row of the CSV output.
+///
+/// Columns which do not apply to the kind of the row are `None`, and left
+/// empty.
+#[derive(Default)]
+struct CsvRow<'a> {
+    /// Kind of the row: `scene`, `mesh`, or `texture`.
+    kind: &'static str,
+    /// Name, or the path of the file for the scene.
+    name: &'a str,
+    /// Number of distinct vertex positions.
+    vertices: Option<usize>,
+    /// Number of triangles.
+    triangles: Option<usize>,
+    /// Number of materials.
+    materials: Option<usize>,
+    /// Surface area.
+    surface_area: Option<f32>,
+    /// Volume.
+    volume: Option<f32>,
+    /// Whether the mesh is watertight.
+    watertight: Option<bool>,
+    /// Number of edges of only one triangle.
+    open_edges: Option<usize>,
+    /// Number of edges shared by more than two triangles.
+    non_manifold_edges: Option<usize>,
+    /// Number of triangles at the same positions as another triangle.
+    duplicate_faces: Option<usize>,
+    /// Size of the bounding box.
+    size: Option<[f32; 3]>,
+    /// Width and height of the texture.
+    dimensions: Option<(u32, u32)>,
+}
+
+impl CsvRow<'_> {
+    /// Returns the fields in the order of [`CSV_HEADER`].
+    fn fields(&self) -> [String; CSV_HEADER.len()] {
+        /// Formats the value, or returns an empty string for `None`.
+        fn opt<T: ToString>(v: Option<T>) -> String {
+            v.map_or_else(String::new, |v| v.to_string())
+        }
+
+        [
+            self.kind.to_owned(),
+            self.name.to_owned(),
+            opt(self.vertices),
+            opt(self.triangles),
+            opt(self.materials),
+            opt(self.surface_area),
+            opt(self.volume),
+            opt(self.watertight),
+            opt(self.open_edges),
+            opt(self.non_manifold_edges),
+            opt(self.duplicate_faces),
+            opt(self.size.map(|size| size[0])),
+            opt(self.size.map(|size| size[1])),
+            opt(self.size.map(|size| size[2])),
+            opt(self.dimensions.map(|(width, _)| width)),
+            opt(self.dimensions.map(|(_, height)| height)),
+        ]
+    }
+}
+
+/// Quotes the CSV field if necessary.
+fn csv_escape(field: &str) -> Cow<'_, str> {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\"")).into()
+    } else {
+        field.into()
+    }
+}
@@ -1,15 +1,142 @@
 //! FBX viewer.
 
+use std::path::Path;
+
 use clap::Parser;
-use fbx_viewer::CliOpt;
-use log::info;
+use fbx_viewer::{
+    data::Scene,
+    fbx::{self, LoadOptions},
+    gltf, CliOpt, Command,
+};
+use tracing::info;
+use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
+use tracing_subscriber::{
+    filter::{EnvFilter, LevelFilter},
+    layer::SubscriberExt,
+    util::SubscriberInitExt,
+    Layer,
+};
+
+use self::{console::LogConsole, timings::LoadTimings};
 
+pub mod config;
+pub mod console;
+pub mod diff;
+pub mod dump;
+pub mod export;
+pub mod extract_textures;
+pub mod info;
+pub mod renderer;
+pub mod timings;
+pub mod viewer;
 pub mod vulkan;
 
 fn main() {
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+    let opt = CliOpt::parse();
+    let console = LogConsole::new();
+    let timings = opt.timings.then(LoadTimings::new);
+    let trace_guard = init_tracing(&console, timings.as_ref(), opt.trace_output.as_deref());
     info!("version: {}", env!("CARGO_PKG_VERSION"));
+    let load_options = LoadOptions {
+        strict: opt.strict,
+        include: opt.include.clone(),
+        exclude: opt.exclude.clone(),
+        no_textures: opt.no_textures,
+        texture_cache: opt.texture_cache,
+        no_materials: opt.no_materials,
+        alpha_test: opt.alpha_test,
+        bboxes_only: opt.preview_bboxes,
+        flatten: opt.flatten,
+    };
 
-    let opt = CliOpt::parse();
-    vulkan::main(opt).expect("Vulkan mode failed");
+    match opt.command {
+        Some(Command::Diff(ref diff_opt)) => {
+            diff::main(diff_opt, &load_options).expect("Diff failed")
+        }
+        Some(Command::Dump(ref dump_opt)) => {
+            dump::main(dump_opt, &load_options).expect("Dump failed")
+        }
+        Some(Command::Export(ref export_opt)) => {
+            export::main(export_opt, &load_options).expect("Export failed")
+        }
+        Some(Command::ExtractTextures(ref extract_opt)) => {
+            extract_textures::main(extract_opt).expect("Texture extraction failed")
+        }
+        Some(Command::Info(ref info_opt)) => {
+            info::main(info_opt, &load_options).expect("Info failed")
+        }
+        // The viewer prints the timings for each loaded scene.
+        None => vulkan::main(opt, &load_options, console, timings.clone(), trace_guard)
+            .expect("Vulkan mode failed"),
+    }
+    if let Some(timings) = timings {
+        eprint!("{}", timings.table());
+    }
+}
+
+/// Loads the scene from the FBX or glTF file.
+///
+/// The format is detected from the file extension, and files with unknown
+/// extensions are loaded as FBX.
+/// The options are used only for FBX files.
+pub fn load_scene(path: &Path, options: &LoadOptions) -> anyhow::Result<Scene> {
+    load_scene_with_progress(path, options, &mut |_| {})
+}
+
+/// Loads the scene from the FBX or glTF file, reporting the progress.
+///
+/// `progress` is called with the ratio (from 0 to 1) of the parsed part of
+/// the file.
+/// The progress is reported only for FBX files.
+pub fn load_scene_with_progress(
+    path: &Path,
+    options: &LoadOptions,
+    progress: &mut dyn FnMut(f64),
+) -> anyhow::Result<Scene> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("gltf") | Some("glb") => gltf::load(path),
+        _ => fbx::load_with_progress(path, options, progress),
+    }
+}
+
+/// Initializes the tracing subscriber.
+///
+/// Logs are written to stderr, filtered by `RUST_LOG` environment variable.
+/// Logs at `INFO` level or above are also captured into the given console.
+/// If `timings` is given, the spans of the loading stages are measured into it.
+/// If `trace_output` is given, all spans and events are also written to the
+/// file in Chrome trace event format, which is flushed when the returned guard
+/// is dropped.
+fn init_tracing(
+    console: &LogConsole,
+    timings: Option<&LoadTimings>,
+    trace_output: Option<&Path>,
+) -> Option<FlushGuard> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("debug"));
+    let fmt_layer = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_filter(filter);
+    let (chrome_layer, guard) = match trace_output {
+        Some(path) => {
+            let (layer, guard) = ChromeLayerBuilder::new()
+                .file(path)
+                .include_args(true)
+                .build();
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(fmt_layer)
+        .with(console.layer().with_filter(LevelFilter::INFO))
+        .with(timings.map(LoadTimings::layer))
+        .with(chrome_layer)
+        .init();
+
+    guard
 }
@@ -0,0 +1,178 @@
+//! Rendering backends.
+//!
+//! The viewer (window events, the camera, and scene loading) is written
+//! against [`Renderer`], so that backends share it.
+
+use cgmath::{Matrix4, Rad};
+use fbx_viewer::{
+    camera::{Camera, Perspective},
+    data::{self, raycast::Ray, MeshIndex},
+    Background, StereoMode,
+};
+use image::RgbaImage;
+use winit::window::Window;
+
+use crate::console::ConsolePanel;
+
+use self::{
+    gizmo::Gizmo,
+    shading::{ShadingMode, ShadingModel},
+};
+
+pub mod font;
+pub mod gizmo;
+pub mod shading;
+pub mod stats;
+
+/// Projection of the perspective views.
+pub const PERSPECTIVE: Perspective = Perspective {
+    fovy: Rad(std::f64::consts::FRAC_PI_3),
+    near: 0.1,
+    far: 1000.0,
+};
+
+/// Function preparing the resources of a loaded scene on the loader thread.
+///
+/// The second argument returns an error if loading is canceled, and should be
+/// checked between long steps.
+pub type Prepare<T> =
+    Box<dyn FnOnce(&data::Scene, &dyn Fn() -> anyhow::Result<()>) -> anyhow::Result<T> + Send>;
+
+/// Settings of the rendered frames.
+#[derive(Debug, Clone)]
+pub struct RenderSettings {
+    /// Whether to show the top, perspective, front, and side views.
+    pub quad_view: bool,
+    /// Stereo mode.
+    pub stereo: Option<StereoMode>,
+    /// Shading mode.
+    pub shading_mode: ShadingMode,
+    /// Lighting model of the lit shading mode.
+    pub shading_model: ShadingModel,
+    /// Whether to highlight back faces.
+    pub highlight_back_faces: bool,
+    /// Whether to draw wireframes.
+    pub wireframe: bool,
+    /// Meshes to draw wireframes over the shaded surfaces of.
+    pub wireframe_overlay: WireframeOverlay,
+    /// Whether to exaggerate z-fighting.
+    pub exaggerate_z_fighting: bool,
+    /// Whether to visualize overdraw.
+    pub overdraw: bool,
+    /// Whether to highlight the topology problems analyzed by
+    /// [`Renderer::analyze_meshes`].
+    pub mesh_analysis: bool,
+    /// Whether to show the tangent frames generated by
+    /// [`Renderer::show_tangent_frames`].
+    pub tangent_frames: bool,
+    /// Background.
+    pub background: Background,
+    /// Mesh to outline.
+    pub selected_mesh: Option<MeshIndex>,
+    /// Transform gizmo to draw over all surfaces.
+    pub gizmo: Option<Gizmo>,
+    /// Log console panel to draw over the frame, if shown.
+    ///
+    /// The panel is not included in captured frames.
+    pub console: Option<ConsolePanel>,
+}
+
+/// Meshes with wireframes drawn over their shaded surfaces.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum WireframeOverlay {
+    /// No meshes.
+    #[default]
+    Off,
+    /// The selected mesh.
+    Selected,
+    /// All meshes.
+    All,
+}
+
+impl WireframeOverlay {
+    /// Returns the next mode in the cycle.
+    pub fn next(self) -> Self {
+        match self {
+            WireframeOverlay::Off => WireframeOverlay::Selected,
+            WireframeOverlay::Selected => WireframeOverlay::All,
+            WireframeOverlay::All => WireframeOverlay::Off,
+        }
+    }
+}
+
+/// Rendering backend drawing to a window.
+pub trait Renderer {
+    /// Resources of a scene prepared on the loader thread.
+    type Prepared: Send + 'static;
+
+    /// Returns the window.
+    fn window(&self) -> &Window;
+
+    /// Returns the size of the rendered frames in physical pixels.
+    fn dimensions(&self) -> [u32; 2];
+
+    /// Returns whether wireframes are supported.
+    fn supports_wireframe(&self) -> bool;
+
+    /// Returns the function to prepare a scene on the loader thread.
+    fn prepare(&self, normals_recomputed: bool) -> Prepare<Self::Prepared>;
+
+    /// Shows the prepared scene instead of the current one.
+    fn load_scene(&mut self, scene: &data::Scene, prepared: Self::Prepared) -> anyhow::Result<()>;
+
+    /// Switches the shown scene between the imported and recomputed normals.
+    fn reload_normals(&mut self, scene: &data::Scene, recomputed: bool) -> anyhow::Result<()>;
+
+    /// Replaces the textures of the shown scene with the remapped image files.
+    fn remap_textures(&mut self) -> anyhow::Result<()>;
+
+    /// Analyzes the topology of the meshes of the shown scene to highlight,
+    /// if not yet.
+    fn analyze_meshes(&mut self, scene: &data::Scene) -> anyhow::Result<()>;
+
+    /// Generates the tangent frames of the shown scene to show, if not yet.
+    fn show_tangent_frames(
+        &mut self,
+        scene: &data::Scene,
+        normals_recomputed: bool,
+    ) -> anyhow::Result<()>;
+
+    /// Changes the render order of the mesh of the shown scene.
+    fn set_render_order(&mut self, mesh: MeshIndex, render_order: i32) -> anyhow::Result<()>;
+
+    /// Changes the transform applied over the geometry of the mesh of the
+    /// shown scene, or removes it if `None` is given.
+    fn set_mesh_transform(
+        &mut self,
+        mesh: MeshIndex,
+        transform: Option<Matrix4<f32>>,
+    ) -> anyhow::Result<()>;
+
+    /// Hides or shows the mesh of the shown scene.
+    fn set_mesh_hidden(&mut self, mesh: MeshIndex, hidden: bool) -> anyhow::Result<()>;
+
+    /// Notifies that the window is resized or its scale factor is changed.
+    fn resize(&mut self, scale_factor: f64);
+
+    /// Renders and presents a frame.
+    fn render(&mut self, camera: &Camera, settings: &RenderSettings) -> anyhow::Result<()>;
+
+    /// Renders and presents a frame, and returns its image.
+    fn capture_frame(
+        &mut self,
+        camera: &Camera,
+        settings: &RenderSettings,
+    ) -> anyhow::Result<RgbaImage>;
+
+    /// Returns the ray through the point in the window, if it is in a
+    /// perspective view.
+    fn pick_ray(
+        &self,
+        camera: &Camera,
+        settings: &RenderSettings,
+        position: [f32; 2],
+    ) -> Option<Ray>;
+
+    /// Returns the statistics of the last frame for display.
+    fn status(&self) -> String;
+}
@@ -0,0 +1,310 @@
+//! Transform gizmo.
+//!
+//! The gizmo has a handle for each world axis around the center of a mesh.
+//! Dragging a handle translates the mesh along the axis, rotates it around
+//! the axis, or scales it along the axis, depending on the mode.
+
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, Rad, Vector3, Zero};
+use fbx_viewer::data::raycast::Ray;
+
+/// Number of the line segments of a rotation handle.
+const CIRCLE_SEGMENTS: usize = 48;
+
+/// Size of the arrow heads and the boxes at the ends of the handles,
+/// relative to the size of the gizmo.
+const HANDLE_END_SIZE: f32 = 0.08;
+
+/// Maximum distance from a handle to pick it, relative to the size of the
+/// gizmo.
+const PICK_TOLERANCE: f32 = 0.06;
+
+/// Minimum scale factor of a drag, to keep the transform invertible.
+const MIN_SCALE: f32 = 0.01;
+
+/// Colors of the handles of the X, Y, and Z axes.
+pub const AXIS_COLORS: [[f32; 4]; 3] = [
+    [1.0, 0.2, 0.2, 1.0],
+    [0.2, 1.0, 0.2, 1.0],
+    [0.2, 0.4, 1.0, 1.0],
+];
+
+/// Color of the handle being dragged.
+pub const ACTIVE_COLOR: [f32; 4] = [1.0, 1.0, 0.2, 1.0];
+
+/// Transform edited by the gizmo.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoMode {
+    /// No gizmo.
+    #[default]
+    Off,
+    /// Translation along the axes.
+    Translate,
+    /// Rotation around the axes.
+    Rotate,
+    /// Scaling along the axes.
+    Scale,
+}
+
+impl GizmoMode {
+    /// Returns the next mode in the cycle.
+    pub fn next(self) -> Self {
+        match self {
+            GizmoMode::Off => GizmoMode::Translate,
+            GizmoMode::Translate => GizmoMode::Rotate,
+            GizmoMode::Rotate => GizmoMode::Scale,
+            GizmoMode::Scale => GizmoMode::Off,
+        }
+    }
+}
+
+/// Transform gizmo drawn over a mesh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gizmo {
+    /// Mode.
+    pub mode: GizmoMode,
+    /// Center in the world space.
+    pub center: Point3<f32>,
+    /// Length of the handles.
+    pub size: f32,
+    /// Axis of the handle being dragged.
+    pub active_axis: Option<usize>,
+}
+
+impl Gizmo {
+    /// Returns the color of the handle of the axis.
+    pub fn color(&self, axis: usize) -> [f32; 4] {
+        if self.active_axis == Some(axis) {
+            ACTIVE_COLOR
+        } else {
+            AXIS_COLORS[axis]
+        }
+    }
+
+    /// Returns the line segments of the handle of the axis.
+    pub fn handle_lines(&self, axis: usize) -> Vec<[Point3<f32>; 2]> {
+        let (dir, u, v) = axis_frame(axis);
+        let center = self.center;
+        let tip = center + dir * self.size;
+        let end = self.size * HANDLE_END_SIZE;
+        match self.mode {
+            GizmoMode::Off => Vec::new(),
+            GizmoMode::Translate => {
+                let base = tip - dir * (end * 2.0);
+                vec![
+                    [center, tip],
+                    [tip, base + u * end],
+                    [tip, base - u * end],
+                    [tip, base + v * end],
+                    [tip, base - v * end],
+                ]
+            }
+            GizmoMode::Rotate => {
+                let point = |i: usize| {
+                    let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+                    center + (u * angle.cos() + v * angle.sin()) * self.size
+                };
+                (0..CIRCLE_SEGMENTS)
+                    .map(|i| [point(i), point(i + 1)])
+                    .collect()
+            }
+            GizmoMode::Scale => {
+                let corners = [u + v, u - v, -u - v, -u + v].map(|offset| tip + offset * end);
+                let mut lines = vec![[center, tip]];
+                lines.extend((0..4).map(|i| [corners[i], corners[(i + 1) % 4]]));
+                lines
+            }
+        }
+    }
+
+    /// Returns the axis of the handle under the ray, if any.
+    pub fn pick(&self, ray: &Ray) -> Option<usize> {
+        let tolerance = self.size * PICK_TOLERANCE;
+        (0..3)
+            .filter_map(|axis| {
+                let distance = self
+                    .handle_lines(axis)
+                    .iter()
+                    .map(|segment| ray_segment_distance(ray, segment))
+                    .fold(f32::INFINITY, f32::min);
+                Some((axis, distance)).filter(|&(_, distance)| distance <= tolerance)
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(axis, _)| axis)
+    }
+
+    /// Returns the transform in the world space applied by dragging the
+    /// handle of the axis from the `from` ray to the `to` ray.
+    ///
+    /// Returns `None` if the rays are parallel to the handle.
+    pub fn drag(&self, axis: usize, from: &Ray, to: &Ray) -> Option<Matrix4<f32>> {
+        let (dir, _, _) = axis_frame(axis);
+        let center = self.center;
+        let about_center = |m: Matrix4<f32>| {
+            Matrix4::from_translation(center.to_vec())
+                * m
+                * Matrix4::from_translation(-center.to_vec())
+        };
+        match self.mode {
+            GizmoMode::Off => None,
+            GizmoMode::Translate => {
+                let from = closest_on_axis(center, dir, from)?;
+                let to = closest_on_axis(center, dir, to)?;
+                Some(Matrix4::from_translation(dir * (to - from)))
+            }
+            GizmoMode::Rotate => {
+                let from = intersect_plane(center, dir, from)? - center;
+                let to = intersect_plane(center, dir, to)? - center;
+                let angle = dir.dot(from.cross(to)).atan2(from.dot(to));
+                Some(about_center(Matrix4::from_axis_angle(dir, Rad(angle))))
+            }
+            GizmoMode::Scale => {
+                let from = closest_on_axis(center, dir, from)?;
+                let to = closest_on_axis(center, dir, to)?;
+                let has_length = from.abs() > self.size * PICK_TOLERANCE;
+                if !has_length {
+                    return None;
+                }
+                let mut factors = Vector3::new(1.0, 1.0, 1.0);
+                factors[axis] = (to / from).max(MIN_SCALE);
+                Some(about_center(Matrix4::from_nonuniform_scale(
+                    factors.x, factors.y, factors.z,
+                )))
+            }
+        }
+    }
+}
+
+/// Returns the unit vector of the axis, and two unit vectors perpendicular
+/// to it and each other.
+fn axis_frame(axis: usize) -> (Vector3<f32>, Vector3<f32>, Vector3<f32>) {
+    let unit = |i: usize| {
+        let mut v = Vector3::zero();
+        v[i % 3] = 1.0;
+        v
+    };
+    (unit(axis), unit(axis + 1), unit(axis + 2))
+}
+
+/// Returns the parameter of the point on the axis line closest to the ray.
+///
+/// Returns `None` if the ray is parallel to the axis.
+fn closest_on_axis(origin: Point3<f32>, dir: Vector3<f32>, ray: &Ray) -> Option<f32> {
+    let w = origin - ray.origin();
+    let b = dir.dot(ray.direction());
+    let denom = 1.0 - b * b;
+    if denom < 1e-6 {
+        return None;
+    }
+    Some((b * ray.direction().dot(w) - dir.dot(w)) / denom)
+}
+
+/// Returns the intersection of the ray and the plane through the point with
+/// the normal.
+///
+/// Returns `None` if the ray is parallel to the plane.
+fn intersect_plane(point: Point3<f32>, normal: Vector3<f32>, ray: &Ray) -> Option<Point3<f32>> {
+    let denom = normal.dot(ray.direction());
+    if denom.abs() < 1e-6 {
+        return None;
+    }
+    Some(ray.at(normal.dot(point - ray.origin()) / denom))
+}
+
+/// Returns the distance between the ray and the line segment.
+fn ray_segment_distance(ray: &Ray, segment: &[Point3<f32>; 2]) -> f32 {
+    let u = segment[1] - segment[0];
+    let w = segment[0] - ray.origin();
+    let a = u.magnitude2();
+    if a == 0.0 {
+        let t = ray.direction().dot(-w).max(0.0);
+        return (segment[0] - ray.at(t)).magnitude();
+    }
+    let b = u.dot(ray.direction());
+    let d = u.dot(w);
+    let e = ray.direction().dot(w);
+    let denom = a - b * b;
+    let s = if denom > 1e-6 {
+        ((b * e - d) / denom).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let t = (b * s + e).max(0.0);
+    let s = ((b * t - d) / a).clamp(0.0, 1.0);
+    (segment[0] + u * s - ray.at(t)).magnitude()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::Transform;
+
+    /// Returns the ray looking down the Z axis through the point on the XY
+    /// plane.
+    fn ray_to(x: f32, y: f32) -> Ray {
+        Ray::new(Point3::new(x, y, 10.0), Vector3::new(0.0, 0.0, -1.0))
+    }
+
+    /// Returns the gizmo of the mode at the origin.
+    fn gizmo(mode: GizmoMode) -> Gizmo {
+        Gizmo {
+            mode,
+            center: Point3::new(0.0, 0.0, 0.0),
+            size: 1.0,
+            active_axis: None,
+        }
+    }
+
+    /// Asserts that the transform moves the point to the expected one.
+    fn assert_moves(m: Matrix4<f32>, p: Point3<f32>, expected: Point3<f32>) {
+        let actual = m.transform_point(p);
+        assert!(
+            (actual - expected).magnitude() < 1e-5,
+            "{:?} is moved to {:?}, expected {:?}",
+            p,
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn pick_handles() {
+        let translate = gizmo(GizmoMode::Translate);
+        assert_eq!(translate.pick(&ray_to(0.5, 0.0)), Some(0));
+        assert_eq!(translate.pick(&ray_to(0.0, 0.7)), Some(1));
+        assert_eq!(translate.pick(&ray_to(0.5, 0.5)), None);
+        // The circle around the Z axis faces the ray.
+        let rotate = gizmo(GizmoMode::Rotate);
+        assert_eq!(rotate.pick(&ray_to(0.6, 0.8)), Some(2));
+        assert_eq!(rotate.pick(&ray_to(0.3, 0.3)), None);
+    }
+
+    #[test]
+    fn drag_handles() {
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        let m = gizmo(GizmoMode::Translate)
+            .drag(0, &ray_to(0.5, 0.0), &ray_to(1.5, 0.3))
+            .expect("Should be draggable");
+        assert_moves(m, origin, Point3::new(1.0, 0.0, 0.0));
+        // The ray is parallel to the Z axis.
+        assert_eq!(
+            gizmo(GizmoMode::Translate).drag(2, &ray_to(0.0, 0.0), &ray_to(1.0, 0.0)),
+            None
+        );
+
+        let m = gizmo(GizmoMode::Rotate)
+            .drag(2, &ray_to(1.0, 0.0), &ray_to(0.0, 2.0))
+            .expect("Should be draggable");
+        assert_moves(m, Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0));
+        assert_moves(m, origin, origin);
+
+        let m = Gizmo {
+            center: Point3::new(1.0, 0.0, 0.0),
+            ..gizmo(GizmoMode::Scale)
+        }
+        .drag(0, &ray_to(2.0, 0.0), &ray_to(3.0, 0.0))
+        .expect("Should be draggable");
+        assert_moves(m, Point3::new(2.0, 1.0, 0.0), Point3::new(3.0, 1.0, 0.0));
+        assert_moves(m, Point3::new(1.0, 1.0, 1.0), Point3::new(1.0, 1.0, 1.0));
+    }
+}
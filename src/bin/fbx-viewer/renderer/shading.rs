@@ -0,0 +1,102 @@
+//! Shading modes.
+
+use fbx_viewer::MaterialOverride;
+
+/// Number of depth steps used to exaggerate z-fighting.
+///
+/// Surfaces closer than a step in the depth buffer fight with each other.
+pub const Z_FIGHTING_DEPTH_STEPS: f32 = 4096.0;
+
+/// Shading mode of the default fragment shader.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ShadingMode {
+    /// Albedo lit by a headlight.
+    #[default]
+    Lit,
+    /// Albedo without lighting.
+    Unlit,
+    /// Normals as colors.
+    Normal,
+    /// UV as colors.
+    Uv,
+    /// Linear depth as grayscale.
+    Depth,
+}
+
+impl ShadingMode {
+    /// Returns the next mode in the cycle.
+    pub fn next(self) -> Self {
+        match self {
+            ShadingMode::Lit => ShadingMode::Unlit,
+            ShadingMode::Unlit => ShadingMode::Normal,
+            ShadingMode::Normal => ShadingMode::Uv,
+            ShadingMode::Uv => ShadingMode::Depth,
+            ShadingMode::Depth => ShadingMode::Lit,
+        }
+    }
+
+    /// Returns the value passed to the shader.
+    ///
+    /// This should be consistent with the constants in `default.frag`.
+    pub fn to_shader_value(self) -> u32 {
+        match self {
+            ShadingMode::Lit => 0,
+            ShadingMode::Unlit => 1,
+            ShadingMode::Normal => 2,
+            ShadingMode::Uv => 3,
+            ShadingMode::Depth => 4,
+        }
+    }
+}
+
+/// Lighting model of the lit shading mode.
+///
+/// Each model is a variant of the default fragment shader specialized by a
+/// constant, so switching models only switches pipelines.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShadingModel {
+    /// Diffuse only.
+    #[default]
+    Lambert,
+    /// Diffuse and Blinn-Phong specular of Phong materials.
+    Phong,
+    /// Cook-Torrance with the parameters approximated from the materials.
+    Pbr,
+}
+
+impl ShadingModel {
+    /// All models, in the order of the cycle.
+    pub const ALL: [Self; 3] = [Self::Lambert, Self::Phong, Self::Pbr];
+
+    /// Returns the next model in the cycle.
+    pub fn next(self) -> Self {
+        match self {
+            ShadingModel::Lambert => ShadingModel::Phong,
+            ShadingModel::Phong => ShadingModel::Pbr,
+            ShadingModel::Pbr => ShadingModel::Lambert,
+        }
+    }
+
+    /// Returns the value of the specialization constant.
+    ///
+    /// This should be consistent with the constants in `default.frag`.
+    pub fn to_shader_value(self) -> u32 {
+        match self {
+            ShadingModel::Lambert => 0,
+            ShadingModel::Phong => 1,
+            ShadingModel::Pbr => 2,
+        }
+    }
+}
+
+/// Returns the value of the material override passed to the shader.
+///
+/// This should be consistent with the constants in `default.frag`.
+pub fn material_override_to_shader_value(material_override: Option<MaterialOverride>) -> u32 {
+    match material_override {
+        None => 0,
+        Some(MaterialOverride::FlatGray) => 1,
+        Some(MaterialOverride::Normals) => 2,
+        Some(MaterialOverride::Checker) => 3,
+    }
+}
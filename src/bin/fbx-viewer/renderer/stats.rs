@@ -0,0 +1,53 @@
+//! Render statistics.
+
+use std::fmt;
+
+/// Per-frame render statistics.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Number of draw calls.
+    pub draw_calls: usize,
+    /// Number of triangles.
+    pub triangles: usize,
+    /// Number of bound descriptor sets.
+    pub descriptor_sets: usize,
+}
+
+impl RenderStats {
+    /// Records a draw call.
+    pub fn record_draw(&mut self, num_indices: usize, num_descriptor_sets: usize) {
+        self.draw_calls += 1;
+        self.triangles += num_indices / 3;
+        self.descriptor_sets += num_descriptor_sets;
+    }
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} draw calls, {} triangles, {} descriptor sets",
+            self.draw_calls, self.triangles, self.descriptor_sets
+        )
+    }
+}
+
+/// Formats the size in bytes in a human readable form.
+pub fn format_bytes(bytes: u64) -> String {
+    /// Units.
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for &next_unit in &UNITS[1..] {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next_unit;
+    }
+    format!("{:.1} {}", size, unit)
+}
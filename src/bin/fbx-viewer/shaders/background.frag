@@ -0,0 +1,14 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(location = 0) out vec4 f_color;
+
+layout(push_constant) uniform PushConstants {
+	vec4 top;
+	vec4 bottom;
+} colors;
+
+void main() {
+	f_color = mix(colors.top, colors.bottom, v_uv.y);
+}
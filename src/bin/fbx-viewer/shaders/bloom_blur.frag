@@ -0,0 +1,26 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D source;
+
+layout(push_constant) uniform PushConstants {
+	// Size of a texel along the blur direction, in UV.
+	vec2 step;
+} constants;
+
+// 9-tap Gaussian blur in 5 bilinear samples: offsets in texels and weights.
+const float OFFSETS[3] = float[](0.0, 1.3846153846, 3.2307692308);
+const float WEIGHTS[3] = float[](0.2270270270, 0.3162162162, 0.0702702703);
+
+void main() {
+	vec3 color = texture(source, v_uv).rgb * WEIGHTS[0];
+	for (int i = 1; i < 3; ++i) {
+		vec2 offset = constants.step * OFFSETS[i];
+		color += texture(source, v_uv + offset).rgb * WEIGHTS[i];
+		color += texture(source, v_uv - offset).rgb * WEIGHTS[i];
+	}
+	f_color = vec4(color, 1.0);
+}
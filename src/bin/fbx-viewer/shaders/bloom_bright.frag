@@ -0,0 +1,22 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D source;
+
+layout(push_constant) uniform PushConstants {
+	// Brightness above which the colors contribute to the bloom.
+	float threshold;
+} constants;
+
+void main() {
+	// The target is half the size of the source, so the bilinear sample
+	// averages 2x2 texels.
+	vec3 color = texture(source, v_uv).rgb;
+	float brightness = max(color.r, max(color.g, color.b));
+	// Keep the part above the threshold, preserving the hue.
+	float excess = max(brightness - constants.threshold, 0.0);
+	f_color = vec4(color * (excess / max(brightness, 1e-4)), 1.0);
+}
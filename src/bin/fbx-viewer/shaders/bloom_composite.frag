@@ -0,0 +1,17 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D scene;
+layout(set = 0, binding = 1) uniform sampler2D bloom;
+
+layout(push_constant) uniform PushConstants {
+	float intensity;
+} constants;
+
+void main() {
+	vec4 color = texture(scene, v_uv);
+	f_color = vec4(color.rgb + texture(bloom, v_uv).rgb * constants.intensity, color.a);
+}
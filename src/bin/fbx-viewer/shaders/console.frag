@@ -0,0 +1,63 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(location = 0) out vec4 f_color;
+
+// Keep in sync with `console.rs`.
+const uint MAX_COLUMNS = 160;
+const uint MAX_ROWS = 40;
+const uint NUM_GLYPHS = 96;
+const uint GLYPH_WIDTH = 5;
+const uint GLYPH_HEIGHT = 7;
+const uint CELL_WIDTH = GLYPH_WIDTH + 1;
+const uint CELL_HEIGHT = GLYPH_HEIGHT + 2;
+
+const vec4 BACKGROUND = vec4(0.0, 0.0, 0.0, 0.75);
+
+layout(set = 0, binding = 0) uniform Text {
+	// Glyph indices of the cells in row-major order, packed four 8-bit
+	// indices per component.
+	uvec4 cells[MAX_COLUMNS * MAX_ROWS / 16];
+	// Text colors of the rows.
+	vec4 colors[MAX_ROWS];
+	// Rows of the glyphs, the first four rows in the first word and the
+	// others in the second, each row in 5 bits with the leftmost pixel in
+	// the highest bit.
+	uvec4 font[NUM_GLYPHS / 2];
+} text;
+
+layout(push_constant) uniform PushConstants {
+	// Top left corner of the panel in pixels.
+	ivec2 origin;
+	uint columns;
+	uint rows;
+	// Size of a glyph pixel in pixels.
+	uint scale;
+} constants;
+
+void main() {
+	ivec2 position = ivec2(gl_FragCoord.xy) - constants.origin;
+	if (position.x < 0 || position.y < 0) {
+		discard;
+	}
+	uvec2 cell_size = uvec2(CELL_WIDTH, CELL_HEIGHT) * constants.scale;
+	uvec2 cell = uvec2(position) / cell_size;
+	if (cell.x >= constants.columns || cell.y >= constants.rows) {
+		discard;
+	}
+
+	f_color = BACKGROUND;
+	// Pixel of the glyph, with a blank row above it.
+	uvec2 pixel = (uvec2(position) % cell_size) / constants.scale - uvec2(0, 1);
+	if (pixel.x >= GLYPH_WIDTH || pixel.y >= GLYPH_HEIGHT) {
+		return;
+	}
+	uint i = cell.y * MAX_COLUMNS + cell.x;
+	uint glyph = (text.cells[i / 16][(i / 4) % 4] >> ((i % 4) * 8)) & 0xFF;
+	uint word = text.font[glyph / 2][(glyph % 2) * 2 + pixel.y / 4];
+	uint row = (word >> ((pixel.y % 4) * GLYPH_WIDTH)) & 0x1F;
+	if (((row >> (GLYPH_WIDTH - 1 - pixel.x)) & 1) != 0) {
+		f_color = text.colors[cell.y];
+	}
+}
@@ -2,9 +2,25 @@
 
 layout(location = 0) in vec3 v_normal;
 layout(location = 1) in vec2 v_uv;
+layout(location = 2) in vec3 v_view_position;
+layout(location = 3) in vec3 v_view_normal;
+layout(location = 4) in vec4 v_color;
+// Second UV set, for the lightmap and ambient occlusion textures.
+layout(location = 5) in vec2 v_uv1;
 
 layout(location = 0) out vec4 f_color;
 
+layout(set = 0, binding = 0) uniform Data {
+	mat4 world;
+	mat4 view;
+	mat4 proj;
+	uint shading_mode;
+	float depth_near;
+	float depth_far;
+	uint highlight_back_faces;
+	float depth_steps;
+} uniforms;
+
 layout(set = 1, binding = 0) uniform sampler2D diffuse;
 
 layout(set = 2, binding = 0) uniform Material {
@@ -12,11 +28,182 @@ layout(set = 2, binding = 0) uniform Material {
 	vec3 diffuse;
 	vec3 emissive;
 	bool enabled;
+	uint override_mode;
+	// Blinn-Phong specular color and exponent.
+	vec3 specular;
+	float shininess;
+	// Fresnel reflectance at normal incidence and perceptual roughness.
+	vec3 f0;
+	float roughness;
+	// Fragments with alpha below this are discarded. Zero for the materials
+	// without alpha test.
+	float alpha_cutoff;
 } material;
 
+#ifdef DEPTH_PEELING
+// Depth of the opaque surfaces, and of the previously peeled layer.
+layout(set = 3, binding = 0) uniform sampler2D opaque_depth;
+layout(set = 3, binding = 1) uniform sampler2D previous_depth;
+
+layout(push_constant) uniform Peel {
+	// Whether this is the first layer, which has no previous layer.
+	uint first_layer;
+	// Whether the colors of the texture are premultiplied by alpha.
+	uint premultiplied_alpha;
+} peel;
+#endif
+
+// Material overrides. Keep in sync with
+// `material_override_to_shader_value` in `shading.rs`.
+const uint OVERRIDE_NONE = 0;
+const uint OVERRIDE_FLAT_GRAY = 1;
+const uint OVERRIDE_NORMALS = 2;
+const uint OVERRIDE_CHECKER = 3;
+
+// Number of checker squares per UV unit.
+const float CHECKER_SCALE = 8.0;
+
+// Lighting models of the lit mode. Keep in sync with `ShadingModel` in
+// `shading.rs`.
+const uint MODEL_LAMBERT = 0;
+const uint MODEL_PHONG = 1;
+const uint MODEL_PBR = 2;
+
+// Lighting model, fixed per pipeline so that unused models are compiled out.
+layout(constant_id = 0) const uint SHADING_MODEL = MODEL_LAMBERT;
+
+// Shading modes. Keep in sync with `ShadingMode` in `shading.rs`.
+const uint SHADING_LIT = 0;
+const uint SHADING_UNLIT = 1;
+const uint SHADING_NORMAL = 2;
+const uint SHADING_UV = 3;
+const uint SHADING_DEPTH = 4;
+
+// Color of back faces highlighted.
+const vec4 BACK_FACE_COLOR = vec4(1.0, 0.0, 0.0, 1.0);
+
+// Ratio of the ambient light in the lit mode.
+const float AMBIENT = 0.3;
+
+const float PI = 3.14159265;
+
+// Returns the color of the surface lit by a headlight.
+//
+// The light, the eye, and the half vector are in the same direction, so
+// `cos_theta` is the cosine of all of the angles between them and the normal.
+vec3 headlight(vec3 albedo, float cos_theta) {
+	vec3 direct;
+	switch (SHADING_MODEL) {
+	case MODEL_PHONG:
+		direct = albedo * cos_theta +
+			material.specular * pow(cos_theta, max(material.shininess, 1.0));
+		break;
+	case MODEL_PBR: {
+		// Cook-Torrance with the GGX distribution and the Schlick-GGX
+		// geometry term.
+		// The Fresnel term is F0, since the eye is in the light direction.
+		float alpha = max(material.roughness * material.roughness, 1e-3);
+		float alpha2 = alpha * alpha;
+		float d = cos_theta * cos_theta * (alpha2 - 1.0) + 1.0;
+		float distribution = alpha2 / (PI * d * d);
+		float k = alpha / 2.0;
+		float g1 = cos_theta / (cos_theta * (1.0 - k) + k);
+		vec3 specular = material.f0 * distribution * g1 * g1 /
+			max(4.0 * cos_theta * cos_theta, 1e-4);
+		// The light intensity is PI, so that the diffuse term matches
+		// the Lambert model.
+		direct = ((1.0 - material.f0) * albedo / PI + specular) * PI * cos_theta;
+		break;
+	}
+	default:
+		direct = albedo * cos_theta;
+		break;
+	}
+	return albedo * AMBIENT + (1.0 - AMBIENT) * direct;
+}
+
 void main() {
-    vec4 diffuse = material.enabled ?
-		vec4(material.diffuse, 1.0) :
-		texture(diffuse, v_uv);
-	f_color = diffuse;
+	// Sample before `diffuse` below shadows the sampler.
+	vec4 texel = texture(diffuse, v_uv);
+	// Cutouts keep their shapes with the material overrides.
+	float alpha = (material.enabled ? 1.0 : texel.a) * v_color.a;
+	if (alpha < material.alpha_cutoff) {
+		discard;
+	}
+	vec4 diffuse;
+	switch (material.override_mode) {
+	case OVERRIDE_FLAT_GRAY:
+		diffuse = vec4(0.8, 0.8, 0.8, 1.0);
+		break;
+	case OVERRIDE_NORMALS:
+		diffuse = vec4(normalize(v_normal) * 0.5 + 0.5, 1.0);
+		break;
+	case OVERRIDE_CHECKER: {
+		vec2 cell = floor(v_uv * CHECKER_SCALE);
+		diffuse = vec4(vec3(mod(cell.x + cell.y, 2.0) * 0.7 + 0.2), 1.0);
+		break;
+	}
+	default:
+		diffuse = material.enabled ? vec4(material.diffuse, 1.0) : texel;
+		// Vertex colors are white if the mesh has none.
+		diffuse *= v_color;
+		break;
+	}
+
+	switch (uniforms.shading_mode) {
+	case SHADING_LIT: {
+		// Headlight: the light comes from the eye.
+		vec3 normal = normalize(v_view_normal);
+		vec3 to_eye = normalize(-v_view_position);
+		float cos_theta = max(dot(normal, to_eye), 0.0);
+		vec3 color = headlight(diffuse.rgb, cos_theta);
+		// The material overrides ignore the emissive color.
+		if (material.override_mode == OVERRIDE_NONE) {
+			color += material.emissive;
+		}
+		f_color = vec4(color, diffuse.a);
+		break;
+	}
+	case SHADING_NORMAL:
+		f_color = vec4(normalize(v_normal) * 0.5 + 0.5, 1.0);
+		break;
+	case SHADING_UV:
+		f_color = vec4(fract(v_uv), 0.0, 1.0);
+		break;
+	case SHADING_DEPTH: {
+		// Linear depth, white at near and black at far.
+		float depth = (-v_view_position.z - uniforms.depth_near) /
+			max(uniforms.depth_far - uniforms.depth_near, 1e-6);
+		f_color = vec4(vec3(1.0 - clamp(depth, 0.0, 1.0)), 1.0);
+		break;
+	}
+	default:
+		f_color = diffuse;
+		break;
+	}
+
+	if (uniforms.highlight_back_faces != 0 && !gl_FrontFacing) {
+		f_color = BACK_FACE_COLOR;
+	}
+
+	// Quantize the depth to exaggerate z-fighting of near-coplanar surfaces.
+	float depth = uniforms.depth_steps > 0.0 ?
+		floor(gl_FragCoord.z * uniforms.depth_steps) / uniforms.depth_steps :
+		gl_FragCoord.z;
+
+#ifdef DEPTH_PEELING
+	// Keep only the fragments in front of the opaque surfaces and behind
+	// the previous layer.
+	ivec2 texel_coord = ivec2(gl_FragCoord.xy);
+	if (depth >= texelFetch(opaque_depth, texel_coord, 0).r ||
+		(peel.first_layer == 0 && depth <= texelFetch(previous_depth, texel_coord, 0).r)) {
+		discard;
+	}
+	// Layers are composited with premultiplied alpha.
+	if (peel.premultiplied_alpha == 0) {
+		f_color.rgb *= f_color.a;
+	}
+#endif
+
+	gl_FragDepth = depth;
 }
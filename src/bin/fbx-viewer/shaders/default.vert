@@ -3,14 +3,25 @@
 layout(location = 0) in vec3 position;
 layout(location = 1) in vec3 normal;
 layout(location = 2) in vec2 uv;
+layout(location = 3) in vec4 color;
+layout(location = 4) in vec2 uv1;
 
 layout(location = 0) out vec3 v_normal;
 layout(location = 1) out vec2 v_uv;
+layout(location = 2) out vec3 v_view_position;
+layout(location = 3) out vec3 v_view_normal;
+layout(location = 4) out vec4 v_color;
+layout(location = 5) out vec2 v_uv1;
 
 layout(set = 0, binding = 0) uniform Data {
 	mat4 world;
 	mat4 view;
 	mat4 proj;
+	uint shading_mode;
+	float depth_near;
+	float depth_far;
+	uint highlight_back_faces;
+	float depth_steps;
 } uniforms;
 
 void main() {
@@ -18,5 +29,10 @@ void main() {
 	v_normal = normal;
 	// Use vulkan coordinate system!
 	v_uv = uv * vec2(1.0, -1.0) + vec2(0.0, 1.0);
-	gl_Position = uniforms.proj * worldview * vec4(position, 1.0);
+	v_uv1 = uv1 * vec2(1.0, -1.0) + vec2(0.0, 1.0);
+	vec4 view_position = worldview * vec4(position, 1.0);
+	v_view_position = view_position.xyz;
+	v_view_normal = mat3(worldview) * normal;
+	v_color = color;
+	gl_Position = uniforms.proj * view_position;
 }
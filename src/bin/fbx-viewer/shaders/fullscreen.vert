@@ -0,0 +1,10 @@
+#version 450
+
+layout(location = 0) out vec2 v_uv;
+
+void main() {
+	// Fullscreen triangle: (-1, -1), (3, -1), (-1, 3).
+	// UV is (0, 0) at the top left, since Y axis is downward in vulkan.
+	v_uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+	gl_Position = vec4(v_uv * 2.0 - 1.0, 1.0, 1.0);
+}
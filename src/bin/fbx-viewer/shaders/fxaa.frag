@@ -0,0 +1,53 @@
+#version 450
+
+// FXAA based on the simplified version of FXAA by Timothy Lottes.
+
+layout(location = 0) in vec2 v_uv;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D source;
+
+const float SPAN_MAX = 8.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float REDUCE_MIN = 1.0 / 128.0;
+
+float luma(vec3 color) {
+	return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+void main() {
+	vec2 texel = 1.0 / vec2(textureSize(source, 0));
+	vec3 rgb_nw = texture(source, v_uv + vec2(-1.0, -1.0) * texel).rgb;
+	vec3 rgb_ne = texture(source, v_uv + vec2(1.0, -1.0) * texel).rgb;
+	vec3 rgb_sw = texture(source, v_uv + vec2(-1.0, 1.0) * texel).rgb;
+	vec3 rgb_se = texture(source, v_uv + vec2(1.0, 1.0) * texel).rgb;
+	vec4 center = texture(source, v_uv);
+
+	float luma_nw = luma(rgb_nw);
+	float luma_ne = luma(rgb_ne);
+	float luma_sw = luma(rgb_sw);
+	float luma_se = luma(rgb_se);
+	float luma_m = luma(center.rgb);
+	float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+	float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+	// Blur along the edge, which is perpendicular to the luma gradient.
+	vec2 dir = vec2(
+		-((luma_nw + luma_ne) - (luma_sw + luma_se)),
+		(luma_nw + luma_sw) - (luma_ne + luma_se));
+	float dir_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * (0.25 * REDUCE_MUL), REDUCE_MIN);
+	float rcp_dir_min = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+	dir = clamp(dir * rcp_dir_min, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * texel;
+
+	vec3 rgb_a = 0.5 * (
+		texture(source, v_uv + dir * (1.0 / 3.0 - 0.5)).rgb +
+		texture(source, v_uv + dir * (2.0 / 3.0 - 0.5)).rgb);
+	vec3 rgb_b = rgb_a * 0.5 + 0.25 * (
+		texture(source, v_uv + dir * -0.5).rgb +
+		texture(source, v_uv + dir * 0.5).rgb);
+	float luma_b = luma(rgb_b);
+	// Use the narrower blur if the wider one samples across other edges.
+	vec3 rgb = (luma_b < luma_min || luma_b > luma_max) ? rgb_a : rgb_b;
+	f_color = vec4(rgb, center.a);
+}
@@ -0,0 +1,37 @@
+#version 450
+
+layout(location = 0) in vec3 v_normal;
+layout(location = 1) in vec2 v_uv;
+layout(location = 2) in vec3 v_view_position;
+layout(location = 3) in vec3 v_view_normal;
+layout(location = 4) in vec4 v_color;
+layout(location = 5) in vec2 v_uv1;
+
+// View-space normal facing the camera.
+layout(location = 0) out vec4 f_normal;
+// View-space position, with `w` of 1 for the covered pixels.
+layout(location = 1) out vec4 f_position;
+
+layout(set = 0, binding = 0) uniform Data {
+	mat4 world;
+	mat4 view;
+	mat4 proj;
+	uint shading_mode;
+	float depth_near;
+	float depth_far;
+	uint highlight_back_faces;
+	float depth_steps;
+} uniforms;
+
+void main() {
+	// The last row of perspective projections is (0, 0, -1, 0), and the one
+	// of orthographic projections is (0, 0, 0, 1).
+	vec3 to_eye = uniforms.proj[3][3] == 0.0 ? -v_view_position : vec3(0.0, 0.0, 1.0);
+	vec3 normal = length(v_view_normal) > 0.0 ? normalize(v_view_normal) : normalize(to_eye);
+	// Both sides are drawn, so the normals of the back faces are flipped.
+	if (dot(normal, to_eye) < 0.0) {
+		normal = -normal;
+	}
+	f_normal = vec4(normal, 0.0);
+	f_position = vec4(v_view_position, 1.0);
+}
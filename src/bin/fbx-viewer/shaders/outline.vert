@@ -0,0 +1,32 @@
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+
+layout(location = 0) out vec4 v_color;
+
+layout(set = 0, binding = 0) uniform Data {
+	mat4 world;
+	mat4 view;
+	mat4 proj;
+} uniforms;
+
+layout(push_constant) uniform PushConstants {
+	vec4 color;
+	vec2 viewport_size;
+	// Width in pixels.
+	float width;
+} outline;
+
+void main() {
+	mat4 worldview = uniforms.view * uniforms.world;
+	vec4 clip = uniforms.proj * worldview * vec4(position, 1.0);
+	// Extrude the vertex along the normal projected to the screen.
+	vec2 clip_normal = (uniforms.proj * vec4(mat3(worldview) * normal, 0.0)).xy;
+	vec2 screen_normal = clip_normal * outline.viewport_size;
+	if (dot(screen_normal, screen_normal) > 0.0) {
+		clip.xy += normalize(screen_normal) * outline.width * 2.0 / outline.viewport_size * clip.w;
+	}
+	v_color = outline.color;
+	gl_Position = clip;
+}
@@ -0,0 +1,18 @@
+#version 450
+
+// Unused, but the inputs should match the outputs of the vertex shader.
+layout(location = 0) in vec3 v_normal;
+layout(location = 1) in vec2 v_uv;
+layout(location = 2) in vec3 v_view_position;
+layout(location = 3) in vec3 v_view_normal;
+layout(location = 4) in vec4 v_color;
+layout(location = 5) in vec2 v_uv1;
+
+layout(location = 0) out vec4 f_color;
+
+// Color added for each layer of fragments.
+const vec3 LAYER_COLOR = vec3(0.12, 0.05, 0.02);
+
+void main() {
+	f_color = vec4(LAYER_COLOR, 1.0);
+}
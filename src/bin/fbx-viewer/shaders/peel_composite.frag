@@ -0,0 +1,14 @@
+#version 450
+
+// Unused, but the input should match the output of the vertex shader.
+layout(location = 0) in vec2 v_uv;
+
+layout(location = 0) out vec4 f_color;
+
+// Premultiplied color of the layer.
+layout(set = 0, binding = 0) uniform sampler2D layer;
+
+void main() {
+	// The layer has the same size as the framebuffer.
+	f_color = texelFetch(layer, ivec2(gl_FragCoord.xy), 0);
+}
@@ -0,0 +1,88 @@
+#version 450
+
+// Unused, but the input should match the output of the vertex shader.
+layout(location = 0) in vec2 v_uv;
+
+// Ambient occlusion, multiplied to the scene color.
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D normals;
+layout(set = 0, binding = 1) uniform sampler2D positions;
+
+layout(push_constant) uniform Params {
+	// Projection of the view.
+	mat4 proj;
+	// Minimum (inclusive) and maximum (exclusive) pixel positions of the
+	// viewport.
+	ivec4 viewport;
+	// Radius of the occlusion in view space.
+	float radius;
+	// Intensity of the occlusion.
+	float intensity;
+} params;
+
+// Number of the samples around each pixel.
+const uint SAMPLES = 16;
+
+// Number of the turns of the spiral of the samples.
+const float TURNS = 7.0;
+
+// Maximum radius of the samples in pixels, to keep the samples of near
+// surfaces from spreading over the texture cache.
+const float MAX_SCREEN_RADIUS = 96.0;
+
+// Cosine of the angle above the tangent plane below which the samples are
+// ignored, to avoid self-occlusion of tessellated curved surfaces.
+const float BIAS = 0.1;
+
+const float TAU = 6.28318531;
+
+// Interleaved gradient noise, to rotate the spiral for each pixel.
+float noise(vec2 p) {
+	return fract(52.9829189 * fract(dot(p, vec2(0.06711056, 0.00583715))));
+}
+
+void main() {
+	ivec2 pixel = ivec2(gl_FragCoord.xy);
+	vec4 position = texelFetch(positions, pixel, 0);
+	f_color = vec4(1.0);
+	// Background.
+	if (position.w == 0.0) {
+		return;
+	}
+	vec3 normal = texelFetch(normals, pixel, 0).xyz;
+
+	// Project the radius at the position to the screen.
+	vec4 center = params.proj * vec4(position.xyz, 1.0);
+	vec4 edge = params.proj * vec4(position.xyz + vec3(0.0, params.radius, 0.0), 1.0);
+	float height = float(params.viewport.w - params.viewport.y);
+	float screen_radius = abs(edge.y / edge.w - center.y / center.w) * 0.5 * height;
+	screen_radius = min(screen_radius, MAX_SCREEN_RADIUS);
+	if (screen_radius < 1.0) {
+		return;
+	}
+
+	float radius2 = params.radius * params.radius;
+	float angle = noise(gl_FragCoord.xy) * TAU;
+	float occlusion = 0.0;
+	for (uint i = 0; i < SAMPLES; i++) {
+		float t = (float(i) + 0.5) / float(SAMPLES);
+		float a = angle + t * TURNS * TAU;
+		ivec2 offset = ivec2(vec2(cos(a), sin(a)) * t * screen_radius);
+		ivec2 sample_pixel = clamp(pixel + offset, params.viewport.xy, params.viewport.zw - 1);
+		vec4 sample_position = texelFetch(positions, sample_pixel, 0);
+		if (sample_position.w == 0.0) {
+			continue;
+		}
+		vec3 v = sample_position.xyz - position.xyz;
+		float distance2 = dot(v, v);
+		// Samples outside of the radius do not occlude.
+		if (distance2 >= radius2 || distance2 < 1e-6 * radius2) {
+			continue;
+		}
+		float cosine = dot(v, normal) * inversesqrt(distance2);
+		occlusion += max(cosine - BIAS, 0.0) * (1.0 - distance2 / radius2);
+	}
+	float ao = clamp(1.0 - params.intensity * 2.0 * occlusion / float(SAMPLES), 0.0, 1.0);
+	f_color = vec4(vec3(ao), 1.0);
+}
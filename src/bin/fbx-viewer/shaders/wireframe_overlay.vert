@@ -0,0 +1,28 @@
+#version 450
+
+layout(location = 0) in vec3 position;
+
+layout(set = 0, binding = 0) uniform Data {
+	mat4 world;
+	mat4 view;
+	mat4 proj;
+} uniforms;
+
+// Ratio of the depth to pull the lines toward the eye by, in place of polygon
+// offset.
+const float DEPTH_OFFSET = 1e-3;
+
+void main() {
+	vec4 view_position = uniforms.view * uniforms.world * vec4(position, 1.0);
+	// Scaling around the eye keeps the projected position in perspective
+	// views, and the depth of orthographic views is linear.
+	bool perspective = uniforms.proj[2][3] != 0.0;
+	if (perspective) {
+		view_position.xyz *= 1.0 - DEPTH_OFFSET;
+	}
+	vec4 clip = uniforms.proj * view_position;
+	if (!perspective) {
+		clip.z -= DEPTH_OFFSET * clip.w;
+	}
+	gl_Position = clip;
+}
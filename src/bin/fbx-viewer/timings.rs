@@ -0,0 +1,134 @@
+//! Load timing breakdown.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tracing::{span, Subscriber};
+use tracing_subscriber::{
+    layer::{Context, Layer},
+    registry::LookupSpan,
+};
+
+/// Stages of loading, and the names of the spans measured as the stages.
+///
+/// Spans of a stage should not be nested in each other, since they would be
+/// counted twice.
+const STAGES: &[(&str, &[&str])] = &[
+    ("parse", &["parse_fbx", "parse_gltf"]),
+    ("triangulation", &["triangulate"]),
+    ("attribute expansion", &["expand_attributes"]),
+    ("image decode", &["decode_image"]),
+    (
+        "GPU upload",
+        &["upload_scene", "upload_vertices", "wait_upload"],
+    ),
+    ("load (total)", &["load_fbx", "load_gltf"]),
+];
+
+/// Accumulated time of a stage.
+#[derive(Debug, Default, Clone, Copy)]
+struct Stage {
+    /// Number of measured spans.
+    count: usize,
+    /// Total busy time of the spans.
+    time: Duration,
+}
+
+/// Load timings.
+///
+/// Accumulates the busy time of the spans of each loading stage across
+/// threads.
+#[derive(Debug, Clone, Default)]
+pub struct LoadTimings {
+    /// Stages, keyed by the names in [`STAGES`].
+    stages: Arc<Mutex<HashMap<&'static str, Stage>>>,
+}
+
+impl LoadTimings {
+    /// Creates a new empty `LoadTimings`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a layer which measures the spans into the timings.
+    pub fn layer(&self) -> TimingsLayer {
+        TimingsLayer {
+            timings: self.clone(),
+        }
+    }
+
+    /// Clears the accumulated timings.
+    pub fn reset(&self) {
+        self.stages
+            .lock()
+            .expect("Load timings are poisoned")
+            .clear();
+    }
+
+    /// Returns the table of the accumulated timings.
+    pub fn table(&self) -> String {
+        let stages = self.stages.lock().expect("Load timings are poisoned");
+        let mut table = format!("{:<20} {:>8} {:>12}\n", "stage", "count", "time (ms)");
+        for (name, _) in STAGES {
+            let stage = stages.get(name).copied().unwrap_or_default();
+            table += &format!(
+                "{:<20} {:>8} {:>12.3}\n",
+                name,
+                stage.count,
+                stage.time.as_secs_f64() * 1000.0
+            );
+        }
+        table
+    }
+
+    /// Adds the busy time of a span to the stage.
+    fn add(&self, stage: &'static str, time: Duration) {
+        let mut stages = self.stages.lock().expect("Load timings are poisoned");
+        let stage = stages.entry(stage).or_default();
+        stage.count += 1;
+        stage.time += time;
+    }
+}
+
+/// Layer which measures the spans of the loading stages into [`LoadTimings`].
+#[derive(Debug, Clone)]
+pub struct TimingsLayer {
+    /// Timings.
+    timings: LoadTimings,
+}
+
+/// Time when a span was entered.
+struct EnteredAt(Instant);
+
+impl<S> Layer<S> for TimingsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if stage_of(span.name()).is_some() {
+                span.extensions_mut().replace(EnteredAt(Instant::now()));
+            }
+        }
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            let entered = span.extensions_mut().remove::<EnteredAt>();
+            if let (Some(stage), Some(EnteredAt(entered))) = (stage_of(span.name()), entered) {
+                self.timings.add(stage, entered.elapsed());
+            }
+        }
+    }
+}
+
+/// Returns the stage which the span with the given name is measured as.
+fn stage_of(span_name: &str) -> Option<&'static str> {
+    STAGES
+        .iter()
+        .find(|(_, spans)| spans.contains(&span_name))
+        .map(|(stage, _)| *stage)
+}
@@ -0,0 +1,1068 @@
+//! Viewer window.
+//!
+//! The window events, the camera, and scene loading are handled here, and
+//! the frames are drawn by a [`Renderer`].
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, bail, Context};
+use cgmath::{InnerSpace, Matrix4, Point3, Rad, SquareMatrix, Transform};
+use fbx_viewer::{
+    camera::Camera,
+    data::{
+        self,
+        raycast::{self, Bvh, Hit, Ray},
+        MeshIndex,
+    },
+    fbx::LoadOptions,
+    util::bbox::{BoundingBox3d, BoundingSphere, OptionalBoundingBox3d},
+    Background, CliOpt, TextureRemap,
+};
+use rgb::RGB;
+use tracing::{debug, debug_span, error, info, warn, Level};
+use tracing_chrome::FlushGuard;
+use winit::{
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    monitor::MonitorHandle,
+    window::{Fullscreen, Window},
+};
+
+use crate::{
+    config::Config,
+    console::{self, ConsolePanel, LogConsole},
+    export,
+    renderer::{
+        gizmo::{Gizmo, GizmoMode},
+        RenderSettings, Renderer, PERSPECTIVE,
+    },
+    timings::LoadTimings,
+};
+
+use self::{
+    camera::CameraController,
+    gallery::Gallery,
+    input::{Action, Input, InputState},
+    loading::{LoadJob, LoadedScene},
+};
+
+mod camera;
+mod gallery;
+mod gesture;
+mod input;
+mod loading;
+
+/// Minimum interval of window title updates.
+const TITLE_UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Background presets cycled at runtime.
+const BACKGROUND_PRESETS: &[Background] = &[
+    // Blue.
+    Background {
+        top: RGB::new(0.0, 0.0, 1.0),
+        bottom: RGB::new(0.0, 0.0, 1.0),
+    },
+    // Dark gray.
+    Background {
+        top: RGB::new(0.125, 0.125, 0.125),
+        bottom: RGB::new(0.125, 0.125, 0.125),
+    },
+    // Light gray.
+    Background {
+        top: RGB::new(0.75, 0.75, 0.75),
+        bottom: RGB::new(0.75, 0.75, 0.75),
+    },
+    // Dark blue-gray gradient.
+    Background {
+        top: RGB::new(0.31, 0.35, 0.43),
+        bottom: RGB::new(0.08, 0.08, 0.09),
+    },
+    // Sky gradient.
+    Background {
+        top: RGB::new(0.44, 0.61, 0.82),
+        bottom: RGB::new(0.9, 0.9, 0.9),
+    },
+];
+
+/// Runs the viewer with the renderer.
+///
+/// Recent logs in the console are summarized in the window title, and all the
+/// filtered logs can be shown in a panel drawn over the frame.
+/// If `load_timings` is given, the timings are printed when each scene is loaded.
+/// The given trace guard is dropped (and the trace is flushed) when the event
+/// loop exits.
+pub fn run<R: Renderer + 'static>(
+    event_loop: EventLoop<()>,
+    mut renderer: R,
+    opt: CliOpt,
+    load_options: &LoadOptions,
+    console: LogConsole,
+    load_timings: Option<LoadTimings>,
+    trace_guard: Option<FlushGuard>,
+) -> anyhow::Result<()> {
+    let monitor = select_monitor(renderer.window(), opt.monitor)?;
+    if let Some(monitor) = &monitor {
+        renderer.window().set_outer_position(monitor.position());
+    }
+    // All sizes and positions are in physical pixels.
+    let mut scale_factor = renderer.window().scale_factor();
+    info!("Scale factor: {}", scale_factor);
+
+    // Bounding box previews are drawn as wireframes, so that nested boxes
+    // are visible.
+    let wireframe = opt.preview_bboxes && renderer.supports_wireframe();
+    if opt.preview_bboxes && !wireframe {
+        warn!("Wireframe is not supported by the device, drawing bounding boxes filled");
+    }
+    let backgrounds = background_cycle(opt.background);
+    let mut background_i = 0;
+    let mut settings = RenderSettings {
+        quad_view: opt.quad_view,
+        stereo: opt.stereo,
+        shading_mode: Default::default(),
+        shading_model: Default::default(),
+        highlight_back_faces: false,
+        wireframe,
+        wireframe_overlay: Default::default(),
+        exaggerate_z_fighting: false,
+        overdraw: false,
+        mesh_analysis: false,
+        tangent_frames: false,
+        background: backgrounds[background_i],
+        selected_mesh: None,
+        gizmo: None,
+        console: None,
+    };
+    if let Some(stereo) = settings.stereo {
+        info!("Stereo mode: {:?}", stereo);
+    }
+
+    // The window is shown while the scene is being loaded.
+    let mut normals_recomputed = opt.recompute_normals;
+    // Starting a job cancels the current one, since the job is replaced.
+    let start_loading = {
+        let load_options = load_options.clone();
+        let load_timings = load_timings.clone();
+        move |renderer: &R, path: PathBuf, normals_recomputed: bool| {
+            // The timings of the canceled job are discarded, though its
+            // current stage may still be measured.
+            if let Some(timings) = &load_timings {
+                timings.reset();
+            }
+            LoadJob::start(
+                path,
+                load_options.clone(),
+                renderer.prepare(normals_recomputed),
+            )
+        }
+    };
+    let export_path = opt.export_path.clone();
+    let texture_remaps = opt.remap_texture.clone();
+    let mut gallery = opt.watch_dir.map(Gallery::new).transpose()?;
+    let fbx_path = match (opt.fbx_path, &mut gallery) {
+        (Some(path), _) => Some(path),
+        (None, Some(gallery)) => gallery.select_newest().map(Path::to_owned),
+        (None, None) => bail!("Scene file is not specified"),
+    };
+    let mut loading = fbx_path
+        .map(|path| start_loading(&renderer, path, normals_recomputed))
+        .transpose()?;
+    let mut config = Config::load();
+    // Snapshot of the recent files being cycled, and the current index.
+    let mut recent_cycle: Option<(Vec<PathBuf>, usize)> = None;
+    let mut shown: Option<ShownScene> = None;
+    let mut camera = CameraController::new(Camera::with_position(Point3::new(0.0, 0.0, 0.0)));
+    let mut last_camera_update = Instant::now();
+    let mut input_state = InputState::new();
+    let mut last_title_update: Option<Instant> = None;
+    let mut console_level = Level::WARN;
+    // Number of the newest logs scrolled past in the console panel, or
+    // `None` if the panel is hidden.
+    let mut console_scroll: Option<usize> = None;
+    let mut gizmo_mode = GizmoMode::Off;
+    let mut gizmo_drag: Option<GizmoDrag> = None;
+
+    // `EventLoop::run()` never returns, so the guard should be dropped explicitly.
+    let mut trace_guard = trace_guard;
+    event_loop.run(move |event, _target_window, cflow| {
+        if let Some(input) = input_state.handle(&event, renderer.dimensions()[1]) {
+            match input {
+                Input::Move(key, pressed) => camera.set_key(key, pressed),
+                Input::ReleaseKeys => {
+                    camera.release_keys();
+                    gizmo_drag = None;
+                }
+                Input::Zoom(factor) => camera.zoom(factor),
+                Input::Turn { right, up } => camera.turn(right, up),
+                Input::Gesture { zoom, right } => {
+                    camera.zoom(zoom);
+                    camera.turn(right, Rad(0.0));
+                }
+                Input::Click(position) => {
+                    let ray = match renderer.pick_ray(camera.camera(), &settings, position) {
+                        Some(v) => v,
+                        None => return,
+                    };
+                    let shown = match &shown {
+                        Some(v) => v,
+                        None => return,
+                    };
+                    let handle = settings.selected_mesh.and_then(|mesh| {
+                        let gizmo = shown.gizmo(gizmo_mode, mesh, None)?;
+                        Some((mesh, gizmo, gizmo.pick(&ray)?))
+                    });
+                    if let Some((mesh, gizmo, axis)) = handle {
+                        gizmo_drag = Some(GizmoDrag {
+                            mesh,
+                            gizmo,
+                            axis,
+                            from: ray,
+                            transform: shown
+                                .edits
+                                .mesh_transforms
+                                .get(&mesh)
+                                .copied()
+                                .unwrap_or_else(Matrix4::identity),
+                        });
+                        return;
+                    }
+                    let hit = shown.pick(&ray);
+                    if input_state.modifiers().alt() {
+                        match hit {
+                            Some(hit) => {
+                                let pivot = ray.at(hit.distance).map(Into::into);
+                                info!("Orbit pivot: {:?}", pivot);
+                                camera.set_pivot(pivot);
+                            }
+                            None => info!("No surface under the cursor to pivot around"),
+                        }
+                        return;
+                    }
+                    match hit {
+                        Some(hit) => {
+                            let name = shown
+                                .scene
+                                .mesh(hit.mesh)
+                                .ok()
+                                .and_then(|mesh| mesh.name.as_deref());
+                            info!(
+                                "Picked mesh {:?} (name = {:?}): submesh = {}, triangle = {}, \
+                                 barycentric = {:?}, distance = {}",
+                                hit.mesh,
+                                name,
+                                hit.submesh,
+                                hit.triangle,
+                                hit.barycentric,
+                                hit.distance
+                            );
+                            match data::TriangleInspection::new(
+                                &shown.scene,
+                                hit.mesh,
+                                hit.submesh,
+                                hit.triangle,
+                            ) {
+                                Ok(inspection) => info!("Picked triangle:\n{}", inspection),
+                                Err(e) => warn!("Failed to inspect the picked triangle: {:#}", e),
+                            }
+                            settings.selected_mesh = Some(hit.mesh);
+                        }
+                        None => {
+                            info!("Picked nothing");
+                            settings.selected_mesh = None;
+                        }
+                    }
+                }
+                Input::Drag(position) => {
+                    let drag = match gizmo_drag {
+                        Some(v) => v,
+                        None => return,
+                    };
+                    let shown = match &mut shown {
+                        Some(v) => v,
+                        None => return,
+                    };
+                    let delta = renderer
+                        .pick_ray(camera.camera(), &settings, position)
+                        .and_then(|ray| drag.gizmo.drag(drag.axis, &drag.from, &ray));
+                    let transform = match delta {
+                        Some(delta) => delta * drag.transform,
+                        None => return,
+                    };
+                    match renderer.set_mesh_transform(drag.mesh, Some(transform)) {
+                        Ok(()) => {
+                            shown.edits.mesh_transforms.insert(drag.mesh, transform);
+                        }
+                        Err(e) => {
+                            error!("Failed to transform mesh: {:#}", e);
+                            gizmo_drag = None;
+                        }
+                    }
+                }
+                Input::Release => {
+                    let transform = gizmo_drag.take().and_then(|drag| {
+                        let transform = shown.as_ref()?.edits.mesh_transforms.get(&drag.mesh)?;
+                        Some((drag.mesh, *transform))
+                    });
+                    if let Some((mesh, transform)) = transform {
+                        info!(
+                            "Transform of mesh {:?}: {:?}",
+                            mesh,
+                            Into::<[[f32; 4]; 4]>::into(transform)
+                        );
+                    }
+                }
+                Input::Action(Action::ResetPosition | Action::ResetPosture) => {
+                    let shown = match &shown {
+                        Some(shown) => shown,
+                        None => return,
+                    };
+                    if input == Input::Action(Action::ResetPosture) {
+                        camera.reset_posture(&shown.initial_camera);
+                    } else {
+                        camera.reset_position(
+                            &shown.initial_camera,
+                            shown.bounding_sphere.center().map(Into::into),
+                        );
+                    }
+                }
+                Input::Action(Action::CameraMode) => {
+                    let pivot = shown.as_ref().map_or_else(
+                        || Point3::new(0.0, 0.0, 0.0),
+                        |shown| shown.bounding_sphere.center().map(Into::into),
+                    );
+                    camera.set_mode(camera.mode().next(), pivot);
+                    info!("Camera mode: {:?}", camera.mode());
+                }
+                Input::Action(Action::Recenter) => {
+                    let center = shown
+                        .as_ref()
+                        .and_then(|shown| shown.mesh_bbox(settings.selected_mesh?));
+                    match center {
+                        Some(bbox) => {
+                            let pivot = bbox.center().map(Into::into);
+                            info!("Orbit pivot: {:?}", pivot);
+                            camera.set_pivot(pivot);
+                        }
+                        None => info!("No selected mesh to re-center on"),
+                    }
+                }
+                Input::Action(Action::Fullscreen) => {
+                    let window = renderer.window();
+                    if window.fullscreen().is_some() {
+                        window.set_fullscreen(None);
+                        info!("Fullscreen: false");
+                    } else {
+                        // `None` monitor means the current one.
+                        window.set_fullscreen(Some(Fullscreen::Borderless(monitor.clone())));
+                        info!("Fullscreen: true");
+                    }
+                    renderer.resize(scale_factor);
+                }
+                Input::Action(Action::RecentFile) => {
+                    let (files, i) = recent_cycle.get_or_insert_with(|| {
+                        let files = config.recent_files.clone();
+                        info!("Recent files ({}):", files.len());
+                        for (i, file) in files.iter().enumerate() {
+                            info!("  [{}] {}", i + 1, file.display());
+                        }
+                        (files, 0)
+                    });
+                    if files.len() < 2 {
+                        info!("No other recent files");
+                        return;
+                    }
+                    // The first file is the current one.
+                    *i = (*i + 1) % files.len();
+                    let path = files[*i].clone();
+                    info!("Opening recent file [{}]: {}", *i + 1, path.display());
+                    match start_loading(&renderer, path, normals_recomputed) {
+                        Ok(job) => loading = Some(job),
+                        Err(e) => error!("{:#}", e),
+                    }
+                }
+                Input::Action(Action::QuadView) => {
+                    settings.quad_view = !settings.quad_view;
+                    info!("Quad view: {}", settings.quad_view);
+                }
+                Input::Action(Action::BackFaces) => {
+                    settings.highlight_back_faces = !settings.highlight_back_faces;
+                    info!("Back face highlighting: {}", settings.highlight_back_faces);
+                }
+                Input::Action(Action::Wireframe) => {
+                    if renderer.supports_wireframe() {
+                        settings.wireframe = !settings.wireframe;
+                        info!("Wireframe: {}", settings.wireframe);
+                    } else {
+                        warn!("Wireframe is not supported by the device");
+                    }
+                }
+                Input::Action(Action::WireframeOverlay) => {
+                    if renderer.supports_wireframe() {
+                        settings.wireframe_overlay = settings.wireframe_overlay.next();
+                        info!("Wireframe overlay: {:?}", settings.wireframe_overlay);
+                    } else {
+                        warn!("Wireframe is not supported by the device");
+                    }
+                }
+                Input::Action(Action::ZFighting) => {
+                    settings.exaggerate_z_fighting = !settings.exaggerate_z_fighting;
+                    info!(
+                        "Z-fighting exaggeration: {}",
+                        settings.exaggerate_z_fighting
+                    );
+                }
+                Input::Action(Action::ShadingMode) => {
+                    settings.shading_mode = settings.shading_mode.next();
+                    info!("Shading mode: {:?}", settings.shading_mode);
+                }
+                Input::Action(Action::ShadingModel) => {
+                    settings.shading_model = settings.shading_model.next();
+                    info!("Shading model: {:?}", settings.shading_model);
+                }
+                Input::Action(Action::Overdraw) => {
+                    settings.overdraw = !settings.overdraw;
+                    info!("Overdraw visualization: {}", settings.overdraw);
+                }
+                Input::Action(Action::MeshAnalysis) => {
+                    settings.mesh_analysis = !settings.mesh_analysis;
+                    info!("Mesh analysis: {}", settings.mesh_analysis);
+                    // Scenes being loaded are analyzed on completion.
+                    let shown = match &shown {
+                        Some(v) => v,
+                        None => return,
+                    };
+                    if settings.mesh_analysis {
+                        if let Err(e) = renderer.analyze_meshes(&shown.scene) {
+                            error!("Failed to analyze meshes: {:#}", e);
+                        }
+                    }
+                }
+                Input::Action(Action::TangentFrames) => {
+                    settings.tangent_frames = !settings.tangent_frames;
+                    info!("Tangent frames: {}", settings.tangent_frames);
+                    let shown = match &shown {
+                        Some(v) => v,
+                        None => return,
+                    };
+                    if settings.tangent_frames {
+                        if let Err(e) =
+                            renderer.show_tangent_frames(&shown.scene, normals_recomputed)
+                        {
+                            error!("Failed to generate tangent frames: {:#}", e);
+                        }
+                    }
+                }
+                Input::Action(Action::Background) => {
+                    background_i = (background_i + 1) % backgrounds.len();
+                    settings.background = backgrounds[background_i];
+                    info!("Background: {:?}", settings.background);
+                }
+                Input::Action(Action::LogLevel) => {
+                    console_level = console::next_filter_level(console_level);
+                    // Show the filtered logs immediately.
+                    last_title_update = None;
+                }
+                Input::Action(Action::Console) => {
+                    console_scroll = match console_scroll {
+                        Some(_) => None,
+                        None => Some(0),
+                    };
+                }
+                Input::Action(Action::ConsoleUp) => {
+                    let len = console.records(console_level).len();
+                    if let Some(scroll) = &mut console_scroll {
+                        *scroll = (*scroll + 1).min(len.saturating_sub(1));
+                    }
+                }
+                Input::Action(Action::ConsoleDown) => {
+                    if let Some(scroll) = &mut console_scroll {
+                        *scroll = scroll.saturating_sub(1);
+                    }
+                }
+                Input::Action(Action::RemapTextures) => {
+                    if shown.is_none() {
+                        return;
+                    }
+                    if let Err(e) = renderer.remap_textures() {
+                        error!("Failed to remap textures: {:#}", e);
+                    }
+                }
+                Input::Action(Action::Normals) => {
+                    normals_recomputed = !normals_recomputed;
+                    if normals_recomputed {
+                        info!("Using recomputed normals");
+                    } else {
+                        info!("Using imported normals");
+                    }
+                    // Scenes being loaded use the setting on completion.
+                    let shown = match &shown {
+                        Some(v) => v,
+                        None => return,
+                    };
+                    if let Err(e) = renderer.reload_normals(&shown.scene, normals_recomputed) {
+                        error!("Failed to reload normals: {:#}", e);
+                    }
+                }
+                Input::Action(Action::Screenshot) => {
+                    let path = screenshot_path();
+                    let result =
+                        renderer
+                            .capture_frame(camera.camera(), &settings)
+                            .and_then(|image| {
+                                image
+                                    .save(&path)
+                                    .with_context(|| format!("Failed to save {}", path.display()))
+                            });
+                    match result {
+                        Ok(()) => info!("Saved screenshot to {}", path.display()),
+                        Err(e) => error!("Failed to capture screenshot: {:#}", e),
+                    }
+                }
+                Input::Action(Action::ExportScene) => {
+                    let shown = match &shown {
+                        Some(v) => v,
+                        None => return,
+                    };
+                    let path = export_path.clone().unwrap_or_else(default_export_path);
+                    match export_scene(shown, &texture_remaps, &path) {
+                        Ok(()) => info!("Exported scene to {}", path.display()),
+                        Err(e) => error!("Failed to export scene: {:#}", e),
+                    }
+                }
+                Input::Action(Action::LowerRenderOrder | Action::RaiseRenderOrder) => {
+                    let (shown, index) = match (&mut shown, settings.selected_mesh) {
+                        (Some(shown), Some(index)) => (shown, index),
+                        _ => {
+                            info!("No selected mesh to change the render order of");
+                            return;
+                        }
+                    };
+                    let mesh = match shown.scene.mesh_mut(index) {
+                        Ok(v) => v,
+                        Err(e) => {
+                            error!("Failed to get selected mesh: {}", e);
+                            return;
+                        }
+                    };
+                    let render_order = if input == Input::Action(Action::LowerRenderOrder) {
+                        mesh.render_order.saturating_sub(1)
+                    } else {
+                        mesh.render_order.saturating_add(1)
+                    };
+                    match renderer.set_render_order(index, render_order) {
+                        Ok(()) => {
+                            mesh.render_order = render_order;
+                            info!(
+                                "Render order of mesh {:?} (name = {:?}): {}",
+                                index, mesh.name, render_order
+                            );
+                        }
+                        Err(e) => error!("Failed to change render order: {:#}", e),
+                    }
+                }
+                Input::Action(Action::Gizmo) => {
+                    gizmo_mode = gizmo_mode.next();
+                    gizmo_drag = None;
+                    info!("Gizmo: {:?}", gizmo_mode);
+                }
+                Input::Action(Action::ResetTransform) => {
+                    let (shown, index) = match (&mut shown, settings.selected_mesh) {
+                        (Some(shown), Some(index)) => (shown, index),
+                        _ => {
+                            info!("No selected mesh to reset the transform of");
+                            return;
+                        }
+                    };
+                    if !shown.edits.mesh_transforms.contains_key(&index) {
+                        info!("Mesh {:?} is not transformed", index);
+                        return;
+                    }
+                    gizmo_drag = None;
+                    match renderer.set_mesh_transform(index, None) {
+                        Ok(()) => {
+                            shown.edits.mesh_transforms.remove(&index);
+                            info!("Reset transform of mesh {:?}", index);
+                        }
+                        Err(e) => error!("Failed to reset transform: {:#}", e),
+                    }
+                }
+                Input::Action(Action::HideMesh) => {
+                    let (shown, index) = match (&mut shown, settings.selected_mesh) {
+                        (Some(shown), Some(index)) => (shown, index),
+                        _ => {
+                            info!("No selected mesh to hide");
+                            return;
+                        }
+                    };
+                    match renderer.set_mesh_hidden(index, true) {
+                        Ok(()) => {
+                            shown.edits.hidden_meshes.insert(index);
+                            settings.selected_mesh = None;
+                            gizmo_drag = None;
+                            info!("Hid mesh {:?}", index);
+                        }
+                        Err(e) => error!("Failed to hide mesh: {:#}", e),
+                    }
+                }
+                Input::Action(Action::ShowAllMeshes) => {
+                    let shown = match &mut shown {
+                        Some(v) => v,
+                        None => return,
+                    };
+                    let count = shown.edits.hidden_meshes.len();
+                    for index in std::mem::take(&mut shown.edits.hidden_meshes) {
+                        if let Err(e) = renderer.set_mesh_hidden(index, false) {
+                            error!("Failed to show mesh: {:#}", e);
+                            shown.edits.hidden_meshes.insert(index);
+                        }
+                    }
+                    info!("Showed {} hidden mesh(es)", count);
+                }
+                Input::Action(Action::CancelLoading) => {
+                    // Dropping the job cancels it.
+                    if let Some(job) = loading.take() {
+                        info!("Canceled loading {}", job.path().display());
+                        last_title_update = None;
+                    }
+                }
+                Input::Action(Action::PreviousFile | Action::NextFile) => {
+                    let gallery = match &mut gallery {
+                        Some(v) => v,
+                        None => return,
+                    };
+                    let offset = if input == Input::Action(Action::PreviousFile) {
+                        -1
+                    } else {
+                        1
+                    };
+                    if let Some(path) = gallery.step(offset) {
+                        recent_cycle = None;
+                        match start_loading(&renderer, path.to_owned(), normals_recomputed) {
+                            Ok(job) => loading = Some(job),
+                            Err(e) => error!("{:#}", e),
+                        }
+                    }
+                }
+            }
+            return;
+        }
+
+        match event {
+            Event::RedrawEventsCleared => {
+                let _span = debug_span!("frame").entered();
+                if let Some(result) = loading.as_ref().and_then(LoadJob::try_finish) {
+                    let job = loading.take().expect("Should never fail: the job exists");
+                    let path = job.path().to_owned();
+                    match result.and_then(|loaded| {
+                        let LoadedScene {
+                            scene,
+                            prepared,
+                            bvh,
+                        } = loaded;
+                        let new_shown = ShownScene::new(path.clone(), scene, bvh)?;
+                        renderer.load_scene(&new_shown.scene, prepared)?;
+                        if settings.mesh_analysis {
+                            renderer.analyze_meshes(&new_shown.scene)?;
+                        }
+                        if settings.tangent_frames {
+                            renderer.show_tangent_frames(&new_shown.scene, normals_recomputed)?;
+                        }
+                        Ok(new_shown)
+                    }) {
+                        Ok(new_shown) => {
+                            if let Some(timings) = &load_timings {
+                                eprint!("Load timings of {}:\n{}", path.display(), timings.table());
+                            }
+                            camera.jump_to(
+                                new_shown.initial_camera,
+                                new_shown.bounding_sphere.center().map(Into::into),
+                            );
+                            settings.selected_mesh = None;
+                            gizmo_drag = None;
+                            shown = Some(new_shown);
+                            config.add_recent_file(&path);
+                            if let Err(e) = config.save() {
+                                warn!("Failed to save recent files: {:#}", e);
+                            }
+                            last_title_update = None;
+                        }
+                        Err(e) => error!("Failed to load {}: {:#}", path.display(), e),
+                    }
+                }
+                if let Some(path) = gallery.as_mut().and_then(Gallery::poll) {
+                    recent_cycle = None;
+                    match start_loading(&renderer, path.to_owned(), normals_recomputed) {
+                        Ok(job) => loading = Some(job),
+                        Err(e) => error!("{:#}", e),
+                    }
+                }
+
+                let now = Instant::now();
+                // Stalls such as resizing should not move the camera far.
+                let elapsed = now
+                    .duration_since(last_camera_update)
+                    .min(Duration::from_millis(100));
+                last_camera_update = now;
+                let camera_speed = shown.as_ref().map_or(1.0, ShownScene::camera_speed);
+                camera.update(elapsed.as_secs_f64(), camera_speed, input_state.modifiers());
+
+                settings.gizmo =
+                    shown
+                        .as_ref()
+                        .zip(settings.selected_mesh)
+                        .and_then(|(shown, mesh)| {
+                            shown.gizmo(gizmo_mode, mesh, gizmo_drag.map(|drag| drag.axis))
+                        });
+                settings.console = console_scroll.map(|scroll| {
+                    let mut records = console.records(console_level);
+                    let title = format!(
+                        "Log console: {} log(s) at {} or above \
+                         (L: level, Up/Down: scroll, Ctrl-L: close)",
+                        records.len(),
+                        console_level
+                    );
+                    records.truncate(records.len().saturating_sub(scroll));
+                    ConsolePanel { title, records }
+                });
+                if let Err(e) = renderer.render(camera.camera(), &settings) {
+                    error!("{:#}", e);
+                }
+
+                if let Some(job) = &loading {
+                    // Update every frame to animate the spinner.
+                    renderer.window().set_title(&format!(
+                        "fbx-viewer: loading {} {}% {} (Esc to cancel)",
+                        file_name(job.path()),
+                        job.progress(),
+                        job.spinner()
+                    ));
+                } else if last_title_update.is_none_or(|t| t.elapsed() >= TITLE_UPDATE_INTERVAL) {
+                    let mut title = match &shown {
+                        Some(shown) => format!(
+                            "fbx-viewer: {} ({} triangles) | ",
+                            file_name(&shown.path),
+                            shown.triangles
+                        ),
+                        None => "fbx-viewer | ".to_owned(),
+                    };
+                    title += &renderer.status();
+                    let records = console.records(console_level);
+                    match records.last() {
+                        Some(last) => {
+                            title += &format!(
+                                " | {} log(s) at {} or above, last: {}",
+                                records.len(),
+                                console_level,
+                                last
+                            )
+                        }
+                        None => title += &format!(" | no logs at {} or above", console_level),
+                    }
+                    renderer.window().set_title(&title);
+                    last_title_update = Some(Instant::now());
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => *cflow = ControlFlow::Exit,
+            Event::LoopDestroyed => drop(trace_guard.take()),
+            Event::WindowEvent {
+                event: WindowEvent::Resized(_),
+                ..
+            } => renderer.resize(scale_factor),
+            Event::WindowEvent {
+                event:
+                    WindowEvent::ScaleFactorChanged {
+                        scale_factor: new_scale_factor,
+                        ..
+                    },
+                ..
+            } => {
+                // The new physical size is notified by `Resized` event.
+                scale_factor = new_scale_factor;
+                info!("Scale factor: {}", scale_factor);
+                renderer.resize(scale_factor);
+            }
+            Event::WindowEvent {
+                event: WindowEvent::DroppedFile(path),
+                ..
+            } => {
+                recent_cycle = None;
+                match start_loading(&renderer, path, normals_recomputed) {
+                    Ok(job) => loading = Some(job),
+                    Err(e) => error!("{:#}", e),
+                }
+            }
+            _ => {}
+        }
+    });
+}
+
+/// Scene shown in the viewer.
+struct ShownScene {
+    /// Scene.
+    scene: data::Scene,
+    /// BVH for picking.
+    bvh: Bvh,
+    /// Bounding box.
+    bbox: BoundingBox3d<f32>,
+    /// Bounding sphere.
+    bounding_sphere: BoundingSphere<f32>,
+    /// Camera to show the whole scene.
+    initial_camera: Camera,
+    /// Path of the file.
+    path: PathBuf,
+    /// Number of triangles in the scene.
+    triangles: usize,
+    /// Edits made by the gizmo and the visibility toggles.
+    ///
+    /// The scene itself is not modified, so that the edits can be reset.
+    edits: data::SceneEdits,
+}
+
+impl ShownScene {
+    /// Creates a new `ShownScene` from the loaded scene.
+    fn new(path: PathBuf, scene: data::Scene, bvh: Bvh) -> anyhow::Result<Self> {
+        info!("Loaded {}", path.display());
+
+        let bbox = scene
+            .geometry_meshes()
+            .map(data::GeometryMesh::bbox_mesh)
+            .collect::<OptionalBoundingBox3d<f32>>()
+            .bounding_box()
+            .ok_or_else(|| anyhow!("No data to show (bounding box is `None`)"))?;
+        info!("Scene bounding box = {:?}", bbox);
+        let bounding_sphere = scene
+            .geometry_meshes()
+            .filter_map(data::GeometryMesh::bounding_sphere_mesh)
+            .reduce(|sum, sphere| sum.union(&sphere))
+            .unwrap_or_else(|| (&bbox).into());
+        info!("Scene bounding sphere = {:?}", bounding_sphere);
+
+        let initial_camera = {
+            let center: Point3<f64> = bounding_sphere.center().map(Into::into);
+            debug!("Center calculated from the bounding sphere: {:?}", center);
+            // Place the camera so that the whole bounding sphere fits in the
+            // vertical field of view.
+            let radius = f64::from(bounding_sphere.radius());
+            let distance = PERSPECTIVE.fitting_distance(radius);
+            let position = Point3::new(center.x, center.y, center.z + distance);
+            Camera::with_position(position)
+        };
+        debug!("Initial camera = {:?}", initial_camera);
+
+        let triangles = scene
+            .meshes()
+            .filter_map(|mesh| scene.geometry_mesh(mesh.geometry_mesh_index).ok())
+            .map(data::GeometryMesh::num_triangles)
+            .sum();
+
+        Ok(Self {
+            scene,
+            bvh,
+            bbox,
+            bounding_sphere,
+            initial_camera,
+            path,
+            triangles,
+            edits: data::SceneEdits::default(),
+        })
+    }
+
+    /// Returns the closest intersection of the ray and the meshes, with the
+    /// transforms applied.
+    fn pick(&self, ray: &Ray) -> Option<Hit> {
+        let transforms = &self.edits.mesh_transforms;
+        let hidden = &self.edits.hidden_meshes;
+        let mut closest = self.bvh.closest_hit_filtered(ray, |mesh| {
+            !transforms.contains_key(&mesh) && !hidden.contains(&mesh)
+        });
+        for (&mesh, transform) in transforms {
+            if hidden.contains(&mesh) {
+                continue;
+            }
+            let inverse = match transform.invert() {
+                Some(v) => v,
+                None => continue,
+            };
+            let local = ray.transform(&inverse);
+            let mut hit = match raycast::closest_hit_in_mesh(&self.scene, mesh, &local) {
+                Some(v) => v,
+                None => continue,
+            };
+            // The distance in the mesh space is scaled by the transform.
+            hit.distance =
+                (transform.transform_point(local.at(hit.distance)) - ray.origin()).magnitude();
+            if closest.is_none_or(|closest| hit.distance < closest.distance) {
+                closest = Some(hit);
+            }
+        }
+
+        closest
+    }
+
+    /// Returns the bounding box of the mesh in the world space.
+    fn mesh_bbox(&self, mesh: MeshIndex) -> Option<BoundingBox3d<f32>> {
+        let geometry = self
+            .scene
+            .geometry_mesh(self.scene.mesh(mesh).ok()?.geometry_mesh_index)
+            .ok()?;
+        let bbox = geometry.bbox_mesh().bounding_box()?;
+        Some(match self.edits.mesh_transforms.get(&mesh) {
+            Some(transform) => bbox.transformed(transform),
+            None => bbox,
+        })
+    }
+
+    /// Returns the gizmo of the mode for the mesh, with the handle of the
+    /// axis being dragged.
+    fn gizmo(&self, mode: GizmoMode, mesh: MeshIndex, active_axis: Option<usize>) -> Option<Gizmo> {
+        if mode == GizmoMode::Off {
+            return None;
+        }
+        let bbox = self.mesh_bbox(mesh)?;
+        Some(Gizmo {
+            mode,
+            center: bbox.center(),
+            // The handles reach the corners of the bounding box.
+            size: bbox.size().magnitude() / 2.0,
+            active_axis,
+        })
+    }
+
+    /// Returns the moving speed of the camera in units per second, based on
+    /// the size of the scene.
+    fn camera_speed(&self) -> f64 {
+        let bbox_size = self.bbox.size();
+        let min_div_4 = bbox_size[0].min(bbox_size[1]).min(bbox_size[2]) / 4.0;
+        let max_div_16 = bbox_size[0].max(bbox_size[1]).max(bbox_size[2]) / 16.0;
+        f64::from(min_div_4.max(max_div_16))
+    }
+}
+
+/// Drag of a handle of the gizmo.
+#[derive(Debug, Clone, Copy)]
+struct GizmoDrag {
+    /// Mesh being transformed.
+    mesh: MeshIndex,
+    /// Gizmo at the start of the drag.
+    gizmo: Gizmo,
+    /// Axis of the handle.
+    axis: usize,
+    /// Ray through the cursor at the start of the drag.
+    from: Ray,
+    /// Transform of the mesh at the start of the drag.
+    transform: Matrix4<f32>,
+}
+
+/// Returns the backgrounds to cycle through, starting with the given one.
+fn background_cycle(initial: Background) -> Vec<Background> {
+    std::iter::once(initial)
+        .chain(
+            BACKGROUND_PRESETS
+                .iter()
+                .copied()
+                .filter(|&bg| bg != initial),
+        )
+        .collect()
+}
+
+/// Selects the monitor with the given index.
+///
+/// Returns `None` if no index is given.
+fn select_monitor(window: &Window, index: Option<usize>) -> anyhow::Result<Option<MonitorHandle>> {
+    let monitors: Vec<_> = window.available_monitors().collect();
+    for (i, monitor) in monitors.iter().enumerate() {
+        debug!(
+            "Monitor available [{}]: name={:?}, size={:?}, position={:?}, scale_factor={}",
+            i,
+            monitor.name(),
+            monitor.size(),
+            monitor.position(),
+            monitor.scale_factor()
+        );
+    }
+    let index = match index {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    let monitor = monitors.get(index).cloned().ok_or_else(|| {
+        anyhow!(
+            "Monitor {} not found ({} monitors available)",
+            index,
+            monitors.len()
+        )
+    })?;
+    info!(
+        "Selected monitor: index={}, name={:?}",
+        index,
+        monitor.name()
+    );
+
+    Ok(Some(monitor))
+}
+
+/// Returns the path to save a new screenshot to.
+///
+/// Screenshots are saved in the current directory, named by the time.
+fn screenshot_path() -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |t| t.as_millis());
+    PathBuf::from(format!("fbx-viewer-{}.png", millis))
+}
+
+/// Returns the path to export a scene to if `--export-path` is not given.
+///
+/// Scenes are exported in the current directory as USDZ, named by the time.
+fn default_export_path() -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |t| t.as_millis());
+    PathBuf::from(format!("fbx-viewer-{}.usdz", millis))
+}
+
+/// Writes the shown scene with its edits to the file.
+///
+/// The texture remaps are applied as they are on screen, and the ones whose
+/// files cannot be loaded are skipped with warnings.
+fn export_scene(shown: &ShownScene, remaps: &[TextureRemap], path: &Path) -> anyhow::Result<()> {
+    let mut edits = shown.edits.clone();
+    for remap in remaps {
+        let textures = shown
+            .scene
+            .textures_with_index()
+            .filter(|(_, texture)| texture.name.as_deref() == Some(&*remap.name))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        if textures.is_empty() {
+            continue;
+        }
+        let image = match image::open(&remap.path) {
+            Ok(v) => data::TextureImage::from(v),
+            Err(e) => {
+                warn!("Failed to load {}: {}", remap.path.display(), e);
+                continue;
+            }
+        };
+        for texture in textures {
+            edits.texture_images.insert(texture, image.clone());
+        }
+    }
+    let scene = shown
+        .scene
+        .with_edits(&edits)
+        .context("Failed to apply the edits")?;
+    export::write_scene(&scene, path)
+}
+
+/// Returns the file name of the path for display.
+fn file_name(path: &Path) -> std::borrow::Cow<'_, str> {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+}
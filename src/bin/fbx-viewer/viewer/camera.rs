@@ -0,0 +1,459 @@
+//! Camera controller.
+//!
+//! The controller has two modes with their own input mappings:
+//!
+//! * Fly mode moves and turns the camera by itself, and the velocity follows
+//!   the held keys smoothly.
+//! * Orbit mode turns the camera around a target point, and the camera
+//!   follows the goal posture smoothly.
+
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+
+use cgmath::{
+    Angle, InnerSpace, Point3, Quaternion, Rad, Rotation, Rotation3, Vector2, Vector3, Zero,
+};
+use fbx_viewer::camera::{smoothing_ratio, Camera};
+use tracing::trace;
+use winit::event::ModifiersState;
+
+/// Time constant of the velocity smoothing in fly mode, in seconds.
+const FLY_SMOOTHING_TIME: f64 = 0.15;
+
+/// Time constant of the posture smoothing in orbit mode, in seconds.
+const ORBIT_SMOOTHING_TIME: f64 = 0.1;
+
+/// Angular speed of the rotation in fly mode, in radians per second.
+const FLY_ANGULAR_SPEED: f64 = FRAC_PI_4;
+
+/// Angular speed of the rotation in orbit mode, in radians per second.
+const ORBIT_ANGULAR_SPEED: f64 = FRAC_PI_2;
+
+/// Zoom speed in orbit mode.
+///
+/// The distance to the target changes by the factor of `e` per second.
+const ORBIT_ZOOM_SPEED: f64 = 1.0;
+
+/// Maximum absolute pitch in orbit mode, which avoids flipping at the poles.
+const ORBIT_MAX_PITCH: f64 = FRAC_PI_2 * 0.99;
+
+/// Camera control mode.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+    /// The camera moves and turns by itself.
+    #[default]
+    Fly,
+    /// The camera turns around a target point.
+    Orbit,
+}
+
+impl CameraMode {
+    /// Returns the next mode in the cycle.
+    pub fn next(self) -> Self {
+        match self {
+            CameraMode::Fly => CameraMode::Orbit,
+            CameraMode::Orbit => CameraMode::Fly,
+        }
+    }
+}
+
+/// Movement key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveKey {
+    /// Forward (`W`).
+    Forward,
+    /// Backward (`S`).
+    Back,
+    /// Left (`A`).
+    Left,
+    /// Right (`D`).
+    Right,
+}
+
+/// Held movement keys.
+#[derive(Debug, Default, Clone, Copy)]
+struct HeldKeys {
+    /// Forward.
+    forward: bool,
+    /// Backward.
+    back: bool,
+    /// Left.
+    left: bool,
+    /// Right.
+    right: bool,
+}
+
+impl HeldKeys {
+    /// Returns the input axes, with X axis to the right and Y axis to the
+    /// forward.
+    fn axes(&self) -> Vector2<f64> {
+        let axis = |positive: bool, negative: bool| f64::from(positive) - f64::from(negative);
+        Vector2::new(axis(self.right, self.left), axis(self.forward, self.back))
+    }
+}
+
+/// Posture of the camera in orbit mode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Orbit {
+    /// Target point.
+    target: Point3<f64>,
+    /// Distance from the target to the eye.
+    distance: f64,
+    /// Yaw.
+    yaw: Rad<f64>,
+    /// Pitch.
+    pitch: Rad<f64>,
+}
+
+impl Orbit {
+    /// Creates a new `Orbit` looking at the point at the given distance in
+    /// front of the camera.
+    fn in_front_of(camera: &Camera, distance: f64) -> Self {
+        let pitch = Rad(camera.pitch.0.clamp(-ORBIT_MAX_PITCH, ORBIT_MAX_PITCH));
+        let orbit = Self {
+            target: Point3::new(0.0, 0.0, 0.0),
+            distance,
+            yaw: camera.yaw,
+            pitch,
+        };
+        Self {
+            target: camera.position - orbit.eye_offset(),
+            ..orbit
+        }
+    }
+
+    /// Creates a new `Orbit` with the eye at `eye`, looking at `target`.
+    fn looking_at(eye: Point3<f64>, target: Point3<f64>) -> Self {
+        let camera = Camera::look_at(eye, target);
+        Self {
+            target,
+            distance: (target - eye).magnitude().max(f64::EPSILON),
+            yaw: camera.yaw,
+            pitch: Rad(camera.pitch.0.clamp(-ORBIT_MAX_PITCH, ORBIT_MAX_PITCH)),
+        }
+    }
+
+    /// Returns the offset of the eye from the target.
+    fn eye_offset(&self) -> Vector3<f64> {
+        let direction = Quaternion::from_angle_y(self.yaw) * Quaternion::from_angle_x(self.pitch);
+        direction.rotate_vector(-Camera::forward() * self.distance)
+    }
+
+    /// Returns the camera.
+    fn camera(&self, scale: f64) -> Camera {
+        Camera {
+            position: self.target + self.eye_offset(),
+            yaw: self.yaw,
+            pitch: self.pitch,
+            scale,
+        }
+    }
+
+    /// Moves toward the goal by the given ratio.
+    fn approach(&mut self, goal: &Self, ratio: f64) {
+        self.target += (goal.target - self.target) * ratio;
+        self.distance += (goal.distance - self.distance) * ratio;
+        // Turn the shorter way.
+        self.yaw = (self.yaw + (goal.yaw - self.yaw).normalize_signed() * ratio).normalize_signed();
+        self.pitch += (goal.pitch - self.pitch) * ratio;
+
+        // Stop completely instead of creeping forever.
+        let epsilon = 1e-4;
+        if (goal.target - self.target).magnitude() < goal.distance * epsilon
+            && (goal.distance - self.distance).abs() < goal.distance * epsilon
+            && (goal.yaw - self.yaw).normalize_signed().0.abs() < epsilon
+            && (goal.pitch - self.pitch).0.abs() < epsilon
+        {
+            *self = *goal;
+        }
+    }
+}
+
+/// State of the mode of the controller.
+#[derive(Debug, Clone, Copy)]
+enum ModeState {
+    /// Fly mode.
+    Fly {
+        /// Velocity in the camera space.
+        velocity: Vector3<f64>,
+        /// Angular velocity (up, right) in radians per second.
+        angular_velocity: Vector2<f64>,
+    },
+    /// Orbit mode.
+    Orbit {
+        /// Current posture.
+        current: Orbit,
+        /// Posture the camera is moving toward.
+        goal: Orbit,
+    },
+}
+
+impl ModeState {
+    /// Returns the initial state of fly mode.
+    fn fly() -> Self {
+        ModeState::Fly {
+            velocity: Vector3::zero(),
+            angular_velocity: Vector2::zero(),
+        }
+    }
+
+    /// Returns the initial state of orbit mode.
+    fn orbit(orbit: Orbit) -> Self {
+        ModeState::Orbit {
+            current: orbit,
+            goal: orbit,
+        }
+    }
+}
+
+/// Camera controller.
+///
+/// The input is applied to the camera on [`CameraController::update`] every
+/// frame.
+#[derive(Debug, Clone)]
+pub struct CameraController {
+    /// Camera.
+    camera: Camera,
+    /// Held movement keys.
+    keys: HeldKeys,
+    /// Mode and its state.
+    state: ModeState,
+    /// Moving speed in units per second, given on the last update.
+    speed: f64,
+}
+
+impl CameraController {
+    /// Creates a new `CameraController` in fly mode.
+    pub fn new(camera: Camera) -> Self {
+        Self {
+            camera,
+            keys: HeldKeys::default(),
+            state: ModeState::fly(),
+            speed: 1.0,
+        }
+    }
+
+    /// Returns the camera.
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    /// Returns the mode.
+    pub fn mode(&self) -> CameraMode {
+        match self.state {
+            ModeState::Fly { .. } => CameraMode::Fly,
+            ModeState::Orbit { .. } => CameraMode::Orbit,
+        }
+    }
+
+    /// Switches the mode.
+    ///
+    /// On switching to orbit mode, the target is placed in front of the
+    /// camera at the same distance as the given pivot, so that the view does
+    /// not change.
+    pub fn set_mode(&mut self, mode: CameraMode, pivot: Point3<f64>) {
+        self.state = match mode {
+            CameraMode::Fly => ModeState::fly(),
+            CameraMode::Orbit => {
+                let distance = (pivot - self.camera.position).magnitude();
+                let orbit = Orbit::in_front_of(&self.camera, distance.max(f64::EPSILON));
+                // The pitch may be clamped.
+                self.camera = orbit.camera(self.camera.scale);
+                ModeState::orbit(orbit)
+            }
+        };
+    }
+
+    /// Moves the target of orbit mode to the pivot.
+    ///
+    /// The camera turns to the pivot smoothly without moving.
+    /// In fly mode, the controller switches to orbit mode.
+    pub fn set_pivot(&mut self, pivot: Point3<f64>) {
+        if (pivot - self.camera.position).magnitude() < f64::EPSILON {
+            return;
+        }
+        let goal = Orbit::looking_at(self.camera.position, pivot);
+        match &mut self.state {
+            ModeState::Fly { .. } => {
+                let distance = (pivot - self.camera.position).magnitude();
+                let current = Orbit::in_front_of(&self.camera, distance);
+                // The pitch may be clamped.
+                self.camera = current.camera(self.camera.scale);
+                self.state = ModeState::Orbit { current, goal };
+            }
+            ModeState::Orbit { goal: old_goal, .. } => *old_goal = goal,
+        }
+        trace!("Set orbit pivot: goal = {:?}", goal);
+    }
+
+    /// Updates the state of the movement key.
+    pub fn set_key(&mut self, key: MoveKey, pressed: bool) {
+        let held = match key {
+            MoveKey::Forward => &mut self.keys.forward,
+            MoveKey::Back => &mut self.keys.back,
+            MoveKey::Left => &mut self.keys.left,
+            MoveKey::Right => &mut self.keys.right,
+        };
+        *held = pressed;
+    }
+
+    /// Releases all the movement keys.
+    ///
+    /// This should be called when the window loses focus, since the release
+    /// events may be missed.
+    pub fn release_keys(&mut self) {
+        self.keys = HeldKeys::default();
+    }
+
+    /// Replaces the camera immediately, without smoothing.
+    ///
+    /// In orbit mode, the camera orbits around the pivot.
+    pub fn jump_to(&mut self, camera: Camera, pivot: Point3<f64>) {
+        self.camera = camera;
+        let mode = self.mode();
+        self.set_mode(mode, pivot);
+    }
+
+    /// Resets the position of the camera.
+    ///
+    /// In orbit mode, the camera moves back to orbit around the pivot.
+    pub fn reset_position(&mut self, initial: &Camera, pivot: Point3<f64>) {
+        match &mut self.state {
+            ModeState::Fly { .. } => {
+                self.camera.position = initial.position;
+                trace!("Reset camera position: camera = {:?}", self.camera);
+            }
+            ModeState::Orbit { goal, .. } => {
+                let distance = (pivot - initial.position).magnitude();
+                *goal = Orbit::in_front_of(initial, distance.max(f64::EPSILON));
+                trace!("Reset orbit: goal = {:?}", goal);
+            }
+        }
+    }
+
+    /// Resets the yaw and the pitch of the camera.
+    pub fn reset_posture(&mut self, initial: &Camera) {
+        match &mut self.state {
+            ModeState::Fly { .. } => {
+                self.camera.yaw = initial.yaw;
+                self.camera.pitch = initial.pitch;
+                trace!("Reset camera posture: camera = {:?}", self.camera);
+            }
+            ModeState::Orbit { goal, .. } => {
+                goal.yaw = initial.yaw;
+                goal.pitch = Rad(initial.pitch.0.clamp(-ORBIT_MAX_PITCH, ORBIT_MAX_PITCH));
+                trace!("Reset orbit posture: goal = {:?}", goal);
+            }
+        }
+    }
+
+    /// Zooms the camera by the factor.
+    ///
+    /// In fly mode, the camera moves forward by the distance of a second for
+    /// each doubling.
+    /// In orbit mode, the distance to the target is divided by the factor.
+    pub fn zoom(&mut self, factor: f64) {
+        if !(factor.is_finite() && factor > 0.0) {
+            return;
+        }
+        match &mut self.state {
+            ModeState::Fly { .. } => self
+                .camera
+                .move_rel(Camera::forward() * self.speed * factor.log2()),
+            ModeState::Orbit { goal, .. } => goal.distance /= factor,
+        }
+    }
+
+    /// Turns the camera to the right and up.
+    ///
+    /// In orbit mode, the camera orbits around the target.
+    pub fn turn(&mut self, right: Rad<f64>, up: Rad<f64>) {
+        match &mut self.state {
+            ModeState::Fly { .. } => {
+                self.camera.rotate_right(right);
+                self.camera.rotate_up(up);
+            }
+            ModeState::Orbit { goal, .. } => {
+                goal.yaw = (goal.yaw + right).normalize_signed();
+                goal.pitch = Rad((goal.pitch - up).0.clamp(-ORBIT_MAX_PITCH, ORBIT_MAX_PITCH));
+            }
+        }
+    }
+
+    /// Applies the input for the elapsed time to the camera.
+    ///
+    /// `speed` is the moving speed in units per second.
+    ///
+    /// In fly mode:
+    ///
+    /// * `W`/`S`: move forward/backward, or up/down with `Shift`.
+    /// * `A`/`D`: move left/right.
+    /// * `Ctrl` + `W`/`A`/`S`/`D`: turn up/left/down/right.
+    ///
+    /// In orbit mode:
+    ///
+    /// * `W`/`A`/`S`/`D`: orbit up/left/down/right around the target.
+    /// * `Shift` + `W`/`S`: zoom in/out.
+    /// * `Ctrl` + `W`/`A`/`S`/`D`: move the target up/left/down/right.
+    pub fn update(&mut self, elapsed_secs: f64, speed: f64, modifiers: ModifiersState) {
+        self.speed = speed;
+        let axes = self.keys.axes();
+        match &mut self.state {
+            ModeState::Fly {
+                velocity,
+                angular_velocity,
+            } => {
+                let (goal_velocity, goal_angular_velocity) = if modifiers.ctrl() {
+                    (Vector3::zero(), axes * FLY_ANGULAR_SPEED)
+                } else if modifiers.shift() {
+                    let v = Camera::right() * axes.x + Camera::up() * axes.y;
+                    (v * speed, Vector2::zero())
+                } else {
+                    let v = Camera::right() * axes.x + Camera::forward() * axes.y;
+                    (v * speed, Vector2::zero())
+                };
+                let ratio = smoothing_ratio(elapsed_secs, FLY_SMOOTHING_TIME);
+                *velocity += (goal_velocity - *velocity) * ratio;
+                *angular_velocity += (goal_angular_velocity - *angular_velocity) * ratio;
+                // Stop completely instead of creeping forever.
+                if (goal_velocity - *velocity).magnitude() < speed * 1e-3 {
+                    *velocity = goal_velocity;
+                }
+                if (goal_angular_velocity - *angular_velocity).magnitude() < 1e-3 {
+                    *angular_velocity = goal_angular_velocity;
+                }
+
+                if !velocity.is_zero() {
+                    self.camera.move_rel(*velocity * elapsed_secs);
+                }
+                if !angular_velocity.is_zero() {
+                    self.camera
+                        .rotate_up(Rad(angular_velocity.y * elapsed_secs));
+                    self.camera
+                        .rotate_right(Rad(angular_velocity.x * elapsed_secs));
+                }
+            }
+            ModeState::Orbit { current, goal } => {
+                if modifiers.ctrl() {
+                    let direction =
+                        Quaternion::from_angle_y(goal.yaw) * Quaternion::from_angle_x(goal.pitch);
+                    let v = Camera::right() * axes.x + Camera::up() * axes.y;
+                    goal.target += direction.rotate_vector(v * speed * elapsed_secs);
+                } else if modifiers.shift() {
+                    goal.distance *= (-axes.y * ORBIT_ZOOM_SPEED * elapsed_secs).exp();
+                } else {
+                    let angle = ORBIT_ANGULAR_SPEED * elapsed_secs;
+                    goal.yaw = (goal.yaw + Rad(axes.x * angle)).normalize_signed();
+                    goal.pitch =
+                        Rad((goal.pitch.0 - axes.y * angle)
+                            .clamp(-ORBIT_MAX_PITCH, ORBIT_MAX_PITCH));
+                }
+
+                if current != goal {
+                    current.approach(goal, smoothing_ratio(elapsed_secs, ORBIT_SMOOTHING_TIME));
+                    self.camera = current.camera(self.camera.scale);
+                    trace!("Camera = {:?}", self.camera);
+                }
+            }
+        }
+    }
+}
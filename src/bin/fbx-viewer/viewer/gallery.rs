@@ -0,0 +1,156 @@
+//! Gallery of the FBX files in a watched directory.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::{ensure, Context};
+use tracing::{debug, info, warn};
+
+/// Interval of directory scans.
+const SCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Modification stamp of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    /// Modification time.
+    modified: Option<SystemTime>,
+    /// File size.
+    len: u64,
+}
+
+/// Gallery of the FBX files in a directory.
+///
+/// The directory is rescanned periodically, and files which are created or
+/// modified are reported once they stop changing, so that files being written
+/// by exporters are not loaded halfway.
+#[derive(Debug)]
+pub struct Gallery {
+    /// Directory.
+    dir: PathBuf,
+    /// Files in the directory, sorted by path.
+    files: BTreeMap<PathBuf, Stamp>,
+    /// Files which changed on the last scan.
+    unsettled: BTreeSet<PathBuf>,
+    /// Current file.
+    current: Option<PathBuf>,
+    /// Time of the last scan.
+    last_scan: Instant,
+}
+
+impl Gallery {
+    /// Creates a new `Gallery` for the directory.
+    pub fn new(dir: PathBuf) -> anyhow::Result<Self> {
+        ensure!(dir.is_dir(), "{} is not a directory", dir.display());
+        let files =
+            scan(&dir).with_context(|| format!("Failed to list files in {}", dir.display()))?;
+        info!("Watching {} ({} FBX files)", dir.display(), files.len());
+
+        Ok(Self {
+            dir,
+            files,
+            unsettled: BTreeSet::new(),
+            current: None,
+            last_scan: Instant::now(),
+        })
+    }
+
+    /// Selects the most recently modified file, and returns it.
+    pub fn select_newest(&mut self) -> Option<&Path> {
+        self.current = self
+            .files
+            .iter()
+            .max_by_key(|(_, stamp)| stamp.modified)
+            .map(|(path, _)| path.clone());
+        self.current.as_deref()
+    }
+
+    /// Selects the file at the given offset from the current one, and returns
+    /// it.
+    ///
+    /// The selection wraps around at the ends.
+    pub fn step(&mut self, offset: isize) -> Option<&Path> {
+        let len = self.files.len() as isize;
+        if len == 0 {
+            return None;
+        }
+        // Without the current file (or if it is removed), the first step
+        // selects the first or the last file.
+        let current_i = self
+            .current
+            .as_ref()
+            .and_then(|current| self.files.keys().position(|path| path == current))
+            .map_or(if offset > 0 { -1 } else { len }, |i| i as isize);
+        let i = (current_i + offset).rem_euclid(len) as usize;
+        self.current = self.files.keys().nth(i).cloned();
+        info!("Gallery [{}/{}]", i + 1, len);
+        self.current.as_deref()
+    }
+
+    /// Rescans the directory if the scan interval has elapsed, and returns
+    /// the newly created or modified file if any.
+    ///
+    /// The returned file is selected as the current one.
+    pub fn poll(&mut self) -> Option<&Path> {
+        if self.last_scan.elapsed() < SCAN_INTERVAL {
+            return None;
+        }
+        self.last_scan = Instant::now();
+        let files = match scan(&self.dir) {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("Failed to list files in {}: {:#}", self.dir.display(), e);
+                return None;
+            }
+        };
+
+        let mut settled = None;
+        for (path, stamp) in &files {
+            if self.files.get(path) == Some(stamp) {
+                if self.unsettled.remove(path) {
+                    settled = Some(path.clone());
+                }
+            } else {
+                debug!("File changed: {}", path.display());
+                self.unsettled.insert(path.clone());
+            }
+        }
+        self.unsettled.retain(|path| files.contains_key(path));
+        self.files = files;
+
+        let settled = settled?;
+        info!("New file in the watched directory: {}", settled.display());
+        self.current = Some(settled);
+        self.current.as_deref()
+    }
+}
+
+/// Returns the FBX files in the directory.
+fn scan(dir: &Path) -> anyhow::Result<BTreeMap<PathBuf, Stamp>> {
+    let mut files = BTreeMap::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let is_fbx = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("fbx"));
+        if !is_fbx {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let stamp = Stamp {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        };
+        files.insert(path, stamp);
+    }
+
+    Ok(files)
+}
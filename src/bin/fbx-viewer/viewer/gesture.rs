@@ -0,0 +1,78 @@
+//! Touch gestures.
+//!
+//! winit 0.24 does not report the pinch and rotation gestures of touchpads,
+//! so they are recognized from the raw touch points (of touchscreens, and of
+//! touchpads on platforms which report them as touches).
+
+use std::collections::HashMap;
+
+use cgmath::{InnerSpace, Rad, Vector2};
+use winit::event::{Touch, TouchPhase};
+
+/// Two-finger gesture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gesture {
+    /// Ratio of the new distance between the fingers to the old one.
+    ///
+    /// Greater than 1 when the fingers are spread.
+    pub pinch: f64,
+    /// Rotation of the fingers.
+    ///
+    /// Positive is clockwise on the screen.
+    pub rotation: Rad<f64>,
+}
+
+/// Recognizer of two-finger gestures.
+#[derive(Debug, Default, Clone)]
+pub struct TouchGestures {
+    /// Locations of the touching fingers, keyed by the finger IDs.
+    touches: HashMap<u64, Vector2<f64>>,
+}
+
+impl TouchGestures {
+    /// Creates a new `TouchGestures`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the touch points, and returns the gesture if exactly two
+    /// fingers are moving.
+    pub fn touch(&mut self, touch: &Touch) -> Option<Gesture> {
+        let location = Vector2::new(touch.location.x, touch.location.y);
+        match touch.phase {
+            TouchPhase::Started => {
+                self.touches.insert(touch.id, location);
+                None
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.touches.remove(&touch.id);
+                None
+            }
+            TouchPhase::Moved => {
+                let old_span = self.span()?;
+                *self.touches.get_mut(&touch.id)? = location;
+                let new_span = self.span()?;
+                let (old_len, new_len) = (old_span.magnitude(), new_span.magnitude());
+                if old_len < f64::EPSILON || new_len < f64::EPSILON {
+                    return None;
+                }
+                // Y axis of the window points down, so the angle from the old
+                // span to the new one is clockwise.
+                Some(Gesture {
+                    pinch: new_len / old_len,
+                    rotation: old_span.angle(new_span),
+                })
+            }
+        }
+    }
+
+    /// Returns the vector between the fingers, if exactly two fingers are
+    /// touching.
+    fn span(&self) -> Option<Vector2<f64>> {
+        let mut touches = self.touches.values();
+        match (touches.next(), touches.next(), touches.next()) {
+            (Some(a), Some(b), None) => Some(b - a),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,511 @@
+//! Input handling.
+//!
+//! Raw window and device events are translated to [`Input`]s here, so that
+//! the event loop only dispatches them.
+
+use cgmath::Rad;
+use winit::{
+    dpi::PhysicalPosition,
+    event::{
+        DeviceEvent, ElementState, Event, KeyboardInput, ModifiersState, MouseButton,
+        MouseScrollDelta, ScanCode, VirtualKeyCode, WindowEvent,
+    },
+};
+
+use crate::viewer::{camera::MoveKey, gesture::TouchGestures};
+
+/// Zoom factor for a line of mouse wheel scrolling.
+const WHEEL_ZOOM_PER_LINE: f64 = 1.1;
+
+/// Angle to turn the camera by touchpad scrolling over the window height.
+const SCROLL_TURN_PER_HEIGHT: Rad<f64> = Rad(std::f64::consts::PI);
+
+/// Scancodes of the movement keys.
+const MOVE_KEYS: &[(ScanCode, MoveKey)] = &[
+    (17, MoveKey::Forward),
+    (31, MoveKey::Back),
+    (30, MoveKey::Left),
+    (32, MoveKey::Right),
+];
+
+/// Scancodes of the actions, with whether `Ctrl` should be held.
+///
+/// Keys without `Ctrl` bindings trigger their actions regardless of `Ctrl`.
+const BINDINGS: &[(ScanCode, bool, Action)] = &[
+    (11, false, Action::ResetPosition),
+    (11, true, Action::ResetPosture),
+    (46, false, Action::CameraMode),
+    (52, false, Action::Recenter),
+    (87, false, Action::Fullscreen),
+    (19, true, Action::RecentFile),
+    (47, false, Action::QuadView),
+    (33, false, Action::BackFaces),
+    (45, false, Action::Wireframe),
+    (45, true, Action::WireframeOverlay),
+    (44, false, Action::ZFighting),
+    (50, false, Action::ShadingMode),
+    (25, false, Action::ShadingModel),
+    (24, false, Action::Overdraw),
+    (34, false, Action::MeshAnalysis),
+    (48, false, Action::Background),
+    (38, false, Action::LogLevel),
+    (38, true, Action::Console),
+    (20, false, Action::RemapTextures),
+    (20, true, Action::TangentFrames),
+    (49, false, Action::Normals),
+    (88, false, Action::Screenshot),
+    (88, true, Action::ExportScene),
+    (26, false, Action::LowerRenderOrder),
+    (27, false, Action::RaiseRenderOrder),
+    (18, false, Action::Gizmo),
+    (18, true, Action::ResetTransform),
+    (35, false, Action::HideMesh),
+    (35, true, Action::ShowAllMeshes),
+    (1, false, Action::CancelLoading),
+];
+
+/// Action triggered by a key press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Reset the camera position (`0`).
+    ResetPosition,
+    /// Reset the camera angle (`Ctrl-0`).
+    ResetPosture,
+    /// Cycle the camera mode (`C`).
+    CameraMode,
+    /// Orbit around the selected mesh (`.`).
+    Recenter,
+    /// Toggle fullscreen (`F11`).
+    Fullscreen,
+    /// Open the next recent file (`Ctrl-R`).
+    RecentFile,
+    /// Show the previous file in the watched directory (`PageUp`).
+    PreviousFile,
+    /// Show the next file in the watched directory (`PageDown`).
+    NextFile,
+    /// Toggle the quad view (`V`).
+    QuadView,
+    /// Toggle back face highlighting (`F`).
+    BackFaces,
+    /// Toggle the wireframe (`X`).
+    Wireframe,
+    /// Cycle the wireframe overlay (`Ctrl-X`).
+    WireframeOverlay,
+    /// Toggle the z-fighting exaggeration (`Z`).
+    ZFighting,
+    /// Cycle the shading mode (`M`).
+    ShadingMode,
+    /// Cycle the lighting model (`P`).
+    ShadingModel,
+    /// Toggle the overdraw visualization (`O`).
+    Overdraw,
+    /// Toggle the highlighting of the topology problems (`G`).
+    MeshAnalysis,
+    /// Toggle the tangent frames (`Ctrl-T`).
+    TangentFrames,
+    /// Cycle the background (`B`).
+    Background,
+    /// Cycle the log level shown in the title and the console (`L`).
+    LogLevel,
+    /// Toggle the log console panel (`Ctrl-L`).
+    Console,
+    /// Scroll the log console panel to the older logs (`Up`).
+    ConsoleUp,
+    /// Scroll the log console panel to the newer logs (`Down`).
+    ConsoleDown,
+    /// Reload the remapped textures (`T`).
+    RemapTextures,
+    /// Toggle the recomputed normals (`N`).
+    Normals,
+    /// Save a screenshot (`F12`).
+    Screenshot,
+    /// Export the scene with the edits (`Ctrl-F12`).
+    ExportScene,
+    /// Draw the selected mesh earlier (`[`).
+    LowerRenderOrder,
+    /// Draw the selected mesh later (`]`).
+    RaiseRenderOrder,
+    /// Cycle the transform gizmo mode (`E`).
+    Gizmo,
+    /// Reset the transform of the selected mesh (`Ctrl-E`).
+    ResetTransform,
+    /// Hide the selected mesh (`H`).
+    HideMesh,
+    /// Show all hidden meshes (`Ctrl-H`).
+    ShowAllMeshes,
+    /// Cancel loading (`Esc`).
+    CancelLoading,
+}
+
+/// Input to the viewer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Input {
+    /// A movement key is pressed or released.
+    Move(MoveKey, bool),
+    /// All the movement keys and the mouse button are released.
+    ReleaseKeys,
+    /// Zoom by the factor.
+    Zoom(f64),
+    /// Turn the camera.
+    Turn {
+        /// Angle to the right.
+        right: Rad<f64>,
+        /// Angle to up.
+        up: Rad<f64>,
+    },
+    /// Zoom and turn by a touch gesture.
+    Gesture {
+        /// Zoom factor.
+        zoom: f64,
+        /// Angle to the right.
+        right: Rad<f64>,
+    },
+    /// The left button is clicked at the position in the window.
+    Click([f32; 2]),
+    /// The cursor is moved to the position in the window while the left
+    /// button is held.
+    Drag([f32; 2]),
+    /// The left button is released.
+    Release,
+    /// An action key is pressed.
+    Action(Action),
+}
+
+/// State of the input devices.
+#[derive(Debug, Clone)]
+pub struct InputState {
+    /// Keyboard modifiers.
+    modifiers: ModifiersState,
+    /// Cursor position in the window.
+    cursor_position: PhysicalPosition<f64>,
+    /// Whether the left button is held.
+    left_pressed: bool,
+    /// Recognizer of touch gestures.
+    touch_gestures: TouchGestures,
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        Self {
+            modifiers: ModifiersState::default(),
+            cursor_position: PhysicalPosition::new(0.0, 0.0),
+            left_pressed: false,
+            touch_gestures: TouchGestures::new(),
+        }
+    }
+}
+
+impl InputState {
+    /// Creates a new `InputState`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the keyboard modifiers.
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    /// Updates the state with the event, and returns the input it causes.
+    ///
+    /// `window_height` is the height of the window in pixels.
+    pub fn handle(&mut self, event: &Event<'_, ()>, window_height: u32) -> Option<Input> {
+        match event {
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::ModifiersChanged(modifiers) => {
+                    self.modifiers = *modifiers;
+                    None
+                }
+                WindowEvent::CursorMoved { position, .. } => {
+                    self.cursor_position = *position;
+                    self.left_pressed.then(|| Input::Drag(self.cursor()))
+                }
+                WindowEvent::Focused(false) => {
+                    self.left_pressed = false;
+                    Some(Input::ReleaseKeys)
+                }
+                WindowEvent::MouseWheel { delta, .. } => Some(self.scroll(*delta, window_height)),
+                WindowEvent::Touch(touch) => {
+                    self.touch_gestures
+                        .touch(touch)
+                        .map(|gesture| Input::Gesture {
+                            zoom: gesture.pinch,
+                            right: gesture.rotation,
+                        })
+                }
+                WindowEvent::MouseInput {
+                    state,
+                    button: MouseButton::Left,
+                    ..
+                } => {
+                    self.left_pressed = *state == ElementState::Pressed;
+                    Some(match state {
+                        ElementState::Pressed => Input::Click(self.cursor()),
+                        ElementState::Released => Input::Release,
+                    })
+                }
+                _ => None,
+            },
+            Event::DeviceEvent {
+                event: DeviceEvent::Key(input),
+                ..
+            } => self.key(input),
+            _ => None,
+        }
+    }
+
+    /// Returns the cursor position in the window.
+    fn cursor(&self) -> [f32; 2] {
+        [self.cursor_position.x as f32, self.cursor_position.y as f32]
+    }
+
+    /// Returns the input of the mouse wheel or touchpad scrolling.
+    fn scroll(&self, delta: MouseScrollDelta, window_height: u32) -> Input {
+        match delta {
+            MouseScrollDelta::LineDelta(_, lines) => {
+                Input::Zoom(WHEEL_ZOOM_PER_LINE.powf(f64::from(lines)))
+            }
+            // Touchpads report two-finger scrolling in pixels.
+            MouseScrollDelta::PixelDelta(delta) => {
+                let height = f64::from(window_height.max(1));
+                let (x, y) = (delta.x / height, delta.y / height);
+                if self.modifiers.ctrl() {
+                    Input::Zoom(y.exp())
+                } else {
+                    Input::Turn {
+                        right: Rad(-x * SCROLL_TURN_PER_HEIGHT.0),
+                        up: Rad(y * SCROLL_TURN_PER_HEIGHT.0),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the input of the key.
+    fn key(&self, input: &KeyboardInput) -> Option<Input> {
+        let pressed = input.state == ElementState::Pressed;
+        if let Some(&(_, key)) = MOVE_KEYS.iter().find(|(code, _)| *code == input.scancode) {
+            return Some(Input::Move(key, pressed));
+        }
+        if !pressed {
+            return None;
+        }
+        match input.virtual_keycode {
+            Some(VirtualKeyCode::PageUp) => return Some(Input::Action(Action::PreviousFile)),
+            Some(VirtualKeyCode::PageDown) => return Some(Input::Action(Action::NextFile)),
+            Some(VirtualKeyCode::Up) => return Some(Input::Action(Action::ConsoleUp)),
+            Some(VirtualKeyCode::Down) => return Some(Input::Action(Action::ConsoleDown)),
+            _ => {}
+        }
+        let ctrl = self.modifiers.ctrl();
+        let bindings = || {
+            BINDINGS
+                .iter()
+                .filter(move |(code, _, _)| *code == input.scancode)
+        };
+        let action = bindings()
+            .find(|&&(_, with_ctrl, _)| with_ctrl == ctrl)
+            .or_else(|| {
+                // Keys bound only without `Ctrl` ignore it.
+                let mut only = bindings();
+                match (only.next(), only.next()) {
+                    (Some(binding @ (_, false, _)), None) => Some(binding),
+                    _ => None,
+                }
+            })?;
+
+        Some(Input::Action(action.2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use winit::dpi::PhysicalPosition;
+
+    /// Returns the keyboard input of the scancode.
+    #[allow(deprecated)]
+    fn key(scancode: ScanCode, state: ElementState) -> KeyboardInput {
+        KeyboardInput {
+            scancode,
+            state,
+            virtual_keycode: None,
+            modifiers: ModifiersState::empty(),
+        }
+    }
+
+    /// Returns the state with the modifiers.
+    fn with_modifiers(modifiers: ModifiersState) -> InputState {
+        InputState {
+            modifiers,
+            ..InputState::new()
+        }
+    }
+
+    #[test]
+    fn move_keys_track_press_and_release() {
+        let state = InputState::new();
+        assert_eq!(
+            state.key(&key(17, ElementState::Pressed)),
+            Some(Input::Move(MoveKey::Forward, true))
+        );
+        assert_eq!(
+            state.key(&key(17, ElementState::Released)),
+            Some(Input::Move(MoveKey::Forward, false))
+        );
+        // Movement keys are not affected by the modifiers.
+        assert_eq!(
+            with_modifiers(ModifiersState::CTRL).key(&key(30, ElementState::Pressed)),
+            Some(Input::Move(MoveKey::Left, true))
+        );
+    }
+
+    #[test]
+    fn actions_on_press_only() {
+        let state = InputState::new();
+        assert_eq!(
+            state.key(&key(45, ElementState::Pressed)),
+            Some(Input::Action(Action::Wireframe))
+        );
+        assert_eq!(state.key(&key(45, ElementState::Released)), None);
+        assert_eq!(state.key(&key(2, ElementState::Pressed)), None);
+    }
+
+    #[test]
+    fn ctrl_bindings() {
+        let plain = InputState::new();
+        let ctrl = with_modifiers(ModifiersState::CTRL);
+        assert_eq!(
+            plain.key(&key(11, ElementState::Pressed)),
+            Some(Input::Action(Action::ResetPosition))
+        );
+        assert_eq!(
+            ctrl.key(&key(11, ElementState::Pressed)),
+            Some(Input::Action(Action::ResetPosture))
+        );
+        // Bound only with `Ctrl`.
+        assert_eq!(plain.key(&key(19, ElementState::Pressed)), None);
+        assert_eq!(
+            ctrl.key(&key(19, ElementState::Pressed)),
+            Some(Input::Action(Action::RecentFile))
+        );
+        // Bound only without `Ctrl`.
+        assert_eq!(
+            ctrl.key(&key(49, ElementState::Pressed)),
+            Some(Input::Action(Action::Normals))
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn page_keys() {
+        let input = KeyboardInput {
+            virtual_keycode: Some(VirtualKeyCode::PageDown),
+            ..key(109, ElementState::Pressed)
+        };
+        assert_eq!(
+            InputState::new().key(&input),
+            Some(Input::Action(Action::NextFile))
+        );
+    }
+
+    #[test]
+    fn scroll() {
+        let state = InputState::new();
+        assert_eq!(
+            state.scroll(MouseScrollDelta::LineDelta(0.0, 2.0), 100),
+            Input::Zoom(WHEEL_ZOOM_PER_LINE * WHEEL_ZOOM_PER_LINE)
+        );
+        let pixels = MouseScrollDelta::PixelDelta(PhysicalPosition::new(50.0, 0.0));
+        match state.scroll(pixels, 100) {
+            Input::Turn { right, up } => {
+                assert!((right.0 + SCROLL_TURN_PER_HEIGHT.0 / 2.0).abs() < 1e-9);
+                assert_eq!(up, Rad(0.0));
+            }
+            input => panic!("Unexpected input: {:?}", input),
+        }
+        let pixels = MouseScrollDelta::PixelDelta(PhysicalPosition::new(0.0, 100.0));
+        match with_modifiers(ModifiersState::CTRL).scroll(pixels, 100) {
+            Input::Zoom(factor) => assert!((factor - std::f64::consts::E).abs() < 1e-9),
+            input => panic!("Unexpected input: {:?}", input),
+        }
+    }
+
+    #[test]
+    fn modifiers_and_cursor() {
+        let mut state = InputState::new();
+        let window_id = unsafe { winit::window::WindowId::dummy() };
+        let device_id = unsafe { winit::event::DeviceId::dummy() };
+        let input = state.handle(
+            &Event::WindowEvent {
+                window_id,
+                event: WindowEvent::ModifiersChanged(ModifiersState::ALT),
+            },
+            100,
+        );
+        assert_eq!(input, None);
+        assert!(state.modifiers().alt());
+        #[allow(deprecated)]
+        let cursor_moved = WindowEvent::CursorMoved {
+            device_id,
+            position: PhysicalPosition::new(12.0, 34.0),
+            modifiers: ModifiersState::empty(),
+        };
+        state.handle(
+            &Event::WindowEvent {
+                window_id,
+                event: cursor_moved,
+            },
+            100,
+        );
+        #[allow(deprecated)]
+        let click = WindowEvent::MouseInput {
+            device_id,
+            state: ElementState::Pressed,
+            button: MouseButton::Left,
+            modifiers: ModifiersState::empty(),
+        };
+        assert_eq!(
+            state.handle(
+                &Event::WindowEvent {
+                    window_id,
+                    event: click,
+                },
+                100
+            ),
+            Some(Input::Click([12.0, 34.0]))
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn drag_while_pressed() {
+        let mut state = InputState::new();
+        let window_id = unsafe { winit::window::WindowId::dummy() };
+        let device_id = unsafe { winit::event::DeviceId::dummy() };
+        let mut handle = |event| state.handle(&Event::WindowEvent { window_id, event }, 100);
+        let cursor_moved = |x, y| WindowEvent::CursorMoved {
+            device_id,
+            position: PhysicalPosition::new(x, y),
+            modifiers: ModifiersState::empty(),
+        };
+        let button = |state| WindowEvent::MouseInput {
+            device_id,
+            state,
+            button: MouseButton::Left,
+            modifiers: ModifiersState::empty(),
+        };
+        assert_eq!(handle(cursor_moved(1.0, 2.0)), None);
+        assert_eq!(
+            handle(button(ElementState::Pressed)),
+            Some(Input::Click([1.0, 2.0]))
+        );
+        assert_eq!(
+            handle(cursor_moved(3.0, 4.0)),
+            Some(Input::Drag([3.0, 4.0]))
+        );
+        assert_eq!(handle(button(ElementState::Released)), Some(Input::Release));
+        assert_eq!(handle(cursor_moved(5.0, 6.0)), None);
+    }
+}
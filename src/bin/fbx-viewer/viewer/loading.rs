@@ -0,0 +1,176 @@
+//! Background scene loading.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context};
+use fbx_viewer::{
+    data::{self, raycast::Bvh},
+    fbx::LoadOptions,
+};
+use tracing::{debug, info, info_span};
+
+use crate::renderer::Prepare;
+
+/// Frames of the progress spinner.
+const SPINNER: &[char] = &['|', '/', '-', '\\'];
+
+/// Interval of the progress spinner frames.
+const SPINNER_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Progress in percent when parsing the file is done.
+///
+/// Parsing usually takes most of the time for large files.
+const PARSED_PERCENT: f64 = 70.0;
+
+/// Scene loaded and prepared for the renderer.
+pub struct LoadedScene<T> {
+    /// Scene.
+    pub scene: data::Scene,
+    /// Resources prepared by the renderer.
+    pub prepared: T,
+    /// BVH for picking.
+    pub bvh: Bvh,
+}
+
+/// Scene loading running on a worker thread.
+///
+/// The job is canceled when dropped.
+pub struct LoadJob<T> {
+    /// Path of the file.
+    path: PathBuf,
+    /// Cancellation token.
+    canceled: Arc<AtomicBool>,
+    /// Progress in percent.
+    progress: Arc<AtomicU32>,
+    /// Receiver of the result.
+    receiver: mpsc::Receiver<anyhow::Result<LoadedScene<T>>>,
+    /// Time when the job started.
+    started: Instant,
+}
+
+impl<T: Send + 'static> LoadJob<T> {
+    /// Starts loading the file and preparing the scene on a worker thread.
+    pub fn start(
+        path: PathBuf,
+        load_options: LoadOptions,
+        prepare: Prepare<T>,
+    ) -> anyhow::Result<Self> {
+        info!("Loading {}", path.display());
+        let canceled = Arc::new(AtomicBool::new(false));
+        let progress = Arc::new(AtomicU32::new(0));
+        let (sender, receiver) = mpsc::channel();
+        {
+            let path = path.clone();
+            let canceled = canceled.clone();
+            let progress = progress.clone();
+            thread::Builder::new()
+                .name("scene-loader".into())
+                .spawn(move || {
+                    let result = load(&path, &load_options, prepare, &canceled, &progress);
+                    // The receiver is dropped if the job is canceled.
+                    let _ = sender.send(result);
+                })
+                .context("Failed to spawn scene loader thread")?;
+        }
+
+        Ok(Self {
+            path,
+            canceled,
+            progress,
+            receiver,
+            started: Instant::now(),
+        })
+    }
+}
+
+impl<T> LoadJob<T> {
+    /// Returns the path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the progress in percent.
+    pub fn progress(&self) -> u32 {
+        self.progress.load(Ordering::Relaxed)
+    }
+
+    /// Returns the result if the job is finished.
+    pub fn try_finish(&self) -> Option<anyhow::Result<LoadedScene<T>>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                Some(Err(anyhow!("Scene loader thread panicked")))
+            }
+        }
+    }
+
+    /// Returns the current frame of the progress spinner.
+    pub fn spinner(&self) -> char {
+        let frame = self.started.elapsed().as_millis() / SPINNER_INTERVAL.as_millis();
+        SPINNER[frame as usize % SPINNER.len()]
+    }
+}
+
+impl<T> Drop for LoadJob<T> {
+    fn drop(&mut self) {
+        self.canceled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Loads the file and prepares the scene.
+///
+/// The cancellation is checked between the steps, since the loaders cannot be
+/// interrupted.
+fn load<T>(
+    path: &Path,
+    load_options: &LoadOptions,
+    prepare: Prepare<T>,
+    canceled: &AtomicBool,
+    progress: &AtomicU32,
+) -> anyhow::Result<LoadedScene<T>> {
+    let _span = info_span!("background_load", path = %path.display()).entered();
+    let check_canceled = || {
+        if canceled.load(Ordering::Relaxed) {
+            bail!("Loading is canceled");
+        }
+        Ok(())
+    };
+
+    let set_progress = |percent: f64| progress.store(percent as u32, Ordering::Relaxed);
+
+    let mut scene = crate::load_scene_with_progress(path, load_options, &mut |ratio| {
+        set_progress(ratio * PARSED_PERCENT)
+    })
+    .with_context(|| format!("Failed to load scene from {}", path.display()))?;
+    // The renderer uploads the geometry meshes as is, and the runtime edits
+    // are made over the geometry meshes, so the meshes are moved to their
+    // world positions beforehand.
+    let num_baked = scene
+        .bake_mesh_transforms()
+        .context("Failed to apply mesh transforms")?;
+    debug!("Applied the transforms of {} meshes", num_baked);
+    set_progress(75.0);
+    check_canceled()?;
+
+    let prepared = prepare(&scene, &check_canceled)?;
+    set_progress(90.0);
+    check_canceled()?;
+    let bvh = Bvh::new(&scene);
+    set_progress(100.0);
+    check_canceled()?;
+
+    Ok(LoadedScene {
+        scene,
+        prepared,
+        bvh,
+    })
+}
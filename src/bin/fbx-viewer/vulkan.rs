@@ -1,599 +1,1444 @@
 //! Vulkan version.
 
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 
-use anyhow::{anyhow, Context};
-use cgmath::{
-    Angle, EuclideanSpace, Matrix4, Point3, Quaternion, Rad, Rotation, Rotation3, Vector3,
+use anyhow::{anyhow, bail, Context};
+use cgmath::{Deg, Matrix4, Point3, Rad};
+use fbx_viewer::{
+    camera::Camera,
+    data::{self, raycast::Ray, MeshIndex},
+    fbx::LoadOptions,
+    util::{bbox::BoundingSphere, frustum::Frustum},
+    CliOpt, CullFaces, PremultipliedAlpha, StereoMode, TextureRemap,
 };
-use fbx_viewer::{fbx, CliOpt};
-use log::{debug, error, info, trace};
+use image::RgbaImage;
+use tracing::{debug, debug_span, error, info, trace, warn};
+use tracing_chrome::FlushGuard;
 use vulkano::{
-    buffer::{BufferUsage, CpuBufferPool},
-    command_buffer::{AutoCommandBufferBuilder, DynamicState, SubpassContents},
-    descriptor::{
-        descriptor_set::{DescriptorSet, PersistentDescriptorSet},
-        pipeline_layout::PipelineLayoutAbstract,
-    },
+    buffer::{BufferUsage, CpuAccessibleBuffer, TypedBufferAccess},
+    command_buffer::{AutoCommandBufferBuilder, DynamicState},
+    descriptor::{descriptor_set::DescriptorSet, pipeline_layout::PipelineLayoutAbstract},
     device::Device,
     format::Format,
-    framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract, Subpass},
-    image::{AttachmentImage, SwapchainImage},
-    pipeline::{vertex::SingleBufferDefinition, viewport::Viewport, GraphicsPipeline},
-    swapchain::{AcquireError, SwapchainCreationError},
+    image::SwapchainImage,
+    pipeline::GraphicsPipeline,
+    swapchain::{AcquireError, Surface, Swapchain, SwapchainCreationError},
     sync::GpuFuture,
 };
-use winit::window::Window;
+use winit::{event_loop::EventLoop, window::Window};
 
-use self::setup::{create_diffuse_texture_desc_set, create_dummy_texture, create_swapchain, setup};
+use crate::{
+    console::LogConsole,
+    renderer::{
+        shading::Z_FIGHTING_DEPTH_STEPS,
+        stats::{format_bytes, RenderStats},
+        Prepare, RenderSettings, Renderer, WireframeOverlay, PERSPECTIVE,
+    },
+    timings::LoadTimings,
+    viewer,
+};
 
+use self::{
+    antialias::RenderTargets,
+    background::BackgroundPipeline,
+    capabilities::Capabilities,
+    console::ConsoleOverlay,
+    depth_peeling::DepthPeeling,
+    drawable::{DrawItem, DrawList, VertexLayout},
+    frame::{Frame, Frames},
+    gizmo::GizmoPipeline,
+    mesh_analysis::MeshAnalysisPipeline,
+    mesh_pipelines::{MeshPipelines, PipelineKey},
+    outline::OutlinePipelines,
+    overdraw::OverdrawPipeline,
+    setup::{
+        create_diffuse_texture_desc_set, create_dummy_texture, create_override_material_desc_set,
+        create_swapchain, select_depth_stencil_format, select_vertex_layout, setup, QueueSet,
+    },
+    ssao::AmbientOcclusion,
+    tangent_frames::{TangentFramePipeline, TangentFrames},
+    texture_stream::TextureStream,
+    timer::{GpuTimer, PassTimings},
+    upload::{upload, UploadParams},
+    wireframe_overlay::WireframeOverlayPipeline,
+};
+
+mod antialias;
+mod background;
+mod bloom;
+mod capabilities;
+mod console;
+mod depth_peeling;
 mod drawable;
+mod frame;
+mod gizmo;
+mod lod;
+mod mesh_analysis;
+mod mesh_pipelines;
+mod outline;
+mod overdraw;
 mod setup;
+mod ssao;
+mod tangent_frames;
+mod texture_stream;
+mod timer;
+mod upload;
+mod view;
+mod wireframe_overlay;
+
+/// Names of the passes timed by the GPU timer.
+const PASSES: &[&str] = &["main"];
 
-/// Depth format.
-const DEPTH_FORMAT: Format = Format::D32Sfloat;
+/// Index of the main pass in [`PASSES`].
+const MAIN_PASS: usize = 0;
 
-pub fn main(opt: CliOpt) -> anyhow::Result<()> {
+/// Runs the viewer with the Vulkan renderer.
+///
+/// Recent logs in the console are summarized in the window title, and all the
+/// filtered logs can be shown in a panel drawn over the frame.
+/// If `load_timings` is given, the timings are printed when each scene is loaded.
+/// The given trace guard is dropped (and the trace is flushed) when the event
+/// loop exits.
+pub fn main(
+    opt: CliOpt,
+    load_options: &LoadOptions,
+    console: LogConsole,
+    load_timings: Option<LoadTimings>,
+    trace_guard: Option<FlushGuard>,
+) -> anyhow::Result<()> {
     info!("Vulkan mode");
 
-    let (device, queue, surface, event_loop) = setup().context("Failed to setup vulkan")?;
-    let window = surface.window();
-    let mut dimensions = window.inner_size().into();
-    let (mut swapchain, images) =
-        create_swapchain(&device, &queue, &surface).context("Failed to create swapchain")?;
+    let (renderer, event_loop) = VulkanRenderer::new(&opt)?;
+    viewer::run(
+        event_loop,
+        renderer,
+        opt,
+        load_options,
+        console,
+        load_timings,
+        trace_guard,
+    )
+}
 
-    let uniform_buffer = CpuBufferPool::<vs::ty::Data>::new(device.clone(), BufferUsage::all());
+/// Renderer using Vulkan.
+pub struct VulkanRenderer {
+    /// Device.
+    device: Arc<Device>,
+    /// Graphics and transfer queues.
+    queues: QueueSet,
+    /// Surface of the window.
+    surface: Arc<Surface<Window>>,
+    /// Swapchain.
+    swapchain: Arc<Swapchain<Window>>,
+    /// Swapchain images.
+    images: Vec<Arc<SwapchainImage<Window>>>,
+    /// Size of the swapchain images.
+    dimensions: [u32; 2],
+    /// Scale factor of the window.
+    ///
+    /// Sizes in logical pixels (such as the outline width) are converted with
+    /// the scale factor.
+    scale_factor: f64,
+    /// Whether the swapchain should be recreated before the next frame.
+    recreate_swapchain: bool,
+    /// Render targets.
+    render_targets: RenderTargets,
+    /// Layout of the vertex buffers.
+    vertex_layout: VertexLayout,
+    /// Mesh pipelines.
+    mesh_pipelines: MeshPipelines,
+    /// Background pipeline.
+    background_pipeline: BackgroundPipeline,
+    /// Outline pipelines.
+    outline_pipelines: OutlinePipelines,
+    /// Overdraw pipeline.
+    overdraw_pipeline: OverdrawPipeline,
+    /// Wireframe overlay pipeline, if wireframes are supported.
+    wireframe_overlay_pipeline: Option<WireframeOverlayPipeline>,
+    /// Mesh analysis pipeline.
+    mesh_analysis_pipeline: MeshAnalysisPipeline,
+    /// Tangent frame pipeline.
+    tangent_frame_pipeline: TangentFramePipeline,
+    /// Transform gizmo pipeline.
+    gizmo_pipeline: GizmoPipeline,
+    /// Log console overlay.
+    console_overlay: ConsoleOverlay,
+    /// Depth peeling of the transparent meshes.
+    depth_peeling: Option<DepthPeeling>,
+    /// Screen-space ambient occlusion of the opaque meshes.
+    ambient_occlusion: Option<AmbientOcclusion>,
+    /// Descriptor set of the texture used by untextured meshes.
+    dummy_texture_desc_set: Arc<dyn DescriptorSet + Send + Sync>,
+    /// Descriptor set of the material used instead of the scene materials.
+    override_material_desc_set: Option<Arc<dyn DescriptorSet + Send + Sync>>,
+    /// Frames in flight.
+    frames: Frames,
+    /// GPU timer, if timestamp queries are supported.
+    gpu_timer: Option<GpuTimer>,
+    /// Latest GPU timings of the passes.
+    pass_timings: Option<PassTimings>,
+    /// Statistics of the last frame.
+    render_stats: RenderStats,
+    /// Crease angle of recomputed normals.
+    crease_angle: Rad<f32>,
+    /// Whether to generate coarser levels of detail of the meshes.
+    lod: bool,
+    /// Whether to partition the meshes into spatial chunks.
+    chunks: bool,
+    /// Texture remaps.
+    texture_remaps: Vec<TextureRemap>,
+    /// Maximum width and height of the uploaded texture images.
+    max_texture_dim: Option<u32>,
+    /// Capabilities of the device.
+    capabilities: Capabilities,
+    /// How to find the texture images premultiplied by alpha.
+    premultiplied_alpha: PremultipliedAlpha,
+    /// Faces to cull on all meshes, or `None` to cull the back faces of
+    /// single-sided materials.
+    cull: Option<CullFaces>,
+    /// Shown scene.
+    shown: Option<GpuScene>,
+}
 
-    let vs = vs::Shader::load(device.clone()).context("Failed to load vertex shader")?;
-    let fs = fs::Shader::load(device.clone()).context("Failed to load fragment shader")?;
+impl VulkanRenderer {
+    /// Creates a new `VulkanRenderer` with a window, and returns it with the
+    /// event loop of the window.
+    pub fn new(opt: &CliOpt) -> anyhow::Result<(Self, EventLoop<()>)> {
+        let (device, queues, surface, event_loop) = setup().context("Failed to setup vulkan")?;
+        let queue = queues.graphics.clone();
+        let window = surface.window();
+        let (swapchain, images) = create_swapchain(&device, &queue, &surface, opt.swapchain_format)
+            .context("Failed to create swapchain")?;
+        let dimensions = swapchain.dimensions();
+        let scale_factor = window.scale_factor();
 
-    let render_pass = Arc::new(
-        vulkano::single_pass_renderpass!(
+        let capabilities = Capabilities::new(&device);
+        let aa = capabilities.anti_aliasing(opt.aa);
+        let depth_format = select_depth_stencil_format(&device, opt.depth_format)?;
+        let mut render_targets = RenderTargets::new(
             device.clone(),
-            attachments: {
-                color: {
-                    load: Clear,
-                    store: Store,
-                    format: swapchain.format(),
-                    samples: 1,
-                },
-                depth: {
-                    load: Clear,
-                    store: DontCare,
-                    format: DEPTH_FORMAT,
-                    samples: 1,
-                }
-            },
-            pass: {
-                color: [color],
-                depth_stencil: {depth}
+            swapchain.format(),
+            depth_format,
+            aa,
+            opt.bloom,
+        )
+        .context("Failed to set up render targets")?;
+        info!("Anti-aliasing: {:?}", aa);
+        if let Some(intensity) = opt.bloom {
+            info!("Bloom intensity: {}", intensity);
+        }
+
+        render_targets
+            .resize(&images)
+            .context("Failed to create framebuffers")?;
+        // The viewport is dynamic, so the pipelines and the descriptor sets are
+        // kept on resize.
+        let vertex_layout = select_vertex_layout(&device, opt.compact_vertices);
+        let mesh_pipelines = MeshPipelines::new(
+            device.clone(),
+            render_targets.scene_pass(),
+            vertex_layout,
+            opt.winding,
+        )
+        .context("Failed to set up mesh pipelines")?;
+        let background_pipeline =
+            BackgroundPipeline::new(device.clone(), render_targets.scene_pass())
+                .context("Failed to set up background pipeline")?;
+        let outline_pipelines =
+            OutlinePipelines::new(device.clone(), render_targets.scene_pass(), vertex_layout)
+                .context("Failed to set up outline pipelines")?;
+        let overdraw_pipeline =
+            OverdrawPipeline::new(device.clone(), render_targets.scene_pass(), vertex_layout)
+                .context("Failed to set up overdraw pipeline")?;
+        let wireframe_overlay_pipeline = capabilities
+            .wireframe
+            .then(|| {
+                WireframeOverlayPipeline::new(
+                    device.clone(),
+                    render_targets.scene_pass(),
+                    vertex_layout,
+                )
+            })
+            .transpose()
+            .context("Failed to set up wireframe overlay pipeline")?;
+        let mesh_analysis_pipeline =
+            MeshAnalysisPipeline::new(device.clone(), render_targets.scene_pass(), vertex_layout)
+                .context("Failed to set up mesh analysis pipeline")?;
+        let tangent_frame_pipeline =
+            TangentFramePipeline::new(device.clone(), render_targets.scene_pass())
+                .context("Failed to set up tangent frame pipeline")?;
+        let gizmo_pipeline = GizmoPipeline::new(device.clone(), render_targets.scene_pass())
+            .context("Failed to set up gizmo pipeline")?;
+        let mut console_overlay = ConsoleOverlay::new(device.clone(), swapchain.format())
+            .context("Failed to set up console overlay")?;
+        console_overlay
+            .resize(&images)
+            .context("Failed to create console framebuffers")?;
+        let mut depth_peeling = opt
+            .depth_peeling
+            .map(|layers| {
+                info!("Depth peeling: {} layers", layers);
+                DepthPeeling::new(
+                    device.clone(),
+                    render_targets.scene_pass(),
+                    vertex_layout,
+                    layers,
+                    opt.winding,
+                )
+                .context("Failed to set up depth peeling")
+            })
+            .transpose()?;
+        if let Some(depth_peeling) = &mut depth_peeling {
+            depth_peeling.resize(dimensions)?;
+        }
+        let mut ambient_occlusion = opt
+            .ssao
+            .map(|intensity| {
+                info!(
+                    "Ambient occlusion intensity: {}, radius: {}",
+                    intensity, opt.ssao_radius
+                );
+                AmbientOcclusion::new(
+                    device.clone(),
+                    render_targets.scene_pass(),
+                    vertex_layout,
+                    depth_format,
+                    intensity,
+                    opt.ssao_radius,
+                )
+                .context("Failed to set up ambient occlusion")
+            })
+            .transpose()?;
+        if let Some(ambient_occlusion) = &mut ambient_occlusion {
+            ambient_occlusion.resize(dimensions)?;
+        }
+
+        let mut previous_frame: Box<dyn GpuFuture> = vulkano::sync::now(device.clone()).boxed();
+
+        let (dummy_texture_image, dummy_texture_sampler, dummy_texture_future) =
+            create_dummy_texture(device.clone(), queue.clone())
+                .context("Failed to create dummy texture")?;
+        previous_frame = previous_frame.join(dummy_texture_future).boxed();
+
+        let dummy_texture_desc_set = create_diffuse_texture_desc_set(
+            dummy_texture_image,
+            dummy_texture_sampler,
+            mesh_pipelines.base().clone(),
+        )?;
+        let override_material_desc_set = match opt.override_material {
+            Some(material_override) => {
+                info!("Overriding materials: {:?}", material_override);
+                let (desc_set, future) = create_override_material_desc_set(
+                    queue.clone(),
+                    mesh_pipelines.base().clone(),
+                    material_override,
+                )?;
+                previous_frame = previous_frame.join(future).boxed();
+                Some(desc_set)
             }
+            None => None,
+        };
+        previous_frame
+            .flush()
+            .context("Failed to prepare resources")?;
+        let mut frames = Frames::new(device.clone(), mesh_pipelines.base().layout())
+            .context("Failed to create per-frame resources")?;
+        frames.wait_for(previous_frame);
+
+        let gpu_timer = GpuTimer::new(device.clone(), queue.clone(), PASSES)?;
+        if gpu_timer.is_none() {
+            info!("Timestamp queries are not supported by the queue");
+        }
+
+        let renderer = Self {
+            device,
+            queues,
+            surface,
+            swapchain,
+            images,
+            dimensions,
+            scale_factor,
+            recreate_swapchain: false,
+            render_targets,
+            vertex_layout,
+            mesh_pipelines,
+            background_pipeline,
+            outline_pipelines,
+            overdraw_pipeline,
+            wireframe_overlay_pipeline,
+            mesh_analysis_pipeline,
+            tangent_frame_pipeline,
+            gizmo_pipeline,
+            console_overlay,
+            depth_peeling,
+            ambient_occlusion,
+            dummy_texture_desc_set,
+            override_material_desc_set,
+            frames,
+            gpu_timer,
+            pass_timings: None,
+            render_stats: RenderStats::default(),
+            crease_angle: Rad::from(Deg(opt.crease_angle)),
+            lod: opt.lod,
+            chunks: opt.chunks,
+            texture_remaps: opt.remap_texture.clone(),
+            max_texture_dim: Some(capabilities.max_texture_dim(opt.max_texture_dim)),
+            capabilities,
+            premultiplied_alpha: opt.premultiplied_alpha,
+            cull: opt.cull,
+            shown: None,
+        };
+        Ok((renderer, event_loop))
+    }
+
+    /// Returns a new loader.
+    fn loader(&self) -> drawable::Loader {
+        drawable::Loader::new(
+            self.device.clone(),
+            self.queues.graphics.clone(),
+            self.vertex_layout,
         )
-        .context("Failed to create render pass")?,
-    );
-
-    let (mut pipeline, mut framebuffers) =
-        window_size_dependent_setup(device.clone(), &vs, &fs, &images, render_pass.clone())
-            .context("Failed to set up pipeline and framebuffers")?;
-    let mut recreate_swapchain = false;
-
-    let mut previous_frame: Box<dyn GpuFuture> = vulkano::sync::now(device.clone()).boxed();
-
-    let (dummy_texture_image, dummy_texture_sampler, dummy_texture_future) =
-        create_dummy_texture(device.clone(), queue.clone())
-            .context("Failed to create dummy texture")?;
-    previous_frame = previous_frame.join(dummy_texture_future).boxed();
-
-    let scene = fbx::load(opt.fbx_path).context("Failed to interpret FBX scene")?;
-    let (mut drawable_scene, drawable_scene_future) =
-        drawable::Loader::new(device.clone(), queue.clone())
-            .load(&scene)
-            .context("Failed to load scene as drawable data")?;
-    drop(scene);
-    let scene_bbox = drawable_scene
-        .bbox()
-        .bounding_box()
-        .ok_or_else(|| anyhow!("No data to show (bounding box is `None`)"))?;
-    info!("Scene bounding box = {:?}", scene_bbox);
-    if let Some(future) = drawable_scene_future {
-        previous_frame = previous_frame.join(future).boxed();
     }
-    previous_frame = drawable_scene
-        .reset_cache_with_pipeline(&pipeline)?
-        .unwrap_or_else(|| vulkano::sync::now(device.clone()).boxed())
-        .join(previous_frame)
-        .boxed();
-    let mut dummy_texture_desc_set = create_diffuse_texture_desc_set(
-        dummy_texture_image.clone(),
-        dummy_texture_sampler.clone(),
-        pipeline.clone(),
-    )?;
-
-    let initial_camera = {
-        let center = Point3::midpoint(scene_bbox.min(), scene_bbox.max()).map(Into::into);
-        debug!("Center calculated from the bounding box: {:?}", center);
-        let size: Vector3<f64> = scene_bbox.size().map(Into::into);
-        let distance = size[0].max(size[1]);
-        let position = Point3::new(center.x, center.y, center.z + distance);
-        Camera::with_position(position)
-    };
-    debug!("Initial camera = {:?}", initial_camera);
-    let mut camera = initial_camera;
-
-    previous_frame
-        .flush()
-        .context("Failed to prepare resources")?;
-
-    let mut kbd_modifiers = winit::event::ModifiersState::default();
-
-    // Use `Option<_>`, since `GpuFuture::then_signal_fence_and_flush()` takes the ownership of the
-    // future (`self`) and `previous_frame` would be temporarily empty.
-    let mut previous_frame: Option<Box<dyn GpuFuture>> = Some(previous_frame);
-    event_loop.run(move |event, _target_window, cflow| {
-        use winit::{
-            event::{DeviceEvent, ElementState, Event, KeyboardInput, ScanCode, WindowEvent},
-            event_loop::ControlFlow,
-        };
 
-        let window = surface.window();
+    /// Recreates the swapchain and the render targets for the current size
+    /// of the window.
+    ///
+    /// Returns `false` if the swapchain cannot be created for the size, such
+    /// as while the window is minimized.
+    fn recreate_swapchain(&mut self) -> anyhow::Result<bool> {
+        trace!("Recreating swapchain");
+        let (swapchain, images) = match self
+            .swapchain
+            .recreate_with_dimensions(self.surface.window().inner_size().into())
+        {
+            Ok(r) => r,
+            Err(SwapchainCreationError::UnsupportedDimensions) => return Ok(false),
+            Err(e) => return Err(e).context("Failed to recreate swapchain"),
+        };
+        self.swapchain = swapchain;
+        self.images = images;
+        // The surface may require a size different from the window.
+        self.dimensions = self.swapchain.dimensions();
 
-        match event {
-            Event::RedrawEventsCleared => {
-                previous_frame
-                    .as_mut()
-                    .expect(
-                        "Should never fail: a future for the previous frame should be available",
-                    )
-                    .cleanup_finished();
+        self.render_targets
+            .resize(&self.images)
+            .context("Failed to create framebuffers")?;
+        if let Some(depth_peeling) = &mut self.depth_peeling {
+            depth_peeling
+                .resize(self.dimensions)
+                .context("Failed to create depth peeling targets")?;
+        }
+        if let Some(ambient_occlusion) = &mut self.ambient_occlusion {
+            ambient_occlusion
+                .resize(self.dimensions)
+                .context("Failed to create ambient occlusion targets")?;
+        }
+        self.console_overlay
+            .resize(&self.images)
+            .context("Failed to create console framebuffers")?;
 
-                if recreate_swapchain {
-                    trace!("Recreating swapchain");
-                    dimensions = window.inner_size().into();
+        trace!("Swapchain recreation done");
+        self.recreate_swapchain = false;
+        Ok(true)
+    }
 
-                    let (new_swapchain, new_images) =
-                        match swapchain.recreate_with_dimensions(dimensions) {
-                            Ok(r) => r,
-                            Err(SwapchainCreationError::UnsupportedDimensions) => return,
-                            Err(e) => panic!("Failed to recreate swapchain: {}", e),
-                        };
-                    swapchain = new_swapchain;
-
-                    let (new_pipeline, new_framebuffers) = window_size_dependent_setup(
-                        device.clone(),
-                        &vs,
-                        &fs,
-                        &new_images,
-                        render_pass.clone(),
-                    )
-                    .expect("Failed to set up pipeline and framebuffers");
-                    pipeline = new_pipeline;
-                    framebuffers = new_framebuffers;
-
-                    dummy_texture_desc_set = create_diffuse_texture_desc_set(
-                        dummy_texture_image.clone(),
-                        dummy_texture_sampler.clone(),
-                        pipeline.clone(),
-                    )
-                    .expect("Failed to create diffuse texture descriptor set");
-                    previous_frame = Some(
-                        drawable_scene
-                            .reset_cache_with_pipeline(&pipeline)
-                            .expect("Failed to reset scene cash")
-                            .unwrap_or_else(|| vulkano::sync::now(device.clone()).boxed()),
-                    );
-
-                    trace!("Swapchain recreation done");
-                    recreate_swapchain = false;
-                }
-                let uniform_buffer_subbuffer = {
-                    let aspect_ratio = dimensions[0] as f32 / dimensions[1] as f32;
-
-                    /// Conversion from GL coordinate system to Vulkan coordinate
-                    /// system.
-                    ///
-                    /// See <https://matthewwellings.com/blog/the-new-vulkan-coordinate-system/>.
-                    const PROJ_GL_TO_VULKAN: Matrix4<f32> = Matrix4::new(
-                        1.0, 0.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, 0.5, 0.5, 0.0, 0.0, 0.0,
-                        1.0,
-                    );
-                    let proj = PROJ_GL_TO_VULKAN
-                        * cgmath::perspective(Rad::turn_div_6(), aspect_ratio, 0.1, 1000.0);
-                    let view: Matrix4<f32> = camera
-                        .view()
-                        .cast()
-                        .unwrap_or_else(|| panic!("Abnormal camera posture: {:?}", camera));
-                    let world = <Matrix4<f32> as cgmath::SquareMatrix>::identity();
-                    let uniform_data = vs::ty::Data {
-                        world: world.into(),
-                        view: view.into(),
-                        proj: proj.into(),
-                    };
+    /// Renders and presents a frame.
+    ///
+    /// If `capture` is true, the frame is also copied to a buffer, which is
+    /// readable after the submission is finished.
+    ///
+    /// Returns `false` if the frame is skipped, such as while the window is
+    /// minimized.
+    fn draw_frame(
+        &mut self,
+        camera: &Camera,
+        settings: &RenderSettings,
+        capture: Option<&Arc<CpuAccessibleBuffer<[[u8; 4]]>>>,
+    ) -> anyhow::Result<bool> {
+        if let Some(shown) = &mut self.shown {
+            let loader = drawable::Loader::new(
+                self.device.clone(),
+                self.queues.graphics.clone(),
+                self.vertex_layout,
+            );
+            match shown.upload_decoded_textures(
+                loader,
+                self.mesh_pipelines.base(),
+                &self.dummy_texture_desc_set,
+            ) {
+                Ok(Some(future)) => self.frames.wait_for(future),
+                Ok(None) => {}
+                Err(e) => error!("{:#}", e),
+            }
+        }
+        if self.recreate_swapchain && !self.recreate_swapchain()? {
+            return Ok(false);
+        }
+        if capture.is_some_and(|buffer| buffer.len() != self.capture_len()) {
+            bail!("The window is resized while capturing the frame");
+        }
+        let frame = self
+            .frames
+            .begin()
+            .context("Failed to wait for the resources of the frame")?;
 
-                    uniform_buffer
-                        .next(uniform_data)
-                        .expect("Failed to put data into uniform buffer")
+        let stereo = settings.stereo;
+        let dimensions = self.dimensions;
+        // A unit sphere is used while no scene is shown.
+        let bounding_sphere = self.shown.as_ref().map_or_else(
+            || BoundingSphere::new(Point3::new(0.0, 0.0, 0.0), 1.0),
+            |shown| shown.bounding_sphere,
+        );
+        let views = view::layout(settings.quad_view, stereo, dimensions)
+            .into_iter()
+            .map(|(kind, viewport)| {
+                let aspect_ratio = viewport.dimensions[0] / viewport.dimensions[1];
+                let (view, proj) = kind.view_proj(camera, &bounding_sphere, aspect_ratio);
+                let world = <Matrix4<f32> as cgmath::SquareMatrix>::identity();
+                let (depth_near, depth_far) = view::depth_range(&view, &bounding_sphere);
+                let uniform_data = vs::ty::Data {
+                    world: world.into(),
+                    view: view.into(),
+                    proj: proj.into(),
+                    shading_mode: settings.shading_mode.to_shader_value(),
+                    depth_near,
+                    depth_far,
+                    highlight_back_faces: settings.highlight_back_faces.into(),
+                    depth_steps: if settings.exaggerate_z_fighting {
+                        Z_FIGHTING_DEPTH_STEPS
+                    } else {
+                        0.0
+                    },
                 };
-                let set0 = {
-                    let layout = pipeline
-                        .layout()
-                        .descriptor_set_layout(0)
-                        .expect("Failed to get the first descriptor set layout of the pipeline");
-                    Arc::new(
-                        PersistentDescriptorSet::start(layout.clone())
-                            .add_buffer(uniform_buffer_subbuffer)
-                            .expect("Failed to add uniform buffer to descriptor set")
-                            .build()
-                            .expect("Failed to build descriptor set"),
-                    )
+                let dynamic_state = DynamicState {
+                    viewports: Some(vec![viewport]),
+                    ..DynamicState::none()
                 };
-                let (image_num, is_suboptimal, acquire_future) =
-                    match vulkano::swapchain::acquire_next_image(swapchain.clone(), None) {
-                        Ok(r) => r,
-                        Err(AcquireError::OutOfDate) => {
-                            recreate_swapchain = true;
-                            return;
-                        }
-                        Err(e) => panic!("`acquire_next_image()` failed: {}", e),
-                    };
-                if is_suboptimal {
-                    recreate_swapchain = true;
-                }
-
-                let command_buffer = {
-                    let mut builder = AutoCommandBufferBuilder::primary_one_time_submit(
-                        device.clone(),
-                        queue.family(),
+                (kind, dynamic_state, uniform_data)
+            })
+            .collect::<Vec<_>>();
+        if let Some(shown) = &mut self.shown {
+            let frustums = views
+                .iter()
+                .map(|(_, _, uniform_data)| {
+                    view::frustum(
+                        &(Matrix4::from(uniform_data.proj) * Matrix4::from(uniform_data.view)),
                     )
-                    .expect("Failed to create command buffer builder");
+                })
+                .collect::<Vec<_>>();
+            shown.request_visible_textures(&frustums);
+            // The levels of detail follow the main camera, even in the
+            // orthographic views.
+            shown
+                .draw_list
+                .update_lods(camera.position.map(|v| v as f32), dimensions[1] as f32);
+        }
+        frame
+            .write_uniforms(
+                views.iter().map(|(_, _, uniform_data)| *uniform_data),
+                &self
+                    .shown
+                    .as_ref()
+                    .map_or_else(Vec::new, |shown| shown.draw_list.transforms()),
+            )
+            .context("Failed to write uniforms")?;
+        let set0 = frame.set0();
+        let (image_num, is_suboptimal, acquire_future) =
+            match vulkano::swapchain::acquire_next_image(self.swapchain.clone(), None) {
+                Ok(r) => r,
+                Err(AcquireError::OutOfDate) => {
+                    self.recreate_swapchain = true;
+                    return Ok(false);
+                }
+                Err(e) => return Err(e).context("`acquire_next_image()` failed"),
+            };
+        if is_suboptimal {
+            self.recreate_swapchain = true;
+        }
 
-                    builder
-                        .begin_render_pass(
-                            framebuffers[image_num].clone(),
-                            SubpassContents::Inline,
-                            vec![[0.0, 0.0, 1.0, 1.0].into(), 1f32.into()],
-                        )
-                        .expect("Failed to begin new render pass creation");
-
-                    // TODO: Draw scene here.
-                    let mut opaque_meshes = Vec::new();
-                    let mut transparent_meshes = Vec::new();
-                    for mesh in &drawable_scene.meshes {
-                        let geometry_mesh_i = mesh.geometry_mesh_index;
-                        let geometry_mesh = drawable_scene
-                            .geometry_mesh(geometry_mesh_i)
-                            .unwrap_or_else(|| {
-                                panic!("Geometry mesh index out of range: {:?}", geometry_mesh_i)
-                            });
-                        for (&material_i, index_buffer) in mesh
-                            .materials
-                            .iter()
-                            .zip(geometry_mesh.indices_per_material.iter())
-                        {
-                            let material =
-                                drawable_scene.material(material_i).unwrap_or_else(|| {
-                                    panic!("Material index out of range: {:?}", material_i)
-                                });
-                            let material_desc_set = material
-                                .cache
-                                .uniform_buffer
-                                .as_ref()
-                                .expect("Material uniform buffer should be uploaded");
-                            let texture = material.diffuse_texture.map(|diffuse_i| {
-                                drawable_scene.texture(diffuse_i).unwrap_or_else(|| {
-                                    panic!("Material index out of range: {:?}", material_i)
-                                })
-                            });
-                            let texture_desc_set: Arc<dyn DescriptorSet + Send + Sync> = texture
-                                .map_or_else(
-                                    || dummy_texture_desc_set.clone(),
-                                    |t| {
-                                        t.cache
-                                    .descriptor_set
-                                    .as_ref()
-                                    .expect(
-                                        "Descriptor set for texture should be initialized but not",
-                                    )
-                                    .clone()
-                                    },
-                                );
-                            let stuff = (
-                                geometry_mesh.vertices.clone(),
-                                index_buffer.clone(),
-                                material_desc_set.clone(),
-                                texture_desc_set,
-                            );
-                            if texture.map_or(false, |t| t.transparent) {
-                                transparent_meshes.push(stuff);
-                            } else {
-                                opaque_meshes.push(stuff);
-                            }
-                        }
-                    }
+        let background = &settings.background;
+        let overdraw = settings.overdraw;
+        let override_material_desc_set = &self.override_material_desc_set;
+        let mut render_stats = RenderStats::default();
+        let command_buffer = {
+            let mut builder = AutoCommandBufferBuilder::primary_one_time_submit(
+                self.device.clone(),
+                self.queues.graphics.family(),
+            )
+            .context("Failed to create command buffer builder")?;
 
-                    // TODO: Draw the whole scene, not only meshes.
-                    for (vertex, index, material, texture_desc_set) in
-                        opaque_meshes.into_iter().chain(transparent_meshes)
-                    {
-                        builder
-                            .draw_indexed(
-                                pipeline.clone(),
-                                &DynamicState::none(),
-                                vertex,
-                                index,
-                                (set0.clone(), texture_desc_set.clone(), material.clone()),
-                                (),
-                                std::iter::empty(),
-                            )
-                            .expect("Failed to add a draw call to command buffer");
-                    }
+            // The override material is opaque.
+            // The layers cannot be shared by the anaglyph views covering the
+            // same pixels.
+            let depth_peeling = self.depth_peeling.as_ref().filter(|_| {
+                !overdraw
+                    && override_material_desc_set.is_none()
+                    && stereo != Some(StereoMode::Anaglyph)
+            });
+            // The occlusion cannot be shared by the anaglyph views, and is
+            // not applied to the lines of the wireframes.
+            let ambient_occlusion = self.ambient_occlusion.as_ref().filter(|_| {
+                !overdraw && !settings.wireframe && stereo != Some(StereoMode::Anaglyph)
+            });
+            let shown = &self.shown;
+            let items = || shown.iter().flat_map(|shown| shown.draw_list.items());
+            let dynamic_states = views
+                .iter()
+                .map(|(_, dynamic_state, _)| dynamic_state)
+                .collect::<Vec<_>>();
+            if let Some(ambient_occlusion) = ambient_occlusion {
+                ambient_occlusion
+                    .draw_gbuffer(&mut builder, &dynamic_states, set0, items())
+                    .context("Failed to draw ambient occlusion G-buffer")?;
+            }
+            if let Some(depth_peeling) = depth_peeling {
+                depth_peeling
+                    .draw(
+                        &mut builder,
+                        &dynamic_states,
+                        set0,
+                        settings.shading_model,
+                        items(),
+                    )
+                    .context("Failed to draw transparent layers")?;
+            }
 
-                    builder
-                        .end_render_pass()
-                        .expect("Failed to end a render pass creation");
+            self.render_targets
+                .begin_scene(
+                    &mut builder,
+                    image_num,
+                    if overdraw {
+                        overdraw::CLEAR_COLOR
+                    } else {
+                        background::clear_color(background)
+                    },
+                )
+                .context("Failed to begin new render pass creation")?;
 
-                    builder
-                        .build()
-                        .expect("Failed to build a new command buffer")
+            // TODO: Draw the whole scene, not only meshes.
+            for (view_i, (kind, dynamic_state, uniform_data)) in views.iter().enumerate() {
+                let uniform_offset = Frame::uniform_offset(view_i, 0);
+                let frustum = view::frustum(
+                    &(Matrix4::from(uniform_data.proj) * Matrix4::from(uniform_data.view)),
+                );
+                // Uniform slot of the transform of the mesh.
+                let mesh_slot = |mesh: Option<MeshIndex>| {
+                    shown.as_ref().zip(mesh).map_or(0, |(shown, mesh)| {
+                        shown.draw_list.transform_slot(mesh.to_usize())
+                    })
                 };
-
-                let future = previous_frame
-                    .take()
-                    .expect(
-                        "Should never fail: a future for the previous frame should be available",
-                    )
-                    .join(acquire_future)
-                    .then_execute(queue.clone(), command_buffer)
-                    .expect("Failed to execute command buffer")
-                    .then_swapchain_present(queue.clone(), swapchain.clone(), image_num)
-                    .then_signal_fence_and_flush();
-                match future {
-                    Ok(future) => {
-                        previous_frame = Some(future.boxed());
+                let is_visible = |item: &DrawItem| {
+                    shown
+                        .as_ref()
+                        .and_then(|shown| shown.draw_list.bounding_box(item))
+                        .is_none_or(|bbox| frustum.intersects_box(&bbox))
+                };
+                let color_mask = kind.color_mask(stereo);
+                // The anaglyph views share the background.
+                let shares_background =
+                    stereo == Some(StereoMode::Anaglyph) && *kind == view::ViewKind::RightEye;
+                if !overdraw && !shares_background {
+                    self.background_pipeline
+                        .draw(&mut builder, dynamic_state, background)
+                        .context("Failed to draw background")?;
+                }
+                for item in items() {
+                    if !is_visible(item) {
+                        continue;
                     }
-                    Err(vulkano::sync::FlushError::OutOfDate) => {
-                        recreate_swapchain = true;
-                        previous_frame = Some(vulkano::sync::now(device.clone()).boxed());
+                    let item_offset = Frame::uniform_offset(view_i, item.transform);
+                    if overdraw {
+                        self.overdraw_pipeline
+                            .draw(
+                                &mut builder,
+                                dynamic_state,
+                                set0.clone(),
+                                item_offset,
+                                item.vertices.clone(),
+                                item.current_indices().clone(),
+                            )
+                            .context("Failed to add a draw call to command buffer")?;
+                        render_stats.record_draw(item.current_indices().len(), 1);
+                        continue;
                     }
-                    Err(e) => {
-                        error!("{}", e);
-                        previous_frame = Some(vulkano::sync::now(device.clone()).boxed());
+                    // Transparent items are composited after the opaque ones.
+                    if item.transparent && depth_peeling.is_some() {
+                        continue;
                     }
+                    // Back faces are always drawn while they are highlighted.
+                    // The override material is opaque and double-sided.
+                    let (texture, material, key) = match override_material_desc_set {
+                        Some(material) => (
+                            self.dummy_texture_desc_set.clone(),
+                            material.clone(),
+                            PipelineKey {
+                                wireframe: settings.wireframe,
+                                color_mask,
+                                shading_model: settings.shading_model,
+                                ..PipelineKey::default()
+                            },
+                        ),
+                        None => (
+                            item.texture.clone(),
+                            item.material.clone(),
+                            PipelineKey {
+                                blend: item.transparent,
+                                premultiplied_alpha: item.premultiplied_alpha,
+                                cull: match self.cull {
+                                    _ if settings.highlight_back_faces => CullFaces::None,
+                                    Some(cull) => cull,
+                                    None if item.double_sided => CullFaces::None,
+                                    None => CullFaces::Back,
+                                },
+                                wireframe: settings.wireframe,
+                                color_mask,
+                                shading_model: settings.shading_model,
+                            },
+                        ),
+                    };
+                    let pipeline = self
+                        .mesh_pipelines
+                        .get(key)
+                        .context("Failed to create mesh pipeline")?;
+                    builder
+                        .draw_indexed(
+                            pipeline,
+                            dynamic_state,
+                            item.vertices.clone(),
+                            item.current_indices().clone(),
+                            (set0.clone(), texture, material),
+                            (),
+                            std::iter::once(item_offset),
+                        )
+                        .context("Failed to add a draw call to command buffer")?;
+                    render_stats.record_draw(item.current_indices().len(), 3);
                 }
-            }
-            Event::WindowEvent {
-                event: WindowEvent::CloseRequested,
-                ..
-            } => *cflow = ControlFlow::Exit,
-            Event::WindowEvent {
-                event: WindowEvent::Resized(_),
-                ..
-            } => recreate_swapchain = true,
-            Event::WindowEvent {
-                event: WindowEvent::ModifiersChanged(modifiers),
-                ..
-            } => kbd_modifiers = modifiers,
-            Event::DeviceEvent {
-                event: DeviceEvent::Key(input),
-                ..
-            } => {
-                const FORWARD: ScanCode = 17;
-                const BACK: ScanCode = 31;
-                const LEFT: ScanCode = 30;
-                const RIGHT: ScanCode = 32;
-                const ZERO: ScanCode = 11;
-                let move_delta = {
-                    let bbox_size = scene_bbox.size();
-                    let min_div_32 = bbox_size[0].min(bbox_size[1]).min(bbox_size[2]) / 32.0;
-                    let max_div_128 = bbox_size[0].max(bbox_size[1]).max(bbox_size[2]) / 128.0;
-                    f64::from(min_div_32.max(max_div_128))
-                };
-                const ANGLE_DELTA: Rad<f64> = Rad(std::f64::consts::FRAC_PI_2 / 16.0);
-                match input {
-                    KeyboardInput {
-                        scancode: FORWARD,
-                        state: ElementState::Pressed,
-                        ..
-                    } => {
-                        if kbd_modifiers.shift() {
-                            camera.move_rel(Camera::up() * move_delta);
-                        } else if kbd_modifiers.ctrl() {
-                            camera.rotate_up(ANGLE_DELTA);
-                        } else {
-                            camera.move_rel(Camera::forward() * move_delta);
-                        }
-                    }
-                    KeyboardInput {
-                        scancode: BACK,
-                        state: ElementState::Pressed,
-                        ..
-                    } => {
-                        if kbd_modifiers.shift() {
-                            camera.move_rel(Camera::up() * -move_delta);
-                        } else if kbd_modifiers.ctrl() {
-                            camera.rotate_up(-ANGLE_DELTA);
-                        } else {
-                            camera.move_rel(Camera::forward() * -move_delta);
+                if let Some(ambient_occlusion) = ambient_occlusion {
+                    ambient_occlusion
+                        .apply(
+                            &mut builder,
+                            dynamic_state,
+                            uniform_data.proj,
+                            bounding_sphere.radius(),
+                        )
+                        .context("Failed to apply ambient occlusion")?;
+                }
+                if let Some(depth_peeling) = depth_peeling {
+                    depth_peeling
+                        .composite(&mut builder, dynamic_state)
+                        .context("Failed to composite transparent layers")?;
+                }
+                // The lines cannot be shared by the anaglyph views.
+                let wireframe_overlay = self
+                    .wireframe_overlay_pipeline
+                    .as_ref()
+                    .filter(|_| !overdraw && stereo != Some(StereoMode::Anaglyph));
+                match (wireframe_overlay, settings.wireframe_overlay) {
+                    (None, _) | (_, WireframeOverlay::Off) => {}
+                    (Some(pipeline), WireframeOverlay::Selected) => {
+                        if let Some(geometry) = shown.as_ref().and_then(|shown| {
+                            let mesh = shown.drawable_scene.mesh(settings.selected_mesh?).ok()?;
+                            shown
+                                .drawable_scene
+                                .geometry_mesh(mesh.geometry_mesh_index)
+                                .ok()
+                        }) {
+                            let offset =
+                                Frame::uniform_offset(view_i, mesh_slot(settings.selected_mesh));
+                            for indices in &geometry.indices_per_material {
+                                pipeline.draw(
+                                    &mut builder,
+                                    dynamic_state,
+                                    set0.clone(),
+                                    offset,
+                                    geometry.vertices.clone(),
+                                    indices.clone(),
+                                    wireframe_overlay::SELECTED_COLOR,
+                                )?;
+                            }
                         }
                     }
-                    KeyboardInput {
-                        scancode: LEFT,
-                        state: ElementState::Pressed,
-                        ..
-                    } => {
-                        if kbd_modifiers.ctrl() {
-                            camera.rotate_right(-ANGLE_DELTA);
-                        } else {
-                            camera.move_rel(Camera::right() * -move_delta);
+                    (Some(pipeline), WireframeOverlay::All) => {
+                        for item in items() {
+                            if !is_visible(item) {
+                                continue;
+                            }
+                            pipeline.draw(
+                                &mut builder,
+                                dynamic_state,
+                                set0.clone(),
+                                Frame::uniform_offset(view_i, item.transform),
+                                item.vertices.clone(),
+                                item.current_indices().clone(),
+                                wireframe_overlay::COLOR,
+                            )?;
                         }
                     }
-                    KeyboardInput {
-                        scancode: RIGHT,
-                        state: ElementState::Pressed,
-                        ..
-                    } => {
-                        if kbd_modifiers.ctrl() {
-                            camera.rotate_right(ANGLE_DELTA);
-                        } else {
-                            camera.move_rel(Camera::right() * move_delta);
-                        }
+                }
+                // Geometry meshes are drawn once for each transform even if
+                // shared by meshes, as the mesh transforms are not applied
+                // unless edited.
+                let mut analyzed = HashSet::new();
+                let meshes = shown
+                    .iter()
+                    .filter(|_| {
+                        settings.mesh_analysis && !overdraw && stereo != Some(StereoMode::Anaglyph)
+                    })
+                    .flat_map(|shown| {
+                        shown
+                            .drawable_scene
+                            .meshes
+                            .iter()
+                            .enumerate()
+                            .filter(move |&(mesh_i, _)| !shown.draw_list.is_hidden(mesh_i))
+                            .map(move |(mesh_i, mesh)| (shown, mesh_i, mesh))
+                    });
+                for (shown, mesh_i, mesh) in meshes {
+                    let slot = shown.draw_list.transform_slot(mesh_i);
+                    if !analyzed.insert((mesh.geometry_mesh_index, slot)) {
+                        continue;
                     }
-                    KeyboardInput {
-                        scancode: ZERO,
-                        state: ElementState::Pressed,
-                        ..
-                    } => {
-                        if kbd_modifiers.ctrl() {
-                            camera.yaw = initial_camera.yaw;
-                            camera.pitch = initial_camera.pitch;
-                            trace!("Reset camera posture: camera = {:?}", camera);
-                        } else {
-                            camera.position = initial_camera.position;
-                            trace!("Reset camera position: camera = {:?}", camera);
-                        }
+                    let geometry =
+                        match shown.drawable_scene.geometry_mesh(mesh.geometry_mesh_index) {
+                            Ok(v) => v,
+                            Err(_) => continue,
+                        };
+                    if let Some(topology) = &geometry.topology {
+                        self.mesh_analysis_pipeline.draw(
+                            &mut builder,
+                            dynamic_state,
+                            set0.clone(),
+                            Frame::uniform_offset(view_i, slot),
+                            geometry.vertices.clone(),
+                            topology,
+                        )?;
                     }
-                    _ => {}
                 }
+                let tangent_frames = shown
+                    .as_ref()
+                    .filter(|_| {
+                        settings.tangent_frames && !overdraw && stereo != Some(StereoMode::Anaglyph)
+                    })
+                    .and_then(|shown| shown.tangent_frames.as_ref());
+                if let Some(tangent_frames) = tangent_frames {
+                    self.tangent_frame_pipeline.draw(
+                        &mut builder,
+                        dynamic_state,
+                        set0.clone(),
+                        uniform_offset,
+                        tangent_frames,
+                    )?;
+                }
+                if let Some(geometry) = shown.as_ref().and_then(|shown| {
+                    let mesh = shown.drawable_scene.mesh(settings.selected_mesh?).ok()?;
+                    shown
+                        .drawable_scene
+                        .geometry_mesh(mesh.geometry_mesh_index)
+                        .ok()
+                }) {
+                    self.outline_pipelines
+                        .draw(
+                            &mut builder,
+                            dynamic_state,
+                            set0.clone(),
+                            Frame::uniform_offset(view_i, mesh_slot(settings.selected_mesh)),
+                            geometry,
+                            self.scale_factor,
+                        )
+                        .context("Failed to draw outline")?;
+                }
+                let gizmo = settings
+                    .gizmo
+                    .as_ref()
+                    .filter(|_| !overdraw && stereo != Some(StereoMode::Anaglyph));
+                if let Some(gizmo) = gizmo {
+                    self.gizmo_pipeline.draw(
+                        &mut builder,
+                        dynamic_state,
+                        set0.clone(),
+                        uniform_offset,
+                        gizmo,
+                    )?;
+                }
+            }
+
+            self.render_targets
+                .end_scene(&mut builder, image_num, dimensions)
+                .context("Failed to end a render pass creation")?;
+            if let Some(buffer) = capture {
+                builder
+                    .copy_image_to_buffer(self.images[image_num].clone(), buffer.clone())
+                    .context("Failed to copy the frame")?;
+            }
+            // The console is drawn after the capture, so that it is not
+            // included in the captured frames.
+            if let Some(panel) = &settings.console {
+                let scale = (2.0 * self.scale_factor).round().max(1.0) as u32;
+                self.console_overlay
+                    .draw(&mut builder, image_num, dimensions, scale, panel)
+                    .context("Failed to draw console")?;
             }
-            _ => {}
+
+            builder
+                .build()
+                .context("Failed to build a new command buffer")?
+        };
+        self.render_stats = render_stats;
+
+        let submit_span = debug_span!("submit").entered();
+        let mut future = self.frames.start_future().join(acquire_future).boxed();
+        if let Some(timer) = self.gpu_timer.as_mut() {
+            let (new_future, timings) = timer
+                .start_frame(future)
+                .context("Failed to start GPU timer")?;
+            if timings.is_some() {
+                self.pass_timings = timings;
+            }
+            future = timer
+                .begin_pass(new_future, MAIN_PASS)
+                .context("Failed to start GPU timer for the main pass")?;
         }
-    });
+        let mut future = future
+            .then_execute(self.queues.graphics.clone(), command_buffer)
+            .context("Failed to execute command buffer")?
+            .boxed();
+        if let Some(timer) = self.gpu_timer.as_mut() {
+            future = timer
+                .end_pass(future, MAIN_PASS)
+                .context("Failed to stop GPU timer for the main pass")?;
+        }
+        let future = future
+            .then_swapchain_present(
+                self.queues.graphics.clone(),
+                self.swapchain.clone(),
+                image_num,
+            )
+            .then_signal_fence_and_flush();
+        submit_span.exit();
+
+        match self.frames.end(future) {
+            Ok(()) => Ok(true),
+            Err(vulkano::sync::FlushError::OutOfDate) => {
+                self.recreate_swapchain = true;
+                Ok(false)
+            }
+            Err(e) => Err(e).context("Failed to submit the frame"),
+        }
+    }
+
+    /// Returns the number of pixels of a captured frame.
+    fn capture_len(&self) -> usize {
+        self.dimensions[0] as usize * self.dimensions[1] as usize
+    }
 }
 
-/// Setups pipeline and framebuffers.
-#[allow(clippy::type_complexity)]
-fn window_size_dependent_setup(
-    device: Arc<Device>,
-    vs: &vs::Shader,
-    fs: &fs::Shader,
-    images: &[Arc<SwapchainImage<Window>>],
-    render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
-) -> anyhow::Result<(
-    Arc<
-        GraphicsPipeline<
-            SingleBufferDefinition<drawable::vertex::Vertex>,
-            Box<dyn PipelineLayoutAbstract + Send + Sync>,
-            Arc<dyn RenderPassAbstract + Send + Sync>,
-        >,
-    >,
-    Vec<Arc<dyn FramebufferAbstract + Send + Sync>>,
-)> {
-    let dimensions = images[0].dimensions();
-    let depth_buffer = AttachmentImage::transient(device.clone(), dimensions, DEPTH_FORMAT)
-        .context("Failed to create depth buffer")?;
-
-    let framebuffers = images
-        .iter()
-        .map(|image| {
-            Framebuffer::start(render_pass.clone())
-                .add(image.clone())
-                .context("Failed to add a swapchain image to framebuffer")?
-                .add(depth_buffer.clone())
-                .context("Failed to add a depth buffer to framebuffer")?
-                .build()
-                .map(|fb| Arc::new(fb) as Arc<dyn FramebufferAbstract + Send + Sync>)
-                .context("Failed to create framebuffer")
-                .map_err(Into::into)
+impl Renderer for VulkanRenderer {
+    type Prepared = drawable::Scene;
+
+    fn window(&self) -> &Window {
+        self.surface.window()
+    }
+
+    fn dimensions(&self) -> [u32; 2] {
+        self.dimensions
+    }
+
+    fn supports_wireframe(&self) -> bool {
+        self.mesh_pipelines.supports_wireframe()
+    }
+
+    fn prepare(&self, normals_recomputed: bool) -> Prepare<Self::Prepared> {
+        let params = UploadParams {
+            recompute_normals: Some(self.crease_angle).filter(|_| normals_recomputed),
+            vertex_layout: self.vertex_layout,
+            lod: self.lod,
+            chunks: self.chunks,
+            capabilities: self.capabilities,
+        };
+        let (device, queues) = (self.device.clone(), self.queues.clone());
+        Box::new(move |scene, check_canceled| {
+            upload(scene, params, device, &queues, check_canceled)
         })
-        .collect::<anyhow::Result<Vec<_>>>()
-        .context("Failed to create framebuffers")?;
-
-    let pipeline = GraphicsPipeline::start()
-        .vertex_input(SingleBufferDefinition::<drawable::Vertex>::new())
-        .vertex_shader(vs.main_entry_point(), ())
-        .triangle_list()
-        .viewports_dynamic_scissors_irrelevant(1)
-        .viewports(std::iter::once(Viewport {
-            origin: [0.0, 0.0],
-            dimensions: [dimensions[0] as f32, dimensions[1] as f32],
-            depth_range: 0.0..1.0,
-        }))
-        .fragment_shader(fs.main_entry_point(), ())
-        .blend_alpha_blending()
-        .depth_stencil_simple_depth()
-        .render_pass(
-            Subpass::from(render_pass.clone(), 0)
-                .ok_or_else(|| anyhow!("Failed to create subpass"))?,
-        )
-        .build(device)
-        .map(Arc::new)
-        .context("Failed to create pipeline")?;
+    }
 
-    Ok((pipeline, framebuffers))
-}
+    fn load_scene(&mut self, _scene: &data::Scene, prepared: Self::Prepared) -> anyhow::Result<()> {
+        let (mut shown, future) = GpuScene::new(
+            prepared,
+            self.max_texture_dim,
+            self.premultiplied_alpha,
+            self.mesh_pipelines.base(),
+            &self.dummy_texture_desc_set,
+        )?;
+        if let Some(future) = future {
+            self.frames.wait_for(future);
+        }
+        if !self.texture_remaps.is_empty() {
+            match shown.remap_textures(
+                self.loader(),
+                &self.texture_remaps,
+                self.mesh_pipelines.base(),
+                &self.dummy_texture_desc_set,
+            ) {
+                Ok(Some(future)) => self.frames.wait_for(future),
+                Ok(None) => {}
+                Err(e) => error!("Failed to remap textures: {:#}", e),
+            }
+        }
+        self.shown = Some(shown);
+        Ok(())
+    }
 
-/// Camera.
-#[derive(Debug, Copy, Clone, PartialEq)]
-struct Camera {
-    /// Eye position.
-    pub position: Point3<f64>,
-    /// Yaw.
-    ///
-    /// Positive is clockwise.
-    pub yaw: Rad<f64>,
-    /// Pitch.
-    ///
-    /// Positive is up.
-    pub pitch: Rad<f64>,
-    /// Scale.
-    pub scale: f64,
-}
+    fn reload_normals(&mut self, scene: &data::Scene, recomputed: bool) -> anyhow::Result<()> {
+        let loader = self.loader();
+        let chunk_loader = self.loader();
+        let crease_angle = self.crease_angle;
+        let shown = match &mut self.shown {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        let recomputed = recomputed.then(|| recompute_normals(scene, crease_angle));
+        let geometries = match &recomputed {
+            Some(geometries) => geometries.iter().collect::<Vec<_>>(),
+            None => scene.geometry_meshes().collect(),
+        };
+        let future = loader
+            .reload_vertices(&mut shown.drawable_scene, geometries.iter().copied())
+            .context("Failed to upload normals")?;
+        if let Some(future) = future {
+            self.frames.wait_for(future);
+        }
+        if shown.tangent_frames.is_some() {
+            let uploaded = TangentFrames::upload(&geometries, self.queues.graphics.clone())?;
+            shown.tangent_frames = match uploaded {
+                Some((frames, future)) => {
+                    self.frames.wait_for(future);
+                    Some(frames)
+                }
+                None => None,
+            };
+        }
+        // The chunks have copies of the vertices.
+        if self.chunks {
+            let future = chunk_loader
+                .upload_chunks(&mut shown.drawable_scene, scene, geometries, self.lod)
+                .context("Failed to upload chunks")?;
+            if let Some(future) = future {
+                self.frames.wait_for(future);
+            }
+        }
+        shown
+            .draw_list
+            .rebuild(&shown.drawable_scene, &self.dummy_texture_desc_set)
+            .context("Failed to rebuild draw list")
+    }
 
-impl Camera {
-    /// Returns the forward direction vector.
-    fn forward() -> Vector3<f64> {
-        -Vector3::unit_z()
+    fn remap_textures(&mut self) -> anyhow::Result<()> {
+        if self.texture_remaps.is_empty() {
+            warn!("No texture remaps are specified (use `--remap-texture`)");
+            return Ok(());
+        }
+        let loader = self.loader();
+        let shown = match &mut self.shown {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        info!("Reloading remapped textures");
+        let future = shown.remap_textures(
+            loader,
+            &self.texture_remaps,
+            self.mesh_pipelines.base(),
+            &self.dummy_texture_desc_set,
+        )?;
+        if let Some(future) = future {
+            self.frames.wait_for(future);
+        }
+        Ok(())
     }
 
-    /// Returns the up direction vector.
-    fn up() -> Vector3<f64> {
-        Vector3::unit_y()
+    fn analyze_meshes(&mut self, scene: &data::Scene) -> anyhow::Result<()> {
+        let loader = self.loader();
+        let shown = match &mut self.shown {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        let geometries = &shown.drawable_scene.geometry_meshes;
+        if geometries
+            .iter()
+            .all(|geometry| geometry.topology.is_some())
+        {
+            return Ok(());
+        }
+        let topologies = {
+            let _span = debug_span!("analyze_topology").entered();
+            scene
+                .geometry_meshes()
+                .map(data::GeometryMesh::topology)
+                .collect::<Vec<_>>()
+        };
+        for (geometry, topology) in scene.geometry_meshes().zip(&topologies) {
+            if topology.is_watertight() && topology.duplicate_faces.is_empty() {
+                continue;
+            }
+            info!(
+                "Topology of geometry mesh {:?}: open edges = {}, non-manifold edges = {}, \
+                 duplicate faces = {}",
+                geometry.name,
+                topology.open_edges.len(),
+                topology.non_manifold_edges.len(),
+                topology.duplicate_faces.len()
+            );
+        }
+        let future = loader
+            .upload_topology(&mut shown.drawable_scene, &topologies)
+            .context("Failed to upload topology problems")?;
+        if let Some(future) = future {
+            self.frames.wait_for(future);
+        }
+        Ok(())
     }
 
-    /// Returns the right direction vector.
-    fn right() -> Vector3<f64> {
-        Vector3::unit_x()
+    fn show_tangent_frames(
+        &mut self,
+        scene: &data::Scene,
+        normals_recomputed: bool,
+    ) -> anyhow::Result<()> {
+        let crease_angle = self.crease_angle;
+        let shown = match &mut self.shown {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        if shown.tangent_frames.is_some() {
+            return Ok(());
+        }
+        let recomputed = normals_recomputed.then(|| recompute_normals(scene, crease_angle));
+        let geometries = match &recomputed {
+            Some(geometries) => geometries.iter().collect::<Vec<_>>(),
+            None => scene.geometry_meshes().collect(),
+        };
+        let uploaded = {
+            let _span = debug_span!("generate_tangent_frames").entered();
+            TangentFrames::upload(&geometries, self.queues.graphics.clone())?
+        };
+        if let Some((frames, future)) = uploaded {
+            self.frames.wait_for(future);
+            shown.tangent_frames = Some(frames);
+        }
+        Ok(())
     }
 
-    /// Creates a new `Camera` with the given initial position.
-    pub fn with_position(position: Point3<f64>) -> Self {
-        Self {
-            position,
-            yaw: Rad(0.0),
-            pitch: Rad(0.0),
-            scale: 1.0,
+    fn set_render_order(&mut self, mesh: MeshIndex, render_order: i32) -> anyhow::Result<()> {
+        let shown = match &mut self.shown {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        if !shown.drawable_scene.chunks.is_empty() {
+            bail!("Render orders of merged meshes cannot be changed (run without `--chunks`)");
         }
+        shown.drawable_scene.mesh_mut(mesh)?.render_order = render_order;
+        shown
+            .draw_list
+            .rebuild(&shown.drawable_scene, &self.dummy_texture_desc_set)
+            .context("Failed to rebuild draw list")
     }
 
-    /// Returns view matrix.
-    pub fn view(&self) -> Matrix4<f64> {
-        Matrix4::from_scale(self.scale)
-            * Matrix4::from(self.camera_direction().conjugate())
-            * Matrix4::from_translation(-self.position.to_vec())
+    fn set_mesh_transform(
+        &mut self,
+        mesh: MeshIndex,
+        transform: Option<Matrix4<f32>>,
+    ) -> anyhow::Result<()> {
+        let shown = match &mut self.shown {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        if !shown.drawable_scene.chunks.is_empty() {
+            bail!("Merged meshes cannot be transformed (run without `--chunks`)");
+        }
+        shown.drawable_scene.mesh(mesh)?;
+        shown.draw_list.set_transform(mesh.to_usize(), transform);
+        Ok(())
     }
 
-    /// Returns the direction the camera is looking at.
-    fn camera_direction(&self) -> Quaternion<f64> {
-        // Note that this is extrinsic rotation.
-        Quaternion::from_angle_y(self.yaw) * Quaternion::from_angle_x(self.pitch)
+    fn set_mesh_hidden(&mut self, mesh: MeshIndex, hidden: bool) -> anyhow::Result<()> {
+        let shown = match &mut self.shown {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        if !shown.drawable_scene.chunks.is_empty() {
+            bail!("Merged meshes cannot be hidden (run without `--chunks`)");
+        }
+        shown.drawable_scene.mesh(mesh)?;
+        shown.draw_list.set_hidden(mesh.to_usize(), hidden);
+        shown
+            .draw_list
+            .rebuild(&shown.drawable_scene, &self.dummy_texture_desc_set)
+            .context("Failed to rebuild draw list")
     }
 
-    /// Moves the camera.
-    pub fn move_rel(&mut self, vec: Vector3<f64>) {
-        self.position += self.camera_direction().rotate_vector(vec);
-        trace!("Camera = {:?}", self);
+    fn resize(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+        self.recreate_swapchain = true;
     }
 
-    /// Rotates the camera to up.
-    pub fn rotate_up(&mut self, angle: Rad<f64>) {
-        self.pitch = (self.pitch + angle).normalize_signed();
-        trace!("Camera = {:?}", self);
+    fn render(&mut self, camera: &Camera, settings: &RenderSettings) -> anyhow::Result<()> {
+        let _span = debug_span!("render").entered();
+        self.draw_frame(camera, settings, None).map(drop)
     }
 
-    /// Rotates the camera to right.
-    pub fn rotate_right(&mut self, angle: Rad<f64>) {
-        self.yaw = (self.yaw - angle).normalize_signed();
-        trace!("Camera = {:?}", self);
+    fn capture_frame(
+        &mut self,
+        camera: &Camera,
+        settings: &RenderSettings,
+    ) -> anyhow::Result<RgbaImage> {
+        // The swapchain images are copied as they are, so only 8-bit RGBA and
+        // BGRA formats are supported.
+        let bgra = match self.swapchain.format() {
+            Format::R8G8B8A8Unorm | Format::R8G8B8A8Srgb => false,
+            Format::B8G8R8A8Unorm | Format::B8G8R8A8Srgb => true,
+            format => bail!("Capturing frames of {:?} is not supported", format),
+        };
+        if self.recreate_swapchain && !self.recreate_swapchain()? {
+            bail!("No frame to capture (the window may be minimized)");
+        }
+        let [width, height] = self.dimensions;
+        let buffer = CpuAccessibleBuffer::from_iter(
+            self.device.clone(),
+            BufferUsage::transfer_destination(),
+            false,
+            (0..self.capture_len()).map(|_| [0u8; 4]),
+        )
+        .context("Failed to create the capture buffer")?;
+        if !self.draw_frame(camera, settings, Some(&buffer))? {
+            bail!("No frame to capture (the window may be minimized)");
+        }
+        self.frames
+            .wait_last()
+            .context("Failed to wait for the captured frame")?;
+
+        let pixels = buffer
+            .read()
+            .map_err(|e| anyhow!("Failed to read the captured frame: {}", e))?;
+        let raw = pixels
+            .iter()
+            .flat_map(|&[a, b, c, d]| if bgra { [c, b, a, d] } else { [a, b, c, d] })
+            .collect();
+        RgbaImage::from_raw(width, height, raw)
+            .ok_or_else(|| anyhow!("Captured frame has an unexpected size"))
     }
+
+    fn pick_ray(
+        &self,
+        camera: &Camera,
+        settings: &RenderSettings,
+        [x, y]: [f32; 2],
+    ) -> Option<Ray> {
+        let viewport = view::layout(settings.quad_view, settings.stereo, self.dimensions)
+            .into_iter()
+            .find(|(_, vp)| {
+                (vp.origin[0]..(vp.origin[0] + vp.dimensions[0])).contains(&x)
+                    && (vp.origin[1]..(vp.origin[1] + vp.dimensions[1])).contains(&y)
+            });
+        let viewport = match viewport {
+            Some((view::ViewKind::Perspective, vp)) => vp,
+            Some(_) => {
+                info!("Picking is available only in the perspective view");
+                return None;
+            }
+            None => return None,
+        };
+        let ndc = [
+            f64::from(2.0 * (x - viewport.origin[0]) / viewport.dimensions[0] - 1.0),
+            f64::from(1.0 - 2.0 * (y - viewport.origin[1]) / viewport.dimensions[1]),
+        ];
+        let aspect_ratio = f64::from(viewport.dimensions[0] / viewport.dimensions[1]);
+        Some(camera.ray(&PERSPECTIVE, ndc, aspect_ratio))
+    }
+
+    fn status(&self) -> String {
+        let mut status = format!(
+            "{}, {} of GPU memory",
+            self.render_stats,
+            format_bytes(self.shown.as_ref().map_or(0, |shown| shown.gpu_memory))
+        );
+        if let Some(timings) = &self.pass_timings {
+            status += &format!(", GPU time: {}", timings);
+        }
+        status
+    }
+}
+
+/// Scene uploaded to the GPU.
+struct GpuScene {
+    /// Drawable scene.
+    drawable_scene: drawable::Scene,
+    /// Bounding sphere.
+    bounding_sphere: BoundingSphere<f32>,
+    /// Estimated GPU memory usage.
+    gpu_memory: u64,
+    /// Draw list.
+    ///
+    /// Rebuilt only when the buffers or the textures of the scene are
+    /// replaced.
+    draw_list: DrawList,
+    /// Decoder of the textures which are not uploaded yet.
+    texture_stream: TextureStream,
+    /// Maximum width and height of the uploaded texture images.
+    max_texture_dim: Option<u32>,
+    /// How to find the texture images premultiplied by alpha.
+    premultiplied_alpha: PremultipliedAlpha,
+    /// Tangent frames, if generated.
+    tangent_frames: Option<TangentFrames>,
+}
+
+impl GpuScene {
+    /// Creates a new `GpuScene` from the uploaded scene.
+    ///
+    /// Texture images larger than `max_texture_dim` are downscaled, and
+    /// whether they are premultiplied by alpha is decided by
+    /// `premultiplied_alpha`.
+    ///
+    /// Returns the future to wait for before drawing the scene, if any.
+    #[allow(clippy::type_complexity)]
+    fn new<Mv, L, Rp>(
+        mut drawable_scene: drawable::Scene,
+        max_texture_dim: Option<u32>,
+        premultiplied_alpha: PremultipliedAlpha,
+        pipeline: &Arc<GraphicsPipeline<Mv, L, Rp>>,
+        dummy_texture_desc_set: &Arc<dyn DescriptorSet + Send + Sync>,
+    ) -> anyhow::Result<(Self, Option<Box<dyn GpuFuture>>)>
+    where
+        L: PipelineLayoutAbstract,
+    {
+        let bbox = drawable_scene
+            .bbox()
+            .bounding_box()
+            .ok_or_else(|| anyhow!("No data to show (bounding box is `None`)"))?;
+        let bounding_sphere = drawable_scene
+            .bounding_sphere()
+            .unwrap_or_else(|| (&bbox).into());
+        let gpu_memory = drawable_scene.estimated_memory();
+        info!(
+            "Estimated GPU memory for the scene = {}",
+            format_bytes(gpu_memory)
+        );
+        let future = drawable_scene.reset_cache_with_pipeline(pipeline)?;
+        let draw_list = DrawList::new(&drawable_scene, dummy_texture_desc_set)
+            .context("Failed to create draw list")?;
+        let texture_stream = TextureStream::new(max_texture_dim, premultiplied_alpha)?;
+
+        let shown = Self {
+            drawable_scene,
+            bounding_sphere,
+            gpu_memory,
+            draw_list,
+            texture_stream,
+            max_texture_dim,
+            premultiplied_alpha,
+            tangent_frames: None,
+        };
+        Ok((shown, future))
+    }
+
+    /// Replaces the texture images with the remapped image files.
+    ///
+    /// Returns the future to wait for before drawing the scene, if any.
+    fn remap_textures<Mv, L, Rp>(
+        &mut self,
+        loader: drawable::Loader,
+        remaps: &[TextureRemap],
+        pipeline: &Arc<GraphicsPipeline<Mv, L, Rp>>,
+        dummy_texture_desc_set: &Arc<dyn DescriptorSet + Send + Sync>,
+    ) -> anyhow::Result<Option<Box<dyn GpuFuture>>>
+    where
+        L: PipelineLayoutAbstract,
+    {
+        let upload_future = loader
+            .remap_textures(
+                &mut self.drawable_scene,
+                remaps,
+                self.max_texture_dim,
+                self.premultiplied_alpha,
+            )
+            .context("Failed to upload remapped textures")?;
+        let cache_future = self.drawable_scene.reset_cache_with_pipeline(pipeline)?;
+        self.draw_list
+            .rebuild(&self.drawable_scene, dummy_texture_desc_set)
+            .context("Failed to rebuild draw list")?;
+
+        Ok(match (upload_future, cache_future) {
+            (Some(a), Some(b)) => Some(a.join(b).boxed()),
+            (a, b) => a.or(b),
+        })
+    }
+
+    /// Requests decoding of the pending textures of the submeshes visible in
+    /// any of the views.
+    fn request_visible_textures(&mut self, frustums: &[Frustum<f32>]) {
+        for item in self.draw_list.items() {
+            let index = match item.pending_texture {
+                Some(v) => v,
+                None => continue,
+            };
+            let visible = self
+                .draw_list
+                .bounding_box(item)
+                .is_none_or(|bbox| frustums.iter().any(|frustum| frustum.intersects_box(&bbox)));
+            if !visible {
+                continue;
+            }
+            match self.drawable_scene.texture(index) {
+                Ok(texture) => self.texture_stream.request(index, texture),
+                Err(e) => warn!("Failed to get pending texture: {}", e),
+            }
+        }
+    }
+
+    /// Uploads the textures decoded since the last call, and rebuilds the
+    /// draw list if any.
+    ///
+    /// Returns the future to wait for before drawing the scene, if any.
+    fn upload_decoded_textures<Mv, L, Rp>(
+        &mut self,
+        loader: drawable::Loader,
+        pipeline: &Arc<GraphicsPipeline<Mv, L, Rp>>,
+        dummy_texture_desc_set: &Arc<dyn DescriptorSet + Send + Sync>,
+    ) -> anyhow::Result<Option<Box<dyn GpuFuture>>>
+    where
+        L: PipelineLayoutAbstract,
+    {
+        let images = self.texture_stream.take_decoded();
+        if images.is_empty() {
+            return Ok(None);
+        }
+        debug!("Uploading {} decoded textures", images.len());
+        let upload_future = loader
+            .upload_textures(&mut self.drawable_scene, images)
+            .context("Failed to upload decoded textures")?;
+        let cache_future = self.drawable_scene.reset_cache_with_pipeline(pipeline)?;
+        self.draw_list
+            .rebuild(&self.drawable_scene, dummy_texture_desc_set)
+            .context("Failed to rebuild draw list")?;
+        self.gpu_memory = self.drawable_scene.estimated_memory();
+
+        Ok(match (upload_future, cache_future) {
+            (Some(a), Some(b)) => Some(a.join(b).boxed()),
+            (a, b) => a.or(b),
+        })
+    }
+}
+
+/// Returns the geometry meshes of the scene with recomputed normals.
+pub(crate) fn recompute_normals(
+    scene: &data::Scene,
+    crease_angle: Rad<f32>,
+) -> Vec<data::GeometryMesh> {
+    scene
+        .geometry_meshes()
+        .cloned()
+        .map(|mut geometry| {
+            geometry.recompute_normals(crease_angle);
+            geometry
+        })
+        .collect()
 }
 
 pub mod vs {
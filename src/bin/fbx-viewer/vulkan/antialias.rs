@@ -0,0 +1,494 @@
+//! Anti-aliasing.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context};
+use fbx_viewer::AntiAliasing;
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, DynamicState, SubpassContents},
+    descriptor::{
+        descriptor_set::{DescriptorSet, PersistentDescriptorSet},
+        pipeline_layout::PipelineLayoutAbstract,
+    },
+    device::Device,
+    format::{ClearValue, Format},
+    framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract, Subpass},
+    image::{AttachmentImage, ImageViewAccess, SwapchainImage},
+    pipeline::{
+        vertex::{BufferlessDefinition, BufferlessVertices},
+        viewport::Viewport,
+        GraphicsPipeline,
+    },
+    sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode},
+};
+use winit::window::Window;
+
+use super::bloom::{self, BloomPass};
+
+/// Number of samples for MSAA.
+pub const MSAA_SAMPLES: u32 = 4;
+
+/// Render targets of the scene for the anti-aliasing method and the bloom.
+pub struct RenderTargets {
+    /// Device.
+    device: Arc<Device>,
+    /// Swapchain format.
+    format: Format,
+    /// Color format of the scene.
+    scene_format: Format,
+    /// Depth-stencil format.
+    depth_format: Format,
+    /// Anti-aliasing method.
+    method: AntiAliasing,
+    /// Render pass for the scene.
+    scene_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    /// Bloom pass, if enabled.
+    bloom: Option<BloomPass>,
+    /// FXAA pass, if enabled.
+    fxaa: Option<FxaaPass>,
+    /// Framebuffers for the scene, for each swapchain image.
+    scene_framebuffers: Vec<Arc<dyn FramebufferAbstract + Send + Sync>>,
+}
+
+impl RenderTargets {
+    /// Creates a new `RenderTargets` for the swapchain format and the
+    /// depth-stencil format.
+    ///
+    /// Bloom of the given intensity is added if `bloom` is `Some`.
+    ///
+    /// Framebuffers are not available until [`resize`][`Self::resize`] is
+    /// called.
+    pub fn new(
+        device: Arc<Device>,
+        format: Format,
+        depth_format: Format,
+        method: AntiAliasing,
+        bloom: Option<f32>,
+    ) -> anyhow::Result<Self> {
+        // With bloom, the scene is drawn in HDR so that the colors brighter
+        // than 1 are kept for the bright pass.
+        let scene_format = match bloom {
+            Some(_) => bloom::HDR_FORMAT,
+            None => format,
+        };
+        let scene_pass: Arc<dyn RenderPassAbstract + Send + Sync> = match method {
+            // For FXAA and bloom, the color is stored to an offscreen image
+            // read by the post-process passes.
+            AntiAliasing::None | AntiAliasing::Fxaa => Arc::new(
+                vulkano::single_pass_renderpass!(
+                    device.clone(),
+                    attachments: {
+                        color: {
+                            load: Clear,
+                            store: Store,
+                            format: scene_format,
+                            samples: 1,
+                        },
+                        depth: {
+                            load: Clear,
+                            store: DontCare,
+                            format: depth_format,
+                            samples: 1,
+                        }
+                    },
+                    pass: {
+                        color: [color],
+                        depth_stencil: {depth}
+                    }
+                )
+                .context("Failed to create render pass")?,
+            ),
+            AntiAliasing::Msaa4 => {
+                let limits = device.physical_device().limits();
+                let supported = limits.framebuffer_color_sample_counts()
+                    & limits.framebuffer_depth_sample_counts();
+                if supported & MSAA_SAMPLES == 0 {
+                    bail!("{}x MSAA is not supported by the device", MSAA_SAMPLES);
+                }
+                Arc::new(
+                    vulkano::single_pass_renderpass!(
+                        device.clone(),
+                        attachments: {
+                            multisampled_color: {
+                                load: Clear,
+                                store: DontCare,
+                                format: scene_format,
+                                samples: MSAA_SAMPLES,
+                            },
+                            color: {
+                                load: DontCare,
+                                store: Store,
+                                format: scene_format,
+                                samples: 1,
+                            },
+                            depth: {
+                                load: Clear,
+                                store: DontCare,
+                                format: depth_format,
+                                samples: MSAA_SAMPLES,
+                            }
+                        },
+                        pass: {
+                            color: [multisampled_color],
+                            depth_stencil: {depth},
+                            resolve: [color],
+                        }
+                    )
+                    .context("Failed to create render pass")?,
+                )
+            }
+        };
+        let fxaa = match method {
+            AntiAliasing::Fxaa => {
+                Some(FxaaPass::new(device.clone(), format).context("Failed to set up FXAA pass")?)
+            }
+            _ => None,
+        };
+        // Bloom writes to the input of FXAA, which is in the swapchain format.
+        let bloom = bloom
+            .map(|intensity| BloomPass::new(device.clone(), format, intensity))
+            .transpose()
+            .context("Failed to set up bloom pass")?;
+
+        Ok(Self {
+            device,
+            format,
+            scene_format,
+            depth_format,
+            method,
+            scene_pass,
+            bloom,
+            fxaa,
+            scene_framebuffers: Vec::new(),
+        })
+    }
+
+    /// Returns the render pass for the scene.
+    ///
+    /// Pipelines for the scene should be created for the first subpass of it.
+    pub fn scene_pass(&self) -> Arc<dyn RenderPassAbstract + Send + Sync> {
+        self.scene_pass.clone()
+    }
+
+    /// Recreates the framebuffers for the swapchain images.
+    pub fn resize(&mut self, images: &[Arc<SwapchainImage<Window>>]) -> anyhow::Result<()> {
+        let device = &self.device;
+        let dimensions = SwapchainImage::dimensions(&images[0]);
+        let depth_samples = match self.method {
+            AntiAliasing::Msaa4 => MSAA_SAMPLES,
+            _ => 1,
+        };
+        let depth_buffer = AttachmentImage::transient_multisampled(
+            device.clone(),
+            dimensions,
+            depth_samples,
+            self.depth_format,
+        )
+        .context("Failed to create depth buffer")?;
+
+        // Post-process passes read the color of the scene from an offscreen
+        // image, and the last of them writes to the swapchain images.
+        let color_targets: Vec<Arc<dyn ImageViewAccess + Send + Sync>> = if self.bloom.is_some()
+            || self.fxaa.is_some()
+        {
+            let scene_image =
+                AttachmentImage::sampled(device.clone(), dimensions, self.scene_format)
+                    .context("Failed to create offscreen color buffer")?;
+            let mut fxaa_input = scene_image.clone();
+            if let Some(bloom) = &mut self.bloom {
+                let outputs = if self.fxaa.is_some() {
+                    fxaa_input = AttachmentImage::sampled(device.clone(), dimensions, self.format)
+                        .context("Failed to create bloom output buffer")?;
+                    vec![fxaa_input.clone() as Arc<dyn ImageViewAccess + Send + Sync>; images.len()]
+                } else {
+                    swapchain_attachments(images)
+                };
+                bloom.resize(scene_image.clone(), outputs)?;
+            }
+            if let Some(fxaa) = &mut self.fxaa {
+                fxaa.resize(fxaa_input, images)?;
+            }
+            vec![scene_image; images.len()]
+        } else {
+            swapchain_attachments(images)
+        };
+
+        let multisampled_color = match self.method {
+            AntiAliasing::Msaa4 => Some(
+                AttachmentImage::transient_multisampled(
+                    device.clone(),
+                    dimensions,
+                    MSAA_SAMPLES,
+                    self.scene_format,
+                )
+                .context("Failed to create multisampled color buffer")?,
+            ),
+            AntiAliasing::None | AntiAliasing::Fxaa => None,
+        };
+        self.scene_framebuffers = color_targets
+            .into_iter()
+            .map(|color| {
+                let framebuffer = match &multisampled_color {
+                    // The multisampled color is resolved to the color target.
+                    Some(multisampled_color) => Arc::new(
+                        Framebuffer::start(self.scene_pass.clone())
+                            .add(multisampled_color.clone())
+                            .context("Failed to add a multisampled color buffer to framebuffer")?
+                            .add(color)
+                            .context("Failed to add a color buffer to framebuffer")?
+                            .add(depth_buffer.clone())
+                            .context("Failed to add a depth buffer to framebuffer")?
+                            .build()
+                            .context("Failed to create framebuffer")?,
+                    )
+                        as Arc<dyn FramebufferAbstract + Send + Sync>,
+                    None => Arc::new(
+                        Framebuffer::start(self.scene_pass.clone())
+                            .add(color)
+                            .context("Failed to add a color buffer to framebuffer")?
+                            .add(depth_buffer.clone())
+                            .context("Failed to add a depth buffer to framebuffer")?
+                            .build()
+                            .context("Failed to create framebuffer")?,
+                    ),
+                };
+                Ok(framebuffer)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(())
+    }
+
+    /// Begins the render pass for the scene.
+    pub fn begin_scene(
+        &self,
+        builder: &mut AutoCommandBufferBuilder,
+        image_num: usize,
+        clear_color: [f32; 4],
+    ) -> anyhow::Result<()> {
+        let depth_stencil = ClearValue::DepthStencil((1.0, 0));
+        let clear_values = match self.method {
+            AntiAliasing::None | AntiAliasing::Fxaa => vec![clear_color.into(), depth_stencil],
+            AntiAliasing::Msaa4 => vec![clear_color.into(), ClearValue::None, depth_stencil],
+        };
+        builder
+            .begin_render_pass(
+                self.scene_framebuffers[image_num].clone(),
+                SubpassContents::Inline,
+                clear_values,
+            )
+            .context("Failed to begin the render pass for the scene")?;
+
+        Ok(())
+    }
+
+    /// Ends the render pass for the scene, and applies the post-process pass
+    /// if necessary.
+    pub fn end_scene(
+        &self,
+        builder: &mut AutoCommandBufferBuilder,
+        image_num: usize,
+        dimensions: [u32; 2],
+    ) -> anyhow::Result<()> {
+        builder
+            .end_render_pass()
+            .context("Failed to end the render pass for the scene")?;
+        if let Some(bloom) = &self.bloom {
+            bloom.draw(builder, image_num, dimensions)?;
+        }
+        if let Some(fxaa) = &self.fxaa {
+            fxaa.draw(builder, image_num, dimensions)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the swapchain images as attachments.
+fn swapchain_attachments(
+    images: &[Arc<SwapchainImage<Window>>],
+) -> Vec<Arc<dyn ImageViewAccess + Send + Sync>> {
+    images
+        .iter()
+        .map(|image| image.clone() as Arc<dyn ImageViewAccess + Send + Sync>)
+        .collect()
+}
+
+/// FXAA post-process pass.
+struct FxaaPass {
+    /// Render pass.
+    render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    /// Pipeline.
+    #[allow(clippy::type_complexity)]
+    pipeline: Arc<
+        GraphicsPipeline<
+            BufferlessDefinition,
+            Box<dyn PipelineLayoutAbstract + Send + Sync>,
+            Arc<dyn RenderPassAbstract + Send + Sync>,
+        >,
+    >,
+    /// Sampler for the scene image.
+    sampler: Arc<Sampler>,
+    /// Descriptor set for the scene image.
+    desc_set: Option<Arc<dyn DescriptorSet + Send + Sync>>,
+    /// Framebuffers for the swapchain images.
+    framebuffers: Vec<Arc<dyn FramebufferAbstract + Send + Sync>>,
+}
+
+impl FxaaPass {
+    /// Creates a new `FxaaPass` which writes to the swapchain images.
+    fn new(device: Arc<Device>, format: Format) -> anyhow::Result<Self> {
+        let render_pass: Arc<dyn RenderPassAbstract + Send + Sync> = Arc::new(
+            vulkano::single_pass_renderpass!(
+                device.clone(),
+                attachments: {
+                    color: {
+                        load: DontCare,
+                        store: Store,
+                        format: format,
+                        samples: 1,
+                    }
+                },
+                pass: {
+                    color: [color],
+                    depth_stencil: {}
+                }
+            )
+            .context("Failed to create FXAA render pass")?,
+        );
+
+        let vs =
+            vs::Shader::load(device.clone()).context("Failed to load fullscreen vertex shader")?;
+        let fs = fs::Shader::load(device.clone()).context("Failed to load FXAA fragment shader")?;
+        let pipeline = GraphicsPipeline::start()
+            .vertex_input(BufferlessDefinition)
+            .vertex_shader(vs.main_entry_point(), ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs.main_entry_point(), ())
+            .render_pass(
+                Subpass::from(render_pass.clone(), 0)
+                    .ok_or_else(|| anyhow!("Failed to create subpass"))?,
+            )
+            .build(device.clone())
+            .map(Arc::new)
+            .context("Failed to create FXAA pipeline")?;
+
+        let sampler = Sampler::new(
+            device,
+            Filter::Linear,
+            Filter::Linear,
+            MipmapMode::Nearest,
+            SamplerAddressMode::ClampToEdge,
+            SamplerAddressMode::ClampToEdge,
+            SamplerAddressMode::ClampToEdge,
+            0.0,
+            1.0,
+            0.0,
+            0.0,
+        )
+        .context("Failed to create sampler")?;
+
+        Ok(Self {
+            render_pass,
+            pipeline,
+            sampler,
+            desc_set: None,
+            framebuffers: Vec::new(),
+        })
+    }
+
+    /// Recreates the descriptor set and the framebuffers.
+    fn resize(
+        &mut self,
+        scene_image: Arc<AttachmentImage>,
+        images: &[Arc<SwapchainImage<Window>>],
+    ) -> anyhow::Result<()> {
+        let layout = self
+            .pipeline
+            .layout()
+            .descriptor_set_layout(0)
+            .context("Failed to get the first descriptor set layout of the FXAA pipeline")?;
+        let desc_set = PersistentDescriptorSet::start(layout.clone())
+            .add_sampled_image(scene_image, self.sampler.clone())
+            .context("Failed to add sampled image to descriptor set")?
+            .build()
+            .context("Failed to build descriptor set")?;
+        self.desc_set = Some(Arc::new(desc_set));
+
+        self.framebuffers = images
+            .iter()
+            .map(|image| {
+                Framebuffer::start(self.render_pass.clone())
+                    .add(image.clone())
+                    .context("Failed to add a swapchain image to framebuffer")?
+                    .build()
+                    .map(|fb| Arc::new(fb) as Arc<dyn FramebufferAbstract + Send + Sync>)
+                    .context("Failed to create framebuffer")
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(())
+    }
+
+    /// Draws the anti-aliased scene image to the swapchain image.
+    fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder,
+        image_num: usize,
+        dimensions: [u32; 2],
+    ) -> anyhow::Result<()> {
+        let desc_set = self
+            .desc_set
+            .clone()
+            .expect("Should never fail: FXAA pass should be resized before drawing");
+        let dynamic_state = DynamicState {
+            viewports: Some(vec![Viewport {
+                origin: [0.0, 0.0],
+                dimensions: [dimensions[0] as f32, dimensions[1] as f32],
+                depth_range: 0.0..1.0,
+            }]),
+            ..DynamicState::none()
+        };
+
+        builder
+            .begin_render_pass(
+                self.framebuffers[image_num].clone(),
+                SubpassContents::Inline,
+                vec![ClearValue::None],
+            )
+            .context("Failed to begin FXAA render pass")?
+            .draw(
+                self.pipeline.clone(),
+                &dynamic_state,
+                BufferlessVertices {
+                    vertices: 3,
+                    instances: 1,
+                },
+                desc_set,
+                (),
+                std::iter::empty(),
+            )
+            .context("Failed to draw FXAA pass")?
+            .end_render_pass()
+            .context("Failed to end FXAA render pass")?;
+
+        Ok(())
+    }
+}
+
+/// Fullscreen vertex shader.
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "src/bin/fbx-viewer/shaders/fullscreen.vert",
+    }
+}
+
+/// FXAA fragment shader.
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/bin/fbx-viewer/shaders/fxaa.frag",
+    }
+}
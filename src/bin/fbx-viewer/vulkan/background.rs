@@ -0,0 +1,121 @@
+//! Background.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use fbx_viewer::Background;
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, DynamicState},
+    descriptor::pipeline_layout::PipelineLayoutAbstract,
+    device::Device,
+    framebuffer::{RenderPassAbstract, Subpass},
+    pipeline::{
+        vertex::{BufferlessDefinition, BufferlessVertices},
+        GraphicsPipeline,
+    },
+};
+
+/// Returns the clear value of the color attachment for the background.
+pub fn clear_color(background: &Background) -> [f32; 4] {
+    let color = background.top;
+    [color.r, color.g, color.b, 1.0]
+}
+
+/// Pipeline to draw gradient backgrounds.
+pub struct BackgroundPipeline {
+    /// Pipeline.
+    #[allow(clippy::type_complexity)]
+    pipeline: Arc<
+        GraphicsPipeline<
+            BufferlessDefinition,
+            Box<dyn PipelineLayoutAbstract + Send + Sync>,
+            Arc<dyn RenderPassAbstract + Send + Sync>,
+        >,
+    >,
+}
+
+impl BackgroundPipeline {
+    /// Creates a new `BackgroundPipeline` for the first subpass of the render
+    /// pass.
+    pub fn new(
+        device: Arc<Device>,
+        render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    ) -> anyhow::Result<Self> {
+        let vs =
+            vs::Shader::load(device.clone()).context("Failed to load background vertex shader")?;
+        let fs = fs::Shader::load(device.clone())
+            .context("Failed to load background fragment shader")?;
+
+        // Depth test is disabled, so the background never occludes the scene.
+        let pipeline = GraphicsPipeline::start()
+            .vertex_input(BufferlessDefinition)
+            .vertex_shader(vs.main_entry_point(), ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs.main_entry_point(), ())
+            .render_pass(
+                Subpass::from(render_pass, 0).ok_or_else(|| anyhow!("Failed to create subpass"))?,
+            )
+            .build(device)
+            .map(Arc::new)
+            .context("Failed to create background pipeline")?;
+
+        Ok(Self { pipeline })
+    }
+
+    /// Draws the background to the viewport.
+    ///
+    /// Nothing is drawn for a solid background, since the clear value already
+    /// fills the viewport.
+    pub fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder,
+        dynamic_state: &DynamicState,
+        background: &Background,
+    ) -> anyhow::Result<()> {
+        if background.is_solid() {
+            return Ok(());
+        }
+
+        let colors = fs::ty::PushConstants {
+            top: [background.top.r, background.top.g, background.top.b, 1.0],
+            bottom: [
+                background.bottom.r,
+                background.bottom.g,
+                background.bottom.b,
+                1.0,
+            ],
+        };
+        builder
+            .draw(
+                self.pipeline.clone(),
+                dynamic_state,
+                BufferlessVertices {
+                    vertices: 3,
+                    instances: 1,
+                },
+                (),
+                colors,
+                std::iter::empty(),
+            )
+            .context("Failed to draw background")?;
+
+        Ok(())
+    }
+}
+
+/// Vertex shader.
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "src/bin/fbx-viewer/shaders/fullscreen.vert",
+    }
+}
+
+/// Fragment shader.
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/bin/fbx-viewer/shaders/background.frag",
+    }
+}
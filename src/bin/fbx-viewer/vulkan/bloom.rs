@@ -0,0 +1,431 @@
+//! Bloom.
+//!
+//! The bright parts of the scene image are extracted to a half-sized image,
+//! blurred, and added to the scene image.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, DynamicState, SubpassContents},
+    descriptor::{
+        descriptor_set::{DescriptorSet, PersistentDescriptorSet, UnsafeDescriptorSetLayout},
+        pipeline_layout::PipelineLayoutAbstract,
+    },
+    device::Device,
+    format::{ClearValue, Format},
+    framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract, Subpass},
+    image::{AttachmentImage, ImageViewAccess},
+    pipeline::{
+        vertex::{BufferlessDefinition, BufferlessVertices},
+        viewport::Viewport,
+        GraphicsPipeline,
+    },
+    sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode},
+};
+
+/// Format of the scene image and the bloom images, which keeps the colors
+/// brighter than 1.
+pub const HDR_FORMAT: Format = Format::R16G16B16A16Sfloat;
+
+/// Brightness above which the colors contribute to the bloom.
+const THRESHOLD: f32 = 0.8;
+
+/// Number of the pairs of the horizontal and vertical blur passes.
+const BLUR_ITERATIONS: usize = 2;
+
+/// Pipeline drawing a fullscreen triangle.
+type FullscreenPipeline = Arc<
+    GraphicsPipeline<
+        BufferlessDefinition,
+        Box<dyn PipelineLayoutAbstract + Send + Sync>,
+        Arc<dyn RenderPassAbstract + Send + Sync>,
+    >,
+>;
+
+/// Images and descriptor sets for the current swapchain size.
+struct Targets {
+    /// Size of the bloom images.
+    bloom_dimensions: [u32; 2],
+    /// Framebuffer of the bloom image.
+    bloom_framebuffer: Arc<dyn FramebufferAbstract + Send + Sync>,
+    /// Framebuffer of the image for the horizontal blur.
+    blur_framebuffer: Arc<dyn FramebufferAbstract + Send + Sync>,
+    /// Descriptor set to read the scene image in the bright pass.
+    bright_set: Arc<dyn DescriptorSet + Send + Sync>,
+    /// Descriptor set to read the bloom image in the horizontal blur pass.
+    blur_h_set: Arc<dyn DescriptorSet + Send + Sync>,
+    /// Descriptor set to read the horizontally blurred image in the vertical
+    /// blur pass.
+    blur_v_set: Arc<dyn DescriptorSet + Send + Sync>,
+    /// Descriptor set to read the scene image and the bloom image in the
+    /// composite pass.
+    composite_set: Arc<dyn DescriptorSet + Send + Sync>,
+    /// Framebuffers of the output images, for each swapchain image.
+    output_framebuffers: Vec<Arc<dyn FramebufferAbstract + Send + Sync>>,
+}
+
+/// Bloom post-process pass.
+pub struct BloomPass {
+    /// Device.
+    device: Arc<Device>,
+    /// Intensity of the bloom added to the scene.
+    intensity: f32,
+    /// Render pass for the bloom images.
+    bloom_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    /// Render pass for the output images.
+    output_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    /// Pipeline to extract the bright parts of the scene.
+    bright_pipeline: FullscreenPipeline,
+    /// Pipeline to blur the bloom images.
+    blur_pipeline: FullscreenPipeline,
+    /// Pipeline to add the bloom to the scene.
+    composite_pipeline: FullscreenPipeline,
+    /// Sampler for the images.
+    sampler: Arc<Sampler>,
+    /// Targets, available after resize.
+    targets: Option<Targets>,
+}
+
+impl BloomPass {
+    /// Creates a new `BloomPass` which writes to images of the given format.
+    pub fn new(device: Arc<Device>, output_format: Format, intensity: f32) -> anyhow::Result<Self> {
+        let bloom_pass =
+            color_pass(device.clone(), HDR_FORMAT).context("Failed to create bloom render pass")?;
+        let output_pass = color_pass(device.clone(), output_format)
+            .context("Failed to create bloom composite render pass")?;
+
+        let vs =
+            vs::Shader::load(device.clone()).context("Failed to load fullscreen vertex shader")?;
+        let bright_fs = bright_fs::Shader::load(device.clone())
+            .context("Failed to load bloom bright-pass fragment shader")?;
+        let blur_fs = blur_fs::Shader::load(device.clone())
+            .context("Failed to load bloom blur fragment shader")?;
+        let composite_fs = composite_fs::Shader::load(device.clone())
+            .context("Failed to load bloom composite fragment shader")?;
+
+        let bright_pipeline = GraphicsPipeline::start()
+            .vertex_input(BufferlessDefinition)
+            .vertex_shader(vs.main_entry_point(), ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(bright_fs.main_entry_point(), ())
+            .render_pass(
+                Subpass::from(bloom_pass.clone(), 0)
+                    .ok_or_else(|| anyhow!("Failed to create subpass"))?,
+            )
+            .build(device.clone())
+            .map(Arc::new)
+            .context("Failed to create bloom bright-pass pipeline")?;
+        let blur_pipeline = GraphicsPipeline::start()
+            .vertex_input(BufferlessDefinition)
+            .vertex_shader(vs.main_entry_point(), ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(blur_fs.main_entry_point(), ())
+            .render_pass(
+                Subpass::from(bloom_pass.clone(), 0)
+                    .ok_or_else(|| anyhow!("Failed to create subpass"))?,
+            )
+            .build(device.clone())
+            .map(Arc::new)
+            .context("Failed to create bloom blur pipeline")?;
+        let composite_pipeline = GraphicsPipeline::start()
+            .vertex_input(BufferlessDefinition)
+            .vertex_shader(vs.main_entry_point(), ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(composite_fs.main_entry_point(), ())
+            .render_pass(
+                Subpass::from(output_pass.clone(), 0)
+                    .ok_or_else(|| anyhow!("Failed to create subpass"))?,
+            )
+            .build(device.clone())
+            .map(Arc::new)
+            .context("Failed to create bloom composite pipeline")?;
+
+        let sampler = Sampler::new(
+            device.clone(),
+            Filter::Linear,
+            Filter::Linear,
+            MipmapMode::Nearest,
+            SamplerAddressMode::ClampToEdge,
+            SamplerAddressMode::ClampToEdge,
+            SamplerAddressMode::ClampToEdge,
+            0.0,
+            1.0,
+            0.0,
+            0.0,
+        )
+        .context("Failed to create sampler")?;
+
+        Ok(Self {
+            device,
+            intensity,
+            bloom_pass,
+            output_pass,
+            bright_pipeline,
+            blur_pipeline,
+            composite_pipeline,
+            sampler,
+            targets: None,
+        })
+    }
+
+    /// Recreates the bloom images, the descriptor sets, and the framebuffers.
+    ///
+    /// `outputs` are the images to write the scene with the bloom to, for
+    /// each swapchain image.
+    pub fn resize(
+        &mut self,
+        scene_image: Arc<AttachmentImage>,
+        outputs: Vec<Arc<dyn ImageViewAccess + Send + Sync>>,
+    ) -> anyhow::Result<()> {
+        let dimensions = AttachmentImage::dimensions(&scene_image);
+        let bloom_dimensions = [(dimensions[0] / 2).max(1), (dimensions[1] / 2).max(1)];
+        let bloom_image =
+            AttachmentImage::sampled(self.device.clone(), bloom_dimensions, HDR_FORMAT)
+                .context("Failed to create bloom image")?;
+        let blur_image =
+            AttachmentImage::sampled(self.device.clone(), bloom_dimensions, HDR_FORMAT)
+                .context("Failed to create bloom blur image")?;
+
+        let bright_set = self.sampled_set(&self.bright_pipeline, scene_image.clone())?;
+        let blur_h_set = self.sampled_set(&self.blur_pipeline, bloom_image.clone())?;
+        let blur_v_set = self.sampled_set(&self.blur_pipeline, blur_image.clone())?;
+        let composite_set = Arc::new(
+            PersistentDescriptorSet::start(descriptor_set_layout(&self.composite_pipeline)?)
+                .add_sampled_image(scene_image, self.sampler.clone())
+                .context("Failed to add scene image to descriptor set")?
+                .add_sampled_image(bloom_image.clone(), self.sampler.clone())
+                .context("Failed to add bloom image to descriptor set")?
+                .build()
+                .context("Failed to build descriptor set")?,
+        );
+
+        let bloom_framebuffer = framebuffer(self.bloom_pass.clone(), bloom_image)?;
+        let blur_framebuffer = framebuffer(self.bloom_pass.clone(), blur_image)?;
+        let output_framebuffers = outputs
+            .into_iter()
+            .map(|image| framebuffer(self.output_pass.clone(), image))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        self.targets = Some(Targets {
+            bloom_dimensions,
+            bloom_framebuffer,
+            blur_framebuffer,
+            bright_set,
+            blur_h_set,
+            blur_v_set,
+            composite_set,
+            output_framebuffers,
+        });
+
+        Ok(())
+    }
+
+    /// Creates a descriptor set for the pipeline, which samples the image.
+    fn sampled_set(
+        &self,
+        pipeline: &FullscreenPipeline,
+        image: Arc<AttachmentImage>,
+    ) -> anyhow::Result<Arc<dyn DescriptorSet + Send + Sync>> {
+        let desc_set = PersistentDescriptorSet::start(descriptor_set_layout(pipeline)?)
+            .add_sampled_image(image, self.sampler.clone())
+            .context("Failed to add sampled image to descriptor set")?
+            .build()
+            .context("Failed to build descriptor set")?;
+
+        Ok(Arc::new(desc_set))
+    }
+
+    /// Draws the scene image with the bloom to the output image.
+    pub fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder,
+        image_num: usize,
+        dimensions: [u32; 2],
+    ) -> anyhow::Result<()> {
+        let targets = self
+            .targets
+            .as_ref()
+            .expect("Should never fail: bloom pass should be resized before drawing");
+        let [width, height] = targets.bloom_dimensions;
+        let bloom_state = dynamic_state(targets.bloom_dimensions);
+
+        draw_fullscreen(
+            builder,
+            targets.bloom_framebuffer.clone(),
+            self.bright_pipeline.clone(),
+            &bloom_state,
+            targets.bright_set.clone(),
+            bright_fs::ty::PushConstants {
+                threshold: THRESHOLD,
+            },
+        )
+        .context("Failed to draw bloom bright pass")?;
+        for _ in 0..BLUR_ITERATIONS {
+            draw_fullscreen(
+                builder,
+                targets.blur_framebuffer.clone(),
+                self.blur_pipeline.clone(),
+                &bloom_state,
+                targets.blur_h_set.clone(),
+                blur_fs::ty::PushConstants {
+                    step: [1.0 / width as f32, 0.0],
+                },
+            )
+            .context("Failed to draw bloom horizontal blur pass")?;
+            draw_fullscreen(
+                builder,
+                targets.bloom_framebuffer.clone(),
+                self.blur_pipeline.clone(),
+                &bloom_state,
+                targets.blur_v_set.clone(),
+                blur_fs::ty::PushConstants {
+                    step: [0.0, 1.0 / height as f32],
+                },
+            )
+            .context("Failed to draw bloom vertical blur pass")?;
+        }
+        draw_fullscreen(
+            builder,
+            targets.output_framebuffers[image_num].clone(),
+            self.composite_pipeline.clone(),
+            &dynamic_state(dimensions),
+            targets.composite_set.clone(),
+            composite_fs::ty::PushConstants {
+                intensity: self.intensity,
+            },
+        )
+        .context("Failed to draw bloom composite pass")?;
+
+        Ok(())
+    }
+}
+
+/// Creates a render pass writing to a color image of the format.
+fn color_pass(
+    device: Arc<Device>,
+    format: Format,
+) -> anyhow::Result<Arc<dyn RenderPassAbstract + Send + Sync>> {
+    let render_pass = vulkano::single_pass_renderpass!(
+        device,
+        attachments: {
+            color: {
+                load: DontCare,
+                store: Store,
+                format: format,
+                samples: 1,
+            }
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {}
+        }
+    )?;
+
+    Ok(Arc::new(render_pass))
+}
+
+/// Creates a framebuffer of the image for the render pass.
+fn framebuffer<I>(
+    render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    image: I,
+) -> anyhow::Result<Arc<dyn FramebufferAbstract + Send + Sync>>
+where
+    I: ImageViewAccess + Send + Sync + 'static,
+{
+    let framebuffer = Framebuffer::start(render_pass)
+        .add(image)
+        .context("Failed to add an image to framebuffer")?
+        .build()
+        .context("Failed to create framebuffer")?;
+
+    Ok(Arc::new(framebuffer))
+}
+
+/// Returns the first descriptor set layout of the pipeline.
+fn descriptor_set_layout(
+    pipeline: &FullscreenPipeline,
+) -> anyhow::Result<Arc<UnsafeDescriptorSetLayout>> {
+    pipeline
+        .layout()
+        .descriptor_set_layout(0)
+        .cloned()
+        .context("Failed to get the first descriptor set layout of the bloom pipeline")
+}
+
+/// Returns the dynamic state with the viewport of the size.
+fn dynamic_state(dimensions: [u32; 2]) -> DynamicState {
+    DynamicState {
+        viewports: Some(vec![Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [dimensions[0] as f32, dimensions[1] as f32],
+            depth_range: 0.0..1.0,
+        }]),
+        ..DynamicState::none()
+    }
+}
+
+/// Draws a fullscreen triangle with the pipeline to the framebuffer.
+fn draw_fullscreen<Pc>(
+    builder: &mut AutoCommandBufferBuilder,
+    framebuffer: Arc<dyn FramebufferAbstract + Send + Sync>,
+    pipeline: FullscreenPipeline,
+    dynamic_state: &DynamicState,
+    desc_set: Arc<dyn DescriptorSet + Send + Sync>,
+    constants: Pc,
+) -> anyhow::Result<()> {
+    builder
+        .begin_render_pass(framebuffer, SubpassContents::Inline, vec![ClearValue::None])
+        .context("Failed to begin render pass")?
+        .draw(
+            pipeline,
+            dynamic_state,
+            BufferlessVertices {
+                vertices: 3,
+                instances: 1,
+            },
+            desc_set,
+            constants,
+            std::iter::empty(),
+        )
+        .context("Failed to draw")?
+        .end_render_pass()
+        .context("Failed to end render pass")?;
+
+    Ok(())
+}
+
+/// Fullscreen vertex shader.
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "src/bin/fbx-viewer/shaders/fullscreen.vert",
+    }
+}
+
+/// Bright-pass fragment shader.
+mod bright_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/bin/fbx-viewer/shaders/bloom_bright.frag",
+    }
+}
+
+/// Blur fragment shader.
+mod blur_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/bin/fbx-viewer/shaders/bloom_blur.frag",
+    }
+}
+
+/// Composite fragment shader.
+mod composite_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/bin/fbx-viewer/shaders/bloom_composite.frag",
+    }
+}
@@ -0,0 +1,152 @@
+//! Device capabilities.
+//!
+//! Only the device features the renderer uses are enabled, and the options
+//! and scenes needing more than the device supports are downgraded or
+//! reported with readable errors before they fail inside Vulkan calls.
+
+use std::sync::Arc;
+
+use anyhow::bail;
+use fbx_viewer::{data, AntiAliasing};
+use tracing::{info, warn};
+use vulkano::{
+    device::{Device, Features},
+    instance::PhysicalDevice,
+};
+
+use crate::vulkan::antialias::MSAA_SAMPLES;
+
+/// Largest index value guaranteed by Vulkan without the
+/// `full_draw_index_uint32` feature.
+const GUARANTEED_MAX_DRAW_INDEX: u32 = (1 << 24) - 1;
+
+/// Returns the features to enable on the device.
+///
+/// The optional features are enabled if supported, and the renderer checks
+/// [`Capabilities`] before using them.
+pub fn device_features(physical: PhysicalDevice<'_>) -> Features {
+    let supported = physical.supported_features();
+    let features = Features {
+        // Wireframes and wireframe overlays.
+        fill_mode_non_solid: supported.fill_mode_non_solid,
+        // Meshes with more than 2^24 vertices.
+        full_draw_index_uint32: supported.full_draw_index_uint32,
+        ..Features::none()
+    };
+    for (name, enabled) in [
+        ("fill_mode_non_solid", features.fill_mode_non_solid),
+        ("full_draw_index_uint32", features.full_draw_index_uint32),
+    ]
+    .iter()
+    {
+        if !enabled {
+            info!("Device feature `{}` is not supported", name);
+        }
+    }
+
+    features
+}
+
+/// Features and limits of the device used by the renderer.
+#[derive(Debug, Clone, Copy)]
+pub struct Capabilities {
+    /// Whether wireframes (non-solid polygon modes) are supported.
+    pub wireframe: bool,
+    /// Sample counts supported by both color and depth attachments, as a bit
+    /// mask.
+    pub sample_counts: u32,
+    /// Maximum width and height of 2D images.
+    pub max_image_dimension_2d: u32,
+    /// Maximum index value of indexed draws.
+    pub max_draw_index: u32,
+}
+
+impl Capabilities {
+    /// Collects the capabilities of the device with the enabled features.
+    pub fn new(device: &Arc<Device>) -> Self {
+        let features = device.enabled_features();
+        let limits = device.physical_device().limits();
+        let max_draw_index = if features.full_draw_index_uint32 {
+            limits.max_draw_indexed_index_value()
+        } else {
+            limits
+                .max_draw_indexed_index_value()
+                .min(GUARANTEED_MAX_DRAW_INDEX)
+        };
+        let capabilities = Self {
+            wireframe: features.fill_mode_non_solid,
+            sample_counts: limits.framebuffer_color_sample_counts()
+                & limits.framebuffer_depth_sample_counts(),
+            max_image_dimension_2d: limits.max_image_dimension_2d(),
+            max_draw_index,
+        };
+        info!("Device capabilities: {:?}", capabilities);
+
+        capabilities
+    }
+
+    /// Returns the anti-aliasing method to use for the requested one.
+    ///
+    /// Falls back to FXAA if MSAA is not supported.
+    pub fn anti_aliasing(&self, requested: AntiAliasing) -> AntiAliasing {
+        match requested {
+            AntiAliasing::Msaa4 if self.sample_counts & MSAA_SAMPLES == 0 => {
+                warn!(
+                    "{}x MSAA is not supported by the device (supported sample counts: {:?}), \
+                     falling back to FXAA",
+                    MSAA_SAMPLES,
+                    self.supported_sample_counts()
+                );
+                AntiAliasing::Fxaa
+            }
+            method => method,
+        }
+    }
+
+    /// Returns the maximum dimension of the texture images to upload, for the
+    /// one given by `--max-texture-dim`.
+    ///
+    /// Images larger than the device supports are downscaled to fit.
+    pub fn max_texture_dim(&self, requested: Option<u32>) -> u32 {
+        let limit = self.max_image_dimension_2d;
+        match requested {
+            Some(dim) if dim > limit => {
+                warn!(
+                    "Maximum texture dimension {} exceeds the device limit, using {}",
+                    dim, limit
+                );
+                limit
+            }
+            Some(dim) => dim,
+            None => limit,
+        }
+    }
+
+    /// Checks that the meshes of the scene can be drawn on the device.
+    pub fn check_scene(&self, scene: &data::Scene) -> anyhow::Result<()> {
+        let max_vertices = u64::from(self.max_draw_index) + 1;
+        for geometry in scene.geometry_meshes() {
+            let vertices = geometry.positions.len() as u64;
+            if vertices > max_vertices {
+                bail!(
+                    "Geometry mesh {:?} has {} vertices, but the device can draw meshes of up \
+                     to {} vertices (use `--exclude` to skip the mesh)",
+                    geometry.name,
+                    vertices,
+                    max_vertices
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the sample counts supported by both color and depth
+    /// attachments.
+    fn supported_sample_counts(&self) -> Vec<u32> {
+        (0..u32::BITS)
+            .map(|bit| 1 << bit)
+            .filter(|count| self.sample_counts & count != 0)
+            .collect()
+    }
+}
@@ -0,0 +1,264 @@
+//! Drawing of the log console panel.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use tracing::Level;
+use vulkano::{
+    buffer::{BufferUsage, CpuAccessibleBuffer},
+    command_buffer::{AutoCommandBufferBuilder, DynamicState, SubpassContents},
+    descriptor::{
+        descriptor_set::PersistentDescriptorSet, pipeline_layout::PipelineLayoutAbstract,
+    },
+    device::Device,
+    format::{ClearValue, Format},
+    framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract, Subpass},
+    image::SwapchainImage,
+    pipeline::{
+        vertex::{BufferlessDefinition, BufferlessVertices},
+        viewport::Viewport,
+        GraphicsPipeline,
+    },
+};
+use winit::window::Window;
+
+use crate::{
+    console::ConsolePanel,
+    renderer::font::{self, GLYPH_HEIGHT, GLYPH_WIDTH},
+};
+
+/// Maximum number of the columns of the panel.
+///
+/// Keep in sync with `console.frag`.
+const MAX_COLUMNS: usize = 160;
+
+/// Maximum number of the rows of the panel.
+///
+/// Keep in sync with `console.frag`.
+const MAX_ROWS: usize = 40;
+
+/// Width of a cell in glyph pixels.
+const CELL_WIDTH: u32 = GLYPH_WIDTH + 1;
+
+/// Height of a cell in glyph pixels.
+const CELL_HEIGHT: u32 = GLYPH_HEIGHT + 2;
+
+/// Margin around the panel in glyph pixels.
+const MARGIN: u32 = 4;
+
+/// Pipeline to draw the log console panel over the swapchain images.
+pub struct ConsoleOverlay {
+    /// Device.
+    device: Arc<Device>,
+    /// Render pass, which keeps the contents of the swapchain image.
+    render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    /// Pipeline.
+    #[allow(clippy::type_complexity)]
+    pipeline: Arc<
+        GraphicsPipeline<
+            BufferlessDefinition,
+            Box<dyn PipelineLayoutAbstract + Send + Sync>,
+            Arc<dyn RenderPassAbstract + Send + Sync>,
+        >,
+    >,
+    /// Rows of the glyphs, packed as in the uniform block of the shader.
+    font: [[u32; 4]; 48],
+    /// Framebuffers for the swapchain images.
+    framebuffers: Vec<Arc<dyn FramebufferAbstract + Send + Sync>>,
+}
+
+impl ConsoleOverlay {
+    /// Creates a new `ConsoleOverlay` for the swapchain images of the format.
+    pub fn new(device: Arc<Device>, format: Format) -> anyhow::Result<Self> {
+        let render_pass = Arc::new(vulkano::single_pass_renderpass!(
+            device.clone(),
+            attachments: {
+                color: {
+                    load: Load,
+                    store: Store,
+                    format: format,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        )?) as Arc<dyn RenderPassAbstract + Send + Sync>;
+
+        let vs =
+            vs::Shader::load(device.clone()).context("Failed to load fullscreen vertex shader")?;
+        let fs =
+            fs::Shader::load(device.clone()).context("Failed to load console fragment shader")?;
+        let pipeline = GraphicsPipeline::start()
+            .vertex_input(BufferlessDefinition)
+            .vertex_shader(vs.main_entry_point(), ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs.main_entry_point(), ())
+            .blend_alpha_blending()
+            .render_pass(
+                Subpass::from(render_pass.clone(), 0)
+                    .ok_or_else(|| anyhow!("Failed to create subpass"))?,
+            )
+            .build(device.clone())
+            .map(Arc::new)
+            .context("Failed to create console pipeline")?;
+
+        let mut packed_font = [[0; 4]; 48];
+        for (glyph, rows) in font::glyphs().iter().enumerate() {
+            for (y, &row) in rows.iter().enumerate() {
+                let word = (glyph % 2) * 2 + y / 4;
+                packed_font[glyph / 2][word] |= u32::from(row) << ((y % 4) as u32 * GLYPH_WIDTH);
+            }
+        }
+
+        Ok(Self {
+            device,
+            render_pass,
+            pipeline,
+            font: packed_font,
+            framebuffers: Vec::new(),
+        })
+    }
+
+    /// Recreates the framebuffers for the swapchain images.
+    pub fn resize(&mut self, images: &[Arc<SwapchainImage<Window>>]) -> anyhow::Result<()> {
+        self.framebuffers = images
+            .iter()
+            .map(|image| {
+                Framebuffer::start(self.render_pass.clone())
+                    .add(image.clone())
+                    .context("Failed to add an image to framebuffer")?
+                    .build()
+                    .map(|fb| Arc::new(fb) as Arc<dyn FramebufferAbstract + Send + Sync>)
+                    .context("Failed to create framebuffer")
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(())
+    }
+
+    /// Draws the panel at the bottom of the swapchain image.
+    ///
+    /// The panel takes up to the lower half of the image, and each glyph
+    /// pixel is drawn as `scale` by `scale` pixels.
+    pub fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder,
+        image_num: usize,
+        dimensions: [u32; 2],
+        scale: u32,
+        panel: &ConsolePanel,
+    ) -> anyhow::Result<()> {
+        let cell = [CELL_WIDTH * scale, CELL_HEIGHT * scale];
+        let margin = MARGIN * scale;
+        let columns = (dimensions[0].saturating_sub(2 * margin) / cell[0]).min(MAX_COLUMNS as u32);
+        let rows = (dimensions[1] / 2 / cell[1]).min(MAX_ROWS as u32);
+        let lines = panel.layout(columns as usize, rows as usize);
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        let mut text = fs::ty::Text {
+            cells: [[0; 4]; MAX_COLUMNS * MAX_ROWS / 16],
+            colors: [[0.0; 4]; MAX_ROWS],
+            font: self.font,
+        };
+        for (y, (level, line)) in lines.iter().enumerate() {
+            text.colors[y] = level_color(*level);
+            for (x, c) in line.chars().enumerate() {
+                let i = y * MAX_COLUMNS + x;
+                text.cells[i / 16][(i / 4) % 4] |= font::glyph_index(c) << ((i % 4) * 8);
+            }
+        }
+        let buffer = CpuAccessibleBuffer::from_data(
+            self.device.clone(),
+            BufferUsage::uniform_buffer(),
+            false,
+            text,
+        )
+        .context("Failed to create console uniform buffer")?;
+        let layout = self
+            .pipeline
+            .layout()
+            .descriptor_set_layout(0)
+            .cloned()
+            .context("Failed to get the descriptor set layout of the console pipeline")?;
+        let desc_set = PersistentDescriptorSet::start(layout)
+            .add_buffer(buffer)
+            .context("Failed to add console uniform buffer to descriptor set")?
+            .build()
+            .context("Failed to build descriptor set")?;
+
+        let dynamic_state = DynamicState {
+            viewports: Some(vec![Viewport {
+                origin: [0.0, 0.0],
+                dimensions: [dimensions[0] as f32, dimensions[1] as f32],
+                depth_range: 0.0..1.0,
+            }]),
+            ..DynamicState::none()
+        };
+        let constants = fs::ty::PushConstants {
+            origin: [
+                margin as i32,
+                dimensions[1].saturating_sub(margin + lines.len() as u32 * cell[1]) as i32,
+            ],
+            columns,
+            rows: lines.len() as u32,
+            scale,
+        };
+        builder
+            .begin_render_pass(
+                self.framebuffers[image_num].clone(),
+                SubpassContents::Inline,
+                vec![ClearValue::None],
+            )
+            .context("Failed to begin console render pass")?
+            .draw(
+                self.pipeline.clone(),
+                &dynamic_state,
+                BufferlessVertices {
+                    vertices: 3,
+                    instances: 1,
+                },
+                Arc::new(desc_set),
+                constants,
+                std::iter::empty(),
+            )
+            .context("Failed to draw console")?
+            .end_render_pass()
+            .context("Failed to end console render pass")?;
+
+        Ok(())
+    }
+}
+
+/// Returns the text color of the row with the level.
+///
+/// Rows without levels (i.e. titles) are white.
+fn level_color(level: Option<Level>) -> [f32; 4] {
+    match level {
+        None => [1.0, 1.0, 1.0, 1.0],
+        Some(Level::ERROR) => [1.0, 0.35, 0.3, 1.0],
+        Some(Level::WARN) => [1.0, 0.85, 0.3, 1.0],
+        Some(Level::INFO) => [0.85, 0.85, 0.85, 1.0],
+        Some(_) => [0.6, 0.6, 0.6, 1.0],
+    }
+}
+
+/// Fullscreen vertex shader.
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "src/bin/fbx-viewer/shaders/fullscreen.vert",
+    }
+}
+
+/// Console fragment shader.
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/bin/fbx-viewer/shaders/console.frag",
+    }
+}
@@ -0,0 +1,639 @@
+//! Depth peeling for order-independent transparency.
+//!
+//! Transparent submeshes are drawn layer by layer from the front: each pass
+//! keeps only the nearest fragments behind the layer peeled by the previous
+//! pass.
+//! The layers are accumulated front to back offscreen, and the result is
+//! composited over the opaque scene.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use fbx_viewer::Winding;
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, DynamicState, SubpassContents},
+    descriptor::{
+        descriptor_set::{DescriptorSet, PersistentDescriptorSet},
+        pipeline_layout::PipelineLayoutAbstract,
+    },
+    device::Device,
+    format::{ClearValue, Format},
+    framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract, Subpass},
+    image::{AttachmentImage, ImageUsage},
+    pipeline::{
+        blend::{AttachmentBlend, BlendFactor, BlendOp},
+        vertex::{BufferlessDefinition, BufferlessVertices},
+        viewport::Viewport,
+        GraphicsPipeline,
+    },
+    sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode},
+};
+
+use crate::{
+    renderer::shading::ShadingModel,
+    vulkan::{
+        drawable::{DrawItem, VertexLayout},
+        frame::Frame,
+        mesh_pipelines::MeshPipeline,
+        vs,
+    },
+};
+
+/// Format of the depth buffers, which are sampled by the peeling passes.
+const DEPTH_FORMAT: Format = Format::D32Sfloat;
+
+/// Format of the layer and the accumulated color.
+const COLOR_FORMAT: Format = Format::R16G16B16A16Sfloat;
+
+/// Pipeline to draw a fullscreen triangle.
+type FullscreenPipeline = GraphicsPipeline<
+    BufferlessDefinition,
+    Box<dyn PipelineLayoutAbstract + Send + Sync>,
+    Arc<dyn RenderPassAbstract + Send + Sync>,
+>;
+
+/// Depth peeling of the transparent submeshes.
+pub struct DepthPeeling {
+    /// Device.
+    device: Arc<Device>,
+    /// Number of layers to peel.
+    layers: u32,
+    /// Render pass for the depth of the opaque submeshes.
+    opaque_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    /// Render pass to peel a layer.
+    peel_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    /// Render pass to accumulate a layer.
+    accumulate_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    /// Pipeline to draw the depth of the opaque submeshes.
+    opaque_pipeline: Arc<MeshPipeline>,
+    /// Pipelines to peel a layer, for each of [`ShadingModel::ALL`].
+    ///
+    /// They share the same pipeline layout.
+    peel_pipelines: Vec<Arc<MeshPipeline>>,
+    /// Pipeline to accumulate a layer behind the previous ones.
+    accumulate_pipeline: Arc<FullscreenPipeline>,
+    /// Pipeline to composite the accumulated layers over the scene.
+    composite_pipeline: Arc<FullscreenPipeline>,
+    /// Sampler for the offscreen images.
+    sampler: Arc<Sampler>,
+    /// Offscreen targets, created on resize.
+    targets: Option<Targets>,
+}
+
+/// Offscreen targets of depth peeling.
+struct Targets {
+    /// Size of the images.
+    dimensions: [u32; 2],
+    /// Accumulated color.
+    accumulated: Arc<AttachmentImage>,
+    /// Framebuffer for the depth of the opaque submeshes.
+    opaque_framebuffer: Arc<dyn FramebufferAbstract + Send + Sync>,
+    /// Framebuffers to peel layers, alternating the depth buffers.
+    peel_framebuffers: [Arc<dyn FramebufferAbstract + Send + Sync>; 2],
+    /// Framebuffer to accumulate layers.
+    accumulate_framebuffer: Arc<dyn FramebufferAbstract + Send + Sync>,
+    /// Descriptor set for the first layer.
+    first_peel_set: Arc<dyn DescriptorSet + Send + Sync>,
+    /// Descriptor sets for the layers after the ones written to each depth
+    /// buffer.
+    peel_sets: [Arc<dyn DescriptorSet + Send + Sync>; 2],
+    /// Descriptor set to read the peeled layer.
+    layer_set: Arc<dyn DescriptorSet + Send + Sync>,
+    /// Descriptor set to read the accumulated color.
+    accumulated_set: Arc<dyn DescriptorSet + Send + Sync>,
+}
+
+impl DepthPeeling {
+    /// Creates a new `DepthPeeling` to peel the given number of layers, and
+    /// to composite them in the first subpass of the scene render pass.
+    ///
+    /// Offscreen targets are not available until [`resize`][`Self::resize`]
+    /// is called.
+    pub fn new(
+        device: Arc<Device>,
+        scene_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+        vertex_layout: VertexLayout,
+        layers: u32,
+        winding: Winding,
+    ) -> anyhow::Result<Self> {
+        let opaque_pass: Arc<dyn RenderPassAbstract + Send + Sync> = Arc::new(
+            vulkano::single_pass_renderpass!(
+                device.clone(),
+                attachments: {
+                    depth: {
+                        load: Clear,
+                        store: Store,
+                        format: DEPTH_FORMAT,
+                        samples: 1,
+                    }
+                },
+                pass: {
+                    color: [],
+                    depth_stencil: {depth}
+                }
+            )
+            .context("Failed to create opaque depth render pass")?,
+        );
+        let peel_pass: Arc<dyn RenderPassAbstract + Send + Sync> = Arc::new(
+            vulkano::single_pass_renderpass!(
+                device.clone(),
+                attachments: {
+                    color: {
+                        load: Clear,
+                        store: Store,
+                        format: COLOR_FORMAT,
+                        samples: 1,
+                    },
+                    depth: {
+                        load: Clear,
+                        store: Store,
+                        format: DEPTH_FORMAT,
+                        samples: 1,
+                    }
+                },
+                pass: {
+                    color: [color],
+                    depth_stencil: {depth}
+                }
+            )
+            .context("Failed to create peeling render pass")?,
+        );
+        let accumulate_pass: Arc<dyn RenderPassAbstract + Send + Sync> = Arc::new(
+            vulkano::single_pass_renderpass!(
+                device.clone(),
+                attachments: {
+                    color: {
+                        load: Load,
+                        store: Store,
+                        format: COLOR_FORMAT,
+                        samples: 1,
+                    }
+                },
+                pass: {
+                    color: [color],
+                    depth_stencil: {}
+                }
+            )
+            .context("Failed to create accumulation render pass")?,
+        );
+
+        let mesh_vs = vs::Shader::load(device.clone()).context("Failed to load vertex shader")?;
+        let depth_only_fs = depth_only_fs::Shader::load(device.clone())
+            .context("Failed to load depth-only fragment shader")?;
+        let peel_fs = peel_fs::Shader::load(device.clone())
+            .context("Failed to load peeling fragment shader")?;
+        let fullscreen_vs = fullscreen_vs::Shader::load(device.clone())
+            .context("Failed to load fullscreen vertex shader")?;
+        let composite_fs = composite_fs::Shader::load(device.clone())
+            .context("Failed to load composite fragment shader")?;
+
+        // Both sides are drawn, since the back faces of transparent surfaces
+        // are visible through the front faces.
+        let opaque_pipeline = GraphicsPipeline::start()
+            .vertex_input(vertex_layout)
+            .vertex_shader(mesh_vs.main_entry_point(), ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(depth_only_fs.main_entry_point(), ())
+            .depth_stencil_simple_depth()
+            .render_pass(
+                Subpass::from(opaque_pass.clone(), 0)
+                    .ok_or_else(|| anyhow!("Failed to create subpass"))?,
+            )
+            .with_auto_layout(device.clone(), &[(0, 0)])
+            .map(Arc::new)
+            .context("Failed to create opaque depth pipeline")?;
+        let peel_pipelines = ShadingModel::ALL
+            .iter()
+            .map(|&model| {
+                let builder = GraphicsPipeline::start()
+                    .vertex_input(vertex_layout)
+                    .vertex_shader(mesh_vs.main_entry_point(), ())
+                    .triangle_list()
+                    .viewports_dynamic_scissors_irrelevant(1);
+                match winding {
+                    Winding::Ccw => builder.front_face_counter_clockwise(),
+                    Winding::Cw => builder.front_face_clockwise(),
+                }
+                .fragment_shader(
+                    peel_fs.main_entry_point(),
+                    peel_fs::SpecializationConstants {
+                        SHADING_MODEL: model.to_shader_value(),
+                    },
+                )
+                .blend_collective(AttachmentBlend::pass_through())
+                .depth_stencil_simple_depth()
+                .render_pass(
+                    Subpass::from(peel_pass.clone(), 0)
+                        .ok_or_else(|| anyhow!("Failed to create subpass"))?,
+                )
+                .with_auto_layout(device.clone(), &[(0, 0)])
+                .map(Arc::new)
+                .with_context(|| format!("Failed to create peeling pipeline for {:?}", model))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        // Colors are premultiplied by alpha.
+        // The accumulated color is drawn under the new layer, since the
+        // layers are peeled from the front.
+        let accumulate_pipeline = create_fullscreen_pipeline(
+            &device,
+            &fullscreen_vs,
+            &composite_fs,
+            accumulate_pass.clone(),
+            AttachmentBlend {
+                enabled: true,
+                color_op: BlendOp::Add,
+                color_source: BlendFactor::OneMinusDstAlpha,
+                color_destination: BlendFactor::One,
+                alpha_op: BlendOp::Add,
+                alpha_source: BlendFactor::OneMinusDstAlpha,
+                alpha_destination: BlendFactor::One,
+                ..AttachmentBlend::pass_through()
+            },
+        )
+        .context("Failed to create accumulation pipeline")?;
+        let composite_pipeline = create_fullscreen_pipeline(
+            &device,
+            &fullscreen_vs,
+            &composite_fs,
+            scene_pass,
+            AttachmentBlend {
+                enabled: true,
+                color_op: BlendOp::Add,
+                color_source: BlendFactor::One,
+                color_destination: BlendFactor::OneMinusSrcAlpha,
+                alpha_op: BlendOp::Add,
+                alpha_source: BlendFactor::One,
+                alpha_destination: BlendFactor::OneMinusSrcAlpha,
+                ..AttachmentBlend::pass_through()
+            },
+        )
+        .context("Failed to create composite pipeline")?;
+
+        // The images are read with `texelFetch`, so the filters are unused.
+        let sampler = Sampler::new(
+            device.clone(),
+            Filter::Nearest,
+            Filter::Nearest,
+            MipmapMode::Nearest,
+            SamplerAddressMode::ClampToEdge,
+            SamplerAddressMode::ClampToEdge,
+            SamplerAddressMode::ClampToEdge,
+            0.0,
+            1.0,
+            0.0,
+            0.0,
+        )
+        .context("Failed to create sampler")?;
+
+        Ok(Self {
+            device,
+            layers,
+            opaque_pass,
+            peel_pass,
+            accumulate_pass,
+            opaque_pipeline,
+            peel_pipelines,
+            accumulate_pipeline,
+            composite_pipeline,
+            sampler,
+            targets: None,
+        })
+    }
+
+    /// Recreates the offscreen targets for the framebuffer size.
+    pub fn resize(&mut self, dimensions: [u32; 2]) -> anyhow::Result<()> {
+        let device = &self.device;
+        let opaque_depth = AttachmentImage::sampled(device.clone(), dimensions, DEPTH_FORMAT)
+            .context("Failed to create opaque depth buffer")?;
+        let peel_depths = [
+            AttachmentImage::sampled(device.clone(), dimensions, DEPTH_FORMAT)
+                .context("Failed to create peeling depth buffer")?,
+            AttachmentImage::sampled(device.clone(), dimensions, DEPTH_FORMAT)
+                .context("Failed to create peeling depth buffer")?,
+        ];
+        let layer = AttachmentImage::sampled(device.clone(), dimensions, COLOR_FORMAT)
+            .context("Failed to create layer color buffer")?;
+        // The accumulated color is cleared by a transfer command every frame.
+        let accumulated = AttachmentImage::with_usage(
+            device.clone(),
+            dimensions,
+            COLOR_FORMAT,
+            ImageUsage {
+                sampled: true,
+                transfer_destination: true,
+                ..ImageUsage::none()
+            },
+        )
+        .context("Failed to create accumulated color buffer")?;
+
+        let opaque_framebuffer = Framebuffer::start(self.opaque_pass.clone())
+            .add(opaque_depth.clone())
+            .context("Failed to add a depth buffer to framebuffer")?
+            .build()
+            .map(|fb| Arc::new(fb) as Arc<dyn FramebufferAbstract + Send + Sync>)
+            .context("Failed to create framebuffer")?;
+        let peel_framebuffer = |depth: &Arc<AttachmentImage>| {
+            Framebuffer::start(self.peel_pass.clone())
+                .add(layer.clone())
+                .context("Failed to add a layer color buffer to framebuffer")?
+                .add(depth.clone())
+                .context("Failed to add a depth buffer to framebuffer")?
+                .build()
+                .map(|fb| Arc::new(fb) as Arc<dyn FramebufferAbstract + Send + Sync>)
+                .context("Failed to create framebuffer")
+        };
+        let peel_framebuffers = [
+            peel_framebuffer(&peel_depths[0])?,
+            peel_framebuffer(&peel_depths[1])?,
+        ];
+        let accumulate_framebuffer = Framebuffer::start(self.accumulate_pass.clone())
+            .add(accumulated.clone())
+            .context("Failed to add an accumulated color buffer to framebuffer")?
+            .build()
+            .map(|fb| Arc::new(fb) as Arc<dyn FramebufferAbstract + Send + Sync>)
+            .context("Failed to create framebuffer")?;
+
+        // The first layer has no previous layer, so the opaque depth is bound
+        // in place of it (and ignored by the shader).
+        let peel_set = |previous: &Arc<AttachmentImage>| {
+            let layout = self.peel_pipelines[0]
+                .layout()
+                .descriptor_set_layout(3)
+                .context("Failed to get the peeling descriptor set layout")?;
+            PersistentDescriptorSet::start(layout.clone())
+                .add_sampled_image(opaque_depth.clone(), self.sampler.clone())
+                .context("Failed to add opaque depth to descriptor set")?
+                .add_sampled_image(previous.clone(), self.sampler.clone())
+                .context("Failed to add previous depth to descriptor set")?
+                .build()
+                .map(|set| Arc::new(set) as Arc<dyn DescriptorSet + Send + Sync>)
+                .context("Failed to build descriptor set")
+        };
+        let first_peel_set = peel_set(&opaque_depth)?;
+        let peel_sets = [peel_set(&peel_depths[0])?, peel_set(&peel_depths[1])?];
+        let fullscreen_set = |pipeline: &FullscreenPipeline, image: &Arc<AttachmentImage>| {
+            let layout = pipeline
+                .layout()
+                .descriptor_set_layout(0)
+                .context("Failed to get the composite descriptor set layout")?;
+            PersistentDescriptorSet::start(layout.clone())
+                .add_sampled_image(image.clone(), self.sampler.clone())
+                .context("Failed to add sampled image to descriptor set")?
+                .build()
+                .map(|set| Arc::new(set) as Arc<dyn DescriptorSet + Send + Sync>)
+                .context("Failed to build descriptor set")
+        };
+        let layer_set = fullscreen_set(&self.accumulate_pipeline, &layer)?;
+        let accumulated_set = fullscreen_set(&self.composite_pipeline, &accumulated)?;
+
+        self.targets = Some(Targets {
+            dimensions,
+            accumulated,
+            opaque_framebuffer,
+            peel_framebuffers,
+            accumulate_framebuffer,
+            first_peel_set,
+            peel_sets,
+            layer_set,
+            accumulated_set,
+        });
+
+        Ok(())
+    }
+
+    /// Peels and accumulates the layers of the transparent items.
+    ///
+    /// This should be called outside of the scene render pass, before
+    /// [`composite`][`Self::composite`].
+    /// Each view is drawn with the dynamic state and the uniforms at the
+    /// dynamic offset of `set0` for its index, and the transparent items are
+    /// lit by the shading model.
+    pub fn draw<'a>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder,
+        dynamic_states: &[&DynamicState],
+        set0: &Arc<dyn DescriptorSet + Send + Sync>,
+        shading_model: ShadingModel,
+        items: impl IntoIterator<Item = &'a DrawItem> + Clone,
+    ) -> anyhow::Result<()> {
+        let targets = self
+            .targets
+            .as_ref()
+            .expect("Should never fail: depth peeling should be resized before drawing");
+        let peel_pipeline = &self.peel_pipelines[ShadingModel::ALL
+            .iter()
+            .position(|&model| model == shading_model)
+            .expect("Should never fail: `ALL` should contain all models")];
+        let fullscreen_state = DynamicState {
+            viewports: Some(vec![Viewport {
+                origin: [0.0, 0.0],
+                dimensions: [targets.dimensions[0] as f32, targets.dimensions[1] as f32],
+                depth_range: 0.0..1.0,
+            }]),
+            ..DynamicState::none()
+        };
+
+        builder
+            .clear_color_image(
+                targets.accumulated.clone(),
+                ClearValue::Float([0.0, 0.0, 0.0, 0.0]),
+            )
+            .context("Failed to clear accumulated color")?;
+
+        builder
+            .begin_render_pass(
+                targets.opaque_framebuffer.clone(),
+                SubpassContents::Inline,
+                vec![ClearValue::Depth(1.0)],
+            )
+            .context("Failed to begin opaque depth render pass")?;
+        for (view_i, dynamic_state) in dynamic_states.iter().enumerate() {
+            for item in items.clone().into_iter().filter(|item| !item.transparent) {
+                builder
+                    .draw_indexed(
+                        self.opaque_pipeline.clone(),
+                        dynamic_state,
+                        item.vertices.clone(),
+                        item.current_indices().clone(),
+                        set0.clone(),
+                        (),
+                        std::iter::once(Frame::uniform_offset(view_i, item.transform)),
+                    )
+                    .context("Failed to draw opaque depth")?;
+            }
+        }
+        builder
+            .end_render_pass()
+            .context("Failed to end opaque depth render pass")?;
+
+        for layer_i in 0..self.layers as usize {
+            let peel_set = match layer_i {
+                0 => targets.first_peel_set.clone(),
+                _ => targets.peel_sets[(layer_i - 1) % 2].clone(),
+            };
+            builder
+                .begin_render_pass(
+                    targets.peel_framebuffers[layer_i % 2].clone(),
+                    SubpassContents::Inline,
+                    vec![[0.0, 0.0, 0.0, 0.0].into(), ClearValue::Depth(1.0)],
+                )
+                .context("Failed to begin peeling render pass")?;
+            for (view_i, dynamic_state) in dynamic_states.iter().enumerate() {
+                for item in items.clone().into_iter().filter(|item| item.transparent) {
+                    let constants = peel_fs::ty::Peel {
+                        first_layer: (layer_i == 0).into(),
+                        premultiplied_alpha: item.premultiplied_alpha.into(),
+                    };
+                    builder
+                        .draw_indexed(
+                            peel_pipeline.clone(),
+                            dynamic_state,
+                            item.vertices.clone(),
+                            item.current_indices().clone(),
+                            (
+                                set0.clone(),
+                                item.texture.clone(),
+                                item.material.clone(),
+                                peel_set.clone(),
+                            ),
+                            constants,
+                            std::iter::once(Frame::uniform_offset(view_i, item.transform)),
+                        )
+                        .context("Failed to draw a transparent layer")?;
+                }
+            }
+            builder
+                .end_render_pass()
+                .context("Failed to end peeling render pass")?
+                .begin_render_pass(
+                    targets.accumulate_framebuffer.clone(),
+                    SubpassContents::Inline,
+                    vec![ClearValue::None],
+                )
+                .context("Failed to begin accumulation render pass")?
+                .draw(
+                    self.accumulate_pipeline.clone(),
+                    &fullscreen_state,
+                    BufferlessVertices {
+                        vertices: 3,
+                        instances: 1,
+                    },
+                    targets.layer_set.clone(),
+                    (),
+                    std::iter::empty(),
+                )
+                .context("Failed to accumulate a layer")?
+                .end_render_pass()
+                .context("Failed to end accumulation render pass")?;
+        }
+
+        Ok(())
+    }
+
+    /// Composites the accumulated layers over the view.
+    ///
+    /// This should be called in the scene render pass, after the opaque
+    /// submeshes of the view are drawn.
+    pub fn composite(
+        &self,
+        builder: &mut AutoCommandBufferBuilder,
+        dynamic_state: &DynamicState,
+    ) -> anyhow::Result<()> {
+        let targets = self
+            .targets
+            .as_ref()
+            .expect("Should never fail: depth peeling should be resized before drawing");
+        builder
+            .draw(
+                self.composite_pipeline.clone(),
+                dynamic_state,
+                BufferlessVertices {
+                    vertices: 3,
+                    instances: 1,
+                },
+                targets.accumulated_set.clone(),
+                (),
+                std::iter::empty(),
+            )
+            .context("Failed to composite transparent layers")?;
+
+        Ok(())
+    }
+}
+
+/// Creates a pipeline to draw a fullscreen triangle with the composite shader.
+fn create_fullscreen_pipeline(
+    device: &Arc<Device>,
+    vs: &fullscreen_vs::Shader,
+    fs: &composite_fs::Shader,
+    render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    blend: AttachmentBlend,
+) -> anyhow::Result<Arc<FullscreenPipeline>> {
+    GraphicsPipeline::start()
+        .vertex_input(BufferlessDefinition)
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_irrelevant(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .blend_collective(blend)
+        .render_pass(
+            Subpass::from(render_pass, 0).ok_or_else(|| anyhow!("Failed to create subpass"))?,
+        )
+        .build(device.clone())
+        .map(Arc::new)
+        .map_err(Into::into)
+}
+
+/// Fragment shader writing only the depth.
+mod depth_only_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/bin/fbx-viewer/shaders/depth_only.frag",
+    }
+}
+
+/// Mesh fragment shader discarding the fragments outside of the layer.
+mod peel_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/bin/fbx-viewer/shaders/default.frag",
+        define: [("DEPTH_PEELING", "1")],
+    }
+}
+
+/// Fullscreen vertex shader.
+mod fullscreen_vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "src/bin/fbx-viewer/shaders/fullscreen.vert",
+    }
+}
+
+/// Fragment shader copying the premultiplied color of a layer.
+mod composite_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/bin/fbx-viewer/shaders/peel_composite.frag",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vulkano::pipeline::shader::ShaderInterfaceDefMatch;
+
+    use super::*;
+
+    #[test]
+    fn shader_interfaces() {
+        depth_only_fs::MainInput
+            .matches(&vs::MainOutput)
+            .expect("The depth-only shader inputs should match the mesh vertex shader outputs");
+        peel_fs::MainInput
+            .matches(&vs::MainOutput)
+            .expect("The peeling shader inputs should match the mesh vertex shader outputs");
+        composite_fs::MainInput
+            .matches(&fullscreen_vs::MainOutput)
+            .expect("The composite shader inputs should match the fullscreen shader outputs");
+    }
+}
@@ -3,10 +3,19 @@
 use vulkano::sync::GpuFuture;
 
 pub use self::{
-    geometry::GeometryMesh, loader::Loader, material::Material, mesh::Mesh, scene::Scene,
-    texture::Texture, vertex::Vertex,
+    chunk::Chunk,
+    draw_list::{DrawItem, DrawList},
+    geometry::{GeometryMesh, Lod, Topology},
+    loader::Loader,
+    material::Material,
+    mesh::Mesh,
+    scene::Scene,
+    texture::Texture,
+    vertex::{CompactVertex, Vertex, VertexLayout},
 };
 
+pub mod chunk;
+pub mod draw_list;
 pub mod geometry;
 mod loader;
 pub mod material;
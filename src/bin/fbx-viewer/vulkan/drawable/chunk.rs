@@ -0,0 +1,154 @@
+//! Spatial chunks of the scene.
+
+use std::collections::BTreeMap;
+
+use fbx_viewer::{
+    data::{self, MaterialIndex},
+    util::bbox::{BoundingBox3d, OptionalBoundingBox3d},
+};
+use rgb::RGBA;
+
+use crate::vulkan::drawable::GeometryMesh;
+
+/// Cells along the longest axis of the scene to partition the meshes into.
+pub const CELLS: u32 = 16;
+
+/// Spatial chunk of the scene, with the meshes in it merged.
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    /// Merged geometry mesh.
+    pub(crate) geometry: GeometryMesh,
+    /// Materials of the submeshes of the merged geometry mesh.
+    pub(crate) materials: Vec<MaterialIndex>,
+    /// Render order of the meshes in the chunk.
+    pub(crate) render_order: i32,
+}
+
+/// Merged meshes of a chunk.
+#[derive(Debug)]
+pub struct MergedMeshes {
+    /// Merged geometry mesh.
+    pub geometry: data::GeometryMesh,
+    /// Materials of the submeshes of the merged geometry mesh.
+    pub materials: Vec<MaterialIndex>,
+    /// Render order of the meshes.
+    pub render_order: i32,
+    /// Whether any of the meshes has vertex colors.
+    has_colors: bool,
+}
+
+impl MergedMeshes {
+    /// Creates a new empty `MergedMeshes` with the name and the render order.
+    fn new(name: String, render_order: i32) -> Self {
+        Self {
+            geometry: data::GeometryMesh {
+                name: Some(name),
+                positions: Vec::new(),
+                normals: Vec::new(),
+                uv: Vec::new(),
+                colors: Vec::new(),
+                indices_per_material: Vec::new(),
+                polygons_per_material: Vec::new(),
+            },
+            materials: Vec::new(),
+            render_order,
+            has_colors: false,
+        }
+    }
+
+    /// Appends the mesh with the geometry.
+    ///
+    /// Submeshes with the same material are merged into one.
+    fn append(&mut self, mesh: &data::Mesh, src: &data::GeometryMesh) {
+        let merged = &mut self.geometry;
+        let base = merged.positions.len() as u32;
+        merged.positions.extend_from_slice(&src.positions);
+        merged.normals.extend_from_slice(&src.normals);
+        merged.uv.extend_from_slice(&src.uv);
+        // Meshes without vertex colors are white.
+        if src.colors.is_empty() {
+            merged
+                .colors
+                .resize(merged.positions.len(), RGBA::new(1.0, 1.0, 1.0, 1.0));
+        } else {
+            merged.colors.extend_from_slice(&src.colors);
+            self.has_colors = true;
+        }
+
+        for (&material, indices) in mesh.materials.iter().zip(&src.indices_per_material) {
+            let submesh_i = match self.materials.iter().position(|&m| m == material) {
+                Some(i) => i,
+                None => {
+                    self.materials.push(material);
+                    merged.indices_per_material.push(Vec::new());
+                    self.materials.len() - 1
+                }
+            };
+            merged.indices_per_material[submesh_i].extend(indices.iter().map(|&i| base + i));
+        }
+    }
+}
+
+/// Partitions the meshes of the scene into chunks, and merges the meshes in
+/// each chunk.
+///
+/// The geometry meshes should be the ones of the scene, possibly with
+/// modified vertex attributes.
+/// The scene is divided into cubic cells, `cells` of them along its longest
+/// axis, and each mesh belongs to the cell containing the center of its
+/// bounding box.
+/// Meshes with different render orders are not merged, so that the chunks
+/// keep the render orders.
+/// Empty meshes are skipped.
+pub fn partition(
+    src_scene: &data::Scene,
+    src_geometries: &[&data::GeometryMesh],
+    cells: u32,
+) -> anyhow::Result<Vec<MergedMeshes>> {
+    let mut meshes = Vec::new();
+    for mesh in src_scene.meshes() {
+        let geometry = *mesh
+            .geometry_mesh_index
+            .resolve(src_scene.id(), src_geometries)?;
+        if let Some(bbox) = geometry.bbox_mesh().bounding_box() {
+            meshes.push((mesh, geometry, bbox));
+        }
+    }
+    let scene_bbox = match meshes
+        .iter()
+        .map(|(_, _, bbox)| *bbox)
+        .collect::<OptionalBoundingBox3d<f32>>()
+        .bounding_box()
+    {
+        Some(v) => v,
+        None => return Ok(Vec::new()),
+    };
+    let size = scene_bbox.size();
+    let cell_size = size.x.max(size.y).max(size.z) / cells.max(1) as f32;
+    let cell_of = |bbox: &BoundingBox3d<f32>| -> [u32; 3] {
+        if cell_size <= 0.0 {
+            return [0; 3];
+        }
+        let v = (bbox.center() - scene_bbox.min()) / cell_size;
+        [v.x as u32, v.y as u32, v.z as u32]
+    };
+
+    let mut chunks = BTreeMap::new();
+    for (mesh, geometry, bbox) in meshes {
+        let cell = cell_of(&bbox);
+        chunks
+            .entry((mesh.render_order, cell))
+            .or_insert_with(|| MergedMeshes::new(format!("chunk {:?}", cell), mesh.render_order))
+            .append(mesh, geometry);
+    }
+
+    Ok(chunks
+        .into_values()
+        .map(|mut merged| {
+            if !merged.has_colors {
+                merged.geometry.colors.clear();
+            }
+            merged
+        })
+        .collect())
+}
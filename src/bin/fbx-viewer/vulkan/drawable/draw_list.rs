@@ -0,0 +1,303 @@
+//! Draw list.
+
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use cgmath::{Matrix4, Point3};
+use fbx_viewer::{
+    data::TextureIndex,
+    util::bbox::{BoundingBox3d, BoundingSphere},
+};
+use vulkano::{
+    buffer::{BufferAccess, ImmutableBuffer},
+    descriptor::descriptor_set::DescriptorSet,
+};
+
+use crate::vulkan::{drawable::Scene, lod};
+
+/// Resources to draw a submesh.
+#[derive(Clone)]
+pub struct DrawItem {
+    /// Vertices.
+    pub vertices: Arc<dyn BufferAccess + Send + Sync>,
+    /// Indices.
+    pub indices: Arc<ImmutableBuffer<[u32]>>,
+    /// Indices of the coarser levels of detail, from the finest.
+    pub lods: Vec<Arc<ImmutableBuffer<[u32]>>>,
+    /// Grid cells along the longest axis of the coarser levels of detail.
+    pub lod_cells: Vec<u32>,
+    /// Current level of detail.
+    ///
+    /// Level 0 is the original submesh.
+    pub lod: usize,
+    /// Descriptor set for the material.
+    pub material: Arc<dyn DescriptorSet + Send + Sync>,
+    /// Descriptor set for the diffuse texture.
+    pub texture: Arc<dyn DescriptorSet + Send + Sync>,
+    /// Diffuse texture which is not uploaded yet.
+    ///
+    /// The dummy texture is used in place of it.
+    pub pending_texture: Option<TextureIndex>,
+    /// Bounding box of the geometry mesh.
+    pub bounding_box: Option<BoundingBox3d<f32>>,
+    /// Bounding sphere of the geometry mesh.
+    pub bounding_sphere: Option<BoundingSphere<f32>>,
+    /// Whether the submesh is alpha blended.
+    pub transparent: bool,
+    /// Whether the colors of the texture are premultiplied by alpha.
+    pub premultiplied_alpha: bool,
+    /// Whether the back faces are visible.
+    pub double_sided: bool,
+    /// Render order of the mesh.
+    pub render_order: i32,
+    /// Index of the mesh in the scene, or `None` for merged meshes.
+    pub mesh: Option<usize>,
+    /// Uniform slot of the transform of the mesh.
+    ///
+    /// This is 0 if the mesh is not transformed, and `i` if it is transformed
+    /// by the `i - 1`th one of [`DrawList::transforms`].
+    pub transform: usize,
+}
+
+impl DrawItem {
+    /// Returns the indices of the current level of detail.
+    pub fn current_indices(&self) -> &Arc<ImmutableBuffer<[u32]>> {
+        self.lod
+            .checked_sub(1)
+            .and_then(|i| self.lods.get(i))
+            .unwrap_or(&self.indices)
+    }
+}
+
+/// Submeshes of the scene in drawing order.
+///
+/// Opaque submeshes are drawn before transparent ones, and each of them are
+/// sorted stably by the render orders of the meshes.
+///
+/// The list should be rebuilt when the buffers or the descriptor sets of the
+/// scene are replaced.
+#[derive(Clone)]
+pub struct DrawList {
+    /// Items.
+    items: Vec<DrawItem>,
+    /// Transforms applied to the meshes over their geometry meshes, with the
+    /// indices of the meshes.
+    transforms: Vec<(usize, Matrix4<f32>)>,
+    /// Indices of the hidden meshes, whose items are left out.
+    hidden: Vec<usize>,
+}
+
+impl DrawList {
+    /// Creates a new `DrawList` for the scene.
+    ///
+    /// `dummy_texture` is used for the submeshes without textures, and for
+    /// the ones whose textures are not uploaded yet.
+    /// The caches of the scene should be initialized.
+    pub fn new(
+        scene: &Scene,
+        dummy_texture: &Arc<dyn DescriptorSet + Send + Sync>,
+    ) -> anyhow::Result<Self> {
+        let mut opaque = Vec::new();
+        let mut transparent = Vec::new();
+        // Chunks are drawn in place of the meshes if any.
+        let meshes = if scene.chunks.is_empty() {
+            scene
+                .meshes
+                .iter()
+                .enumerate()
+                .map(|(mesh_i, mesh)| {
+                    Ok((
+                        scene.geometry_mesh(mesh.geometry_mesh_index)?,
+                        &mesh.materials,
+                        mesh.render_order,
+                        Some(mesh_i),
+                    ))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?
+        } else {
+            scene
+                .chunks
+                .iter()
+                .map(|chunk| (&chunk.geometry, &chunk.materials, chunk.render_order, None))
+                .collect()
+        };
+        for (geometry_mesh, materials, render_order, mesh) in meshes {
+            for (submesh_i, (&material_i, indices)) in materials
+                .iter()
+                .zip(&geometry_mesh.indices_per_material)
+                .enumerate()
+            {
+                let material = scene.material(material_i)?;
+                let material_desc_set = material
+                    .cache
+                    .uniform_buffer
+                    .clone()
+                    .ok_or_else(|| anyhow!("Material uniform buffer should be uploaded"))?;
+                let texture = material
+                    .diffuse_texture
+                    .map(|diffuse_i| scene.texture(diffuse_i))
+                    .transpose()?;
+                let texture_desc_set = texture.and_then(|t| t.cache.descriptor_set.clone());
+                let pending_texture = match (&texture_desc_set, texture) {
+                    (None, Some(t)) if t.image.is_none() => material.diffuse_texture,
+                    (None, Some(_)) => {
+                        return Err(anyhow!(
+                            "Descriptor set for texture should be initialized but not"
+                        ))
+                    }
+                    _ => None,
+                };
+                let item = DrawItem {
+                    vertices: geometry_mesh.vertices.clone(),
+                    indices: indices.clone(),
+                    lods: geometry_mesh
+                        .lods
+                        .iter()
+                        .filter_map(|lod| lod.indices_per_material.get(submesh_i).cloned())
+                        .collect(),
+                    lod_cells: geometry_mesh.lods.iter().map(|lod| lod.cells).collect(),
+                    lod: 0,
+                    material: material_desc_set,
+                    texture: texture_desc_set.unwrap_or_else(|| dummy_texture.clone()),
+                    pending_texture,
+                    bounding_box: geometry_mesh.bounding_box.bounding_box(),
+                    bounding_sphere: geometry_mesh.bounding_sphere,
+                    transparent: texture.is_some_and(|t| t.transparent) && !material.alpha_tested,
+                    premultiplied_alpha: texture.is_some_and(|t| t.premultiplied),
+                    double_sided: material.double_sided,
+                    render_order,
+                    mesh,
+                    transform: 0,
+                };
+                if item.transparent {
+                    transparent.push(item);
+                } else {
+                    opaque.push(item);
+                }
+            }
+        }
+        opaque.sort_by_key(|item| item.render_order);
+        transparent.sort_by_key(|item| item.render_order);
+        opaque.append(&mut transparent);
+
+        Ok(Self {
+            items: opaque,
+            transforms: Vec::new(),
+            hidden: Vec::new(),
+        })
+    }
+
+    /// Rebuilds the list for the scene, keeping the levels of detail of the
+    /// items.
+    ///
+    /// The scene should be the one the list is created for, with replaced
+    /// buffers or descriptor sets.
+    pub fn rebuild(
+        &mut self,
+        scene: &Scene,
+        dummy_texture: &Arc<dyn DescriptorSet + Send + Sync>,
+    ) -> anyhow::Result<()> {
+        let mut rebuilt = Self::new(scene, dummy_texture)?;
+        rebuilt.hidden = std::mem::take(&mut self.hidden);
+        let hidden = &rebuilt.hidden;
+        rebuilt
+            .items
+            .retain(|item| item.mesh.is_none_or(|mesh| !hidden.contains(&mesh)));
+        if rebuilt.items.len() == self.items.len() {
+            for (item, old) in rebuilt.items.iter_mut().zip(&self.items) {
+                item.lod = old.lod.min(item.lods.len());
+            }
+        }
+        rebuilt.transforms = std::mem::take(&mut self.transforms);
+        rebuilt.assign_transforms();
+        *self = rebuilt;
+
+        Ok(())
+    }
+
+    /// Returns the items in drawing order.
+    pub fn items(&self) -> &[DrawItem] {
+        &self.items
+    }
+
+    /// Sets the transform applied to the mesh over its geometry mesh, or
+    /// removes it if `None` is given.
+    pub fn set_transform(&mut self, mesh: usize, transform: Option<Matrix4<f32>>) {
+        let existing = self.transforms.iter().position(|&(i, _)| i == mesh);
+        match (existing, transform) {
+            (Some(pos), Some(transform)) => self.transforms[pos].1 = transform,
+            (Some(pos), None) => {
+                self.transforms.remove(pos);
+            }
+            (None, Some(transform)) => self.transforms.push((mesh, transform)),
+            (None, None) => {}
+        }
+        self.assign_transforms();
+    }
+
+    /// Hides or shows the mesh.
+    ///
+    /// The list should be rebuilt to apply the change.
+    pub fn set_hidden(&mut self, mesh: usize, hidden: bool) {
+        let existing = self.hidden.iter().position(|&i| i == mesh);
+        match (existing, hidden) {
+            (Some(pos), false) => {
+                self.hidden.remove(pos);
+            }
+            (None, true) => self.hidden.push(mesh),
+            _ => {}
+        }
+    }
+
+    /// Returns whether the mesh is hidden.
+    pub fn is_hidden(&self, mesh: usize) -> bool {
+        self.hidden.contains(&mesh)
+    }
+
+    /// Returns the transforms of the meshes in the order of their uniform
+    /// slots.
+    pub fn transforms(&self) -> Vec<Matrix4<f32>> {
+        self.transforms
+            .iter()
+            .map(|&(_, transform)| transform)
+            .collect()
+    }
+
+    /// Returns the uniform slot of the transform of the mesh.
+    pub fn transform_slot(&self, mesh: usize) -> usize {
+        self.transforms
+            .iter()
+            .position(|&(i, _)| i == mesh)
+            .map_or(0, |pos| pos + 1)
+    }
+
+    /// Returns the bounding box of the item in the world space.
+    pub fn bounding_box(&self, item: &DrawItem) -> Option<BoundingBox3d<f32>> {
+        let bbox = item.bounding_box?;
+        Some(match item.transform.checked_sub(1) {
+            Some(i) => bbox.transformed(&self.transforms[i].1),
+            None => bbox,
+        })
+    }
+
+    /// Updates the uniform slots of the items for the transforms.
+    fn assign_transforms(&mut self) {
+        for i in 0..self.items.len() {
+            self.items[i].transform = self.items[i]
+                .mesh
+                .map_or(0, |mesh| self.transform_slot(mesh));
+        }
+    }
+
+    /// Selects the levels of detail of the items for the eye position.
+    pub fn update_lods(&mut self, eye: Point3<f32>, viewport_height: f32) {
+        for item in &mut self.items {
+            let sphere = match item.bounding_sphere {
+                Some(v) if !item.lods.is_empty() => v,
+                _ => continue,
+            };
+            let diameter = lod::projected_diameter(&sphere, eye, viewport_height);
+            item.lod = lod::select_level(item.lod, &item.lod_cells, diameter);
+        }
+    }
+}
@@ -2,22 +2,51 @@
 
 use std::{fmt, sync::Arc};
 
-use fbx_viewer::util::bbox::OptionalBoundingBox3d;
-use vulkano::buffer::ImmutableBuffer;
-
-use crate::vulkan::drawable::Vertex;
+use fbx_viewer::util::bbox::{BoundingSphere, OptionalBoundingBox3d};
+use vulkano::buffer::{BufferAccess, ImmutableBuffer};
 
 /// Geometry mesh.
 #[derive(Clone)]
 pub struct GeometryMesh {
     /// Name.
     pub(crate) name: Option<String>,
-    /// Vertices.
-    pub(crate) vertices: Arc<ImmutableBuffer<[Vertex]>>,
+    /// Vertices, in the layout of the loader.
+    pub(crate) vertices: Arc<dyn BufferAccess + Send + Sync>,
     /// Indices per materials.
     pub(crate) indices_per_material: Vec<Arc<ImmutableBuffer<[u32]>>>,
+    /// Coarser levels of detail, from the finest.
+    pub(crate) lods: Vec<Lod>,
     /// Bounding box.
     pub(crate) bounding_box: OptionalBoundingBox3d<f32>,
+    /// Bounding sphere.
+    pub(crate) bounding_sphere: Option<BoundingSphere<f32>>,
+    /// Topology problems, if analyzed.
+    pub(crate) topology: Option<Topology>,
+}
+
+/// Line lists of the topology problems of a geometry mesh.
+///
+/// The lines use the vertices of the mesh, and are `None` if there are no
+/// such problems.
+#[derive(Clone)]
+pub struct Topology {
+    /// Open edges.
+    pub(crate) open_edges: Option<Arc<ImmutableBuffer<[u32]>>>,
+    /// Non-manifold edges.
+    pub(crate) non_manifold_edges: Option<Arc<ImmutableBuffer<[u32]>>>,
+    /// Edges of the duplicate faces.
+    pub(crate) duplicate_faces: Option<Arc<ImmutableBuffer<[u32]>>>,
+}
+
+/// Level of detail of a geometry mesh.
+///
+/// The level uses the same vertices as the original mesh.
+#[derive(Clone)]
+pub struct Lod {
+    /// Grid cells along the longest axis the vertices are clustered into.
+    pub(crate) cells: u32,
+    /// Indices per materials.
+    pub(crate) indices_per_material: Vec<Arc<ImmutableBuffer<[u32]>>>,
 }
 
 impl fmt::Debug for GeometryMesh {
@@ -25,7 +54,10 @@ impl fmt::Debug for GeometryMesh {
         f.debug_struct("GeometryMesh")
             .field("name", &self.name)
             .field("indices_per_material_len", &self.indices_per_material.len())
+            .field("lods_len", &self.lods.len())
             .field("bounding_box", &self.bounding_box)
+            .field("bounding_sphere", &self.bounding_sphere)
+            .field("topology_analyzed", &self.topology.is_some())
             .finish()
     }
 }
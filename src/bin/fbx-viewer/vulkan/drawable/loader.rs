@@ -3,19 +3,28 @@
 use std::sync::Arc;
 
 use anyhow::Context;
-use fbx_viewer::data;
+use fbx_viewer::{
+    data::{self, TextureImage, TextureIndex},
+    PremultipliedAlpha, TextureRemap,
+};
+use image::RgbaImage;
+use tracing::{debug, info, info_span, warn};
 use vulkano::{
-    buffer::{BufferUsage, ImmutableBuffer},
+    buffer::{BufferAccess, BufferUsage, ImmutableBuffer},
     device::{Device, Queue},
     format::R8G8B8A8Srgb,
     image::{Dimensions, ImmutableImage, MipmapsCount},
-    sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode},
+    sampler::SamplerAddressMode,
     sync::GpuFuture,
 };
 
-use crate::vulkan::{
-    drawable::{self, join_futures},
-    fs,
+use crate::{
+    renderer::shading::material_override_to_shader_value,
+    vulkan::{
+        drawable::{self, chunk, join_futures, texture::SamplerCache, VertexLayout},
+        fs, lod,
+        texture_stream::{is_premultiplied, prepare_image, DecodedImage},
+    },
 };
 
 /// Loader.
@@ -24,17 +33,23 @@ pub struct Loader {
     device: Arc<Device>,
     /// Queue.
     queue: Arc<Queue>,
+    /// Layout of the vertex buffers.
+    vertex_layout: VertexLayout,
     /// GPU future.
     future: Option<Box<dyn GpuFuture>>,
+    /// Samplers shared by the textures.
+    samplers: SamplerCache,
 }
 
 impl Loader {
     /// Creates a new `Loader`.
-    pub fn new(device: Arc<Device>, queue: Arc<Queue>) -> Self {
+    pub fn new(device: Arc<Device>, queue: Arc<Queue>, vertex_layout: VertexLayout) -> Self {
         Self {
             device,
             queue,
+            vertex_layout,
             future: None,
+            samplers: SamplerCache::default(),
         }
     }
 
@@ -43,64 +58,38 @@ impl Loader {
         mut self,
         src_scene: &data::Scene,
     ) -> anyhow::Result<(drawable::Scene, Option<Box<dyn GpuFuture>>)> {
-        let mut scene = drawable::Scene::default();
+        let _span = info_span!("upload_scene").entered();
+        let mut scene = drawable::Scene::new(src_scene.id());
 
         for src_geometry in src_scene.geometry_meshes() {
-            let vertices = src_geometry
-                .positions
-                .iter()
-                .cloned()
-                .map(Into::into)
-                .zip(src_geometry.normals.iter().cloned().map(Into::into))
-                .zip(src_geometry.uv.iter().cloned().map(Into::into))
-                .map(|((position, normal), uv)| drawable::Vertex {
-                    position,
-                    normal,
-                    uv,
-                })
-                .collect::<Vec<_>>();
-            let (vertices, vertices_future) = ImmutableBuffer::from_iter(
-                vertices.into_iter(),
-                BufferUsage::all(),
-                self.queue.clone(),
-            )?;
-            join_futures(&mut self.future, vertices_future);
-
-            let indices_per_material = src_geometry
-                .indices_per_material
-                .iter()
-                .map(|indices| {
-                    let (buf, buf_future) = ImmutableBuffer::from_iter(
-                        indices.iter().cloned(),
-                        BufferUsage::all(),
-                        self.queue.clone(),
-                    )?;
-                    join_futures(&mut self.future, buf_future);
-                    Ok(buf)
-                })
-                .collect::<anyhow::Result<Vec<_>>>()
-                .context("Failed to upload index buffers")?;
-            let bounding_box = src_geometry.bbox_mesh();
-            let geometry = drawable::GeometryMesh {
-                name: src_geometry.name.clone(),
-                vertices,
-                indices_per_material,
-                bounding_box,
-            };
+            let geometry = self.upload_geometry(src_geometry)?;
             scene.geometry_meshes.push(geometry);
         }
 
         for src_material in src_scene.materials() {
             let diffuse_texture_exists = src_material.diffuse_texture.is_some();
-            let data = match src_material.data {
-                data::ShadingData::Lambert(lambert) => fs::ty::Material {
-                    ambient: lambert.ambient.into(),
-                    _dummy0: [0; 4],
-                    diffuse: lambert.diffuse.into(),
-                    emissive: lambert.emissive.into(),
-                    _dummy1: [0; 4],
-                    enabled: !diffuse_texture_exists as u32,
-                },
+            // The renderer has no environment map yet, so the reflection of
+            // Phong materials is ignored.
+            let lambert = src_material.data.lambert();
+            let (specular, shininess) = match &src_material.data {
+                data::ShadingData::Lambert(_) => ([0.0; 3], 0.0),
+                data::ShadingData::Phong(phong) => (phong.specular.into(), phong.shininess),
+            };
+            let pbr = src_material.data.to_pbr();
+            let data = fs::ty::Material {
+                ambient: lambert.ambient.into(),
+                _dummy0: [0; 4],
+                diffuse: lambert.diffuse.into(),
+                emissive: lambert.emissive.into(),
+                _dummy1: [0; 4],
+                enabled: !diffuse_texture_exists as u32,
+                override_mode: material_override_to_shader_value(None),
+                _dummy2: [0; 12],
+                specular,
+                shininess,
+                f0: pbr.f0.into(),
+                roughness: pbr.roughness,
+                alpha_cutoff: src_material.alpha_cutoff.unwrap_or(0.0),
             };
             let (data, data_future) =
                 ImmutableBuffer::from_data(data, BufferUsage::all(), self.queue.clone())
@@ -111,6 +100,8 @@ impl Loader {
                 name: src_material.name.clone(),
                 diffuse_texture: src_material.diffuse_texture,
                 data,
+                double_sided: src_material.double_sided,
+                alpha_tested: src_material.alpha_cutoff.is_some(),
                 cache: Default::default(),
             };
             scene.materials.push(material);
@@ -120,20 +111,9 @@ impl Loader {
             scene.meshes.push(src_mesh.clone());
         }
 
+        // Texture images are decoded and uploaded when they are first
+        // visible.
         for src_texture in src_scene.textures() {
-            let dim = Dimensions::Dim2d {
-                width: src_texture.image.width(),
-                height: src_texture.image.height(),
-            };
-            let (image, image_future) = ImmutableImage::from_iter(
-                src_texture.image.to_rgba8().into_raw().into_iter(),
-                dim,
-                MipmapsCount::One,
-                R8G8B8A8Srgb,
-                self.queue.clone(),
-            )
-            .context("Failed to upload texture image")?;
-            join_futures(&mut self.future, image_future);
             let wrap_mode_u = match src_texture.wrap_mode_u {
                 data::WrapMode::Repeat => SamplerAddressMode::Repeat,
                 data::WrapMode::ClampToEdge => SamplerAddressMode::ClampToEdge,
@@ -142,31 +122,373 @@ impl Loader {
                 data::WrapMode::Repeat => SamplerAddressMode::Repeat,
                 data::WrapMode::ClampToEdge => SamplerAddressMode::ClampToEdge,
             };
-            let sampler = Sampler::new(
-                self.device.clone(),
-                Filter::Linear,
-                Filter::Linear,
-                MipmapMode::Nearest,
-                wrap_mode_u,
-                wrap_mode_v,
-                SamplerAddressMode::Repeat,
-                0.0,
-                1.0,
-                0.0,
-                0.0,
-            )
-            .context("Failed to create sampler")?;
+            let sampler = self.samplers.get(&self.device, wrap_mode_u, wrap_mode_v)?;
 
             let texture = drawable::Texture {
                 name: src_texture.name.clone(),
-                image,
+                source: src_texture.image.clone(),
+                image: None,
                 sampler,
                 transparent: src_texture.transparent,
+                premultiplied: false,
                 cache: Default::default(),
             };
             scene.textures.push(texture);
         }
+        debug!(
+            "{} samplers for {} textures",
+            self.samplers.len(),
+            scene.textures.len()
+        );
 
         Ok((scene, self.future))
     }
+
+    /// Replaces vertex buffers of the scene with the given geometry meshes.
+    ///
+    /// The geometry meshes should be the ones the scene is loaded from, possibly
+    /// with modified vertex attributes.
+    pub(crate) fn reload_vertices<'a>(
+        mut self,
+        scene: &mut drawable::Scene,
+        src_geometries: impl IntoIterator<Item = &'a data::GeometryMesh>,
+    ) -> anyhow::Result<Option<Box<dyn GpuFuture>>> {
+        let _span = info_span!("upload_vertices").entered();
+        for (geometry, src_geometry) in scene.geometry_meshes.iter_mut().zip(src_geometries) {
+            geometry.vertices = self.upload_vertices(src_geometry)?;
+        }
+
+        Ok(self.future)
+    }
+
+    /// Generates and uploads the coarser levels of detail of the geometry
+    /// meshes.
+    ///
+    /// The geometry meshes should be the ones the scene is loaded from.
+    pub(crate) fn upload_lods<'a>(
+        mut self,
+        scene: &mut drawable::Scene,
+        src_geometries: impl IntoIterator<Item = &'a data::GeometryMesh>,
+    ) -> anyhow::Result<Option<Box<dyn GpuFuture>>> {
+        let _span = info_span!("upload_lods").entered();
+        for (geometry, src_geometry) in scene.geometry_meshes.iter_mut().zip(src_geometries) {
+            self.upload_geometry_lods(geometry, src_geometry)?;
+        }
+
+        Ok(self.future)
+    }
+
+    /// Uploads the line lists of the topology problems of the geometry
+    /// meshes, replacing the existing ones.
+    pub(crate) fn upload_topology<'a>(
+        mut self,
+        scene: &mut drawable::Scene,
+        topologies: impl IntoIterator<Item = &'a data::MeshTopology>,
+    ) -> anyhow::Result<Option<Box<dyn GpuFuture>>> {
+        let _span = info_span!("upload_topology").entered();
+        for (geometry, topology) in scene.geometry_meshes.iter_mut().zip(topologies) {
+            let face_edges = topology
+                .duplicate_faces
+                .iter()
+                .flat_map(|&[i0, i1, i2]| [i0, i1, i1, i2, i2, i0])
+                .collect();
+            geometry.topology = Some(drawable::Topology {
+                open_edges: self.upload_lines(topology.open_edges.concat())?,
+                non_manifold_edges: self.upload_lines(topology.non_manifold_edges.concat())?,
+                duplicate_faces: self.upload_lines(face_edges)?,
+            });
+        }
+
+        Ok(self.future)
+    }
+
+    /// Uploads the index buffer of the line list, or returns `None` if it
+    /// is empty.
+    fn upload_lines(
+        &mut self,
+        indices: Vec<u32>,
+    ) -> anyhow::Result<Option<Arc<ImmutableBuffer<[u32]>>>> {
+        if indices.is_empty() {
+            return Ok(None);
+        }
+        let (buf, buf_future) =
+            ImmutableBuffer::from_iter(indices.into_iter(), BufferUsage::all(), self.queue.clone())
+                .context("Failed to upload line indices")?;
+        join_futures(&mut self.future, buf_future);
+
+        Ok(Some(buf))
+    }
+
+    /// Partitions the meshes of the scene into spatial chunks, and uploads
+    /// the merged buffers of the chunks.
+    ///
+    /// The geometry meshes should be the ones the scene is loaded from,
+    /// possibly with modified vertex attributes.
+    /// If `lod` is `true`, the levels of detail of the chunks are also
+    /// generated.
+    pub(crate) fn upload_chunks<'a>(
+        mut self,
+        scene: &mut drawable::Scene,
+        src_scene: &data::Scene,
+        src_geometries: impl IntoIterator<Item = &'a data::GeometryMesh>,
+        lod: bool,
+    ) -> anyhow::Result<Option<Box<dyn GpuFuture>>> {
+        let _span = info_span!("upload_chunks").entered();
+        let src_geometries = src_geometries.into_iter().collect::<Vec<_>>();
+        let merged = chunk::partition(src_scene, &src_geometries, chunk::CELLS)
+            .context("Failed to partition scene into chunks")?;
+        info!(
+            "Partitioned {} meshes into {} chunks",
+            scene.meshes.len(),
+            merged.len()
+        );
+        scene.chunks.clear();
+        for merged in merged {
+            let mut geometry = self.upload_geometry(&merged.geometry)?;
+            if lod {
+                self.upload_geometry_lods(&mut geometry, &merged.geometry)?;
+            }
+            scene.chunks.push(drawable::Chunk {
+                geometry,
+                materials: merged.materials,
+                render_order: merged.render_order,
+            });
+        }
+
+        Ok(self.future)
+    }
+
+    /// Replaces the images of the textures in the scene with the remapped
+    /// image files.
+    ///
+    /// Remaps whose files cannot be loaded or which match no textures are
+    /// skipped with warnings.
+    /// Images larger than `max_texture_dim` are downscaled, and whether they
+    /// are premultiplied by alpha is decided by `premultiplied_alpha`.
+    /// The caches of the replaced textures are reset.
+    pub(crate) fn remap_textures(
+        mut self,
+        scene: &mut drawable::Scene,
+        remaps: &[TextureRemap],
+        max_texture_dim: Option<u32>,
+        premultiplied_alpha: PremultipliedAlpha,
+    ) -> anyhow::Result<Option<Box<dyn GpuFuture>>> {
+        for remap in remaps {
+            let mut textures = scene
+                .textures
+                .iter_mut()
+                .filter(|texture| texture.name.as_deref() == Some(&*remap.name))
+                .peekable();
+            if textures.peek().is_none() {
+                warn!("No textures are named {:?}", remap.name);
+                continue;
+            }
+            let image = match image::open(&remap.path) {
+                Ok(v) => TextureImage::from(v),
+                Err(e) => {
+                    warn!("Failed to load {}: {}", remap.path.display(), e);
+                    continue;
+                }
+            };
+            let premultiplied = is_premultiplied(&image, premultiplied_alpha);
+            let image = self.upload_image(prepare_image(image.get()?, max_texture_dim))?;
+            for texture in textures {
+                info!(
+                    "Replaced texture {:?} with {}",
+                    remap.name,
+                    remap.path.display()
+                );
+                texture.image = Some(image.clone());
+                texture.premultiplied = premultiplied;
+                texture.cache.reset();
+            }
+        }
+
+        Ok(self.future)
+    }
+
+    /// Uploads the decoded images of the textures.
+    ///
+    /// Textures which already have images (such as remapped ones) are left
+    /// as is.
+    /// The caches of the uploaded textures are reset.
+    pub(crate) fn upload_textures(
+        mut self,
+        scene: &mut drawable::Scene,
+        images: impl IntoIterator<Item = (TextureIndex, DecodedImage)>,
+    ) -> anyhow::Result<Option<Box<dyn GpuFuture>>> {
+        let _span = info_span!("upload_textures").entered();
+        for (index, image) in images {
+            let texture = scene.texture_mut(index)?;
+            if texture.image.is_some() {
+                continue;
+            }
+            texture.image = Some(self.upload_image(image.image)?);
+            texture.premultiplied = image.premultiplied;
+            // Images decoded after loading are not scanned by the loaders.
+            if texture.source.is_opaque() == Some(true) {
+                texture.transparent = false;
+            }
+            texture.cache.reset();
+        }
+
+        Ok(self.future)
+    }
+
+    /// Uploads the texture image.
+    fn upload_image(
+        &mut self,
+        image: RgbaImage,
+    ) -> anyhow::Result<Arc<ImmutableImage<R8G8B8A8Srgb>>> {
+        let dim = Dimensions::Dim2d {
+            width: image.width(),
+            height: image.height(),
+        };
+        let (image, image_future) = ImmutableImage::from_iter(
+            image.into_raw().into_iter(),
+            dim,
+            MipmapsCount::One,
+            R8G8B8A8Srgb,
+            self.queue.clone(),
+        )
+        .context("Failed to upload texture image")?;
+        join_futures(&mut self.future, image_future);
+
+        Ok(image)
+    }
+
+    /// Uploads the buffers of the geometry mesh.
+    fn upload_geometry(
+        &mut self,
+        src_geometry: &data::GeometryMesh,
+    ) -> anyhow::Result<drawable::GeometryMesh> {
+        let vertices = self.upload_vertices(src_geometry)?;
+        let indices_per_material = self
+            .upload_indices(&src_geometry.indices_per_material)
+            .context("Failed to upload index buffers")?;
+
+        Ok(drawable::GeometryMesh {
+            name: src_geometry.name.clone(),
+            vertices,
+            indices_per_material,
+            lods: Vec::new(),
+            bounding_box: src_geometry.bbox_mesh(),
+            bounding_sphere: src_geometry.bounding_sphere_mesh(),
+            topology: None,
+        })
+    }
+
+    /// Generates and uploads the coarser levels of detail of the geometry
+    /// mesh, replacing the existing ones.
+    ///
+    /// Levels which remove too few triangles from the finer level are
+    /// skipped.
+    fn upload_geometry_lods(
+        &mut self,
+        geometry: &mut drawable::GeometryMesh,
+        src_geometry: &data::GeometryMesh,
+    ) -> anyhow::Result<()> {
+        geometry.lods.clear();
+        let mut triangles = src_geometry.num_triangles();
+        for &cells in &lod::GRID_CELLS {
+            let indices_per_material = src_geometry.clustered_indices(cells);
+            let lod_triangles = indices_per_material
+                .iter()
+                .map(|indices| indices.len() / 3)
+                .sum::<usize>();
+            if lod_triangles as f32 > triangles as f32 * lod::MAX_TRIANGLE_RATIO {
+                continue;
+            }
+            debug!(
+                "Level of detail {} of {:?}: {} triangles",
+                geometry.lods.len() + 1,
+                geometry.name,
+                lod_triangles
+            );
+            triangles = lod_triangles;
+            let indices_per_material = self
+                .upload_indices(&indices_per_material)
+                .context("Failed to upload index buffers of level of detail")?;
+            geometry.lods.push(drawable::Lod {
+                cells,
+                indices_per_material,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Uploads the index buffers per materials.
+    fn upload_indices(
+        &mut self,
+        indices_per_material: &[Vec<u32>],
+    ) -> anyhow::Result<Vec<Arc<ImmutableBuffer<[u32]>>>> {
+        indices_per_material
+            .iter()
+            .map(|indices| {
+                let (buf, buf_future) = ImmutableBuffer::from_iter(
+                    indices.iter().cloned(),
+                    BufferUsage::all(),
+                    self.queue.clone(),
+                )?;
+                join_futures(&mut self.future, buf_future);
+                Ok(buf)
+            })
+            .collect()
+    }
+
+    /// Uploads the vertices of the geometry mesh.
+    fn upload_vertices(
+        &mut self,
+        src_geometry: &data::GeometryMesh,
+    ) -> anyhow::Result<Arc<dyn BufferAccess + Send + Sync>> {
+        // Meshes without vertex colors are white.
+        let colors = src_geometry
+            .colors
+            .iter()
+            .map(|c| [c.r, c.g, c.b, c.a])
+            .chain(std::iter::repeat([1.0; 4]));
+        let vertices = src_geometry
+            .positions
+            .iter()
+            .cloned()
+            .map(Into::into)
+            .zip(src_geometry.normals.iter().cloned().map(Into::into))
+            .zip(src_geometry.uv.iter().cloned().map(Into::into))
+            .zip(colors)
+            .map(|(((position, normal), uv), color)| drawable::Vertex {
+                position,
+                normal,
+                uv,
+                color,
+                // Only one UV set is loaded for now, so the second UV set
+                // falls back to the first one.
+                uv1: uv,
+            });
+        let vertices: Arc<dyn BufferAccess + Send + Sync> = match self.vertex_layout {
+            VertexLayout::Full => {
+                let (vertices, vertices_future) = ImmutableBuffer::from_iter(
+                    vertices.collect::<Vec<_>>().into_iter(),
+                    BufferUsage::all(),
+                    self.queue.clone(),
+                )?;
+                join_futures(&mut self.future, vertices_future);
+                vertices
+            }
+            VertexLayout::Compact => {
+                let (vertices, vertices_future) = ImmutableBuffer::from_iter(
+                    vertices
+                        .map(drawable::CompactVertex::from)
+                        .collect::<Vec<_>>()
+                        .into_iter(),
+                    BufferUsage::all(),
+                    self.queue.clone(),
+                )?;
+                join_futures(&mut self.future, vertices_future);
+                vertices
+            }
+        };
+
+        Ok(vertices)
+    }
 }
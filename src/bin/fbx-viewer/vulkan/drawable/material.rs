@@ -16,6 +16,11 @@ pub struct Material {
     pub(crate) diffuse_texture: Option<TextureIndex>,
     /// Shading parameters.
     pub(crate) data: Arc<ImmutableBuffer<ShaderMaterial>>,
+    /// Whether the back faces are visible.
+    pub(crate) double_sided: bool,
+    /// Whether the material is drawn as a cutout instead of being alpha
+    /// blended.
+    pub(crate) alpha_tested: bool,
     /// Cache.
     pub(crate) cache: MaterialCache,
 }
@@ -25,6 +30,8 @@ impl fmt::Debug for Material {
         f.debug_struct("Material")
             .field("name", &self.name)
             .field("diffuse_texture", &self.diffuse_texture)
+            .field("double_sided", &self.double_sided)
+            .field("alpha_tested", &self.alpha_tested)
             .finish()
     }
 }
@@ -2,30 +2,25 @@
 
 use std::sync::Arc;
 
-use anyhow::Context;
 use fbx_viewer::{
-    data::{GeometryMeshIndex, MaterialIndex, TextureIndex},
-    util::bbox::OptionalBoundingBox3d,
+    data::{GeometryMeshIndex, IndexError, MaterialIndex, MeshIndex, SceneId, TextureIndex},
+    util::bbox::{BoundingSphere, OptionalBoundingBox3d},
 };
 use vulkano::{
-    buffer::ImmutableBuffer,
-    descriptor::{
-        descriptor_set::{PersistentDescriptorSet, PersistentDescriptorSetBuf},
-        pipeline_layout::PipelineLayoutAbstract,
-    },
-    pipeline::GraphicsPipeline,
-    sync::GpuFuture,
+    buffer::BufferAccess, descriptor::pipeline_layout::PipelineLayoutAbstract,
+    pipeline::GraphicsPipeline, sync::GpuFuture,
 };
 
 use crate::vulkan::{
-    drawable::{GeometryMesh, Material, Mesh, Texture},
-    fs::ty::Material as ShaderMaterial,
-    setup::create_diffuse_texture_desc_set,
+    drawable::{Chunk, GeometryMesh, Material, Mesh, Texture},
+    setup::{create_diffuse_texture_desc_set, create_material_desc_set},
 };
 
 /// Scene.
-#[derive(Default, Debug, Clone)]
+#[derive(Debug, Clone)]
 pub struct Scene {
+    /// ID of the source scene.
+    pub(crate) scene_id: SceneId,
     /// Name.
     #[allow(dead_code)]
     pub(crate) name: Option<String>,
@@ -37,22 +32,52 @@ pub struct Scene {
     pub(crate) meshes: Vec<Mesh>,
     /// Textures.
     pub(crate) textures: Vec<Texture>,
+    /// Spatial chunks of the meshes, drawn in place of the meshes if any.
+    pub(crate) chunks: Vec<Chunk>,
 }
 
 impl Scene {
+    /// Creates a new empty `Scene` for the given source scene.
+    pub fn new(scene_id: SceneId) -> Self {
+        Self {
+            scene_id,
+            name: None,
+            geometry_meshes: Vec::new(),
+            materials: Vec::new(),
+            meshes: Vec::new(),
+            textures: Vec::new(),
+            chunks: Vec::new(),
+        }
+    }
+
     /// Returns a reference to the geometry mesh.
-    pub fn geometry_mesh(&self, i: GeometryMeshIndex) -> Option<&GeometryMesh> {
-        self.geometry_meshes.get(i.to_usize())
+    pub fn geometry_mesh(&self, i: GeometryMeshIndex) -> Result<&GeometryMesh, IndexError> {
+        i.resolve(self.scene_id, &self.geometry_meshes)
     }
 
     /// Returns a reference to the material.
-    pub fn material(&self, i: MaterialIndex) -> Option<&Material> {
-        self.materials.get(i.to_usize())
+    pub fn material(&self, i: MaterialIndex) -> Result<&Material, IndexError> {
+        i.resolve(self.scene_id, &self.materials)
+    }
+
+    /// Returns a reference to the mesh.
+    pub fn mesh(&self, i: MeshIndex) -> Result<&Mesh, IndexError> {
+        i.resolve(self.scene_id, &self.meshes)
+    }
+
+    /// Returns a mutable reference to the mesh.
+    pub fn mesh_mut(&mut self, i: MeshIndex) -> Result<&mut Mesh, IndexError> {
+        i.resolve_mut(self.scene_id, &mut self.meshes)
     }
 
     /// Returns a reference to the texture.
-    pub fn texture(&self, i: TextureIndex) -> Option<&Texture> {
-        self.textures.get(i.to_usize())
+    pub fn texture(&self, i: TextureIndex) -> Result<&Texture, IndexError> {
+        i.resolve(self.scene_id, &self.textures)
+    }
+
+    /// Returns a mutable reference to the texture.
+    pub fn texture_mut(&mut self, i: TextureIndex) -> Result<&mut Texture, IndexError> {
+        i.resolve_mut(self.scene_id, &mut self.textures)
     }
 
     /// Returns bounding box of all geometries.
@@ -63,6 +88,45 @@ impl Scene {
             .collect()
     }
 
+    /// Returns bounding sphere of all geometries.
+    pub fn bounding_sphere(&self) -> Option<BoundingSphere<f32>> {
+        self.geometry_meshes
+            .iter()
+            .filter_map(|gm| gm.bounding_sphere)
+            .reduce(|sum, sphere| sum.union(&sphere))
+    }
+
+    /// Returns the estimated GPU memory used by the buffers and the images
+    /// uploaded so far in bytes.
+    pub fn estimated_memory(&self) -> u64 {
+        let geometries: usize = self
+            .geometry_meshes
+            .iter()
+            .chain(self.chunks.iter().map(|chunk| &chunk.geometry))
+            .map(|gm| {
+                gm.vertices.size()
+                    + gm.indices_per_material
+                        .iter()
+                        .chain(gm.lods.iter().flat_map(|lod| &lod.indices_per_material))
+                        .map(|indices| indices.size())
+                        .sum::<usize>()
+            })
+            .sum();
+        let materials: usize = self.materials.iter().map(|m| m.data.size()).sum();
+        // Textures are uploaded as 4 bytes per texel without mipmaps.
+        let textures: u64 = self
+            .textures
+            .iter()
+            .filter_map(|t| t.image.as_ref())
+            .map(|image| {
+                let dim = image.dimensions();
+                u64::from(dim.width()) * u64::from(dim.height()) * 4
+            })
+            .sum();
+
+        geometries as u64 + materials as u64 + textures
+    }
+
     /// Reset and initialize caches with the given pipeline.
     pub fn reset_cache_with_pipeline<Mv, L, Rp>(
         &mut self,
@@ -81,44 +145,18 @@ impl Scene {
             )?);
         }
 
+        // Textures which are not uploaded yet have no descriptor sets.
         for texture in &mut self.textures {
             texture.cache.reset();
-            texture.cache.descriptor_set = Some(create_diffuse_texture_desc_set(
-                texture.image.clone(),
-                texture.sampler.clone(),
-                pipeline.clone(),
-            )?);
+            if let Some(image) = &texture.image {
+                texture.cache.descriptor_set = Some(create_diffuse_texture_desc_set(
+                    image.clone(),
+                    texture.sampler.clone(),
+                    pipeline.clone(),
+                )?);
+            }
         }
 
         Ok(future)
     }
 }
-
-/// Creates a descriptor set for the given material uniform buffer.
-#[allow(clippy::type_complexity)]
-fn create_material_desc_set<Mv, L, Rp>(
-    material_buf: Arc<ImmutableBuffer<ShaderMaterial>>,
-    pipeline: Arc<GraphicsPipeline<Mv, L, Rp>>,
-) -> anyhow::Result<
-    Arc<
-        PersistentDescriptorSet<(
-            (),
-            PersistentDescriptorSetBuf<Arc<ImmutableBuffer<ShaderMaterial>>>,
-        )>,
-    >,
->
-where
-    L: PipelineLayoutAbstract,
-{
-    let layout = pipeline
-        .layout()
-        .descriptor_set_layout(2)
-        .context("Failed to get the second descriptor set layout of the pipeline")?;
-    let desc_set = PersistentDescriptorSet::start(layout.clone())
-        .add_buffer(material_buf)
-        .context("Failed to add material data to descriptor set")?
-        .build()
-        .context("Failed to build material descriptor set")?;
-
-    Ok(Arc::new(desc_set) as Arc<_>)
-}
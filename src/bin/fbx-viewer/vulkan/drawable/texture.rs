@@ -1,10 +1,15 @@
 //! Texture.
 
-use std::{fmt, sync::Arc};
+use std::{collections::HashMap, fmt, sync::Arc};
 
+use anyhow::Context;
+use fbx_viewer::data::TextureImage;
 use vulkano::{
-    descriptor::descriptor_set::DescriptorSet, format::R8G8B8A8Srgb, image::ImmutableImage,
-    sampler::Sampler,
+    descriptor::descriptor_set::DescriptorSet,
+    device::Device,
+    format::R8G8B8A8Srgb,
+    image::ImmutableImage,
+    sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode},
 };
 
 /// Texture.
@@ -12,14 +17,22 @@ use vulkano::{
 pub struct Texture {
     /// Name.
     pub(crate) name: Option<String>,
-    /// Image.
-    pub(crate) image: Arc<ImmutableImage<R8G8B8A8Srgb>>,
+    /// Source image.
+    pub(crate) source: TextureImage,
+    /// Uploaded image.
+    ///
+    /// `None` until the texture is first visible.
+    pub(crate) image: Option<Arc<ImmutableImage<R8G8B8A8Srgb>>>,
     /// Sampler.
     pub(crate) sampler: Arc<Sampler>,
     /// Whether the texture can be transparent.
     ///
     /// If `false`, the texture can be assumed to have no transparent texels.
+    /// This is cleared when the image is uploaded if it has no transparent
+    /// texels.
     pub(crate) transparent: bool,
+    /// Whether the colors of the uploaded image are premultiplied by alpha.
+    pub(crate) premultiplied: bool,
     /// Cache.
     pub(crate) cache: TextureCache,
 }
@@ -29,6 +42,7 @@ impl fmt::Debug for Texture {
         f.debug_struct("Texture")
             .field("name", &self.name)
             .field("transparent", &self.transparent)
+            .field("premultiplied", &self.premultiplied)
             .field("image", &self.image)
             .field("sampler", &self.sampler)
             .finish()
@@ -48,3 +62,58 @@ impl TextureCache {
         *self = Default::default();
     }
 }
+
+/// Cache of the texture samplers, keyed by the wrap modes.
+///
+/// Textures with the same wrap modes share a sampler.
+#[derive(Default)]
+pub struct SamplerCache {
+    /// Created samplers.
+    samplers: HashMap<[SamplerAddressMode; 2], Arc<Sampler>>,
+}
+
+impl SamplerCache {
+    /// Returns the sampler with the wrap modes, creating it if necessary.
+    pub fn get(
+        &mut self,
+        device: &Arc<Device>,
+        wrap_mode_u: SamplerAddressMode,
+        wrap_mode_v: SamplerAddressMode,
+    ) -> anyhow::Result<Arc<Sampler>> {
+        let key = [wrap_mode_u, wrap_mode_v];
+        if let Some(sampler) = self.samplers.get(&key) {
+            return Ok(sampler.clone());
+        }
+        let sampler = create_sampler(device.clone(), wrap_mode_u, wrap_mode_v)?;
+        self.samplers.insert(key, sampler.clone());
+
+        Ok(sampler)
+    }
+
+    /// Returns the number of the created samplers.
+    pub fn len(&self) -> usize {
+        self.samplers.len()
+    }
+}
+
+/// Creates a sampler for textures with the wrap modes.
+pub fn create_sampler(
+    device: Arc<Device>,
+    wrap_mode_u: SamplerAddressMode,
+    wrap_mode_v: SamplerAddressMode,
+) -> anyhow::Result<Arc<Sampler>> {
+    Sampler::new(
+        device,
+        Filter::Linear,
+        Filter::Linear,
+        MipmapMode::Nearest,
+        wrap_mode_u,
+        wrap_mode_v,
+        SamplerAddressMode::Repeat,
+        0.0,
+        1.0,
+        0.0,
+        0.0,
+    )
+    .context("Failed to create sampler")
+}
@@ -2,6 +2,21 @@
 // Allow `clippy::needless_borrow` for `vulkano::impl_vertex` macro.
 #![allow(clippy::needless_borrow)]
 
+use std::sync::Arc;
+
+use half::f16;
+use vulkano::{
+    buffer::BufferAccess,
+    format::Format,
+    pipeline::{
+        shader::ShaderInterfaceDef,
+        vertex::{
+            AttributeInfo, IncompatibleVertexDefinitionError, InputRate, Vertex as _,
+            VertexDefinition, VertexSource,
+        },
+    },
+};
+
 /// Vertex.
 #[derive(Default, Debug, Clone, Copy)]
 pub struct Vertex {
@@ -11,6 +26,153 @@ pub struct Vertex {
     pub normal: [f32; 3],
     /// UV.
     pub uv: [f32; 2],
+    /// Vertex color.
+    pub color: [f32; 4],
+    /// Second UV, for lightmaps and ambient occlusion maps.
+    pub uv1: [f32; 2],
+}
+
+vulkano::impl_vertex!(Vertex, position, normal, uv, color, uv1);
+
+/// Compact vertex.
+///
+/// Half the size of [`Vertex`], for huge meshes.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct CompactVertex {
+    /// Position.
+    pub position: [f32; 3],
+    /// Normal, as 10-10-10-2 snorm.
+    pub normal: u32,
+    /// UV, as half floats.
+    pub uv: [u16; 2],
+    /// Vertex color, as 8-bit unorm.
+    pub color: [u8; 4],
+    /// Second UV, as half floats.
+    pub uv1: [u16; 2],
+}
+
+vulkano::impl_vertex!(CompactVertex, position, normal, uv, color, uv1);
+
+impl From<Vertex> for CompactVertex {
+    fn from(v: Vertex) -> Self {
+        Self {
+            position: v.position,
+            normal: pack_snorm_10_10_10_2(v.normal),
+            uv: pack_half2(v.uv),
+            color: v.color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8),
+            uv1: pack_half2(v.uv1),
+        }
+    }
+}
+
+/// Packs the vector as `A2B10G10R10_SNORM_PACK32` with zero alpha.
+fn pack_snorm_10_10_10_2(v: [f32; 3]) -> u32 {
+    let [r, g, b] = v.map(|c| ((c.clamp(-1.0, 1.0) * 511.0).round() as i32 as u32) & 0x3ff);
+    r | (g << 10) | (b << 20)
+}
+
+/// Packs the vector as `R16G16_SFLOAT`.
+fn pack_half2(v: [f32; 2]) -> [u16; 2] {
+    v.map(|c| f16::from_f32(c).to_bits())
+}
+
+/// Layout of vertex buffers.
+///
+/// The vertex shaders read the same attributes from both layouts, since
+/// Vulkan converts the packed formats to floats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VertexLayout {
+    /// [`Vertex`].
+    Full,
+    /// [`CompactVertex`].
+    Compact,
+}
+
+impl VertexLayout {
+    /// Returns the size of a vertex in bytes.
+    pub fn stride(self) -> usize {
+        match self {
+            Self::Full => std::mem::size_of::<Vertex>(),
+            Self::Compact => std::mem::size_of::<CompactVertex>(),
+        }
+    }
+
+    /// Returns the formats used by the layout.
+    pub fn formats(self) -> impl Iterator<Item = Format> {
+        ["position", "normal", "uv", "color", "uv1"]
+            .iter()
+            .filter_map(move |name| self.attribute(name))
+            .map(|(_, format)| format)
+    }
+
+    /// Returns the offset and the format of the attribute.
+    fn attribute(self, name: &str) -> Option<(usize, Format)> {
+        let format = match (self, name) {
+            (_, "position") => Format::R32G32B32Sfloat,
+            (Self::Full, "normal") => Format::R32G32B32Sfloat,
+            (Self::Full, "uv" | "uv1") => Format::R32G32Sfloat,
+            (Self::Full, "color") => Format::R32G32B32A32Sfloat,
+            (Self::Compact, "normal") => Format::A2B10G10R10SnormPack32,
+            (Self::Compact, "uv" | "uv1") => Format::R16G16Sfloat,
+            (Self::Compact, "color") => Format::R8G8B8A8Unorm,
+            _ => return None,
+        };
+        let member = match self {
+            Self::Full => Vertex::member(name)?,
+            Self::Compact => CompactVertex::member(name)?,
+        };
+        Some((member.offset, format))
+    }
 }
 
-vulkano::impl_vertex!(Vertex, position, normal, uv);
+unsafe impl<I> VertexDefinition<I> for VertexLayout
+where
+    I: ShaderInterfaceDef,
+{
+    type BuffersIter = std::option::IntoIter<(u32, usize, InputRate)>;
+    type AttribsIter = std::vec::IntoIter<(u32, u32, AttributeInfo)>;
+
+    fn definition(
+        &self,
+        interface: &I,
+    ) -> Result<(Self::BuffersIter, Self::AttribsIter), IncompatibleVertexDefinitionError> {
+        // Unlike `SingleBufferDefinition`, the formats come from the layout
+        // rather than the shader, since the shader inputs are all floats.
+        // Every attribute occupies a single location.
+        let attribs = interface
+            .elements()
+            .map(|e| {
+                let name = e.name.as_deref().unwrap_or_default();
+                let (offset, format) = self.attribute(name).ok_or_else(|| {
+                    IncompatibleVertexDefinitionError::MissingAttribute {
+                        attribute: name.to_owned(),
+                    }
+                })?;
+                Ok((e.location.start, 0, AttributeInfo { offset, format }))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let buffers = Some((0, self.stride(), InputRate::Vertex));
+
+        Ok((buffers.into_iter(), attribs.into_iter()))
+    }
+}
+
+unsafe impl VertexSource<Arc<dyn BufferAccess + Send + Sync>> for VertexLayout {
+    fn decode(
+        &self,
+        source: Arc<dyn BufferAccess + Send + Sync>,
+    ) -> (Vec<Box<dyn BufferAccess + Send + Sync>>, usize, usize) {
+        let len = source.size() / self.stride();
+        (vec![Box::new(source)], len, 1)
+    }
+}
+
+unsafe impl VertexSource<Vec<Arc<dyn BufferAccess + Send + Sync>>> for VertexLayout {
+    fn decode(
+        &self,
+        mut source: Vec<Arc<dyn BufferAccess + Send + Sync>>,
+    ) -> (Vec<Box<dyn BufferAccess + Send + Sync>>, usize, usize) {
+        assert_eq!(source.len(), 1, "Only a single vertex buffer is supported");
+        <Self as VertexSource<Arc<_>>>::decode(self, source.remove(0))
+    }
+}
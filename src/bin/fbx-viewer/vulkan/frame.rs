@@ -0,0 +1,267 @@
+//! Per-frame resources.
+//!
+//! Up to [`FRAMES_IN_FLIGHT`] frames are rendered at the same time.
+//! Each frame has its own uniform buffer and fence, and the resources of a
+//! frame are reused only after its fence is signalled.
+//!
+//! The uniform buffer of a frame has a slot for each view and each mesh
+//! transform. The slots are selected by dynamic offsets, so the descriptor
+//! set is created only when the buffer grows for more transforms.
+
+use std::{mem, sync::Arc};
+
+use anyhow::{ensure, Context};
+use cgmath::Matrix4;
+use vulkano::{
+    buffer::{BufferSlice, BufferUsage, CpuAccessibleBuffer, TypedBufferAccess},
+    descriptor::{
+        descriptor_set::{DescriptorSet, PersistentDescriptorSet, UnsafeDescriptorSetLayout},
+        pipeline_layout::PipelineLayoutAbstract,
+    },
+    device::Device,
+    swapchain::PresentFuture,
+    sync::{FenceSignalFuture, FlushError, GpuFuture},
+};
+use winit::window::Window;
+
+use super::{view::MAX_VIEWS, vs};
+
+/// Number of frames rendered at the same time.
+pub const FRAMES_IN_FLIGHT: usize = 2;
+
+/// Future signalled when a frame is presented.
+pub type FrameFence = FenceSignalFuture<PresentFuture<Box<dyn GpuFuture>, Window>>;
+
+/// Uniforms of a view, padded to be placed at valid dynamic offsets.
+///
+/// 256 bytes is the largest `minUniformBufferOffsetAlignment` allowed by the
+/// Vulkan specification.
+#[derive(Clone, Copy)]
+#[repr(C, align(256))]
+struct UniformSlot(vs::ty::Data);
+
+/// Resources of a frame.
+pub struct Frame {
+    /// Device.
+    device: Arc<Device>,
+    /// Layout of the descriptor set.
+    layout: Arc<UnsafeDescriptorSetLayout>,
+    /// Uniform buffer with a slot for each view and each transform.
+    uniform_buffer: Arc<CpuAccessibleBuffer<[UniformSlot]>>,
+    /// Descriptor set with a slot of the uniform buffer.
+    set0: Arc<dyn DescriptorSet + Send + Sync>,
+    /// Fence of the last submission using the resources.
+    fence: Option<Arc<FrameFence>>,
+}
+
+impl Frame {
+    /// Creates a new `Frame`.
+    fn new(device: Arc<Device>, layout: Arc<UnsafeDescriptorSetLayout>) -> anyhow::Result<Self> {
+        let (uniform_buffer, set0) = Self::allocate(device.clone(), layout.clone(), MAX_VIEWS)?;
+
+        Ok(Self {
+            device,
+            layout,
+            uniform_buffer,
+            set0,
+            fence: None,
+        })
+    }
+
+    /// Creates a uniform buffer with the number of slots, and the descriptor
+    /// set with it.
+    #[allow(clippy::type_complexity)]
+    fn allocate(
+        device: Arc<Device>,
+        layout: Arc<UnsafeDescriptorSetLayout>,
+        slots: usize,
+    ) -> anyhow::Result<(
+        Arc<CpuAccessibleBuffer<[UniformSlot]>>,
+        Arc<dyn DescriptorSet + Send + Sync>,
+    )> {
+        // The initial values are never read, since the uniforms are written
+        // before every draw.
+        let initial = UniformSlot(vs::ty::Data {
+            world: [[0.0; 4]; 4],
+            view: [[0.0; 4]; 4],
+            proj: [[0.0; 4]; 4],
+            shading_mode: 0,
+            depth_near: 0.0,
+            depth_far: 0.0,
+            highlight_back_faces: 0,
+            depth_steps: 0.0,
+        });
+        let uniform_buffer = CpuAccessibleBuffer::from_iter(
+            device,
+            BufferUsage::uniform_buffer(),
+            false,
+            std::iter::repeat_n(initial, slots),
+        )
+        .context("Failed to create uniform buffer")?;
+        let slot = BufferSlice::from_typed_buffer_access(uniform_buffer.clone())
+            .index(0)
+            .context("Uniform buffer should not be empty")?;
+        let set0 = Arc::new(
+            PersistentDescriptorSet::start(layout)
+                .add_buffer(slot)
+                .context("Failed to add uniform buffer to descriptor set")?
+                .build()
+                .context("Failed to build descriptor set")?,
+        );
+
+        Ok((uniform_buffer, set0))
+    }
+
+    /// Writes the uniforms of the views, and the copies of them with the
+    /// world matrices replaced by each of the transforms.
+    ///
+    /// The buffer grows if it has too few slots for the transforms, and then
+    /// the descriptor set is replaced.
+    pub fn write_uniforms(
+        &mut self,
+        uniforms: impl ExactSizeIterator<Item = vs::ty::Data> + Clone,
+        transforms: &[Matrix4<f32>],
+    ) -> anyhow::Result<()> {
+        ensure!(
+            uniforms.len() <= MAX_VIEWS,
+            "Too many views: {} > {}",
+            uniforms.len(),
+            MAX_VIEWS
+        );
+        let num_slots = MAX_VIEWS * (transforms.len() + 1);
+        if self.uniform_buffer.len() < num_slots {
+            let (uniform_buffer, set0) =
+                Self::allocate(self.device.clone(), self.layout.clone(), num_slots)?;
+            self.uniform_buffer = uniform_buffer;
+            self.set0 = set0;
+        }
+        let mut slots = self
+            .uniform_buffer
+            .write()
+            .context("Failed to lock uniform buffer")?;
+        for (slot, data) in slots.iter_mut().zip(uniforms.clone()) {
+            slot.0 = data;
+        }
+        for (transform_i, transform) in transforms.iter().enumerate() {
+            let start = (transform_i + 1) * MAX_VIEWS;
+            for (slot, data) in slots[start..].iter_mut().zip(uniforms.clone()) {
+                slot.0 = vs::ty::Data {
+                    world: (Matrix4::from(data.world) * transform).into(),
+                    ..data
+                };
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the descriptor set with the uniforms.
+    ///
+    /// The set should be bound with the dynamic offset returned by
+    /// [`Frame::uniform_offset`].
+    pub fn set0(&self) -> &Arc<dyn DescriptorSet + Send + Sync> {
+        &self.set0
+    }
+
+    /// Returns the dynamic offset of the uniforms of the view with the
+    /// transform.
+    ///
+    /// Transform 0 is the identity, and transform `i` is the `i - 1`th one
+    /// given to [`Frame::write_uniforms`].
+    pub fn uniform_offset(view_i: usize, transform: usize) -> u32 {
+        ((transform * MAX_VIEWS + view_i) * mem::size_of::<UniformSlot>()) as u32
+    }
+}
+
+/// Frames in flight.
+pub struct Frames {
+    /// Device.
+    device: Arc<Device>,
+    /// Frames.
+    frames: Vec<Frame>,
+    /// Index of the current frame.
+    current: usize,
+    /// GPU operations which the next frame should wait for, such as uploads.
+    pending: Option<Box<dyn GpuFuture>>,
+}
+
+impl Frames {
+    /// Creates a new `Frames` for the pipeline.
+    ///
+    /// The uniform buffer at set 0, binding 0 of the pipeline should be
+    /// dynamic.
+    pub fn new(device: Arc<Device>, pipeline: &dyn PipelineLayoutAbstract) -> anyhow::Result<Self> {
+        let layout = pipeline
+            .descriptor_set_layout(0)
+            .context("Failed to get the first descriptor set layout of the pipeline")?;
+        let frames = (0..FRAMES_IN_FLIGHT)
+            .map(|_| Frame::new(device.clone(), layout.clone()))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            device,
+            frames,
+            current: 0,
+            pending: None,
+        })
+    }
+
+    /// Makes the next frame wait for the given GPU operations.
+    pub fn wait_for(&mut self, future: Box<dyn GpuFuture>) {
+        self.pending = Some(match self.pending.take() {
+            Some(pending) => pending.join(future).boxed(),
+            None => future,
+        });
+    }
+
+    /// Waits until the resources of the current frame become available, and
+    /// returns them.
+    pub fn begin(&mut self) -> Result<&mut Frame, FlushError> {
+        if let Some(pending) = self.pending.as_mut() {
+            pending.cleanup_finished();
+        }
+        let frame = &mut self.frames[self.current];
+        if let Some(fence) = frame.fence.take() {
+            fence.wait(None)?;
+        }
+        Ok(frame)
+    }
+
+    /// Returns the future which the current frame should start after.
+    ///
+    /// This is the submission of the previous frame joined with the pending
+    /// operations.
+    pub fn start_future(&mut self) -> Box<dyn GpuFuture> {
+        let previous = (self.current + FRAMES_IN_FLIGHT - 1) % FRAMES_IN_FLIGHT;
+        let future = match self.frames[previous].fence.clone() {
+            Some(fence) => fence.boxed(),
+            None => vulkano::sync::now(self.device.clone()).boxed(),
+        };
+        match self.pending.take() {
+            Some(pending) => future.join(pending).boxed(),
+            None => future,
+        }
+    }
+
+    /// Waits until the last finished frame is executed.
+    pub fn wait_last(&self) -> Result<(), FlushError> {
+        let last = (self.current + FRAMES_IN_FLIGHT - 1) % FRAMES_IN_FLIGHT;
+        match &self.frames[last].fence {
+            Some(fence) => fence.wait(None),
+            None => Ok(()),
+        }
+    }
+
+    /// Finishes the current frame with the result of its submission, and
+    /// advances to the next frame.
+    ///
+    /// If the submission failed, the frame has no fence to wait for.
+    // The fence is shared only by the frames on the render thread, and
+    // `Arc` is required since `Rc` does not implement `GpuFuture`.
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn end(&mut self, fence: Result<FrameFence, FlushError>) -> Result<(), FlushError> {
+        let result = fence.map(|fence| self.frames[self.current].fence = Some(Arc::new(fence)));
+        self.current = (self.current + 1) % FRAMES_IN_FLIGHT;
+        result
+    }
+}
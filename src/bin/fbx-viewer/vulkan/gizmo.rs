@@ -0,0 +1,115 @@
+//! Drawing of the transform gizmo.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use vulkano::{
+    buffer::{BufferUsage, CpuAccessibleBuffer},
+    command_buffer::{AutoCommandBufferBuilder, DynamicState},
+    descriptor::{descriptor_set::DescriptorSet, pipeline_layout::PipelineLayoutAbstract},
+    device::Device,
+    framebuffer::{RenderPassAbstract, Subpass},
+    pipeline::{vertex::SingleBufferDefinition, GraphicsPipeline},
+};
+
+use crate::{
+    renderer::gizmo::Gizmo,
+    vulkan::{
+        tangent_frames::LineVertex,
+        wireframe_overlay::{fs, vs},
+    },
+};
+
+/// Pipeline to draw the transform gizmo.
+///
+/// The gizmo is drawn without depth test, so that the handles inside the
+/// mesh are also visible.
+pub struct GizmoPipeline {
+    /// Device.
+    device: Arc<Device>,
+    /// Pipeline.
+    #[allow(clippy::type_complexity)]
+    pipeline: Arc<
+        GraphicsPipeline<
+            SingleBufferDefinition<LineVertex>,
+            Box<dyn PipelineLayoutAbstract + Send + Sync>,
+            Arc<dyn RenderPassAbstract + Send + Sync>,
+        >,
+    >,
+}
+
+impl GizmoPipeline {
+    /// Creates a new `GizmoPipeline` for the first subpass of the render
+    /// pass.
+    pub fn new(
+        device: Arc<Device>,
+        render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    ) -> anyhow::Result<Self> {
+        let vs = vs::Shader::load(device.clone()).context("Failed to load gizmo vertex shader")?;
+        let fs =
+            fs::Shader::load(device.clone()).context("Failed to load gizmo fragment shader")?;
+
+        let pipeline = GraphicsPipeline::start()
+            .vertex_input_single_buffer::<LineVertex>()
+            .vertex_shader(vs.main_entry_point(), ())
+            .line_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs.main_entry_point(), ())
+            .render_pass(
+                Subpass::from(render_pass, 0).ok_or_else(|| anyhow!("Failed to create subpass"))?,
+            )
+            .with_auto_layout(device.clone(), &[(0, 0)])
+            .map(Arc::new)
+            .context("Failed to create gizmo pipeline")?;
+
+        Ok(Self { device, pipeline })
+    }
+
+    /// Draws the handles of the gizmo.
+    ///
+    /// `set0` should be the descriptor set with the untransformed uniforms at
+    /// `uniform_offset`.
+    pub fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder,
+        dynamic_state: &DynamicState,
+        set0: Arc<dyn DescriptorSet + Send + Sync>,
+        uniform_offset: u32,
+        gizmo: &Gizmo,
+    ) -> anyhow::Result<()> {
+        for axis in 0..3 {
+            let vertices = gizmo
+                .handle_lines(axis)
+                .into_iter()
+                .flatten()
+                .map(|position| LineVertex {
+                    position: position.into(),
+                })
+                .collect::<Vec<_>>();
+            if vertices.is_empty() {
+                continue;
+            }
+            let buffer = CpuAccessibleBuffer::from_iter(
+                self.device.clone(),
+                BufferUsage::vertex_buffer(),
+                false,
+                vertices.into_iter(),
+            )
+            .context("Failed to create gizmo vertex buffer")?;
+            builder
+                .draw(
+                    self.pipeline.clone(),
+                    dynamic_state,
+                    buffer,
+                    set0.clone(),
+                    fs::ty::PushConstants {
+                        color: gizmo.color(axis),
+                    },
+                    std::iter::once(uniform_offset),
+                )
+                .context("Failed to draw gizmo")?;
+        }
+
+        Ok(())
+    }
+}
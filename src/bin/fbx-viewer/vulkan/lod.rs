@@ -0,0 +1,60 @@
+//! Levels of detail.
+
+use cgmath::{MetricSpace, Point3};
+use fbx_viewer::util::bbox::BoundingSphere;
+
+use crate::renderer::PERSPECTIVE;
+
+/// Cells along the longest axis of the meshes to cluster the vertices into,
+/// for each generated level of detail from the finest.
+pub const GRID_CELLS: [u32; 3] = [64, 24, 8];
+
+/// Maximum ratio of the triangles of a level to the ones of the finer level.
+///
+/// Levels removing fewer triangles are not generated.
+pub const MAX_TRIANGLE_RATIO: f32 = 0.75;
+
+/// Projected size of the cells in pixels up to which a level is used.
+const MAX_CELL_PIXELS: f32 = 2.0;
+
+/// Ratio of the cell size thresholds to switch to coarser and finer levels.
+///
+/// Meshes around a threshold keep their current levels, so that they do not
+/// flicker between the levels.
+const HYSTERESIS: f32 = 1.25;
+
+/// Returns the diameter of the bounding sphere projected on the viewport in
+/// pixels.
+///
+/// Spheres containing the eye are infinitely large.
+pub fn projected_diameter(
+    sphere: &BoundingSphere<f32>,
+    eye: Point3<f32>,
+    viewport_height: f32,
+) -> f32 {
+    let distance = eye.distance(sphere.center());
+    if distance <= sphere.radius() {
+        return f32::INFINITY;
+    }
+    let tan = (PERSPECTIVE.fovy.0 as f32 / 2.0).tan();
+    viewport_height * sphere.radius() / (distance * tan)
+}
+
+/// Returns the level to draw a mesh at, given its current level and its
+/// projected diameter in pixels.
+///
+/// Level 0 is the original mesh, and `level_cells` are the grid cells of the
+/// coarser levels from the finest.
+pub fn select_level(current: usize, level_cells: &[u32], diameter: f32) -> usize {
+    // The coarsest level whose cells are at most `max_cell` pixels.
+    let coarsest_within = |max_cell: f32| {
+        level_cells
+            .iter()
+            .rposition(|&cells| diameter / cells as f32 <= max_cell)
+            .map_or(0, |i| i + 1)
+    };
+    let coarser = coarsest_within(MAX_CELL_PIXELS / HYSTERESIS);
+    let finer = coarsest_within(MAX_CELL_PIXELS * HYSTERESIS);
+
+    current.clamp(coarser, finer)
+}
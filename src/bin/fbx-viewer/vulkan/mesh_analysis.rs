@@ -0,0 +1,112 @@
+//! Highlighting of the topology problems of meshes.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use vulkano::{
+    buffer::{BufferAccess, ImmutableBuffer},
+    command_buffer::{AutoCommandBufferBuilder, DynamicState},
+    descriptor::{descriptor_set::DescriptorSet, pipeline_layout::PipelineLayoutAbstract},
+    device::Device,
+    framebuffer::{RenderPassAbstract, Subpass},
+    pipeline::GraphicsPipeline,
+};
+
+use crate::vulkan::{
+    drawable::{Topology, VertexLayout},
+    wireframe_overlay::{fs, vs},
+};
+
+/// Color of the open edges.
+const OPEN_EDGE_COLOR: [f32; 4] = [1.0, 0.1, 0.1, 1.0];
+
+/// Color of the non-manifold edges.
+const NON_MANIFOLD_EDGE_COLOR: [f32; 4] = [1.0, 0.1, 1.0, 1.0];
+
+/// Color of the edges of the duplicate faces.
+const DUPLICATE_FACE_COLOR: [f32; 4] = [1.0, 1.0, 0.1, 1.0];
+
+/// Pipeline to draw the topology problems of meshes as lines.
+///
+/// The lines are drawn without depth test, so that the problems behind the
+/// surfaces are also visible.
+pub struct MeshAnalysisPipeline {
+    /// Pipeline.
+    #[allow(clippy::type_complexity)]
+    pipeline: Arc<
+        GraphicsPipeline<
+            VertexLayout,
+            Box<dyn PipelineLayoutAbstract + Send + Sync>,
+            Arc<dyn RenderPassAbstract + Send + Sync>,
+        >,
+    >,
+}
+
+impl MeshAnalysisPipeline {
+    /// Creates a new `MeshAnalysisPipeline` for the first subpass of the
+    /// render pass.
+    pub fn new(
+        device: Arc<Device>,
+        render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+        vertex_layout: VertexLayout,
+    ) -> anyhow::Result<Self> {
+        let vs = vs::Shader::load(device.clone())
+            .context("Failed to load mesh analysis vertex shader")?;
+        let fs = fs::Shader::load(device.clone())
+            .context("Failed to load mesh analysis fragment shader")?;
+
+        let pipeline = GraphicsPipeline::start()
+            .vertex_input(vertex_layout)
+            .vertex_shader(vs.main_entry_point(), ())
+            .line_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs.main_entry_point(), ())
+            .render_pass(
+                Subpass::from(render_pass, 0).ok_or_else(|| anyhow!("Failed to create subpass"))?,
+            )
+            .with_auto_layout(device, &[(0, 0)])
+            .map(Arc::new)
+            .context("Failed to create mesh analysis pipeline")?;
+
+        Ok(Self { pipeline })
+    }
+
+    /// Draws the topology problems of the geometry mesh with the vertices.
+    ///
+    /// `set0` should be the descriptor set with the transform uniforms at
+    /// `uniform_offset`.
+    pub fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder,
+        dynamic_state: &DynamicState,
+        set0: Arc<dyn DescriptorSet + Send + Sync>,
+        uniform_offset: u32,
+        vertices: Arc<dyn BufferAccess + Send + Sync>,
+        topology: &Topology,
+    ) -> anyhow::Result<()> {
+        let lines = [
+            (&topology.duplicate_faces, DUPLICATE_FACE_COLOR),
+            (&topology.non_manifold_edges, NON_MANIFOLD_EDGE_COLOR),
+            (&topology.open_edges, OPEN_EDGE_COLOR),
+        ];
+        for (indices, color) in lines {
+            let indices: &Arc<ImmutableBuffer<[u32]>> = match indices {
+                Some(v) => v,
+                None => continue,
+            };
+            builder
+                .draw_indexed(
+                    self.pipeline.clone(),
+                    dynamic_state,
+                    vertices.clone(),
+                    indices.clone(),
+                    set0.clone(),
+                    fs::ty::PushConstants { color },
+                    std::iter::once(uniform_offset),
+                )
+                .context("Failed to draw topology problems")?;
+        }
+
+        Ok(())
+    }
+}
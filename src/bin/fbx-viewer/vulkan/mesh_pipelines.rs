@@ -0,0 +1,222 @@
+//! Pipelines to draw meshes.
+
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::{anyhow, Context};
+use fbx_viewer::{CullFaces, Winding};
+use tracing::debug;
+use vulkano::{
+    descriptor::pipeline_layout::PipelineLayoutAbstract,
+    device::Device,
+    framebuffer::{RenderPassAbstract, Subpass},
+    pipeline::{
+        blend::{AttachmentBlend, BlendFactor, BlendOp},
+        GraphicsPipeline,
+    },
+};
+
+use crate::{
+    renderer::shading::ShadingModel,
+    vulkan::{drawable::VertexLayout, fs, vs},
+};
+
+/// Pipeline to draw meshes.
+pub type MeshPipeline = GraphicsPipeline<
+    VertexLayout,
+    Box<dyn PipelineLayoutAbstract + Send + Sync>,
+    Arc<dyn RenderPassAbstract + Send + Sync>,
+>;
+
+/// Render state which needs a separate pipeline.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    /// Whether the submesh is alpha blended.
+    pub blend: bool,
+    /// Whether the blended colors are premultiplied by alpha.
+    pub premultiplied_alpha: bool,
+    /// Faces to cull.
+    pub cull: CullFaces,
+    /// Whether the triangles are drawn as lines.
+    pub wireframe: bool,
+    /// Color channels to write.
+    pub color_mask: ColorMask,
+    /// Lighting model the fragment shader is specialized for.
+    pub shading_model: ShadingModel,
+}
+
+/// Color channels written by a pipeline.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorMask {
+    /// All channels.
+    #[default]
+    All,
+    /// Red channel, for the left eye of anaglyphs.
+    Red,
+    /// Green and blue channels, for the right eye of anaglyphs.
+    Cyan,
+}
+
+impl ColorMask {
+    /// Returns whether the red, green, and blue channels are written.
+    fn channels(self) -> (bool, bool, bool) {
+        match self {
+            Self::All => (true, true, true),
+            Self::Red => (true, false, false),
+            Self::Cyan => (false, true, true),
+        }
+    }
+}
+
+/// Cache of the pipelines to draw meshes, keyed by the render state.
+///
+/// The pipelines are created on first use.
+/// All of them share the same shaders (specialized for the lighting models)
+/// and pipeline layout, so descriptor sets created for
+/// [`base`][`Self::base`] can be used with any of them.
+pub struct MeshPipelines {
+    /// Device.
+    device: Arc<Device>,
+    /// Vertex shader.
+    vs: vs::Shader,
+    /// Fragment shader.
+    fs: fs::Shader,
+    /// Render pass.
+    render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    /// Layout of the vertex buffers.
+    vertex_layout: VertexLayout,
+    /// Winding order of the front faces.
+    winding: Winding,
+    /// Pipeline for the default state.
+    base: Arc<MeshPipeline>,
+    /// Created pipelines.
+    pipelines: HashMap<PipelineKey, Arc<MeshPipeline>>,
+}
+
+impl MeshPipelines {
+    /// Creates a new `MeshPipelines` for the first subpass of the render pass.
+    pub fn new(
+        device: Arc<Device>,
+        render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+        vertex_layout: VertexLayout,
+        winding: Winding,
+    ) -> anyhow::Result<Self> {
+        let vs = vs::Shader::load(device.clone()).context("Failed to load vertex shader")?;
+        let fs = fs::Shader::load(device.clone()).context("Failed to load fragment shader")?;
+        let base = create_pipeline(
+            &device,
+            &vs,
+            &fs,
+            render_pass.clone(),
+            vertex_layout,
+            winding,
+            PipelineKey::default(),
+        )?;
+        let pipelines = Some((PipelineKey::default(), base.clone()))
+            .into_iter()
+            .collect();
+
+        Ok(Self {
+            device,
+            vs,
+            fs,
+            render_pass,
+            vertex_layout,
+            winding,
+            base,
+            pipelines,
+        })
+    }
+
+    /// Returns the pipeline for the default state (opaque, double-sided, and
+    /// filled).
+    pub fn base(&self) -> &Arc<MeshPipeline> {
+        &self.base
+    }
+
+    /// Returns whether wireframe pipelines are supported by the device.
+    pub fn supports_wireframe(&self) -> bool {
+        self.device.enabled_features().fill_mode_non_solid
+    }
+
+    /// Returns the pipeline for the state, creating it if necessary.
+    pub fn get(&mut self, key: PipelineKey) -> anyhow::Result<Arc<MeshPipeline>> {
+        if let Some(pipeline) = self.pipelines.get(&key) {
+            return Ok(pipeline.clone());
+        }
+        debug!("Creating mesh pipeline: {:?}", key);
+        let pipeline = create_pipeline(
+            &self.device,
+            &self.vs,
+            &self.fs,
+            self.render_pass.clone(),
+            self.vertex_layout,
+            self.winding,
+            key,
+        )?;
+        self.pipelines.insert(key, pipeline.clone());
+
+        Ok(pipeline)
+    }
+}
+
+/// Creates the pipeline to draw meshes with the given state.
+fn create_pipeline(
+    device: &Arc<Device>,
+    vs: &vs::Shader,
+    fs: &fs::Shader,
+    render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    vertex_layout: VertexLayout,
+    winding: Winding,
+    key: PipelineKey,
+) -> anyhow::Result<Arc<MeshPipeline>> {
+    let mut builder = GraphicsPipeline::start()
+        .vertex_input(vertex_layout)
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_irrelevant(1)
+        .fragment_shader(
+            fs.main_entry_point(),
+            fs::SpecializationConstants {
+                SHADING_MODEL: key.shading_model.to_shader_value(),
+            },
+        )
+        .depth_stencil_simple_depth()
+        .render_pass(
+            Subpass::from(render_pass, 0).ok_or_else(|| anyhow!("Failed to create subpass"))?,
+        );
+    builder = match winding {
+        Winding::Ccw => builder.front_face_counter_clockwise(),
+        Winding::Cw => builder.front_face_clockwise(),
+    };
+    // Draw both sides by default, so that flipped faces are visible.
+    builder = match key.cull {
+        CullFaces::None => builder.cull_mode_disabled(),
+        CullFaces::Back => builder.cull_mode_back(),
+        CullFaces::Front => builder.cull_mode_front(),
+    };
+    let mut blend = match (key.blend, key.premultiplied_alpha) {
+        (true, false) => AttachmentBlend::alpha_blending(),
+        (true, true) => AttachmentBlend {
+            enabled: true,
+            color_op: BlendOp::Add,
+            color_source: BlendFactor::One,
+            color_destination: BlendFactor::OneMinusSrcAlpha,
+            alpha_op: BlendOp::Add,
+            alpha_source: BlendFactor::One,
+            alpha_destination: BlendFactor::OneMinusSrcAlpha,
+            ..AttachmentBlend::pass_through()
+        },
+        (false, _) => AttachmentBlend::pass_through(),
+    };
+    (blend.mask_red, blend.mask_green, blend.mask_blue) = key.color_mask.channels();
+    builder = builder.blend_collective(blend);
+    if key.wireframe {
+        builder = builder.polygon_mode_line();
+    }
+
+    builder
+        // The uniforms of the views are selected by dynamic offsets.
+        .with_auto_layout(device.clone(), &[(0, 0)])
+        .map(Arc::new)
+        .with_context(|| format!("Failed to create mesh pipeline for {:?}", key))
+}
@@ -0,0 +1,186 @@
+//! Outline of the selected mesh.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, DynamicState},
+    descriptor::{descriptor_set::DescriptorSet, pipeline_layout::PipelineLayoutAbstract},
+    device::Device,
+    framebuffer::{RenderPassAbstract, Subpass},
+    pipeline::{
+        blend::AttachmentBlend,
+        depth_stencil::{Compare, DepthBounds, DepthStencil, Stencil, StencilOp},
+        GraphicsPipeline,
+    },
+};
+
+use crate::vulkan::drawable::{GeometryMesh, VertexLayout};
+
+/// Outline color.
+const COLOR: [f32; 4] = [1.0, 0.6, 0.0, 1.0];
+
+/// Outline width in logical pixels.
+const WIDTH: f32 = 3.0;
+
+/// Stencil value for the pixels covered by the selected mesh.
+const STENCIL_SELECTED: u32 = 1;
+
+/// Pipeline type used for outlines.
+type OutlinePipeline = GraphicsPipeline<
+    VertexLayout,
+    Box<dyn PipelineLayoutAbstract + Send + Sync>,
+    Arc<dyn RenderPassAbstract + Send + Sync>,
+>;
+
+/// Pipelines to draw the outline of a mesh.
+///
+/// The mesh is first drawn to the stencil buffer, and then drawn again
+/// extruded along the normals only where the stencil is not set.
+/// The outline is visible even if the mesh is occluded.
+pub struct OutlinePipelines {
+    /// Pipeline to write the mesh to the stencil buffer.
+    mask: Arc<OutlinePipeline>,
+    /// Pipeline to draw the outline.
+    outline: Arc<OutlinePipeline>,
+}
+
+impl OutlinePipelines {
+    /// Creates a new `OutlinePipelines` for the first subpass of the render
+    /// pass.
+    ///
+    /// The render pass should have a stencil attachment.
+    pub fn new(
+        device: Arc<Device>,
+        render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+        vertex_layout: VertexLayout,
+    ) -> anyhow::Result<Self> {
+        let vs =
+            vs::Shader::load(device.clone()).context("Failed to load outline vertex shader")?;
+        let fs =
+            fs::Shader::load(device.clone()).context("Failed to load outline fragment shader")?;
+
+        let stencil = |compare, pass_op, write_mask| Stencil {
+            compare,
+            pass_op,
+            fail_op: StencilOp::Keep,
+            depth_fail_op: StencilOp::Keep,
+            compare_mask: Some(!0),
+            write_mask: Some(write_mask),
+            reference: Some(STENCIL_SELECTED),
+        };
+        let depth_stencil = |stencil: Stencil| DepthStencil {
+            depth_compare: Compare::Always,
+            depth_write: false,
+            depth_bounds_test: DepthBounds::Disabled,
+            stencil_front: stencil,
+            stencil_back: stencil,
+        };
+        let subpass =
+            Subpass::from(render_pass, 0).ok_or_else(|| anyhow!("Failed to create subpass"))?;
+
+        let mask = GraphicsPipeline::start()
+            .vertex_input(vertex_layout)
+            .vertex_shader(vs.main_entry_point(), ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs.main_entry_point(), ())
+            .blend_collective(AttachmentBlend {
+                mask_red: false,
+                mask_green: false,
+                mask_blue: false,
+                mask_alpha: false,
+                ..AttachmentBlend::pass_through()
+            })
+            .depth_stencil(depth_stencil(stencil(
+                Compare::Always,
+                StencilOp::Replace,
+                !0,
+            )))
+            .render_pass(subpass.clone())
+            .with_auto_layout(device.clone(), &[(0, 0)])
+            .map(Arc::new)
+            .context("Failed to create outline mask pipeline")?;
+        let outline = GraphicsPipeline::start()
+            .vertex_input(vertex_layout)
+            .vertex_shader(vs.main_entry_point(), ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs.main_entry_point(), ())
+            .depth_stencil(depth_stencil(stencil(
+                Compare::NotEqual,
+                StencilOp::Keep,
+                0,
+            )))
+            .render_pass(subpass)
+            .with_auto_layout(device, &[(0, 0)])
+            .map(Arc::new)
+            .context("Failed to create outline pipeline")?;
+
+        Ok(Self { mask, outline })
+    }
+
+    /// Draws the outline of the geometry mesh.
+    ///
+    /// `set0` should be the descriptor set with the transform uniforms used
+    /// for the mesh, at `uniform_offset`.
+    /// `scale_factor` is the ratio of physical pixels to logical pixels.
+    pub fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder,
+        dynamic_state: &DynamicState,
+        set0: Arc<dyn DescriptorSet + Send + Sync>,
+        uniform_offset: u32,
+        geometry: &GeometryMesh,
+        scale_factor: f64,
+    ) -> anyhow::Result<()> {
+        let viewport_size = dynamic_state
+            .viewports
+            .as_ref()
+            .and_then(|viewports| viewports.first())
+            .map(|viewport| viewport.dimensions)
+            .ok_or_else(|| anyhow!("Viewport is not set"))?;
+
+        // Write all the submeshes to the stencil buffer first, so that the
+        // outline does not overlap any of them.
+        let width = WIDTH * scale_factor as f32;
+        for (pipeline, width) in &[(&self.mask, 0.0), (&self.outline, width)] {
+            let constants = vs::ty::PushConstants {
+                color: COLOR,
+                viewport_size,
+                width: *width,
+            };
+            for index in &geometry.indices_per_material {
+                builder
+                    .draw_indexed(
+                        (*pipeline).clone(),
+                        dynamic_state,
+                        geometry.vertices.clone(),
+                        index.clone(),
+                        set0.clone(),
+                        constants,
+                        std::iter::once(uniform_offset),
+                    )
+                    .context("Failed to draw outline")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Outline vertex shader.
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "src/bin/fbx-viewer/shaders/outline.vert",
+    }
+}
+
+/// Outline fragment shader.
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/bin/fbx-viewer/shaders/outline.frag",
+    }
+}
@@ -0,0 +1,127 @@
+//! Overdraw visualization.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use vulkano::{
+    buffer::{BufferAccess, ImmutableBuffer},
+    command_buffer::{AutoCommandBufferBuilder, DynamicState},
+    descriptor::{descriptor_set::DescriptorSet, pipeline_layout::PipelineLayoutAbstract},
+    device::Device,
+    framebuffer::{RenderPassAbstract, Subpass},
+    pipeline::{
+        blend::{AttachmentBlend, BlendFactor, BlendOp},
+        GraphicsPipeline,
+    },
+};
+
+use crate::vulkan::{drawable::VertexLayout, vs};
+
+/// Background color in the overdraw mode.
+pub const CLEAR_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+/// Pipeline to visualize overdraw.
+///
+/// All fragments are accumulated additively without depth test, so the
+/// brightness of a pixel shows how many times it is drawn.
+pub struct OverdrawPipeline {
+    /// Pipeline.
+    #[allow(clippy::type_complexity)]
+    pipeline: Arc<
+        GraphicsPipeline<
+            VertexLayout,
+            Box<dyn PipelineLayoutAbstract + Send + Sync>,
+            Arc<dyn RenderPassAbstract + Send + Sync>,
+        >,
+    >,
+}
+
+impl OverdrawPipeline {
+    /// Creates a new `OverdrawPipeline` for the first subpass of the render
+    /// pass.
+    pub fn new(
+        device: Arc<Device>,
+        render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+        vertex_layout: VertexLayout,
+    ) -> anyhow::Result<Self> {
+        let vs = vs::Shader::load(device.clone()).context("Failed to load vertex shader")?;
+        let fs =
+            fs::Shader::load(device.clone()).context("Failed to load overdraw fragment shader")?;
+
+        let pipeline = GraphicsPipeline::start()
+            .vertex_input(vertex_layout)
+            .vertex_shader(vs.main_entry_point(), ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs.main_entry_point(), ())
+            .blend_collective(AttachmentBlend {
+                enabled: true,
+                color_op: BlendOp::Add,
+                color_source: BlendFactor::One,
+                color_destination: BlendFactor::One,
+                alpha_op: BlendOp::Add,
+                alpha_source: BlendFactor::One,
+                alpha_destination: BlendFactor::One,
+                ..AttachmentBlend::pass_through()
+            })
+            .depth_stencil_disabled()
+            .render_pass(
+                Subpass::from(render_pass, 0).ok_or_else(|| anyhow!("Failed to create subpass"))?,
+            )
+            .with_auto_layout(device, &[(0, 0)])
+            .map(Arc::new)
+            .context("Failed to create overdraw pipeline")?;
+
+        Ok(Self { pipeline })
+    }
+
+    /// Draws the triangles.
+    ///
+    /// `set0` should be the descriptor set with the transform uniforms at
+    /// `uniform_offset`.
+    pub fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder,
+        dynamic_state: &DynamicState,
+        set0: Arc<dyn DescriptorSet + Send + Sync>,
+        uniform_offset: u32,
+        vertices: Arc<dyn BufferAccess + Send + Sync>,
+        indices: Arc<ImmutableBuffer<[u32]>>,
+    ) -> anyhow::Result<()> {
+        builder
+            .draw_indexed(
+                self.pipeline.clone(),
+                dynamic_state,
+                vertices,
+                indices,
+                set0,
+                (),
+                std::iter::once(uniform_offset),
+            )
+            .context("Failed to draw overdraw")?;
+
+        Ok(())
+    }
+}
+
+/// Overdraw fragment shader.
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/bin/fbx-viewer/shaders/overdraw.frag",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vulkano::pipeline::shader::ShaderInterfaceDefMatch;
+
+    use super::*;
+
+    #[test]
+    fn shader_interface() {
+        fs::MainInput
+            .matches(&vs::MainOutput)
+            .expect("The fragment shader inputs should match the vertex shader outputs");
+    }
+}
@@ -3,18 +3,20 @@
 use std::sync::Arc;
 
 use anyhow::{anyhow, Context};
-use log::{debug, info};
+use fbx_viewer::{DepthFormat, MaterialOverride, SwapchainFormat};
+use tracing::{debug, info, warn};
 use vulkano::{
+    buffer::{BufferUsage, ImmutableBuffer},
     descriptor::{
-        descriptor_set::{DescriptorSet, PersistentDescriptorSet},
+        descriptor_set::{DescriptorSet, PersistentDescriptorSet, PersistentDescriptorSetBuf},
         pipeline_layout::PipelineLayoutAbstract,
     },
-    device::{Device, DeviceExtensions, Queue},
-    format::R8G8B8A8Srgb,
+    device::{Device, DeviceCreationError, DeviceExtensions, Queue},
+    format::{Format, R8G8B8A8Srgb},
     image::{Dimensions, ImmutableImage, MipmapsCount, SwapchainImage},
-    instance::{Instance, PhysicalDevice},
+    instance::{Instance, InstanceExtensions, PhysicalDevice, QueueFamily},
     pipeline::GraphicsPipeline,
-    sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode},
+    sampler::{Sampler, SamplerAddressMode},
     swapchain::{
         ColorSpace, FullscreenExclusive, PresentMode, Surface, SurfaceTransform, Swapchain,
     },
@@ -26,12 +28,39 @@ use winit::{
     window::{Window, WindowBuilder},
 };
 
+use crate::{
+    renderer::shading::material_override_to_shader_value,
+    vulkan::{
+        capabilities::device_features,
+        drawable::{texture::create_sampler, VertexLayout},
+        fs::ty::Material as ShaderMaterial,
+    },
+};
+
+/// Queues of the device.
+#[derive(Debug, Clone)]
+pub struct QueueSet {
+    /// Queue to draw and present the frames.
+    pub graphics: Arc<Queue>,
+    /// Queue to upload the scenes on the loader thread.
+    ///
+    /// This is the graphics queue if the device has no other queue for it.
+    /// Resources are shared by all queue families of the device, so the
+    /// uploaded ones can be used on the graphics queue without ownership
+    /// transfers.
+    pub transfer: Arc<Queue>,
+}
+
 /// Initialize vulkan.
 #[allow(clippy::type_complexity)]
-pub fn setup() -> anyhow::Result<(Arc<Device>, Arc<Queue>, Arc<Surface<Window>>, EventLoop<()>)> {
+pub fn setup() -> anyhow::Result<(Arc<Device>, QueueSet, Arc<Surface<Window>>, EventLoop<()>)> {
     // Create an instance of vulkan.
     let instance = {
-        let extensions = vulkano_win::required_extensions();
+        let mut extensions = vulkano_win::required_extensions();
+        // Color spaces other than sRGB (such as the one for HDR) are
+        // reported only with the extension.
+        extensions.ext_swapchain_colorspace = InstanceExtensions::supported_by_core()
+            .is_ok_and(|supported| supported.ext_swapchain_colorspace);
         Instance::new(None, &extensions, None).context("Failed to create vulkan instance")?
     };
     debug!("Successfully created vulkan instance: {:?}", instance);
@@ -68,11 +97,13 @@ pub fn setup() -> anyhow::Result<(Arc<Device>, Arc<Queue>, Arc<Surface<Window>>,
     // List device queue families.
     for family in physical.queue_families() {
         debug!(
-            "Queue family found: id={:?}, count={:?}, graphics={:?}, compute={:?}",
+            "Queue family found: id={:?}, count={:?}, graphics={:?}, compute={:?}, \
+             transfer={:?}",
             family.id(),
             family.queues_count(),
             family.supports_graphics(),
             family.supports_compute(),
+            family.explicitly_supports_transfers(),
         );
     }
 
@@ -87,37 +118,94 @@ pub fn setup() -> anyhow::Result<(Arc<Device>, Arc<Queue>, Arc<Surface<Window>>,
         queue_family.queues_count()
     );
 
+    let transfer_family = select_transfer_family(physical, queue_family);
+    match transfer_family {
+        Some(family) => info!(
+            "Using queue family for uploads: id={:?}, count={:?}",
+            family.id(),
+            family.queues_count()
+        ),
+        None => info!("No separate queues for uploads, sharing the graphics queue"),
+    }
+
     // Initialize device.
-    let (device, queue) = {
-        /// Queue priority, between 0.0 and 1.0.
+    let (device, queues) = {
+        /// Queue priority of the graphics queue, between 0.0 and 1.0.
         ///
-        /// This can be any value in the range, because in this program only one
-        /// queue family is used.
-        const QUEUE_PRIORITY: f32 = 0.5;
+        /// This is higher than the one of the transfer queue, so that uploads
+        /// do not delay the frames.
+        const GRAPHICS_PRIORITY: f32 = 1.0;
+        /// Queue priority of the transfer queue.
+        const TRANSFER_PRIORITY: f32 = 0.5;
         let device_ext = DeviceExtensions {
             khr_swapchain: true,
             ..DeviceExtensions::none()
         };
-        let (device, mut queues) = Device::new(
-            physical,
-            physical.supported_features(),
-            &device_ext,
-            [(queue_family, QUEUE_PRIORITY)].iter().cloned(),
-        )
-        .context("Failed to create device")?;
-        (device, queues.next().expect("Should never fail"))
+        let create = |transfer_family: Option<QueueFamily<'_>>| {
+            let families = std::iter::once((queue_family, GRAPHICS_PRIORITY))
+                .chain(transfer_family.map(|family| (family, TRANSFER_PRIORITY)));
+            let (device, mut queues) =
+                Device::new(physical, &device_features(physical), &device_ext, families)?;
+            let graphics = queues.next().expect("Should never fail: requested");
+            let transfer = queues.next().unwrap_or_else(|| graphics.clone());
+            Ok::<_, DeviceCreationError>((device, QueueSet { graphics, transfer }))
+        };
+        match create(transfer_family) {
+            Ok(v) => v,
+            Err(e) if transfer_family.is_some() => {
+                warn!(
+                    "Failed to create device with a separate transfer queue, \
+                     falling back to a single queue: {}",
+                    e
+                );
+                create(None).context("Failed to create device")?
+            }
+            Err(e) => return Err(e).context("Failed to create device"),
+        }
     };
     info!("Successfully created device object");
 
-    Ok((device, queue, surface, event_loop))
+    Ok((device, queues, surface, event_loop))
+}
+
+/// Selects the queue family to request a transfer queue from, in addition to
+/// the graphics queue of the graphics family.
+///
+/// Families dedicated to transfers are preferred, then other families with
+/// transfer support, and then the graphics family if it has more than one
+/// queue.
+/// Returns `None` if no other queues are available.
+fn select_transfer_family<'a>(
+    physical: PhysicalDevice<'a>,
+    graphics: QueueFamily<'a>,
+) -> Option<QueueFamily<'a>> {
+    // Graphics and compute families support transfers implicitly.
+    let supports_transfers = |family: &QueueFamily<'_>| {
+        family.explicitly_supports_transfers()
+            || family.supports_graphics()
+            || family.supports_compute()
+    };
+    let others = || {
+        physical
+            .queue_families()
+            .filter(move |family| family.id() != graphics.id())
+            .filter(|family| family.queues_count() > 0 && supports_transfers(family))
+    };
+    others()
+        .find(|family| !family.supports_graphics() && !family.supports_compute())
+        .or_else(|| others().next())
+        .or_else(|| Some(graphics).filter(|family| family.queues_count() > 1))
 }
 
 /// Create swapchain.
+///
+/// The format is selected by [`select_swapchain_format`].
 #[allow(clippy::type_complexity)]
 pub fn create_swapchain(
     device: &Arc<Device>,
     queue: &Arc<Queue>,
     surface: &Arc<Surface<Window>>,
+    preferred_format: SwapchainFormat,
 ) -> anyhow::Result<(Arc<Swapchain<Window>>, Vec<Arc<SwapchainImage<Window>>>)> {
     let caps = surface
         .capabilities(device.physical_device())
@@ -130,8 +218,12 @@ pub fn create_swapchain(
         .next()
         .ok_or_else(|| anyhow!("No desired composite alpha modes are supported"))?;
     info!("Selected alpha composite mode: {:?}", alpha);
-    let format = caps.supported_formats[0].0;
-    info!("Selected swapchain format: {:?}", format);
+    let (format, color_space) = select_swapchain_format(&caps.supported_formats, preferred_format)
+        .ok_or_else(|| anyhow!("No swapchain formats are supported"))?;
+    info!(
+        "Selected swapchain format: {:?}, color space: {:?}",
+        format, color_space
+    );
 
     let window = surface.window();
     let (swapchain, image) = Swapchain::new(
@@ -148,12 +240,132 @@ pub fn create_swapchain(
         PresentMode::Fifo,
         FullscreenExclusive::Default,
         true,
-        ColorSpace::SrgbNonLinear,
+        color_space,
     )
     .context("Failed to create swapchain")?;
     Ok((swapchain, image))
 }
 
+/// Selects a swapchain format and a color space from the supported ones.
+///
+/// The shaders write linear colors, so formats with sRGB encoding are
+/// preferred, and formats without it are used only if explicitly requested
+/// or if nothing else is supported (then the colors are too dark).
+/// The HDR format is linear, and shows the same colors as sRGB formats with
+/// the values above 1 brighter on HDR displays.
+/// If the preferred format is not supported, the automatic selection is used
+/// instead.
+/// Returns `None` if no formats are supported.
+pub fn select_swapchain_format(
+    supported: &[(Format, ColorSpace)],
+    preferred: SwapchainFormat,
+) -> Option<(Format, ColorSpace)> {
+    let find = |format: Format, color_space: ColorSpace| {
+        supported
+            .iter()
+            .copied()
+            .find(|&candidate| candidate == (format, color_space))
+    };
+    let srgb = |format: Format| find(format, ColorSpace::SrgbNonLinear);
+    let requested = match preferred {
+        SwapchainFormat::Auto => None,
+        SwapchainFormat::Hdr => Some(find(
+            Format::R16G16B16A16Sfloat,
+            ColorSpace::ExtendedSrgbLinear,
+        )),
+        SwapchainFormat::Bgra8Srgb => Some(srgb(Format::B8G8R8A8Srgb)),
+        SwapchainFormat::Rgba8Srgb => Some(srgb(Format::R8G8B8A8Srgb)),
+        SwapchainFormat::Bgra8Unorm => Some(srgb(Format::B8G8R8A8Unorm)),
+        SwapchainFormat::Rgba8Unorm => Some(srgb(Format::R8G8B8A8Unorm)),
+    };
+    match requested {
+        Some(Some(selected)) => return Some(selected),
+        Some(None) => warn!(
+            "Swapchain format {:?} is not supported, falling back to the automatic selection",
+            preferred
+        ),
+        None => {}
+    }
+
+    let selected = [
+        Format::B8G8R8A8Srgb,
+        Format::R8G8B8A8Srgb,
+        Format::A8B8G8R8SrgbPack32,
+    ]
+    .iter()
+    .copied()
+    .find_map(srgb);
+    if selected.is_some() {
+        return selected;
+    }
+    let fallback = supported.first().copied()?;
+    warn!(
+        "No sRGB swapchain formats are supported, colors may be wrong with {:?}",
+        fallback
+    );
+    Some(fallback)
+}
+
+/// Selects a depth-stencil format supported by the device.
+///
+/// The format for the preferred depth format is used if supported, and the
+/// other one is used otherwise.
+pub fn select_depth_stencil_format(
+    device: &Arc<Device>,
+    preferred: DepthFormat,
+) -> anyhow::Result<Format> {
+    let candidates = match preferred {
+        DepthFormat::D32 => [Format::D32Sfloat_S8Uint, Format::D24Unorm_S8Uint],
+        DepthFormat::D24 => [Format::D24Unorm_S8Uint, Format::D32Sfloat_S8Uint],
+    };
+    // Vulkan guarantees that at least one of them is supported.
+    let format = candidates
+        .iter()
+        .copied()
+        .find(|format| {
+            format
+                .properties(device.physical_device())
+                .optimal_tiling_features
+                .depth_stencil_attachment
+        })
+        .ok_or_else(|| anyhow!("No depth-stencil formats are supported"))?;
+    if format != candidates[0] {
+        warn!(
+            "Depth-stencil format {:?} is not supported, falling back to {:?}",
+            candidates[0], format
+        );
+    }
+    info!("Selected depth-stencil format: {:?}", format);
+
+    Ok(format)
+}
+
+/// Selects the vertex layout.
+///
+/// Falls back to the full layout if the packed formats of the compact layout
+/// cannot be used for vertex buffers.
+pub fn select_vertex_layout(device: &Arc<Device>, compact: bool) -> VertexLayout {
+    if !compact {
+        return VertexLayout::Full;
+    }
+    let unsupported = VertexLayout::Compact.formats().find(|format| {
+        !format
+            .properties(device.physical_device())
+            .buffer_features
+            .vertex_buffer
+    });
+    if let Some(format) = unsupported {
+        warn!(
+            "Vertex format {:?} is not supported, falling back to the full vertex layout",
+            format
+        );
+        return VertexLayout::Full;
+    }
+    info!("Using the compact vertex layout");
+
+    VertexLayout::Compact
+}
+
 /// Creates dummy 1x1 white texture.
 #[allow(clippy::type_complexity)]
 pub fn create_dummy_texture(
@@ -177,20 +389,11 @@ pub fn create_dummy_texture(
         queue,
     )
     .context("Failed to upload dummy texture image")?;
-    let sampler = Sampler::new(
+    let sampler = create_sampler(
         device,
-        Filter::Linear,
-        Filter::Linear,
-        MipmapMode::Nearest,
-        SamplerAddressMode::Repeat,
         SamplerAddressMode::Repeat,
         SamplerAddressMode::Repeat,
-        0.0,
-        1.0,
-        0.0,
-        0.0,
-    )
-    .context("Failed to create sampler")?;
+    )?;
 
     Ok((image, sampler, Box::new(img_future)))
 }
@@ -216,3 +419,104 @@ where
 
     Ok(Arc::new(desc_set) as Arc<_>)
 }
+
+/// Creates a descriptor set for the given material uniform buffer.
+#[allow(clippy::type_complexity)]
+pub fn create_material_desc_set<Mv, L, Rp>(
+    material_buf: Arc<ImmutableBuffer<ShaderMaterial>>,
+    pipeline: Arc<GraphicsPipeline<Mv, L, Rp>>,
+) -> anyhow::Result<
+    Arc<
+        PersistentDescriptorSet<(
+            (),
+            PersistentDescriptorSetBuf<Arc<ImmutableBuffer<ShaderMaterial>>>,
+        )>,
+    >,
+>
+where
+    L: PipelineLayoutAbstract,
+{
+    let layout = pipeline
+        .layout()
+        .descriptor_set_layout(2)
+        .context("Failed to get the second descriptor set layout of the pipeline")?;
+    let desc_set = PersistentDescriptorSet::start(layout.clone())
+        .add_buffer(material_buf)
+        .context("Failed to add material data to descriptor set")?
+        .build()
+        .context("Failed to build material descriptor set")?;
+
+    Ok(Arc::new(desc_set) as Arc<_>)
+}
+
+/// Creates a descriptor set for the material which overrides all materials.
+#[allow(clippy::type_complexity)]
+pub fn create_override_material_desc_set<Mv, L, Rp>(
+    queue: Arc<Queue>,
+    pipeline: Arc<GraphicsPipeline<Mv, L, Rp>>,
+    material_override: MaterialOverride,
+) -> anyhow::Result<(Arc<dyn DescriptorSet + Send + Sync>, Box<dyn GpuFuture>)>
+where
+    L: PipelineLayoutAbstract,
+{
+    // The override shades the meshes by itself, and the other fields are
+    // unused.
+    let data = ShaderMaterial {
+        ambient: [0.0; 3],
+        _dummy0: [0; 4],
+        diffuse: [0.0; 3],
+        emissive: [0.0; 3],
+        _dummy1: [0; 4],
+        enabled: 1,
+        override_mode: material_override_to_shader_value(Some(material_override)),
+        _dummy2: [0; 12],
+        specular: [0.0; 3],
+        shininess: 0.0,
+        f0: [0.0; 3],
+        roughness: 1.0,
+        alpha_cutoff: 0.0,
+    };
+    let (buf, future) = ImmutableBuffer::from_data(data, BufferUsage::all(), queue)
+        .context("Failed to upload override material")?;
+    let desc_set = create_material_desc_set(buf, pipeline)?;
+
+    Ok((desc_set as Arc<_>, Box::new(future)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swapchain_format_preference() {
+        let supported = [
+            (Format::B8G8R8A8Unorm, ColorSpace::SrgbNonLinear),
+            (Format::B8G8R8A8Srgb, ColorSpace::SrgbNonLinear),
+            (Format::R16G16B16A16Sfloat, ColorSpace::ExtendedSrgbLinear),
+        ];
+        let select = |preferred| select_swapchain_format(&supported, preferred);
+        assert_eq!(
+            select(SwapchainFormat::Auto),
+            Some((Format::B8G8R8A8Srgb, ColorSpace::SrgbNonLinear))
+        );
+        assert_eq!(
+            select(SwapchainFormat::Hdr),
+            Some((Format::R16G16B16A16Sfloat, ColorSpace::ExtendedSrgbLinear))
+        );
+        assert_eq!(
+            select(SwapchainFormat::Bgra8Unorm),
+            Some((Format::B8G8R8A8Unorm, ColorSpace::SrgbNonLinear))
+        );
+        // Unsupported formats fall back to the automatic selection.
+        assert_eq!(
+            select(SwapchainFormat::Rgba8Srgb),
+            Some((Format::B8G8R8A8Srgb, ColorSpace::SrgbNonLinear))
+        );
+        // Non-sRGB formats are used if nothing else is supported.
+        assert_eq!(
+            select_swapchain_format(&supported[..1], SwapchainFormat::Auto),
+            Some((Format::B8G8R8A8Unorm, ColorSpace::SrgbNonLinear))
+        );
+        assert_eq!(select_swapchain_format(&[], SwapchainFormat::Auto), None);
+    }
+}
@@ -0,0 +1,387 @@
+//! Screen-space ambient occlusion.
+//!
+//! The view-space normals and positions of the opaque submeshes are drawn to
+//! an offscreen G-buffer before the scene.
+//! The occlusion of each pixel is estimated from the surfaces around it in
+//! the G-buffer, and multiplied to the scene color before the post-process
+//! passes.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, DynamicState, SubpassContents},
+    descriptor::{
+        descriptor_set::{DescriptorSet, PersistentDescriptorSet},
+        pipeline_layout::PipelineLayoutAbstract,
+    },
+    device::Device,
+    format::{ClearValue, Format},
+    framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract, Subpass},
+    image::AttachmentImage,
+    pipeline::{
+        blend::{AttachmentBlend, BlendFactor, BlendOp},
+        vertex::{BufferlessDefinition, BufferlessVertices},
+        GraphicsPipeline,
+    },
+    sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode},
+};
+
+use crate::vulkan::{
+    drawable::{DrawItem, VertexLayout},
+    frame::Frame,
+    mesh_pipelines::MeshPipeline,
+    vs,
+};
+
+/// Format of the view-space normals.
+const NORMAL_FORMAT: Format = Format::R16G16B16A16Sfloat;
+
+/// Format of the view-space positions.
+///
+/// Half floats are not precise enough for the distances between the nearby
+/// surfaces of large scenes.
+const POSITION_FORMAT: Format = Format::R32G32B32A32Sfloat;
+
+/// Pipeline to draw a fullscreen triangle.
+type FullscreenPipeline = GraphicsPipeline<
+    BufferlessDefinition,
+    Box<dyn PipelineLayoutAbstract + Send + Sync>,
+    Arc<dyn RenderPassAbstract + Send + Sync>,
+>;
+
+/// Screen-space ambient occlusion of the opaque submeshes.
+pub struct AmbientOcclusion {
+    /// Device.
+    device: Arc<Device>,
+    /// Intensity of the occlusion.
+    intensity: f32,
+    /// Radius of the occlusion relative to the radius of the scene.
+    radius: f32,
+    /// Depth-stencil format of the G-buffer.
+    depth_format: Format,
+    /// Render pass for the G-buffer.
+    gbuffer_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    /// Pipeline to draw the opaque submeshes to the G-buffer.
+    gbuffer_pipeline: Arc<MeshPipeline>,
+    /// Pipeline to multiply the occlusion to the scene color.
+    occlusion_pipeline: Arc<FullscreenPipeline>,
+    /// Sampler for the G-buffer.
+    sampler: Arc<Sampler>,
+    /// Offscreen targets, created on resize.
+    targets: Option<Targets>,
+}
+
+/// Offscreen targets of the ambient occlusion.
+struct Targets {
+    /// Framebuffer of the G-buffer.
+    gbuffer_framebuffer: Arc<dyn FramebufferAbstract + Send + Sync>,
+    /// Descriptor set to read the G-buffer.
+    gbuffer_set: Arc<dyn DescriptorSet + Send + Sync>,
+}
+
+impl AmbientOcclusion {
+    /// Creates a new `AmbientOcclusion` of the intensity and the radius
+    /// relative to the scene, to be applied in the first subpass of the scene
+    /// render pass.
+    ///
+    /// Offscreen targets are not available until [`resize`][`Self::resize`]
+    /// is called.
+    pub fn new(
+        device: Arc<Device>,
+        scene_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+        vertex_layout: VertexLayout,
+        depth_format: Format,
+        intensity: f32,
+        radius: f32,
+    ) -> anyhow::Result<Self> {
+        let gbuffer_pass: Arc<dyn RenderPassAbstract + Send + Sync> = Arc::new(
+            vulkano::single_pass_renderpass!(
+                device.clone(),
+                attachments: {
+                    normal: {
+                        load: Clear,
+                        store: Store,
+                        format: NORMAL_FORMAT,
+                        samples: 1,
+                    },
+                    position: {
+                        load: Clear,
+                        store: Store,
+                        format: POSITION_FORMAT,
+                        samples: 1,
+                    },
+                    depth: {
+                        load: Clear,
+                        store: DontCare,
+                        format: depth_format,
+                        samples: 1,
+                    }
+                },
+                pass: {
+                    color: [normal, position],
+                    depth_stencil: {depth}
+                }
+            )
+            .context("Failed to create G-buffer render pass")?,
+        );
+
+        let mesh_vs = vs::Shader::load(device.clone()).context("Failed to load vertex shader")?;
+        let gbuffer_fs = gbuffer_fs::Shader::load(device.clone())
+            .context("Failed to load G-buffer fragment shader")?;
+        let fullscreen_vs = fullscreen_vs::Shader::load(device.clone())
+            .context("Failed to load fullscreen vertex shader")?;
+        let occlusion_fs = occlusion_fs::Shader::load(device.clone())
+            .context("Failed to load ambient occlusion fragment shader")?;
+
+        // Both sides are drawn, and the shader flips the normals of the back
+        // faces.
+        let gbuffer_pipeline = GraphicsPipeline::start()
+            .vertex_input(vertex_layout)
+            .vertex_shader(mesh_vs.main_entry_point(), ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(gbuffer_fs.main_entry_point(), ())
+            .depth_stencil_simple_depth()
+            .render_pass(
+                Subpass::from(gbuffer_pass.clone(), 0)
+                    .ok_or_else(|| anyhow!("Failed to create subpass"))?,
+            )
+            .with_auto_layout(device.clone(), &[(0, 0)])
+            .map(Arc::new)
+            .context("Failed to create G-buffer pipeline")?;
+        // The scene color is multiplied by the occlusion, and its alpha is
+        // kept.
+        let occlusion_pipeline = GraphicsPipeline::start()
+            .vertex_input(BufferlessDefinition)
+            .vertex_shader(fullscreen_vs.main_entry_point(), ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(occlusion_fs.main_entry_point(), ())
+            .blend_collective(AttachmentBlend {
+                enabled: true,
+                color_op: BlendOp::Add,
+                color_source: BlendFactor::Zero,
+                color_destination: BlendFactor::SrcColor,
+                alpha_op: BlendOp::Add,
+                alpha_source: BlendFactor::Zero,
+                alpha_destination: BlendFactor::One,
+                ..AttachmentBlend::pass_through()
+            })
+            .render_pass(
+                Subpass::from(scene_pass, 0).ok_or_else(|| anyhow!("Failed to create subpass"))?,
+            )
+            .build(device.clone())
+            .map(Arc::new)
+            .context("Failed to create ambient occlusion pipeline")?;
+
+        // The images are read with `texelFetch`, so the filters are unused.
+        let sampler = Sampler::new(
+            device.clone(),
+            Filter::Nearest,
+            Filter::Nearest,
+            MipmapMode::Nearest,
+            SamplerAddressMode::ClampToEdge,
+            SamplerAddressMode::ClampToEdge,
+            SamplerAddressMode::ClampToEdge,
+            0.0,
+            1.0,
+            0.0,
+            0.0,
+        )
+        .context("Failed to create sampler")?;
+
+        Ok(Self {
+            device,
+            intensity,
+            radius,
+            depth_format,
+            gbuffer_pass,
+            gbuffer_pipeline,
+            occlusion_pipeline,
+            sampler,
+            targets: None,
+        })
+    }
+
+    /// Recreates the offscreen targets for the framebuffer size.
+    pub fn resize(&mut self, dimensions: [u32; 2]) -> anyhow::Result<()> {
+        let device = &self.device;
+        let normals = AttachmentImage::sampled(device.clone(), dimensions, NORMAL_FORMAT)
+            .context("Failed to create G-buffer normal image")?;
+        let positions = AttachmentImage::sampled(device.clone(), dimensions, POSITION_FORMAT)
+            .context("Failed to create G-buffer position image")?;
+        let depth = AttachmentImage::transient(device.clone(), dimensions, self.depth_format)
+            .context("Failed to create G-buffer depth buffer")?;
+
+        let gbuffer_framebuffer = Framebuffer::start(self.gbuffer_pass.clone())
+            .add(normals.clone())
+            .context("Failed to add a normal image to framebuffer")?
+            .add(positions.clone())
+            .context("Failed to add a position image to framebuffer")?
+            .add(depth)
+            .context("Failed to add a depth buffer to framebuffer")?
+            .build()
+            .map(|fb| Arc::new(fb) as Arc<dyn FramebufferAbstract + Send + Sync>)
+            .context("Failed to create framebuffer")?;
+        let layout = self
+            .occlusion_pipeline
+            .layout()
+            .descriptor_set_layout(0)
+            .context("Failed to get the ambient occlusion descriptor set layout")?;
+        let gbuffer_set = PersistentDescriptorSet::start(layout.clone())
+            .add_sampled_image(normals, self.sampler.clone())
+            .context("Failed to add normal image to descriptor set")?
+            .add_sampled_image(positions, self.sampler.clone())
+            .context("Failed to add position image to descriptor set")?
+            .build()
+            .map(|set| Arc::new(set) as Arc<dyn DescriptorSet + Send + Sync>)
+            .context("Failed to build descriptor set")?;
+
+        self.targets = Some(Targets {
+            gbuffer_framebuffer,
+            gbuffer_set,
+        });
+
+        Ok(())
+    }
+
+    /// Draws the opaque items to the G-buffer.
+    ///
+    /// This should be called outside of the scene render pass, before
+    /// [`apply`][`Self::apply`].
+    /// Each view is drawn with the dynamic state and the uniforms at the
+    /// dynamic offset of `set0` for its index.
+    pub fn draw_gbuffer<'a>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder,
+        dynamic_states: &[&DynamicState],
+        set0: &Arc<dyn DescriptorSet + Send + Sync>,
+        items: impl IntoIterator<Item = &'a DrawItem> + Clone,
+    ) -> anyhow::Result<()> {
+        let targets = self
+            .targets
+            .as_ref()
+            .expect("Should never fail: ambient occlusion should be resized before drawing");
+
+        builder
+            .begin_render_pass(
+                targets.gbuffer_framebuffer.clone(),
+                SubpassContents::Inline,
+                vec![
+                    [0.0, 0.0, 0.0, 0.0].into(),
+                    [0.0, 0.0, 0.0, 0.0].into(),
+                    ClearValue::DepthStencil((1.0, 0)),
+                ],
+            )
+            .context("Failed to begin G-buffer render pass")?;
+        for (view_i, dynamic_state) in dynamic_states.iter().enumerate() {
+            for item in items.clone().into_iter().filter(|item| !item.transparent) {
+                builder
+                    .draw_indexed(
+                        self.gbuffer_pipeline.clone(),
+                        dynamic_state,
+                        item.vertices.clone(),
+                        item.current_indices().clone(),
+                        set0.clone(),
+                        (),
+                        std::iter::once(Frame::uniform_offset(view_i, item.transform)),
+                    )
+                    .context("Failed to draw G-buffer")?;
+            }
+        }
+        builder
+            .end_render_pass()
+            .context("Failed to end G-buffer render pass")?;
+
+        Ok(())
+    }
+
+    /// Multiplies the occlusion to the view.
+    ///
+    /// This should be called in the scene render pass, after the opaque
+    /// submeshes of the view are drawn.
+    /// `proj` is the projection of the view, and `scene_radius` is the radius
+    /// of the bounding sphere of the scene.
+    pub fn apply(
+        &self,
+        builder: &mut AutoCommandBufferBuilder,
+        dynamic_state: &DynamicState,
+        proj: [[f32; 4]; 4],
+        scene_radius: f32,
+    ) -> anyhow::Result<()> {
+        let targets = self
+            .targets
+            .as_ref()
+            .expect("Should never fail: ambient occlusion should be resized before drawing");
+        let viewport = dynamic_state
+            .viewports
+            .as_ref()
+            .and_then(|viewports| viewports.first())
+            .expect("Should never fail: views should have viewports");
+        let [x, y] = viewport.origin;
+        let [width, height] = viewport.dimensions;
+        let params = occlusion_fs::ty::Params {
+            proj,
+            viewport: [x as i32, y as i32, (x + width) as i32, (y + height) as i32],
+            radius: self.radius * scene_radius,
+            intensity: self.intensity,
+        };
+        builder
+            .draw(
+                self.occlusion_pipeline.clone(),
+                dynamic_state,
+                BufferlessVertices {
+                    vertices: 3,
+                    instances: 1,
+                },
+                targets.gbuffer_set.clone(),
+                params,
+                std::iter::empty(),
+            )
+            .context("Failed to apply ambient occlusion")?;
+
+        Ok(())
+    }
+}
+
+/// Fragment shader writing the view-space normals and positions.
+mod gbuffer_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/bin/fbx-viewer/shaders/gbuffer.frag",
+    }
+}
+
+/// Fullscreen vertex shader.
+mod fullscreen_vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "src/bin/fbx-viewer/shaders/fullscreen.vert",
+    }
+}
+
+/// Fragment shader estimating the ambient occlusion.
+mod occlusion_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/bin/fbx-viewer/shaders/ssao.frag",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vulkano::pipeline::shader::ShaderInterfaceDefMatch;
+
+    use super::*;
+
+    #[test]
+    fn shader_interfaces() {
+        gbuffer_fs::MainInput
+            .matches(&vs::MainOutput)
+            .expect("The G-buffer shader inputs should match the mesh vertex shader outputs");
+        occlusion_fs::MainInput
+            .matches(&fullscreen_vs::MainOutput)
+            .expect("The occlusion shader inputs should match the fullscreen shader outputs");
+    }
+}
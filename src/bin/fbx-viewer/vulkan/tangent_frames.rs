@@ -0,0 +1,202 @@
+//! Visualization of the tangent frames of the vertices.
+// Allow `clippy::needless_borrow` for `vulkano::impl_vertex` macro.
+#![allow(clippy::needless_borrow)]
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use cgmath::{InnerSpace, Point3, Vector3};
+use fbx_viewer::data;
+use vulkano::{
+    buffer::{BufferUsage, ImmutableBuffer},
+    command_buffer::{AutoCommandBufferBuilder, DynamicState},
+    descriptor::{descriptor_set::DescriptorSet, pipeline_layout::PipelineLayoutAbstract},
+    device::{Device, Queue},
+    framebuffer::{RenderPassAbstract, Subpass},
+    pipeline::{vertex::SingleBufferDefinition, GraphicsPipeline},
+    sync::GpuFuture,
+};
+
+use crate::vulkan::wireframe_overlay::{fs, vs};
+
+/// Maximum number of the vertices to draw the frames of, in a scene.
+///
+/// Vertices are sampled with the same stride from all geometry meshes.
+const MAX_FRAMES: usize = 20_000;
+
+/// Length of the arrows relative to the average edge length of the mesh.
+const ARROW_SCALE: f32 = 0.5;
+
+/// Colors of the tangent, bitangent, and normal arrows.
+const COLORS: [[f32; 4]; 3] = [
+    [1.0, 0.0, 0.0, 1.0],
+    [0.0, 1.0, 0.0, 1.0],
+    [0.0, 0.0, 1.0, 1.0],
+];
+
+/// Endpoint of a line.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct LineVertex {
+    /// Position.
+    pub position: [f32; 3],
+}
+
+vulkano::impl_vertex!(LineVertex, position);
+
+/// Line lists of the tangent, bitangent, and normal arrows of a scene.
+pub struct TangentFrames {
+    /// Lines of the tangents, the bitangents, and the normals.
+    lines: [Arc<ImmutableBuffer<[LineVertex]>>; 3],
+}
+
+impl TangentFrames {
+    /// Generates the tangents of the geometry meshes, and uploads the arrows
+    /// of the sampled vertices.
+    ///
+    /// Returns `None` if the geometry meshes have no vertices.
+    pub fn upload(
+        geometries: &[&data::GeometryMesh],
+        queue: Arc<Queue>,
+    ) -> anyhow::Result<Option<(Self, Box<dyn GpuFuture>)>> {
+        let num_vertices = geometries
+            .iter()
+            .map(|geometry| geometry.positions.len())
+            .sum::<usize>();
+        if num_vertices == 0 {
+            return Ok(None);
+        }
+        let step = num_vertices.div_ceil(MAX_FRAMES);
+
+        let mut lines: [Vec<LineVertex>; 3] = Default::default();
+        for geometry in geometries {
+            let length = average_edge_length(geometry) * ARROW_SCALE;
+            let tangents = geometry.tangents();
+            for i in (0..geometry.positions.len()).step_by(step) {
+                let position = geometry.positions[i];
+                let normal = geometry
+                    .normals
+                    .get(i)
+                    .copied()
+                    .unwrap_or_else(|| Vector3::new(0.0, 0.0, 0.0));
+                let tangent = tangents[i].truncate();
+                let bitangent = normal.cross(tangent) * tangents[i].w;
+                for (lines, direction) in lines.iter_mut().zip([tangent, bitangent, normal]) {
+                    lines.extend_from_slice(&[
+                        LineVertex {
+                            position: position.into(),
+                        },
+                        LineVertex {
+                            position: (position + direction * length).into(),
+                        },
+                    ]);
+                }
+            }
+        }
+
+        let mut future: Option<Box<dyn GpuFuture>> = None;
+        let mut upload = |lines: Vec<LineVertex>| -> anyhow::Result<_> {
+            let (buf, buf_future) =
+                ImmutableBuffer::from_iter(lines.into_iter(), BufferUsage::all(), queue.clone())
+                    .context("Failed to upload tangent frames")?;
+            future = Some(match future.take() {
+                Some(prev) => prev.join(buf_future).boxed(),
+                None => buf_future.boxed(),
+            });
+            Ok(buf)
+        };
+        let [tangents, bitangents, normals] = lines;
+        let lines = [upload(tangents)?, upload(bitangents)?, upload(normals)?];
+        let future = future.expect("Should never fail: buffers are uploaded");
+
+        Ok(Some((Self { lines }, future)))
+    }
+}
+
+/// Pipeline to draw the tangent frames.
+pub struct TangentFramePipeline {
+    /// Pipeline.
+    #[allow(clippy::type_complexity)]
+    pipeline: Arc<
+        GraphicsPipeline<
+            SingleBufferDefinition<LineVertex>,
+            Box<dyn PipelineLayoutAbstract + Send + Sync>,
+            Arc<dyn RenderPassAbstract + Send + Sync>,
+        >,
+    >,
+}
+
+impl TangentFramePipeline {
+    /// Creates a new `TangentFramePipeline` for the first subpass of the
+    /// render pass.
+    pub fn new(
+        device: Arc<Device>,
+        render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    ) -> anyhow::Result<Self> {
+        let vs = vs::Shader::load(device.clone())
+            .context("Failed to load tangent frame vertex shader")?;
+        let fs = fs::Shader::load(device.clone())
+            .context("Failed to load tangent frame fragment shader")?;
+
+        let pipeline = GraphicsPipeline::start()
+            .vertex_input_single_buffer::<LineVertex>()
+            .vertex_shader(vs.main_entry_point(), ())
+            .line_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs.main_entry_point(), ())
+            .depth_stencil_simple_depth()
+            .render_pass(
+                Subpass::from(render_pass, 0).ok_or_else(|| anyhow!("Failed to create subpass"))?,
+            )
+            .with_auto_layout(device, &[(0, 0)])
+            .map(Arc::new)
+            .context("Failed to create tangent frame pipeline")?;
+
+        Ok(Self { pipeline })
+    }
+
+    /// Draws the tangent frames.
+    ///
+    /// `set0` should be the descriptor set with the transform uniforms at
+    /// `uniform_offset`.
+    pub fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder,
+        dynamic_state: &DynamicState,
+        set0: Arc<dyn DescriptorSet + Send + Sync>,
+        uniform_offset: u32,
+        frames: &TangentFrames,
+    ) -> anyhow::Result<()> {
+        for (lines, &color) in frames.lines.iter().zip(&COLORS) {
+            builder
+                .draw(
+                    self.pipeline.clone(),
+                    dynamic_state,
+                    lines.clone(),
+                    set0.clone(),
+                    fs::ty::PushConstants { color },
+                    std::iter::once(uniform_offset),
+                )
+                .context("Failed to draw tangent frames")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the average length of the edges of the triangles of the mesh.
+fn average_edge_length(geometry: &data::GeometryMesh) -> f32 {
+    let (sum, count) =
+        geometry
+            .triangles()
+            .fold((0.0_f64, 0_usize), |(sum, count), [p0, p1, p2]| {
+                let length = |a: Point3<f32>, b: Point3<f32>| f64::from((b - a).magnitude());
+                (
+                    sum + length(p0, p1) + length(p1, p2) + length(p2, p0),
+                    count + 3,
+                )
+            });
+    if count == 0 {
+        return 0.0;
+    }
+    (sum / count as f64) as f32
+}
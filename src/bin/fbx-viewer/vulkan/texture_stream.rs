@@ -0,0 +1,145 @@
+//! On-demand decoding of texture images.
+
+use std::{collections::HashSet, sync::mpsc, thread};
+
+use anyhow::Context;
+use fbx_viewer::{
+    data::{TextureImage, TextureIndex},
+    PremultipliedAlpha,
+};
+use image::{imageops::FilterType, DynamicImage, RgbaImage};
+use tracing::{debug, debug_span, warn};
+
+use crate::vulkan::drawable;
+
+/// Decode request.
+struct Request {
+    /// Texture index.
+    index: TextureIndex,
+    /// Name of the texture.
+    name: Option<String>,
+    /// Image to decode.
+    image: TextureImage,
+}
+
+/// Texture image decoded for upload.
+pub struct DecodedImage {
+    /// Image, downscaled if necessary.
+    pub image: RgbaImage,
+    /// Whether the colors are premultiplied by alpha.
+    pub premultiplied: bool,
+}
+
+/// Decoder of texture images running on a worker thread.
+///
+/// Each texture is decoded at most once, in the order of the requests.
+/// The worker thread exits when the stream is dropped.
+pub struct TextureStream {
+    /// Sender of the requests.
+    sender: mpsc::Sender<Request>,
+    /// Receiver of the decoded images.
+    receiver: mpsc::Receiver<(TextureIndex, DecodedImage)>,
+    /// Textures already requested.
+    requested: HashSet<TextureIndex>,
+}
+
+impl TextureStream {
+    /// Creates a new `TextureStream` and starts its worker thread.
+    ///
+    /// Images larger than `max_dim` are downscaled on the worker thread.
+    pub fn new(
+        max_dim: Option<u32>,
+        premultiplied_alpha: PremultipliedAlpha,
+    ) -> anyhow::Result<Self> {
+        let (sender, requests) = mpsc::channel::<Request>();
+        let (decoded, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("texture-decoder".into())
+            .spawn(move || {
+                for request in requests {
+                    let _span = debug_span!("decode_texture", name = ?request.name).entered();
+                    match request.image.get() {
+                        Ok(image) => {
+                            debug!("Decoded texture {:?}", request.name);
+                            // Scan the alpha channel here, not on the render
+                            // thread.
+                            request.image.is_opaque();
+                            let image = DecodedImage {
+                                image: prepare_image(image, max_dim),
+                                premultiplied: is_premultiplied(
+                                    &request.image,
+                                    premultiplied_alpha,
+                                ),
+                            };
+                            // The receiver is dropped with the scene.
+                            if decoded.send((request.index, image)).is_err() {
+                                break;
+                            }
+                        }
+                        // The dummy texture stays in place of the failed one.
+                        Err(e) => warn!("Failed to decode texture {:?}: {:#}", request.name, e),
+                    }
+                }
+            })
+            .context("Failed to spawn texture decoder thread")?;
+
+        Ok(Self {
+            sender,
+            receiver,
+            requested: HashSet::new(),
+        })
+    }
+
+    /// Requests decoding of the texture image, unless it is already
+    /// requested.
+    pub fn request(&mut self, index: TextureIndex, texture: &drawable::Texture) {
+        if !self.requested.insert(index) {
+            return;
+        }
+        let request = Request {
+            index,
+            name: texture.name.clone(),
+            image: texture.source.clone(),
+        };
+        if self.sender.send(request).is_err() {
+            warn!("Texture decoder thread has exited");
+        }
+    }
+
+    /// Returns the images decoded since the last call.
+    pub fn take_decoded(&mut self) -> Vec<(TextureIndex, DecodedImage)> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+/// Returns whether the colors of the decoded image are premultiplied by
+/// alpha in the mode.
+pub fn is_premultiplied(image: &TextureImage, mode: PremultipliedAlpha) -> bool {
+    match mode {
+        PremultipliedAlpha::Never => false,
+        PremultipliedAlpha::Detect => image.is_premultiplied() == Some(true),
+        PremultipliedAlpha::Always => true,
+    }
+}
+
+/// Converts the image to RGBA, downscaling it to fit in `max_dim` if
+/// necessary.
+///
+/// The aspect ratio is preserved.
+pub fn prepare_image(image: &DynamicImage, max_dim: Option<u32>) -> RgbaImage {
+    match max_dim {
+        Some(max_dim) if image.width() > max_dim || image.height() > max_dim => {
+            let _span = debug_span!("downscale_texture").entered();
+            let resized = image.resize(max_dim, max_dim, FilterType::Lanczos3);
+            debug!(
+                "Downscaled texture from {}x{} to {}x{}",
+                image.width(),
+                image.height(),
+                resized.width(),
+                resized.height()
+            );
+            resized.to_rgba8()
+        }
+        _ => image.to_rgba8(),
+    }
+}
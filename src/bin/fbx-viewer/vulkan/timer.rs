@@ -0,0 +1,352 @@
+//! GPU timer using timestamp queries.
+//!
+//! vulkano does not support queries in `AutoCommandBufferBuilder`, so
+//! timestamps are written by small separate command buffers submitted between
+//! the passes.
+
+use std::{fmt, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use vulkano::{
+    buffer::BufferAccess,
+    command_buffer::{
+        pool::standard::{StandardCommandPoolAlloc, StandardCommandPoolBuilder},
+        sys::{Flags, UnsafeCommandBuffer, UnsafeCommandBufferBuilder},
+        CommandBuffer, CommandBufferExecError, Kind,
+    },
+    device::{Device, DeviceOwned, Queue},
+    framebuffer::{FramebufferAbstract, RenderPassAbstract},
+    image::{ImageAccess, ImageLayout},
+    query::{QueryType, UnsafeQueryPool},
+    sync::{AccessCheckError, AccessFlagBits, GpuFuture, PipelineStages},
+    VulkanObject,
+};
+
+/// Number of query pools used in rotation.
+///
+/// Results of a frame are read when its pool is reused.
+const NUM_POOLS: usize = 2;
+
+/// Number of frames to wait for the results of a pool.
+///
+/// Queries of a frame whose submission failed never become available, so the
+/// results are discarded after this number of frames.
+const MAX_WAIT_FRAMES: u32 = 16;
+
+/// GPU timer for the passes.
+pub struct GpuTimer {
+    /// Device.
+    device: Arc<Device>,
+    /// Queue.
+    queue: Arc<Queue>,
+    /// Pass names.
+    passes: &'static [&'static str],
+    /// Query pools.
+    pools: Vec<Arc<UnsafeQueryPool>>,
+    /// Whether the pools have pending queries.
+    used: [bool; NUM_POOLS],
+    /// Number of frames waited for the results of the pools.
+    waited_frames: [u32; NUM_POOLS],
+    /// Index of the pool for the current frame, if timing is active.
+    current: Option<usize>,
+    /// Index of the pool for the next frame.
+    next: usize,
+    /// Nanoseconds per timestamp tick.
+    timestamp_period: f64,
+    /// Mask of the valid timestamp bits.
+    timestamp_mask: u64,
+}
+
+impl GpuTimer {
+    /// Creates a new `GpuTimer` for the given passes.
+    ///
+    /// Returns `Ok(None)` if the queue does not support timestamps.
+    pub fn new(
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+        passes: &'static [&'static str],
+    ) -> anyhow::Result<Option<Self>> {
+        let valid_bits = match queue.family().timestamp_valid_bits() {
+            Some(v) if v > 0 => v,
+            _ => return Ok(None),
+        };
+        let timestamp_mask = if valid_bits >= 64 {
+            !0
+        } else {
+            (1 << valid_bits) - 1
+        };
+        let timestamp_period = f64::from(device.physical_device().limits().timestamp_period());
+        let pools = (0..NUM_POOLS)
+            .map(|_| {
+                // One more slot than necessary, since
+                // `UnsafeQueryPool::queries_range()` rejects ranges ending at
+                // the last slot.
+                UnsafeQueryPool::new(
+                    device.clone(),
+                    QueryType::Timestamp,
+                    passes.len() as u32 * 2 + 1,
+                )
+                .map(Arc::new)
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to create timestamp query pools")?;
+
+        Ok(Some(Self {
+            device,
+            queue,
+            passes,
+            pools,
+            used: [false; NUM_POOLS],
+            waited_frames: [0; NUM_POOLS],
+            current: None,
+            next: 0,
+            timestamp_period,
+            timestamp_mask,
+        }))
+    }
+
+    /// Starts timing of a frame after the given future.
+    ///
+    /// Returns the timings of the frame which previously used the query pool,
+    /// if available.
+    ///
+    /// If the queries of the pool are not finished yet, the frame is not timed.
+    pub fn start_frame(
+        &mut self,
+        future: Box<dyn GpuFuture>,
+    ) -> anyhow::Result<(Box<dyn GpuFuture>, Option<PassTimings>)> {
+        let pool_i = self.next;
+        self.next = (self.next + 1) % NUM_POOLS;
+        self.current = None;
+
+        let mut timings = None;
+        if self.used[pool_i] {
+            match self.read_results(pool_i)? {
+                Some(v) => timings = Some(v),
+                None if self.waited_frames[pool_i] < MAX_WAIT_FRAMES => {
+                    self.waited_frames[pool_i] += 1;
+                    return Ok((future, None));
+                }
+                None => tracing::warn!("Discarding timestamp queries which never became available"),
+            }
+        }
+        self.waited_frames[pool_i] = 0;
+
+        let pool = self.pools[pool_i].clone();
+        let num_queries = self.passes.len() as u32 * 2;
+        let command_buffer = self.record(pool, |builder, pool| unsafe {
+            builder.reset_query_pool(
+                pool.queries_range(0, num_queries)
+                    .expect("Should never fail: the pool has enough slots"),
+            );
+        })?;
+        self.used[pool_i] = true;
+        self.current = Some(pool_i);
+
+        Ok((
+            future
+                .then_execute(self.queue.clone(), command_buffer)
+                .context("Failed to reset timestamp queries")?
+                .boxed(),
+            timings,
+        ))
+    }
+
+    /// Writes a timestamp at the beginning of the pass after the given future.
+    pub fn begin_pass(
+        &mut self,
+        future: Box<dyn GpuFuture>,
+        pass: usize,
+    ) -> anyhow::Result<Box<dyn GpuFuture>> {
+        self.write_timestamp(
+            future,
+            pass as u32 * 2,
+            PipelineStages {
+                top_of_pipe: true,
+                ..PipelineStages::none()
+            },
+        )
+    }
+
+    /// Writes a timestamp at the end of the pass after the given future.
+    pub fn end_pass(
+        &mut self,
+        future: Box<dyn GpuFuture>,
+        pass: usize,
+    ) -> anyhow::Result<Box<dyn GpuFuture>> {
+        self.write_timestamp(
+            future,
+            pass as u32 * 2 + 1,
+            PipelineStages {
+                bottom_of_pipe: true,
+                ..PipelineStages::none()
+            },
+        )
+    }
+
+    /// Writes a timestamp to the given query of the current pool.
+    fn write_timestamp(
+        &mut self,
+        future: Box<dyn GpuFuture>,
+        query: u32,
+        stages: PipelineStages,
+    ) -> anyhow::Result<Box<dyn GpuFuture>> {
+        let pool_i = match self.current {
+            Some(v) => v,
+            None => return Ok(future),
+        };
+        let pool = self.pools[pool_i].clone();
+        let command_buffer = self.record(pool, |builder, pool| unsafe {
+            builder.write_timestamp(
+                pool.query(query)
+                    .expect("Should never fail: the pool has enough slots"),
+                stages,
+            );
+        })?;
+
+        Ok(future
+            .then_execute(self.queue.clone(), command_buffer)
+            .context("Failed to write timestamp")?
+            .boxed())
+    }
+
+    /// Records a command buffer using the query pool.
+    fn record(
+        &self,
+        pool: Arc<UnsafeQueryPool>,
+        f: impl FnOnce(&mut UnsafeCommandBufferBuilder<StandardCommandPoolBuilder>, &UnsafeQueryPool),
+    ) -> anyhow::Result<QueryCommandBuffer> {
+        let command_pool = Device::standard_command_pool(&self.device, self.queue.family());
+        // Safety: The command buffer only uses the query pool, which is kept
+        // alive by `QueryCommandBuffer`, and the queries are not reset while
+        // they are in use, since a pool is reused only after its results
+        // become available.
+        let inner = unsafe {
+            let mut builder = UnsafeCommandBufferBuilder::new(
+                &command_pool,
+                Kind::primary(),
+                Flags::OneTimeSubmit,
+            )
+            .context("Failed to create command buffer builder")?;
+            f(&mut builder, &pool);
+            builder.build().context("Failed to build command buffer")?
+        };
+
+        Ok(QueryCommandBuffer { inner, pool })
+    }
+
+    /// Reads the results of the query pool.
+    ///
+    /// Returns `Ok(None)` if the results are not available yet.
+    fn read_results(&self, pool_i: usize) -> anyhow::Result<Option<PassTimings>> {
+        let pool = &self.pools[pool_i];
+        let num_queries = self.passes.len() * 2;
+        let mut data = vec![0_u64; num_queries];
+        // Safety: `data` has enough space for the results of the queries.
+        let result = unsafe {
+            let vk = self.device.pointers();
+            vk.GetQueryPoolResults(
+                self.device.internal_object(),
+                pool.internal_object(),
+                0,
+                num_queries as u32,
+                data.len() * std::mem::size_of::<u64>(),
+                data.as_mut_ptr() as *mut _,
+                std::mem::size_of::<u64>() as vk_sys::DeviceSize,
+                vk_sys::QUERY_RESULT_64_BIT,
+            )
+        };
+        match result {
+            vk_sys::SUCCESS => {}
+            vk_sys::NOT_READY => return Ok(None),
+            e => anyhow::bail!("Failed to get timestamp query results: VkResult = {}", e),
+        }
+
+        let timings = self
+            .passes
+            .iter()
+            .zip(data.chunks_exact(2))
+            .map(|(&name, timestamps)| {
+                let ticks = timestamps[1].wrapping_sub(timestamps[0]) & self.timestamp_mask;
+                let nanos = ticks as f64 * self.timestamp_period;
+                (name, Duration::from_nanos(nanos as u64))
+            })
+            .collect();
+
+        Ok(Some(PassTimings(timings)))
+    }
+}
+
+/// GPU time of the passes.
+#[derive(Debug, Clone)]
+pub struct PassTimings(Vec<(&'static str, Duration)>);
+
+impl fmt::Display for PassTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, time)) in self.0.iter().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{} {:.2} ms", name, time.as_secs_f64() * 1000.0)?;
+        }
+        Ok(())
+    }
+}
+
+/// Command buffer which only uses a query pool.
+struct QueryCommandBuffer {
+    /// Command buffer.
+    inner: UnsafeCommandBuffer<StandardCommandPoolAlloc>,
+    /// Query pool used by the command buffer.
+    // Held only to keep the pool alive while the command buffer is in use.
+    #[allow(dead_code)]
+    pool: Arc<UnsafeQueryPool>,
+}
+
+unsafe impl DeviceOwned for QueryCommandBuffer {
+    fn device(&self) -> &Arc<Device> {
+        self.inner.device()
+    }
+}
+
+unsafe impl CommandBuffer for QueryCommandBuffer {
+    type PoolAlloc = StandardCommandPoolAlloc;
+
+    fn inner(&self) -> &UnsafeCommandBuffer<Self::PoolAlloc> {
+        &self.inner
+    }
+
+    fn lock_submit(
+        &self,
+        _future: &dyn GpuFuture,
+        _queue: &Queue,
+    ) -> Result<(), CommandBufferExecError> {
+        // No buffers nor images to lock.
+        Ok(())
+    }
+
+    unsafe fn unlock(&self) {}
+
+    fn check_buffer_access(
+        &self,
+        _buffer: &dyn BufferAccess,
+        _exclusive: bool,
+        _queue: &Queue,
+    ) -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
+        Err(AccessCheckError::Unknown)
+    }
+
+    fn check_image_access(
+        &self,
+        _image: &dyn ImageAccess,
+        _layout: ImageLayout,
+        _exclusive: bool,
+        _queue: &Queue,
+    ) -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
+        Err(AccessCheckError::Unknown)
+    }
+
+    fn kind(&self) -> Kind<&dyn RenderPassAbstract, &dyn FramebufferAbstract> {
+        Kind::Primary
+    }
+}
@@ -0,0 +1,99 @@
+//! Uploading loaded scenes on the loader thread.
+
+use std::sync::Arc;
+
+use anyhow::Context;
+use cgmath::Rad;
+use fbx_viewer::data;
+use tracing::{info, info_span};
+use vulkano::{device::Device, sync::GpuFuture};
+
+use crate::vulkan::{capabilities::Capabilities, drawable, recompute_normals, setup::QueueSet};
+
+/// Parameters of uploads.
+#[derive(Debug, Clone, Copy)]
+pub struct UploadParams {
+    /// Crease angle to recompute normals with, or `None` to use imported
+    /// normals.
+    pub recompute_normals: Option<Rad<f32>>,
+    /// Layout of the vertex buffers.
+    pub vertex_layout: drawable::VertexLayout,
+    /// Whether to generate coarser levels of detail of the meshes.
+    pub lod: bool,
+    /// Whether to partition the meshes into spatial chunks.
+    pub chunks: bool,
+    /// Capabilities of the device to check the scene against.
+    pub capabilities: Capabilities,
+}
+
+/// Uploads the scene with the transfer queue, and waits for the upload.
+///
+/// The GPU resources of the returned scene are ready to use, but the caches
+/// are not initialized.
+pub fn upload(
+    scene: &data::Scene,
+    params: UploadParams,
+    device: Arc<Device>,
+    queues: &QueueSet,
+    check_canceled: &dyn Fn() -> anyhow::Result<()>,
+) -> anyhow::Result<drawable::Scene> {
+    params.capabilities.check_scene(scene)?;
+    let queue = queues.transfer.clone();
+    let loader = drawable::Loader::new(device.clone(), queue.clone(), params.vertex_layout);
+    let (mut drawable_scene, mut future) = loader
+        .load(scene)
+        .context("Failed to load scene as drawable data")?;
+    let recomputed = match params.recompute_normals {
+        Some(crease_angle) => {
+            info!("Recomputing normals: crease angle = {:?}", crease_angle);
+            let geometries = recompute_normals(scene, crease_angle);
+            check_canceled()?;
+            let reload_future =
+                drawable::Loader::new(device.clone(), queue.clone(), params.vertex_layout)
+                    .reload_vertices(&mut drawable_scene, &geometries)
+                    .context("Failed to upload recomputed normals")?;
+            future = match (future, reload_future) {
+                (Some(a), Some(b)) => Some(a.join(b).boxed()),
+                (a, b) => a.or(b),
+            };
+            Some(geometries)
+        }
+        None => None,
+    };
+    if params.lod {
+        check_canceled()?;
+        let lod_future = drawable::Loader::new(device.clone(), queue.clone(), params.vertex_layout)
+            .upload_lods(&mut drawable_scene, scene.geometry_meshes())
+            .context("Failed to upload levels of detail")?;
+        future = match (future, lod_future) {
+            (Some(a), Some(b)) => Some(a.join(b).boxed()),
+            (a, b) => a.or(b),
+        };
+    }
+    if params.chunks {
+        check_canceled()?;
+        let geometries = match &recomputed {
+            Some(geometries) => geometries.iter().collect::<Vec<_>>(),
+            None => scene.geometry_meshes().collect(),
+        };
+        let chunk_future = drawable::Loader::new(device, queue, params.vertex_layout)
+            .upload_chunks(&mut drawable_scene, scene, geometries, params.lod)
+            .context("Failed to upload chunks")?;
+        future = match (future, chunk_future) {
+            (Some(a), Some(b)) => Some(a.join(b).boxed()),
+            (a, b) => a.or(b),
+        };
+    }
+    // Wait for the upload here, so that the futures need not be sent to the
+    // render thread.
+    if let Some(future) = future {
+        let _span = info_span!("wait_upload").entered();
+        future
+            .then_signal_fence_and_flush()
+            .context("Failed to upload scene")?
+            .wait(None)
+            .context("Failed to upload scene")?;
+    }
+
+    Ok(drawable_scene)
+}
@@ -0,0 +1,231 @@
+//! Views.
+
+use cgmath::{Matrix4, Rad, Vector3};
+use fbx_viewer::{
+    camera::Camera,
+    util::{
+        bbox::BoundingSphere,
+        frustum::{ClipDepth, Frustum},
+    },
+    StereoMode,
+};
+use vulkano::pipeline::viewport::Viewport;
+
+use crate::{renderer::PERSPECTIVE, vulkan::mesh_pipelines::ColorMask};
+
+/// Conversion from GL coordinate system to Vulkan coordinate system.
+///
+/// See <https://matthewwellings.com/blog/the-new-vulkan-coordinate-system/>.
+const PROJ_GL_TO_VULKAN: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, 0.5, 0.5, 0.0, 0.0, 0.0, 1.0,
+);
+
+/// Near plane distance of the perspective views.
+const NEAR: f32 = PERSPECTIVE.near as f32;
+
+/// Far plane distance of the perspective views.
+const FAR: f32 = PERSPECTIVE.far as f32;
+
+/// Distance between the eyes relative to the diameter of the scene.
+///
+/// This follows the 1/30 rule of stereo photography.
+const EYE_SEPARATION_RATIO: f32 = 1.0 / 30.0;
+
+/// View kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewKind {
+    /// Perspective view with the user camera.
+    Perspective,
+    /// Orthographic view from the top.
+    Top,
+    /// Orthographic view from the front.
+    Front,
+    /// Orthographic view from the right side.
+    Side,
+    /// Perspective view for the left eye of the stereo pair.
+    LeftEye,
+    /// Perspective view for the right eye of the stereo pair.
+    RightEye,
+}
+
+impl ViewKind {
+    /// Returns view and projection matrices.
+    ///
+    /// Orthographic views are fit to the given bounding sphere of the scene.
+    /// The eye separation of the stereo views is derived from the size of
+    /// the scene, and the eyes converge at its center.
+    pub fn view_proj(
+        self,
+        camera: &Camera,
+        scene_sphere: &BoundingSphere<f32>,
+        aspect_ratio: f32,
+    ) -> (Matrix4<f32>, Matrix4<f32>) {
+        let camera_view = || -> Matrix4<f32> {
+            camera
+                .view()
+                .cast()
+                .unwrap_or_else(|| panic!("Abnormal camera posture: {:?}", camera))
+        };
+        let (eye_dir, up) = match self {
+            ViewKind::Perspective => {
+                let proj = PROJ_GL_TO_VULKAN
+                    * cgmath::perspective(Rad(PERSPECTIVE.fovy.0 as f32), aspect_ratio, NEAR, FAR);
+                return (camera_view(), proj);
+            }
+            ViewKind::LeftEye | ViewKind::RightEye => {
+                // The eyes look in parallel with asymmetric frustums, so that
+                // the views have no vertical parallax.
+                let view = camera_view();
+                let separation = scene_sphere.radius() * 2.0 * EYE_SEPARATION_RATIO;
+                let eye_offset = match self {
+                    ViewKind::LeftEye => -separation / 2.0,
+                    _ => separation / 2.0,
+                };
+                let center = view * scene_sphere.center().to_homogeneous();
+                let convergence = (-center.z).max(NEAR * 2.0);
+                let top = NEAR * (PERSPECTIVE.fovy.0 as f32 / 2.0).tan();
+                let right = top * aspect_ratio;
+                let shift = eye_offset * NEAR / convergence;
+                let view = Matrix4::from_translation(Vector3::new(-eye_offset, 0.0, 0.0)) * view;
+                let proj = PROJ_GL_TO_VULKAN
+                    * cgmath::frustum(-right - shift, right - shift, -top, top, NEAR, FAR);
+                return (view, proj);
+            }
+            ViewKind::Top => (Vector3::unit_y(), -Vector3::unit_z()),
+            ViewKind::Front => (Vector3::unit_z(), Vector3::unit_y()),
+            ViewKind::Side => (Vector3::unit_x(), Vector3::unit_y()),
+        };
+
+        let center = scene_sphere.center();
+        let radius = scene_sphere.radius().max(f32::EPSILON);
+        let eye = center + eye_dir * (radius * 2.0);
+        let view = Matrix4::look_at_rh(eye, center, up);
+        let (half_width, half_height) = (radius * aspect_ratio, radius);
+        let proj = PROJ_GL_TO_VULKAN
+            * cgmath::ortho(
+                -half_width,
+                half_width,
+                -half_height,
+                half_height,
+                radius,
+                radius * 3.0,
+            );
+
+        (view, proj)
+    }
+
+    /// Returns the color channels to draw the view to.
+    pub fn color_mask(self, stereo: Option<StereoMode>) -> ColorMask {
+        match (stereo, self) {
+            (Some(StereoMode::Anaglyph), ViewKind::LeftEye) => ColorMask::Red,
+            (Some(StereoMode::Anaglyph), ViewKind::RightEye) => ColorMask::Cyan,
+            _ => ColorMask::All,
+        }
+    }
+}
+
+/// Returns the frustum of the view-projection matrix.
+pub fn frustum(view_proj: &Matrix4<f32>) -> Frustum<f32> {
+    Frustum::from_view_proj(view_proj, ClipDepth::ZeroToOne)
+}
+
+/// Returns the range of the view-space depth covered by the bounding sphere.
+///
+/// The near end is clamped to zero.
+pub fn depth_range(view: &Matrix4<f32>, scene_sphere: &BoundingSphere<f32>) -> (f32, f32) {
+    let center = view * scene_sphere.center().to_homogeneous();
+    let distance = -center.z;
+    let radius = scene_sphere.radius();
+
+    ((distance - radius).max(0.0), distance + radius)
+}
+
+/// Maximum number of views returned by [`layout`].
+pub const MAX_VIEWS: usize = 4;
+
+/// Returns the views and their viewports for the window of the given
+/// dimensions.
+///
+/// If `quad` is `true`, the window is split into top, perspective, front, and
+/// side views.
+/// Stereo modes take precedence over the quad view.
+///
+/// In the anaglyph mode, both eyes cover the whole window.
+/// The left eye is drawn to the far half of the depth range and the right eye
+/// to the near half, so that the right eye is not occluded by the left one.
+pub fn layout(
+    quad: bool,
+    stereo: Option<StereoMode>,
+    dimensions: [u32; 2],
+) -> Vec<(ViewKind, Viewport)> {
+    let (width, height) = (dimensions[0] as f32, dimensions[1] as f32);
+    match stereo {
+        Some(StereoMode::Anaglyph) => {
+            return vec![
+                (
+                    ViewKind::LeftEye,
+                    Viewport {
+                        origin: [0.0, 0.0],
+                        dimensions: [width, height],
+                        depth_range: 0.5..1.0,
+                    },
+                ),
+                (
+                    ViewKind::RightEye,
+                    Viewport {
+                        origin: [0.0, 0.0],
+                        dimensions: [width, height],
+                        depth_range: 0.0..0.5,
+                    },
+                ),
+            ];
+        }
+        Some(StereoMode::SideBySide) => {
+            let half_width = width / 2.0;
+            return [(ViewKind::LeftEye, 0.0), (ViewKind::RightEye, half_width)]
+                .iter()
+                .map(|&(kind, x)| {
+                    (
+                        kind,
+                        Viewport {
+                            origin: [x, 0.0],
+                            dimensions: [half_width, height],
+                            depth_range: 0.0..1.0,
+                        },
+                    )
+                })
+                .collect();
+        }
+        None => {}
+    }
+    if !quad {
+        return vec![(
+            ViewKind::Perspective,
+            Viewport {
+                origin: [0.0, 0.0],
+                dimensions: [width, height],
+                depth_range: 0.0..1.0,
+            },
+        )];
+    }
+
+    let (half_width, half_height) = (width / 2.0, height / 2.0);
+    [
+        (ViewKind::Top, [0.0, 0.0]),
+        (ViewKind::Perspective, [half_width, 0.0]),
+        (ViewKind::Front, [0.0, half_height]),
+        (ViewKind::Side, [half_width, half_height]),
+    ]
+    .iter()
+    .map(|&(kind, origin)| {
+        (
+            kind,
+            Viewport {
+                origin,
+                dimensions: [half_width, half_height],
+                depth_range: 0.0..1.0,
+            },
+        )
+    })
+    .collect()
+}
@@ -0,0 +1,121 @@
+//! Wireframe overlay over shaded surfaces.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use vulkano::{
+    buffer::{BufferAccess, ImmutableBuffer},
+    command_buffer::{AutoCommandBufferBuilder, DynamicState},
+    descriptor::{descriptor_set::DescriptorSet, pipeline_layout::PipelineLayoutAbstract},
+    device::Device,
+    framebuffer::{RenderPassAbstract, Subpass},
+    pipeline::{blend::AttachmentBlend, GraphicsPipeline},
+};
+
+use crate::vulkan::drawable::VertexLayout;
+
+/// Color of the lines of all meshes.
+pub const COLOR: [f32; 4] = [0.05, 0.05, 0.05, 0.6];
+
+/// Color of the lines of the selected mesh.
+pub const SELECTED_COLOR: [f32; 4] = [1.0, 0.6, 0.0, 1.0];
+
+/// Pipeline to draw the edges of triangles over the shaded surfaces.
+///
+/// The lines are pulled toward the eye in the vertex shader, since the
+/// pipeline builder has no polygon offset, so that they pass the depth test
+/// against the surfaces they are on.
+pub struct WireframeOverlayPipeline {
+    /// Pipeline.
+    #[allow(clippy::type_complexity)]
+    pipeline: Arc<
+        GraphicsPipeline<
+            VertexLayout,
+            Box<dyn PipelineLayoutAbstract + Send + Sync>,
+            Arc<dyn RenderPassAbstract + Send + Sync>,
+        >,
+    >,
+}
+
+impl WireframeOverlayPipeline {
+    /// Creates a new `WireframeOverlayPipeline` for the first subpass of the
+    /// render pass.
+    ///
+    /// The device should support wireframes.
+    pub fn new(
+        device: Arc<Device>,
+        render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+        vertex_layout: VertexLayout,
+    ) -> anyhow::Result<Self> {
+        let vs = vs::Shader::load(device.clone())
+            .context("Failed to load wireframe overlay vertex shader")?;
+        let fs = fs::Shader::load(device.clone())
+            .context("Failed to load wireframe overlay fragment shader")?;
+
+        let pipeline = GraphicsPipeline::start()
+            .vertex_input(vertex_layout)
+            .vertex_shader(vs.main_entry_point(), ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs.main_entry_point(), ())
+            .polygon_mode_line()
+            .cull_mode_disabled()
+            .blend_collective(AttachmentBlend::alpha_blending())
+            .depth_stencil_simple_depth()
+            .depth_write(false)
+            .render_pass(
+                Subpass::from(render_pass, 0).ok_or_else(|| anyhow!("Failed to create subpass"))?,
+            )
+            .with_auto_layout(device, &[(0, 0)])
+            .map(Arc::new)
+            .context("Failed to create wireframe overlay pipeline")?;
+
+        Ok(Self { pipeline })
+    }
+
+    /// Draws the edges of the triangles in the color.
+    ///
+    /// `set0` should be the descriptor set with the transform uniforms at
+    /// `uniform_offset`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder,
+        dynamic_state: &DynamicState,
+        set0: Arc<dyn DescriptorSet + Send + Sync>,
+        uniform_offset: u32,
+        vertices: Arc<dyn BufferAccess + Send + Sync>,
+        indices: Arc<ImmutableBuffer<[u32]>>,
+        color: [f32; 4],
+    ) -> anyhow::Result<()> {
+        builder
+            .draw_indexed(
+                self.pipeline.clone(),
+                dynamic_state,
+                vertices,
+                indices,
+                set0,
+                fs::ty::PushConstants { color },
+                std::iter::once(uniform_offset),
+            )
+            .context("Failed to draw wireframe overlay")?;
+
+        Ok(())
+    }
+}
+
+/// Wireframe overlay vertex shader.
+pub(super) mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "src/bin/fbx-viewer/shaders/wireframe_overlay.vert",
+    }
+}
+
+/// Wireframe overlay fragment shader.
+pub(super) mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/bin/fbx-viewer/shaders/wireframe_overlay.frag",
+    }
+}
@@ -0,0 +1,279 @@
+//! Camera.
+
+use cgmath::{
+    Angle, EuclideanSpace, InnerSpace, Matrix4, Point3, Quaternion, Rad, Rotation, Rotation3,
+    Vector3,
+};
+use serde::{Deserialize, Serialize};
+use tracing::trace;
+
+use crate::data::raycast::Ray;
+
+/// Camera.
+///
+/// The camera looks toward -Z with +Y up at zero yaw and pitch.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Camera {
+    /// Eye position.
+    pub position: Point3<f64>,
+    /// Yaw.
+    ///
+    /// Positive is clockwise.
+    pub yaw: Rad<f64>,
+    /// Pitch.
+    ///
+    /// Positive is up.
+    pub pitch: Rad<f64>,
+    /// Scale.
+    pub scale: f64,
+}
+
+impl Camera {
+    /// Returns the forward direction vector in the camera space.
+    pub fn forward() -> Vector3<f64> {
+        -Vector3::unit_z()
+    }
+
+    /// Returns the up direction vector in the camera space.
+    pub fn up() -> Vector3<f64> {
+        Vector3::unit_y()
+    }
+
+    /// Returns the right direction vector in the camera space.
+    pub fn right() -> Vector3<f64> {
+        Vector3::unit_x()
+    }
+
+    /// Creates a new `Camera` with the given initial position.
+    pub fn with_position(position: Point3<f64>) -> Self {
+        Self {
+            position,
+            yaw: Rad(0.0),
+            pitch: Rad(0.0),
+            scale: 1.0,
+        }
+    }
+
+    /// Creates a new `Camera` at `eye` looking at `target`.
+    ///
+    /// The camera has no roll, so it looks straight up or down if the target
+    /// is right above or below the eye.
+    /// If the target is at the eye, the camera looks toward -Z.
+    pub fn look_at(eye: Point3<f64>, target: Point3<f64>) -> Self {
+        let offset = target - eye;
+        let distance = offset.magnitude();
+        if !(distance.is_finite() && distance > 0.0) {
+            return Self::with_position(eye);
+        }
+        let direction = offset / distance;
+        Self {
+            position: eye,
+            yaw: Rad((-direction.x).atan2(-direction.z)),
+            pitch: Rad(direction.y.clamp(-1.0, 1.0).asin()),
+            scale: 1.0,
+        }
+    }
+
+    /// Returns view matrix.
+    pub fn view(&self) -> Matrix4<f64> {
+        Matrix4::from_scale(self.scale)
+            * Matrix4::from(self.camera_direction().conjugate())
+            * Matrix4::from_translation(-self.position.to_vec())
+    }
+
+    /// Returns the direction the camera is looking at in the world space.
+    pub fn direction(&self) -> Vector3<f64> {
+        self.camera_direction().rotate_vector(Self::forward())
+    }
+
+    /// Returns the direction the camera is looking at.
+    fn camera_direction(&self) -> Quaternion<f64> {
+        // Note that this is extrinsic rotation.
+        Quaternion::from_angle_y(self.yaw) * Quaternion::from_angle_x(self.pitch)
+    }
+
+    /// Returns the ray from the eye through the given point on the screen.
+    ///
+    /// `ndc` is the normalized device coordinates of the point, with X axis
+    /// to the right and Y axis to the top.
+    pub fn ray(&self, projection: &Perspective, ndc: [f64; 2], aspect_ratio: f64) -> Ray {
+        let tan = (projection.fovy / 2.0).tan();
+        let direction = self.camera_direction().rotate_vector(Vector3::new(
+            ndc[0] * tan * aspect_ratio,
+            ndc[1] * tan,
+            -1.0,
+        ));
+        Ray::new(
+            self.position.cast().expect("Abnormal camera position"),
+            direction.cast().expect("Abnormal camera posture"),
+        )
+    }
+
+    /// Moves the camera.
+    pub fn move_rel(&mut self, vec: Vector3<f64>) {
+        self.position += self.camera_direction().rotate_vector(vec);
+        trace!("Camera = {:?}", self);
+    }
+
+    /// Rotates the camera to up.
+    pub fn rotate_up(&mut self, angle: Rad<f64>) {
+        self.pitch = (self.pitch + angle).normalize_signed();
+        trace!("Camera = {:?}", self);
+    }
+
+    /// Rotates the camera to right.
+    pub fn rotate_right(&mut self, angle: Rad<f64>) {
+        self.yaw = (self.yaw - angle).normalize_signed();
+        trace!("Camera = {:?}", self);
+    }
+
+    /// Interpolates the cameras linearly.
+    ///
+    /// `t` is 0.0 at `self` and 1.0 at `goal`.
+    /// The yaw turns the shorter way.
+    pub fn lerp(&self, goal: &Camera, t: f64) -> Self {
+        Self {
+            position: self.position + (goal.position - self.position) * t,
+            yaw: (self.yaw + (goal.yaw - self.yaw).normalize_signed() * t).normalize_signed(),
+            pitch: self.pitch + (goal.pitch - self.pitch) * t,
+            scale: self.scale + (goal.scale - self.scale) * t,
+        }
+    }
+}
+
+/// Perspective projection.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Perspective {
+    /// Vertical field of view.
+    pub fovy: Rad<f64>,
+    /// Distance to the near plane.
+    pub near: f64,
+    /// Distance to the far plane.
+    pub far: f64,
+}
+
+impl Perspective {
+    /// Returns the projection matrix for the aspect ratio (width / height).
+    ///
+    /// The matrix maps the depth to `[-w, w]`, as OpenGL.
+    pub fn matrix(&self, aspect_ratio: f64) -> Matrix4<f64> {
+        cgmath::perspective(self.fovy, aspect_ratio, self.near, self.far)
+    }
+
+    /// Returns the distance from the center of a sphere with the radius at
+    /// which the sphere fits in the vertical field of view.
+    pub fn fitting_distance(&self, radius: f64) -> f64 {
+        radius / (self.fovy / 2.0).sin()
+    }
+}
+
+/// Returns the ratio to move toward the goal in the elapsed time, for the
+/// exponential smoothing with the given time constant.
+///
+/// Applying the ratio every frame makes the motion independent of the frame
+/// rate.
+pub fn smoothing_ratio(elapsed_secs: f64, time_constant: f64) -> f64 {
+    1.0 - (-elapsed_secs / time_constant).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::{Deg, Transform};
+
+    /// Returns whether the vectors are almost equal.
+    fn almost_eq(a: Vector3<f64>, b: Vector3<f64>) -> bool {
+        (a - b).magnitude() < 1e-9
+    }
+
+    #[test]
+    fn look_at() {
+        let eye = Point3::new(1.0, 2.0, 3.0);
+        for &target in &[
+            Point3::new(1.0, 2.0, -5.0),
+            Point3::new(4.0, 2.0, 3.0),
+            Point3::new(-3.0, 5.0, 8.0),
+            Point3::new(1.0, -7.0, 3.5),
+        ] {
+            let camera = Camera::look_at(eye, target);
+            assert!(almost_eq(camera.direction(), (target - eye).normalize()));
+            // The target is straight ahead in the camera space.
+            let in_view = camera.view().transform_point(target);
+            assert!(almost_eq(
+                in_view.to_vec(),
+                Camera::forward() * (target - eye).magnitude()
+            ));
+        }
+        assert_eq!(Camera::look_at(eye, eye), Camera::with_position(eye));
+    }
+
+    #[test]
+    fn ray_through_center() {
+        let camera = Camera::look_at(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0));
+        let projection = Perspective {
+            fovy: Deg(60.0).into(),
+            near: 0.1,
+            far: 100.0,
+        };
+        let ray = camera.ray(&projection, [0.0, 0.0], 1.5);
+        let direction = ray.direction().normalize();
+        assert!((direction.x - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn fitting_distance() {
+        let projection = Perspective {
+            fovy: Deg(60.0).into(),
+            near: 0.1,
+            far: 100.0,
+        };
+        assert!((projection.fitting_distance(1.0) - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn lerp() {
+        let a = Camera {
+            position: Point3::new(0.0, 0.0, 0.0),
+            yaw: Deg(170.0).into(),
+            pitch: Deg(0.0).into(),
+            scale: 1.0,
+        };
+        let b = Camera {
+            position: Point3::new(2.0, 4.0, 6.0),
+            yaw: Deg(-170.0).into(),
+            pitch: Deg(20.0).into(),
+            scale: 3.0,
+        };
+        assert_eq!(a.lerp(&b, 0.0), a);
+        let mid = a.lerp(&b, 0.5);
+        assert_eq!(mid.position, Point3::new(1.0, 2.0, 3.0));
+        // Turns across 180 degrees instead of through 0 degrees.
+        assert!((Deg::from(mid.yaw).0.abs() - 180.0).abs() < 1e-9);
+        assert!((Deg::from(mid.pitch).0 - 10.0).abs() < 1e-9);
+        assert_eq!(mid.scale, 2.0);
+        let end = a.lerp(&b, 1.0);
+        assert!((end.yaw - b.yaw).normalize_signed().0.abs() < 1e-9);
+    }
+
+    #[test]
+    fn smoothing_is_frame_rate_independent() {
+        let once = smoothing_ratio(0.2, 0.1);
+        let half = smoothing_ratio(0.1, 0.1);
+        let twice = 1.0 - (1.0 - half) * (1.0 - half);
+        assert!((once - twice).abs() < 1e-12);
+    }
+
+    #[test]
+    fn serde_roundtrip() -> anyhow::Result<()> {
+        let camera = Camera {
+            position: Point3::new(1.0, -2.0, 3.5),
+            yaw: Rad(0.25),
+            pitch: Rad(-0.5),
+            scale: 2.0,
+        };
+        let json = serde_json::to_string(&camera)?;
+        assert_eq!(serde_json::from_str::<Camera>(&json)?, camera);
+        Ok(())
+    }
+}
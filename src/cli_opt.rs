@@ -1,12 +1,467 @@
 //! CLI options.
 
-use std::path::PathBuf;
+use std::{path::PathBuf, str::FromStr};
 
-use clap::Parser;
+use anyhow::{anyhow, bail, Context};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use glob::Pattern;
+use rgb::RGB;
 
 /// CLI options.
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct CliOpt {
+    /// Subcommand
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// FBX or glTF file
+    #[arg(required_unless_present = "watch_dir")]
+    pub fbx_path: Option<PathBuf>,
+    /// Show the FBX files in the directory (PgUp/PgDn to page through them),
+    /// and newly created or modified files automatically
+    #[arg(long, value_name = "DIR")]
+    pub watch_dir: Option<PathBuf>,
+    /// Recompute normals instead of using imported ones
+    #[arg(long)]
+    pub recompute_normals: bool,
+    /// Crease angle in degrees for recomputed normals
+    #[arg(long, value_name = "DEGREES", default_value_t = 30.0)]
+    pub crease_angle: f32,
+    /// Start with the quad view (top, perspective, front, and side)
+    #[arg(long)]
+    pub quad_view: bool,
+    /// Background color ("#RRGGBB"), or top and bottom colors of a vertical
+    /// gradient ("#RRGGBB,#RRGGBB")
+    #[arg(long, value_name = "COLORS", default_value = "#0000ff")]
+    pub background: Background,
+    /// Anti-aliasing method
+    #[arg(long, value_enum, value_name = "METHOD", default_value_t = AntiAliasing::None)]
+    pub aa: AntiAliasing,
+    /// Add bloom of the given intensity (e.g. 1.0) around the emissive
+    /// surfaces and the bright highlights
+    #[arg(long, value_name = "INTENSITY", value_parser = parse_bloom_intensity)]
+    pub bloom: Option<f32>,
+    /// Darken the creases and the contact areas with screen-space ambient
+    /// occlusion of the given intensity (e.g. 1.0)
+    #[arg(long, value_name = "INTENSITY", value_parser = parse_ssao_intensity)]
+    pub ssao: Option<f32>,
+    /// Radius of the screen-space ambient occlusion, relative to the radius
+    /// of the bounding sphere of the scene
+    #[arg(long, value_name = "RATIO", default_value_t = 0.05, value_parser = parse_ssao_radius)]
+    pub ssao_radius: f32,
+    /// Index of the monitor to show the window and go fullscreen on (F11)
+    #[arg(long, value_name = "INDEX")]
+    pub monitor: Option<usize>,
+    /// Depth buffer format
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = DepthFormat::D32)]
+    pub depth_format: DepthFormat,
+    /// Swapchain format: 8-bit sRGB (auto), 16-bit float in the extended
+    /// sRGB linear color space for HDR displays (hdr), or a specific format
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = SwapchainFormat::Auto)]
+    pub swapchain_format: SwapchainFormat,
+    /// Replace the image of the textures named NAME with the image file (can
+    /// be repeated; T reloads the files)
+    #[arg(long, value_name = "NAME=PATH")]
+    pub remap_texture: Vec<TextureRemap>,
+    /// Ignore the loaded materials and draw all meshes with the given material
+    #[arg(long, value_enum, value_name = "MATERIAL")]
+    pub override_material: Option<MaterialOverride>,
+    /// Store vertex normals, UVs, and colors in packed formats, roughly
+    /// halving the vertex memory
+    #[arg(long)]
+    pub compact_vertices: bool,
+    /// Generate simplified versions of the meshes, and draw them in place of
+    /// the meshes far from the camera
+    #[arg(long)]
+    pub lod: bool,
+    /// Partition the meshes into spatial chunks and merge the meshes in each
+    /// chunk, to draw scenes of many small meshes with fewer draw calls
+    #[arg(long)]
+    pub chunks: bool,
+    /// Downscale texture images larger than the given size in either
+    /// dimension before uploading them, to save GPU memory
+    #[arg(long, value_name = "PIXELS", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_texture_dim: Option<u32>,
+    /// Draw transparent surfaces with depth peeling of the given number of
+    /// layers, instead of alpha blending in drawing order
+    #[arg(long, value_name = "LAYERS", value_parser = clap::value_parser!(u32).range(1..))]
+    pub depth_peeling: Option<u32>,
+    /// Render the scene for both eyes, as a red-cyan anaglyph or side by side
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub stereo: Option<StereoMode>,
+    /// File to export the scene with the edits (transforms, hidden meshes,
+    /// and texture remaps) to on Ctrl-F12, in the format detected from the
+    /// extension (ply, stl, usda, or usdz) [default: fbx-viewer-<time>.usdz]
+    #[arg(long, value_name = "PATH")]
+    pub export_path: Option<PathBuf>,
+    /// Write a trace in Chrome trace event format to the file
+    #[arg(long, global = true, value_name = "PATH")]
+    pub trace_output: Option<PathBuf>,
+    /// Print the time spent in each stage of loading (parsing,
+    /// triangulation, attribute expansion, image decoding, and GPU upload)
+    #[arg(long, global = true)]
+    pub timings: bool,
+    /// Fail on any FBX object which cannot be loaded, instead of skipping it
+    #[arg(long, global = true)]
+    pub strict: bool,
+    /// Load only the FBX meshes whose model or geometry names match the glob
+    /// pattern (can be repeated)
+    #[arg(long, global = true, value_name = "GLOB")]
+    pub include: Vec<Pattern>,
+    /// Skip the FBX meshes whose model or geometry names match the glob
+    /// pattern (can be repeated)
+    #[arg(long, global = true, value_name = "GLOB")]
+    pub exclude: Vec<Pattern>,
+    /// Skip loading FBX textures
+    #[arg(long, global = true)]
+    pub no_textures: bool,
+    /// Cache decoded FBX textures on disk, and use the cached ones
+    #[arg(long, global = true, value_enum, value_name = "MODE")]
+    pub texture_cache: Option<TextureCacheMode>,
+    /// Skip loading FBX materials and textures, and use a single default
+    /// material
+    #[arg(long, global = true)]
+    pub no_materials: bool,
+    /// Draw the FBX materials with transparent textures as cutouts, which
+    /// discard the fragments with alpha below the given value (from 0 to 1)
+    #[arg(long, global = true, value_name = "CUTOFF", value_parser = parse_alpha_cutoff)]
+    pub alpha_test: Option<f32>,
+    /// How to find the textures whose colors are premultiplied by alpha,
+    /// which are blended accordingly
+    #[arg(long, value_enum, value_name = "MODE", default_value_t = PremultipliedAlpha::Never)]
+    pub premultiplied_alpha: PremultipliedAlpha,
+    /// Faces to cull on all meshes, instead of the back faces of single-sided
+    /// materials only
+    #[arg(long, value_enum, value_name = "FACES")]
+    pub cull: Option<CullFaces>,
+    /// Winding order of the front faces, for files exported clockwise
+    #[arg(long, value_enum, value_name = "ORDER", default_value_t = Winding::Ccw)]
+    pub winding: Winding,
+    /// Load each FBX mesh as its bounding box, and draw them as wireframes,
+    /// for a quick preview of huge files
+    #[arg(long, global = true)]
+    pub preview_bboxes: bool,
+    /// Bake the transforms of the FBX node hierarchy into the vertices,
+    /// instead of loading each mesh in its local coordinates
+    #[arg(long, global = true)]
+    pub flatten: bool,
+}
+
+/// Anti-aliasing method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AntiAliasing {
+    /// No anti-aliasing.
+    None,
+    /// FXAA post-process pass.
+    Fxaa,
+    /// 4x MSAA.
+    Msaa4,
+}
+
+/// How to find the texture images premultiplied by alpha.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PremultipliedAlpha {
+    /// All images have straight alpha.
+    Never,
+    /// Images whose color components never exceed their alpha are
+    /// premultiplied.
+    Detect,
+    /// All images are premultiplied.
+    Always,
+}
+
+/// Faces to cull.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+pub enum CullFaces {
+    /// No faces.
+    #[default]
+    None,
+    /// Back faces.
+    Back,
+    /// Front faces.
+    Front,
+}
+
+/// Winding order of the front faces seen from the front.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Winding {
+    /// Counter-clockwise.
+    Ccw,
+    /// Clockwise.
+    Cw,
+}
+
+/// Material to draw all meshes with, instead of the loaded materials.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MaterialOverride {
+    /// Flat gray.
+    FlatGray,
+    /// Normals as colors.
+    Normals,
+    /// Checkerboard in UV space.
+    Checker,
+}
+
+/// Stereo rendering mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StereoMode {
+    /// Red for the left eye and cyan for the right eye.
+    Anaglyph,
+    /// Left eye on the left half and right eye on the right half.
+    SideBySide,
+}
+
+/// Storage of decoded textures in the on-disk cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TextureCacheMode {
+    /// Uncompressed pixels, fastest to load.
+    Raw,
+    /// Compressed pixels, smaller on disk.
+    Compressed,
+}
+
+/// Depth buffer format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DepthFormat {
+    /// 32-bit floating point depth.
+    D32,
+    /// 24-bit normalized integer depth.
+    D24,
+}
+
+/// Swapchain format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SwapchainFormat {
+    /// 8-bit sRGB format, in the BGRA or RGBA order.
+    Auto,
+    /// 16-bit floating point format in the extended sRGB linear color space.
+    Hdr,
+    /// 8-bit BGRA sRGB format.
+    Bgra8Srgb,
+    /// 8-bit RGBA sRGB format.
+    Rgba8Srgb,
+    /// 8-bit BGRA normalized format, without sRGB encoding.
+    Bgra8Unorm,
+    /// 8-bit RGBA normalized format, without sRGB encoding.
+    Rgba8Unorm,
+}
+
+/// Subcommands.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Compare two FBX or glTF files and print the differences
+    Diff(DiffOpt),
+    /// Dump the scene of an FBX or glTF file in a machine-readable format
+    Dump(DumpOpt),
+    /// Export an FBX or glTF file to another format
+    Export(ExportOpt),
+    /// Write the textures embedded in an FBX file to a directory
+    ExtractTextures(ExtractTexturesOpt),
+    /// Print statistics of the meshes in an FBX or glTF file
+    Info(InfoOpt),
+}
+
+/// Options for `diff` subcommand.
+#[derive(Debug, Args)]
+pub struct DiffOpt {
+    /// Old FBX or glTF file
+    pub old: PathBuf,
+    /// New FBX or glTF file
+    pub new: PathBuf,
+}
+
+/// Options for `dump` subcommand.
+#[derive(Debug, Args)]
+pub struct DumpOpt {
+    /// FBX or glTF file
+    pub fbx_path: PathBuf,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = DumpFormat::Json)]
+    pub format: DumpFormat,
+}
+
+/// Output format of `dump` subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DumpFormat {
+    /// JSON.
+    Json,
+}
+
+/// Options for `export` subcommand.
+#[derive(Debug, Args)]
+pub struct ExportOpt {
+    /// FBX or glTF file
+    pub fbx_path: PathBuf,
+    /// Output file (the format is detected from the extension: ply, stl, usda,
+    /// usdz)
+    pub output: PathBuf,
+    /// Fail if any mesh is not watertight
+    #[arg(long)]
+    pub check_watertight: bool,
+}
+
+/// Options for `extract-textures` subcommand.
+#[derive(Debug, Args)]
+pub struct ExtractTexturesOpt {
     /// FBX file
     pub fbx_path: PathBuf,
+    /// Output directory (created if missing)
+    #[arg(long, value_name = "DIR")]
+    pub out: PathBuf,
+}
+
+/// Options for `info` subcommand.
+#[derive(Debug, Args)]
+pub struct InfoOpt {
+    /// FBX or glTF file
+    pub fbx_path: PathBuf,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = InfoFormat::Text)]
+    pub format: InfoFormat,
+}
+
+/// Output format of `info` subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InfoFormat {
+    /// Human-readable text.
+    Text,
+    /// JSON.
+    Json,
+    /// CSV with a row for the scene and each mesh and texture.
+    Csv,
+}
+
+/// Background of the viewer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Background {
+    /// Color at the top.
+    pub top: RGB<f32>,
+    /// Color at the bottom.
+    pub bottom: RGB<f32>,
+}
+
+impl Background {
+    /// Creates a new `Background` with a single color.
+    pub fn solid(color: RGB<f32>) -> Self {
+        Self {
+            top: color,
+            bottom: color,
+        }
+    }
+
+    /// Creates a new `Background` with a vertical gradient.
+    pub fn gradient(top: RGB<f32>, bottom: RGB<f32>) -> Self {
+        Self { top, bottom }
+    }
+
+    /// Returns whether the background has a single color.
+    pub fn is_solid(&self) -> bool {
+        self.top == self.bottom
+    }
+}
+
+impl FromStr for Background {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(',') {
+            Some((top, bottom)) => Ok(Self::gradient(
+                parse_hex_color(top.trim())?,
+                parse_hex_color(bottom.trim())?,
+            )),
+            None => parse_hex_color(s.trim()).map(Self::solid),
+        }
+    }
+}
+
+/// Replacement of a texture image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextureRemap {
+    /// Name of the textures to replace.
+    pub name: String,
+    /// Path of the image file.
+    pub path: PathBuf,
+}
+
+impl FromStr for TextureRemap {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, path) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Texture remap should be in `NAME=PATH` format: {:?}", s))?;
+        if path.is_empty() {
+            bail!("Path of the texture remap is empty: {:?}", s);
+        }
+
+        Ok(Self {
+            name: name.to_owned(),
+            path: path.into(),
+        })
+    }
+}
+
+/// Parses an alpha cutoff from 0 to 1.
+fn parse_alpha_cutoff(s: &str) -> anyhow::Result<f32> {
+    let cutoff = s
+        .parse::<f32>()
+        .with_context(|| format!("Invalid alpha cutoff: {:?}", s))?;
+    if !(0.0..=1.0).contains(&cutoff) {
+        bail!("Alpha cutoff should be from 0 to 1: {:?}", s);
+    }
+
+    Ok(cutoff)
+}
+
+/// Parses a non-negative bloom intensity.
+fn parse_bloom_intensity(s: &str) -> anyhow::Result<f32> {
+    let intensity = s
+        .parse::<f32>()
+        .with_context(|| format!("Invalid bloom intensity: {:?}", s))?;
+    if !intensity.is_finite() || intensity < 0.0 {
+        bail!("Bloom intensity should be non-negative: {:?}", s);
+    }
+
+    Ok(intensity)
+}
+
+/// Parses a non-negative ambient occlusion intensity.
+fn parse_ssao_intensity(s: &str) -> anyhow::Result<f32> {
+    let intensity = s
+        .parse::<f32>()
+        .with_context(|| format!("Invalid ambient occlusion intensity: {:?}", s))?;
+    if !intensity.is_finite() || intensity < 0.0 {
+        bail!(
+            "Ambient occlusion intensity should be non-negative: {:?}",
+            s
+        );
+    }
+
+    Ok(intensity)
+}
+
+/// Parses a positive ambient occlusion radius.
+fn parse_ssao_radius(s: &str) -> anyhow::Result<f32> {
+    let radius = s
+        .parse::<f32>()
+        .with_context(|| format!("Invalid ambient occlusion radius: {:?}", s))?;
+    if !radius.is_finite() || radius <= 0.0 {
+        bail!("Ambient occlusion radius should be positive: {:?}", s);
+    }
+
+    Ok(radius)
+}
+
+/// Parses a color in `#RRGGBB` format.
+fn parse_hex_color(s: &str) -> anyhow::Result<RGB<f32>> {
+    let hex = s
+        .strip_prefix('#')
+        .ok_or_else(|| anyhow!("Color should start with `#`: {:?}", s))?;
+    if hex.len() != 6 {
+        bail!("Color should be in `#RRGGBB` format: {:?}", s);
+    }
+    let value = u32::from_str_radix(hex, 16).with_context(|| format!("Invalid color: {:?}", s))?;
+    let component = |shift: u32| ((value >> shift) & 0xff) as f32 / 255.0;
+
+    Ok(RGB::new(component(16), component(8), component(0)))
 }
@@ -1,15 +1,31 @@
 //! 3D content data.
 
 pub use self::{
-    geometry::GeometryMesh,
-    material::{LambertData, Material, ShadingData},
+    diff::{CountDiff, ObjectDiff, SceneDiff, ValueChange},
+    edits::SceneEdits,
+    geometry::{DegenerateTriangles, GeometryMesh, MeshTopology},
+    inspect::{TriangleInspection, VertexInspection},
+    material::{LambertData, Material, PbrData, PhongData, ShadingData},
     mesh::Mesh,
-    scene::{GeometryMeshIndex, MaterialIndex, MeshIndex, Scene, TextureIndex},
-    texture::{Texture, WrapMode},
+    node::{Node, RotationOrder},
+    scene::{
+        GeometryMeshIndex, IndexError, MaterialIndex, MeshIndex, NodeIndex, Scene, SceneId,
+        TextureIndex,
+    },
+    stats::{MeshStats, SceneStats, TextureStats},
+    texture::{Texture, TextureImage, WrapMode},
+    warning::LoadWarning,
 };
 
+mod diff;
+mod edits;
 mod geometry;
+mod inspect;
 mod material;
 mod mesh;
+mod node;
+pub mod raycast;
 mod scene;
+mod stats;
 mod texture;
+mod warning;
@@ -0,0 +1,438 @@
+//! Scene diff.
+
+use std::{collections::HashMap, fmt};
+
+use rgb::RGB;
+
+use crate::data::{GeometryMesh, Material, Mesh, Scene, ShadingData, Texture};
+
+/// Differences between two scenes.
+#[derive(Debug, Clone)]
+pub struct SceneDiff {
+    /// Numbers of objects.
+    pub counts: Vec<CountDiff>,
+    /// Mesh differences.
+    pub meshes: Vec<ObjectDiff>,
+    /// Material differences.
+    pub materials: Vec<ObjectDiff>,
+    /// Texture differences.
+    pub textures: Vec<ObjectDiff>,
+}
+
+impl SceneDiff {
+    /// Compares the two scenes.
+    pub fn new(old: &Scene, new: &Scene) -> Self {
+        let counts = vec![
+            CountDiff::new(
+                "geometry meshes",
+                old.geometry_meshes().count(),
+                new.geometry_meshes().count(),
+            ),
+            CountDiff::new("meshes", old.meshes().count(), new.meshes().count()),
+            CountDiff::new(
+                "materials",
+                old.materials().count(),
+                new.materials().count(),
+            ),
+            CountDiff::new("textures", old.textures().count(), new.textures().count()),
+        ];
+
+        let meshes = diff_objects(
+            old.meshes().map(|mesh| MeshSummary::new(old, mesh)),
+            new.meshes().map(|mesh| MeshSummary::new(new, mesh)),
+        );
+        let materials = diff_objects(
+            old.materials()
+                .map(|material| MaterialSummary::new(old, material)),
+            new.materials()
+                .map(|material| MaterialSummary::new(new, material)),
+        );
+        let textures = diff_objects(
+            old.textures().map(TextureSummary::new),
+            new.textures().map(TextureSummary::new),
+        );
+
+        Self {
+            counts,
+            meshes,
+            materials,
+            textures,
+        }
+    }
+
+    /// Returns whether the scenes have no differences.
+    pub fn is_empty(&self) -> bool {
+        self.counts.iter().all(|count| count.old == count.new)
+            && self.meshes.is_empty()
+            && self.materials.is_empty()
+            && self.textures.is_empty()
+    }
+}
+
+impl fmt::Display for SceneDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No differences found.");
+        }
+
+        writeln!(f, "Object counts:")?;
+        for count in &self.counts {
+            writeln!(f, "    {}", count)?;
+        }
+        for (label, diffs) in &[
+            ("Meshes", &self.meshes),
+            ("Materials", &self.materials),
+            ("Textures", &self.textures),
+        ] {
+            if diffs.is_empty() {
+                continue;
+            }
+            writeln!(f, "{}:", label)?;
+            for diff in diffs.iter() {
+                write!(f, "{}", diff)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Difference of the numbers of objects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CountDiff {
+    /// Object type.
+    pub label: &'static str,
+    /// Number of objects in the old scene.
+    pub old: usize,
+    /// Number of objects in the new scene.
+    pub new: usize,
+}
+
+impl CountDiff {
+    /// Creates a new `CountDiff`.
+    fn new(label: &'static str, old: usize, new: usize) -> Self {
+        Self { label, old, new }
+    }
+}
+
+impl fmt::Display for CountDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.label, self.old)?;
+        if self.old != self.new {
+            write!(
+                f,
+                " -> {} ({:+})",
+                self.new,
+                self.new as i64 - self.old as i64
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Difference of an object.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectDiff {
+    /// The object exists only in the new scene.
+    Added {
+        /// Name.
+        name: String,
+    },
+    /// The object exists only in the old scene.
+    Removed {
+        /// Name.
+        name: String,
+    },
+    /// The object seems to be renamed.
+    Renamed {
+        /// Old name.
+        old: String,
+        /// New name.
+        new: String,
+    },
+    /// The object exists in both scenes, but has different values.
+    Changed {
+        /// Name.
+        name: String,
+        /// Changed values.
+        changes: Vec<ValueChange>,
+    },
+}
+
+impl fmt::Display for ObjectDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjectDiff::Added { name } => writeln!(f, "    + {:?}", name),
+            ObjectDiff::Removed { name } => writeln!(f, "    - {:?}", name),
+            ObjectDiff::Renamed { old, new } => writeln!(f, "    ~ {:?} -> {:?}", old, new),
+            ObjectDiff::Changed { name, changes } => {
+                writeln!(f, "    * {:?}", name)?;
+                for change in changes {
+                    writeln!(f, "        {}", change)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Changed value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueChange {
+    /// Property name.
+    pub property: &'static str,
+    /// Old value.
+    pub old: String,
+    /// New value.
+    pub new: String,
+}
+
+impl fmt::Display for ValueChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.property, self.old, self.new)
+    }
+}
+
+/// Summary of an object to be compared.
+trait Summary {
+    /// Returns the name.
+    fn name(&self) -> &str;
+
+    /// Returns the comparable values.
+    fn values(&self) -> Vec<(&'static str, String)>;
+}
+
+/// Compares the objects and returns the differences.
+///
+/// Objects are matched by names (and the order of appearance among objects
+/// with the same name).
+/// Unmatched objects with identical values are reported as renamed.
+fn diff_objects<T: Summary>(
+    old: impl IntoIterator<Item = T>,
+    new: impl IntoIterator<Item = T>,
+) -> Vec<ObjectDiff> {
+    /// Returns objects keyed by name and occurrence count.
+    fn keyed<T: Summary>(iter: impl IntoIterator<Item = T>) -> Vec<((String, usize), T)> {
+        let mut occurrences = HashMap::new();
+        iter.into_iter()
+            .map(|obj| {
+                let count = occurrences.entry(obj.name().to_owned()).or_insert(0);
+                let key = (obj.name().to_owned(), *count);
+                *count += 1;
+                (key, obj)
+            })
+            .collect()
+    }
+
+    let old = keyed(old);
+    let mut new: HashMap<_, _> = keyed(new).into_iter().enumerate().collect();
+    let mut new_by_key: HashMap<(String, usize), usize> =
+        new.iter().map(|(&i, (key, _))| (key.clone(), i)).collect();
+
+    let mut diffs = Vec::new();
+    let mut removed = Vec::new();
+    for (key, old_obj) in old {
+        let new_obj = match new_by_key.remove(&key).and_then(|i| new.remove(&i)) {
+            Some((_, obj)) => obj,
+            None => {
+                removed.push(old_obj);
+                continue;
+            }
+        };
+        let new_values = new_obj.values();
+        let changes = old_obj
+            .values()
+            .into_iter()
+            .zip(new_values)
+            .filter(|(old, new)| old.1 != new.1)
+            .map(|((property, old), (_, new))| ValueChange { property, old, new })
+            .collect::<Vec<_>>();
+        if !changes.is_empty() {
+            diffs.push(ObjectDiff::Changed {
+                name: key.0,
+                changes,
+            });
+        }
+    }
+
+    let mut added = new.into_iter().collect::<Vec<_>>();
+    added.sort_by_key(|(i, _)| *i);
+    let mut added = added
+        .into_iter()
+        .map(|(_, (_, obj))| obj)
+        .collect::<Vec<_>>();
+    for old_obj in removed {
+        let old_values = old_obj.values();
+        match added.iter().position(|obj| obj.values() == old_values) {
+            Some(pos) => {
+                let new_obj = added.remove(pos);
+                diffs.push(ObjectDiff::Renamed {
+                    old: old_obj.name().to_owned(),
+                    new: new_obj.name().to_owned(),
+                });
+            }
+            None => diffs.push(ObjectDiff::Removed {
+                name: old_obj.name().to_owned(),
+            }),
+        }
+    }
+    diffs.extend(added.into_iter().map(|obj| ObjectDiff::Added {
+        name: obj.name().to_owned(),
+    }));
+
+    diffs
+}
+
+/// Mesh summary.
+struct MeshSummary<'a> {
+    /// Name.
+    name: &'a str,
+    /// Number of vertices.
+    num_vertices: Option<usize>,
+    /// Number of triangles.
+    num_triangles: Option<usize>,
+    /// Material names.
+    materials: Vec<&'a str>,
+}
+
+impl<'a> MeshSummary<'a> {
+    /// Creates a new `MeshSummary`.
+    fn new(scene: &'a Scene, mesh: &'a Mesh) -> Self {
+        let geometry = scene.geometry_mesh(mesh.geometry_mesh_index).ok();
+        Self {
+            name: mesh.name.as_deref().unwrap_or_default(),
+            num_vertices: geometry.map(|geometry| geometry.positions.len()),
+            num_triangles: geometry.map(GeometryMesh::num_triangles),
+            materials: mesh
+                .materials
+                .iter()
+                .map(|&i| {
+                    scene
+                        .material(i)
+                        .ok()
+                        .and_then(|material| material.name.as_deref())
+                        .unwrap_or_default()
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Summary for MeshSummary<'_> {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn values(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("vertices", format!("{:?}", self.num_vertices)),
+            ("triangles", format!("{:?}", self.num_triangles)),
+            ("materials", format!("{:?}", self.materials)),
+        ]
+    }
+}
+
+/// Material summary.
+struct MaterialSummary<'a> {
+    /// Name.
+    name: &'a str,
+    /// Material.
+    material: &'a Material,
+    /// Diffuse texture name.
+    diffuse_texture: Option<&'a str>,
+}
+
+impl<'a> MaterialSummary<'a> {
+    /// Creates a new `MaterialSummary`.
+    fn new(scene: &'a Scene, material: &'a Material) -> Self {
+        Self {
+            name: material.name.as_deref().unwrap_or_default(),
+            material,
+            diffuse_texture: material.diffuse_texture.map(|i| {
+                scene
+                    .texture(i)
+                    .ok()
+                    .and_then(|texture| texture.name.as_deref())
+                    .unwrap_or_default()
+            }),
+        }
+    }
+}
+
+impl Summary for MaterialSummary<'_> {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn values(&self) -> Vec<(&'static str, String)> {
+        let data = &self.material.data;
+        let lambert = data.lambert();
+        let mut values = vec![
+            (
+                "shading model",
+                match data {
+                    ShadingData::Lambert(_) => "Lambert",
+                    ShadingData::Phong(_) => "Phong",
+                }
+                .to_owned(),
+            ),
+            ("ambient", format_rgb(lambert.ambient)),
+            ("diffuse", format_rgb(lambert.diffuse)),
+            ("emissive", format_rgb(lambert.emissive)),
+            ("diffuse texture", format!("{:?}", self.diffuse_texture)),
+        ];
+        if let ShadingData::Phong(phong) = data {
+            values.push(("specular", format_rgb(phong.specular)));
+            values.push(("shininess", format!("{:.3}", phong.shininess)));
+            values.push(("reflection", format_rgb(phong.reflection)));
+        }
+        values
+    }
+}
+
+/// Texture summary.
+struct TextureSummary<'a> {
+    /// Name.
+    name: &'a str,
+    /// Texture.
+    texture: &'a Texture,
+}
+
+impl<'a> TextureSummary<'a> {
+    /// Creates a new `TextureSummary`.
+    fn new(texture: &'a Texture) -> Self {
+        Self {
+            name: texture.name.as_deref().unwrap_or_default(),
+            texture,
+        }
+    }
+}
+
+impl Summary for TextureSummary<'_> {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn values(&self) -> Vec<(&'static str, String)> {
+        let texture = self.texture;
+        let (size, color) = match texture.image.get() {
+            Ok(image) => (
+                format!("{}x{}", image.width(), image.height()),
+                format!("{:?}", image.color()),
+            ),
+            Err(_) => ("(undecodable)".to_owned(), "(undecodable)".to_owned()),
+        };
+        vec![
+            ("size", size),
+            ("color", color),
+            ("transparent", texture.transparent.to_string()),
+            ("wrap mode U", format!("{:?}", texture.wrap_mode_u)),
+            ("wrap mode V", format!("{:?}", texture.wrap_mode_v)),
+        ]
+    }
+}
+
+/// Formats the color.
+fn format_rgb(color: RGB<f32>) -> String {
+    format!("[{:.3}, {:.3}, {:.3}]", color.r, color.g, color.b)
+}
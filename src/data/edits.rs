@@ -0,0 +1,147 @@
+//! Runtime edits of scenes.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use cgmath::Matrix4;
+
+use crate::data::{IndexError, MeshIndex, Scene, TextureImage, TextureIndex};
+
+/// Edits made over a scene, such as the ones made in the viewer.
+///
+/// The edits are kept apart from the scene, so that they can be reset, and
+/// are baked into a copy of the scene by [`Scene::with_edits`].
+#[derive(Debug, Default, Clone)]
+pub struct SceneEdits {
+    /// Transforms applied to the meshes over their geometry meshes.
+    pub mesh_transforms: BTreeMap<MeshIndex, Matrix4<f32>>,
+    /// Hidden meshes.
+    pub hidden_meshes: BTreeSet<MeshIndex>,
+    /// Images replacing the ones of the textures.
+    pub texture_images: BTreeMap<TextureIndex, TextureImage>,
+}
+
+impl SceneEdits {
+    /// Returns whether no edits are made.
+    pub fn is_empty(&self) -> bool {
+        self.mesh_transforms.is_empty()
+            && self.hidden_meshes.is_empty()
+            && self.texture_images.is_empty()
+    }
+}
+
+impl Scene {
+    /// Returns a copy of the scene with the edits baked.
+    ///
+    /// Each transformed mesh gets its own copy of the geometry mesh with the
+    /// transform applied, since geometry meshes can be shared by meshes.
+    /// Hidden meshes are removed, so the indices of the meshes of the
+    /// returned scene can differ from the original ones. The returned scene
+    /// has its own ID, and does not accept the indices of the original scene.
+    ///
+    /// Returns an error if any of the edits refers to a missing object.
+    pub fn with_edits(&self, edits: &SceneEdits) -> Result<Scene, IndexError> {
+        let mut scene = self.clone();
+        let id = scene.id();
+        for (&texture, image) in &edits.texture_images {
+            self.texture(texture)?;
+            scene.texture_mut(texture.rebind(id))?.image = image.clone();
+        }
+        for (&mesh, transform) in &edits.mesh_transforms {
+            let mut geometry = self
+                .geometry_mesh(self.mesh(mesh)?.geometry_mesh_index)?
+                .clone();
+            geometry.transform(transform);
+            let geometry = scene.add_geometry_mesh(geometry);
+            scene.mesh_mut(mesh.rebind(id))?.geometry_mesh_index = geometry;
+        }
+        for &mesh in &edits.hidden_meshes {
+            self.mesh(mesh)?;
+        }
+        let original = self.id();
+        scene.retain_meshes(|mesh, _| !edits.hidden_meshes.contains(&mesh.rebind(original)));
+
+        Ok(scene)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::{Point3, SquareMatrix, Vector3};
+
+    use crate::data::{GeometryMesh, Mesh};
+
+    #[test]
+    fn bake_edits() -> anyhow::Result<()> {
+        let mut scene = Scene::new();
+        let geometry = scene.add_geometry_mesh(GeometryMesh {
+            name: None,
+            positions: vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            normals: vec![Vector3::new(0.0, 0.0, 1.0); 3],
+            uv: Vec::new(),
+            colors: Vec::new(),
+            indices_per_material: vec![vec![0, 1, 2]],
+            polygons_per_material: vec![vec![0]],
+        });
+        // The geometry mesh is shared by the meshes.
+        let meshes = ["moved", "hidden", "kept"].map(|name| {
+            scene.add_mesh(Mesh {
+                name: Some(name.to_owned()),
+                geometry_mesh_index: geometry,
+                materials: Vec::new(),
+                transform: Matrix4::identity(),
+                node: None,
+                render_order: 0,
+            })
+        });
+
+        let mut edits = SceneEdits::default();
+        assert!(edits.is_empty());
+        edits.mesh_transforms.insert(
+            meshes[0],
+            Matrix4::from_translation(Vector3::new(0.0, 0.0, 2.0)),
+        );
+        edits.hidden_meshes.insert(meshes[1]);
+        let edited = scene.with_edits(&edits)?;
+
+        let names = edited
+            .meshes()
+            .map(|mesh| mesh.name.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(names, [Some("moved"), Some("kept")]);
+        let positions = |mesh: &Mesh| -> anyhow::Result<_> {
+            Ok(edited
+                .geometry_mesh(mesh.geometry_mesh_index)?
+                .positions
+                .clone())
+        };
+        let mut meshes_iter = edited.meshes();
+        let moved = meshes_iter.next().expect("Should exist");
+        let kept = meshes_iter.next().expect("Should exist");
+        assert_eq!(positions(moved)?[1], Point3::new(1.0, 0.0, 2.0));
+        assert_eq!(positions(kept)?[1], Point3::new(1.0, 0.0, 0.0));
+        // The original scene is not modified.
+        assert_eq!(scene.meshes().count(), 3);
+        assert_eq!(scene.geometry_meshes().count(), 1);
+        // The indices do not mix between the scenes, since they point to
+        // different meshes.
+        let (kept_index, _) = edited.meshes_with_index().nth(1).expect("Should exist");
+        assert!(matches!(
+            scene.mesh(kept_index),
+            Err(IndexError::SceneMismatch { .. })
+        ));
+        assert!(matches!(
+            edited.mesh(meshes[2]),
+            Err(IndexError::SceneMismatch { .. })
+        ));
+
+        let other = Scene::new();
+        assert!(other.with_edits(&edits).is_err());
+        Ok(())
+    }
+}
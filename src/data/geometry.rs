@@ -1,8 +1,16 @@
 //! Geometry.
 
-use cgmath::{Point2, Point3, Vector3};
+use std::collections::HashMap;
 
-use crate::util::bbox::OptionalBoundingBox3d;
+use cgmath::{
+    Angle, EuclideanSpace, InnerSpace, Matrix, Matrix3, Matrix4, Point2, Point3, Rad, SquareMatrix,
+    Vector3, Vector4, Zero,
+};
+use rgb::RGBA;
+
+use crate::util::bbox::{
+    BoundingBox3d, BoundingSphere, OptionalBoundingBox3d, OrientedBoundingBox3d,
+};
 
 /// Geometry mesh.
 #[derive(Debug, Clone)]
@@ -15,11 +23,169 @@ pub struct GeometryMesh {
     pub normals: Vec<Vector3<f32>>,
     /// UV.
     pub uv: Vec<Point2<f32>>,
+    /// Vertex colors.
+    ///
+    /// Empty if the mesh has no vertex colors, otherwise as many as the
+    /// positions.
+    pub colors: Vec<RGBA<f32>>,
     /// Indices per materials.
     pub indices_per_material: Vec<Vec<u32>>,
+    /// Source polygon indices of the triangles per materials.
+    ///
+    /// Empty if the source polygons are not tracked, otherwise each has an
+    /// element per triangle of the corresponding indices in
+    /// `indices_per_material`.
+    pub polygons_per_material: Vec<Vec<u32>>,
 }
 
 impl GeometryMesh {
+    /// Creates a box mesh of the bounding box.
+    ///
+    /// Each face has its own four vertices with the outward normal and zero
+    /// UV, and all the triangles use the first material.
+    pub fn from_bounding_box(
+        name: Option<String>,
+        bbox: &BoundingBox3d<f32>,
+        num_materials: usize,
+    ) -> Self {
+        let (min, max) = (bbox.min(), bbox.max());
+        let mut positions = Vec::with_capacity(24);
+        let mut normals = Vec::with_capacity(24);
+        let mut indices = Vec::with_capacity(36);
+        for axis in 0..3 {
+            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+            for &positive in &[false, true] {
+                // Counter-clockwise seen from the outside.
+                let mut corners = [(false, false), (true, false), (true, true), (false, true)];
+                if !positive {
+                    corners.reverse();
+                }
+                let mut normal = Vector3::zero();
+                normal[axis] = if positive { 1.0 } else { -1.0 };
+                let base = positions.len() as u32;
+                for &(max_u, max_v) in &corners {
+                    let mut p = min;
+                    if positive {
+                        p[axis] = max[axis];
+                    }
+                    if max_u {
+                        p[u] = max[u];
+                    }
+                    if max_v {
+                        p[v] = max[v];
+                    }
+                    positions.push(p);
+                    normals.push(normal);
+                }
+                indices.extend([0, 1, 2, 0, 2, 3].iter().map(|i| base + i));
+            }
+        }
+        let mut indices_per_material = vec![Vec::new(); num_materials];
+        if let Some(first) = indices_per_material.first_mut() {
+            *first = indices;
+        }
+
+        Self {
+            name,
+            uv: vec![Point2::new(0.0, 0.0); positions.len()],
+            positions,
+            normals,
+            colors: Vec::new(),
+            indices_per_material,
+            polygons_per_material: Vec::new(),
+        }
+    }
+
+    /// Removes degenerate triangles from the indices, and returns the numbers
+    /// of the removed triangles.
+    ///
+    /// Triangles which use the same vertex twice, or whose vertices are
+    /// collinear (within the floating point precision) or not finite, are
+    /// removed, since they are invisible and make NaN normals.
+    /// The vertices are not modified.
+    pub fn remove_degenerate_triangles(&mut self) -> DegenerateTriangles {
+        let mut removed = DegenerateTriangles::default();
+        let positions = &self.positions;
+        for (submesh_i, indices) in self.indices_per_material.iter_mut().enumerate() {
+            let mut polygons = self.polygons_per_material.get_mut(submesh_i);
+            let mut kept = 0;
+            for tri_i in 0..indices.len() / 3 {
+                let tri = [
+                    indices[tri_i * 3],
+                    indices[tri_i * 3 + 1],
+                    indices[tri_i * 3 + 2],
+                ];
+                if tri[0] == tri[1] || tri[1] == tri[2] || tri[2] == tri[0] {
+                    removed.duplicate_indices += 1;
+                    continue;
+                }
+                let [p0, p1, p2] = tri.map(|i| positions[i as usize]);
+                let longest_sq = (p1 - p0)
+                    .magnitude2()
+                    .max((p2 - p1).magnitude2())
+                    .max((p0 - p2).magnitude2());
+                // False for NaN.
+                let has_area = (p1 - p0).cross(p2 - p0).magnitude() > f32::EPSILON * longest_sq;
+                if !has_area {
+                    removed.zero_area += 1;
+                    continue;
+                }
+                indices.copy_within(tri_i * 3..tri_i * 3 + 3, kept * 3);
+                if let Some(polygons) = polygons.as_mut() {
+                    polygons[kept] = polygons[tri_i];
+                }
+                kept += 1;
+            }
+            indices.truncate(kept * 3);
+            if let Some(polygons) = polygons {
+                polygons.truncate(kept);
+            }
+        }
+
+        removed
+    }
+
+    /// Returns the source polygon index of the triangle in the submesh, if
+    /// tracked.
+    pub fn source_polygon(&self, submesh_i: usize, triangle_i: usize) -> Option<u32> {
+        self.polygons_per_material
+            .get(submesh_i)?
+            .get(triangle_i)
+            .copied()
+    }
+
+    /// Applies the transform to the positions and the normals.
+    ///
+    /// Mirroring transforms also flip the winding order of the triangles.
+    pub fn transform(&mut self, transform: &Matrix4<f32>) {
+        let normal_matrix = {
+            let m = Matrix3::from_cols(
+                transform.x.truncate(),
+                transform.y.truncate(),
+                transform.z.truncate(),
+            );
+            m.invert().unwrap_or(m).transpose()
+        };
+        for p in &mut self.positions {
+            *p = Point3::from_homogeneous(transform * p.to_homogeneous());
+        }
+        for n in &mut self.normals {
+            let transformed = normal_matrix * *n;
+            if !transformed.is_zero() {
+                *n = transformed.normalize();
+            }
+        }
+        if transform.determinant() < 0.0 {
+            for tri in self
+                .indices_per_material
+                .iter_mut()
+                .flat_map(|indices| indices.chunks_exact_mut(3))
+            {
+                tri.swap(1, 2);
+            }
+        }
+    }
+
     /// Returns bounding box of the submesh at the given index.
     pub fn bbox_submesh(&self, submesh_i: usize) -> OptionalBoundingBox3d<f32> {
         self.indices_per_material.get(submesh_i).map_or_else(
@@ -28,7 +194,6 @@ impl GeometryMesh {
                 submesh
                     .iter()
                     .map(|&pos_i| self.positions[pos_i as usize])
-                    .map(Point3::from)
                     .collect()
             },
         )
@@ -36,6 +201,420 @@ impl GeometryMesh {
 
     /// Returns bounding box of the whole mesh.
     pub fn bbox_mesh(&self) -> OptionalBoundingBox3d<f32> {
-        self.positions.iter().cloned().map(Point3::from).collect()
+        self.positions.iter().cloned().collect()
+    }
+
+    /// Returns bounding sphere of the whole mesh.
+    pub fn bounding_sphere_mesh(&self) -> Option<BoundingSphere<f32>> {
+        BoundingSphere::from_points(&self.positions)
+    }
+
+    /// Returns oriented bounding box of the whole mesh.
+    pub fn obb_mesh(&self) -> Option<OrientedBoundingBox3d<f32>> {
+        OrientedBoundingBox3d::from_points(&self.positions)
+    }
+
+    /// Returns an iterator of the vertex positions of all triangles.
+    pub fn triangles(&self) -> impl Iterator<Item = [Point3<f32>; 3]> + '_ {
+        self.indices_per_material
+            .iter()
+            .flat_map(|indices| indices.chunks_exact(3))
+            .map(move |tri| {
+                [
+                    self.positions[tri[0] as usize],
+                    self.positions[tri[1] as usize],
+                    self.positions[tri[2] as usize],
+                ]
+            })
+    }
+
+    /// Returns the number of triangles.
+    pub fn num_triangles(&self) -> usize {
+        self.indices_per_material
+            .iter()
+            .map(|indices| indices.len() / 3)
+            .sum()
+    }
+
+    /// Returns the indices per materials simplified by vertex clustering.
+    ///
+    /// The bounding box is divided into cubic cells, `cells` of them along its
+    /// longest axis, and the vertices in each cell are merged into the first
+    /// one of them.
+    /// Triangles with two vertices in the same cell are removed, as well as
+    /// the duplicates of the remaining triangles in each submesh.
+    /// The vertices are not modified, so the indices can be used with them.
+    pub fn clustered_indices(&self, cells: u32) -> Vec<Vec<u32>> {
+        let bbox = match self.bbox_mesh().bounding_box() {
+            Some(v) => v,
+            None => return self.indices_per_material.clone(),
+        };
+        let size = bbox.size();
+        let longest = size.x.max(size.y).max(size.z);
+        if cells == 0 || longest <= 0.0 {
+            return self.indices_per_material.clone();
+        }
+        let cell_size = longest / cells as f32;
+        let min = bbox.min();
+        let mut representatives = HashMap::new();
+        let mut merge = |i: u32| {
+            let v = (self.positions[i as usize] - min) / cell_size;
+            let cell = [v.x as u32, v.y as u32, v.z as u32];
+            (cell, *representatives.entry(cell).or_insert(i))
+        };
+
+        self.indices_per_material
+            .iter()
+            .map(|indices| {
+                let mut triangles = std::collections::HashSet::new();
+                let mut clustered = Vec::new();
+                for tri in indices.chunks_exact(3) {
+                    let [(c0, i0), (c1, i1), (c2, i2)] =
+                        [merge(tri[0]), merge(tri[1]), merge(tri[2])];
+                    if c0 == c1 || c1 == c2 || c2 == c0 {
+                        continue;
+                    }
+                    // Rotate the cells to start from the smallest one, keeping
+                    // the winding, so that the duplicates have the same key.
+                    let mut key = [c0, c1, c2];
+                    let first = (0..3).min_by_key(|&k| key[k]).unwrap_or(0);
+                    key.rotate_left(first);
+                    if triangles.insert(key) {
+                        clustered.extend_from_slice(&[i0, i1, i2]);
+                    }
+                }
+                clustered
+            })
+            .collect()
+    }
+
+    /// Returns the number of distinct vertex positions.
+    pub fn num_distinct_positions(&self) -> usize {
+        self.positions
+            .iter()
+            .map(|p| position_key(*p))
+            .collect::<std::collections::HashSet<_>>()
+            .len()
+    }
+
+    /// Returns the surface area.
+    pub fn surface_area(&self) -> f32 {
+        self.triangles()
+            .map(|[p0, p1, p2]| f64::from((p1 - p0).cross(p2 - p0).magnitude()) / 2.0)
+            .sum::<f64>() as f32
+    }
+
+    /// Returns the volume enclosed by the mesh.
+    ///
+    /// This is the sum of the signed volumes of the tetrahedra made from the
+    /// origin and each triangle, so the result is meaningful only if the mesh
+    /// is watertight and consistently oriented.
+    pub fn volume(&self) -> f32 {
+        let signed: f64 = self
+            .triangles()
+            .map(|[p0, p1, p2]| f64::from(p0.to_vec().dot(p1.to_vec().cross(p2.to_vec()))) / 6.0)
+            .sum();
+        signed.abs() as f32
+    }
+
+    /// Returns whether the mesh is watertight.
+    ///
+    /// Vertices at the same position are considered identical, and the mesh
+    /// is watertight if every edge is shared by exactly two triangles.
+    pub fn is_watertight(&self) -> bool {
+        self.num_triangles() != 0 && self.topology().is_watertight()
+    }
+
+    /// Finds the open edges, the non-manifold edges, and the duplicate faces.
+    ///
+    /// Vertices at the same position are considered identical.
+    /// The problems are listed in the order they are found in the submeshes.
+    pub fn topology(&self) -> MeshTopology {
+        // Edges by the positions, with the vertices of the first occurrence
+        // and the number of the triangles sharing them.
+        let mut edges: Vec<([u32; 2], usize)> = Vec::new();
+        let mut edge_indices = HashMap::new();
+        let mut faces = std::collections::HashSet::new();
+        let mut duplicate_faces = Vec::new();
+        for tri in self
+            .indices_per_material
+            .iter()
+            .flat_map(|indices| indices.chunks_exact(3))
+        {
+            let keys = [tri[0], tri[1], tri[2]].map(|i| position_key(self.positions[i as usize]));
+            for &(a, b) in &[(0, 1), (1, 2), (2, 0)] {
+                let key = if keys[a] < keys[b] {
+                    (keys[a], keys[b])
+                } else {
+                    (keys[b], keys[a])
+                };
+                let edge_i = *edge_indices.entry(key).or_insert_with(|| {
+                    edges.push(([tri[a], tri[b]], 0));
+                    edges.len() - 1
+                });
+                edges[edge_i].1 += 1;
+            }
+            // Faces are the same regardless of the winding.
+            let mut face = keys;
+            face.sort_unstable();
+            if !faces.insert(face) {
+                duplicate_faces.push([tri[0], tri[1], tri[2]]);
+            }
+        }
+
+        MeshTopology {
+            open_edges: edges
+                .iter()
+                .filter(|(_, count)| *count == 1)
+                .map(|&(edge, _)| edge)
+                .collect(),
+            non_manifold_edges: edges
+                .iter()
+                .filter(|(_, count)| *count > 2)
+                .map(|&(edge, _)| edge)
+                .collect(),
+            duplicate_faces,
+        }
+    }
+
+    /// Generates the tangents of the vertices from the UV.
+    ///
+    /// The tangent at a vertex is the direction of increasing U averaged over
+    /// the adjacent triangles, made orthogonal to the normal.
+    /// The W component is the handedness (1 or -1): the bitangent, the
+    /// direction of increasing V, is `normal.cross(tangent) * w`.
+    /// Vertices without UV gradients get an arbitrary tangent orthogonal to
+    /// the normal.
+    pub fn tangents(&self) -> Vec<Vector4<f32>> {
+        let mut tangents = vec![Vector3::zero(); self.positions.len()];
+        let mut bitangents = vec![Vector3::zero(); self.positions.len()];
+        for tri in self
+            .indices_per_material
+            .iter()
+            .flat_map(|indices| indices.chunks_exact(3))
+        {
+            let [i0, i1, i2] = [tri[0] as usize, tri[1] as usize, tri[2] as usize];
+            let (e1, e2) = (
+                self.positions[i1] - self.positions[i0],
+                self.positions[i2] - self.positions[i0],
+            );
+            let uv = |i: usize| {
+                self.uv
+                    .get(i)
+                    .copied()
+                    .unwrap_or_else(|| Point2::new(0.0, 0.0))
+            };
+            let (d1, d2) = (uv(i1) - uv(i0), uv(i2) - uv(i0));
+            let r = (d1.x * d2.y - d2.x * d1.y).recip();
+            if !r.is_finite() {
+                continue;
+            }
+            let tangent = (e1 * d2.y - e2 * d1.y) * r;
+            let bitangent = (e2 * d1.x - e1 * d2.x) * r;
+            for &i in &[i0, i1, i2] {
+                tangents[i] += tangent;
+                bitangents[i] += bitangent;
+            }
+        }
+
+        tangents
+            .into_iter()
+            .zip(bitangents)
+            .enumerate()
+            .map(|(i, (tangent, bitangent))| {
+                let normal = self.normals.get(i).copied().unwrap_or_else(Vector3::zero);
+                let mut orthogonal = tangent - normal * normal.dot(tangent);
+                if !orthogonal.magnitude2().is_normal() {
+                    let axis = if normal.x.abs() < 0.9 {
+                        Vector3::unit_x()
+                    } else {
+                        Vector3::unit_y()
+                    };
+                    orthogonal = axis - normal * normal.dot(axis);
+                }
+                let orthogonal = orthogonal.normalize();
+                let w = if normal.cross(orthogonal).dot(bitangent) < 0.0 {
+                    -1.0
+                } else {
+                    1.0
+                };
+                orthogonal.extend(w)
+            })
+            .collect()
+    }
+
+    /// Recomputes normals from the faces.
+    ///
+    /// The normal at a vertex is the area-weighted average of the normals of
+    /// the adjacent faces within the crease angle from the face the vertex
+    /// belongs to, so edges sharper than the crease angle are kept hard.
+    pub fn recompute_normals(&mut self, crease_angle: Rad<f32>) {
+        let cos_crease = crease_angle.cos();
+        let triangles = self
+            .indices_per_material
+            .iter()
+            .flat_map(|indices| indices.chunks_exact(3))
+            .map(|tri| [tri[0] as usize, tri[1] as usize, tri[2] as usize])
+            .collect::<Vec<_>>();
+        // Not normalized, to weight normals by the face areas.
+        let face_normals = triangles
+            .iter()
+            .map(|&[i0, i1, i2]| {
+                let [p0, p1, p2] = [self.positions[i0], self.positions[i1], self.positions[i2]];
+                (p1 - p0).cross(p2 - p0)
+            })
+            .collect::<Vec<_>>();
+        let mut adjacent_faces: HashMap<_, Vec<usize>> = HashMap::new();
+        for (tri_i, tri) in triangles.iter().enumerate() {
+            for &vi in tri {
+                adjacent_faces
+                    .entry(position_key(self.positions[vi]))
+                    .or_default()
+                    .push(tri_i);
+            }
+        }
+
+        let unit = |v: Vector3<f32>| {
+            if v.is_zero() {
+                v
+            } else {
+                v.normalize()
+            }
+        };
+        let mut normals = vec![Vector3::zero(); self.positions.len()];
+        for (tri_i, tri) in triangles.iter().enumerate() {
+            let face_normal = unit(face_normals[tri_i]);
+            for &vi in tri {
+                let sum = adjacent_faces[&position_key(self.positions[vi])]
+                    .iter()
+                    .map(|&other_i| face_normals[other_i])
+                    .filter(|&other| unit(other).dot(face_normal) >= cos_crease)
+                    .fold(Vector3::zero(), |sum, other| sum + other);
+                normals[vi] = if sum.is_zero() {
+                    face_normal
+                } else {
+                    sum.normalize()
+                };
+            }
+        }
+
+        self.normals = normals;
+    }
+}
+
+/// Topology problems of a mesh.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct MeshTopology {
+    /// Edges of only one triangle, as the indices of their vertices.
+    pub open_edges: Vec<[u32; 2]>,
+    /// Edges shared by more than two triangles, as the indices of their
+    /// vertices.
+    pub non_manifold_edges: Vec<[u32; 2]>,
+    /// Triangles at the same positions as another triangle, as the indices of
+    /// their vertices.
+    ///
+    /// The first one of such triangles is not included.
+    pub duplicate_faces: Vec<[u32; 3]>,
+}
+
+impl MeshTopology {
+    /// Returns whether every edge is shared by exactly two triangles.
+    ///
+    /// Note that this is also true for empty meshes.
+    pub fn is_watertight(&self) -> bool {
+        self.open_edges.is_empty() && self.non_manifold_edges.is_empty()
+    }
+}
+
+/// Numbers of degenerate triangles.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DegenerateTriangles {
+    /// Triangles using the same vertex more than once.
+    pub duplicate_indices: usize,
+    /// Triangles with zero area.
+    pub zero_area: usize,
+}
+
+impl DegenerateTriangles {
+    /// Returns the total number of degenerate triangles.
+    pub fn total(&self) -> usize {
+        self.duplicate_indices + self.zero_area
+    }
+}
+
+/// Returns a hashable key for the position.
+fn position_key(p: Point3<f32>) -> [u32; 3] {
+    // Normalize negative zeros so that `0.0` and `-0.0` are the same position.
+    [p.x + 0.0, p.y + 0.0, p.z + 0.0].map(f32::to_bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a unit square of two triangles with the indices.
+    fn square(indices: Vec<u32>) -> GeometryMesh {
+        let positions = vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        ];
+        GeometryMesh {
+            name: None,
+            normals: vec![Vector3::unit_z(); positions.len()],
+            uv: vec![Point2::new(0.0, 0.0); positions.len()],
+            positions,
+            colors: Vec::new(),
+            indices_per_material: vec![indices],
+            polygons_per_material: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn tangents() {
+        let mut mesh = square(vec![0, 1, 2, 0, 2, 3]);
+        mesh.uv = mesh
+            .positions
+            .iter()
+            .map(|p| Point2::new(p.x, p.y))
+            .collect();
+        assert!(mesh
+            .tangents()
+            .iter()
+            .all(|&tangent| tangent == Vector4::new(1.0, 0.0, 0.0, 1.0)));
+
+        // Mirrored V flips the handedness.
+        mesh.uv = mesh
+            .positions
+            .iter()
+            .map(|p| Point2::new(p.x, 1.0 - p.y))
+            .collect();
+        assert!(mesh
+            .tangents()
+            .iter()
+            .all(|&tangent| tangent == Vector4::new(1.0, 0.0, 0.0, -1.0)));
+    }
+
+    #[test]
+    fn topology() {
+        let open = square(vec![0, 1, 2, 0, 2, 3]).topology();
+        assert_eq!(open.open_edges, [[0, 1], [1, 2], [2, 3], [3, 0]]);
+        assert!(open.non_manifold_edges.is_empty());
+        assert!(open.duplicate_faces.is_empty());
+
+        // The first triangle is repeated with the opposite winding.
+        let duplicate = square(vec![0, 1, 2, 0, 2, 3, 2, 1, 0]).topology();
+        assert_eq!(duplicate.open_edges, [[2, 3], [3, 0]]);
+        assert_eq!(duplicate.non_manifold_edges, [[2, 0]]);
+        assert_eq!(duplicate.duplicate_faces, [[2, 1, 0]]);
+        assert!(!duplicate.is_watertight());
+
+        // A tetrahedron on three of the corners.
+        let mut closed = square(vec![0, 2, 1, 0, 1, 4, 1, 2, 4, 2, 0, 4]);
+        closed.positions.push(Point3::new(0.0, 0.0, 1.0));
+        assert_eq!(closed.topology(), MeshTopology::default());
+        assert!(closed.is_watertight());
+        assert!(!square(Vec::new()).is_watertight());
     }
 }
@@ -0,0 +1,220 @@
+//! Inspection of triangles.
+
+use std::fmt;
+
+use anyhow::{anyhow, Context};
+use cgmath::{Point2, Point3, Vector3};
+use rgb::RGBA;
+
+use crate::data::{MaterialIndex, MeshIndex, Scene, TextureIndex};
+
+/// Attributes of a triangle and what it maps to, for debugging the loaders.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriangleInspection {
+    /// Mesh.
+    pub mesh: MeshIndex,
+    /// Mesh name.
+    pub mesh_name: Option<String>,
+    /// Submesh (material slot) index.
+    pub submesh: usize,
+    /// Triangle index in the submesh.
+    pub triangle: usize,
+    /// Source polygon index, if tracked.
+    pub polygon: Option<u32>,
+    /// Vertices.
+    pub vertices: [VertexInspection; 3],
+    /// Material of the submesh, if the mesh has it.
+    pub material: Option<MaterialIndex>,
+    /// Material name.
+    pub material_name: Option<String>,
+    /// Diffuse texture of the material.
+    pub texture: Option<TextureIndex>,
+    /// Texture name.
+    pub texture_name: Option<String>,
+}
+
+/// Attributes of a vertex of a triangle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VertexInspection {
+    /// Index in the geometry mesh.
+    pub index: u32,
+    /// Position in the geometry mesh space.
+    pub position: Point3<f32>,
+    /// Normal, if available.
+    pub normal: Option<Vector3<f32>>,
+    /// UV, if available.
+    pub uv: Option<Point2<f32>>,
+    /// Vertex color, if available.
+    pub color: Option<RGBA<f32>>,
+}
+
+impl TriangleInspection {
+    /// Inspects the triangle of the mesh.
+    ///
+    /// Returns an error if the triangle does not exist, or it refers to
+    /// vertices out of range.
+    pub fn new(
+        scene: &Scene,
+        mesh_i: MeshIndex,
+        submesh: usize,
+        triangle: usize,
+    ) -> anyhow::Result<Self> {
+        let mesh = scene.mesh(mesh_i).context("Failed to get mesh")?;
+        let geometry = scene
+            .geometry_mesh(mesh.geometry_mesh_index)
+            .context("Failed to get geometry mesh")?;
+        let indices = geometry
+            .indices_per_material
+            .get(submesh)
+            .and_then(|indices| indices.get(triangle * 3..triangle * 3 + 3))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Triangle {} of submesh {} does not exist",
+                    triangle,
+                    submesh
+                )
+            })?;
+        let vertex = |index: u32| -> anyhow::Result<VertexInspection> {
+            let i = index as usize;
+            let position = *geometry
+                .positions
+                .get(i)
+                .ok_or_else(|| anyhow!("Vertex index {} is out of range", index))?;
+            Ok(VertexInspection {
+                index,
+                position,
+                normal: geometry.normals.get(i).copied(),
+                uv: geometry.uv.get(i).copied(),
+                color: geometry.colors.get(i).copied(),
+            })
+        };
+        let vertices = [
+            vertex(indices[0])?,
+            vertex(indices[1])?,
+            vertex(indices[2])?,
+        ];
+
+        let material = mesh.materials.get(submesh).copied();
+        let material_data = material.and_then(|i| scene.material(i).ok());
+        let texture = material_data.and_then(|material| material.diffuse_texture);
+        let texture_name = texture
+            .and_then(|i| scene.texture(i).ok())
+            .and_then(|texture| texture.name.clone());
+
+        Ok(Self {
+            mesh: mesh_i,
+            mesh_name: mesh.name.clone(),
+            submesh,
+            triangle,
+            polygon: geometry.source_polygon(submesh, triangle),
+            vertices,
+            material,
+            material_name: material_data.and_then(|material| material.name.clone()),
+            texture,
+            texture_name,
+        })
+    }
+}
+
+impl fmt::Display for TriangleInspection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "mesh = {:?} (name = {:?}), submesh = {}, triangle = {}, source polygon = {:?}",
+            self.mesh, self.mesh_name, self.submesh, self.triangle, self.polygon
+        )?;
+        write!(f, "material = ")?;
+        match self.material {
+            Some(material) => write!(f, "{:?} (name = {:?})", material, self.material_name)?,
+            None => write!(f, "none")?,
+        }
+        write!(f, ", texture = ")?;
+        match self.texture {
+            Some(texture) => writeln!(f, "{:?} (name = {:?})", texture, self.texture_name)?,
+            None => writeln!(f, "none")?,
+        }
+        for (i, vertex) in self.vertices.iter().enumerate() {
+            write!(
+                f,
+                "vertex {}: index = {}, position = {:?}, normal = {:?}, uv = {:?}",
+                i,
+                vertex.index,
+                Into::<[f32; 3]>::into(vertex.position),
+                vertex.normal.map(Into::<[f32; 3]>::into),
+                vertex.uv.map(Into::<[f32; 2]>::into),
+            )?;
+            if let Some(color) = vertex.color {
+                write!(
+                    f,
+                    ", color = [{}, {}, {}, {}]",
+                    color.r, color.g, color.b, color.a
+                )?;
+            }
+            if i < 2 {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::{Matrix4, SquareMatrix};
+
+    use crate::data::{GeometryMesh, Material, Mesh};
+
+    #[test]
+    fn inspect_triangle() -> anyhow::Result<()> {
+        let mut scene = Scene::new();
+        let geometry = scene.add_geometry_mesh(GeometryMesh {
+            name: None,
+            positions: vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+                Point3::new(1.0, 1.0, 0.0),
+            ],
+            normals: vec![Vector3::new(0.0, 0.0, 1.0); 4],
+            uv: vec![
+                Point2::new(0.0, 0.0),
+                Point2::new(1.0, 0.0),
+                Point2::new(0.0, 1.0),
+                Point2::new(1.0, 1.0),
+            ],
+            colors: Vec::new(),
+            indices_per_material: vec![vec![0, 1, 2], vec![1, 3, 2]],
+            polygons_per_material: vec![vec![0], vec![0]],
+        });
+        let material = scene.add_material(Material {
+            name: Some("second".to_owned()),
+            ..Default::default()
+        });
+        let mesh = scene.add_mesh(Mesh {
+            name: Some("quad".to_owned()),
+            geometry_mesh_index: geometry,
+            materials: vec![material, material],
+            transform: Matrix4::identity(),
+            node: None,
+            render_order: 0,
+        });
+
+        let inspection = TriangleInspection::new(&scene, mesh, 1, 0)?;
+        assert_eq!(inspection.mesh_name.as_deref(), Some("quad"));
+        assert_eq!(inspection.material, Some(material));
+        assert_eq!(inspection.material_name.as_deref(), Some("second"));
+        assert_eq!(inspection.texture, None);
+        assert_eq!(inspection.polygon, Some(0));
+        let indices = inspection.vertices.map(|vertex| vertex.index);
+        assert_eq!(indices, [1, 3, 2]);
+        assert_eq!(inspection.vertices[1].position, Point3::new(1.0, 1.0, 0.0));
+        assert_eq!(inspection.vertices[1].uv, Some(Point2::new(1.0, 1.0)));
+        assert_eq!(inspection.vertices[1].color, None);
+
+        assert!(TriangleInspection::new(&scene, mesh, 1, 1).is_err());
+        assert!(TriangleInspection::new(&scene, mesh, 2, 0).is_err());
+        Ok(())
+    }
+}
@@ -13,6 +13,32 @@ pub struct Material {
     pub diffuse_texture: Option<TextureIndex>,
     /// Shading parameters.
     pub data: ShadingData,
+    /// Whether the back faces are visible.
+    pub double_sided: bool,
+    /// Alpha cutoff.
+    ///
+    /// If `Some`, fragments with alpha below the cutoff are discarded and the
+    /// others are drawn opaque, as foliage and fences, instead of being alpha
+    /// blended.
+    pub alpha_cutoff: Option<f32>,
+}
+
+impl Default for Material {
+    /// Returns an unnamed gray double-sided Lambert material without
+    /// textures.
+    fn default() -> Self {
+        Self {
+            name: None,
+            diffuse_texture: None,
+            data: ShadingData::Lambert(LambertData {
+                ambient: RGB::new(0.0, 0.0, 0.0),
+                diffuse: RGB::new(0.8, 0.8, 0.8),
+                emissive: RGB::new(0.0, 0.0, 0.0),
+            }),
+            double_sided: true,
+            alpha_cutoff: None,
+        }
+    }
 }
 
 /// Shading data.
@@ -20,6 +46,26 @@ pub struct Material {
 pub enum ShadingData {
     /// Lambert material.
     Lambert(LambertData),
+    /// Phong material.
+    Phong(PhongData),
+}
+
+impl ShadingData {
+    /// Returns the Lambert part of the shading data.
+    pub fn lambert(&self) -> &LambertData {
+        match self {
+            ShadingData::Lambert(lambert) => lambert,
+            ShadingData::Phong(phong) => &phong.lambert,
+        }
+    }
+
+    /// Returns approximate parameters for physically based rendering.
+    pub fn to_pbr(&self) -> PbrData {
+        match self {
+            ShadingData::Lambert(lambert) => lambert.to_pbr(),
+            ShadingData::Phong(phong) => phong.to_pbr(),
+        }
+    }
 }
 
 /// Lambert data.
@@ -32,3 +78,77 @@ pub struct LambertData {
     /// Emissive.
     pub emissive: RGB<f32>,
 }
+
+impl LambertData {
+    /// Returns approximate parameters for physically based rendering.
+    ///
+    /// Lambert materials have no specular term, so they are mapped to fully
+    /// rough dielectrics.
+    pub fn to_pbr(&self) -> PbrData {
+        PbrData {
+            base_color: self.diffuse,
+            emissive: self.emissive,
+            roughness: 1.0,
+            f0: RGB::from([PbrData::DIELECTRIC_F0; 3]),
+        }
+    }
+}
+
+/// Phong data.
+#[derive(Debug, Clone, Copy)]
+pub struct PhongData {
+    /// Lambert part.
+    pub lambert: LambertData,
+    /// Specular.
+    pub specular: RGB<f32>,
+    /// Shininess (specular exponent).
+    pub shininess: f32,
+    /// Reflection.
+    ///
+    /// This is the color of the environment reflected by the surface,
+    /// already multiplied by the reflection factor.
+    pub reflection: RGB<f32>,
+}
+
+impl PhongData {
+    /// Returns approximate parameters for physically based rendering.
+    ///
+    /// The shininess is converted to roughness using the Blinn-Phong to
+    /// Beckmann mapping `alpha = sqrt(2 / (shininess + 2))`, and the
+    /// perceptual roughness `sqrt(alpha)` is returned.
+    /// The specular color is used as F0 as is (clamped to `[0, 1]`), since it
+    /// is what legacy pipelines tuned as "specular reflectance".
+    pub fn to_pbr(&self) -> PbrData {
+        let shininess = self.shininess.max(0.0);
+        let alpha = (2.0 / (shininess + 2.0)).sqrt();
+        let f0 = RGB {
+            r: self.specular.r.clamp(0.0, 1.0),
+            g: self.specular.g.clamp(0.0, 1.0),
+            b: self.specular.b.clamp(0.0, 1.0),
+        };
+        PbrData {
+            base_color: self.lambert.diffuse,
+            emissive: self.lambert.emissive,
+            roughness: alpha.sqrt(),
+            f0,
+        }
+    }
+}
+
+/// Approximate parameters for physically based rendering.
+#[derive(Debug, Clone, Copy)]
+pub struct PbrData {
+    /// Base color.
+    pub base_color: RGB<f32>,
+    /// Emissive.
+    pub emissive: RGB<f32>,
+    /// Perceptual roughness, between 0.0 (mirror) and 1.0 (fully rough).
+    pub roughness: f32,
+    /// Fresnel reflectance at normal incidence.
+    pub f0: RGB<f32>,
+}
+
+impl PbrData {
+    /// F0 commonly used for dielectrics.
+    pub const DIELECTRIC_F0: f32 = 0.04;
+}
@@ -1,6 +1,8 @@
 //! Mesh.
 
-use crate::data::{GeometryMeshIndex, MaterialIndex};
+use cgmath::Matrix4;
+
+use crate::data::{GeometryMeshIndex, MaterialIndex, NodeIndex};
 
 /// Mesh.
 #[derive(Debug, Clone)]
@@ -11,6 +13,23 @@ pub struct Mesh {
     pub geometry_mesh_index: GeometryMeshIndex,
     /// Materials.
     pub materials: Vec<MaterialIndex>,
+    /// Transform from the space of the geometry mesh to the world space.
+    ///
+    /// This is not applied to the geometry mesh, and is the identity if the
+    /// transform is already baked into the geometry mesh.
+    pub transform: Matrix4<f32>,
+    /// Node the mesh is attached to.
+    ///
+    /// This is `None` if the file has no node for the mesh, or the node
+    /// failed to load.
+    pub node: Option<NodeIndex>,
+    /// Render order.
+    ///
+    /// Meshes with smaller values are drawn first among the opaque meshes and
+    /// among the transparent meshes, and meshes with the same value are drawn
+    /// in the loaded order.
+    /// This is 0 unless specified by the file or the user.
+    pub render_order: i32,
 }
 
 impl Mesh {
@@ -0,0 +1,128 @@
+//! Node.
+
+use cgmath::{Deg, Matrix4, SquareMatrix, Vector3};
+
+use crate::data::NodeIndex;
+
+/// Node of the scene hierarchy.
+///
+/// The transform of a node relative to its parent is
+/// `T * Roff * Rp * Rpre * R * Rpost^-1 * Rp^-1 * Soff * Sp * S * Sp^-1`,
+/// where `Rpre` and `Rpost` are in the XYZ order and `R` is in
+/// `rotation_order`.
+/// Children inherit the whole transforms of their parents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    /// Name.
+    pub name: Option<String>,
+    /// Parent node.
+    pub parent: Option<NodeIndex>,
+    /// Child nodes.
+    pub children: Vec<NodeIndex>,
+    /// Local translation (`T`).
+    pub translation: Vector3<f64>,
+    /// Local rotation in Euler angles in degrees (`R`).
+    pub rotation: Vector3<f64>,
+    /// Order of the local rotation.
+    pub rotation_order: RotationOrder,
+    /// Local scaling (`S`).
+    pub scaling: Vector3<f64>,
+    /// Rotation applied before the local rotation, in degrees (`Rpre`).
+    pub pre_rotation: Vector3<f64>,
+    /// Rotation applied after the local rotation, in degrees (`Rpost`).
+    pub post_rotation: Vector3<f64>,
+    /// Rotation offset (`Roff`).
+    pub rotation_offset: Vector3<f64>,
+    /// Rotation pivot (`Rp`).
+    pub rotation_pivot: Vector3<f64>,
+    /// Scaling offset (`Soff`).
+    pub scaling_offset: Vector3<f64>,
+    /// Scaling pivot (`Sp`).
+    pub scaling_pivot: Vector3<f64>,
+}
+
+impl Node {
+    /// Returns the transform of the node relative to its parent.
+    pub fn local_transform(&self) -> Matrix4<f64> {
+        let rotation_pivot = Matrix4::from_translation(self.rotation_pivot);
+        let scaling_pivot = Matrix4::from_translation(self.scaling_pivot);
+        let inverse = |m: Matrix4<f64>| m.invert().unwrap_or(m);
+
+        Matrix4::from_translation(self.translation)
+            * Matrix4::from_translation(self.rotation_offset)
+            * rotation_pivot
+            * RotationOrder::Xyz.matrix(self.pre_rotation)
+            * self.rotation_order.matrix(self.rotation)
+            * inverse(RotationOrder::Xyz.matrix(self.post_rotation))
+            * inverse(rotation_pivot)
+            * Matrix4::from_translation(self.scaling_offset)
+            * scaling_pivot
+            * Matrix4::from_nonuniform_scale(self.scaling.x, self.scaling.y, self.scaling.z)
+            * inverse(scaling_pivot)
+    }
+}
+
+impl Default for Node {
+    fn default() -> Self {
+        let zero = Vector3::new(0.0, 0.0, 0.0);
+        Self {
+            name: None,
+            parent: None,
+            children: Vec::new(),
+            translation: zero,
+            rotation: zero,
+            rotation_order: RotationOrder::Xyz,
+            scaling: Vector3::new(1.0, 1.0, 1.0),
+            pre_rotation: zero,
+            post_rotation: zero,
+            rotation_offset: zero,
+            rotation_pivot: zero,
+            scaling_offset: zero,
+            scaling_pivot: zero,
+        }
+    }
+}
+
+/// Order of the rotations around the axes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RotationOrder {
+    /// X, Y, then Z.
+    Xyz,
+    /// X, Z, then Y.
+    Xzy,
+    /// Y, Z, then X.
+    Yzx,
+    /// Y, X, then Z.
+    Yxz,
+    /// Z, X, then Y.
+    Zxy,
+    /// Z, Y, then X.
+    Zyx,
+}
+
+impl RotationOrder {
+    /// Returns the axes in the order of the rotations.
+    fn axes(self) -> [usize; 3] {
+        match self {
+            Self::Xyz => [0, 1, 2],
+            Self::Xzy => [0, 2, 1],
+            Self::Yzx => [1, 2, 0],
+            Self::Yxz => [1, 0, 2],
+            Self::Zxy => [2, 0, 1],
+            Self::Zyx => [2, 1, 0],
+        }
+    }
+
+    /// Returns the rotation matrix of the Euler angles in degrees.
+    pub fn matrix(self, degrees: Vector3<f64>) -> Matrix4<f64> {
+        self.axes().iter().fold(Matrix4::identity(), |m, &axis| {
+            let angle = Deg(degrees[axis]);
+            let r = match axis {
+                0 => Matrix4::from_angle_x(angle),
+                1 => Matrix4::from_angle_y(angle),
+                _ => Matrix4::from_angle_z(angle),
+            };
+            r * m
+        })
+    }
+}
@@ -0,0 +1,677 @@
+//! Ray casting.
+
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, Transform, Vector3};
+
+use crate::{
+    data::{MeshIndex, Scene},
+    util::bbox::{BoundingBox3d, OptionalBoundingBox3d},
+};
+
+/// Maximum number of triangles in a leaf node.
+const MAX_LEAF_TRIANGLES: usize = 4;
+
+/// Ray.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    /// Origin.
+    origin: Point3<f32>,
+    /// Normalized direction.
+    direction: Vector3<f32>,
+}
+
+impl Ray {
+    /// Creates a new ray.
+    ///
+    /// The direction is normalized.
+    pub fn new(origin: Point3<f32>, direction: Vector3<f32>) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    /// Returns the origin.
+    pub fn origin(&self) -> Point3<f32> {
+        self.origin
+    }
+
+    /// Returns the normalized direction.
+    pub fn direction(&self) -> Vector3<f32> {
+        self.direction
+    }
+
+    /// Returns the point at the given distance from the origin.
+    pub fn at(&self, distance: f32) -> Point3<f32> {
+        self.origin + self.direction * distance
+    }
+
+    /// Returns the ray transformed by the matrix.
+    ///
+    /// Distances along the transformed ray differ from the original ones if
+    /// the matrix scales.
+    pub fn transform(&self, m: &Matrix4<f32>) -> Self {
+        Self::new(
+            m.transform_point(self.origin),
+            m.transform_vector(self.direction),
+        )
+    }
+}
+
+/// Intersection of a ray and a triangle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    /// Mesh.
+    pub mesh: MeshIndex,
+    /// Submesh (material slot) index.
+    pub submesh: usize,
+    /// Triangle index in the submesh.
+    pub triangle: usize,
+    /// Barycentric coordinates, i.e. weights of the three vertices.
+    pub barycentric: Vector3<f32>,
+    /// Distance from the ray origin.
+    pub distance: f32,
+}
+
+/// Returns the closest intersection of the ray and the scene.
+///
+/// This builds a BVH for every call.
+/// Use [`Bvh`] to cast many rays against the same scene.
+pub fn closest_hit(scene: &Scene, ray: &Ray) -> Option<Hit> {
+    Bvh::new(scene).closest_hit(ray)
+}
+
+/// Returns the closest intersection of the ray and the mesh.
+///
+/// This tests all triangles of the mesh, with the transform of the mesh
+/// applied.
+pub fn closest_hit_in_mesh(scene: &Scene, mesh: MeshIndex, ray: &Ray) -> Option<Hit> {
+    let mesh_data = scene.mesh(mesh).ok()?;
+    let geometry = scene.geometry_mesh(mesh_data.geometry_mesh_index).ok()?;
+    let mut closest: Option<Hit> = None;
+    for (submesh, indices) in geometry.indices_per_material.iter().enumerate() {
+        for (triangle, tri_indices) in indices.chunks_exact(3).enumerate() {
+            let vertices =
+                triangle_vertices(&mesh_data.transform, &geometry.positions, tri_indices);
+            let (distance, u, v) = match intersect_triangle(ray, &vertices) {
+                Some(v) => v,
+                None => continue,
+            };
+            if closest.is_some_and(|hit| hit.distance <= distance) {
+                continue;
+            }
+            closest = Some(Hit {
+                mesh,
+                submesh,
+                triangle,
+                barycentric: Vector3::new(1.0 - u - v, u, v),
+                distance,
+            });
+        }
+    }
+
+    closest
+}
+
+/// Bounding volume hierarchy of the triangles in a scene.
+#[derive(Debug, Clone)]
+pub struct Bvh {
+    /// Nodes.
+    ///
+    /// The first node is the root, if available.
+    nodes: Vec<Node>,
+    /// Triangles.
+    triangles: Vec<Triangle>,
+}
+
+impl Bvh {
+    /// Builds a BVH for the given scene.
+    ///
+    /// The triangles are in the world space, with the transforms of the
+    /// meshes applied.
+    pub fn new(scene: &Scene) -> Self {
+        let mut triangles = Vec::new();
+        for (mesh_i, mesh) in scene.meshes_with_index() {
+            let geometry = match scene.geometry_mesh(mesh.geometry_mesh_index) {
+                Ok(v) => v,
+                Err(_) => continue,
+            };
+            for (submesh, indices) in geometry.indices_per_material.iter().enumerate() {
+                for (triangle, tri_indices) in indices.chunks_exact(3).enumerate() {
+                    let vertices =
+                        triangle_vertices(&mesh.transform, &geometry.positions, tri_indices);
+                    triangles.push(Triangle {
+                        vertices,
+                        mesh: mesh_i,
+                        submesh,
+                        triangle,
+                    });
+                }
+            }
+        }
+
+        let mut nodes = Vec::new();
+        if !triangles.is_empty() {
+            let len = triangles.len();
+            build_node(&mut nodes, &mut triangles, 0, len);
+        }
+
+        Self { nodes, triangles }
+    }
+
+    /// Returns the closest intersection of the ray and the triangles.
+    pub fn closest_hit(&self, ray: &Ray) -> Option<Hit> {
+        self.closest_hit_filtered(ray, |_| true)
+    }
+
+    /// Returns the closest intersection of the ray and the triangles of the
+    /// meshes accepted by the filter.
+    pub fn closest_hit_filtered(
+        &self,
+        ray: &Ray,
+        filter: impl Fn(MeshIndex) -> bool,
+    ) -> Option<Hit> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let inv_dir = Vector3::new(
+            ray.direction.x.recip(),
+            ray.direction.y.recip(),
+            ray.direction.z.recip(),
+        );
+        let mut closest: Option<Hit> = None;
+        let mut stack = vec![0];
+        while let Some(node_i) = stack.pop() {
+            let node = &self.nodes[node_i];
+            let max_distance = closest.map_or(f32::INFINITY, |hit| hit.distance);
+            if intersect_bbox(&node.bbox, ray, inv_dir, max_distance).is_none() {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf { start, end } => {
+                    for tri in &self.triangles[start..end] {
+                        if !filter(tri.mesh) {
+                            continue;
+                        }
+                        let (distance, u, v) = match intersect_triangle(ray, &tri.vertices) {
+                            Some(v) => v,
+                            None => continue,
+                        };
+                        if closest.is_some_and(|hit| hit.distance <= distance) {
+                            continue;
+                        }
+                        closest = Some(Hit {
+                            mesh: tri.mesh,
+                            submesh: tri.submesh,
+                            triangle: tri.triangle,
+                            barycentric: Vector3::new(1.0 - u - v, u, v),
+                            distance,
+                        });
+                    }
+                }
+                NodeKind::Branch { left, right } => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+            }
+        }
+
+        closest
+    }
+}
+
+/// BVH node.
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    /// Bounding box of the triangles under the node.
+    bbox: BoundingBox3d<f32>,
+    /// Node kind.
+    kind: NodeKind,
+}
+
+/// BVH node kind.
+#[derive(Debug, Clone, Copy)]
+enum NodeKind {
+    /// Leaf node with the triangles in the range.
+    Leaf {
+        /// Start index of the triangles.
+        start: usize,
+        /// End index of the triangles.
+        end: usize,
+    },
+    /// Branch node with the two children.
+    Branch {
+        /// Left child node index.
+        left: usize,
+        /// Right child node index.
+        right: usize,
+    },
+}
+
+/// Triangle.
+#[derive(Debug, Clone, Copy)]
+struct Triangle {
+    /// Vertex positions.
+    vertices: [Point3<f32>; 3],
+    /// Mesh.
+    mesh: MeshIndex,
+    /// Submesh index.
+    submesh: usize,
+    /// Triangle index in the submesh.
+    triangle: usize,
+}
+
+impl Triangle {
+    /// Returns the centroid.
+    fn centroid(&self) -> Point3<f32> {
+        Point3::centroid(&self.vertices)
+    }
+}
+
+/// Builds a node for the triangles in the range, and returns the node index.
+///
+/// The range should not be empty.
+fn build_node(
+    nodes: &mut Vec<Node>,
+    triangles: &mut [Triangle],
+    start: usize,
+    end: usize,
+) -> usize {
+    let bbox = triangles[start..end]
+        .iter()
+        .flat_map(|tri| tri.vertices.iter())
+        .collect::<OptionalBoundingBox3d<f32>>()
+        .bounding_box()
+        .expect("Should never fail: the range is not empty");
+    let node_i = nodes.len();
+    nodes.push(Node {
+        bbox,
+        kind: NodeKind::Leaf { start, end },
+    });
+    if end - start <= MAX_LEAF_TRIANGLES {
+        return node_i;
+    }
+
+    // Split at the median along the longest axis of the centroids.
+    let centroid_size = triangles[start..end]
+        .iter()
+        .map(Triangle::centroid)
+        .collect::<OptionalBoundingBox3d<f32>>()
+        .bounding_box()
+        .expect("Should never fail: the range is not empty")
+        .size();
+    let axis = if centroid_size.x >= centroid_size.y && centroid_size.x >= centroid_size.z {
+        0
+    } else if centroid_size.y >= centroid_size.z {
+        1
+    } else {
+        2
+    };
+    let mid = (start + end) / 2;
+    triangles[start..end].select_nth_unstable_by(mid - start, |a, b| {
+        a.centroid()[axis]
+            .partial_cmp(&b.centroid()[axis])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let left = build_node(nodes, triangles, start, mid);
+    let right = build_node(nodes, triangles, mid, end);
+    nodes[node_i].kind = NodeKind::Branch { left, right };
+
+    node_i
+}
+
+/// Returns the distance to the bounding box if the ray intersects it within
+/// the given distance.
+fn intersect_bbox(
+    bbox: &BoundingBox3d<f32>,
+    ray: &Ray,
+    inv_dir: Vector3<f32>,
+    max_distance: f32,
+) -> Option<f32> {
+    let (min, max) = (bbox.min(), bbox.max());
+    let mut near = 0.0_f32;
+    let mut far = max_distance;
+    for axis in 0..3 {
+        // The slab test gives NaN for the rays parallel to the axis and
+        // starting on the planes of the box.
+        if ray.direction[axis] == 0.0 {
+            if ray.origin[axis] < min[axis] || ray.origin[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let t0 = (min[axis] - ray.origin[axis]) * inv_dir[axis];
+        let t1 = (max[axis] - ray.origin[axis]) * inv_dir[axis];
+        near = near.max(t0.min(t1));
+        far = far.min(t0.max(t1));
+    }
+
+    if near <= far {
+        Some(near)
+    } else {
+        None
+    }
+}
+
+/// Returns the vertices of the triangle with the transform applied.
+fn triangle_vertices(
+    transform: &Matrix4<f32>,
+    positions: &[Point3<f32>],
+    indices: &[u32],
+) -> [Point3<f32>; 3] {
+    [0, 1, 2].map(|i| transform.transform_point(positions[indices[i] as usize]))
+}
+
+/// Returns the distance and the barycentric `(u, v)` if the ray intersects the
+/// triangle.
+///
+/// Both sides of the triangle are hit.
+fn intersect_triangle(ray: &Ray, vertices: &[Point3<f32>; 3]) -> Option<(f32, f32, f32)> {
+    // Möller–Trumbore algorithm.
+    let e1 = vertices[1] - vertices[0];
+    let e2 = vertices[2] - vertices[0];
+    let p = ray.direction.cross(e2);
+    let det = e1.dot(p);
+    if det == 0.0 {
+        return None;
+    }
+    let inv_det = det.recip();
+
+    let s = ray.origin - vertices[0];
+    let u = s.dot(p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(e1);
+    let v = ray.direction.dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let distance = e2.dot(q) * inv_det;
+    if distance < 0.0 {
+        return None;
+    }
+
+    Some((distance, u, v))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::data::{GeometryMesh, Mesh};
+
+    /// Adds a mesh of the triangles to the scene.
+    fn add_mesh(
+        scene: &mut Scene,
+        positions: Vec<Point3<f32>>,
+        indices: Vec<u32>,
+        transform: Matrix4<f32>,
+    ) -> MeshIndex {
+        let geometry = scene.add_geometry_mesh(GeometryMesh {
+            name: None,
+            normals: Vec::new(),
+            uv: Vec::new(),
+            colors: Vec::new(),
+            polygons_per_material: vec![(0..indices.len() as u32 / 3).collect()],
+            indices_per_material: vec![indices],
+            positions,
+        });
+        scene.add_mesh(Mesh {
+            name: None,
+            geometry_mesh_index: geometry,
+            materials: Vec::new(),
+            transform,
+            node: None,
+            render_order: 0,
+        })
+    }
+
+    /// Adds a mesh of a right triangle on the XY plane at the height.
+    fn add_triangle(scene: &mut Scene, z: f32) -> MeshIndex {
+        add_mesh(
+            scene,
+            vec![
+                Point3::new(0.0, 0.0, z),
+                Point3::new(1.0, 0.0, z),
+                Point3::new(0.0, 1.0, z),
+            ],
+            vec![0, 1, 2],
+            Matrix4::from_scale(1.0),
+        )
+    }
+
+    /// Returns the closest hit of all meshes, testing every triangle.
+    fn brute_force_closest_hit(scene: &Scene, ray: &Ray) -> Option<Hit> {
+        scene
+            .meshes_with_index()
+            .filter_map(|(mesh, _)| closest_hit_in_mesh(scene, mesh, ray))
+            .min_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap())
+    }
+
+    #[test]
+    fn single_triangle() {
+        let mut scene = Scene::new();
+        let mesh = add_triangle(&mut scene, 0.0);
+        let bvh = Bvh::new(&scene);
+
+        let ray = Ray::new(Point3::new(0.25, 0.5, 2.0), Vector3::new(0.0, 0.0, -2.0));
+        for hit in [
+            closest_hit_in_mesh(&scene, mesh, &ray),
+            bvh.closest_hit(&ray),
+        ] {
+            let hit = hit.expect("The ray should hit the triangle");
+            assert_eq!((hit.mesh, hit.submesh, hit.triangle), (mesh, 0, 0));
+            assert!((hit.distance - 2.0).abs() < 1e-6, "{:?}", hit);
+            assert!((hit.barycentric - Vector3::new(0.25, 0.25, 0.5)).magnitude() < 1e-6);
+            let sum = hit.barycentric.x + hit.barycentric.y + hit.barycentric.z;
+            assert!((sum - 1.0).abs() < 1e-6, "{:?}", hit);
+        }
+
+        // Outside of the triangle, and pointing away from it.
+        let misses = [
+            Ray::new(Point3::new(0.75, 0.75, 2.0), Vector3::new(0.0, 0.0, -1.0)),
+            Ray::new(Point3::new(0.25, 0.25, 2.0), Vector3::new(0.0, 0.0, 1.0)),
+        ];
+        for ray in &misses {
+            assert_eq!(closest_hit_in_mesh(&scene, mesh, ray), None);
+            assert_eq!(bvh.closest_hit(ray), None);
+        }
+    }
+
+    /// Tests that the nearest of the overlapping triangles is hit from both
+    /// sides.
+    #[test]
+    fn closest_of_stacked_triangles() {
+        let mut scene = Scene::new();
+        let stacked = add_mesh(
+            &mut scene,
+            (0..3)
+                .flat_map(|z| {
+                    let z = z as f32;
+                    [
+                        Point3::new(0.0, 0.0, z),
+                        Point3::new(1.0, 0.0, z),
+                        Point3::new(0.0, 1.0, z),
+                    ]
+                })
+                .collect(),
+            (0..9).collect(),
+            Matrix4::from_scale(1.0),
+        );
+        let single = add_triangle(&mut scene, 1.5);
+        let bvh = Bvh::new(&scene);
+
+        let from_above = Ray::new(Point3::new(0.25, 0.25, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit = closest_hit_in_mesh(&scene, stacked, &from_above).unwrap();
+        assert_eq!((hit.triangle, hit.distance), (2, 3.0));
+        let hit = bvh.closest_hit(&from_above).unwrap();
+        assert_eq!((hit.mesh, hit.triangle, hit.distance), (stacked, 2, 3.0));
+
+        let from_below = Ray::new(Point3::new(0.25, 0.25, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = bvh.closest_hit(&from_below).unwrap();
+        assert_eq!((hit.mesh, hit.triangle, hit.distance), (stacked, 0, 5.0));
+
+        // Between the triangles of the stacked mesh.
+        let from_middle = Ray::new(Point3::new(0.25, 0.25, 1.75), Vector3::new(0.0, 0.0, -1.0));
+        let hit = bvh.closest_hit(&from_middle).unwrap();
+        assert_eq!(hit.mesh, single);
+        assert!((hit.distance - 0.25).abs() < 1e-6, "{:?}", hit);
+    }
+
+    /// Tests the rays parallel to the axes, whose inverse directions have
+    /// infinite components.
+    #[test]
+    fn axis_aligned_rays() {
+        let mut scene = Scene::new();
+        // On the plane `x = 2`.
+        let mesh = add_mesh(
+            &mut scene,
+            vec![
+                Point3::new(2.0, 0.0, 0.0),
+                Point3::new(2.0, 1.0, 0.0),
+                Point3::new(2.0, 0.0, 1.0),
+            ],
+            vec![0, 1, 2],
+            Matrix4::from_scale(1.0),
+        );
+        let bvh = Bvh::new(&scene);
+
+        let ray = Ray::new(Point3::new(0.0, 0.25, 0.25), Vector3::new(1.0, 0.0, 0.0));
+        let hit = bvh.closest_hit(&ray).unwrap();
+        assert_eq!((hit.mesh, hit.distance), (mesh, 2.0));
+        // The origin is on the planes of the bounding box, where the slab
+        // test multiplies zero by infinity.
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.5), Vector3::new(1.0, 0.0, 0.0));
+        let hit = bvh.closest_hit(&ray).unwrap();
+        assert_eq!((hit.mesh, hit.distance), (mesh, 2.0));
+        // Parallel to the triangle.
+        let ray = Ray::new(Point3::new(2.0, -1.0, 0.25), Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(bvh.closest_hit(&ray), None);
+        // Passing by the bounding box.
+        let ray = Ray::new(Point3::new(0.0, 2.0, 0.25), Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(bvh.closest_hit(&ray), None);
+    }
+
+    /// Tests the rays starting inside of the bounding boxes.
+    #[test]
+    fn ray_inside_box() {
+        let bbox =
+            BoundingBox3d::from(Point3::new(0.0, 0.0, 0.0)).insert(Point3::new(1.0, 1.0, 1.0));
+        let ray = Ray::new(Point3::new(0.5, 0.5, 0.5), Vector3::new(1.0, 1.0, 0.0));
+        let inv_dir = ray.direction.map(f32::recip);
+        assert_eq!(
+            intersect_bbox(&bbox, &ray, inv_dir, f32::INFINITY),
+            Some(0.0)
+        );
+        // Behind the origin.
+        let ray = Ray::new(Point3::new(2.0, 0.5, 0.5), Vector3::new(1.0, 0.0, 0.0));
+        let inv_dir = ray.direction.map(f32::recip);
+        assert_eq!(intersect_bbox(&bbox, &ray, inv_dir, f32::INFINITY), None);
+
+        // Inside of a cube, whose faces are split into several leaves.
+        let mut scene = Scene::new();
+        let positions = (0..8)
+            .map(|i| Point3::new((i & 1) as f32, ((i >> 1) & 1) as f32, (i >> 2) as f32))
+            .collect();
+        let indices = vec![
+            0, 2, 1, 1, 2, 3, // -Z
+            4, 5, 6, 5, 7, 6, // +Z
+            0, 1, 4, 1, 5, 4, // -Y
+            2, 6, 3, 3, 6, 7, // +Y
+            0, 4, 2, 2, 4, 6, // -X
+            1, 3, 5, 3, 7, 5, // +X
+        ];
+        let cube = add_mesh(&mut scene, positions, indices, Matrix4::from_scale(1.0));
+        let bvh = Bvh::new(&scene);
+        let ray = Ray::new(Point3::new(0.5, 0.25, 0.25), Vector3::new(1.0, 0.0, 0.0));
+        let hit = bvh.closest_hit(&ray).unwrap();
+        assert_eq!(hit.mesh, cube);
+        // On the +X face.
+        assert!(hit.triangle >= 10, "{:?}", hit);
+        assert!((hit.distance - 0.5).abs() < 1e-6, "{:?}", hit);
+    }
+
+    #[test]
+    fn filtered() {
+        let mut scene = Scene::new();
+        let lower = add_triangle(&mut scene, 0.0);
+        let upper = add_triangle(&mut scene, 1.0);
+        let bvh = Bvh::new(&scene);
+        let ray = Ray::new(Point3::new(0.25, 0.25, 2.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert_eq!(bvh.closest_hit(&ray).unwrap().mesh, upper);
+        let hit = bvh
+            .closest_hit_filtered(&ray, |mesh| mesh != upper)
+            .unwrap();
+        assert_eq!((hit.mesh, hit.distance), (lower, 2.0));
+        assert_eq!(bvh.closest_hit_filtered(&ray, |_| false), None);
+    }
+
+    /// Tests that the BVH finds the same hits as testing all triangles.
+    #[test]
+    fn bvh_matches_brute_force() {
+        const GRID: u32 = 12;
+
+        let mut scene = Scene::new();
+        // Bumpy height fields, the second one transformed.
+        let positions = (0..=GRID)
+            .flat_map(|y| (0..=GRID).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let (x, y) = (x as f32 / GRID as f32, y as f32 / GRID as f32);
+                let z = 0.1 * (x * 13.0).sin() * (y * 7.0).cos();
+                Point3::new(x, y, z)
+            })
+            .collect::<Vec<_>>();
+        let indices = (0..GRID)
+            .flat_map(|y| (0..GRID).map(move |x| y * (GRID + 1) + x))
+            .flat_map(|i| [i, i + 1, i + GRID + 1, i + 1, i + GRID + 2, i + GRID + 1])
+            .collect::<Vec<_>>();
+        assert!(indices.len() / 3 > MAX_LEAF_TRIANGLES * 16);
+        add_mesh(
+            &mut scene,
+            positions.clone(),
+            indices.clone(),
+            Matrix4::from_scale(1.0),
+        );
+        add_mesh(
+            &mut scene,
+            positions,
+            indices,
+            Matrix4::from_translation(Vector3::new(0.2, 0.1, 0.3))
+                * Matrix4::from_angle_x(cgmath::Deg(30.0)),
+        );
+        let bvh = Bvh::new(&scene);
+
+        // Deterministic pseudo-random rays from above the fields.
+        let mut state = 1_u32;
+        let mut random = move || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) as f32 / (1 << 24) as f32
+        };
+        let mut hits = 0;
+        for _ in 0..500 {
+            let origin = Point3::new(random() * 1.4 - 0.2, random() * 1.4 - 0.2, 2.0);
+            let direction = Vector3::new(random() - 0.5, random() - 0.5, -1.0);
+            let ray = Ray::new(origin, direction);
+            let expected = brute_force_closest_hit(&scene, &ray);
+            let actual = bvh.closest_hit(&ray);
+            match (expected, actual) {
+                (None, None) => {}
+                (Some(expected), Some(actual)) => {
+                    hits += 1;
+                    // Rays through shared edges may hit either triangle.
+                    assert!(
+                        (expected.distance - actual.distance).abs() < 1e-5,
+                        "{:?} != {:?}",
+                        expected,
+                        actual
+                    );
+                }
+                _ => panic!("{:?} != {:?} for {:?}", expected, actual, ray),
+            }
+        }
+        assert!(hits > 100, "Too few rays hit the scene: {}", hits);
+    }
+}
@@ -1,10 +1,25 @@
 //! Scene.
 
-use crate::data::{GeometryMesh, Material, Mesh, Texture};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    error, fmt,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use cgmath::{Matrix4, Rad, SquareMatrix};
+
+use crate::data::{
+    GeometryMesh, LoadWarning, Material, Mesh, MeshStats, Node, SceneStats, Texture, TextureStats,
+};
 
 /// Scene.
-#[derive(Default, Debug, Clone)]
+///
+/// Clones get new IDs, since they can diverge from the original scene.
+#[derive(Debug)]
 pub struct Scene {
+    /// Scene ID.
+    id: SceneId,
     /// Scene name.
     name: Option<String>,
     /// Geometry mesh.
@@ -13,8 +28,14 @@ pub struct Scene {
     materials: Vec<Material>,
     /// Meshes.
     meshes: Vec<Mesh>,
+    /// Nodes.
+    ///
+    /// Parents precede their children.
+    nodes: Vec<Node>,
     /// Textures.
     textures: Vec<Texture>,
+    /// Failures on loading objects skipped in the scene.
+    warnings: Vec<LoadWarning>,
 }
 
 impl Scene {
@@ -23,6 +44,16 @@ impl Scene {
         Self::default()
     }
 
+    /// Returns the scene ID.
+    pub fn id(&self) -> SceneId {
+        self.id
+    }
+
+    /// Returns the scene name.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
     /// Sets the scene name.
     pub fn set_name(&mut self, name: impl Into<Option<String>>) {
         self.name = name.into();
@@ -30,7 +61,7 @@ impl Scene {
 
     /// Add a geometry mesh.
     pub(crate) fn add_geometry_mesh(&mut self, mesh: GeometryMesh) -> GeometryMeshIndex {
-        let index = GeometryMeshIndex::new(self.meshes.len());
+        let index = GeometryMeshIndex::new(self.id, self.geometry_meshes.len());
         self.geometry_meshes.push(mesh);
         index
     }
@@ -41,13 +72,43 @@ impl Scene {
     }
 
     /// Returns a reference to the geometry mesh.
-    pub fn geometry_mesh(&self, i: GeometryMeshIndex) -> Option<&GeometryMesh> {
-        self.geometry_meshes.get(i.to_usize())
+    ///
+    /// Returns an error if the index is out of range or belongs to another
+    /// scene.
+    pub fn geometry_mesh(&self, i: GeometryMeshIndex) -> Result<&GeometryMesh, IndexError> {
+        i.resolve(self.id, &self.geometry_meshes)
+    }
+
+    /// Returns an iterator of geometry meshes with their indices.
+    pub fn geometry_meshes_with_index(
+        &self,
+    ) -> impl Iterator<Item = (GeometryMeshIndex, &GeometryMesh)> {
+        let id = self.id;
+        self.geometry_meshes
+            .iter()
+            .enumerate()
+            .map(move |(i, v)| (GeometryMeshIndex::new(id, i), v))
+    }
+
+    /// Recomputes normals of all geometry meshes.
+    ///
+    /// See [`GeometryMesh::recompute_normals`].
+    pub fn recompute_normals(&mut self, crease_angle: Rad<f32>) {
+        for mesh in &mut self.geometry_meshes {
+            mesh.recompute_normals(crease_angle);
+        }
+    }
+
+    /// Returns the index of the first geometry mesh with the given name.
+    pub fn geometry_mesh_by_name(&self, name: &str) -> Option<GeometryMeshIndex> {
+        self.geometry_meshes_with_index()
+            .find(|(_, v)| v.name.as_deref() == Some(name))
+            .map(|(i, _)| i)
     }
 
     /// Add a material.
     pub(crate) fn add_material(&mut self, material: Material) -> MaterialIndex {
-        let index = MaterialIndex::new(self.materials.len());
+        let index = MaterialIndex::new(self.id, self.materials.len());
         self.materials.push(material);
         index
     }
@@ -58,13 +119,32 @@ impl Scene {
     }
 
     /// Returns a reference to the material.
-    pub fn material(&self, i: MaterialIndex) -> Option<&Material> {
-        self.materials.get(i.to_usize())
+    ///
+    /// Returns an error if the index is out of range or belongs to another
+    /// scene.
+    pub fn material(&self, i: MaterialIndex) -> Result<&Material, IndexError> {
+        i.resolve(self.id, &self.materials)
+    }
+
+    /// Returns an iterator of materials with their indices.
+    pub fn materials_with_index(&self) -> impl Iterator<Item = (MaterialIndex, &Material)> {
+        let id = self.id;
+        self.materials
+            .iter()
+            .enumerate()
+            .map(move |(i, v)| (MaterialIndex::new(id, i), v))
+    }
+
+    /// Returns the index of the first material with the given name.
+    pub fn material_by_name(&self, name: &str) -> Option<MaterialIndex> {
+        self.materials_with_index()
+            .find(|(_, v)| v.name.as_deref() == Some(name))
+            .map(|(i, _)| i)
     }
 
     /// Add a mesh.
     pub(crate) fn add_mesh(&mut self, mesh: Mesh) -> MeshIndex {
-        let index = MeshIndex::new(self.meshes.len());
+        let index = MeshIndex::new(self.id, self.meshes.len());
         self.meshes.push(mesh);
         index
     }
@@ -75,13 +155,208 @@ impl Scene {
     }
 
     /// Returns a reference to the mesh.
-    pub fn mesh(&self, i: MeshIndex) -> Option<&Mesh> {
-        self.meshes.get(i.to_usize())
+    ///
+    /// Returns an error if the index is out of range or belongs to another
+    /// scene.
+    pub fn mesh(&self, i: MeshIndex) -> Result<&Mesh, IndexError> {
+        i.resolve(self.id, &self.meshes)
+    }
+
+    /// Returns a mutable reference to the mesh.
+    ///
+    /// Returns an error if the index is out of range or belongs to another
+    /// scene.
+    pub fn mesh_mut(&mut self, i: MeshIndex) -> Result<&mut Mesh, IndexError> {
+        i.resolve_mut(self.id, &mut self.meshes)
+    }
+
+    /// Removes the meshes for which the predicate returns `false`.
+    ///
+    /// The indices of the following meshes are shifted.
+    pub(crate) fn retain_meshes(&mut self, mut f: impl FnMut(MeshIndex, &Mesh) -> bool) {
+        let id = self.id;
+        let mut i = 0;
+        self.meshes.retain(|mesh| {
+            let keep = f(MeshIndex::new(id, i), mesh);
+            i += 1;
+            keep
+        });
+    }
+
+    /// Returns the geometry mesh of the mesh with the transform of the mesh
+    /// applied, i.e. in the world space.
+    ///
+    /// The geometry mesh is borrowed as is if the transform is the identity.
+    pub fn world_geometry_mesh(&self, mesh: &Mesh) -> Result<Cow<'_, GeometryMesh>, IndexError> {
+        let geometry = self.geometry_mesh(mesh.geometry_mesh_index)?;
+        if mesh.transform == Matrix4::identity() {
+            return Ok(Cow::Borrowed(geometry));
+        }
+        let mut geometry = geometry.clone();
+        geometry.transform(&mesh.transform);
+
+        Ok(Cow::Owned(geometry))
+    }
+
+    /// Bakes the transforms of the meshes into their geometry meshes, and
+    /// returns the number of the baked meshes.
+    ///
+    /// Meshes sharing a geometry mesh with the same transform keep sharing
+    /// it, and the others get their own copies of the geometry mesh.
+    /// The transforms of the meshes become the identity.
+    ///
+    /// Returns an error without changing the scene if any of the meshes
+    /// refers to a missing geometry mesh.
+    pub fn bake_mesh_transforms(&mut self) -> Result<usize, IndexError> {
+        let identity = Matrix4::identity();
+        for mesh in &self.meshes {
+            self.geometry_mesh(mesh.geometry_mesh_index)?;
+        }
+
+        // Transforms baked into each geometry mesh, and the resulting
+        // geometry meshes.
+        let mut variants: HashMap<GeometryMeshIndex, Vec<(Matrix4<f32>, GeometryMeshIndex)>> =
+            HashMap::new();
+        for mesh in &self.meshes {
+            if mesh.transform == identity {
+                let index = mesh.geometry_mesh_index;
+                variants
+                    .entry(index)
+                    .or_insert_with(|| vec![(identity, index)]);
+            }
+        }
+        // Copies are made from the geometry meshes before transforming them in
+        // place.
+        let mut copies = Vec::new();
+        let mut in_place = Vec::new();
+        let mut num_baked = 0;
+        for mesh in &mut self.meshes {
+            if mesh.transform == identity {
+                continue;
+            }
+            let source = mesh.geometry_mesh_index;
+            let variants = variants.entry(source).or_default();
+            let target = match variants.iter().find(|(t, _)| *t == mesh.transform) {
+                Some(&(_, target)) => target,
+                None => {
+                    let target = if variants.is_empty() {
+                        in_place.push((source, mesh.transform));
+                        source
+                    } else {
+                        copies.push((source, mesh.transform));
+                        GeometryMeshIndex::new(
+                            self.id,
+                            self.geometry_meshes.len() + copies.len() - 1,
+                        )
+                    };
+                    variants.push((mesh.transform, target));
+                    target
+                }
+            };
+            mesh.geometry_mesh_index = target;
+            mesh.transform = identity;
+            num_baked += 1;
+        }
+
+        for (source, transform) in copies {
+            let mut geometry = self.geometry_meshes[source.to_usize()].clone();
+            geometry.transform(&transform);
+            self.geometry_meshes.push(geometry);
+        }
+        for (source, transform) in in_place {
+            self.geometry_meshes[source.to_usize()].transform(&transform);
+        }
+
+        Ok(num_baked)
+    }
+
+    /// Returns an iterator of meshes with their indices.
+    pub fn meshes_with_index(&self) -> impl Iterator<Item = (MeshIndex, &Mesh)> {
+        let id = self.id;
+        self.meshes
+            .iter()
+            .enumerate()
+            .map(move |(i, v)| (MeshIndex::new(id, i), v))
+    }
+
+    /// Returns the index of the first mesh with the given name.
+    pub fn mesh_by_name(&self, name: &str) -> Option<MeshIndex> {
+        self.meshes_with_index()
+            .find(|(_, v)| v.name.as_deref() == Some(name))
+            .map(|(i, _)| i)
+    }
+
+    /// Add a node.
+    ///
+    /// The node is appended to the children of its parent, which should be
+    /// already added.
+    /// Returns an error if the parent is not in the scene.
+    pub(crate) fn add_node(&mut self, node: Node) -> Result<NodeIndex, IndexError> {
+        let index = NodeIndex::new(self.id, self.nodes.len());
+        if let Some(parent) = node.parent {
+            parent
+                .resolve_mut(self.id, &mut self.nodes)?
+                .children
+                .push(index);
+        }
+        self.nodes.push(node);
+        Ok(index)
+    }
+
+    /// Returns an iterator of nodes.
+    ///
+    /// Parents precede their children.
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.nodes.iter()
+    }
+
+    /// Returns a reference to the node.
+    ///
+    /// Returns an error if the index is out of range or belongs to another
+    /// scene.
+    pub fn node(&self, i: NodeIndex) -> Result<&Node, IndexError> {
+        i.resolve(self.id, &self.nodes)
+    }
+
+    /// Returns an iterator of nodes with their indices.
+    pub fn nodes_with_index(&self) -> impl Iterator<Item = (NodeIndex, &Node)> {
+        let id = self.id;
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(move |(i, v)| (NodeIndex::new(id, i), v))
+    }
+
+    /// Returns an iterator of the nodes without parents.
+    pub fn root_nodes(&self) -> impl Iterator<Item = NodeIndex> + '_ {
+        self.nodes_with_index()
+            .filter(|(_, node)| node.parent.is_none())
+            .map(|(i, _)| i)
+    }
+
+    /// Returns the index of the first node with the given name.
+    pub fn node_by_name(&self, name: &str) -> Option<NodeIndex> {
+        self.nodes_with_index()
+            .find(|(_, v)| v.name.as_deref() == Some(name))
+            .map(|(i, _)| i)
+    }
+
+    /// Returns the transform from the space of the node to the world space.
+    pub fn world_transform(&self, i: NodeIndex) -> Result<Matrix4<f64>, IndexError> {
+        let mut node = self.node(i)?;
+        let mut transform = node.local_transform();
+        // Parents precede their children, so the walk terminates.
+        while let Some(parent) = node.parent {
+            node = self.node(parent)?;
+            transform = node.local_transform() * transform;
+        }
+
+        Ok(transform)
     }
 
     /// Add a texture.
     pub(crate) fn add_texture(&mut self, texture: Texture) -> TextureIndex {
-        let index = TextureIndex::new(self.textures.len());
+        let index = TextureIndex::new(self.id, self.textures.len());
         self.textures.push(texture);
         index
     }
@@ -92,11 +367,176 @@ impl Scene {
     }
 
     /// Returns a reference to the texture.
-    pub fn texture(&self, i: TextureIndex) -> Option<&Texture> {
-        self.textures.get(i.to_usize())
+    ///
+    /// Returns an error if the index is out of range or belongs to another
+    /// scene.
+    pub fn texture(&self, i: TextureIndex) -> Result<&Texture, IndexError> {
+        i.resolve(self.id, &self.textures)
+    }
+
+    /// Returns a mutable reference to the texture.
+    ///
+    /// Returns an error if the index is out of range or belongs to another
+    /// scene.
+    pub fn texture_mut(&mut self, i: TextureIndex) -> Result<&mut Texture, IndexError> {
+        i.resolve_mut(self.id, &mut self.textures)
     }
+
+    /// Returns an iterator of textures with their indices.
+    pub fn textures_with_index(&self) -> impl Iterator<Item = (TextureIndex, &Texture)> {
+        let id = self.id;
+        self.textures
+            .iter()
+            .enumerate()
+            .map(move |(i, v)| (TextureIndex::new(id, i), v))
+    }
+
+    /// Returns the index of the first texture with the given name.
+    pub fn texture_by_name(&self, name: &str) -> Option<TextureIndex> {
+        self.textures_with_index()
+            .find(|(_, v)| v.name.as_deref() == Some(name))
+            .map(|(i, _)| i)
+    }
+
+    /// Add a load warning.
+    pub(crate) fn add_warning(&mut self, warning: LoadWarning) {
+        self.warnings.push(warning);
+    }
+
+    /// Returns the failures on loading objects which are skipped in the scene.
+    pub fn warnings(&self) -> &[LoadWarning] {
+        &self.warnings
+    }
+
+    /// Computes statistics of the meshes, the materials, and the textures.
+    ///
+    /// The mesh statistics are measured in the world space.
+    ///
+    /// Returns an error if any of the meshes refers to a missing geometry
+    /// mesh.
+    pub fn stats(&self) -> Result<SceneStats, IndexError> {
+        let meshes = self
+            .meshes
+            .iter()
+            .map(|mesh| {
+                let geometry = self.world_geometry_mesh(mesh)?;
+                Ok(MeshStats::new(mesh, &geometry))
+            })
+            .collect::<Result<_, _>>()?;
+        let textures = self.textures.iter().map(TextureStats::new).collect();
+
+        Ok(SceneStats {
+            meshes,
+            num_materials: self.materials.len(),
+            textures,
+        })
+    }
+}
+
+impl Clone for Scene {
+    fn clone(&self) -> Self {
+        let mut scene = Self {
+            id: SceneId::new(),
+            name: self.name.clone(),
+            geometry_meshes: self.geometry_meshes.clone(),
+            materials: self.materials.clone(),
+            meshes: self.meshes.clone(),
+            nodes: self.nodes.clone(),
+            textures: self.textures.clone(),
+            warnings: self.warnings.clone(),
+        };
+        // Keep in sync with the index fields of the resources.
+        let id = scene.id;
+        for mesh in &mut scene.meshes {
+            mesh.geometry_mesh_index = mesh.geometry_mesh_index.rebind(id);
+            for material in &mut mesh.materials {
+                *material = material.rebind(id);
+            }
+            mesh.node = mesh.node.map(|node| node.rebind(id));
+        }
+        for material in &mut scene.materials {
+            material.diffuse_texture = material.diffuse_texture.map(|texture| texture.rebind(id));
+        }
+        for node in &mut scene.nodes {
+            node.parent = node.parent.map(|parent| parent.rebind(id));
+            for child in &mut node.children {
+                *child = child.rebind(id);
+            }
+        }
+        scene
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self {
+            id: SceneId::new(),
+            name: None,
+            geometry_meshes: Default::default(),
+            materials: Default::default(),
+            meshes: Default::default(),
+            nodes: Default::default(),
+            textures: Default::default(),
+            warnings: Default::default(),
+        }
+    }
+}
+
+/// Scene ID.
+///
+/// Every scene created in the process gets a distinct ID, so that indices
+/// from one scene are not silently used to access another scene.
+/// This includes clones of a scene, such as the scenes returned by
+/// [`Scene::with_edits`], whose indices can point to different resources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SceneId(u64);
+
+impl SceneId {
+    /// Allocates a new unique scene ID.
+    fn new() -> Self {
+        /// Next scene ID.
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Error on accessing scene resources by an index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexError {
+    /// The index belongs to another scene.
+    SceneMismatch {
+        /// ID of the scene being accessed.
+        expected: SceneId,
+        /// ID of the scene the index belongs to.
+        actual: SceneId,
+    },
+    /// The index is out of range.
+    OutOfRange {
+        /// Index.
+        index: usize,
+        /// Number of the resources.
+        len: usize,
+    },
 }
 
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexError::SceneMismatch { expected, actual } => write!(
+                f,
+                "Index for another scene: expected scene={:?}, got scene={:?}",
+                expected, actual
+            ),
+            IndexError::OutOfRange { index, len } => {
+                write!(f, "Index out of range: index={}, len={}", index, len)
+            }
+        }
+    }
+}
+
+impl error::Error for IndexError {}
+
 /// Defines independent index types for resource types.
 macro_rules! define_index_type {
     ($(
@@ -118,22 +558,81 @@ macro_rules! define_index_type {
     ) => {
         $(#[$meta])*
         #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-        pub struct $ty(u32);
+        pub struct $ty {
+            /// Scene the index belongs to.
+            scene: SceneId,
+            /// Index.
+            index: u32,
+        }
 
         impl $ty {
             /// Creates a new index.
             ///
             /// # Panics
             ///
-            /// Panics if the given index is larger than `std::u32::MAX`.
-            pub(crate) fn new(i: usize) -> Self {
-                assert!(i <= std::u32::MAX as usize);
-                Self(i as u32)
+            /// Panics if the given index is larger than `u32::MAX`.
+            pub(crate) fn new(scene: SceneId, i: usize) -> Self {
+                assert!(i <= u32::MAX as usize);
+                Self {
+                    scene,
+                    index: i as u32,
+                }
+            }
+
+            /// Returns the index at the same position in the given scene.
+            ///
+            /// This is useful to access a clone of a scene with the indices
+            /// of the original scene.
+            pub(crate) fn rebind(self, scene: SceneId) -> Self {
+                Self { scene, ..self }
             }
 
             /// Retuns `usize` value.
             pub fn to_usize(self) -> usize {
-                self.0 as usize
+                self.index as usize
+            }
+
+            /// Returns the ID of the scene the index belongs to.
+            pub fn scene_id(self) -> SceneId {
+                self.scene
+            }
+
+            /// Returns the item at the index in the given resources of the
+            /// given scene.
+            ///
+            /// This is useful to access resources derived from a scene with
+            /// the indices of the original scene.
+            pub fn resolve<T>(self, scene: SceneId, items: &[T]) -> Result<&T, IndexError> {
+                if self.scene != scene {
+                    return Err(IndexError::SceneMismatch {
+                        expected: scene,
+                        actual: self.scene,
+                    });
+                }
+                items.get(self.to_usize()).ok_or(IndexError::OutOfRange {
+                    index: self.to_usize(),
+                    len: items.len(),
+                })
+            }
+
+            /// Returns the mutable item at the index in the given resources
+            /// of the given scene.
+            pub fn resolve_mut<T>(
+                self,
+                scene: SceneId,
+                items: &mut [T],
+            ) -> Result<&mut T, IndexError> {
+                if self.scene != scene {
+                    return Err(IndexError::SceneMismatch {
+                        expected: scene,
+                        actual: self.scene,
+                    });
+                }
+                let len = items.len();
+                items.get_mut(self.to_usize()).ok_or(IndexError::OutOfRange {
+                    index: self.to_usize(),
+                    len,
+                })
             }
         }
     };
@@ -146,6 +645,144 @@ define_index_type! {
     MaterialIndex;
     /// Mesh index.
     MeshIndex;
+    /// Node index.
+    NodeIndex;
     /// Texture index.
     TextureIndex;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::{InnerSpace, Point3, Vector3};
+
+    /// Tests that the world transforms are composed along the hierarchy.
+    #[test]
+    fn node_hierarchy() -> anyhow::Result<()> {
+        let mut scene = Scene::new();
+        let root = scene.add_node(Node {
+            name: Some("root".to_owned()),
+            translation: Vector3::new(0.0, 0.0, 5.0),
+            ..Default::default()
+        })?;
+        let child = scene.add_node(Node {
+            name: Some("child".to_owned()),
+            parent: Some(root),
+            translation: Vector3::new(1.0, 0.0, 0.0),
+            rotation: Vector3::new(0.0, 0.0, 90.0),
+            ..Default::default()
+        })?;
+
+        assert_eq!(scene.root_nodes().collect::<Vec<_>>(), [root]);
+        assert_eq!(scene.node(root)?.children, [child]);
+        assert_eq!(scene.node_by_name("child"), Some(child));
+        // (1, 0, 0) is rotated to (0, 1, 0), and translated by the nodes.
+        let p = scene.world_transform(child)? * Point3::new(1.0, 0.0, 0.0).to_homogeneous();
+        assert!(
+            (Point3::from_homogeneous(p) - Point3::new(1.0, 1.0, 5.0)).magnitude() < 1e-9,
+            "{:?}",
+            p
+        );
+
+        // The parent belongs to another scene.
+        assert!(Scene::new()
+            .add_node(Node {
+                parent: Some(root),
+                ..Default::default()
+            })
+            .is_err());
+        Ok(())
+    }
+
+    /// Tests that the meshes sharing a geometry mesh with the same transform
+    /// keep sharing it.
+    #[test]
+    fn bake_mesh_transforms() -> anyhow::Result<()> {
+        let mut scene = Scene::new();
+        let geometry = scene.add_geometry_mesh(GeometryMesh {
+            name: None,
+            positions: vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            normals: vec![Vector3::new(0.0, 0.0, 1.0); 3],
+            uv: Vec::new(),
+            colors: Vec::new(),
+            indices_per_material: vec![vec![0, 1, 2]],
+            polygons_per_material: vec![vec![0]],
+        });
+        let up = Matrix4::from_translation(Vector3::new(0.0, 0.0, 2.0));
+        let meshes = [up, up, Matrix4::identity()].map(|transform| {
+            scene.add_mesh(Mesh {
+                name: None,
+                geometry_mesh_index: geometry,
+                materials: Vec::new(),
+                transform,
+                node: None,
+                render_order: 0,
+            })
+        });
+
+        assert_eq!(scene.bake_mesh_transforms()?, 2);
+        assert_eq!(scene.geometry_meshes().count(), 2);
+        let positions = |i: MeshIndex| -> anyhow::Result<_> {
+            let mesh = scene.mesh(i)?;
+            assert_eq!(mesh.transform, Matrix4::identity());
+            Ok(scene.geometry_mesh(mesh.geometry_mesh_index)?.positions[1])
+        };
+        assert_eq!(positions(meshes[0])?, Point3::new(1.0, 0.0, 2.0));
+        assert_eq!(positions(meshes[1])?, Point3::new(1.0, 0.0, 2.0));
+        assert_eq!(positions(meshes[2])?, Point3::new(1.0, 0.0, 0.0));
+        assert_eq!(scene.bake_mesh_transforms()?, 0);
+        Ok(())
+    }
+
+    /// Tests that clones do not accept the indices of the original scene, and
+    /// that the indices in their resources point to their own resources.
+    #[test]
+    fn clone_gets_new_id() -> anyhow::Result<()> {
+        let mut scene = Scene::new();
+        let root = scene.add_node(Node::default())?;
+        let child = scene.add_node(Node {
+            parent: Some(root),
+            translation: Vector3::new(1.0, 0.0, 0.0),
+            ..Default::default()
+        })?;
+        let geometry = scene.add_geometry_mesh(GeometryMesh {
+            name: None,
+            positions: Vec::new(),
+            normals: Vec::new(),
+            uv: Vec::new(),
+            colors: Vec::new(),
+            indices_per_material: Vec::new(),
+            polygons_per_material: Vec::new(),
+        });
+        let mesh = scene.add_mesh(Mesh {
+            name: None,
+            geometry_mesh_index: geometry,
+            materials: Vec::new(),
+            transform: Matrix4::identity(),
+            node: Some(child),
+            render_order: 0,
+        });
+
+        let cloned = scene.clone();
+        assert_ne!(cloned.id(), scene.id());
+        assert!(matches!(
+            cloned.mesh(mesh),
+            Err(IndexError::SceneMismatch { .. })
+        ));
+        let (_, cloned_mesh) = cloned.meshes_with_index().next().expect("Should exist");
+        cloned.geometry_mesh(cloned_mesh.geometry_mesh_index)?;
+        let cloned_node = cloned_mesh.node.expect("Should exist");
+        assert_eq!(
+            cloned.world_transform(cloned_node)?,
+            scene.world_transform(child)?
+        );
+        let cloned_root = cloned.root_nodes().next().expect("Should exist");
+        assert_eq!(cloned.node(cloned_root)?.children, [cloned_node]);
+        Ok(())
+    }
+}
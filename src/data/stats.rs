@@ -0,0 +1,261 @@
+//! Scene statistics.
+
+use std::fmt;
+
+use cgmath::Vector3;
+
+use crate::data::{GeometryMesh, Mesh, Texture};
+
+/// Statistics of a scene.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneStats {
+    /// Statistics of the meshes.
+    pub meshes: Vec<MeshStats>,
+    /// Number of materials.
+    pub num_materials: usize,
+    /// Statistics of the textures.
+    pub textures: Vec<TextureStats>,
+}
+
+impl SceneStats {
+    /// Returns the total number of triangles.
+    pub fn num_triangles(&self) -> usize {
+        self.meshes.iter().map(|mesh| mesh.num_triangles).sum()
+    }
+
+    /// Returns the total surface area.
+    pub fn surface_area(&self) -> f32 {
+        self.meshes.iter().map(|mesh| mesh.surface_area).sum()
+    }
+}
+
+impl fmt::Display for SceneStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Meshes: {}", self.meshes.len())?;
+        for mesh in &self.meshes {
+            write!(f, "{}", mesh)?;
+        }
+        writeln!(f, "Total triangles: {}", self.num_triangles())?;
+        writeln!(f, "Total surface area: {}", self.surface_area())?;
+        writeln!(f, "Materials: {}", self.num_materials)?;
+        writeln!(f, "Textures: {}", self.textures.len())?;
+        for texture in &self.textures {
+            write!(f, "{}", texture)?;
+        }
+        Ok(())
+    }
+}
+
+/// Statistics of a mesh.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshStats {
+    /// Name.
+    pub name: Option<String>,
+    /// Number of distinct vertex positions.
+    pub num_vertices: usize,
+    /// Number of triangles.
+    pub num_triangles: usize,
+    /// Surface area.
+    pub surface_area: f32,
+    /// Volume.
+    ///
+    /// This is an estimate and is meaningful only if `watertight` is `true`.
+    pub volume: f32,
+    /// Whether the mesh is watertight.
+    pub watertight: bool,
+    /// Number of edges of only one triangle.
+    pub open_edges: usize,
+    /// Number of edges shared by more than two triangles.
+    pub non_manifold_edges: usize,
+    /// Number of triangles at the same positions as another triangle.
+    pub duplicate_faces: usize,
+    /// Size of the bounding box.
+    pub dimensions: Option<Vector3<f32>>,
+}
+
+impl MeshStats {
+    /// Computes statistics of the mesh.
+    pub(crate) fn new(mesh: &Mesh, geometry: &GeometryMesh) -> Self {
+        let num_triangles = geometry.num_triangles();
+        let topology = geometry.topology();
+        Self {
+            name: mesh.name.clone(),
+            num_vertices: geometry.num_distinct_positions(),
+            num_triangles,
+            surface_area: geometry.surface_area(),
+            volume: geometry.volume(),
+            watertight: num_triangles != 0 && topology.is_watertight(),
+            open_edges: topology.open_edges.len(),
+            non_manifold_edges: topology.non_manifold_edges.len(),
+            duplicate_faces: topology.duplicate_faces.len(),
+            dimensions: geometry.bbox_mesh().bounding_box().map(|bbox| bbox.size()),
+        }
+    }
+}
+
+impl fmt::Display for MeshStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "    {:?}", self.name.as_deref().unwrap_or_default())?;
+        writeln!(f, "        vertices: {}", self.num_vertices)?;
+        writeln!(f, "        triangles: {}", self.num_triangles)?;
+        writeln!(f, "        surface area: {}", self.surface_area)?;
+        if self.watertight {
+            writeln!(f, "        volume: {}", self.volume)?;
+        } else {
+            writeln!(f, "        volume: {} (not watertight)", self.volume)?;
+        }
+        writeln!(
+            f,
+            "        open edges: {}, non-manifold edges: {}, duplicate faces: {}",
+            self.open_edges, self.non_manifold_edges, self.duplicate_faces
+        )?;
+        match self.dimensions {
+            Some(size) => writeln!(
+                f,
+                "        dimensions: {} x {} x {}",
+                size.x, size.y, size.z
+            ),
+            None => writeln!(f, "        dimensions: (empty)"),
+        }
+    }
+}
+
+/// Statistics of a texture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextureStats {
+    /// Name.
+    pub name: Option<String>,
+    /// Width and height, or `None` if the image is broken.
+    pub dimensions: Option<(u32, u32)>,
+}
+
+impl TextureStats {
+    /// Computes statistics of the texture.
+    pub(crate) fn new(texture: &Texture) -> Self {
+        Self {
+            name: texture.name.clone(),
+            dimensions: texture.image.dimensions().ok(),
+        }
+    }
+}
+
+impl fmt::Display for TextureStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.name.as_deref().unwrap_or_default();
+        match self.dimensions {
+            Some((width, height)) => writeln!(f, "    {:?}: {} x {}", name, width, height),
+            None => writeln!(f, "    {:?}: (broken image)", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::{Matrix4, Point3, SquareMatrix};
+
+    use crate::data::{GeometryMeshIndex, Scene};
+
+    /// Returns a mesh of the geometry mesh with the identity transform.
+    fn mesh(geometry_mesh_index: GeometryMeshIndex) -> Mesh {
+        Mesh {
+            name: Some("mesh".to_owned()),
+            geometry_mesh_index,
+            materials: Vec::new(),
+            transform: Matrix4::identity(),
+            node: None,
+            render_order: 0,
+        }
+    }
+
+    /// Returns a geometry mesh of the triangles.
+    fn geometry(positions: Vec<Point3<f32>>, indices: Vec<u32>) -> GeometryMesh {
+        let num_triangles = indices.len() as u32 / 3;
+        GeometryMesh {
+            name: None,
+            positions,
+            normals: Vec::new(),
+            uv: Vec::new(),
+            colors: Vec::new(),
+            indices_per_material: vec![indices],
+            polygons_per_material: vec![(0..num_triangles).collect()],
+        }
+    }
+
+    /// Returns a geometry mesh of the unit cube with the triangles facing
+    /// outward.
+    fn unit_cube() -> GeometryMesh {
+        // The index of each corner is `x + 2y + 4z`.
+        let positions = (0..8)
+            .map(|i| Point3::new((i & 1) as f32, ((i >> 1) & 1) as f32, (i >> 2) as f32))
+            .collect();
+        let quads: [[u32; 4]; 6] = [
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+        ];
+        let indices = quads
+            .iter()
+            .flat_map(|&[a, b, c, d]| [a, b, c, a, c, d])
+            .collect();
+        geometry(positions, indices)
+    }
+
+    /// Tests the statistics of a closed mesh.
+    #[test]
+    fn unit_cube_stats() {
+        let mut scene = Scene::new();
+        let index = scene.add_geometry_mesh(unit_cube());
+        let stats = MeshStats::new(&mesh(index), scene.geometry_mesh(index).unwrap());
+
+        assert_eq!(stats.num_vertices, 8);
+        assert_eq!(stats.num_triangles, 12);
+        assert!((stats.surface_area - 6.0).abs() < 1e-6, "{:?}", stats);
+        assert!((stats.volume - 1.0).abs() < 1e-6, "{:?}", stats);
+        assert!(stats.watertight);
+        assert_eq!(stats.open_edges, 0);
+        assert_eq!(stats.non_manifold_edges, 0);
+        assert_eq!(stats.duplicate_faces, 0);
+        assert_eq!(stats.dimensions, Some(Vector3::new(1.0, 1.0, 1.0)));
+    }
+
+    /// Tests that the boundary edges of an open mesh are counted.
+    #[test]
+    fn single_quad_stats() {
+        let mut scene = Scene::new();
+        let positions = vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        ];
+        let index = scene.add_geometry_mesh(geometry(positions, vec![0, 1, 2, 0, 2, 3]));
+        let stats = MeshStats::new(&mesh(index), scene.geometry_mesh(index).unwrap());
+
+        assert_eq!(stats.num_triangles, 2);
+        assert!((stats.surface_area - 1.0).abs() < 1e-6, "{:?}", stats);
+        assert!(!stats.watertight);
+        // The diagonal is shared by the two triangles.
+        assert_eq!(stats.open_edges, 4);
+        assert_eq!(stats.non_manifold_edges, 0);
+    }
+
+    /// Tests that meshes with missing geometry meshes are not skipped.
+    #[test]
+    fn missing_geometry() {
+        let mut other = Scene::new();
+        let foreign = other.add_geometry_mesh(unit_cube());
+
+        let mut scene = Scene::new();
+        let index = scene.add_geometry_mesh(unit_cube());
+        scene.add_mesh(mesh(index));
+        assert_eq!(scene.stats().unwrap().meshes.len(), 1);
+
+        scene.add_mesh(mesh(foreign));
+        assert!(scene.stats().is_err());
+    }
+}
@@ -1,8 +1,16 @@
 //! Texture.
 
-use std::fmt;
+use std::{
+    fmt,
+    io::Cursor,
+    sync::{Arc, OnceLock},
+};
 
-use image::DynamicImage;
+use anyhow::{anyhow, Context};
+use image::{DynamicImage, ImageFormat};
+use tracing::debug_span;
+
+use crate::{util::texture_cache, TextureCacheMode};
 
 /// Texture.
 #[derive(Clone)]
@@ -10,10 +18,13 @@ pub struct Texture {
     /// Name.
     pub name: Option<String>,
     /// Image.
-    pub image: DynamicImage,
+    pub image: TextureImage,
     /// Whether the texture can be transparent.
     ///
     /// If `false`, the texture can be assumed to have no transparent texels.
+    /// This is inferred from the material slot of the texture, and is
+    /// cleared on load if the image is already decoded and has no transparent
+    /// texels (see [`TextureImage::is_opaque`]).
     pub transparent: bool,
     /// Wrap mode for U axis.
     pub wrap_mode_u: WrapMode,
@@ -36,16 +47,16 @@ impl fmt::Debug for Texture {
             color: image::ColorType,
         }
 
+        // Debug output should not decode the image.
+        let image = self.image.decoded().map(|image| ImageInfo {
+            width: image.width(),
+            height: image.height(),
+            color: image.color(),
+        });
+
         f.debug_struct("Texture")
             .field("name", &self.name)
-            .field(
-                "image",
-                &ImageInfo {
-                    width: self.image.width(),
-                    height: self.image.height(),
-                    color: self.image.color(),
-                },
-            )
+            .field("image", &image)
             .field("transparent", &self.transparent)
             .field("wrap_mode_u", &self.wrap_mode_u)
             .field("wrap_mode_v", &self.wrap_mode_v)
@@ -61,3 +72,272 @@ pub enum WrapMode {
     /// Clamp to edge.
     ClampToEdge,
 }
+
+/// Texture image, which may be decoded on first use.
+///
+/// Clones share the decoded image.
+#[derive(Clone)]
+pub struct TextureImage {
+    /// Shared state.
+    inner: Arc<TextureImageInner>,
+}
+
+/// Shared state of [`TextureImage`].
+struct TextureImageInner {
+    /// Encoded image, if the image is decoded lazily.
+    encoded: Option<EncodedImage>,
+    /// Decoded image, or the message of the decode error.
+    decoded: OnceLock<Result<DynamicImage, String>>,
+    /// Whether the decoded image has no transparent texels.
+    opaque: OnceLock<bool>,
+    /// Whether the color components of the decoded image never exceed the
+    /// alpha.
+    within_alpha: OnceLock<bool>,
+}
+
+/// Encoded image data.
+struct EncodedImage {
+    /// Content of the image file.
+    content: Vec<u8>,
+    /// Format of the image, or `None` to guess from the content.
+    format: Option<ImageFormat>,
+    /// Mode of the texture cache to use on decoding.
+    cache: Option<TextureCacheMode>,
+}
+
+impl TextureImage {
+    /// Creates a new `TextureImage` which is decoded on the first access.
+    ///
+    /// If `format` is `None`, the format is guessed from the content.
+    pub fn encoded(
+        content: Vec<u8>,
+        format: Option<ImageFormat>,
+        cache: Option<TextureCacheMode>,
+    ) -> Self {
+        Self {
+            inner: Arc::new(TextureImageInner {
+                encoded: Some(EncodedImage {
+                    content,
+                    format,
+                    cache,
+                }),
+                decoded: OnceLock::new(),
+                opaque: OnceLock::new(),
+                within_alpha: OnceLock::new(),
+            }),
+        }
+    }
+
+    /// Returns the image, decoding it if necessary.
+    ///
+    /// Decode errors are remembered, so the image is decoded at most once.
+    pub fn get(&self) -> anyhow::Result<&DynamicImage> {
+        let decoded = self.inner.decoded.get_or_init(|| {
+            let encoded = self
+                .inner
+                .encoded
+                .as_ref()
+                .expect("Should never fail: images without data are decoded on creation");
+            encoded.decode().map_err(|e| format!("{:#}", e))
+        });
+        decoded.as_ref().map_err(|e| anyhow!("{}", e))
+    }
+
+    /// Returns the image if it is already decoded.
+    pub fn decoded(&self) -> Option<&DynamicImage> {
+        self.inner.decoded.get()?.as_ref().ok()
+    }
+
+    /// Returns the width and the height of the image.
+    ///
+    /// Only the header is read if the image is not decoded yet.
+    pub fn dimensions(&self) -> anyhow::Result<(u32, u32)> {
+        if let Some(image) = self.decoded() {
+            return Ok((image.width(), image.height()));
+        }
+        match &self.inner.encoded {
+            Some(encoded) => encoded.dimensions(),
+            None => self.get().map(|image| (image.width(), image.height())),
+        }
+    }
+
+    /// Returns whether the image has no transparent texels, or `None` if the
+    /// image is not decoded yet.
+    ///
+    /// The image is scanned on the first call after it is decoded, until a
+    /// transparent texel is found.
+    pub fn is_opaque(&self) -> Option<bool> {
+        let image = self.decoded()?;
+        Some(
+            *self
+                .inner
+                .opaque
+                .get_or_init(|| !has_transparent_texels(image)),
+        )
+    }
+
+    /// Returns whether the image looks premultiplied by alpha, or `None` if
+    /// the image is not decoded yet.
+    ///
+    /// Images with transparent texels are considered premultiplied if no
+    /// color component of any texel exceeds its alpha.
+    /// Straight alpha images rarely satisfy this, and the ones which do (such
+    /// as cutouts on black) are drawn almost the same either way.
+    pub fn is_premultiplied(&self) -> Option<bool> {
+        if self.is_opaque()? {
+            return Some(false);
+        }
+        let image = self.decoded()?;
+        Some(
+            *self
+                .inner
+                .within_alpha
+                .get_or_init(|| colors_within_alpha(image)),
+        )
+    }
+
+    /// Returns whether the image is already decoded (or failed to decode).
+    pub fn is_decoded(&self) -> bool {
+        self.inner.decoded.get().is_some()
+    }
+}
+
+impl From<DynamicImage> for TextureImage {
+    fn from(image: DynamicImage) -> Self {
+        Self {
+            inner: Arc::new(TextureImageInner {
+                encoded: None,
+                decoded: OnceLock::from(Ok(image)),
+                opaque: OnceLock::new(),
+                within_alpha: OnceLock::new(),
+            }),
+        }
+    }
+}
+
+impl fmt::Debug for TextureImage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TextureImage")
+            .field("decoded", &self.is_decoded())
+            .finish()
+    }
+}
+
+/// Returns whether the image has any texel with alpha below the maximum.
+///
+/// Images without alpha channels are not scanned.
+fn has_transparent_texels(image: &DynamicImage) -> bool {
+    let _span = debug_span!("scan_alpha").entered();
+    match image {
+        DynamicImage::ImageLumaA8(image) => image.pixels().any(|p| p.0[1] < u8::MAX),
+        DynamicImage::ImageRgba8(image) => image.pixels().any(|p| p.0[3] < u8::MAX),
+        DynamicImage::ImageLumaA16(image) => image.pixels().any(|p| p.0[1] < u16::MAX),
+        DynamicImage::ImageRgba16(image) => image.pixels().any(|p| p.0[3] < u16::MAX),
+        DynamicImage::ImageRgba32F(image) => image.pixels().any(|p| p.0[3] < 1.0),
+        image if image.color().has_alpha() => image.to_rgba8().pixels().any(|p| p.0[3] < u8::MAX),
+        _ => false,
+    }
+}
+
+/// Returns whether no color component of any texel exceeds its alpha.
+///
+/// Images are compared in 8 bits per component.
+fn colors_within_alpha(image: &DynamicImage) -> bool {
+    let _span = debug_span!("scan_premultiplied").entered();
+    let within = |[r, g, b, a]: [u8; 4]| r.max(g).max(b) <= a;
+    match image {
+        DynamicImage::ImageRgba8(image) => image.pixels().all(|p| within(p.0)),
+        image => image.to_rgba8().pixels().all(|p| within(p.0)),
+    }
+}
+
+impl EncodedImage {
+    /// Reads the width and the height from the header.
+    fn dimensions(&self) -> anyhow::Result<(u32, u32)> {
+        let mut reader = image::io::Reader::new(Cursor::new(&self.content));
+        match self.format {
+            Some(format) => reader.set_format(format),
+            None => {
+                reader = reader
+                    .with_guessed_format()
+                    .context("Failed to guess image format")?
+            }
+        }
+        reader
+            .into_dimensions()
+            .context("Failed to read image dimensions")
+    }
+
+    /// Decodes the image, using the texture cache if enabled.
+    fn decode(&self) -> anyhow::Result<DynamicImage> {
+        let content = &self.content;
+        let decode = || {
+            debug_span!("decode_image", size = content.len()).in_scope(|| match self.format {
+                Some(format) => image::load_from_memory_with_format(content, format)
+                    .with_context(|| format!("Failed to load {:?} image", format)),
+                None => image::load_from_memory(content).context("Failed to load image"),
+            })
+        };
+        match self.cache {
+            Some(mode) => texture_cache::load_or_decode(content, mode, decode),
+            None => decode(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use image::{Rgb, RgbImage, Rgba, RgbaImage};
+
+    #[test]
+    fn opaque_images() {
+        let rgb = RgbImage::from_pixel(4, 4, Rgb([10, 20, 30]));
+        assert_eq!(
+            TextureImage::from(DynamicImage::ImageRgb8(rgb)).is_opaque(),
+            Some(true)
+        );
+
+        let mut rgba = RgbaImage::from_pixel(4, 4, Rgba([10, 20, 30, 255]));
+        assert_eq!(
+            TextureImage::from(DynamicImage::ImageRgba8(rgba.clone())).is_opaque(),
+            Some(true)
+        );
+        rgba.put_pixel(3, 3, Rgba([10, 20, 30, 254]));
+        assert_eq!(
+            TextureImage::from(DynamicImage::ImageRgba8(rgba)).is_opaque(),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn premultiplied_images() {
+        let mut rgba = RgbaImage::from_pixel(4, 4, Rgba([100, 50, 0, 128]));
+        rgba.put_pixel(0, 0, Rgba([0, 0, 0, 0]));
+        assert_eq!(
+            TextureImage::from(DynamicImage::ImageRgba8(rgba.clone())).is_premultiplied(),
+            Some(true)
+        );
+        // A bright color with low alpha is straight.
+        rgba.put_pixel(1, 0, Rgba([255, 255, 255, 16]));
+        assert_eq!(
+            TextureImage::from(DynamicImage::ImageRgba8(rgba)).is_premultiplied(),
+            Some(false)
+        );
+        // Opaque images are not premultiplied, even if dark.
+        let opaque = RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 255]));
+        assert_eq!(
+            TextureImage::from(DynamicImage::ImageRgba8(opaque)).is_premultiplied(),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn undecoded_image_is_not_scanned() {
+        let image = TextureImage::encoded(Vec::new(), Some(ImageFormat::Png), None);
+        assert_eq!(image.is_opaque(), None);
+        assert_eq!(image.is_premultiplied(), None);
+        assert!(!image.is_decoded());
+    }
+}
@@ -0,0 +1,18 @@
+//! Load warning.
+
+use std::fmt;
+
+/// Failure on loading an object, which is skipped in the scene.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadWarning {
+    /// Description of the object, such as the kind and the name.
+    pub object: String,
+    /// Error message.
+    pub message: String,
+}
+
+impl fmt::Display for LoadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.object, self.message)
+    }
+}
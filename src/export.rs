@@ -0,0 +1,5 @@
+//! Exporters.
+
+pub mod ply;
+pub mod stl;
+pub mod usd;
@@ -0,0 +1,304 @@
+//! PLY export.
+//!
+//! All meshes in the scene are merged into a single binary PLY mesh, with the
+//! transforms of the meshes baked into the vertices.
+//! Vertex colors are the diffuse colors of the materials, since `data::Scene`
+//! has no vertex colors.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Context};
+use cgmath::{Point2, Point3, Vector3, Zero};
+use rgb::{ComponentMap, RGB};
+
+use crate::data::Scene;
+
+/// Color of vertices without materials.
+const DEFAULT_COLOR: RGB<u8> = RGB::new(255, 255, 255);
+
+/// Writes the merged meshes of the scene to the file in binary PLY format.
+pub fn write_ply(scene: &Scene, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    write_ply_impl(scene, path.as_ref())
+}
+
+/// Non-generic part of [`write_ply`].
+fn write_ply_impl(scene: &Scene, path: &Path) -> anyhow::Result<()> {
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create PLY file {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    write(&mut writer, scene)?;
+    writer
+        .flush()
+        .with_context(|| format!("Failed to write PLY file {}", path.display()))?;
+
+    Ok(())
+}
+
+/// Writes the merged meshes of the scene in binary PLY format.
+pub fn write(w: &mut impl Write, scene: &Scene) -> anyhow::Result<()> {
+    let mesh = MergedMesh::new(scene)?;
+
+    writeln!(w, "ply")?;
+    writeln!(w, "format binary_little_endian 1.0")?;
+    writeln!(w, "comment Exported by fbx-viewer")?;
+    writeln!(w, "element vertex {}", mesh.positions.len())?;
+    for property in &["x", "y", "z", "nx", "ny", "nz"] {
+        writeln!(w, "property float {}", property)?;
+    }
+    for property in &["red", "green", "blue"] {
+        writeln!(w, "property uchar {}", property)?;
+    }
+    for property in &["s", "t"] {
+        writeln!(w, "property float {}", property)?;
+    }
+    writeln!(w, "element face {}", mesh.indices.len() / 3)?;
+    writeln!(w, "property list uchar int vertex_indices")?;
+    writeln!(w, "end_header")?;
+
+    for (((position, normal), color), uv) in mesh
+        .positions
+        .iter()
+        .zip(&mesh.normals)
+        .zip(&mesh.colors)
+        .zip(&mesh.uv)
+    {
+        let floats = [
+            position.x, position.y, position.z, normal.x, normal.y, normal.z,
+        ];
+        for v in &floats {
+            w.write_all(&v.to_le_bytes())?;
+        }
+        w.write_all(&[color.r, color.g, color.b])?;
+        for v in &[uv.x, uv.y] {
+            w.write_all(&v.to_le_bytes())?;
+        }
+    }
+    for tri in mesh.indices.chunks_exact(3) {
+        w.write_all(&[3])?;
+        for &i in tri {
+            w.write_all(&(i as i32).to_le_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Mesh merged from all meshes in a scene.
+struct MergedMesh {
+    /// Positions.
+    positions: Vec<Point3<f32>>,
+    /// Normals.
+    normals: Vec<Vector3<f32>>,
+    /// UV.
+    uv: Vec<Point2<f32>>,
+    /// Vertex colors.
+    colors: Vec<RGB<u8>>,
+    /// Triangle indices.
+    indices: Vec<u32>,
+}
+
+impl MergedMesh {
+    /// Merges the meshes in the scene.
+    fn new(scene: &Scene) -> anyhow::Result<Self> {
+        let mut merged = Self {
+            positions: Vec::new(),
+            normals: Vec::new(),
+            uv: Vec::new(),
+            colors: Vec::new(),
+            indices: Vec::new(),
+        };
+
+        for mesh in scene.meshes() {
+            let geometry = scene.world_geometry_mesh(mesh)?;
+            let num_vertices = geometry.positions.len();
+            // Vertex indices are written as signed integers.
+            let base = merged.positions.len();
+            if base + num_vertices > i32::MAX as usize {
+                bail!("Too many vertices for PLY: {}", base + num_vertices);
+            }
+            let base = base as u32;
+
+            merged.positions.extend(&geometry.positions);
+            // Missing attributes are filled with zeros.
+            if geometry.normals.len() == num_vertices {
+                merged.normals.extend(&geometry.normals);
+            } else {
+                merged
+                    .normals
+                    .extend(std::iter::repeat_n(Vector3::zero(), num_vertices));
+            }
+            if geometry.uv.len() == num_vertices {
+                merged.uv.extend(&geometry.uv);
+            } else {
+                merged
+                    .uv
+                    .extend(std::iter::repeat_n(Point2::new(0.0, 0.0), num_vertices));
+            }
+
+            let mut colors = vec![DEFAULT_COLOR; num_vertices];
+            for (submesh_i, indices) in geometry.indices_per_material.iter().enumerate() {
+                let color = match mesh.materials.get(submesh_i) {
+                    Some(&material_i) => {
+                        to_rgb8(scene.material(material_i)?.data.lambert().diffuse)
+                    }
+                    None => DEFAULT_COLOR,
+                };
+                for &i in indices {
+                    colors[i as usize] = color;
+                }
+                merged.indices.extend(indices.iter().map(|&i| base + i));
+            }
+            merged.colors.extend(colors);
+        }
+
+        Ok(merged)
+    }
+}
+
+/// Converts the color to 8-bit color.
+fn to_rgb8(color: RGB<f32>) -> RGB<u8> {
+    color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::{Matrix4, SquareMatrix};
+
+    use crate::data::{GeometryMesh, LambertData, Material, MaterialIndex, Mesh, ShadingData};
+
+    /// Size of a vertex record in bytes.
+    const VERTEX_SIZE: usize = 6 * 4 + 3 + 2 * 4;
+
+    /// Size of a face record in bytes.
+    const FACE_SIZE: usize = 1 + 3 * 4;
+
+    /// Adds a mesh of a triangle to the scene.
+    fn add_triangle(scene: &mut Scene, z: f32, attributes: bool, materials: Vec<MaterialIndex>) {
+        let geometry = scene.add_geometry_mesh(GeometryMesh {
+            name: None,
+            positions: vec![
+                Point3::new(0.0, 0.0, z),
+                Point3::new(1.0, 0.0, z),
+                Point3::new(0.0, 1.0, z),
+            ],
+            normals: if attributes {
+                vec![Vector3::new(0.0, 0.0, 1.0); 3]
+            } else {
+                Vec::new()
+            },
+            uv: if attributes {
+                vec![
+                    Point2::new(0.0, 0.0),
+                    Point2::new(1.0, 0.0),
+                    Point2::new(0.0, 1.0),
+                ]
+            } else {
+                Vec::new()
+            },
+            colors: Vec::new(),
+            indices_per_material: vec![vec![0, 1, 2]],
+            polygons_per_material: vec![vec![0]],
+        });
+        scene.add_mesh(Mesh {
+            name: None,
+            geometry_mesh_index: geometry,
+            materials,
+            transform: Matrix4::identity(),
+            node: None,
+            render_order: 0,
+        });
+    }
+
+    /// Returns the little endian `f32` values in the bytes.
+    fn floats(bytes: &[u8]) -> Vec<f32> {
+        bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect()
+    }
+
+    /// Tests that the meshes are merged with their attributes.
+    #[test]
+    fn merged_meshes() -> anyhow::Result<()> {
+        let mut scene = Scene::new();
+        let material = scene.add_material(Material {
+            data: ShadingData::Lambert(LambertData {
+                ambient: RGB::new(0.0, 0.0, 0.0),
+                diffuse: RGB::new(1.0, 0.5, 0.0),
+                emissive: RGB::new(0.0, 0.0, 0.0),
+            }),
+            ..Default::default()
+        });
+        add_triangle(&mut scene, 0.0, true, vec![material]);
+        add_triangle(&mut scene, 2.0, false, Vec::new());
+
+        let mut ply = Vec::new();
+        write(&mut ply, &scene)?;
+        let header_end = b"end_header\n";
+        let body_start = ply
+            .windows(header_end.len())
+            .position(|w| w == header_end)
+            .expect("No end of header")
+            + header_end.len();
+        let header = std::str::from_utf8(&ply[..body_start])?;
+        assert_eq!(
+            header.lines().collect::<Vec<_>>(),
+            [
+                "ply",
+                "format binary_little_endian 1.0",
+                "comment Exported by fbx-viewer",
+                "element vertex 6",
+                "property float x",
+                "property float y",
+                "property float z",
+                "property float nx",
+                "property float ny",
+                "property float nz",
+                "property uchar red",
+                "property uchar green",
+                "property uchar blue",
+                "property float s",
+                "property float t",
+                "element face 2",
+                "property list uchar int vertex_indices",
+                "end_header",
+            ]
+        );
+
+        let body = &ply[body_start..];
+        assert_eq!(body.len(), 6 * VERTEX_SIZE + 2 * FACE_SIZE);
+        let vertices = body[..6 * VERTEX_SIZE]
+            .chunks_exact(VERTEX_SIZE)
+            .collect::<Vec<_>>();
+        // The first mesh has the normals, the UV, and the material color.
+        assert_eq!(floats(&vertices[1][..24]), [1.0, 0.0, 0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(vertices[1][24..27], [255, 128, 0]);
+        assert_eq!(floats(&vertices[1][27..]), [1.0, 0.0]);
+        // The second mesh has zero normals and UV, and the default color.
+        assert_eq!(floats(&vertices[4][..24]), [1.0, 0.0, 2.0, 0.0, 0.0, 0.0]);
+        assert_eq!(vertices[4][24..27], [255, 255, 255]);
+        assert_eq!(floats(&vertices[4][27..]), [0.0, 0.0]);
+
+        let faces = body[6 * VERTEX_SIZE..]
+            .chunks_exact(FACE_SIZE)
+            .map(|face| {
+                assert_eq!(face[0], 3);
+                face[1..]
+                    .chunks_exact(4)
+                    .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        // The indices of the second mesh are offset by the vertices of the
+        // first mesh.
+        assert_eq!(faces, [[0, 1, 2], [3, 4, 5]]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+//! STL export.
+//!
+//! All meshes in the scene are merged into a single binary STL solid, with
+//! the transforms of the meshes baked into the positions.
+
+use std::{
+    convert::TryFrom,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{anyhow, Context};
+use cgmath::{InnerSpace, Zero};
+
+use crate::data::Scene;
+
+/// Header of STL files.
+///
+/// This should not start with `solid`, which marks ASCII STL files.
+const HEADER: &[u8] = b"Binary STL exported by fbx-viewer";
+
+/// Writes the merged meshes of the scene to the file in binary STL format.
+pub fn write_stl(scene: &Scene, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    write_stl_impl(scene, path.as_ref())
+}
+
+/// Writes the merged meshes of the scene to the file in binary STL format.
+fn write_stl_impl(scene: &Scene, path: &Path) -> anyhow::Result<()> {
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create STL file {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    write(&mut writer, scene)?;
+    writer
+        .flush()
+        .with_context(|| format!("Failed to write STL file {}", path.display()))?;
+
+    Ok(())
+}
+
+/// Writes the merged meshes of the scene in binary STL format.
+pub fn write(w: &mut impl Write, scene: &Scene) -> anyhow::Result<()> {
+    let geometries = scene
+        .meshes()
+        .map(|mesh| scene.world_geometry_mesh(mesh))
+        .collect::<Result<Vec<_>, _>>()?;
+    let num_triangles = geometries
+        .iter()
+        .map(|geometry| geometry.num_triangles())
+        .sum::<usize>();
+    let num_triangles = u32::try_from(num_triangles)
+        .map_err(|_| anyhow!("Too many triangles for STL: {}", num_triangles))?;
+
+    let mut header = [0_u8; 80];
+    header[..HEADER.len()].copy_from_slice(HEADER);
+    w.write_all(&header)?;
+    w.write_all(&num_triangles.to_le_bytes())?;
+
+    for [p0, p1, p2] in geometries.iter().flat_map(|geometry| geometry.triangles()) {
+        let normal = (p1 - p0).cross(p2 - p0);
+        let normal = if normal.is_zero() {
+            normal
+        } else {
+            normal.normalize()
+        };
+        let floats = [
+            normal.x, normal.y, normal.z, p0.x, p0.y, p0.z, p1.x, p1.y, p1.z, p2.x, p2.y, p2.z,
+        ];
+        for v in &floats {
+            w.write_all(&v.to_le_bytes())?;
+        }
+        // Attribute byte count.
+        w.write_all(&[0; 2])?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::{Matrix4, Point3, Vector3};
+
+    use crate::data::{GeometryMesh, Mesh};
+
+    /// Tests that the mesh transforms are baked, keeping the triangles facing
+    /// outward with mirroring transforms.
+    #[test]
+    fn mirrored_transform() -> anyhow::Result<()> {
+        let mut scene = Scene::new();
+        let geometry = scene.add_geometry_mesh(GeometryMesh {
+            name: None,
+            positions: vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            normals: vec![Vector3::new(0.0, 0.0, 1.0); 3],
+            uv: Vec::new(),
+            colors: Vec::new(),
+            indices_per_material: vec![vec![0, 1, 2]],
+            polygons_per_material: vec![vec![0]],
+        });
+        scene.add_mesh(Mesh {
+            name: None,
+            geometry_mesh_index: geometry,
+            materials: Vec::new(),
+            transform: Matrix4::from_translation(Vector3::new(0.0, 0.0, 2.0))
+                * Matrix4::from_nonuniform_scale(-1.0, 1.0, 1.0),
+            node: None,
+            render_order: 0,
+        });
+
+        let mut stl = Vec::new();
+        write(&mut stl, &scene)?;
+        let floats = stl[84..132]
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect::<Vec<_>>();
+        // Normal, then the vertices in the flipped order.
+        assert_eq!(
+            floats,
+            [0.0, 0.0, 1.0, 0.0, 0.0, 2.0, 0.0, 1.0, 2.0, -1.0, 0.0, 2.0]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,829 @@
+//! USD export.
+//!
+//! Scenes are written as USDA text layers, or as USDZ packages which contain a
+//! USDA layer and the textures.
+//! Nodes are written as nested `Xform` prims with their local transforms, and
+//! meshes are put under the prims of their nodes, or directly under the root
+//! prim if they have no nodes.
+
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    fs::{self, File},
+    io::{self, BufWriter, Cursor, Write},
+    path::Path,
+};
+
+use anyhow::{anyhow, Context};
+use cgmath::{Matrix4, Point2, Point3, SquareMatrix, Vector3};
+use image::ImageOutputFormat;
+use rgb::RGB;
+
+use crate::data::{Material, Mesh, NodeIndex, Scene, WrapMode};
+
+/// Path of the scope of the materials.
+const MATERIALS_PATH: &str = "/Root/Materials";
+
+/// Name of the USDA layer in USDZ packages.
+const USDZ_LAYER_NAME: &str = "scene.usda";
+
+/// Directory of the textures in USDZ packages.
+const USDZ_TEXTURE_DIR: &str = "textures";
+
+/// Writes the scene to the file in USDA format.
+///
+/// Textures are written as PNG files to the `<stem>_textures` directory next
+/// to the file.
+pub fn write_usda(scene: &Scene, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    write_usda_impl(scene, path.as_ref())
+}
+
+/// Writes the scene to the file in USDA format.
+fn write_usda_impl(scene: &Scene, path: &Path) -> anyhow::Result<()> {
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| anyhow!("Invalid USDA file name: {}", path.display()))?;
+    let texture_dir = format!("{}_textures", stem);
+    let layer = Layer::new(scene, &texture_dir)?;
+
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    if !layer.textures.is_empty() {
+        let dir = base_dir.join(&texture_dir);
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create texture directory {}", dir.display()))?;
+    }
+    for (name, data) in &layer.textures {
+        let texture_path = base_dir.join(name);
+        fs::write(&texture_path, data)
+            .with_context(|| format!("Failed to write texture {}", texture_path.display()))?;
+    }
+    fs::write(path, &layer.usda)
+        .with_context(|| format!("Failed to write USDA file {}", path.display()))?;
+
+    Ok(())
+}
+
+/// Writes the scene to the file in USDZ format.
+pub fn write_usdz(scene: &Scene, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    write_usdz_impl(scene, path.as_ref())
+}
+
+/// Writes the scene to the file in USDZ format.
+fn write_usdz_impl(scene: &Scene, path: &Path) -> anyhow::Result<()> {
+    let layer = Layer::new(scene, USDZ_TEXTURE_DIR)?;
+
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create USDZ file {}", path.display()))?;
+    let mut archive = ZipWriter::new(BufWriter::new(file));
+    // The first file in the package is the default layer.
+    archive.add(USDZ_LAYER_NAME, &layer.usda)?;
+    for (name, data) in &layer.textures {
+        archive.add(name, data)?;
+    }
+    archive
+        .finish()?
+        .flush()
+        .with_context(|| format!("Failed to write USDZ file {}", path.display()))?;
+
+    Ok(())
+}
+
+/// USDA layer with the textures it refers to.
+struct Layer {
+    /// Content of the USDA layer.
+    usda: Vec<u8>,
+    /// Relative paths and PNG data of the textures.
+    textures: Vec<(String, Vec<u8>)>,
+}
+
+impl Layer {
+    /// Creates a USDA layer for the scene.
+    ///
+    /// Textures are referred to as PNG files in the given relative directory.
+    fn new(scene: &Scene, texture_dir: &str) -> anyhow::Result<Self> {
+        let textures = scene
+            .textures()
+            .enumerate()
+            .map(|(i, texture)| {
+                let image = texture
+                    .image
+                    .get()
+                    .with_context(|| format!("Failed to decode texture {:?}", texture.name))?;
+                let mut png = Vec::new();
+                image
+                    .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+                    .with_context(|| format!("Failed to encode texture {:?}", texture.name))?;
+                Ok((format!("{}/texture_{}.png", texture_dir, i), png))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut usda = Vec::new();
+        write_layer(&mut usda, scene, &textures)?;
+
+        Ok(Self { usda, textures })
+    }
+}
+
+/// Writes the scene as a USDA layer.
+fn write_layer(
+    w: &mut impl Write,
+    scene: &Scene,
+    textures: &[(String, Vec<u8>)],
+) -> anyhow::Result<()> {
+    writeln!(w, "#usda 1.0")?;
+    writeln!(w, "(")?;
+    writeln!(w, "    defaultPrim = \"Root\"")?;
+    writeln!(w, "    upAxis = \"Y\"")?;
+    writeln!(w, ")")?;
+    writeln!(w)?;
+    writeln!(w, "def Xform \"Root\"")?;
+    writeln!(w, "{{")?;
+
+    let mut material_names = PrimNames::default();
+    let mut material_paths = Vec::new();
+    writeln!(w, "    def Scope \"Materials\"")?;
+    writeln!(w, "    {{")?;
+    for (i, material) in scene.materials().enumerate() {
+        let name = material_names.unique(material.name.as_deref(), "Material", i);
+        let path = format!("{}/{}", MATERIALS_PATH, name);
+        let texture = material
+            .diffuse_texture
+            .map(|index| {
+                let texture = scene.texture(index)?;
+                let path = textures[index.to_usize()].0.as_str();
+                Ok::<_, anyhow::Error>((path, texture.wrap_mode_u, texture.wrap_mode_v))
+            })
+            .transpose()?;
+        write_material(w, &name, &path, material, texture)?;
+        material_paths.push(path);
+    }
+    writeln!(w, "    }}")?;
+
+    PrimWriter::new(scene, &material_paths).write_root(w)?;
+
+    writeln!(w, "}}")?;
+
+    Ok(())
+}
+
+/// Mesh to write, with its index and the transform relative to its parent
+/// prim.
+type PlacedMesh<'a> = (usize, &'a Mesh, Matrix4<f64>);
+
+/// Writer of the prims of the nodes and the meshes.
+struct PrimWriter<'a> {
+    /// Scene.
+    scene: &'a Scene,
+    /// Paths of the materials.
+    material_paths: &'a [String],
+    /// Meshes under the prims of the nodes.
+    node_meshes: HashMap<NodeIndex, Vec<PlacedMesh<'a>>>,
+    /// Meshes directly under the root prim.
+    root_meshes: Vec<PlacedMesh<'a>>,
+}
+
+impl<'a> PrimWriter<'a> {
+    /// Creates a new `PrimWriter`.
+    ///
+    /// Meshes whose nodes are missing or have singular world transforms are
+    /// put directly under the root prim.
+    fn new(scene: &'a Scene, material_paths: &'a [String]) -> Self {
+        let mut node_meshes: HashMap<_, Vec<_>> = HashMap::new();
+        let mut root_meshes = Vec::new();
+        for (i, mesh) in scene.meshes().enumerate() {
+            let transform = mesh
+                .transform
+                .cast::<f64>()
+                .expect("`f32` values should be representable as `f64`");
+            // The prim of the node has the world transform of the node, so
+            // the mesh prim has the rest of the transform of the mesh.
+            let relative = mesh.node.and_then(|node| {
+                let world = scene.world_transform(node).ok()?;
+                Some((node, world.invert()? * transform))
+            });
+            match relative {
+                Some((node, relative)) => node_meshes
+                    .entry(node)
+                    .or_default()
+                    .push((i, mesh, relative)),
+                None => root_meshes.push((i, mesh, transform)),
+            }
+        }
+
+        Self {
+            scene,
+            material_paths,
+            node_meshes,
+            root_meshes,
+        }
+    }
+
+    /// Writes the prims under the root prim.
+    fn write_root(&self, w: &mut impl Write) -> anyhow::Result<()> {
+        let mut names = PrimNames::default();
+        // The scope of the materials is a sibling of the prims.
+        names.unique(Some("Materials"), "", 0);
+        for node in self.scene.root_nodes() {
+            self.write_node(w, 1, &mut names, node)?;
+        }
+        self.write_meshes(w, 1, &mut names, &self.root_meshes)
+    }
+
+    /// Writes the `Xform` prim of the node with its descendants and meshes.
+    fn write_node(
+        &self,
+        w: &mut impl Write,
+        level: usize,
+        names: &mut PrimNames,
+        index: NodeIndex,
+    ) -> anyhow::Result<()> {
+        let node = self.scene.node(index)?;
+        let name = names.unique(node.name.as_deref(), "Node", index.to_usize());
+        let pad = indent(level);
+
+        writeln!(w)?;
+        writeln!(w, "{}def Xform \"{}\"", pad, name)?;
+        writeln!(w, "{}{{", pad)?;
+        write_transform(w, level + 1, &node.local_transform())?;
+        let mut child_names = PrimNames::default();
+        for &child in &node.children {
+            self.write_node(w, level + 1, &mut child_names, child)?;
+        }
+        if let Some(meshes) = self.node_meshes.get(&index) {
+            self.write_meshes(w, level + 1, &mut child_names, meshes)?;
+        }
+        writeln!(w, "{}}}", pad)?;
+
+        Ok(())
+    }
+
+    /// Writes the mesh prims.
+    fn write_meshes(
+        &self,
+        w: &mut impl Write,
+        level: usize,
+        names: &mut PrimNames,
+        meshes: &[PlacedMesh<'_>],
+    ) -> anyhow::Result<()> {
+        for (i, mesh, transform) in meshes {
+            let name = names.unique(mesh.name.as_deref(), "Mesh", *i);
+            let materials = mesh
+                .materials
+                .iter()
+                .map(|index| {
+                    self.scene.material(*index)?;
+                    Ok(self.material_paths[index.to_usize()].as_str())
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            write_mesh(w, level, self.scene, &name, mesh, transform, &materials)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes a `UsdPreviewSurface` material.
+///
+/// `texture` is the relative path and the wrap modes of the diffuse texture.
+fn write_material(
+    w: &mut impl Write,
+    name: &str,
+    path: &str,
+    material: &Material,
+    texture: Option<(&str, WrapMode, WrapMode)>,
+) -> io::Result<()> {
+    let pbr = material.data.to_pbr();
+
+    writeln!(w)?;
+    writeln!(w, "        def Material \"{}\"", name)?;
+    writeln!(w, "        {{")?;
+    writeln!(
+        w,
+        "            token outputs:surface.connect = <{}/PreviewSurface.outputs:surface>",
+        path
+    )?;
+    writeln!(w)?;
+    writeln!(w, "            def Shader \"PreviewSurface\"")?;
+    writeln!(w, "            {{")?;
+    writeln!(
+        w,
+        "                uniform token info:id = \"UsdPreviewSurface\""
+    )?;
+    match texture {
+        Some(_) => writeln!(
+            w,
+            "                color3f inputs:diffuseColor.connect = <{}/DiffuseTexture.outputs:rgb>",
+            path
+        )?,
+        None => writeln!(
+            w,
+            "                color3f inputs:diffuseColor = {}",
+            color(pbr.base_color)
+        )?,
+    }
+    writeln!(
+        w,
+        "                color3f inputs:emissiveColor = {}",
+        color(pbr.emissive)
+    )?;
+    writeln!(
+        w,
+        "                float inputs:roughness = {}",
+        pbr.roughness
+    )?;
+    writeln!(
+        w,
+        "                color3f inputs:specularColor = {}",
+        color(pbr.f0)
+    )?;
+    writeln!(w, "                int inputs:useSpecularWorkflow = 1")?;
+    writeln!(w, "                token outputs:surface")?;
+    writeln!(w, "            }}")?;
+
+    if let Some((file, wrap_u, wrap_v)) = texture {
+        writeln!(w)?;
+        writeln!(w, "            def Shader \"DiffuseTexture\"")?;
+        writeln!(w, "            {{")?;
+        writeln!(
+            w,
+            "                uniform token info:id = \"UsdUVTexture\""
+        )?;
+        writeln!(w, "                asset inputs:file = @{}@", file)?;
+        writeln!(
+            w,
+            "                float2 inputs:st.connect = <{}/UvReader.outputs:result>",
+            path
+        )?;
+        writeln!(
+            w,
+            "                token inputs:wrapS = \"{}\"",
+            wrap(wrap_u)
+        )?;
+        writeln!(
+            w,
+            "                token inputs:wrapT = \"{}\"",
+            wrap(wrap_v)
+        )?;
+        writeln!(w, "                float3 outputs:rgb")?;
+        writeln!(w, "            }}")?;
+        writeln!(w)?;
+        writeln!(w, "            def Shader \"UvReader\"")?;
+        writeln!(w, "            {{")?;
+        writeln!(
+            w,
+            "                uniform token info:id = \"UsdPrimvarReader_float2\""
+        )?;
+        writeln!(w, "                string inputs:varname = \"st\"")?;
+        writeln!(w, "                float2 outputs:result")?;
+        writeln!(w, "            }}")?;
+    }
+
+    writeln!(w, "        }}")?;
+
+    Ok(())
+}
+
+/// Writes a mesh prim at the given indentation level.
+///
+/// `transform` is the transform of the prim relative to its parent prim, and
+/// `materials` are the paths of the materials bound to the submeshes.
+fn write_mesh(
+    w: &mut impl Write,
+    level: usize,
+    scene: &Scene,
+    name: &str,
+    mesh: &Mesh,
+    transform: &Matrix4<f64>,
+    materials: &[&str],
+) -> anyhow::Result<()> {
+    let geometry = scene.geometry_mesh(mesh.geometry_mesh_index)?;
+    let num_triangles = geometry.num_triangles();
+    let pad = indent(level);
+    let inner = indent(level + 1);
+
+    writeln!(w)?;
+    writeln!(w, "{}def Mesh \"{}\" (", pad, name)?;
+    writeln!(w, "{}prepend apiSchemas = [\"MaterialBindingAPI\"]", inner)?;
+    writeln!(w, "{})", pad)?;
+    writeln!(w, "{}{{", pad)?;
+    write_transform(w, level + 1, transform)?;
+    if let Some(bbox) = geometry.bbox_mesh().bounding_box() {
+        writeln!(
+            w,
+            "{}float3[] extent = [{}, {}]",
+            inner,
+            point3(bbox.min()),
+            point3(bbox.max())
+        )?;
+    }
+    writeln!(
+        w,
+        "{}int[] faceVertexCounts = [{}]",
+        inner,
+        join(std::iter::repeat_n("3", num_triangles))
+    )?;
+    writeln!(
+        w,
+        "{}int[] faceVertexIndices = [{}]",
+        inner,
+        join(geometry.indices_per_material.iter().flatten())
+    )?;
+    if geometry.normals.len() == geometry.positions.len() {
+        writeln!(
+            w,
+            "{}normal3f[] normals = [{}] (",
+            inner,
+            join(geometry.normals.iter().map(|&n| vector3(n)))
+        )?;
+        writeln!(w, "{}    interpolation = \"vertex\"", inner)?;
+        writeln!(w, "{})", inner)?;
+    }
+    writeln!(
+        w,
+        "{}point3f[] points = [{}]",
+        inner,
+        join(geometry.positions.iter().map(|&p| point3(p)))
+    )?;
+    if geometry.uv.len() == geometry.positions.len() {
+        writeln!(
+            w,
+            "{}texCoord2f[] primvars:st = [{}] (",
+            inner,
+            join(geometry.uv.iter().map(|&uv| point2(uv)))
+        )?;
+        writeln!(w, "{}    interpolation = \"vertex\"", inner)?;
+        writeln!(w, "{})", inner)?;
+    }
+    writeln!(w, "{}uniform token subdivisionScheme = \"none\"", inner)?;
+
+    match materials {
+        [] => {}
+        [material] => writeln!(w, "{}rel material:binding = <{}>", inner, material)?,
+        _ => {
+            let subset = indent(level + 2);
+            let mut first_face = 0;
+            for (i, (indices, material)) in geometry
+                .indices_per_material
+                .iter()
+                .zip(materials)
+                .enumerate()
+            {
+                let num_faces = indices.len() / 3;
+                writeln!(w)?;
+                writeln!(w, "{}def GeomSubset \"Submesh_{}\" (", inner, i)?;
+                writeln!(w, "{}prepend apiSchemas = [\"MaterialBindingAPI\"]", subset)?;
+                writeln!(w, "{})", inner)?;
+                writeln!(w, "{}{{", inner)?;
+                writeln!(w, "{}uniform token elementType = \"face\"", subset)?;
+                writeln!(w, "{}uniform token familyName = \"materialBind\"", subset)?;
+                writeln!(
+                    w,
+                    "{}int[] indices = [{}]",
+                    subset,
+                    join(first_face..(first_face + num_faces))
+                )?;
+                writeln!(w, "{}rel material:binding = <{}>", subset, material)?;
+                writeln!(w, "{}}}", inner)?;
+                first_face += num_faces;
+            }
+        }
+    }
+
+    writeln!(w, "{}}}", pad)?;
+
+    Ok(())
+}
+
+/// Writes the `xformOp:transform` attribute of a prim, unless the transform
+/// is the identity.
+fn write_transform(w: &mut impl Write, level: usize, m: &Matrix4<f64>) -> io::Result<()> {
+    if *m == Matrix4::identity() {
+        return Ok(());
+    }
+    // USD matrices are row-major with row vectors, so the columns of `m` are
+    // written as the rows.
+    let rows = [m.x, m.y, m.z, m.w]
+        .iter()
+        .map(|c| format!("({}, {}, {}, {})", c.x, c.y, c.z, c.w))
+        .collect::<Vec<_>>();
+    writeln!(
+        w,
+        "{}matrix4d xformOp:transform = ({})",
+        indent(level),
+        rows.join(", ")
+    )?;
+    writeln!(
+        w,
+        "{}uniform token[] xformOpOrder = [\"xformOp:transform\"]",
+        indent(level)
+    )
+}
+
+/// Returns the indentation of the given level.
+fn indent(level: usize) -> String {
+    "    ".repeat(level)
+}
+
+/// Unique prim names among siblings.
+#[derive(Default)]
+struct PrimNames {
+    /// Names already used.
+    used: HashSet<String>,
+}
+
+impl PrimNames {
+    /// Returns a unique valid prim name for the object.
+    ///
+    /// Invalid characters in the name are replaced with `_`, and unnamed
+    /// objects are named `<prefix>_<index>`.
+    fn unique(&mut self, name: Option<&str>, prefix: &str, index: usize) -> String {
+        let base = match name {
+            Some(name) if !name.is_empty() => {
+                let mut sanitized = name
+                    .chars()
+                    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                    .collect::<String>();
+                if sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+                    sanitized.insert(0, '_');
+                }
+                sanitized
+            }
+            _ => format!("{}_{}", prefix, index),
+        };
+        let mut name = base.clone();
+        let mut suffix = 1;
+        while !self.used.insert(name.clone()) {
+            name = format!("{}_{}", base, suffix);
+            suffix += 1;
+        }
+
+        name
+    }
+}
+
+/// Returns the USD representation of the color.
+fn color(c: RGB<f32>) -> String {
+    format!("({}, {}, {})", c.r, c.g, c.b)
+}
+
+/// Returns the USD representation of the point.
+fn point3(p: Point3<f32>) -> String {
+    format!("({}, {}, {})", p.x, p.y, p.z)
+}
+
+/// Returns the USD representation of the vector.
+fn vector3(v: Vector3<f32>) -> String {
+    format!("({}, {}, {})", v.x, v.y, v.z)
+}
+
+/// Returns the USD representation of the 2D point.
+fn point2(p: Point2<f32>) -> String {
+    format!("({}, {})", p.x, p.y)
+}
+
+/// Returns the USD token for the wrap mode.
+fn wrap(mode: WrapMode) -> &'static str {
+    match mode {
+        WrapMode::Repeat => "repeat",
+        WrapMode::ClampToEdge => "clamp",
+    }
+}
+
+/// Joins the values with `, `.
+fn join<T: ToString>(values: impl IntoIterator<Item = T>) -> String {
+    values
+        .into_iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Writer of uncompressed zip archives.
+///
+/// The data of each file is aligned to 64 bytes, as USDZ requires.
+struct ZipWriter<W> {
+    /// Writer.
+    writer: W,
+    /// Number of bytes written.
+    offset: u32,
+    /// Files written.
+    entries: Vec<ZipEntry>,
+}
+
+/// File in a zip archive.
+struct ZipEntry {
+    /// File name.
+    name: String,
+    /// CRC-32 of the data.
+    crc32: u32,
+    /// Size of the data.
+    size: u32,
+    /// Offset of the local file header.
+    offset: u32,
+}
+
+impl<W: Write> ZipWriter<W> {
+    /// Alignment of file data.
+    const ALIGNMENT: u32 = 64;
+    /// Header ID of the extra field used for padding.
+    const PADDING_HEADER_ID: u16 = 0x1986;
+    /// Version needed to extract (2.0).
+    const VERSION: u16 = 20;
+    /// Modification date (1980-01-01 in MS-DOS format).
+    const DATE: u16 = (1 << 5) | 1;
+
+    /// Creates a new `ZipWriter`.
+    fn new(writer: W) -> Self {
+        Self {
+            writer,
+            offset: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds a file.
+    fn add(&mut self, name: &str, data: &[u8]) -> anyhow::Result<()> {
+        let size = u32::try_from(data.len())
+            .map_err(|_| anyhow!("File too large for zip archive: {}", name))?;
+        let name_len = u16::try_from(name.len())
+            .map_err(|_| anyhow!("File name too long for zip archive: {}", name))?;
+
+        // Pad the extra field so that the data starts at an aligned offset.
+        let header_end = self.offset + 30 + u32::from(name_len);
+        let mut padding = (Self::ALIGNMENT - header_end % Self::ALIGNMENT) % Self::ALIGNMENT;
+        if padding != 0 && padding < 4 {
+            padding += Self::ALIGNMENT;
+        }
+        let padding = padding as u16;
+
+        let entry = ZipEntry {
+            name: name.to_owned(),
+            crc32: crc32(data),
+            size,
+            offset: self.offset,
+        };
+        let mut header = Vec::new();
+        header.extend_from_slice(&0x0403_4b50_u32.to_le_bytes());
+        header.extend_from_slice(&Self::VERSION.to_le_bytes());
+        // Flags, compression method (stored), and modification time.
+        header.extend_from_slice(&[0; 6]);
+        header.extend_from_slice(&Self::DATE.to_le_bytes());
+        header.extend_from_slice(&entry.crc32.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&name_len.to_le_bytes());
+        header.extend_from_slice(&padding.to_le_bytes());
+        header.extend_from_slice(name.as_bytes());
+        if padding != 0 {
+            header.extend_from_slice(&Self::PADDING_HEADER_ID.to_le_bytes());
+            header.extend_from_slice(&(padding - 4).to_le_bytes());
+            header.resize(header.len() + usize::from(padding - 4), 0);
+        }
+
+        self.write(&header)?;
+        self.write(data)?;
+        self.entries.push(entry);
+
+        Ok(())
+    }
+
+    /// Writes the central directory and returns the inner writer.
+    fn finish(mut self) -> anyhow::Result<W> {
+        let directory_offset = self.offset;
+        let num_entries = u16::try_from(self.entries.len())
+            .map_err(|_| anyhow!("Too many files for zip archive"))?;
+
+        let mut directory = Vec::new();
+        for entry in &self.entries {
+            directory.extend_from_slice(&0x0201_4b50_u32.to_le_bytes());
+            // Version made by, and version needed to extract.
+            directory.extend_from_slice(&Self::VERSION.to_le_bytes());
+            directory.extend_from_slice(&Self::VERSION.to_le_bytes());
+            // Flags, compression method (stored), and modification time.
+            directory.extend_from_slice(&[0; 6]);
+            directory.extend_from_slice(&Self::DATE.to_le_bytes());
+            directory.extend_from_slice(&entry.crc32.to_le_bytes());
+            directory.extend_from_slice(&entry.size.to_le_bytes());
+            directory.extend_from_slice(&entry.size.to_le_bytes());
+            directory.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+            // Extra field length, comment length, disk number, and internal
+            // and external attributes.
+            directory.extend_from_slice(&[0; 12]);
+            directory.extend_from_slice(&entry.offset.to_le_bytes());
+            directory.extend_from_slice(entry.name.as_bytes());
+        }
+        let directory_size = directory.len() as u32;
+
+        directory.extend_from_slice(&0x0605_4b50_u32.to_le_bytes());
+        // Disk numbers.
+        directory.extend_from_slice(&[0; 4]);
+        directory.extend_from_slice(&num_entries.to_le_bytes());
+        directory.extend_from_slice(&num_entries.to_le_bytes());
+        directory.extend_from_slice(&directory_size.to_le_bytes());
+        directory.extend_from_slice(&directory_offset.to_le_bytes());
+        // Comment length.
+        directory.extend_from_slice(&[0; 2]);
+        self.write(&directory)?;
+
+        Ok(self.writer)
+    }
+
+    /// Writes the bytes and advances the offset.
+    fn write(&mut self, bytes: &[u8]) -> anyhow::Result<()> {
+        self.offset = u32::try_from(bytes.len())
+            .ok()
+            .and_then(|len| self.offset.checked_add(len))
+            .ok_or_else(|| anyhow!("Zip archive too large"))?;
+        self.writer
+            .write_all(bytes)
+            .context("Failed to write zip archive")
+    }
+}
+
+/// Computes CRC-32 (ISO-HDLC) of the data.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0_u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::data::{GeometryMesh, Node};
+
+    /// Tests that the meshes are nested under the prims of the node
+    /// hierarchy.
+    #[test]
+    fn node_hierarchy() -> anyhow::Result<()> {
+        let mut scene = Scene::new();
+        let parent = scene.add_node(Node {
+            name: Some("parent".to_owned()),
+            translation: Vector3::new(0.0, 0.0, 5.0),
+            ..Default::default()
+        })?;
+        let child = scene.add_node(Node {
+            name: Some("child".to_owned()),
+            parent: Some(parent),
+            translation: Vector3::new(1.0, 0.0, 0.0),
+            ..Default::default()
+        })?;
+        let geometry = scene.add_geometry_mesh(GeometryMesh {
+            name: None,
+            positions: vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            normals: Vec::new(),
+            uv: Vec::new(),
+            colors: Vec::new(),
+            indices_per_material: vec![vec![0, 1, 2]],
+            polygons_per_material: vec![vec![0]],
+        });
+        scene.add_mesh(Mesh {
+            name: Some("quad".to_owned()),
+            geometry_mesh_index: geometry,
+            materials: Vec::new(),
+            transform: Matrix4::from_translation(Vector3::new(1.0, 0.0, 5.0)),
+            node: Some(child),
+            render_order: 0,
+        });
+
+        let mut usda = Vec::new();
+        write_layer(&mut usda, &scene, &[])?;
+        let usda = String::from_utf8(usda)?;
+        let lines = usda.lines().collect::<Vec<_>>();
+        let line_of = |line: &str| {
+            lines
+                .iter()
+                .position(|l| *l == line)
+                .unwrap_or_else(|| panic!("No line {:?} in:\n{}", line, usda))
+        };
+
+        let parent_i = line_of("    def Xform \"parent\"");
+        let child_i = line_of("        def Xform \"child\"");
+        let mesh_i = line_of("            def Mesh \"quad\" (");
+        assert!(parent_i < child_i && child_i < mesh_i);
+        assert_eq!(
+            lines[parent_i + 2],
+            "        matrix4d xformOp:transform = \
+             ((1, 0, 0, 0), (0, 1, 0, 0), (0, 0, 1, 0), (0, 0, 5, 1))"
+        );
+        // The transform of the mesh is the one of its node.
+        assert!(!lines[mesh_i..].iter().any(|l| l.contains("xformOp")));
+        Ok(())
+    }
+}
@@ -1,24 +1,232 @@
 //! FBX.
 
-use std::path::Path;
+use std::{
+    io::{self, Cursor, Read, Seek, SeekFrom},
+    path::Path,
+};
 
 use anyhow::bail;
-use fbxcel_dom::any::AnyDocument;
+use fbxcel_dom::{any::AnyDocument, v7400::Document};
+use glob::Pattern;
+use tracing::{info, info_span};
 
-use crate::data::Scene;
+use crate::{data::Scene, util::memory::peak_resident_size, TextureCacheMode};
+
+pub use self::v7400::EmbeddedMedia;
 
 mod v7400;
 
-/// Loads FBX data.
+/// Options for loading FBX data.
+#[derive(Debug, Default, Clone)]
+pub struct LoadOptions {
+    /// Whether to fail on any object which cannot be loaded.
+    ///
+    /// If `false`, such objects are skipped and recorded as warnings of the
+    /// scene (see [`Scene::warnings`]).
+    pub strict: bool,
+    /// Patterns of the names of meshes to load.
+    ///
+    /// If empty, all meshes are loaded.
+    pub include: Vec<Pattern>,
+    /// Patterns of the names of meshes not to load.
+    pub exclude: Vec<Pattern>,
+    /// Whether to skip loading textures.
+    pub no_textures: bool,
+    /// On-disk cache of decoded textures, or `None` to decode all textures.
+    pub texture_cache: Option<TextureCacheMode>,
+    /// Whether to skip loading materials.
+    ///
+    /// If `true`, all meshes use a single default material and no textures
+    /// are loaded.
+    pub no_materials: bool,
+    /// Alpha cutoff of the materials with transparent textures.
+    ///
+    /// If `Some`, such materials are drawn as cutouts (see
+    /// [`Material::alpha_cutoff`][`crate::data::Material::alpha_cutoff`])
+    /// instead of being alpha blended.
+    pub alpha_test: Option<f32>,
+    /// Whether to load each geometry mesh as its bounding box.
+    ///
+    /// If `true`, polygons are not triangulated, attributes such as normals
+    /// and UV are not read, and no textures are loaded.
+    pub bboxes_only: bool,
+    /// Whether to bake the transforms of the model nodes into the geometry
+    /// meshes.
+    ///
+    /// If `false`, the geometry meshes are in their local spaces, and the
+    /// transforms are kept in [`Mesh::transform`][`crate::data::Mesh::transform`].
+    pub flatten: bool,
+}
+
+impl LoadOptions {
+    /// Returns whether the mesh with the given names should be loaded.
+    ///
+    /// Both the model name and the geometry name are matched against the
+    /// patterns, and unnamed objects are matched as empty names.
+    pub fn accepts_mesh(&self, model_name: Option<&str>, geometry_name: Option<&str>) -> bool {
+        let names = [model_name.unwrap_or(""), geometry_name.unwrap_or("")];
+        let matches = |patterns: &[Pattern]| {
+            patterns
+                .iter()
+                .any(|pattern| names.iter().any(|name| pattern.matches(name)))
+        };
+
+        (self.include.is_empty() || matches(&self.include)) && !matches(&self.exclude)
+    }
+}
+
+/// Loads FBX data with the default options.
+///
+/// Objects which cannot be loaded are skipped.
 pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Scene> {
-    load_impl(path.as_ref())
+    load_impl(path.as_ref(), &LoadOptions::default(), &mut |_| {})
+}
+
+/// Loads FBX data with the given options.
+pub fn load_with_options(path: impl AsRef<Path>, options: &LoadOptions) -> anyhow::Result<Scene> {
+    load_impl(path.as_ref(), options, &mut |_| {})
+}
+
+/// Loads FBX data with the given options, reporting the progress of parsing.
+///
+/// `progress` is called with the ratio (from 0 to 1) of the parsed bytes to
+/// the file size.
+pub fn load_with_progress(
+    path: impl AsRef<Path>,
+    options: &LoadOptions,
+    progress: &mut dyn FnMut(f64),
+) -> anyhow::Result<Scene> {
+    load_impl(path.as_ref(), options, progress)
 }
 
 /// Loads FBX data.
-fn load_impl(path: &Path) -> anyhow::Result<Scene> {
-    let file = std::io::BufReader::new(std::fs::File::open(path)?);
-    match AnyDocument::from_seekable_reader(file)? {
-        AnyDocument::V7400(_ver, doc) => v7400::from_doc(doc),
+fn load_impl(
+    path: &Path,
+    options: &LoadOptions,
+    progress: &mut dyn FnMut(f64),
+) -> anyhow::Result<Scene> {
+    let _span = info_span!("load_fbx", path = %path.display()).entered();
+    let doc = parse(path, progress)?;
+    let scene =
+        info_span!("convert_fbx").in_scope(|| v7400::from_doc(doc, options, path.parent()))?;
+    if let Some(size) = peak_resident_size() {
+        info!("Peak memory usage after loading FBX: {} bytes", size);
+    }
+
+    Ok(scene)
+}
+
+/// Loads FBX data from the reader with the default options.
+///
+/// Objects which cannot be loaded are skipped.
+pub fn load_from_reader(reader: impl Read + Seek) -> anyhow::Result<Scene> {
+    load_from_reader_with_options(reader, &LoadOptions::default())
+}
+
+/// Loads FBX data from the reader with the given options.
+pub fn load_from_reader_with_options(
+    reader: impl Read + Seek,
+    options: &LoadOptions,
+) -> anyhow::Result<Scene> {
+    let _span = info_span!("load_fbx").entered();
+    let doc = parse_reader(reader)?;
+    info_span!("convert_fbx").in_scope(|| v7400::from_doc(doc, options, None))
+}
+
+/// Loads FBX data from the bytes with the default options.
+///
+/// Objects which cannot be loaded are skipped.
+pub fn load_from_bytes(bytes: &[u8]) -> anyhow::Result<Scene> {
+    load_from_reader(Cursor::new(bytes))
+}
+
+/// Loads the media (such as texture images) embedded in the FBX file.
+pub fn load_embedded_media(path: impl AsRef<Path>) -> anyhow::Result<Vec<EmbeddedMedia>> {
+    load_embedded_media_impl(path.as_ref())
+}
+
+/// Loads the media embedded in the FBX file.
+fn load_embedded_media_impl(path: &Path) -> anyhow::Result<Vec<EmbeddedMedia>> {
+    let _span = info_span!("load_fbx_embedded_media", path = %path.display()).entered();
+    let doc = parse(path, &mut |_| {})?;
+    Ok(v7400::embedded_media(&doc))
+}
+
+/// Parses the FBX file.
+///
+/// With `mmap` feature, the file is memory-mapped instead of being read through
+/// a buffer.
+fn parse(path: &Path, progress: &mut dyn FnMut(f64)) -> anyhow::Result<Box<Document>> {
+    let file = std::fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    #[cfg(feature = "mmap")]
+    {
+        // SAFETY: The mapping is dropped before returning, and the file is
+        // assumed not to be modified by other processes while parsing.
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        parse_reader(ProgressReader::new(Cursor::new(&mmap[..]), len, progress))
+    }
+    #[cfg(not(feature = "mmap"))]
+    parse_reader(ProgressReader::new(
+        std::io::BufReader::new(file),
+        len,
+        progress,
+    ))
+}
+
+/// Parses the FBX data from the reader.
+fn parse_reader(reader: impl Read + Seek) -> anyhow::Result<Box<Document>> {
+    let doc = info_span!("parse_fbx").in_scope(|| AnyDocument::from_seekable_reader(reader))?;
+    match doc {
+        AnyDocument::V7400(_ver, doc) => Ok(doc),
         _ => bail!("Unknown FBX DOM version"),
     }
 }
+
+/// Reader which reports the position relative to the total length.
+struct ProgressReader<'a, R> {
+    /// Inner reader.
+    inner: R,
+    /// Current position.
+    position: u64,
+    /// Total length.
+    len: u64,
+    /// Callback called with the ratio of the position to the length.
+    progress: &'a mut dyn FnMut(f64),
+}
+
+impl<'a, R> ProgressReader<'a, R> {
+    /// Creates a new `ProgressReader` at the beginning of the data.
+    fn new(inner: R, len: u64, progress: &'a mut dyn FnMut(f64)) -> Self {
+        Self {
+            inner,
+            position: 0,
+            len,
+            progress,
+        }
+    }
+
+    /// Updates the position and reports the progress.
+    fn set_position(&mut self, position: u64) {
+        self.position = position;
+        if self.len > 0 {
+            (self.progress)((position as f64 / self.len as f64).min(1.0));
+        }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.inner.read(buf)?;
+        self.set_position(self.position + size as u64);
+        Ok(size)
+    }
+}
+
+impl<R: Seek> Seek for ProgressReader<'_, R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = self.inner.seek(pos)?;
+        self.set_position(position);
+        Ok(position)
+    }
+}
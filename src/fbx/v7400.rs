@@ -3,39 +3,106 @@
 use std::{collections::HashMap, path::Path};
 
 use anyhow::{anyhow, bail, Context};
-use cgmath::{Point2, Point3, Vector3};
+use cgmath::{Matrix4, Point2, Point3, SquareMatrix, Vector3};
 use fbxcel_dom::v7400::{
     data::{
         material::ShadingModel, mesh::layer::TypedLayerElementHandle,
         texture::WrapMode as RawWrapMode,
     },
-    object::{self, model::TypedModelHandle, ObjectId, TypedObjectHandle},
+    object::{
+        self,
+        material::MaterialProperties,
+        model::{ModelHandle, TypedModelHandle},
+        property::loaders::PrimitiveLoader,
+        video::TypedVideoHandle,
+        ObjectId, TypedObjectHandle,
+    },
     Document,
 };
-use log::{debug, trace};
-use rgb::ComponentMap;
+use rgb::{ComponentMap, RGBA};
+use tracing::{debug, debug_span, trace, warn};
 
 use crate::{
     data::{
-        GeometryMesh, GeometryMeshIndex, LambertData, Material, MaterialIndex, Mesh, MeshIndex,
-        Scene, ShadingData, Texture, TextureIndex, WrapMode,
+        GeometryMesh, GeometryMeshIndex, LambertData, LoadWarning, Material, MaterialIndex, Mesh,
+        MeshIndex, Node, NodeIndex, PhongData, Scene, ShadingData, Texture, TextureImage,
+        TextureIndex, WrapMode,
+    },
+    fbx::LoadOptions,
+    util::{
+        bbox::OptionalBoundingBox3d,
+        iter::{OptionIteratorExt, ResultIteratorExt},
     },
-    util::iter::{OptionIteratorExt, ResultIteratorExt},
 };
 
 use self::triangulator::triangulator;
 
+#[cfg(test)]
+mod fixture;
+mod texture_search;
+mod transform;
 mod triangulator;
 
+/// Name of the user property of the models with the render order.
+const RENDER_ORDER_PROPERTY: &str = "RenderOrder";
+
+/// Maximum depth of the model hierarchy.
+///
+/// Deeper hierarchies are considered cyclic.
+const MAX_NODE_DEPTH: usize = 1024;
+
 /// Loads the data from the document.
-pub fn from_doc(doc: Box<Document>) -> anyhow::Result<Scene> {
-    Loader::new(&doc).load()
+///
+/// External texture files are searched from `base_dir`, which should be the
+/// directory of the FBX file.
+/// If `base_dir` is `None`, only embedded textures are loaded.
+pub fn from_doc(
+    doc: Box<Document>,
+    options: &LoadOptions,
+    base_dir: Option<&Path>,
+) -> anyhow::Result<Scene> {
+    Loader::new(&doc, options, base_dir).load()
+}
+
+/// Media embedded in an FBX file.
+#[derive(Debug, Clone)]
+pub struct EmbeddedMedia {
+    /// Name of the video clip object.
+    pub name: Option<String>,
+    /// Relative filename of the original file.
+    pub relative_filename: Option<String>,
+    /// Content of the file.
+    pub content: Vec<u8>,
+}
+
+/// Returns the media embedded in the document.
+///
+/// Video clips without content (i.e. referring external files) are skipped.
+pub fn embedded_media(doc: &Document) -> Vec<EmbeddedMedia> {
+    doc.objects()
+        .filter_map(|obj| match obj.get_typed() {
+            TypedObjectHandle::Video(TypedVideoHandle::Clip(clip)) => Some(clip),
+            _ => None,
+        })
+        .filter_map(|clip| {
+            let content = clip.content()?;
+            Some(EmbeddedMedia {
+                name: clip.name().map(Into::into),
+                relative_filename: clip.relative_filename().ok().map(Into::into),
+                content: content.to_vec(),
+            })
+        })
+        .collect()
 }
 
 /// FBX data loader.
 pub struct Loader<'a> {
     /// Document.
     doc: &'a Document,
+    /// Options.
+    options: &'a LoadOptions,
+    /// Directory to search external texture files from.
+    base_dir: Option<&'a Path>,
     /// Scene.
     scene: Scene,
     /// Geometry mesh indices.
@@ -44,39 +111,87 @@ pub struct Loader<'a> {
     material_indices: HashMap<ObjectId, MaterialIndex>,
     /// Mesh indices.
     mesh_indices: HashMap<ObjectId, MeshIndex>,
+    /// Node indices.
+    node_indices: HashMap<ObjectId, NodeIndex>,
     /// Texture indices.
     texture_indices: HashMap<ObjectId, TextureIndex>,
+    /// Index of the default material used when materials are not loaded.
+    default_material_index: Option<MaterialIndex>,
 }
 
 impl<'a> Loader<'a> {
     /// Creates a new `Loader`.
-    fn new(doc: &'a Document) -> Self {
+    fn new(doc: &'a Document, options: &'a LoadOptions, base_dir: Option<&'a Path>) -> Self {
         Self {
             doc,
+            options,
+            base_dir,
             scene: Default::default(),
             geometry_mesh_indices: Default::default(),
             material_indices: Default::default(),
             mesh_indices: Default::default(),
+            node_indices: Default::default(),
             texture_indices: Default::default(),
+            default_material_index: None,
         }
     }
 
     /// Loads the document.
     fn load(mut self) -> anyhow::Result<Scene> {
+        // Nodes are loaded for all models, including the filtered meshes, so
+        // that the hierarchy is complete.
+        for obj in self.doc.objects() {
+            if let TypedObjectHandle::Model(model) = obj.get_typed() {
+                if let Err(e) = self.load_node(&model, 0) {
+                    warn!("Failed to load node of {:?}: {:#}", model, e);
+                }
+            }
+        }
         for obj in self.doc.objects() {
             if let TypedObjectHandle::Model(TypedModelHandle::Mesh(mesh)) = obj.get_typed() {
-                self.load_mesh(mesh)?;
+                let geometry_name = mesh.geometry().ok().and_then(|geometry| geometry.name());
+                if !self.options.accepts_mesh(mesh.name(), geometry_name) {
+                    trace!("Skipping filtered mesh: {:?}", mesh);
+                    continue;
+                }
+                let result = self.load_mesh(mesh);
+                self.skip_on_error(result, || format!("Mesh {:?}", mesh.name()))?;
             }
         }
 
         Ok(self.scene)
     }
 
+    /// Returns the loaded value, or records the error as a warning in the
+    /// lenient mode.
+    ///
+    /// Returns the error as is in the strict mode.
+    fn skip_on_error<T>(
+        &mut self,
+        result: anyhow::Result<T>,
+        object: impl FnOnce() -> String,
+    ) -> anyhow::Result<Option<T>> {
+        match result {
+            Ok(v) => Ok(Some(v)),
+            Err(e) if self.options.strict => Err(e),
+            Err(e) => {
+                let warning = LoadWarning {
+                    object: object(),
+                    message: format!("{:#}", e),
+                };
+                warn!("Skipped on load failure: {}", warning);
+                self.scene.add_warning(warning);
+                Ok(None)
+            }
+        }
+    }
+
     /// Loads the geometry.
     fn load_geometry_mesh(
         &mut self,
         mesh_obj: object::geometry::MeshHandle<'a>,
         num_materials: usize,
+        transform: Option<&Matrix4<f32>>,
     ) -> anyhow::Result<GeometryMeshIndex> {
         if let Some(index) = self.geometry_mesh_indices.get(&mesh_obj.object_id()) {
             return Ok(*index);
@@ -84,13 +199,24 @@ impl<'a> Loader<'a> {
 
         debug!("Loading geometry mesh: {:?}", mesh_obj);
 
+        if self.options.bboxes_only {
+            return self.load_geometry_bbox(mesh_obj, num_materials, transform);
+        }
+
         let polygon_vertices = mesh_obj
             .polygon_vertices()
             .context("Failed to get polygon vertices")?;
-        let triangle_pvi_indices = polygon_vertices
-            .triangulate_each(triangulator)
+        // Looking up the last control point of a truncated array panics in
+        // `fbxcel-dom`, so reject such arrays beforehand.
+        let _ = polygon_vertices
+            .raw_control_points()
+            .context("Failed to get control points")?;
+        let triangle_pvi_indices = debug_span!("triangulate")
+            .in_scope(|| polygon_vertices.triangulate_each(triangulator))
             .context("Triangulation failed")?;
 
+        // Attributes are expanded to the vertices of the triangles.
+        let _expand_span = debug_span!("expand_attributes").entered();
         let positions = triangle_pvi_indices
             .iter_control_point_indices()
             .ok_or_else(|| anyhow!("Failed to get control point index"))
@@ -162,8 +288,32 @@ impl<'a> Loader<'a> {
                 .context("Failed to reconstruct UV vertices")?
         };
 
-        let indices_per_material = {
+        // Vertex colors are optional.
+        let colors = match layer.layer_element_entries().find_map(|entry| {
+            match entry.typed_layer_element() {
+                Ok(TypedLayerElementHandle::Color(handle)) => Some(handle),
+                _ => None,
+            }
+        }) {
+            Some(handle) => {
+                let colors = handle.color().context("Failed to get vertex colors")?;
+                triangle_pvi_indices
+                    .triangle_vertex_indices()
+                    .map(|tri_vi| {
+                        colors
+                            .color(&triangle_pvi_indices, tri_vi)
+                            .map(|[r, g, b, a]| RGBA::new(r as f32, g as f32, b as f32, a as f32))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .context("Failed to reconstruct vertex colors")?
+            }
+            None => Vec::new(),
+        };
+
+        // The polygon of each triangle is recorded on triangulation.
+        let (indices_per_material, polygons_per_material) = {
             let mut indices_per_material = vec![Vec::new(); num_materials];
+            let mut polygons_per_material = vec![Vec::new(); num_materials];
             let materials = layer
                 .layer_element_entries()
                 .filter_map(|entry| match entry.typed_layer_element() {
@@ -179,8 +329,9 @@ impl<'a> Loader<'a> {
                     .material_index(&triangle_pvi_indices, tri_vi)
                     .context("Failed to get mesh-local material index")?
                     .to_u32();
+                let local_material_index = local_material_index as usize;
                 indices_per_material
-                    .get_mut(local_material_index as usize)
+                    .get_mut(local_material_index)
                     .ok_or_else(|| {
                         anyhow!(
                             "Mesh-local material index out of range: num_materials={:?}, got={:?}",
@@ -189,8 +340,16 @@ impl<'a> Loader<'a> {
                         )
                     })?
                     .push(tri_vi.to_usize() as u32);
+                if tri_vi.to_usize() % 3 == 0 {
+                    let polygon = triangle_pvi_indices
+                        .polygon_index(tri_vi.triangle_index())
+                        .ok_or_else(|| {
+                            anyhow!("Failed to get polygon index: tri_vi={:?}", tri_vi)
+                        })?;
+                    polygons_per_material[local_material_index].push(polygon.to_usize() as u32);
+                }
             }
-            indices_per_material
+            (indices_per_material, polygons_per_material)
         };
 
         if positions.len() != normals.len() {
@@ -208,19 +367,79 @@ impl<'a> Loader<'a> {
             );
         }
 
-        let mesh = GeometryMesh {
+        if !colors.is_empty() && positions.len() != colors.len() {
+            bail!(
+                "Vertices length mismatch: positions.len={:?}, colors.len={:?}",
+                positions.len(),
+                colors.len()
+            );
+        }
+
+        let mut mesh = GeometryMesh {
             name: mesh_obj.name().map(Into::into),
             positions,
             normals,
             uv,
+            colors,
             indices_per_material,
+            polygons_per_material,
         };
+        let removed = mesh.remove_degenerate_triangles();
+        if removed.total() != 0 {
+            warn!(
+                "Removed degenerate triangles from geometry mesh {:?}: \
+                 duplicate indices = {}, zero area = {}",
+                mesh.name, removed.duplicate_indices, removed.zero_area
+            );
+        }
+        if let Some(transform) = transform {
+            mesh.transform(transform);
+        }
 
         debug!("Successfully loaded geometry mesh: {:?}", mesh_obj);
 
         Ok(self.scene.add_geometry_mesh(mesh))
     }
 
+    /// Loads the geometry mesh as its bounding box.
+    ///
+    /// The bounding box is the one of the transformed control points.
+    fn load_geometry_bbox(
+        &mut self,
+        mesh_obj: object::geometry::MeshHandle<'a>,
+        num_materials: usize,
+        transform: Option<&Matrix4<f32>>,
+    ) -> anyhow::Result<GeometryMeshIndex> {
+        let bbox = mesh_obj
+            .polygon_vertices()
+            .context("Failed to get polygon vertices")?
+            .raw_control_points()
+            .context("Failed to get control points")?
+            .map(|p| {
+                Point3::from(p).cast().ok_or_else(|| {
+                    anyhow!("Failed to convert floating point values: point={:?}", p)
+                })
+            })
+            .map(|p| {
+                p.map(|p: Point3<f32>| match transform {
+                    Some(transform) => Point3::from_homogeneous(transform * p.to_homogeneous()),
+                    None => p,
+                })
+            })
+            .collect::<anyhow::Result<OptionalBoundingBox3d<f32>>>()?
+            .bounding_box()
+            .ok_or_else(|| anyhow!("No control points"))?;
+        let mesh =
+            GeometryMesh::from_bounding_box(mesh_obj.name().map(Into::into), &bbox, num_materials);
+
+        debug!(
+            "Successfully loaded bounding box of geometry mesh: {:?}",
+            mesh_obj
+        );
+
+        Ok(self.scene.add_geometry_mesh(mesh))
+    }
+
     /// Loads the material.
     fn load_material(
         &mut self,
@@ -236,52 +455,62 @@ impl<'a> Loader<'a> {
             .transparent_texture()
             .map(|v| (true, v))
             .or_else(|| material_obj.diffuse_texture().map(|v| (false, v)))
+            .filter(|_| !self.options.no_textures && !self.options.bboxes_only)
             .map(|(transparent, texture_obj)| {
-                self.load_texture(texture_obj, transparent)
-                    .context("Failed to load diffuse texture")
+                let result = self
+                    .load_texture(texture_obj, transparent)
+                    .context("Failed to load diffuse texture");
+                self.skip_on_error(result, || format!("Texture {:?}", texture_obj.name()))
             })
-            .transpose()?;
+            .transpose()?
+            .flatten();
 
         let properties = material_obj.properties();
         let shading_data = match properties
             .shading_model_or_default()
             .context("Failed to get shading model")?
         {
-            ShadingModel::Lambert | ShadingModel::Phong => {
-                let ambient_color = properties
-                    .ambient_color_or_default()
-                    .context("Failed to get ambient color")?;
-                let ambient_factor = properties
-                    .ambient_factor_or_default()
-                    .context("Failed to get ambient factor")?;
-                let ambient = (ambient_color * ambient_factor).map(|v| v as f32);
-                let diffuse_color = properties
-                    .diffuse_color_or_default()
-                    .context("Failed to get diffuse color")?;
-                let diffuse_factor = properties
-                    .diffuse_factor_or_default()
-                    .context("Failed to get diffuse factor")?;
-                let diffuse = (diffuse_color * diffuse_factor).map(|v| v as f32);
-                let emissive_color = properties
-                    .emissive_color_or_default()
-                    .context("Failed to get emissive color")?;
-                let emissive_factor = properties
-                    .emissive_factor_or_default()
-                    .context("Failed to get emissive factor")?;
-                let emissive = (emissive_color * emissive_factor).map(|v| v as f32);
-                ShadingData::Lambert(LambertData {
-                    ambient,
-                    diffuse,
-                    emissive,
+            ShadingModel::Lambert => ShadingData::Lambert(load_lambert_data(&properties)?),
+            ShadingModel::Phong => {
+                let lambert = load_lambert_data(&properties)?;
+                let specular_color = properties
+                    .specular_or_default()
+                    .context("Failed to get specular color")?;
+                let specular_factor = properties
+                    .specular_factor_or_default()
+                    .context("Failed to get specular factor")?;
+                let specular = (specular_color * specular_factor).map(|v| v as f32);
+                let shininess = properties
+                    .shininess_or_default()
+                    .context("Failed to get shininess")? as f32;
+                let reflection_color = properties
+                    .reflection_or_default()
+                    .context("Failed to get reflection color")?;
+                let reflection_factor = properties
+                    .reflection_factor_or_default()
+                    .context("Failed to get reflection factor")?;
+                let reflection = (reflection_color * reflection_factor).map(|v| v as f32);
+                ShadingData::Phong(PhongData {
+                    lambert,
+                    specular,
+                    shininess,
+                    reflection,
                 })
             }
             v => bail!("Unknown shading model: {:?}", v),
         };
 
+        // FBX materials have no standard alpha cutoff.
+        let transparent = diffuse_texture
+            .and_then(|index| self.scene.texture(index).ok())
+            .is_some_and(|texture| texture.transparent);
         let material = Material {
             name: material_obj.name().map(Into::into),
             diffuse_texture,
             data: shading_data,
+            // FBX materials have no standard flag for back-face culling.
+            double_sided: true,
+            alpha_cutoff: self.options.alpha_test.filter(|_| transparent),
         };
 
         debug!("Successfully loaded material: {:?}", material_obj);
@@ -299,20 +528,54 @@ impl<'a> Loader<'a> {
 
         let geometry_obj = mesh_obj.geometry().context("Failed to get geometry")?;
 
-        let materials = mesh_obj
-            .materials()
-            .map(|material_obj| self.load_material(material_obj))
-            .collect::<anyhow::Result<Vec<_>>>()
-            .context("Failed to load materials for mesh")?;
+        let materials = if self.options.no_materials {
+            let scene = &mut self.scene;
+            let default_material_index = *self
+                .default_material_index
+                .get_or_insert_with(|| scene.add_material(Material::default()));
+            vec![default_material_index; mesh_obj.materials().count()]
+        } else {
+            mesh_obj
+                .materials()
+                .map(|material_obj| self.load_material(material_obj))
+                .collect::<anyhow::Result<Vec<_>>>()
+                .context("Failed to load materials for mesh")?
+        };
 
+        let (node, transform) = match self.load_mesh_node(&mesh_obj) {
+            Ok((node, transform)) => (Some(node), transform),
+            // The transform is only informational unless it is baked.
+            Err(e) if !self.options.flatten => {
+                warn!("Failed to get transform of {:?}: {:#}", mesh_obj, e);
+                let node = self.node_indices.get(&mesh_obj.object_id()).copied();
+                (node, Matrix4::identity())
+            }
+            Err(e) => return Err(e.context("Failed to get transform")),
+        };
+        let baked = Some(transform).filter(|_| self.options.flatten);
         let geometry_index = self
-            .load_geometry_mesh(geometry_obj, materials.len())
+            .load_geometry_mesh(geometry_obj, materials.len(), baked.as_ref())
             .context("Failed to load geometry mesh")?;
 
+        let render_order = match load_render_order(&mesh_obj) {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("Failed to get render order of {:?}: {:#}", mesh_obj, e);
+                0
+            }
+        };
+
         let mesh = Mesh {
             name: mesh_obj.name().map(Into::into),
             geometry_mesh_index: geometry_index,
             materials,
+            transform: if baked.is_some() {
+                Matrix4::identity()
+            } else {
+                transform
+            },
+            node,
+            render_order,
         };
 
         debug!("Successfully loaded mesh: {:?}", mesh_obj);
@@ -320,6 +583,47 @@ impl<'a> Loader<'a> {
         Ok(self.scene.add_mesh(mesh))
     }
 
+    /// Loads the node of the mesh, and returns it with the transform from the
+    /// geometry space of the mesh to the world space.
+    fn load_mesh_node(
+        &mut self,
+        mesh_obj: &ModelHandle<'a>,
+    ) -> anyhow::Result<(NodeIndex, Matrix4<f32>)> {
+        let node = self.load_node(mesh_obj, 0)?;
+        let transform =
+            self.scene.world_transform(node)? * transform::geometric_transform(mesh_obj)?;
+        let transform = transform
+            .cast::<f32>()
+            .ok_or_else(|| anyhow!("Failed to convert floating point values of transform"))?;
+
+        Ok((node, transform))
+    }
+
+    /// Loads the node of the model, after the nodes of its ancestors.
+    fn load_node(&mut self, model: &ModelHandle<'a>, depth: usize) -> anyhow::Result<NodeIndex> {
+        if let Some(index) = self.node_indices.get(&model.object_id()) {
+            return Ok(*index);
+        }
+        if depth > MAX_NODE_DEPTH {
+            bail!("Model hierarchy is too deep or cyclic");
+        }
+
+        let parent = match model.parent_model() {
+            Some(parent) => Some(self.load_node(&parent, depth + 1)?),
+            None => None,
+        };
+        let node = Node {
+            name: model.name().map(Into::into),
+            parent,
+            ..transform::load_node_transform(model)
+                .with_context(|| format!("Failed to load transform of {:?}", model.name()))?
+        };
+        let index = self.scene.add_node(node)?;
+        self.node_indices.insert(model.object_id(), index);
+
+        Ok(index)
+    }
+
     /// Loads the texture.
     fn load_texture(
         &mut self,
@@ -360,8 +664,8 @@ impl<'a> Loader<'a> {
 
         let texture = Texture {
             name: texture_obj.name().map(Into::into),
+            transparent: transparent && image.is_opaque() != Some(true),
             image,
-            transparent,
             wrap_mode_u,
             wrap_mode_v,
         };
@@ -372,10 +676,13 @@ impl<'a> Loader<'a> {
     }
 
     /// Loads the texture image.
+    ///
+    /// The image is decoded on first use, except in the strict mode where
+    /// decode errors should fail the load.
     fn load_video_clip(
         &mut self,
         video_clip_obj: object::video::ClipHandle<'a>,
-    ) -> anyhow::Result<image::DynamicImage> {
+    ) -> anyhow::Result<TextureImage> {
         debug!("Loading texture image: {:?}", video_clip_obj);
 
         let relative_filename = video_clip_obj
@@ -387,17 +694,432 @@ impl<'a> Loader<'a> {
             .and_then(std::ffi::OsStr::to_str)
             .map(str::to_ascii_lowercase);
         trace!("File extension: {:?}", file_ext);
-        let content = video_clip_obj
-            .content()
-            .ok_or_else(|| anyhow!("Currently, only embedded texture is supported"))?;
-        let image = match file_ext.as_ref().map(AsRef::as_ref) {
-            Some("tga") => image::load_from_memory_with_format(content, image::ImageFormat::Tga)
-                .context("Failed to load TGA image")?,
-            _ => image::load_from_memory(content).context("Failed to load image")?,
+        let content = match video_clip_obj.content() {
+            Some(v) => v.to_vec(),
+            None => {
+                let base_dir = self.base_dir.ok_or_else(|| {
+                    anyhow!("External texture cannot be loaded without the FBX file path")
+                })?;
+                let path = texture_search::resolve(base_dir, relative_filename)
+                    .ok_or_else(|| anyhow!("Texture file is not found: {:?}", relative_filename))?;
+                debug!("Loading external texture file: {}", path.display());
+                std::fs::read(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?
+            }
         };
+        // TGA files have no magic bytes to guess the format from.
+        let format = match file_ext.as_deref() {
+            Some("tga") => Some(image::ImageFormat::Tga),
+            _ => None,
+        };
+        let image = TextureImage::encoded(content, format, self.options.texture_cache);
+        if self.options.strict {
+            image.get()?;
+        }
 
         debug!("Successfully loaded texture image: {:?}", video_clip_obj);
 
         Ok(image)
     }
 }
+
+/// Loads the render order from the user property of the model, or returns 0
+/// if the property is absent.
+fn load_render_order(model: &object::ObjectHandle<'_>) -> anyhow::Result<i32> {
+    match model
+        .properties_by_native_typename("FbxNode")
+        .get_property(RENDER_ORDER_PROPERTY)
+    {
+        Some(prop) => prop
+            .load_value(PrimitiveLoader::<i32>::new())
+            .with_context(|| format!("Failed to load {}", RENDER_ORDER_PROPERTY)),
+        None => Ok(0),
+    }
+}
+
+/// Loads Lambert parameters from the material properties.
+fn load_lambert_data(properties: &MaterialProperties<'_>) -> anyhow::Result<LambertData> {
+    let ambient_color = properties
+        .ambient_color_or_default()
+        .context("Failed to get ambient color")?;
+    let ambient_factor = properties
+        .ambient_factor_or_default()
+        .context("Failed to get ambient factor")?;
+    let ambient = (ambient_color * ambient_factor).map(|v| v as f32);
+    let diffuse_color = properties
+        .diffuse_color_or_default()
+        .context("Failed to get diffuse color")?;
+    let diffuse_factor = properties
+        .diffuse_factor_or_default()
+        .context("Failed to get diffuse factor")?;
+    let diffuse = (diffuse_color * diffuse_factor).map(|v| v as f32);
+    let emissive_color = properties
+        .emissive_color_or_default()
+        .context("Failed to get emissive color")?;
+    let emissive_factor = properties
+        .emissive_factor_or_default()
+        .context("Failed to get emissive factor")?;
+    let emissive = (emissive_color * emissive_factor).map(|v| v as f32);
+
+    Ok(LambertData {
+        ambient,
+        diffuse,
+        emissive,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::InnerSpace;
+
+    use self::fixture::MeshFixture;
+
+    /// Control points of a unit square on the XY plane.
+    const SQUARE: [[f64; 3]; 4] = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [0.0, 1.0, 0.0],
+    ];
+
+    /// Loads the meshes with the options.
+    fn load(meshes: &[MeshFixture], options: &LoadOptions) -> anyhow::Result<Scene> {
+        from_doc(fixture::document(meshes)?, options, None)
+    }
+
+    /// Returns the options of the strict mode.
+    fn strict() -> LoadOptions {
+        LoadOptions {
+            strict: true,
+            ..Default::default()
+        }
+    }
+
+    /// Tests that quads are loaded as triangles.
+    #[test]
+    fn quad() -> anyhow::Result<()> {
+        let scene = load(
+            &[MeshFixture::new("quad", &SQUARE, &[&[0, 1, 2, 3]])],
+            &strict(),
+        )?;
+        let geometry = scene.geometry_meshes().next().expect("No geometry meshes");
+        assert_eq!(geometry.name.as_deref(), Some("quad"));
+        assert_eq!(geometry.positions.len(), 6);
+        assert_eq!(geometry.normals.len(), 6);
+        assert_eq!(geometry.uv.len(), 6);
+        assert!(geometry.colors.is_empty());
+        assert_eq!(geometry.indices_per_material, [vec![0, 1, 2, 3, 4, 5]]);
+        assert_eq!(geometry.polygons_per_material, [vec![0, 0]]);
+        assert_eq!(geometry.positions[3], Point3::new(1.0, 1.0, 0.0));
+        assert_eq!(geometry.uv[3], Point2::new(1.0, 1.0));
+        assert!(geometry
+            .normals
+            .iter()
+            .all(|&normal| normal == Vector3::unit_z()));
+        Ok(())
+    }
+
+    /// Tests that concave polygons are loaded.
+    #[test]
+    fn concave_polygon() -> anyhow::Result<()> {
+        let points = [
+            [0.0, 0.0, 0.0],
+            [2.0, 0.0, 0.0],
+            [2.0, 2.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 2.0, 0.0],
+        ];
+        let scene = load(&[MeshFixture::polygon("concave", &points)], &strict())?;
+        let geometry = scene.geometry_meshes().next().expect("No geometry meshes");
+        assert_eq!(geometry.positions.len(), 9);
+        // All the triangles share the concave vertex.
+        assert!(geometry
+            .positions
+            .chunks(3)
+            .all(|tri| tri.contains(&Point3::new(1.0, 1.0, 0.0))));
+        Ok(())
+    }
+
+    /// Tests that zero-area triangles are removed.
+    #[test]
+    fn degenerate_triangles() -> anyhow::Result<()> {
+        let points = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [2.0, 0.0, 0.0],
+        ];
+        let mesh = MeshFixture::new("degenerate", &points, &[&[0, 1, 4], &[0, 1, 2, 3]]);
+        let scene = load(&[mesh], &strict())?;
+        let geometry = scene.geometry_meshes().next().expect("No geometry meshes");
+        assert_eq!(geometry.indices_per_material, [vec![3, 4, 5, 6, 7, 8]]);
+        assert_eq!(geometry.polygons_per_material, [vec![1, 1]]);
+        Ok(())
+    }
+
+    /// Tests that the triangles are split by the materials.
+    #[test]
+    fn multiple_materials() -> anyhow::Result<()> {
+        let points = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [2.0, 0.0, 0.0],
+            [2.0, 1.0, 0.0],
+        ];
+        let mesh = MeshFixture::new(
+            "two_materials",
+            &points,
+            &[&[0, 1, 2, 3], &[1, 4, 5], &[1, 5, 2]],
+        )
+        .with_materials(2, &[1, 0, 0]);
+        let scene = load(&[mesh], &strict())?;
+        assert_eq!(scene.materials().count(), 2);
+        let mesh = scene.meshes().next().expect("No meshes");
+        assert_eq!(mesh.materials.len(), 2);
+        let geometry = scene.geometry_mesh(mesh.geometry_mesh_index)?;
+        assert_eq!(
+            geometry.indices_per_material,
+            [vec![6, 7, 8, 9, 10, 11], vec![0, 1, 2, 3, 4, 5]]
+        );
+        assert_eq!(geometry.polygons_per_material, [vec![1, 2], vec![0, 0]]);
+        assert_eq!(geometry.source_polygon(0, 1), Some(2));
+        Ok(())
+    }
+
+    /// Tests that meshes without normals are skipped in the lenient mode.
+    #[test]
+    fn missing_normals() -> anyhow::Result<()> {
+        let broken = MeshFixture::new("broken", &SQUARE, &[&[0, 1, 2, 3]]).without_normals();
+        let valid = MeshFixture::new("valid", &SQUARE, &[&[0, 1, 2, 3]]);
+        let scene = load(&[broken.clone(), valid], &LoadOptions::default())?;
+        assert_eq!(scene.meshes().count(), 1);
+        assert_eq!(
+            scene.meshes().next().unwrap().name.as_deref(),
+            Some("valid")
+        );
+        assert_eq!(scene.warnings().len(), 1);
+        assert!(scene.warnings()[0]
+            .message
+            .contains("Failed to get normals"));
+
+        assert!(load(&[broken], &strict()).is_err());
+        Ok(())
+    }
+
+    /// Tests that meshes without UV are rejected.
+    #[test]
+    fn missing_uv() {
+        let mesh = MeshFixture::new("no_uv", &SQUARE, &[&[0, 1, 2, 3]]).without_uv();
+        let err = load(&[mesh], &strict()).expect_err("Mesh without UV is loaded");
+        assert!(format!("{:#}", err).contains("Failed to get UV"));
+    }
+
+    /// Tests that meshes without the material layer element are rejected.
+    #[test]
+    fn missing_material_layer() {
+        let mesh =
+            MeshFixture::new("no_materials", &SQUARE, &[&[0, 1, 2, 3]]).without_material_layer();
+        let err = load(&[mesh], &strict()).expect_err("Mesh without materials is loaded");
+        assert!(format!("{:#}", err).contains("Materials not found"));
+    }
+
+    /// Tests that material indices out of range are rejected.
+    #[test]
+    fn material_index_out_of_range() {
+        let mesh =
+            MeshFixture::new("out_of_range", &SQUARE, &[&[0, 1, 2, 3]]).with_materials(1, &[1]);
+        let err = load(&[mesh], &strict()).expect_err("Invalid material index is accepted");
+        assert!(format!("{:#}", err).contains("out of range"));
+    }
+
+    /// Tests that truncated control point arrays are rejected.
+    #[test]
+    fn truncated_control_points() {
+        let mesh = MeshFixture::new("truncated", &SQUARE, &[&[0, 1, 2, 3]]).truncated();
+        let err = load(&[mesh], &strict()).expect_err("Truncated control points are accepted");
+        assert!(format!("{:#}", err).contains("Failed to get control points"));
+    }
+
+    /// Tests that meshes with unsupported polygons are skipped in the lenient
+    /// mode.
+    #[test]
+    fn unsupported_polygon() -> anyhow::Result<()> {
+        let points = [
+            [0.0, 0.0, 0.0],
+            [4.0, 0.0, 0.0],
+            [4.0, 3.0, 0.0],
+            [3.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 3.0, 0.0],
+        ];
+        let scene = load(
+            &[MeshFixture::polygon("m", &points)],
+            &LoadOptions::default(),
+        )?;
+        assert_eq!(scene.meshes().count(), 0);
+        assert_eq!(scene.warnings().len(), 1);
+        assert!(scene.warnings()[0].message.contains("Triangulation failed"));
+        Ok(())
+    }
+
+    /// Tests that meshes are loaded as their bounding boxes without
+    /// triangulation and attributes.
+    #[test]
+    fn bboxes_only() -> anyhow::Result<()> {
+        let points = [
+            [0.0, 0.0, 0.0],
+            [3.0, 0.0, 0.0],
+            [1.0, 1.0, 2.0],
+            [3.0, 3.0, 0.0],
+            [2.0, 1.0, -1.0],
+            [0.0, 3.0, 0.0],
+        ];
+        let options = LoadOptions {
+            bboxes_only: true,
+            ..strict()
+        };
+        let scene = load(
+            &[MeshFixture::polygon("m", &points)
+                .without_normals()
+                .without_uv()],
+            &options,
+        )?;
+        let geometry = scene.geometry_meshes().next().expect("No geometry meshes");
+        assert_eq!(geometry.positions.len(), 24);
+        assert_eq!(geometry.normals.len(), 24);
+        assert_eq!(geometry.uv.len(), 24);
+        assert_eq!(geometry.indices_per_material.len(), 1);
+        assert_eq!(geometry.indices_per_material[0].len(), 36);
+        let bbox = geometry.bbox_mesh().bounding_box().expect("Empty mesh");
+        assert_eq!(bbox.min(), Point3::new(0.0, 0.0, -1.0));
+        assert_eq!(bbox.max(), Point3::new(3.0, 3.0, 2.0));
+        // All triangles face outward.
+        let center = bbox.center();
+        assert!(geometry
+            .triangles()
+            .all(|[a, b, c]| { (b - a).cross(c - a).dot(a - center) > 0.0 }));
+        Ok(())
+    }
+
+    /// Tests that the transforms of the model hierarchy are baked with
+    /// `flatten`.
+    #[test]
+    fn flatten() -> anyhow::Result<()> {
+        let fixture = MeshFixture::new("quad", &SQUARE, &[&[0, 1, 2, 3]])
+            .with_transform([1.0, 0.0, 0.0], [0.0, 0.0, 90.0], [2.0, 2.0, 2.0])
+            .with_parent([0.0, 0.0, 5.0]);
+        let options = LoadOptions {
+            flatten: true,
+            ..strict()
+        };
+        let scene = load(&[fixture], &options)?;
+        let geometry = scene.geometry_meshes().next().expect("No geometry meshes");
+        let mesh = scene.meshes().next().expect("No meshes");
+        assert_eq!(mesh.transform, Matrix4::identity());
+        // (1, 1, 0) is scaled to (2, 2, 0), rotated to (-2, 2, 0), and
+        // translated by the model and the parent.
+        let p = geometry.positions[3];
+        assert!(
+            (p - Point3::new(-1.0, 2.0, 5.0)).magnitude() < 1e-5,
+            "{:?}",
+            p
+        );
+        assert!(geometry
+            .normals
+            .iter()
+            .all(|&normal| (normal - Vector3::unit_z()).magnitude() < 1e-5));
+        Ok(())
+    }
+
+    /// Tests that the transforms are kept on the meshes without `flatten`, and
+    /// the model hierarchy is loaded as nodes.
+    #[test]
+    fn transform_without_flatten() -> anyhow::Result<()> {
+        let fixture = MeshFixture::new("quad", &SQUARE, &[&[0, 1, 2, 3]])
+            .with_transform([1.0, 0.0, 0.0], [0.0, 0.0, 0.0], [1.0, 1.0, 1.0])
+            .with_parent([0.0, 0.0, 5.0]);
+        let scene = load(&[fixture], &strict())?;
+        let geometry = scene.geometry_meshes().next().expect("No geometry meshes");
+        let mesh = scene.meshes().next().expect("No meshes");
+        assert_eq!(geometry.positions[3], Point3::new(1.0, 1.0, 0.0));
+        assert_eq!(
+            mesh.transform,
+            Matrix4::from_translation(Vector3::new(1.0, 0.0, 5.0))
+        );
+        // The model and its parent are loaded as nodes.
+        let node = mesh.node.expect("No node of the mesh");
+        let parent = scene.node(node)?.parent.expect("No parent node");
+        assert_eq!(scene.node(node)?.translation, Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(scene.node(parent)?.name.as_deref(), Some("quad_parent"));
+        assert_eq!(scene.node(parent)?.children, [node]);
+        assert_eq!(scene.root_nodes().collect::<Vec<_>>(), [parent]);
+        Ok(())
+    }
+
+    /// Tests that the mesh transforms are applied in the exports and the ray
+    /// casts without `flatten`, as if they were baked.
+    #[test]
+    fn export_transformed() -> anyhow::Result<()> {
+        use crate::{
+            data::raycast::{self, Ray},
+            export::stl,
+        };
+
+        let fixture = MeshFixture::new("quad", &SQUARE, &[&[0, 1, 2, 3]])
+            .with_transform([1.0, 0.0, 0.0], [0.0, 0.0, 90.0], [2.0, 2.0, 2.0])
+            .with_parent([0.0, 0.0, 5.0]);
+        let scene = load(std::slice::from_ref(&fixture), &strict())?;
+        let flattened = load(
+            &[fixture],
+            &LoadOptions {
+                flatten: true,
+                ..strict()
+            },
+        )?;
+
+        let mut exported = Vec::new();
+        stl::write(&mut exported, &scene)?;
+        let mut expected = Vec::new();
+        stl::write(&mut expected, &flattened)?;
+        assert_eq!(exported, expected);
+        // Vertices of the first triangle follow the header, the triangle
+        // count, and the normal.
+        let floats = exported[96..132]
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect::<Vec<_>>();
+        assert!(floats.chunks_exact(3).all(|p| (p[2] - 5.0).abs() < 1e-5));
+
+        // (1, 1, 0) is moved to (-1, 2, 5).
+        let ray = Ray::new(Point3::new(-0.9, 1.9, 10.0), -Vector3::unit_z());
+        let hit = raycast::closest_hit(&scene, &ray).expect("No hit");
+        assert!((hit.distance - 5.0).abs() < 1e-5, "{:?}", hit);
+        Ok(())
+    }
+
+    /// Tests that the render order is loaded from the user property.
+    #[test]
+    fn render_order() -> anyhow::Result<()> {
+        let scene = load(
+            &[
+                MeshFixture::new("default", &SQUARE, &[&[0, 1, 2, 3]]),
+                MeshFixture::new("decal", &SQUARE, &[&[0, 1, 2, 3]]).with_render_order(3),
+            ],
+            &strict(),
+        )?;
+        let render_order = |name| -> anyhow::Result<i32> {
+            let index = scene
+                .mesh_by_name(name)
+                .ok_or_else(|| anyhow!("No mesh {:?}", name))?;
+            Ok(scene.mesh(index)?.render_order)
+        };
+        assert_eq!(render_order("default")?, 0);
+        assert_eq!(render_order("decal")?, 3);
+        Ok(())
+    }
+}
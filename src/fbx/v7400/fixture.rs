@@ -0,0 +1,461 @@
+//! Synthetic FBX documents for tests.
+//!
+//! The documents contain only the nodes the loader reads: model meshes with
+//! their geometry meshes, Lambert materials, and parent null models, and the
+//! connections between them.
+
+use std::io::Cursor;
+
+use anyhow::anyhow;
+use fbxcel::{
+    low::{v7400::AttributeValue, FbxVersion},
+    writer::v7400::binary::{FbxFooter, Result as WriteResult, Writer},
+};
+use fbxcel_dom::v7400::{
+    object::{self, geometry::TypedGeometryHandle, TypedObjectHandle},
+    Document,
+};
+
+/// Writer of the synthetic documents.
+type FbxWriter = Writer<Cursor<Vec<u8>>>;
+
+/// Mesh in a synthetic document.
+#[derive(Debug, Clone)]
+pub(super) struct MeshFixture {
+    /// Name of the model and the geometry.
+    name: String,
+    /// Control points.
+    control_points: Vec<[f64; 3]>,
+    /// Polygons as the control point indices.
+    polygons: Vec<Vec<i32>>,
+    /// Whether the normals are written.
+    normals: bool,
+    /// Whether the UV is written.
+    uv: bool,
+    /// Material index of each polygon, or `None` to write no material layer
+    /// element.
+    polygon_materials: Option<Vec<i32>>,
+    /// Number of the materials connected to the model.
+    num_materials: usize,
+    /// Whether the last coordinate of the control points is dropped.
+    truncated: bool,
+    /// Translation, rotation in degrees, and scaling of the model.
+    transform: Option<[[f64; 3]; 3]>,
+    /// Translation of the parent null model, or `None` to put the model at
+    /// the root.
+    parent_translation: Option<[f64; 3]>,
+    /// Render order user property of the model.
+    render_order: Option<i32>,
+}
+
+impl MeshFixture {
+    /// Creates a new `MeshFixture` with the normals, the UV, and a single
+    /// material.
+    pub(super) fn new(name: &str, control_points: &[[f64; 3]], polygons: &[&[i32]]) -> Self {
+        Self {
+            name: name.to_owned(),
+            control_points: control_points.to_vec(),
+            polygons: polygons.iter().map(|polygon| polygon.to_vec()).collect(),
+            normals: true,
+            uv: true,
+            polygon_materials: Some(vec![0; polygons.len()]),
+            num_materials: 1,
+            truncated: false,
+            transform: None,
+            parent_translation: None,
+            render_order: None,
+        }
+    }
+
+    /// Creates a new `MeshFixture` of a single polygon through the points.
+    pub(super) fn polygon(name: &str, points: &[[f64; 3]]) -> Self {
+        let polygon = (0..points.len() as i32).collect::<Vec<_>>();
+        Self::new(name, points, &[&polygon])
+    }
+
+    /// Removes the normals.
+    pub(super) fn without_normals(self) -> Self {
+        Self {
+            normals: false,
+            ..self
+        }
+    }
+
+    /// Removes the UV.
+    pub(super) fn without_uv(self) -> Self {
+        Self { uv: false, ..self }
+    }
+
+    /// Removes the material layer element, keeping the materials connected.
+    pub(super) fn without_material_layer(self) -> Self {
+        Self {
+            polygon_materials: None,
+            ..self
+        }
+    }
+
+    /// Drops the last coordinate of the control points, as in broken files.
+    pub(super) fn truncated(self) -> Self {
+        Self {
+            truncated: true,
+            ..self
+        }
+    }
+
+    /// Sets the local translation, rotation in degrees, and scaling of the
+    /// model.
+    pub(super) fn with_transform(
+        self,
+        translation: [f64; 3],
+        rotation: [f64; 3],
+        scaling: [f64; 3],
+    ) -> Self {
+        Self {
+            transform: Some([translation, rotation, scaling]),
+            ..self
+        }
+    }
+
+    /// Puts the model under a null model with the translation.
+    pub(super) fn with_parent(self, translation: [f64; 3]) -> Self {
+        Self {
+            parent_translation: Some(translation),
+            ..self
+        }
+    }
+
+    /// Sets the render order user property of the model.
+    pub(super) fn with_render_order(self, render_order: i32) -> Self {
+        Self {
+            render_order: Some(render_order),
+            ..self
+        }
+    }
+
+    /// Connects the materials, and assigns them to the polygons.
+    pub(super) fn with_materials(self, num_materials: usize, polygon_materials: &[i32]) -> Self {
+        Self {
+            polygon_materials: Some(polygon_materials.to_vec()),
+            num_materials,
+            ..self
+        }
+    }
+}
+
+/// Returns the object ID of the model of the `i`-th mesh.
+fn model_id(i: usize) -> i64 {
+    (i as i64 + 1) * 1000
+}
+
+/// Returns the object ID of the parent model of the `i`-th mesh.
+fn parent_id(i: usize) -> i64 {
+    model_id(i) + 50
+}
+
+/// Returns the object ID of the geometry of the `i`-th mesh.
+fn geometry_id(i: usize) -> i64 {
+    model_id(i) + 1
+}
+
+/// Returns the object ID of the `j`-th material of the `i`-th mesh.
+fn material_id(i: usize, j: usize) -> i64 {
+    model_id(i) + 100 + j as i64
+}
+
+/// Writes a binary FBX document with the meshes.
+pub(super) fn write_fbx(meshes: &[MeshFixture]) -> WriteResult<Vec<u8>> {
+    let mut writer = Writer::new(Cursor::new(Vec::new()), FbxVersion::V7_4)?;
+
+    open_node(&mut writer, "Documents", vec![])?;
+    writer.close_node()?;
+
+    open_node(&mut writer, "Objects", vec![])?;
+    for (i, mesh) in meshes.iter().enumerate() {
+        write_model(&mut writer, i, mesh)?;
+        if let Some(translation) = mesh.parent_translation {
+            write_parent(&mut writer, i, mesh, translation)?;
+        }
+        write_geometry(&mut writer, i, mesh)?;
+        for j in 0..mesh.num_materials {
+            write_material(&mut writer, i, j, &format!("{}_material{}", mesh.name, j))?;
+        }
+    }
+    writer.close_node()?;
+
+    open_node(&mut writer, "Connections", vec![])?;
+    for (i, mesh) in meshes.iter().enumerate() {
+        match mesh.parent_translation {
+            Some(_) => {
+                connect(&mut writer, parent_id(i), 0)?;
+                connect(&mut writer, model_id(i), parent_id(i))?;
+            }
+            None => connect(&mut writer, model_id(i), 0)?,
+        }
+        connect(&mut writer, geometry_id(i), model_id(i))?;
+        for j in 0..mesh.num_materials {
+            connect(&mut writer, material_id(i, j), model_id(i))?;
+        }
+    }
+    writer.close_node()?;
+
+    let sink = writer.finalize_and_flush(&FbxFooter::default())?;
+    Ok(sink.into_inner())
+}
+
+/// Writes and parses a binary FBX document with the meshes.
+pub(super) fn document(meshes: &[MeshFixture]) -> anyhow::Result<Box<Document>> {
+    // The writer errors are not `Send`.
+    let bytes = write_fbx(meshes).map_err(|e| anyhow!("Failed to write fixture: {}", e))?;
+    crate::fbx::parse_reader(Cursor::new(bytes))
+}
+
+/// Returns the first geometry mesh in the document.
+pub(super) fn first_geometry_mesh(
+    doc: &Document,
+) -> anyhow::Result<object::geometry::MeshHandle<'_>> {
+    doc.objects()
+        .find_map(|obj| match obj.get_typed() {
+            TypedObjectHandle::Geometry(TypedGeometryHandle::Mesh(mesh)) => Some(mesh),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow!("No geometry meshes in the fixture"))
+}
+
+/// Opens a node with the attributes.
+fn open_node(writer: &mut FbxWriter, name: &str, attrs: Vec<AttributeValue>) -> WriteResult<()> {
+    let mut attrs_writer = writer.new_node(name)?;
+    for attr in attrs {
+        match attr {
+            AttributeValue::I32(v) => attrs_writer.append_i32(v)?,
+            AttributeValue::I64(v) => attrs_writer.append_i64(v)?,
+            AttributeValue::F64(v) => attrs_writer.append_f64(v)?,
+            AttributeValue::ArrI32(v) => attrs_writer.append_arr_i32_from_iter(None, v)?,
+            AttributeValue::ArrF64(v) => attrs_writer.append_arr_f64_from_iter(None, v)?,
+            AttributeValue::String(v) => attrs_writer.append_string_direct(&v)?,
+            v => panic!("Attribute {:?} is not used in fixtures", v),
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes a node with the attributes and no children.
+fn leaf_node(writer: &mut FbxWriter, name: &str, attrs: Vec<AttributeValue>) -> WriteResult<()> {
+    open_node(writer, name, attrs)?;
+    writer.close_node()?;
+    Ok(())
+}
+
+/// Returns the name and the class attribute of an object.
+fn name_class(name: &str, class: &str) -> AttributeValue {
+    format!("{}\u{0}\u{1}{}", name, class).into()
+}
+
+/// Writes the model of the `i`-th mesh.
+fn write_model(writer: &mut FbxWriter, i: usize, mesh: &MeshFixture) -> WriteResult<()> {
+    open_node(
+        writer,
+        "Model",
+        vec![
+            model_id(i).into(),
+            name_class(&mesh.name, "Model"),
+            "Mesh".into(),
+        ],
+    )?;
+    if mesh.transform.is_some() || mesh.render_order.is_some() {
+        open_node(writer, "Properties70", vec![])?;
+        if let Some([translation, rotation, scaling]) = mesh.transform {
+            write_vector3(writer, "Lcl Translation", translation)?;
+            write_vector3(writer, "Lcl Rotation", rotation)?;
+            write_vector3(writer, "Lcl Scaling", scaling)?;
+        }
+        if let Some(render_order) = mesh.render_order {
+            leaf_node(
+                writer,
+                "P",
+                vec![
+                    "RenderOrder".into(),
+                    "int".into(),
+                    "Integer".into(),
+                    "AU".into(),
+                    render_order.into(),
+                ],
+            )?;
+        }
+        writer.close_node()?;
+    }
+    writer.close_node()?;
+    Ok(())
+}
+
+/// Writes the parent null model of the `i`-th mesh.
+fn write_parent(
+    writer: &mut FbxWriter,
+    i: usize,
+    mesh: &MeshFixture,
+    translation: [f64; 3],
+) -> WriteResult<()> {
+    open_node(
+        writer,
+        "Model",
+        vec![
+            parent_id(i).into(),
+            name_class(&format!("{}_parent", mesh.name), "Model"),
+            "Null".into(),
+        ],
+    )?;
+    open_node(writer, "Properties70", vec![])?;
+    write_vector3(writer, "Lcl Translation", translation)?;
+    writer.close_node()?;
+    writer.close_node()?;
+    Ok(())
+}
+
+/// Writes the 3D vector property.
+fn write_vector3(writer: &mut FbxWriter, name: &str, [x, y, z]: [f64; 3]) -> WriteResult<()> {
+    leaf_node(
+        writer,
+        "P",
+        vec![
+            name.into(),
+            name.into(),
+            "".into(),
+            "A".into(),
+            x.into(),
+            y.into(),
+            z.into(),
+        ],
+    )
+}
+
+/// Writes the geometry of the `i`-th mesh.
+fn write_geometry(writer: &mut FbxWriter, i: usize, mesh: &MeshFixture) -> WriteResult<()> {
+    open_node(
+        writer,
+        "Geometry",
+        vec![
+            geometry_id(i).into(),
+            name_class(&mesh.name, "Geometry"),
+            "Mesh".into(),
+        ],
+    )?;
+
+    let mut vertices = mesh
+        .control_points
+        .iter()
+        .flatten()
+        .copied()
+        .collect::<Vec<_>>();
+    if mesh.truncated {
+        vertices.pop();
+    }
+    leaf_node(writer, "Vertices", vec![vertices.into()])?;
+    // The last index of each polygon is stored as its bitwise negation.
+    let polygon_vertices = mesh.polygons.iter().flat_map(|polygon| {
+        let last = polygon.len().saturating_sub(1);
+        polygon
+            .iter()
+            .enumerate()
+            .map(move |(k, &cpi)| if k == last { !cpi } else { cpi })
+    });
+    leaf_node(
+        writer,
+        "PolygonVertexIndex",
+        vec![polygon_vertices.collect::<Vec<_>>().into()],
+    )?;
+
+    let mut layer_elements = Vec::new();
+    if mesh.normals {
+        let normals = mesh.control_points.iter().flat_map(|_| [0.0, 0.0, 1.0]);
+        write_layer_element(
+            writer,
+            "LayerElementNormal",
+            "ByControlPoint",
+            "Direct",
+            ("Normals", normals.collect::<Vec<f64>>().into()),
+        )?;
+        layer_elements.push("LayerElementNormal");
+    }
+    if mesh.uv {
+        let uv = mesh.control_points.iter().flat_map(|&[x, y, _]| [x, y]);
+        write_layer_element(
+            writer,
+            "LayerElementUV",
+            "ByControlPoint",
+            "Direct",
+            ("UV", uv.collect::<Vec<f64>>().into()),
+        )?;
+        layer_elements.push("LayerElementUV");
+    }
+    if let Some(polygon_materials) = &mesh.polygon_materials {
+        write_layer_element(
+            writer,
+            "LayerElementMaterial",
+            "ByPolygon",
+            "IndexToDirect",
+            ("Materials", polygon_materials.clone().into()),
+        )?;
+        layer_elements.push("LayerElementMaterial");
+    }
+
+    open_node(writer, "Layer", vec![0i32.into()])?;
+    for ty in layer_elements {
+        open_node(writer, "LayerElement", vec![])?;
+        leaf_node(writer, "Type", vec![ty.into()])?;
+        leaf_node(writer, "TypedIndex", vec![0i32.into()])?;
+        writer.close_node()?;
+    }
+    writer.close_node()?;
+
+    writer.close_node()?;
+    Ok(())
+}
+
+/// Writes the layer element with the index 0.
+fn write_layer_element(
+    writer: &mut FbxWriter,
+    name: &str,
+    mapping: &str,
+    reference: &str,
+    (data_name, data): (&str, AttributeValue),
+) -> WriteResult<()> {
+    open_node(writer, name, vec![0i32.into()])?;
+    leaf_node(writer, "MappingInformationType", vec![mapping.into()])?;
+    leaf_node(writer, "ReferenceInformationType", vec![reference.into()])?;
+    leaf_node(writer, data_name, vec![data])?;
+    writer.close_node()?;
+    Ok(())
+}
+
+/// Writes the `j`-th material of the `i`-th mesh.
+fn write_material(writer: &mut FbxWriter, i: usize, j: usize, name: &str) -> WriteResult<()> {
+    open_node(
+        writer,
+        "Material",
+        vec![
+            material_id(i, j).into(),
+            name_class(name, "Material"),
+            "".into(),
+        ],
+    )?;
+    open_node(writer, "Properties70", vec![])?;
+    leaf_node(
+        writer,
+        "P",
+        vec![
+            "ShadingModel".into(),
+            "KString".into(),
+            "".into(),
+            "".into(),
+            "Lambert".into(),
+        ],
+    )?;
+    writer.close_node()?;
+    writer.close_node()?;
+    Ok(())
+}
+
+/// Writes the connection from the child object to the parent object.
+fn connect(writer: &mut FbxWriter, child: i64, parent: i64) -> WriteResult<()> {
+    leaf_node(writer, "C", vec!["OO".into(), child.into(), parent.into()])
+}
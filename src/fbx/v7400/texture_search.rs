@@ -0,0 +1,91 @@
+//! Search for external texture files.
+
+use std::{
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use tracing::{trace, warn};
+
+/// Names of the directories commonly used for textures.
+const TEXTURE_DIRS: &[&str] = &["textures", "Textures", "maps", "Maps"];
+
+/// Maximum depth of directories to search for a file with the same name.
+const MAX_SEARCH_DEPTH: usize = 8;
+
+/// Resolves the path of the external texture file.
+///
+/// The relative filename stored in the FBX file is tried first.
+/// If the file does not exist there, the file with the same name is searched
+/// in the common texture directories next to the FBX file and its directory,
+/// and then anywhere under the directory of the FBX file, as DCC tools do.
+pub(super) fn resolve(base_dir: &Path, relative_filename: &str) -> Option<PathBuf> {
+    // The parent of a bare file name is empty.
+    let base_dir = if base_dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        base_dir
+    };
+    // FBX files exported on Windows use backslashes as separators.
+    let relative = relative_filename.replace('\\', "/");
+    let stored = base_dir.join(&relative);
+    if stored.is_file() {
+        return Some(stored);
+    }
+    let file_name = Path::new(&relative).file_name()?;
+    trace!(
+        "Texture file is not found at {}, searching {:?}",
+        stored.display(),
+        file_name
+    );
+
+    let sibling_dirs = TEXTURE_DIRS.iter().flat_map(|dir| {
+        Some(base_dir.join(dir))
+            .into_iter()
+            .chain(base_dir.parent().map(|parent| parent.join(dir)))
+    });
+    let found = sibling_dirs
+        .map(|dir| dir.join(file_name))
+        .find(|candidate| candidate.is_file())
+        .or_else(|| find_file(base_dir, file_name, MAX_SEARCH_DEPTH))?;
+    warn!(
+        "Texture file {:?} is not found at the stored path, using {}",
+        relative_filename,
+        found.display()
+    );
+
+    Some(found)
+}
+
+/// Finds the file with the given name under the directory.
+///
+/// The names are compared case-insensitively, and hidden directories are
+/// skipped.
+fn find_file(dir: &Path, file_name: &OsStr, depth: usize) -> Option<PathBuf> {
+    let file_name = file_name.to_string_lossy();
+    let mut subdirs = Vec::new();
+    for entry in fs::read_dir(dir).ok()?.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let file_type = match entry.file_type() {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        if file_type.is_dir() {
+            if !name.starts_with('.') {
+                subdirs.push(entry.path());
+            }
+        } else if name.eq_ignore_ascii_case(&file_name) {
+            return Some(entry.path());
+        }
+    }
+    if depth == 0 {
+        return None;
+    }
+    // Files in the directory are preferred to the ones in the subdirectories.
+    subdirs.sort();
+    subdirs
+        .iter()
+        .find_map(|subdir| find_file(subdir, OsStr::new(&*file_name), depth - 1))
+}
@@ -0,0 +1,89 @@
+//! Transforms of the model nodes.
+//!
+//! The node transforms are loaded into [`Node`]s, and the geometric transform
+//! `Tg * Rg * Sg` applies only to the geometry of the model, not to its
+//! children.
+
+use anyhow::{bail, Context};
+use cgmath::{Matrix4, Vector3};
+use fbxcel_dom::v7400::object::{
+    property::{
+        loaders::{F64Arr3Loader, PrimitiveLoader},
+        ObjectProperties,
+    },
+    ObjectHandle,
+};
+
+use crate::data::{Node, RotationOrder};
+
+/// Loads the transform of the model relative to its parent.
+///
+/// The name and the hierarchy of the returned node are left empty.
+pub(super) fn load_node_transform(model: &ObjectHandle<'_>) -> anyhow::Result<Node> {
+    let props = properties(model);
+    let rotation_order = match props.get_property("RotationOrder") {
+        Some(prop) => rotation_order(
+            prop.load_value(PrimitiveLoader::<i32>::new())
+                .context("Failed to load RotationOrder")?,
+        )?,
+        None => RotationOrder::Xyz,
+    };
+
+    Ok(Node {
+        translation: vector3(&props, "Lcl Translation", 0.0)?,
+        rotation: vector3(&props, "Lcl Rotation", 0.0)?,
+        rotation_order,
+        scaling: vector3(&props, "Lcl Scaling", 1.0)?,
+        pre_rotation: vector3(&props, "PreRotation", 0.0)?,
+        post_rotation: vector3(&props, "PostRotation", 0.0)?,
+        rotation_offset: vector3(&props, "RotationOffset", 0.0)?,
+        rotation_pivot: vector3(&props, "RotationPivot", 0.0)?,
+        scaling_offset: vector3(&props, "ScalingOffset", 0.0)?,
+        scaling_pivot: vector3(&props, "ScalingPivot", 0.0)?,
+        ..Node::default()
+    })
+}
+
+/// Returns the transform from the geometry space of the model to the space
+/// of the model node.
+pub(super) fn geometric_transform(model: &ObjectHandle<'_>) -> anyhow::Result<Matrix4<f64>> {
+    let props = properties(model);
+    let scaling = vector3(&props, "GeometricScaling", 1.0)?;
+
+    Ok(
+        Matrix4::from_translation(vector3(&props, "GeometricTranslation", 0.0)?)
+            * RotationOrder::Xyz.matrix(vector3(&props, "GeometricRotation", 0.0)?)
+            * Matrix4::from_nonuniform_scale(scaling.x, scaling.y, scaling.z),
+    )
+}
+
+/// Returns the properties of the model.
+fn properties<'a>(model: &ObjectHandle<'a>) -> ObjectProperties<'a> {
+    model.properties_by_native_typename("FbxNode")
+}
+
+/// Loads the 3D vector property, or returns the vector filled with `default`
+/// if the property is absent.
+fn vector3(props: &ObjectProperties<'_>, name: &str, default: f64) -> anyhow::Result<Vector3<f64>> {
+    match props.get_property(name) {
+        Some(prop) => prop
+            .load_value(F64Arr3Loader)
+            .map(Vector3::from)
+            .with_context(|| format!("Failed to load {}", name)),
+        None => Ok(Vector3::new(default, default, default)),
+    }
+}
+
+/// Converts the value of the `RotationOrder` property.
+fn rotation_order(v: i32) -> anyhow::Result<RotationOrder> {
+    Ok(match v {
+        0 => RotationOrder::Xyz,
+        1 => RotationOrder::Xzy,
+        2 => RotationOrder::Yzx,
+        3 => RotationOrder::Yxz,
+        4 => RotationOrder::Zxy,
+        5 => RotationOrder::Zyx,
+        // Spheric XYZ is not supported.
+        v => bail!("Unsupported rotation order: {}", v),
+    })
+}
@@ -197,3 +197,110 @@ fn smallest_direction(v: &Vector3<f64>) -> Axis {
         Axis::Y
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fbx::v7400::fixture::{self, MeshFixture};
+
+    /// Triangulates the polygon through the points, and returns the control
+    /// point indices of the triangles.
+    fn triangulate(points: &[[f64; 3]]) -> anyhow::Result<Vec<[u32; 3]>> {
+        let doc = fixture::document(&[MeshFixture::polygon("polygon", points)])?;
+        let polygon_vertices = fixture::first_geometry_mesh(&doc)?.polygon_vertices()?;
+        let triangles = polygon_vertices.triangulate_each(triangulator)?;
+        let cpis = triangles
+            .iter_control_point_indices()
+            .map(|cpi| cpi.map(|cpi| cpi.to_u32()))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| anyhow!("Control point index out of range"))?;
+        Ok(cpis.chunks(3).map(|tri| [tri[0], tri[1], tri[2]]).collect())
+    }
+
+    /// Tests that triangles are kept as is.
+    #[test]
+    fn triangle() -> anyhow::Result<()> {
+        let triangles = triangulate(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]])?;
+        assert_eq!(triangles, [[0, 1, 2]]);
+        Ok(())
+    }
+
+    /// Tests that convex quads are cut from the first vertex.
+    #[test]
+    fn convex_quad() -> anyhow::Result<()> {
+        let triangles = triangulate(&[
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0],
+        ])?;
+        assert_eq!(triangles, [[0, 1, 2], [2, 3, 0]]);
+        Ok(())
+    }
+
+    /// Tests that concave quads are cut from the concave vertex.
+    #[test]
+    fn concave_quad() -> anyhow::Result<()> {
+        // The fourth vertex is concave.
+        let triangles = triangulate(&[
+            [0.0, 0.0, 0.0],
+            [2.0, 0.0, 0.0],
+            [0.0, 2.0, 0.0],
+            [0.5, 0.5, 0.0],
+        ])?;
+        assert_eq!(triangles, [[0, 1, 3], [3, 1, 2]]);
+        Ok(())
+    }
+
+    /// Tests that convex polygons are triangulated as a fan.
+    #[test]
+    fn convex_pentagon() -> anyhow::Result<()> {
+        let triangles = triangulate(&[
+            [0.0, 0.0, 0.0],
+            [2.0, 0.0, 0.0],
+            [3.0, 1.0, 0.0],
+            [1.0, 2.0, 0.0],
+            [-1.0, 1.0, 0.0],
+        ])?;
+        assert_eq!(triangles, [[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+        Ok(())
+    }
+
+    /// Tests that polygons with a concave vertex are triangulated as a fan
+    /// around it.
+    #[test]
+    fn concave_pentagon() -> anyhow::Result<()> {
+        // The fourth vertex is concave, and the polygon is on the YZ plane.
+        let triangles = triangulate(&[
+            [0.0, 0.0, 0.0],
+            [0.0, 2.0, 0.0],
+            [0.0, 2.0, 2.0],
+            [0.0, 1.0, 1.0],
+            [0.0, 0.0, 2.0],
+        ])?;
+        assert_eq!(triangles, [[3, 4, 0], [3, 0, 1], [3, 1, 2]]);
+        Ok(())
+    }
+
+    /// Tests that polygons with two or more concave vertices are rejected.
+    #[test]
+    fn two_concave_vertices() {
+        let result = triangulate(&[
+            [0.0, 0.0, 0.0],
+            [4.0, 0.0, 0.0],
+            [4.0, 3.0, 0.0],
+            [3.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 3.0, 0.0],
+        ]);
+        assert!(result.is_err());
+    }
+
+    /// Tests that polygons with less than three vertices are rejected.
+    #[test]
+    fn degenerate_polygon() {
+        let result = triangulate(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0]]);
+        assert!(result.is_err());
+    }
+}
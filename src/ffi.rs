@@ -0,0 +1,387 @@
+//! C API.
+//!
+//! This module exports `extern "C"` functions to load FBX files and query the
+//! flattened mesh, material, and texture data.
+//! See `include/fbx_viewer.h` for the C declarations.
+//!
+//! All pointers returned from the functions are owned by the scene, and valid
+//! until the scene is freed by [`fbx_viewer_scene_free`].
+
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString},
+    os::raw::c_char,
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use anyhow::{anyhow, Context};
+use image::RgbaImage;
+
+use crate::{
+    data::{Scene, ShadingData, WrapMode},
+    fbx,
+};
+
+thread_local! {
+    /// Message of the last error on the thread.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Scene loaded from an FBX file.
+///
+/// This is opaque to C.
+pub struct FbxViewerScene {
+    /// Scene.
+    scene: Scene,
+    /// Names of the meshes.
+    mesh_names: Vec<Option<CString>>,
+    /// Names of the materials.
+    material_names: Vec<Option<CString>>,
+    /// Names of the textures.
+    texture_names: Vec<Option<CString>>,
+    /// Texture images converted to RGBA.
+    texture_images: Vec<RgbaImage>,
+}
+
+impl FbxViewerScene {
+    /// Creates a new `FbxViewerScene`.
+    ///
+    /// The transforms of the meshes are baked, so that the meshes are in the
+    /// world space.
+    /// Returns an error if a texture image cannot be decoded.
+    fn new(mut scene: Scene) -> anyhow::Result<Self> {
+        scene
+            .bake_mesh_transforms()
+            .context("Failed to bake mesh transforms")?;
+        // Names with NUL characters cannot be passed to C.
+        let c_name = |name: &Option<String>| name.clone().and_then(|s| CString::new(s).ok());
+        Ok(Self {
+            mesh_names: scene.meshes().map(|v| c_name(&v.name)).collect(),
+            material_names: scene.materials().map(|v| c_name(&v.name)).collect(),
+            texture_names: scene.textures().map(|v| c_name(&v.name)).collect(),
+            texture_images: scene
+                .textures()
+                .map(|v| {
+                    let image = v
+                        .image
+                        .get()
+                        .with_context(|| format!("Failed to decode texture {:?}", v.name))?;
+                    Ok(image.to_rgba8())
+                })
+                .collect::<anyhow::Result<_>>()?,
+            scene,
+        })
+    }
+}
+
+/// Flattened mesh.
+#[repr(C)]
+pub struct FbxViewerMesh {
+    /// Name, or null if unnamed.
+    pub name: *const c_char,
+    /// Positions (3 floats per vertex).
+    pub positions: *const f32,
+    /// Normals (3 floats per vertex).
+    pub normals: *const f32,
+    /// UV (2 floats per vertex).
+    pub uv: *const f32,
+    /// Number of vertices.
+    pub num_vertices: usize,
+    /// Number of submeshes.
+    pub num_submeshes: usize,
+}
+
+/// Submesh with a single material.
+#[repr(C)]
+pub struct FbxViewerSubmesh {
+    /// Triangle vertex indices.
+    pub indices: *const u32,
+    /// Number of indices.
+    pub num_indices: usize,
+    /// Material index, or `SIZE_MAX` if the submesh has no material.
+    pub material: usize,
+}
+
+/// Material.
+#[repr(C)]
+pub struct FbxViewerMaterial {
+    /// Name, or null if unnamed.
+    pub name: *const c_char,
+    /// Ambient color.
+    pub ambient: [f32; 3],
+    /// Diffuse color.
+    pub diffuse: [f32; 3],
+    /// Emissive color.
+    pub emissive: [f32; 3],
+    /// Specular color (black for Lambert materials).
+    pub specular: [f32; 3],
+    /// Shininess (zero for Lambert materials).
+    pub shininess: f32,
+    /// Diffuse texture index, or `SIZE_MAX` if the material has no texture.
+    pub diffuse_texture: usize,
+}
+
+/// Texture.
+#[repr(C)]
+pub struct FbxViewerTexture {
+    /// Name, or null if unnamed.
+    pub name: *const c_char,
+    /// Width.
+    pub width: u32,
+    /// Height.
+    pub height: u32,
+    /// RGBA pixels (4 bytes per pixel, rows from top to bottom).
+    pub pixels: *const u8,
+    /// Whether the texture can be transparent.
+    pub transparent: bool,
+    /// Whether the texture repeats along U axis (clamped otherwise).
+    pub repeat_u: bool,
+    /// Whether the texture repeats along V axis (clamped otherwise).
+    pub repeat_v: bool,
+}
+
+/// Loads the FBX file.
+///
+/// Returns null on failure, and the error message can be retrieved by
+/// [`fbx_viewer_last_error`].
+///
+/// # Safety
+///
+/// `path` should be a valid NUL-terminated UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn fbx_viewer_scene_load(path: *const c_char) -> *mut FbxViewerScene {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        if path.is_null() {
+            return Err(anyhow!("Path is null"));
+        }
+        let path = CStr::from_ptr(path)
+            .to_str()
+            .context("Path is not valid UTF-8")?;
+        let scene =
+            fbx::load(path).with_context(|| format!("Failed to load FBX scene from {}", path))?;
+        FbxViewerScene::new(scene)
+    }))
+    .unwrap_or_else(|_| Err(anyhow!("Panicked while loading FBX scene")));
+
+    match result {
+        Ok(scene) => Box::into_raw(Box::new(scene)),
+        Err(e) => {
+            set_last_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Frees the scene.
+///
+/// # Safety
+///
+/// `scene` should be null or a scene returned by [`fbx_viewer_scene_load`]
+/// which is not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn fbx_viewer_scene_free(scene: *mut FbxViewerScene) {
+    if !scene.is_null() {
+        drop(Box::from_raw(scene));
+    }
+}
+
+/// Returns the message of the last error on the current thread, or null if no
+/// errors happened.
+///
+/// The returned string is valid until the next call of a function in this API
+/// on the same thread.
+#[no_mangle]
+pub extern "C" fn fbx_viewer_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |s| s.as_ptr()))
+}
+
+/// Returns the number of meshes.
+///
+/// # Safety
+///
+/// `scene` should be a valid scene.
+#[no_mangle]
+pub unsafe extern "C" fn fbx_viewer_scene_num_meshes(scene: *const FbxViewerScene) -> usize {
+    (*scene).mesh_names.len()
+}
+
+/// Writes the mesh at the index to `out`.
+///
+/// Returns `false` if the index is out of range.
+///
+/// # Safety
+///
+/// `scene` should be a valid scene, and `out` should be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn fbx_viewer_scene_mesh(
+    scene: *const FbxViewerScene,
+    index: usize,
+    out: *mut FbxViewerMesh,
+) -> bool {
+    let scene = &*scene;
+    let mesh = match scene.scene.meshes().nth(index) {
+        Some(v) => v,
+        None => return false,
+    };
+    let geometry = match scene.scene.geometry_mesh(mesh.geometry_mesh_index) {
+        Ok(v) => v,
+        Err(e) => {
+            set_last_error(e.into());
+            return false;
+        }
+    };
+
+    // `Point3`, `Vector3`, and `Point2` of cgmath are `#[repr(C)]`.
+    *out = FbxViewerMesh {
+        name: c_str_ptr(&scene.mesh_names[index]),
+        positions: geometry.positions.as_ptr() as *const f32,
+        normals: geometry.normals.as_ptr() as *const f32,
+        uv: geometry.uv.as_ptr() as *const f32,
+        num_vertices: geometry.positions.len(),
+        num_submeshes: geometry.indices_per_material.len(),
+    };
+    true
+}
+
+/// Writes the submesh of the mesh to `out`.
+///
+/// Returns `false` if the index is out of range.
+///
+/// # Safety
+///
+/// `scene` should be a valid scene, and `out` should be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn fbx_viewer_scene_submesh(
+    scene: *const FbxViewerScene,
+    mesh_index: usize,
+    submesh_index: usize,
+    out: *mut FbxViewerSubmesh,
+) -> bool {
+    let scene = &(*scene).scene;
+    let mesh = match scene.meshes().nth(mesh_index) {
+        Some(v) => v,
+        None => return false,
+    };
+    let indices = match scene
+        .geometry_mesh(mesh.geometry_mesh_index)
+        .ok()
+        .and_then(|geometry| geometry.indices_per_material.get(submesh_index))
+    {
+        Some(v) => v,
+        None => return false,
+    };
+
+    *out = FbxViewerSubmesh {
+        indices: indices.as_ptr(),
+        num_indices: indices.len(),
+        material: mesh
+            .materials
+            .get(submesh_index)
+            .map_or(usize::MAX, |i| i.to_usize()),
+    };
+    true
+}
+
+/// Returns the number of materials.
+///
+/// # Safety
+///
+/// `scene` should be a valid scene.
+#[no_mangle]
+pub unsafe extern "C" fn fbx_viewer_scene_num_materials(scene: *const FbxViewerScene) -> usize {
+    (*scene).material_names.len()
+}
+
+/// Writes the material at the index to `out`.
+///
+/// Returns `false` if the index is out of range.
+///
+/// # Safety
+///
+/// `scene` should be a valid scene, and `out` should be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn fbx_viewer_scene_material(
+    scene: *const FbxViewerScene,
+    index: usize,
+    out: *mut FbxViewerMaterial,
+) -> bool {
+    let scene = &*scene;
+    let material = match scene.scene.materials().nth(index) {
+        Some(v) => v,
+        None => return false,
+    };
+    let lambert = material.data.lambert();
+    let (specular, shininess) = match &material.data {
+        ShadingData::Lambert(_) => ([0.0; 3], 0.0),
+        ShadingData::Phong(phong) => (phong.specular.into(), phong.shininess),
+    };
+
+    *out = FbxViewerMaterial {
+        name: c_str_ptr(&scene.material_names[index]),
+        ambient: lambert.ambient.into(),
+        diffuse: lambert.diffuse.into(),
+        emissive: lambert.emissive.into(),
+        specular,
+        shininess,
+        diffuse_texture: material
+            .diffuse_texture
+            .map_or(usize::MAX, |i| i.to_usize()),
+    };
+    true
+}
+
+/// Returns the number of textures.
+///
+/// # Safety
+///
+/// `scene` should be a valid scene.
+#[no_mangle]
+pub unsafe extern "C" fn fbx_viewer_scene_num_textures(scene: *const FbxViewerScene) -> usize {
+    (*scene).texture_images.len()
+}
+
+/// Writes the texture at the index to `out`.
+///
+/// Returns `false` if the index is out of range.
+///
+/// # Safety
+///
+/// `scene` should be a valid scene, and `out` should be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn fbx_viewer_scene_texture(
+    scene: *const FbxViewerScene,
+    index: usize,
+    out: *mut FbxViewerTexture,
+) -> bool {
+    let scene = &*scene;
+    let (texture, image) = match scene.scene.textures().nth(index) {
+        Some(v) => (v, &scene.texture_images[index]),
+        None => return false,
+    };
+
+    *out = FbxViewerTexture {
+        name: c_str_ptr(&scene.texture_names[index]),
+        width: image.width(),
+        height: image.height(),
+        pixels: image.as_ptr(),
+        transparent: texture.transparent,
+        repeat_u: texture.wrap_mode_u == WrapMode::Repeat,
+        repeat_v: texture.wrap_mode_v == WrapMode::Repeat,
+    };
+    true
+}
+
+/// Sets the last error on the current thread.
+fn set_last_error(e: anyhow::Error) {
+    // Messages with NUL characters are replaced, since they cannot be passed
+    // to C.
+    let message = CString::new(format!("{:#}", e))
+        .unwrap_or_else(|_| CString::new("Error message contains NUL").expect("No NUL"));
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Returns the pointer to the C string, or null.
+fn c_str_ptr(s: &Option<CString>) -> *const c_char {
+    s.as_ref().map_or(ptr::null(), |s| s.as_ptr())
+}
@@ -0,0 +1,438 @@
+//! glTF.
+//!
+//! glTF 2.0 files are loaded into the same [`Scene`] as FBX files, so that an
+//! FBX file and its glTF conversion can be compared.
+//! `Scene` has no node hierarchy, so the node transforms are baked into the
+//! vertices of each mesh instance.
+
+use std::{collections::HashMap, path::Path};
+
+use ::gltf::{
+    buffer, image as gltf_image,
+    material::AlphaMode,
+    mesh::Mode,
+    texture::{Texture as GltfTexture, WrappingMode},
+    Document, Node,
+};
+use anyhow::{anyhow, bail, Context};
+use cgmath::{Deg, InnerSpace, Matrix, Matrix3, Matrix4, Point2, Point3, SquareMatrix, Vector3};
+use image::{DynamicImage, ImageBuffer};
+use rgb::{ComponentMap, RGB, RGBA};
+use tracing::{debug, debug_span, info_span, warn};
+
+use crate::data::{
+    GeometryMesh, LambertData, Material, MaterialIndex, Mesh, PbrData, PhongData, Scene,
+    ShadingData, Texture, TextureImage, TextureIndex, WrapMode,
+};
+
+/// Crease angle used for meshes without normals.
+const CREASE_ANGLE: Deg<f32> = Deg(30.0);
+
+/// Vertex color of the primitives without vertex colors.
+const WHITE: RGBA<f32> = RGBA::new(1.0, 1.0, 1.0, 1.0);
+
+/// Loads glTF data.
+pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Scene> {
+    load_impl(path.as_ref())
+}
+
+/// Loads glTF data.
+fn load_impl(path: &Path) -> anyhow::Result<Scene> {
+    let _span = info_span!("load_gltf", path = %path.display()).entered();
+    let (document, buffers, images) = info_span!("parse_gltf").in_scope(|| ::gltf::import(path))?;
+    info_span!("convert_gltf").in_scope(|| Loader::new(&document, &buffers, &images).load())
+}
+
+/// Loader.
+struct Loader<'a> {
+    /// Document.
+    document: &'a Document,
+    /// Buffer data.
+    buffers: &'a [buffer::Data],
+    /// Image data.
+    images: &'a [gltf_image::Data],
+    /// Scene.
+    scene: Scene,
+    /// Material indices, keyed by glTF material index (`None` for the default
+    /// material).
+    material_indices: HashMap<Option<usize>, MaterialIndex>,
+    /// Texture indices, keyed by glTF texture index and transparency.
+    texture_indices: HashMap<(usize, bool), TextureIndex>,
+}
+
+impl<'a> Loader<'a> {
+    /// Creates a new `Loader`.
+    fn new(
+        document: &'a Document,
+        buffers: &'a [buffer::Data],
+        images: &'a [gltf_image::Data],
+    ) -> Self {
+        Self {
+            document,
+            buffers,
+            images,
+            scene: Default::default(),
+            material_indices: Default::default(),
+            texture_indices: Default::default(),
+        }
+    }
+
+    /// Loads the scene.
+    fn load(mut self) -> anyhow::Result<Scene> {
+        let gltf_scene = self
+            .document
+            .default_scene()
+            .or_else(|| self.document.scenes().next())
+            .ok_or_else(|| anyhow!("No scenes found"))?;
+        self.scene.set_name(gltf_scene.name().map(Into::into));
+
+        for node in gltf_scene.nodes() {
+            self.load_node(node, Matrix4::identity())
+                .context("Failed to load node")?;
+        }
+
+        Ok(self.scene)
+    }
+
+    /// Loads the node and its descendants.
+    fn load_node(&mut self, node: Node<'_>, parent: Matrix4<f32>) -> anyhow::Result<()> {
+        let transform = parent * Matrix4::from(node.transform().matrix());
+        if node.mesh().is_some() {
+            self.load_mesh(&node, transform)
+                .with_context(|| format!("Failed to load mesh of node {:?}", node.name()))?;
+        }
+        for child in node.children() {
+            self.load_node(child, transform)?;
+        }
+
+        Ok(())
+    }
+
+    /// Loads the mesh of the node, with the transform baked.
+    fn load_mesh(&mut self, node: &Node<'_>, transform: Matrix4<f32>) -> anyhow::Result<()> {
+        let mesh_obj = match node.mesh() {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        debug!("Loading mesh: name={:?}", mesh_obj.name());
+
+        let normal_matrix = {
+            let m = Matrix3::from_cols(
+                transform.x.truncate(),
+                transform.y.truncate(),
+                transform.z.truncate(),
+            );
+            m.invert().unwrap_or(m).transpose()
+        };
+        // Mirroring transforms flip the winding order.
+        let flip_winding = transform.determinant() < 0.0;
+
+        let mut geometry = GeometryMesh {
+            name: mesh_obj.name().map(Into::into),
+            positions: Vec::new(),
+            normals: Vec::new(),
+            uv: Vec::new(),
+            colors: Vec::new(),
+            indices_per_material: Vec::new(),
+            polygons_per_material: Vec::new(),
+        };
+        let mut materials = Vec::new();
+        let mut has_all_normals = true;
+        for primitive in mesh_obj.primitives() {
+            let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
+            let positions = match reader.read_positions() {
+                Some(v) => v.map(Point3::from).collect::<Vec<_>>(),
+                None => {
+                    warn!("Primitive without positions is ignored");
+                    continue;
+                }
+            };
+            let indices = match reader.read_indices() {
+                Some(v) => v.into_u32().collect::<Vec<_>>(),
+                None => (0..positions.len() as u32).collect(),
+            };
+            let triangulated =
+                debug_span!("triangulate").in_scope(|| triangulate(primitive.mode(), &indices));
+            let mut indices = match triangulated {
+                Some(v) => v,
+                None => {
+                    warn!(
+                        "Primitive with mode {:?} is ignored, as it is not a triangle",
+                        primitive.mode()
+                    );
+                    continue;
+                }
+            };
+            if let Some(&i) = indices.iter().find(|&&i| i as usize >= positions.len()) {
+                bail!(
+                    "Vertex index out of range: index={}, len={}",
+                    i,
+                    positions.len()
+                );
+            }
+            if flip_winding {
+                for tri in indices.chunks_exact_mut(3) {
+                    tri.swap(1, 2);
+                }
+            }
+            let normals = match reader.read_normals() {
+                Some(v) => v
+                    .map(|n| (normal_matrix * Vector3::from(n)).normalize())
+                    .collect(),
+                None => {
+                    has_all_normals = false;
+                    vec![Vector3::new(0.0, 0.0, 0.0); positions.len()]
+                }
+            };
+            // glTF UV origin is at the top left, but FBX UV origin (and that
+            // of `Scene`) is at the bottom left.
+            let uv = match reader.read_tex_coords(0) {
+                Some(v) => v.into_f32().map(|[u, v]| Point2::new(u, 1.0 - v)).collect(),
+                None => vec![Point2::new(0.0, 0.0); positions.len()],
+            };
+            let colors = reader
+                .read_colors(0)
+                .map(|v| v.into_rgba_f32().map(RGBA::from).collect::<Vec<_>>());
+            if normals.len() != positions.len()
+                || uv.len() != positions.len()
+                || colors.as_ref().is_some_and(|c| c.len() != positions.len())
+            {
+                bail!(
+                    "Vertices length mismatch: positions.len={:?}, normals.len={:?}, uv.len={:?}, colors.len={:?}",
+                    positions.len(),
+                    normals.len(),
+                    uv.len(),
+                    colors.as_ref().map(Vec::len)
+                );
+            }
+
+            let base = geometry.positions.len() as u32;
+            geometry.positions.extend(
+                positions
+                    .into_iter()
+                    .map(|p| Point3::from_homogeneous(transform * p.to_homogeneous())),
+            );
+            geometry.normals.extend(normals);
+            geometry.uv.extend(uv);
+            if let Some(colors) = colors {
+                // Primitives without vertex colors are white.
+                geometry.colors.resize(base as usize, WHITE);
+                geometry.colors.extend(colors);
+            }
+            geometry
+                .indices_per_material
+                .push(indices.into_iter().map(|i| base + i).collect());
+            materials.push(
+                self.load_material(primitive.material())
+                    .context("Failed to load material")?,
+            );
+        }
+        if !geometry.colors.is_empty() {
+            geometry.colors.resize(geometry.positions.len(), WHITE);
+        }
+        let removed = geometry.remove_degenerate_triangles();
+        if removed.total() != 0 {
+            warn!(
+                "Removed degenerate triangles from mesh {:?}: \
+                 duplicate indices = {}, zero area = {}",
+                geometry.name, removed.duplicate_indices, removed.zero_area
+            );
+        }
+        if !has_all_normals {
+            geometry.recompute_normals(CREASE_ANGLE.into());
+        }
+
+        let geometry_mesh_index = self.scene.add_geometry_mesh(geometry);
+        let mesh = Mesh {
+            name: node.name().or_else(|| mesh_obj.name()).map(Into::into),
+            geometry_mesh_index,
+            materials,
+            transform: Matrix4::identity(),
+            node: None,
+            render_order: 0,
+        };
+        self.scene.add_mesh(mesh);
+
+        Ok(())
+    }
+
+    /// Loads the material.
+    ///
+    /// The metallic-roughness parameters are converted to Phong parameters.
+    fn load_material(
+        &mut self,
+        material_obj: ::gltf::Material<'_>,
+    ) -> anyhow::Result<MaterialIndex> {
+        if let Some(index) = self.material_indices.get(&material_obj.index()) {
+            return Ok(*index);
+        }
+
+        debug!("Loading material: name={:?}", material_obj.name());
+
+        let pbr = material_obj.pbr_metallic_roughness();
+        let transparent = material_obj.alpha_mode() != AlphaMode::Opaque;
+        let diffuse_texture = pbr
+            .base_color_texture()
+            .map(|info| {
+                self.load_texture(info.texture(), transparent)
+                    .context("Failed to load base color texture")
+            })
+            .transpose()?;
+
+        let [r, g, b, _] = pbr.base_color_factor();
+        let base_color = RGB::new(r, g, b);
+        let metallic = pbr.metallic_factor();
+        // Metals reflect with their base color.
+        let specular =
+            base_color.map(|c| PbrData::DIELECTRIC_F0 + (c - PbrData::DIELECTRIC_F0) * metallic);
+        let lambert = LambertData {
+            ambient: RGB::new(0.0, 0.0, 0.0),
+            diffuse: base_color.map(|c| c * (1.0 - metallic)),
+            emissive: material_obj.emissive_factor().into(),
+        };
+        let material = Material {
+            name: material_obj.name().map(Into::into),
+            diffuse_texture,
+            data: ShadingData::Phong(PhongData {
+                lambert,
+                specular,
+                shininess: roughness_to_shininess(pbr.roughness_factor()),
+                reflection: RGB::new(0.0, 0.0, 0.0),
+            }),
+            double_sided: material_obj.double_sided(),
+            // The cutoff defaults to 0.5 in the specification.
+            alpha_cutoff: (material_obj.alpha_mode() == AlphaMode::Mask)
+                .then(|| material_obj.alpha_cutoff().unwrap_or(0.5)),
+        };
+
+        debug!(
+            "Successfully loaded material: name={:?}",
+            material_obj.name()
+        );
+
+        let index = self.scene.add_material(material);
+        self.material_indices.insert(material_obj.index(), index);
+        Ok(index)
+    }
+
+    /// Loads the texture.
+    fn load_texture(
+        &mut self,
+        texture_obj: GltfTexture<'_>,
+        transparent: bool,
+    ) -> anyhow::Result<TextureIndex> {
+        let key = (texture_obj.index(), transparent);
+        if let Some(index) = self.texture_indices.get(&key) {
+            return Ok(*index);
+        }
+
+        debug!("Loading texture: name={:?}", texture_obj.name());
+
+        let image_data = self
+            .images
+            .get(texture_obj.source().index())
+            .ok_or_else(|| anyhow!("Image not found for texture {:?}", texture_obj.name()))?;
+        let sampler = texture_obj.sampler();
+        let image = TextureImage::from(
+            to_dynamic_image(image_data).context("Failed to load texture image")?,
+        );
+        let texture = Texture {
+            name: texture_obj
+                .name()
+                .or_else(|| texture_obj.source().name())
+                .map(Into::into),
+            transparent: transparent && image.is_opaque() != Some(true),
+            image,
+            wrap_mode_u: to_wrap_mode(sampler.wrap_s()),
+            wrap_mode_v: to_wrap_mode(sampler.wrap_t()),
+        };
+
+        debug!("Successfully loaded texture: name={:?}", texture_obj.name());
+
+        let index = self.scene.add_texture(texture);
+        self.texture_indices.insert(key, index);
+        Ok(index)
+    }
+}
+
+/// Converts the vertex indices of the primitive to triangle list indices.
+///
+/// Returns `None` if the primitive is not made of triangles.
+fn triangulate(mode: Mode, indices: &[u32]) -> Option<Vec<u32>> {
+    match mode {
+        Mode::Triangles => Some(indices.to_vec()),
+        Mode::TriangleStrip => Some(
+            indices
+                .windows(3)
+                .enumerate()
+                .flat_map(|(i, tri)| {
+                    // Every other triangle in a strip has the opposite winding.
+                    if i % 2 == 0 {
+                        [tri[0], tri[1], tri[2]]
+                    } else {
+                        [tri[1], tri[0], tri[2]]
+                    }
+                })
+                .collect(),
+        ),
+        Mode::TriangleFan => Some(
+            indices
+                .get(1..)
+                .unwrap_or_default()
+                .windows(2)
+                .flat_map(|edge| [indices[0], edge[0], edge[1]])
+                .collect(),
+        ),
+        Mode::Points | Mode::Lines | Mode::LineLoop | Mode::LineStrip => None,
+    }
+}
+
+/// Converts the perceptual roughness to Phong shininess.
+///
+/// This is the inverse of the conversion in [`PhongData::to_pbr`].
+fn roughness_to_shininess(roughness: f32) -> f32 {
+    // Avoid infinite shininess for perfect mirrors.
+    let alpha = (roughness * roughness).max(1e-3);
+    2.0 / (alpha * alpha) - 2.0
+}
+
+/// Converts the glTF wrapping mode.
+///
+/// Mirrored repeat is not supported and is treated as repeat.
+fn to_wrap_mode(mode: WrappingMode) -> WrapMode {
+    match mode {
+        WrappingMode::ClampToEdge => WrapMode::ClampToEdge,
+        WrappingMode::MirroredRepeat | WrappingMode::Repeat => WrapMode::Repeat,
+    }
+}
+
+/// Converts the glTF image data to an image.
+fn to_dynamic_image(data: &gltf_image::Data) -> anyhow::Result<DynamicImage> {
+    use gltf_image::Format;
+
+    let (width, height) = (data.width, data.height);
+    let pixels = data.pixels.clone();
+    let to_u16 = |pixels: &[u8]| {
+        pixels
+            .chunks_exact(2)
+            .map(|v| u16::from_ne_bytes([v[0], v[1]]))
+            .collect::<Vec<_>>()
+    };
+    let image = match data.format {
+        Format::R8 => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLuma8),
+        Format::R8G8 => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLumaA8),
+        Format::R8G8B8 => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8),
+        Format::R8G8B8A8 => {
+            ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgba8)
+        }
+        Format::R16G16B16 => {
+            ImageBuffer::from_raw(width, height, to_u16(&pixels)).map(DynamicImage::ImageRgb16)
+        }
+        Format::R16G16B16A16 => {
+            ImageBuffer::from_raw(width, height, to_u16(&pixels)).map(DynamicImage::ImageRgba16)
+        }
+        format => bail!("Unsupported image format: {:?}", format),
+    };
+
+    image.ok_or_else(|| anyhow!("Image data is smaller than the image size"))
+}
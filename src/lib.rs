@@ -2,9 +2,18 @@
 #![warn(missing_docs)]
 #![warn(clippy::missing_docs_in_private_items)]
 
-pub use self::cli_opt::CliOpt;
+pub use self::cli_opt::{
+    AntiAliasing, Background, CliOpt, Command, CullFaces, DepthFormat, DiffOpt, DumpFormat,
+    DumpOpt, ExportOpt, ExtractTexturesOpt, InfoFormat, InfoOpt, MaterialOverride,
+    PremultipliedAlpha, StereoMode, SwapchainFormat, TextureCacheMode, TextureRemap, Winding,
+};
 
+pub mod camera;
 mod cli_opt;
 pub mod data;
+pub mod export;
 pub mod fbx;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod gltf;
 pub mod util;
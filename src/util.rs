@@ -1,4 +1,7 @@
 //! Utils.
 
 pub mod bbox;
+pub mod frustum;
 pub mod iter;
+pub mod memory;
+pub mod texture_cache;
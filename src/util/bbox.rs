@@ -2,7 +2,10 @@
 
 use std::iter::FromIterator;
 
-use cgmath::{num_traits::Float, BaseFloat, Point3, Vector3};
+use cgmath::{
+    num_traits::Float, BaseFloat, EuclideanSpace, InnerSpace, Matrix4, MetricSpace, Point3,
+    Transform, Vector3, Zero,
+};
 
 /// 3D bounding box.
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -26,12 +29,91 @@ impl<S: BaseFloat> BoundingBox3d<S> {
 
     /// Returns the size of the bounding box.
     pub fn size(&self) -> Vector3<S> {
-        use cgmath::EuclideanSpace;
-
         self.max.to_vec() - self.min.to_vec()
     }
 
-    /// Extedns the bounding box to contain the given point.
+    /// Returns the center of the bounding box.
+    pub fn center(&self) -> Point3<S> {
+        self.min.midpoint(self.max)
+    }
+
+    /// Returns whether the bounding box contains the given point.
+    ///
+    /// Points on the faces are contained.
+    pub fn contains_point(&self, p: Point3<S>) -> bool {
+        (self.min.x <= p.x && p.x <= self.max.x)
+            && (self.min.y <= p.y && p.y <= self.max.y)
+            && (self.min.z <= p.z && p.z <= self.max.z)
+    }
+
+    /// Same as [`contains_point`][`Self::contains_point`].
+    pub fn contains(&self, p: Point3<S>) -> bool {
+        self.contains_point(p)
+    }
+
+    /// Returns whether the bounding box contains the other box entirely.
+    ///
+    /// Boxes contain themselves.
+    pub fn contains_box(&self, o: &BoundingBox3d<S>) -> bool {
+        self.contains_point(o.min) && self.contains_point(o.max)
+    }
+
+    /// Returns whether the bounding boxes intersect.
+    ///
+    /// Boxes touching at a face, an edge, or a corner intersect.
+    pub fn intersects(&self, o: &BoundingBox3d<S>) -> bool {
+        (self.min.x <= o.max.x && o.min.x <= self.max.x)
+            && (self.min.y <= o.max.y && o.min.y <= self.max.y)
+            && (self.min.z <= o.max.z && o.min.z <= self.max.z)
+    }
+
+    /// Returns the intersection of the bounding boxes, or `None` if they do
+    /// not intersect.
+    ///
+    /// Boxes touching each other intersect at a flat box.
+    pub fn intersection(&self, o: &BoundingBox3d<S>) -> Option<Self> {
+        if !self.intersects(o) {
+            return None;
+        }
+        Some(Self {
+            min: element_wise_apply(self.min, o.min, Float::max),
+            max: element_wise_apply(self.max, o.max, Float::min),
+        })
+    }
+
+    /// Returns the eight corners.
+    ///
+    /// The `i`-th corner takes the maximum x, y, and z if the bits 0, 1, and
+    /// 2 of `i` are set respectively, and the minimum otherwise.
+    pub fn corners(&self) -> [Point3<S>; 8] {
+        let (min, max) = (self.min, self.max);
+        [0, 1, 2, 3, 4, 5, 6, 7].map(|i| {
+            let pick = |bit: usize, min: S, max: S| if i & bit == 0 { min } else { max };
+            Point3::new(
+                pick(1, min.x, max.x),
+                pick(2, min.y, max.y),
+                pick(4, min.z, max.z),
+            )
+        })
+    }
+
+    /// Returns the bounding box of this box transformed by the affine
+    /// matrix.
+    ///
+    /// The result contains the transformed box, but is larger than it if the
+    /// matrix rotates the box.
+    pub fn transformed(&self, m: &Matrix4<S>) -> Self {
+        let [first, rest @ ..] = self.corners();
+        BoundingBox3d::from(m.transform_point(first))
+            .insert_extend(rest.iter().map(|&p| m.transform_point(p)))
+    }
+
+    /// Same as [`transformed`][`Self::transformed`].
+    pub fn transform(&self, m: &Matrix4<S>) -> Self {
+        self.transformed(m)
+    }
+
+    /// Extends the bounding box to contain the given point.
     pub fn insert(&self, p: Point3<S>) -> Self {
         Self {
             min: element_wise_apply(self.min, p, Float::min),
@@ -39,7 +121,7 @@ impl<S: BaseFloat> BoundingBox3d<S> {
         }
     }
 
-    /// Extedns the bounding box to contain the given points.
+    /// Extends the bounding box to contain the given points.
     pub fn insert_extend(&self, iter: impl IntoIterator<Item = Point3<S>>) -> Self {
         iter.into_iter().fold(*self, |bbox, p| bbox.insert(p))
     }
@@ -88,14 +170,14 @@ impl<S: BaseFloat> OptionalBoundingBox3d<S> {
         self.bbox
     }
 
-    /// Extedns the bounding box to contain the given point.
+    /// Extends the bounding box to contain the given point.
     pub fn insert(&self, p: Point3<S>) -> Self {
         self.bbox
             .map_or_else(|| p.into(), |bbox| bbox.insert(p))
             .into()
     }
 
-    /// Extedns the bounding box to contain the given points.
+    /// Extends the bounding box to contain the given points.
     pub fn insert_extend(&self, iter: impl IntoIterator<Item = Point3<S>>) -> Self {
         iter.into_iter().fold(*self, |bbox, p| bbox.insert(p))
     }
@@ -113,6 +195,37 @@ impl<S: BaseFloat> OptionalBoundingBox3d<S> {
     pub fn union_extend(&self, iter: impl IntoIterator<Item = OptionalBoundingBox3d<S>>) -> Self {
         iter.into_iter().fold(*self, |bbox, p| bbox.union(&p))
     }
+
+    /// Returns the bounding box of this box transformed by the affine
+    /// matrix.
+    ///
+    /// See [`BoundingBox3d::transformed`].
+    pub fn transformed(&self, m: &Matrix4<S>) -> Self {
+        self.bbox.map(|bbox| bbox.transformed(m)).into()
+    }
+
+    /// Same as [`transformed`][`Self::transformed`].
+    pub fn transform(&self, m: &Matrix4<S>) -> Self {
+        self.transformed(m)
+    }
+
+    /// Returns whether the bounding box contains the given point.
+    ///
+    /// Empty boxes contain no points.
+    pub fn contains_point(&self, p: Point3<S>) -> bool {
+        self.bbox.is_some_and(|bbox| bbox.contains_point(p))
+    }
+
+    /// Returns the intersection of the bounding boxes.
+    ///
+    /// The result is empty if either of them is empty or they do not
+    /// intersect.
+    pub fn intersection(&self, o: &OptionalBoundingBox3d<S>) -> Self {
+        match (&self.bbox, &o.bbox) {
+            (Some(b), Some(o)) => b.intersection(o).into(),
+            _ => Self::new(),
+        }
+    }
 }
 
 impl<S> Default for OptionalBoundingBox3d<S> {
@@ -242,6 +355,285 @@ impl<'a, S: 'a + BaseFloat> FromIterator<&'a OptionalBoundingBox3d<S>>
     }
 }
 
+/// Bounding sphere.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere<S> {
+    /// Center.
+    center: Point3<S>,
+    /// Radius.
+    radius: S,
+}
+
+impl<S: BaseFloat> BoundingSphere<S> {
+    /// Creates a new bounding sphere.
+    pub fn new(center: Point3<S>, radius: S) -> Self {
+        Self { center, radius }
+    }
+
+    /// Computes a bounding sphere of the given points using Ritter's
+    /// algorithm.
+    ///
+    /// The result is not the minimal sphere, but is usually only a few
+    /// percent larger than it.
+    /// Returns `None` if there are no points.
+    pub fn from_points(points: &[Point3<S>]) -> Option<Self> {
+        let first = *points.first()?;
+        let farthest_from = |p: Point3<S>| {
+            points.iter().fold(p, |farthest, &q| {
+                if p.distance2(q) > p.distance2(farthest) {
+                    q
+                } else {
+                    farthest
+                }
+            })
+        };
+        let a = farthest_from(first);
+        let b = farthest_from(a);
+        let initial = Self {
+            center: a.midpoint(b),
+            radius: a.distance(b) / two(),
+        };
+
+        Some(initial.insert_extend(points.iter().cloned()))
+    }
+
+    /// Returns the center.
+    pub fn center(&self) -> Point3<S> {
+        self.center
+    }
+
+    /// Returns the radius.
+    pub fn radius(&self) -> S {
+        self.radius
+    }
+
+    /// Returns whether the sphere contains the given point.
+    pub fn contains(&self, p: Point3<S>) -> bool {
+        self.center.distance2(p) <= self.radius * self.radius
+    }
+
+    /// Extends the sphere to contain the given point.
+    pub fn insert(&self, p: Point3<S>) -> Self {
+        let distance = self.center.distance(p);
+        if distance <= self.radius {
+            return *self;
+        }
+        let radius = (self.radius + distance) / two();
+        Self {
+            center: self.center + (p - self.center) * ((radius - self.radius) / distance),
+            radius,
+        }
+    }
+
+    /// Extends the sphere to contain the given points.
+    pub fn insert_extend(&self, iter: impl IntoIterator<Item = Point3<S>>) -> Self {
+        iter.into_iter().fold(*self, |sphere, p| sphere.insert(p))
+    }
+
+    /// Returns the smallest sphere containing both spheres.
+    pub fn union(&self, o: &BoundingSphere<S>) -> Self {
+        let distance = self.center.distance(o.center);
+        if distance + o.radius <= self.radius {
+            return *self;
+        }
+        if distance + self.radius <= o.radius {
+            return *o;
+        }
+        let radius = (distance + self.radius + o.radius) / two();
+        Self {
+            center: self.center + (o.center - self.center) * ((radius - self.radius) / distance),
+            radius,
+        }
+    }
+}
+
+impl<S: BaseFloat> From<&BoundingBox3d<S>> for BoundingSphere<S> {
+    fn from(bbox: &BoundingBox3d<S>) -> Self {
+        Self {
+            center: bbox.min.midpoint(bbox.max),
+            radius: bbox.size().magnitude() / two(),
+        }
+    }
+}
+
+/// Oriented bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrientedBoundingBox3d<S> {
+    /// Center.
+    center: Point3<S>,
+    /// Orthonormal axes.
+    axes: [Vector3<S>; 3],
+    /// Half of the extents along the axes.
+    half_extents: Vector3<S>,
+}
+
+impl<S: BaseFloat> OrientedBoundingBox3d<S> {
+    /// Computes an oriented bounding box of the given points.
+    ///
+    /// Axes are the principal components of the points.
+    /// If the axis-aligned box is tighter than that, the axis-aligned box is
+    /// returned.
+    /// Returns `None` if there are no points.
+    pub fn from_points(points: &[Point3<S>]) -> Option<Self> {
+        if points.is_empty() {
+            return None;
+        }
+
+        let num_points = S::from(points.len()).expect("Should never fail");
+        let mean = points
+            .iter()
+            .fold(Vector3::zero(), |sum, p| sum + p.to_vec())
+            / num_points;
+        let mut covariance = [[S::zero(); 3]; 3];
+        for p in points {
+            let d = p.to_vec() - mean;
+            for (row, &d_row) in covariance.iter_mut().zip(&[d.x, d.y, d.z]) {
+                row[0] += d_row * d.x;
+                row[1] += d_row * d.y;
+                row[2] += d_row * d.z;
+            }
+        }
+
+        let pca = Self::with_axes(points, symmetric_eigenvectors(covariance));
+        let aligned = Self::with_axes(
+            points,
+            [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()],
+        );
+        if aligned.volume() <= pca.volume() {
+            Some(aligned)
+        } else {
+            Some(pca)
+        }
+    }
+
+    /// Creates the smallest box with the given axes containing the points.
+    ///
+    /// `points` should not be empty.
+    fn with_axes(points: &[Point3<S>], axes: [Vector3<S>; 3]) -> Self {
+        let mut min = [S::infinity(); 3];
+        let mut max = [S::neg_infinity(); 3];
+        for p in points {
+            for (axis_i, axis) in axes.iter().enumerate() {
+                let t = axis.dot(p.to_vec());
+                min[axis_i] = min[axis_i].min(t);
+                max[axis_i] = max[axis_i].max(t);
+            }
+        }
+        let center = axes
+            .iter()
+            .enumerate()
+            .fold(Vector3::zero(), |sum, (i, &axis)| {
+                sum + axis * ((min[i] + max[i]) / two())
+            });
+
+        Self {
+            center: Point3::from_vec(center),
+            axes,
+            half_extents: Vector3::new(
+                (max[0] - min[0]) / two(),
+                (max[1] - min[1]) / two(),
+                (max[2] - min[2]) / two(),
+            ),
+        }
+    }
+
+    /// Returns the center.
+    pub fn center(&self) -> Point3<S> {
+        self.center
+    }
+
+    /// Returns the orthonormal axes.
+    pub fn axes(&self) -> [Vector3<S>; 3] {
+        self.axes
+    }
+
+    /// Returns the half of the extents along the axes.
+    pub fn half_extents(&self) -> Vector3<S> {
+        self.half_extents
+    }
+
+    /// Returns the extents along the axes.
+    pub fn size(&self) -> Vector3<S> {
+        self.half_extents * two()
+    }
+
+    /// Returns the volume.
+    pub fn volume(&self) -> S {
+        let size = self.size();
+        size.x * size.y * size.z
+    }
+
+    /// Returns the eight corners.
+    pub fn corners(&self) -> [Point3<S>; 8] {
+        let [x, y, z] = self.axes;
+        let (x, y, z) = (
+            x * self.half_extents.x,
+            y * self.half_extents.y,
+            z * self.half_extents.z,
+        );
+        let c = self.center;
+        [
+            c - x - y - z,
+            c + x - y - z,
+            c - x + y - z,
+            c + x + y - z,
+            c - x - y + z,
+            c + x - y + z,
+            c - x + y + z,
+            c + x + y + z,
+        ]
+    }
+
+    /// Returns the bounding sphere of the box.
+    pub fn bounding_sphere(&self) -> BoundingSphere<S> {
+        BoundingSphere::new(self.center, self.half_extents.magnitude())
+    }
+}
+
+/// Returns normalized eigenvectors of the given symmetric matrix, using the
+/// Jacobi eigenvalue algorithm.
+fn symmetric_eigenvectors<S: BaseFloat>(mut a: [[S; 3]; 3]) -> [Vector3<S>; 3] {
+    /// Maximum number of sweeps.
+    const MAX_SWEEPS: usize = 32;
+
+    let mut v = [[S::zero(); 3]; 3];
+    for (i, row) in v.iter_mut().enumerate() {
+        row[i] = S::one();
+    }
+    for _ in 0..MAX_SWEEPS {
+        let off_diagonal = a[0][1].abs() + a[0][2].abs() + a[1][2].abs();
+        if off_diagonal <= S::default_epsilon() {
+            break;
+        }
+        for &(p, q) in &[(0, 1), (0, 2), (1, 2)] {
+            if a[p][q].abs() <= S::default_epsilon() {
+                continue;
+            }
+            // Rotate so that `a[p][q]` becomes zero.
+            let theta = (a[q][q] - a[p][p]) / (two::<S>() * a[p][q]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + S::one()).sqrt());
+            let c = S::one() / (t * t + S::one()).sqrt();
+            let s = t * c;
+            for row in a.iter_mut().chain(v.iter_mut()) {
+                let (kp, kq) = (row[p], row[q]);
+                row[p] = c * kp - s * kq;
+                row[q] = s * kp + c * kq;
+            }
+            let (row_p, row_q) = (a[p], a[q]);
+            a[p] = [0, 1, 2].map(|k| c * row_p[k] - s * row_q[k]);
+            a[q] = [0, 1, 2].map(|k| s * row_p[k] + c * row_q[k]);
+        }
+    }
+
+    let column = |i: usize| Vector3::new(v[0][i], v[1][i], v[2][i]).normalize();
+    [column(0), column(1), column(2)]
+}
+
+/// Returns 2.
+fn two<S: BaseFloat>() -> S {
+    S::one() + S::one()
+}
+
 /// Applies the given function element wise.
 fn element_wise_apply<S, U, F>(a: Point3<S>, b: Point3<S>, f: F) -> Point3<U>
 where
@@ -249,3 +641,280 @@ where
 {
     Point3::new(f(a.x, b.x), f(a.y, b.y), f(a.z, b.z))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::{Deg, Matrix4};
+
+    /// Returns the box from `(0, 0, 0)` to `(1, 2, 3)`.
+    fn unit_box() -> BoundingBox3d<f64> {
+        BoundingBox3d::from(Point3::new(0.0, 0.0, 0.0)).insert(Point3::new(1.0, 2.0, 3.0))
+    }
+
+    #[test]
+    fn center_and_size() {
+        let bbox = unit_box();
+        assert_eq!(bbox.center(), Point3::new(0.5, 1.0, 1.5));
+        assert_eq!(bbox.size(), Vector3::new(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn contains_point() {
+        let bbox = unit_box();
+        assert!(bbox.contains_point(Point3::new(0.5, 1.0, 1.5)));
+        assert!(bbox.contains_point(Point3::new(1.0, 2.0, 3.0)));
+        assert!(!bbox.contains_point(Point3::new(1.5, 1.0, 1.5)));
+        assert!(!bbox.contains_point(Point3::new(0.5, -0.1, 1.5)));
+        // The alias agrees.
+        assert!(bbox.contains(Point3::new(0.5, 1.0, 1.5)));
+        assert!(!bbox.contains(Point3::new(1.5, 1.0, 1.5)));
+
+        let optional = OptionalBoundingBox3d::from(bbox);
+        assert!(optional.contains_point(Point3::new(0.5, 1.0, 1.5)));
+        assert!(!optional.contains_point(Point3::new(1.5, 1.0, 1.5)));
+        assert!(!OptionalBoundingBox3d::new().contains_point(Point3::new(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn contains_box() {
+        let bbox = unit_box();
+        assert!(bbox.contains_box(&bbox));
+        let inner =
+            BoundingBox3d::from(Point3::new(0.25, 0.5, 0.75)).insert(Point3::new(1.0, 1.0, 1.0));
+        assert!(bbox.contains_box(&inner));
+        assert!(!inner.contains_box(&bbox));
+        let crossing = inner.insert(Point3::new(1.5, 1.0, 1.0));
+        assert!(!bbox.contains_box(&crossing));
+    }
+
+    #[test]
+    fn intersection() {
+        let bbox = unit_box();
+        let other =
+            BoundingBox3d::from(Point3::new(0.5, 1.0, -1.0)).insert(Point3::new(2.0, 3.0, 1.0));
+        let expected =
+            BoundingBox3d::from(Point3::new(0.5, 1.0, 0.0)).insert(Point3::new(1.0, 2.0, 1.0));
+        assert_eq!(bbox.intersection(&other), Some(expected));
+        assert_eq!(other.intersection(&bbox), Some(expected));
+
+        // Touching boxes intersect at a flat box.
+        let touching =
+            BoundingBox3d::from(Point3::new(1.0, 0.0, 0.0)).insert(Point3::new(2.0, 2.0, 3.0));
+        let face = bbox
+            .intersection(&touching)
+            .expect("Touching boxes should intersect");
+        assert_eq!(face.size(), Vector3::new(0.0, 2.0, 3.0));
+
+        let apart = BoundingBox3d::from(Point3::new(0.0, 0.0, 4.0));
+        assert_eq!(bbox.intersection(&apart), None);
+        assert_eq!(
+            OptionalBoundingBox3d::from(bbox)
+                .intersection(&apart.into())
+                .bounding_box(),
+            None
+        );
+    }
+
+    #[test]
+    fn intersects() {
+        let bbox = unit_box();
+        let moved = |x: f64| {
+            BoundingBox3d::from(Point3::new(x, 1.0, 1.0)).insert(Point3::new(x + 1.0, 4.0, 4.0))
+        };
+        assert!(bbox.intersects(&moved(0.5)));
+        assert!(bbox.intersects(&moved(1.0)));
+        assert!(!bbox.intersects(&moved(1.5)));
+        assert!(moved(0.5).intersects(&bbox));
+        // Nested boxes intersect.
+        let inner = BoundingBox3d::from(Point3::new(0.25, 0.5, 0.75));
+        assert!(bbox.intersects(&inner));
+        assert!(inner.intersects(&bbox));
+    }
+
+    #[test]
+    fn corners() {
+        let corners = unit_box().corners();
+        assert_eq!(corners[0], Point3::new(0.0, 0.0, 0.0));
+        assert_eq!(corners[5], Point3::new(1.0, 0.0, 3.0));
+        assert_eq!(corners[7], Point3::new(1.0, 2.0, 3.0));
+        let collected = corners.iter().collect::<OptionalBoundingBox3d<f64>>();
+        assert_eq!(collected.bounding_box(), Some(unit_box()));
+    }
+
+    #[test]
+    fn transformed() {
+        let bbox = unit_box();
+        let translated = bbox.transformed(&Matrix4::from_translation(Vector3::new(1.0, 0.0, -1.0)));
+        assert_eq!(translated.min(), Point3::new(1.0, 0.0, -1.0));
+        assert_eq!(translated.max(), Point3::new(2.0, 2.0, 2.0));
+
+        // Rotating by 90 degrees around Z maps `(x, y)` to `(-y, x)`.
+        let rotated = bbox.transformed(&Matrix4::from_angle_z(Deg(90.0)));
+        assert!((rotated.min() - Point3::new(-2.0, 0.0, 0.0)).magnitude() < 1e-9);
+        assert!((rotated.max() - Point3::new(0.0, 1.0, 3.0)).magnitude() < 1e-9);
+
+        // Mirroring keeps the minimum below the maximum.
+        let mirrored = bbox.transformed(&Matrix4::from_nonuniform_scale(-1.0, 1.0, 1.0));
+        assert_eq!(mirrored.min(), Point3::new(-1.0, 0.0, 0.0));
+        assert_eq!(mirrored.max(), Point3::new(0.0, 2.0, 3.0));
+
+        // The alias agrees.
+        let scale = Matrix4::from_scale(2.0);
+        assert_eq!(bbox.transform(&scale), bbox.transformed(&scale));
+
+        let optional = OptionalBoundingBox3d::from(bbox);
+        assert_eq!(
+            optional.transformed(&scale).bounding_box(),
+            Some(bbox.transformed(&scale))
+        );
+        assert_eq!(optional.transform(&scale), optional.transformed(&scale));
+        let empty = OptionalBoundingBox3d::<f64>::new();
+        assert_eq!(empty.transformed(&scale), empty);
+    }
+
+    /// Returns points scattered in the box from `(-1, -2, -3)` to `(1, 2, 3)`.
+    fn scattered_points() -> Vec<Point3<f64>> {
+        let mut state = 1_u32;
+        let mut random = move || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) as f64 / (1 << 24) as f64 * 2.0 - 1.0
+        };
+        (0..200)
+            .map(|_| Point3::new(random(), random() * 2.0, random() * 3.0))
+            .collect()
+    }
+
+    /// Asserts that the vectors are orthonormal.
+    fn assert_orthonormal(vectors: &[Vector3<f64>; 3]) {
+        for (i, a) in vectors.iter().enumerate() {
+            for (j, b) in vectors.iter().enumerate() {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!(
+                    (a.dot(*b) - expected).abs() < 1e-9,
+                    "Not orthonormal: {:?}",
+                    vectors
+                );
+            }
+        }
+    }
+
+    /// Returns whether the oriented box contains the point, within the
+    /// epsilon.
+    fn obb_contains(obb: &OrientedBoundingBox3d<f64>, p: Point3<f64>) -> bool {
+        let d = p - obb.center();
+        obb.axes()
+            .iter()
+            .zip(&[
+                obb.half_extents().x,
+                obb.half_extents().y,
+                obb.half_extents().z,
+            ])
+            .all(|(axis, &half_extent)| axis.dot(d).abs() <= half_extent + 1e-9)
+    }
+
+    #[test]
+    fn bounding_sphere_from_points() {
+        assert_eq!(BoundingSphere::<f64>::from_points(&[]), None);
+
+        let point = Point3::new(1.0, 2.0, 3.0);
+        let single = BoundingSphere::from_points(&[point]).unwrap();
+        assert_eq!((single.center(), single.radius()), (point, 0.0));
+
+        let points = scattered_points();
+        let sphere = BoundingSphere::from_points(&points).unwrap();
+        for &p in &points {
+            assert!(
+                sphere.center().distance(p) <= sphere.radius() + 1e-9,
+                "{:?} is outside of {:?}",
+                p,
+                sphere
+            );
+        }
+
+        // The minimal sphere of the corners has the half diagonal as the
+        // radius.
+        let corners = unit_box().corners();
+        let sphere = BoundingSphere::from_points(&corners).unwrap();
+        let half_diagonal = unit_box().size().magnitude() / 2.0;
+        assert!(corners
+            .iter()
+            .all(|&p| sphere.center().distance(p) <= sphere.radius() + 1e-9));
+        assert!(sphere.radius() <= half_diagonal * 1.1, "{:?}", sphere);
+    }
+
+    #[test]
+    fn oriented_bounding_box_from_points() {
+        assert_eq!(OrientedBoundingBox3d::<f64>::from_points(&[]), None);
+
+        let point = Point3::new(1.0, 2.0, 3.0);
+        let single = OrientedBoundingBox3d::from_points(&[point]).unwrap();
+        assert!((single.center() - point).magnitude() < 1e-9);
+        assert_eq!(single.half_extents(), Vector3::new(0.0, 0.0, 0.0));
+
+        let points = scattered_points();
+        let obb = OrientedBoundingBox3d::from_points(&points).unwrap();
+        assert_orthonormal(&obb.axes());
+        for &p in &points {
+            assert!(obb_contains(&obb, p), "{:?} is outside of {:?}", p, obb);
+        }
+
+        // The box of the rotated corners is the rotated box.
+        let transform = Matrix4::from_translation(Vector3::new(5.0, -1.0, 2.0))
+            * Matrix4::from_axis_angle(Vector3::new(1.0, 1.0, 0.0).normalize(), Deg(30.0))
+            * Matrix4::from_angle_z(Deg(20.0));
+        let box_size = Vector3::new(4.0, 2.0, 1.0);
+        let original =
+            BoundingBox3d::from(Point3::new(0.0, 0.0, 0.0)).insert(Point3::from_vec(box_size));
+        let corners = original
+            .corners()
+            .map(|p| Point3::from_homogeneous(transform * p.to_homogeneous()));
+        let obb = OrientedBoundingBox3d::from_points(&corners).unwrap();
+        assert_orthonormal(&obb.axes());
+        assert!(corners.iter().all(|&p| obb_contains(&obb, p)));
+        let mut size = [obb.size().x, obb.size().y, obb.size().z];
+        size.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        for (actual, expected) in size.iter().zip(&[1.0, 2.0, 4.0]) {
+            assert!((actual - expected).abs() < 1e-6, "{:?}", obb);
+        }
+        let center = Point3::from_homogeneous(transform * original.center().to_homogeneous());
+        assert!((obb.center() - center).magnitude() < 1e-6, "{:?}", obb);
+    }
+
+    #[test]
+    fn eigenvectors() {
+        // The eigenvectors of the diagonal matrices are the axes.
+        let vectors = symmetric_eigenvectors([[3.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 2.0]]);
+        assert_orthonormal(&vectors);
+        for (v, axis) in
+            vectors
+                .iter()
+                .zip(&[Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()])
+        {
+            assert!((v.dot(*axis).abs() - 1.0).abs() < 1e-9, "{:?}", vectors);
+        }
+        let vectors = symmetric_eigenvectors([[2.0, 0.0, 0.0], [0.0, 2.0, 0.0], [0.0, 0.0, 2.0]]);
+        assert_orthonormal(&vectors);
+
+        // The eigenvalues are 4, 2, and 2.
+        let a = [[3.0, 1.0, 0.0], [1.0, 3.0, 0.0], [0.0, 0.0, 2.0]];
+        let vectors = symmetric_eigenvectors(a);
+        assert_orthonormal(&vectors);
+        let mut eigenvalues = Vec::new();
+        for v in &vectors {
+            let av = Vector3::new(
+                a[0][0] * v.x + a[0][1] * v.y + a[0][2] * v.z,
+                a[1][0] * v.x + a[1][1] * v.y + a[1][2] * v.z,
+                a[2][0] * v.x + a[2][1] * v.y + a[2][2] * v.z,
+            );
+            let eigenvalue = v.dot(av);
+            assert!((av - v * eigenvalue).magnitude() < 1e-9, "{:?}", vectors);
+            eigenvalues.push(eigenvalue);
+        }
+        eigenvalues.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert!((eigenvalues[0] - 2.0).abs() < 1e-9);
+        assert!((eigenvalues[1] - 2.0).abs() < 1e-9);
+        assert!((eigenvalues[2] - 4.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,216 @@
+//! View frustum.
+
+use cgmath::{BaseFloat, EuclideanSpace, InnerSpace, Matrix, Matrix4, Point3, Vector3, Vector4};
+
+use crate::util::bbox::{BoundingBox3d, BoundingSphere};
+
+/// Range of the depth in the clip space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipDepth {
+    /// From `-w` to `w`, as OpenGL and `cgmath` projections.
+    NegativeOneToOne,
+    /// From `0` to `w`, as Vulkan and Direct3D.
+    ZeroToOne,
+}
+
+/// Plane.
+///
+/// Points on the side the normal points to are in front of the plane.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Plane<S> {
+    /// Unit normal.
+    normal: Vector3<S>,
+    /// Signed distance of the origin from the plane.
+    distance: S,
+}
+
+impl<S: BaseFloat> Plane<S> {
+    /// Creates a new plane `dot(normal, p) + distance = 0` from the
+    /// coefficients, normalizing them.
+    ///
+    /// Returns `None` if the normal is zero.
+    pub fn from_coefficients(v: Vector4<S>) -> Option<Self> {
+        let normal = v.truncate();
+        let length = normal.magnitude();
+        if length <= S::zero() || !length.is_finite() {
+            return None;
+        }
+        Some(Self {
+            normal: normal / length,
+            distance: v.w / length,
+        })
+    }
+
+    /// Returns the unit normal.
+    pub fn normal(&self) -> Vector3<S> {
+        self.normal
+    }
+
+    /// Returns the signed distance of the origin from the plane.
+    pub fn distance(&self) -> S {
+        self.distance
+    }
+
+    /// Returns the signed distance of the point from the plane.
+    ///
+    /// The distance is positive in front of the plane.
+    pub fn signed_distance(&self, p: Point3<S>) -> S {
+        self.normal.dot(p.to_vec()) + self.distance
+    }
+}
+
+/// View frustum, the volume visible through a view-projection matrix.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum<S> {
+    /// Left, right, bottom, top, near, and far planes facing inward.
+    ///
+    /// Planes of degenerate projections (such as the far plane of infinite
+    /// projections) are `None`, and bound nothing.
+    planes: [Option<Plane<S>>; 6],
+}
+
+impl<S: BaseFloat> Frustum<S> {
+    /// Extracts the frustum planes from the view-projection matrix.
+    pub fn from_view_proj(view_proj: &Matrix4<S>, depth: ClipDepth) -> Self {
+        let [x, y, z, w] = [0, 1, 2, 3].map(|i| view_proj.row(i));
+        let near = match depth {
+            ClipDepth::NegativeOneToOne => w + z,
+            ClipDepth::ZeroToOne => z,
+        };
+        let planes = [w + x, w - x, w + y, w - y, near, w - z].map(Plane::from_coefficients);
+
+        Self { planes }
+    }
+
+    /// Returns the left, right, bottom, top, near, and far planes facing
+    /// inward.
+    ///
+    /// Planes of degenerate projections are `None`.
+    pub fn planes(&self) -> &[Option<Plane<S>>; 6] {
+        &self.planes
+    }
+
+    /// Returns whether the frustum contains the point.
+    pub fn contains_point(&self, p: Point3<S>) -> bool {
+        self.planes
+            .iter()
+            .flatten()
+            .all(|plane| plane.signed_distance(p) >= S::zero())
+    }
+
+    /// Returns whether the bounding box may intersect the frustum.
+    ///
+    /// The box is rejected only if it is entirely behind one of the planes,
+    /// so boxes near the edges of the frustum may be reported as intersecting
+    /// even if they are outside of it.
+    pub fn intersects_box(&self, bbox: &BoundingBox3d<S>) -> bool {
+        let (min, max) = (bbox.min(), bbox.max());
+        self.planes.iter().flatten().all(|plane| {
+            // The corner farthest in front of the plane.
+            let n = plane.normal;
+            let pick = |n: S, min: S, max: S| if n >= S::zero() { max } else { min };
+            let corner = Point3::new(
+                pick(n.x, min.x, max.x),
+                pick(n.y, min.y, max.y),
+                pick(n.z, min.z, max.z),
+            );
+            plane.signed_distance(corner) >= S::zero()
+        })
+    }
+
+    /// Returns whether the bounding sphere may intersect the frustum.
+    ///
+    /// As [`intersects_box`][`Self::intersects_box`], spheres near the edges
+    /// of the frustum may be reported as intersecting.
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere<S>) -> bool {
+        self.planes
+            .iter()
+            .flatten()
+            .all(|plane| plane.signed_distance(sphere.center()) >= -sphere.radius())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::{ortho, perspective, Deg};
+
+    /// Returns the frustum of a perspective camera at the origin looking
+    /// toward -Z, with the near and far planes at 1 and 10.
+    fn perspective_frustum() -> Frustum<f64> {
+        let proj = perspective(Deg(90.0), 1.0, 1.0, 10.0);
+        Frustum::from_view_proj(&proj, ClipDepth::NegativeOneToOne)
+    }
+
+    #[test]
+    fn contains_point() {
+        let frustum = perspective_frustum();
+        assert!(frustum.contains_point(Point3::new(0.0, 0.0, -5.0)));
+        assert!(frustum.contains_point(Point3::new(4.0, -4.0, -5.0)));
+        assert!(!frustum.contains_point(Point3::new(6.0, 0.0, -5.0)));
+        assert!(!frustum.contains_point(Point3::new(0.0, 0.0, -0.5)));
+        assert!(!frustum.contains_point(Point3::new(0.0, 0.0, -11.0)));
+        assert!(!frustum.contains_point(Point3::new(0.0, 0.0, 5.0)));
+    }
+
+    #[test]
+    fn planes_are_normalized() {
+        let frustum = perspective_frustum();
+        let planes = frustum.planes();
+        assert!(planes.iter().all(|plane| {
+            let plane = plane.expect("Planes should not be degenerate");
+            (plane.normal().magnitude() - 1.0).abs() < 1e-9
+        }));
+        let near = planes[4].expect("Near plane should not be degenerate");
+        assert!((near.signed_distance(Point3::new(0.0, 0.0, -3.0)) - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn zero_to_one_depth() {
+        // Vulkan projection with the depth mapped from `[-w, w]` to `[0, w]`.
+        let correction = Matrix4::new(
+            1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.5, 0.0, 0.0, 0.0, 0.5, 1.0,
+        );
+        let proj = correction * perspective(Deg(90.0), 1.0, 1.0, 10.0);
+        let frustum = Frustum::from_view_proj(&proj, ClipDepth::ZeroToOne);
+        assert!(frustum.contains_point(Point3::new(0.0, 0.0, -1.5)));
+        assert!(!frustum.contains_point(Point3::new(0.0, 0.0, -0.5)));
+        assert!(!frustum.contains_point(Point3::new(0.0, 0.0, -11.0)));
+    }
+
+    #[test]
+    fn intersects_box() {
+        let frustum = perspective_frustum();
+        let bbox = |min: [f64; 3], max: [f64; 3]| {
+            BoundingBox3d::from(Point3::from(min)).insert(Point3::from(max))
+        };
+        assert!(frustum.intersects_box(&bbox([-1.0, -1.0, -6.0], [1.0, 1.0, -4.0])));
+        // Crossing the right plane.
+        assert!(frustum.intersects_box(&bbox([4.0, -1.0, -6.0], [8.0, 1.0, -4.0])));
+        // Containing the whole frustum.
+        assert!(frustum.intersects_box(&bbox([-20.0, -20.0, -20.0], [20.0, 20.0, 20.0])));
+        assert!(!frustum.intersects_box(&bbox([7.0, -1.0, -6.0], [8.0, 1.0, -4.0])));
+        assert!(!frustum.intersects_box(&bbox([-1.0, -1.0, 1.0], [1.0, 1.0, 2.0])));
+        assert!(!frustum.intersects_box(&bbox([-1.0, -1.0, -20.0], [1.0, 1.0, -12.0])));
+    }
+
+    #[test]
+    fn intersects_sphere() {
+        let frustum = perspective_frustum();
+        let sphere = |center: [f64; 3], radius: f64| BoundingSphere::new(center.into(), radius);
+        assert!(frustum.intersects_sphere(&sphere([0.0, 0.0, -5.0], 1.0)));
+        assert!(frustum.intersects_sphere(&sphere([6.0, 0.0, -5.0], 1.0)));
+        assert!(!frustum.intersects_sphere(&sphere([8.0, 0.0, -5.0], 1.0)));
+        assert!(!frustum.intersects_sphere(&sphere([0.0, 0.0, 2.0], 1.0)));
+    }
+
+    #[test]
+    fn orthographic() {
+        let proj = ortho(-2.0, 2.0, -1.0, 1.0, 0.0, 4.0);
+        let frustum = Frustum::from_view_proj(&proj, ClipDepth::NegativeOneToOne);
+        assert!(frustum.contains_point(Point3::new(1.5, 0.5, -2.0)));
+        assert!(!frustum.contains_point(Point3::new(1.5, 1.5, -2.0)));
+        assert!(!frustum.contains_point(Point3::new(0.0, 0.0, -5.0)));
+    }
+}
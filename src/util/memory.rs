@@ -0,0 +1,13 @@
+//! Memory usage.
+
+/// Returns the peak resident set size of the process in bytes, if available.
+///
+/// This is supported only on Linux.
+pub fn peak_resident_size() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    // The line is like `VmHWM:     1234 kB`.
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+
+    Some(kib * 1024)
+}
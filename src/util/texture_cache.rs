@@ -0,0 +1,143 @@
+//! On-disk cache of decoded texture images.
+//!
+//! Decoded images are stored as RGBA8 in `fbx-viewer/textures` under the user
+//! cache directory (`$XDG_CACHE_HOME`, `%LOCALAPPDATA%`, or `~/.cache`), keyed
+//! by the hash and the length of the encoded image data.
+
+use std::{
+    env, fs,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, ensure, Context};
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use image::{DynamicImage, RgbaImage};
+use tracing::{debug, debug_span, warn};
+
+use crate::TextureCacheMode;
+
+/// Magic bytes of the cache files.
+const MAGIC: &[u8; 8] = b"FBXVTEX1";
+
+/// Flag of the cache files with compressed pixels.
+const FLAG_COMPRESSED: u8 = 1;
+
+/// Size of the header: magic, flags, width, and height.
+const HEADER_LEN: usize = MAGIC.len() + 1 + 4 + 4;
+
+/// Returns the decoded image, from the cache if available.
+///
+/// If the image is not cached, it is decoded by `decode` and written to the
+/// cache in the given mode.
+/// Failures of the cache are logged and otherwise ignored.
+pub fn load_or_decode(
+    content: &[u8],
+    mode: TextureCacheMode,
+    decode: impl FnOnce() -> anyhow::Result<DynamicImage>,
+) -> anyhow::Result<DynamicImage> {
+    let path = match cache_path(content) {
+        Some(v) => v,
+        None => return decode(),
+    };
+    if path.exists() {
+        match debug_span!("read_texture_cache").in_scope(|| read(&path)) {
+            Ok(image) => {
+                debug!("Loaded texture from cache: {}", path.display());
+                return Ok(DynamicImage::ImageRgba8(image));
+            }
+            Err(e) => warn!("Failed to read texture cache {}: {:#}", path.display(), e),
+        }
+    }
+
+    let image = decode()?;
+    if let Err(e) = write(&path, &image.to_rgba8(), mode) {
+        warn!("Failed to write texture cache {}: {:#}", path.display(), e);
+    }
+
+    Ok(image)
+}
+
+/// Returns the path of the cache file for the encoded image data.
+fn cache_path(content: &[u8]) -> Option<PathBuf> {
+    let dir = env::var_os("XDG_CACHE_HOME")
+        .or_else(|| env::var_os("LOCALAPPDATA"))
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+    let name = format!("{:016x}-{:x}.tex", fnv1a(content), content.len());
+    Some(dir.join("fbx-viewer").join("textures").join(name))
+}
+
+/// Returns the 64-bit FNV-1a hash of the data.
+///
+/// This is stable across builds, unlike the hasher of the standard library.
+fn fnv1a(data: &[u8]) -> u64 {
+    /// FNV offset basis.
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    /// FNV prime.
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    data.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// Reads the image from the cache file.
+fn read(path: &Path) -> anyhow::Result<RgbaImage> {
+    let data = fs::read(path).context("Failed to read file")?;
+    ensure!(
+        data.len() >= HEADER_LEN && data.starts_with(MAGIC),
+        "Not a texture cache file"
+    );
+    let flags = data[MAGIC.len()];
+    let dimension = |offset: usize| {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&data[offset..offset + 4]);
+        u32::from_le_bytes(bytes)
+    };
+    let width = dimension(MAGIC.len() + 1);
+    let height = dimension(MAGIC.len() + 5);
+    let body = &data[HEADER_LEN..];
+    let pixels = if flags & FLAG_COMPRESSED != 0 {
+        let mut pixels = Vec::new();
+        ZlibDecoder::new(body)
+            .read_to_end(&mut pixels)
+            .context("Failed to decompress pixels")?;
+        pixels
+    } else {
+        body.to_vec()
+    };
+    match RgbaImage::from_raw(width, height, pixels) {
+        Some(image) => Ok(image),
+        None => bail!("Pixels are truncated"),
+    }
+}
+
+/// Writes the image to the cache file.
+fn write(path: &Path, image: &RgbaImage, mode: TextureCacheMode) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).context("Failed to create cache directory")?;
+    }
+    let compressed = mode == TextureCacheMode::Compressed;
+    let mut data = Vec::with_capacity(HEADER_LEN + image.as_raw().len());
+    data.extend_from_slice(MAGIC);
+    data.push(if compressed { FLAG_COMPRESSED } else { 0 });
+    data.extend_from_slice(&image.width().to_le_bytes());
+    data.extend_from_slice(&image.height().to_le_bytes());
+    if compressed {
+        let mut encoder = ZlibEncoder::new(data, Compression::fast());
+        encoder.write_all(image.as_raw())?;
+        data = encoder.finish().context("Failed to compress pixels")?;
+    } else {
+        data.extend_from_slice(image.as_raw());
+    }
+
+    // Write to a temporary file first, so that other processes never read
+    // partially written files.
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, data).context("Failed to write file")?;
+    fs::rename(&tmp_path, path).context("Failed to rename file")?;
+    debug!("Wrote texture cache: {}", path.display());
+
+    Ok(())
+}